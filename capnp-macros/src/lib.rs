@@ -0,0 +1,308 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! # Cap'n Proto Macros
+//!
+//! Procedural macros for building Cap'n Proto messages with literal syntax.
+//!
+//! [`capnp_build!`] takes the `Owned` type of a generated struct followed by a
+//! braced list of field values, and expands to calls on the generated builders,
+//! producing a [`capnp::message::TypedBuilder`]:
+//!
+//! ```
+//! use capnp::schema_capnp::{node, ElementSize};
+//! use capnp_macros::capnp_build;
+//!
+//! let message = capnp_build!(node::Owned {
+//!     id: 0x1234,
+//!     display_name: "example.capnp:Point",
+//!     nested_nodes: [{ name: "Inner", id: 0x5678 }],
+//!     struct: {
+//!         data_word_count: 1,
+//!         preferred_list_encoding: inlineComposite,
+//!         fields: [
+//!             { name: "x", code_order: 0, slot: { offset: 0, type: { float32: () } } },
+//!         ],
+//!     },
+//! });
+//!
+//! let node = message.get_root_as_reader().unwrap();
+//! assert_eq!(node.get_display_name().unwrap(), "example.capnp:Point");
+//! assert_eq!(node.get_nested_nodes().unwrap().get(0).get_id(), 0x5678);
+//! let node::Struct(st) = node.which().unwrap() else { panic!() };
+//! assert_eq!(st.get_preferred_list_encoding().unwrap(), ElementSize::InlineComposite);
+//! ```
+//!
+//! Field names are written as in the Rust API (`display_name`), and each value is one of:
+//!
+//!  * `{ ... }`, a struct or group field, filled in recursively;
+//!  * `[ ... ]`, a list whose elements are themselves values;
+//!  * a bare identifier, naming an enumerant as it is written in the schema (`inlineComposite`);
+//!  * any other Rust expression, passed to the field's setter.
+//!
+//! Since a bare identifier is taken to be an enumerant, a local variable must be
+//! parenthesized to be used as a value: `id: (id)`.
+//!
+//! A field that does not exist is a compile error, reported at the field name:
+//!
+//! ```compile_fail
+//! use capnp::schema_capnp::node;
+//! use capnp_macros::capnp_build;
+//!
+//! let message = capnp_build!(node::Owned { dispaly_name: "typo" });
+//! ```
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, bracketed, parse_macro_input, token, Expr, Ident, Path, Token};
+
+/// Builds a message of the given struct type from a literal description of its fields.
+///
+/// See the [crate-level documentation](crate) for the accepted syntax.
+#[proc_macro]
+pub fn capnp_build(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let build = parse_macro_input!(input as Build);
+    match build.expand() {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct Build {
+    ty: Path,
+    root: StructValue,
+}
+
+struct StructValue {
+    fields: Punctuated<FieldValue, Token![,]>,
+}
+
+struct FieldValue {
+    name: Ident,
+    value: Value,
+}
+
+enum Value {
+    Struct(StructValue),
+    List(Punctuated<Value, Token![,]>),
+    Enumerant(Ident),
+    Expr(Expr),
+}
+
+impl Parse for Build {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            ty: input.parse()?,
+            root: input.parse()?,
+        })
+    }
+}
+
+impl Parse for StructValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        braced!(content in input);
+        Ok(Self {
+            fields: content.parse_terminated(FieldValue::parse, Token![,])?,
+        })
+    }
+}
+
+impl Parse for FieldValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Accept keywords like `type` and `struct`, which are common field names.
+        let name = Ident::parse_any(input)?;
+        input.parse::<Token![:]>()?;
+        Ok(Self {
+            name,
+            value: input.parse()?,
+        })
+    }
+}
+
+impl Parse for Value {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(token::Brace) {
+            return Ok(Self::Struct(input.parse()?));
+        }
+        if input.peek(token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            return Ok(Self::List(
+                content.parse_terminated(Value::parse, Token![,])?,
+            ));
+        }
+
+        // A lone identifier names an enumerant, unless it is a boolean literal.
+        let fork = input.fork();
+        if let Ok(ident) = fork.call(Ident::parse_any) {
+            if (fork.is_empty() || fork.peek(Token![,])) && ident != "true" && ident != "false" {
+                input.call(Ident::parse_any)?;
+                return Ok(Self::Enumerant(ident));
+            }
+        }
+        match input.parse()? {
+            // Parentheses only serve to mark an identifier as an expression.
+            Expr::Paren(paren) => Ok(Self::Expr(*paren.expr)),
+            expr => Ok(Self::Expr(expr)),
+        }
+    }
+}
+
+struct Idents {
+    builder: Ident,
+    list: Ident,
+}
+
+impl Build {
+    fn expand(&self) -> syn::Result<TokenStream> {
+        let idents = Idents {
+            builder: Ident::new("builder", Span::mixed_site()),
+            list: Ident::new("list", Span::mixed_site()),
+        };
+        let message = Ident::new("message", Span::mixed_site());
+        let builder = &idents.builder;
+        let ty = &self.ty;
+
+        let init_root = if self.root.fields.is_empty() {
+            quote! { #message.init_root(); }
+        } else {
+            let body = self.root.expand(&idents)?;
+            quote! {
+                {
+                    let mut #builder = #message.init_root();
+                    #body
+                }
+            }
+        };
+        Ok(quote! {
+            {
+                let mut #message = ::capnp::message::TypedBuilder::<#ty>::new_default();
+                #init_root
+                #message
+            }
+        })
+    }
+}
+
+impl StructValue {
+    /// Statements that set each field on `builder`.
+    fn expand(&self, idents: &Idents) -> syn::Result<TokenStream> {
+        let Idents { builder, list } = idents;
+        let mut names: Vec<Ident> = Vec::new();
+        let mut result = TokenStream::new();
+        for field in &self.fields {
+            let name = field.name.unraw();
+            if names.contains(&name) {
+                return Err(syn::Error::new(
+                    field.name.span(),
+                    format!("field `{name}` is set more than once"),
+                ));
+            }
+            let span = field.name.span();
+            let init = format_ident!("init_{}", name, span = span);
+            let set = format_ident!("set_{}", name, span = span);
+            result.extend(match &field.value {
+                Value::Struct(value) if value.fields.is_empty() => {
+                    quote! { #builder.reborrow().#init(); }
+                }
+                Value::Struct(value) => {
+                    let body = value.expand(idents)?;
+                    quote! {
+                        {
+                            let mut #builder = #builder.reborrow().#init();
+                            #body
+                        }
+                    }
+                }
+                Value::List(elements) => {
+                    let len = elements.len() as u32;
+                    let body = expand_elements(elements, idents)?;
+                    quote! {
+                        {
+                            let mut #list = #builder.reborrow().#init(#len);
+                            #body
+                        }
+                    }
+                }
+                Value::Enumerant(enumerant) => {
+                    let enumerant = enumerant.unraw().to_string();
+                    quote! {
+                        #builder.#set(::capnp::private::macros::enumerant_by_name(#enumerant));
+                    }
+                }
+                Value::Expr(expr) => quote! {
+                    ::capnp::private::macros::SetterResult::check(#builder.#set(#expr));
+                },
+            });
+            names.push(name);
+        }
+        Ok(result)
+    }
+}
+
+/// Statements that set each element of `list`.
+fn expand_elements(
+    elements: &Punctuated<Value, Token![,]>,
+    idents: &Idents,
+) -> syn::Result<TokenStream> {
+    let Idents { builder, list } = idents;
+    let mut result = TokenStream::new();
+    for (index, element) in elements.iter().enumerate() {
+        let index = index as u32;
+        result.extend(match element {
+            // Struct elements are allocated along with the list.
+            Value::Struct(value) if value.fields.is_empty() => TokenStream::new(),
+            Value::Struct(value) => {
+                let body = value.expand(idents)?;
+                quote! {
+                    {
+                        let mut #builder = #list.reborrow().get(#index);
+                        #body
+                    }
+                }
+            }
+            Value::List(inner) => {
+                let len = inner.len() as u32;
+                let body = expand_elements(inner, idents)?;
+                quote! {
+                    {
+                        let mut #list = #list.reborrow().init(#index, #len);
+                        #body
+                    }
+                }
+            }
+            Value::Enumerant(enumerant) => {
+                let enumerant = enumerant.unraw().to_string();
+                quote! {
+                    #list.set(#index, ::capnp::private::macros::enumerant_by_name(#enumerant));
+                }
+            }
+            Value::Expr(expr) => quote! {
+                ::capnp::private::macros::SetterResult::check(#list.set(#index, #expr));
+            },
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,91 @@
+use capnp::schema_capnp::{code_generator_request, node, type_, ElementSize};
+use capnp_macros::capnp_build;
+
+#[test]
+fn empty() {
+    let message = capnp_build!(node::Owned {});
+    let node = message.get_root_as_reader().unwrap();
+    assert_eq!(node.get_id(), 0);
+    assert!(!node.has_display_name());
+}
+
+#[test]
+fn scalars_and_text() {
+    let prefix_length = 8;
+    let message = capnp_build!(node::Owned {
+        id: 0xabcd,
+        display_name: "foo.capnp:Bar",
+        display_name_prefix_length: (prefix_length),
+        is_generic: true,
+    });
+    let node = message.get_root_as_reader().unwrap();
+    assert_eq!(node.get_id(), 0xabcd);
+    assert_eq!(node.get_display_name().unwrap(), "foo.capnp:Bar");
+    assert_eq!(node.get_display_name_prefix_length(), 8);
+    assert!(node.get_is_generic());
+}
+
+#[test]
+fn groups_unions_and_enums() {
+    let message = capnp_build!(node::Owned {
+        struct: {
+            data_word_count: 2,
+            preferred_list_encoding: inlineComposite,
+            fields: [
+                { name: "a", slot: { offset: 0, type: { uint32: () } } },
+                { name: "b", code_order: 1, group: { type_id: 0x1234 } },
+                {},
+            ],
+        },
+    });
+    let node = message.get_root_as_reader().unwrap();
+    let node::Struct(st) = node.which().unwrap() else {
+        panic!("expected a struct node")
+    };
+    assert_eq!(st.get_data_word_count(), 2);
+    assert_eq!(
+        st.get_preferred_list_encoding().unwrap(),
+        ElementSize::InlineComposite
+    );
+
+    let fields = st.get_fields().unwrap();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields.get(0).get_name().unwrap(), "a");
+    let capnp::schema_capnp::field::Slot(slot) = fields.get(0).which().unwrap() else {
+        panic!("expected a slot")
+    };
+    assert!(matches!(
+        slot.get_type().unwrap().which().unwrap(),
+        type_::Uint32(())
+    ));
+    assert_eq!(fields.get(1).get_code_order(), 1);
+    let capnp::schema_capnp::field::Group(group) = fields.get(1).which().unwrap() else {
+        panic!("expected a group")
+    };
+    assert_eq!(group.get_type_id(), 0x1234);
+    assert!(!fields.get(2).has_name());
+}
+
+#[test]
+fn lists() {
+    let message = capnp_build!(code_generator_request::Owned {
+        requested_files: [
+            {
+                id: 1,
+                filename: "a.capnp",
+                imports: [{ id: 2, name: "b.capnp" }, { id: 3, name: "c.capnp" }],
+            },
+        ],
+        nodes: [],
+    });
+    let request = message.get_root_as_reader().unwrap();
+    assert!(request.has_nodes());
+    assert_eq!(request.get_nodes().unwrap().len(), 0);
+    let files = request.get_requested_files().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files.get(0).get_filename().unwrap(), "a.capnp");
+    let imports = files.get(0).get_imports().unwrap();
+    assert_eq!(imports.len(), 2);
+    assert_eq!(imports.get(1).get_id(), 3);
+    assert_eq!(imports.get(1).get_name().unwrap(), "c.capnp");
+}
@@ -19,16 +19,17 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use capnp::any_pointer;
-use capnp::private::capability::{
-    ClientHook, ParamsHook, PipelineHook, PipelineOp, RequestHook, ResultsHook,
+use super::capnp;
+use super::capnp::any_pointer;
+use super::capnp::private::capability::{
+    ClientHook, ParamsHook, PipelineHook, PipelineOp, RequestHook, ResultsHook, TailSend,
 };
-use capnp::Error;
+use super::capnp::Error;
 
-use capnp::capability::{Promise, RemotePromise};
-use capnp::traits::ImbueMut;
+use super::capnp::capability::{Promise, RemotePromise};
+use super::capnp::traits::ImbueMut;
 
-use std::rc::Rc;
+use super::shared::Rc;
 
 pub struct Pipeline {
     error: Error,
@@ -51,15 +52,15 @@ impl PipelineHook for Pipeline {
 
 pub struct Request {
     error: Error,
-    message: ::capnp::message::Builder<::capnp::message::HeapAllocator>,
+    message: capnp::message::Builder<capnp::message::HeapAllocator>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
 }
 
 impl Request {
-    pub fn new(error: Error, _size_hint: Option<::capnp::MessageSize>) -> Self {
+    pub fn new(error: Error, _size_hint: Option<capnp::MessageSize>) -> Self {
         Self {
             error,
-            message: ::capnp::message::Builder::new_default(),
+            message: capnp::message::Builder::new_default(),
             cap_table: Vec::new(),
         }
     }
@@ -81,8 +82,8 @@ impl RequestHook for Request {
             pipeline: any_pointer::Pipeline::new(Box::new(pipeline)),
         }
     }
-    fn tail_send(self: Box<Self>) -> Option<(u32, Promise<(), Error>, Box<dyn PipelineHook>)> {
-        None
+    fn tail_send(self: Box<Self>) -> Result<TailSend, Box<dyn RequestHook>> {
+        Err(self)
     }
 }
 
@@ -118,12 +119,9 @@ impl ClientHook for Client {
         &self,
         _interface_id: u64,
         _method_id: u16,
-        size_hint: Option<::capnp::MessageSize>,
-    ) -> ::capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
-        ::capnp::capability::Request::new(Box::new(Request::new(
-            self.inner.error.clone(),
-            size_hint,
-        )))
+        size_hint: Option<capnp::MessageSize>,
+    ) -> capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
+        capnp::capability::Request::new(Box::new(Request::new(self.inner.error.clone(), size_hint)))
     }
 
     fn call(
@@ -153,7 +151,7 @@ impl ClientHook for Client {
     }
 
     fn when_resolved(&self) -> Promise<(), Error> {
-        crate::rpc::default_when_resolved_impl(self)
+        super::rpc::default_when_resolved_impl(self)
     }
 }
 
@@ -0,0 +1,218 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Flow control for streaming calls, i.e. calls to methods declared with `-> stream`.
+
+use super::capnp::capability::Promise;
+use super::capnp::Error;
+
+use futures::channel::{mpsc, oneshot};
+use futures::{FutureExt, StreamExt, TryFutureExt};
+
+use super::shared::{BoxFuture, Rc, RefCell, Weak};
+
+/// The window size used by the default implementation of
+/// [`Connection::new_stream()`](super::Connection::new_stream), in bytes.
+pub const DEFAULT_WINDOW_SIZE: usize = 65536;
+
+enum State {
+    /// Waiting for the window to open up, in order to let these sends complete.
+    Running(Vec<oneshot::Sender<Result<(), Error>>>),
+
+    /// A call failed. All later sends fail with the same error.
+    Failed(Error),
+}
+
+struct Inner {
+    window_size: usize,
+
+    /// The number of bytes that have been sent but not yet acknowledged.
+    in_flight: usize,
+
+    /// The size of the largest message sent so far.
+    max_message_size: usize,
+
+    state: State,
+
+    /// Waiting for `in_flight` to drop to zero.
+    empty_waiters: Vec<oneshot::Sender<Result<(), Error>>>,
+}
+
+impl Inner {
+    fn is_ready(&self) -> bool {
+        // We extend the window by `max_message_size` so that, after sending a message larger than
+        // the window, we do not have to wait a whole round trip before sending the next one.
+        self.in_flight <= self.max_message_size
+            || self.in_flight < self.window_size + self.max_message_size
+    }
+
+    fn acked(&mut self, size: usize, result: Result<(), Error>) {
+        self.in_flight -= size;
+        let is_ready = self.is_ready();
+        match (&mut self.state, result) {
+            (State::Running(blocked_sends), Ok(())) => {
+                if is_ready {
+                    for fulfiller in blocked_sends.drain(..) {
+                        let _ = fulfiller.send(Ok(()));
+                    }
+                }
+            }
+            (State::Running(blocked_sends), Err(e)) => {
+                for fulfiller in blocked_sends.drain(..) {
+                    let _ = fulfiller.send(Err(e.clone()));
+                }
+                self.state = State::Failed(e);
+            }
+            (State::Failed(_), _) => {
+                // An earlier call already failed. This one was in flight at the time.
+            }
+        }
+        if self.in_flight == 0 {
+            let result = match &self.state {
+                State::Running(_) => Ok(()),
+                State::Failed(e) => Err(e.clone()),
+            };
+            for fulfiller in self.empty_waiters.drain(..) {
+                let _ = fulfiller.send(result.clone());
+            }
+        }
+    }
+}
+
+/// The size of a call that is in flight, to be reported to `Inner::acked()` when it returns. If
+/// dropped first, e.g. because the connection was torn down, the call is reported as failed.
+struct PendingAck {
+    inner: Weak<RefCell<Inner>>,
+    size: usize,
+    reported: bool,
+}
+
+impl PendingAck {
+    fn report(&mut self, result: Result<(), Error>) {
+        if self.reported {
+            return;
+        }
+        self.reported = true;
+        if let Some(inner) = self.inner.upgrade() {
+            inner.borrow_mut().acked(self.size, result);
+        }
+    }
+}
+
+impl Drop for PendingAck {
+    fn drop(&mut self) {
+        self.report(Err(Error::disconnected(
+            "stream was canceled before the call returned".to_string(),
+        )));
+    }
+}
+
+/// A flow controller that allows a fixed number of bytes to be in flight at once.
+pub struct FixedWindowFlowController {
+    inner: Rc<RefCell<Inner>>,
+    acks: mpsc::UnboundedSender<BoxFuture<()>>,
+}
+
+impl FixedWindowFlowController {
+    /// Creates a flow controller with a window of `window_size` bytes. Also returns a promise
+    /// that must be driven in order for the flow controller to process acknowledgements. It
+    /// completes once the flow controller has been dropped and all of its calls have returned.
+    pub fn new(window_size: usize) -> (Self, Promise<(), Error>) {
+        let (acks, receiver) = mpsc::unbounded::<BoxFuture<()>>();
+        let driver = receiver.for_each_concurrent(None, |ack| ack).map(Ok);
+        let controller = Self {
+            inner: Rc::new(RefCell::new(Inner {
+                window_size,
+                in_flight: 0,
+                max_message_size: 0,
+                state: State::Running(Vec::new()),
+                empty_waiters: Vec::new(),
+            })),
+            acks,
+        };
+        (controller, Promise::from_future(driver))
+    }
+}
+
+impl super::FlowController for FixedWindowFlowController {
+    fn send(
+        &mut self,
+        message: Box<dyn super::OutgoingMessage>,
+        ack: Promise<(), Error>,
+    ) -> Promise<(), Error> {
+        // The message must be sent right away, to keep it in order with other calls.
+        let (_, sent) = message.send();
+        let size = sent
+            .get_segments_for_output()
+            .iter()
+            .map(|segment| segment.len())
+            .sum();
+
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.in_flight += size;
+            inner.max_message_size = inner.max_message_size.max(size);
+        }
+
+        // If the driver is gone, this drops `pending`, which fails the stream.
+        let mut pending = PendingAck {
+            inner: Rc::downgrade(&self.inner),
+            size,
+            reported: false,
+        };
+        let _ = self
+            .acks
+            .unbounded_send(Box::pin(ack.map(move |result| pending.report(result))));
+
+        let mut inner = self.inner.borrow_mut();
+        let is_ready = inner.is_ready();
+        match &mut inner.state {
+            State::Failed(e) => Promise::err(e.clone()),
+            State::Running(_) if is_ready => Promise::ok(()),
+            State::Running(blocked_sends) => {
+                let (fulfiller, promise) = oneshot::channel();
+                blocked_sends.push(fulfiller);
+                Promise::from_future(
+                    promise
+                        .map_err(super::canceled_to_error)
+                        .and_then(futures::future::ready),
+                )
+            }
+        }
+    }
+
+    fn wait_all_acked(&mut self) -> Promise<(), Error> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.in_flight == 0 {
+            return match &inner.state {
+                State::Running(_) => Promise::ok(()),
+                State::Failed(e) => Promise::err(e.clone()),
+            };
+        }
+        let (fulfiller, promise) = oneshot::channel();
+        inner.empty_waiters.push(fulfiller);
+        Promise::from_future(
+            promise
+                .map_err(super::canceled_to_error)
+                .and_then(futures::future::ready),
+        )
+    }
+}
@@ -18,23 +18,25 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use capnp::capability::{self, Promise};
-use capnp::private::capability::{
+use super::capnp;
+use super::capnp::capability::{self, MaybeSend, MaybeSync, Promise};
+use super::capnp::private::capability::{
     ClientHook, ParamsHook, PipelineHook, PipelineOp, RequestHook, ResponseHook, ResultsHook,
+    TailSend,
 };
-use capnp::traits::{Imbue, ImbueMut};
-use capnp::Error;
-use capnp::{any_pointer, message};
+use super::capnp::traits::{Imbue, ImbueMut};
+use super::capnp::Error;
+use super::capnp::{any_pointer, message};
 
 use futures::channel::oneshot;
 use futures::TryFutureExt;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use super::shared::{Rc, RefCell};
+use std::collections::VecDeque;
 
-pub trait ResultsDoneHook {
+pub trait ResultsDoneHook: MaybeSend + MaybeSync {
     fn add_ref(&self) -> Box<dyn ResultsDoneHook>;
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader>;
+    fn get(&self) -> capnp::Result<any_pointer::Reader>;
 }
 
 impl Clone for Box<dyn ResultsDoneHook> {
@@ -54,7 +56,7 @@ impl Response {
 }
 
 impl ResponseHook for Response {
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
         self.results.get()
     }
 }
@@ -74,7 +76,7 @@ impl Params {
 }
 
 impl ParamsHook for Params {
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
         let mut result: any_pointer::Reader = self.request.get_root_as_reader()?;
         result.imbue(&self.cap_table);
         Ok(result)
@@ -90,7 +92,7 @@ struct Results {
 impl Results {
     fn new(fulfiller: oneshot::Sender<Box<dyn ResultsDoneHook>>) -> Self {
         Self {
-            message: Some(::capnp::message::Builder::new_default()),
+            message: Some(capnp::message::Builder::new_default()),
             cap_table: Vec::new(),
             results_done_fulfiller: Some(fulfiller),
         }
@@ -99,19 +101,19 @@ impl Results {
 
 impl Drop for Results {
     fn drop(&mut self) {
-        if let (Some(message), Some(fulfiller)) =
-            (self.message.take(), self.results_done_fulfiller.take())
-        {
-            let cap_table = ::std::mem::take(&mut self.cap_table);
-            let _ = fulfiller.send(Box::new(ResultsDone::new(message, cap_table)));
-        } else {
-            unreachable!()
+        match (self.message.take(), self.results_done_fulfiller.take()) {
+            (Some(message), Some(fulfiller)) => {
+                let cap_table = ::std::mem::take(&mut self.cap_table);
+                let _ = fulfiller.send(Box::new(ResultsDone::new(message, cap_table)));
+            }
+            (None, None) => (), // tail call
+            _ => unreachable!(),
         }
     }
 }
 
 impl ResultsHook for Results {
-    fn get(&mut self) -> ::capnp::Result<any_pointer::Builder> {
+    fn get(&mut self) -> capnp::Result<any_pointer::Builder> {
         match *self {
             Self {
                 message: Some(ref mut message),
@@ -126,15 +128,27 @@ impl ResultsHook for Results {
         }
     }
 
-    fn tail_call(self: Box<Self>, _request: Box<dyn RequestHook>) -> Promise<(), Error> {
-        unimplemented!()
+    fn tail_call(self: Box<Self>, request: Box<dyn RequestHook>) -> Promise<(), Error> {
+        self.direct_tail_call(request).0
     }
 
     fn direct_tail_call(
-        self: Box<Self>,
-        _request: Box<dyn RequestHook>,
+        mut self: Box<Self>,
+        request: Box<dyn RequestHook>,
     ) -> (Promise<(), Error>, Box<dyn PipelineHook>) {
-        unimplemented!()
+        // Our caller gets the results of `request` directly, rather than a copy of them.
+        let fulfiller = self
+            .results_done_fulfiller
+            .take()
+            .expect("results already done");
+        self.message = None;
+        self.cap_table.clear();
+
+        let capability::RemotePromise { promise, pipeline } = request.send();
+        let promise = promise.map_ok(move |response| {
+            let _ = fulfiller.send(Box::new(TailResults::new(response.hook)));
+        });
+        (Promise::from_future(promise), pipeline.hook)
     }
 
     fn allow_cancellation(&self) {
@@ -143,7 +157,7 @@ impl ResultsHook for Results {
 }
 
 struct ResultsDoneInner {
-    message: ::capnp::message::Builder<::capnp::message::HeapAllocator>,
+    message: capnp::message::Builder<capnp::message::HeapAllocator>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
 }
 
@@ -168,15 +182,39 @@ impl ResultsDoneHook for ResultsDone {
             inner: self.inner.clone(),
         })
     }
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
         let mut result: any_pointer::Reader = self.inner.message.get_root_as_reader()?;
         result.imbue(&self.inner.cap_table);
         Ok(result)
     }
 }
 
+/// The results of a call that ended with a tail call, i.e. the response to the tail call.
+struct TailResults {
+    response: Rc<Box<dyn ResponseHook>>,
+}
+
+impl TailResults {
+    fn new(response: Box<dyn ResponseHook>) -> Self {
+        Self {
+            response: Rc::new(response),
+        }
+    }
+}
+
+impl ResultsDoneHook for TailResults {
+    fn add_ref(&self) -> Box<dyn ResultsDoneHook> {
+        Box::new(Self {
+            response: self.response.clone(),
+        })
+    }
+    fn get(&self) -> capnp::Result<any_pointer::Reader<'_>> {
+        self.response.get()
+    }
+}
+
 pub struct Request {
-    message: message::Builder<::capnp::message::HeapAllocator>,
+    message: message::Builder<capnp::message::HeapAllocator>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
     interface_id: u64,
     method_id: u16,
@@ -187,7 +225,7 @@ impl Request {
     pub fn new(
         interface_id: u64,
         method_id: u16,
-        _size_hint: Option<::capnp::MessageSize>,
+        _size_hint: Option<capnp::MessageSize>,
         client: Box<dyn ClientHook>,
     ) -> Self {
         Self {
@@ -222,11 +260,11 @@ impl RequestHook for Request {
 
         let (results_done_fulfiller, results_done_promise) =
             oneshot::channel::<Box<dyn ResultsDoneHook>>();
-        let results_done_promise = results_done_promise.map_err(crate::canceled_to_error);
+        let results_done_promise = results_done_promise.map_err(super::canceled_to_error);
         let results = Results::new(results_done_fulfiller);
         let promise = client.call(interface_id, method_id, Box::new(params), Box::new(results));
 
-        let (pipeline_sender, mut pipeline) = crate::queued::Pipeline::new();
+        let (pipeline_sender, mut pipeline) = super::queued::Pipeline::new();
 
         let p = futures::future::try_join(promise, results_done_promise).and_then(
             move |((), results_done_hook)| {
@@ -240,7 +278,7 @@ impl RequestHook for Request {
             },
         );
 
-        let (left, right) = crate::split::split(p);
+        let (left, right) = super::split::split(p);
 
         pipeline.drive(right);
         let pipeline = any_pointer::Pipeline::new(Box::new(pipeline));
@@ -250,8 +288,9 @@ impl RequestHook for Request {
             pipeline,
         }
     }
-    fn tail_send(self: Box<Self>) -> Option<(u32, Promise<(), Error>, Box<dyn PipelineHook>)> {
-        unimplemented!()
+    fn tail_send(self: Box<Self>) -> Result<TailSend, Box<dyn RequestHook>> {
+        // Local calls have no question to take the results from.
+        Err(self)
     }
 }
 
@@ -293,7 +332,73 @@ impl PipelineHook for Pipeline {
             .get_pipelined_cap(ops)
         {
             Ok(v) => v,
-            Err(e) => Box::new(crate::broken::Client::new(e, true, 0)) as Box<dyn ClientHook>,
+            Err(e) => Box::new(super::broken::Client::new(e, true, 0)) as Box<dyn ClientHook>,
+        }
+    }
+}
+
+/// Keeps calls from being dispatched to a server while it is handling a streaming call.
+#[derive(Default)]
+struct StreamState {
+    /// Whether new calls must wait in `blocked_calls`. Stays set while a call from the queue is
+    /// being handed its turn.
+    blocked: bool,
+
+    /// The calls that arrived while blocked, in order.
+    blocked_calls: VecDeque<oneshot::Sender<()>>,
+
+    /// If a streaming call failed, all later calls fail with the same error.
+    broken: Option<Error>,
+}
+
+impl StreamState {
+    /// Gives the turn to the next blocked call, or unblocks the server if there are none.
+    fn unblock_next(&mut self) {
+        while let Some(next) = self.blocked_calls.pop_front() {
+            if next.send(()).is_ok() {
+                return;
+            }
+        }
+        self.blocked = false;
+    }
+}
+
+/// A call's turn to be dispatched while the server is blocked. Passes the turn on when dropped,
+/// which happens once the call has been dispatched, or if it is canceled.
+struct Turn {
+    state: Rc<RefCell<StreamState>>,
+
+    /// Resolves when `StreamState::unblock_next()` gives us the turn. `None` if we have it.
+    waiting: Option<oneshot::Receiver<()>>,
+}
+
+impl Turn {
+    /// Queues up behind the calls that are already blocked.
+    fn new(state: &Rc<RefCell<StreamState>>) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        state.borrow_mut().blocked_calls.push_back(sender);
+        Self {
+            state: state.clone(),
+            waiting: Some(receiver),
+        }
+    }
+
+    async fn wait(&mut self) {
+        if let Some(receiver) = self.waiting.as_mut() {
+            let _ = receiver.await;
+        }
+        self.waiting = None;
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let has_turn = match &mut self.waiting {
+            None => true,
+            Some(receiver) => matches!(receiver.try_recv(), Ok(Some(()))),
+        };
+        if has_turn {
+            self.state.borrow_mut().unblock_next();
         }
     }
 }
@@ -303,6 +408,7 @@ where
     S: capability::Server,
 {
     inner: Rc<RefCell<S>>,
+    stream_state: Rc<RefCell<StreamState>>,
 }
 
 impl<S> Client<S>
@@ -310,13 +416,14 @@ where
     S: capability::Server,
 {
     pub fn new(server: S) -> Self {
-        Self {
-            inner: Rc::new(RefCell::new(server)),
-        }
+        Self::from_rc(Rc::new(RefCell::new(server)))
     }
 
     pub fn from_rc(inner: Rc<RefCell<S>>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            stream_state: Default::default(),
+        }
     }
 }
 
@@ -327,6 +434,7 @@ where
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            stream_state: self.stream_state.clone(),
         }
     }
 }
@@ -342,7 +450,7 @@ where
         &self,
         interface_id: u64,
         method_id: u16,
-        size_hint: Option<::capnp::MessageSize>,
+        size_hint: Option<capnp::MessageSize>,
     ) -> capability::Request<any_pointer::Owned, any_pointer::Owned> {
         capability::Request::new(Box::new(Request::new(
             interface_id,
@@ -366,19 +474,51 @@ where
         // TODO: actually use some kind of queue here to guarantee that call order in maintained.
         // This currently relies on the task scheduler being first-in-first-out.
         let inner = self.inner.clone();
+        let stream_state = self.stream_state.clone();
         Promise::from_future(async move {
-            let f = {
+            // While a streaming call is in progress, wait for it and any calls before us.
+            let blocked = stream_state.borrow().blocked;
+            let turn = if blocked {
+                let mut turn = Turn::new(&stream_state);
+                turn.wait().await;
+                Some(turn)
+            } else {
+                None
+            };
+            let broken = stream_state.borrow().broken.clone();
+            if let Some(e) = broken {
+                return Err(e);
+            }
+
+            let result = {
                 // We put this borrow_mut() inside a block to avoid a potential
                 // double borrow during f.await
                 let server = &mut *inner.borrow_mut();
                 server.dispatch_call(
                     interface_id,
                     method_id,
-                    ::capnp::capability::Params::new(params),
-                    ::capnp::capability::Results::new(results),
+                    capnp::capability::Params::new(params),
+                    capnp::capability::Results::new(results),
                 )
             };
-            f.await
+            if !result.is_streaming {
+                drop(turn);
+                return result.promise.await;
+            }
+
+            // Block later calls until this one is done, keeping our turn if we already had it.
+            let _turn = turn.unwrap_or_else(|| {
+                stream_state.borrow_mut().blocked = true;
+                Turn {
+                    state: stream_state.clone(),
+                    waiting: None,
+                }
+            });
+            let r = result.promise.await;
+            if let Err(e) = &r {
+                stream_state.borrow_mut().broken = Some(e.clone());
+            }
+            r
         })
     }
 
@@ -399,6 +539,6 @@ where
     }
 
     fn when_resolved(&self) -> Promise<(), Error> {
-        crate::rpc::default_when_resolved_impl(self)
+        super::rpc::default_when_resolved_impl(self)
     }
 }
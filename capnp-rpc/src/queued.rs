@@ -19,19 +19,21 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use capnp::any_pointer;
-use capnp::capability::Promise;
-use capnp::private::capability::{ClientHook, ParamsHook, PipelineHook, PipelineOp, ResultsHook};
-use capnp::Error;
+use super::capnp;
+use super::capnp::any_pointer;
+use super::capnp::capability::{MaybeSend, Promise};
+use super::capnp::private::capability::{
+    ClientHook, ParamsHook, PipelineHook, PipelineOp, ResultsHook,
+};
+use super::capnp::Error;
 
 use futures::{Future, FutureExt, TryFutureExt};
 
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use super::shared::{Rc, RefCell, Weak};
 
+use super::sender_queue::SenderQueue;
+use super::{broken, local};
 use crate::attach::Attach;
-use crate::sender_queue::SenderQueue;
-use crate::{broken, local};
 
 pub struct PipelineInner {
     // Once the promise resolves, this will become non-null and point to the underlying object.
@@ -74,7 +76,7 @@ impl Drop for PipelineInnerSender {
             if let Some(pipeline_inner) = weak_queued.upgrade() {
                 PipelineInner::resolve(
                     &pipeline_inner,
-                    Ok(Box::new(crate::broken::Pipeline::new(Error::failed(
+                    Ok(Box::new(super::broken::Pipeline::new(Error::failed(
                         "PipelineInnerSender was canceled".into(),
                     )))),
                 );
@@ -87,7 +89,7 @@ impl PipelineInnerSender {
     pub fn complete(mut self, pipeline: Box<dyn PipelineHook>) {
         if let Some(weak_queued) = self.inner.take() {
             if let Some(pipeline_inner) = weak_queued.upgrade() {
-                crate::queued::PipelineInner::resolve(&pipeline_inner, Ok(pipeline));
+                super::queued::PipelineInner::resolve(&pipeline_inner, Ok(pipeline));
             }
         }
     }
@@ -115,7 +117,7 @@ impl Pipeline {
 
     pub fn drive<F>(&mut self, promise: F)
     where
-        F: Future<Output = Result<(), Error>> + 'static + Unpin,
+        F: Future<Output = Result<(), Error>> + MaybeSend + 'static + Unpin,
     {
         let new = Promise::from_future(
             futures::future::try_join(self.inner.borrow_mut().promise_to_drive.clone(), promise)
@@ -224,7 +226,7 @@ impl Client {
 
     pub fn drive<F>(&mut self, promise: F)
     where
-        F: Future<Output = Result<(), Error>> + 'static + Unpin,
+        F: Future<Output = Result<(), Error>> + MaybeSend + 'static + Unpin,
     {
         assert!(self.inner.borrow().promise_to_drive.is_none());
         self.inner.borrow_mut().promise_to_drive = Some(Promise::from_future(promise).shared());
@@ -241,9 +243,9 @@ impl ClientHook for Client {
         &self,
         interface_id: u64,
         method_id: u16,
-        size_hint: Option<::capnp::MessageSize>,
-    ) -> ::capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
-        ::capnp::capability::Request::new(Box::new(local::Request::new(
+        size_hint: Option<capnp::MessageSize>,
+    ) -> capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
+        capnp::capability::Request::new(Box::new(local::Request::new(
             interface_id,
             method_id,
             size_hint,
@@ -309,6 +311,6 @@ impl ClientHook for Client {
     }
 
     fn when_resolved(&self) -> Promise<(), Error> {
-        crate::rpc::default_when_resolved_impl(self)
+        super::rpc::default_when_resolved_impl(self)
     }
 }
@@ -1,12 +1,12 @@
-use std::cell::RefCell;
+use super::capnp;
+use super::shared::{Rc, RefCell};
 use std::marker::PhantomData;
-use std::rc::Rc;
 
-use capnp::capability::{FromClientHook, Promise};
-use capnp::private::capability::{ClientHook, RequestHook};
+use super::capnp::capability::{FromClientHook, MaybeSend, Promise};
+use super::capnp::private::capability::{ClientHook, RequestHook};
 use futures::TryFutureExt;
 
-pub trait SetTarget<C> {
+pub trait SetTarget<C>: MaybeSend {
     fn add_ref(&self) -> Box<dyn SetTarget<C>>;
     fn set_target(&self, target: C);
 }
@@ -21,13 +21,13 @@ struct ClientInner<F, C> {
     connect: F,
     current: Option<Box<dyn ClientHook>>,
     generation: usize,
-    marker: PhantomData<C>,
+    marker: PhantomData<fn() -> C>,
 }
 
 impl<F, C> ClientInner<F, C>
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
 {
     fn get_current(&mut self) -> Box<dyn ClientHook> {
@@ -36,7 +36,7 @@ where
         } else {
             let hook = match (self.connect)() {
                 Ok(hook) => hook.into_client_hook(),
-                Err(err) => crate::broken::new_cap(err),
+                Err(err) => super::broken::new_cap(err),
             };
             self.current = Some(hook.add_ref());
             hook
@@ -51,7 +51,7 @@ struct Client<F, C> {
 impl<F, C> Client<F, C>
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -70,7 +70,10 @@ where
         self.inner.borrow_mut().get_current()
     }
 
-    fn wrap<T: 'static>(&self, promise: Promise<T, capnp::Error>) -> Promise<T, capnp::Error> {
+    fn wrap<T: MaybeSend + 'static>(
+        &self,
+        promise: Promise<T, capnp::Error>,
+    ) -> Promise<T, capnp::Error> {
         let c = self.clone();
         let generation = self.inner.borrow().generation;
         Promise::from_future(promise.map_err(move |err| {
@@ -81,7 +84,7 @@ where
                 inner.generation = generation + 1;
                 match (inner.connect)() {
                     Ok(hook) => inner.current = Some(hook.into_client_hook()),
-                    Err(err) => inner.current = Some(crate::broken::new_cap(err)),
+                    Err(err) => inner.current = Some(super::broken::new_cap(err)),
                 }
             }
             err
@@ -91,7 +94,7 @@ where
 
 impl<F: 'static, C> SetTarget<C> for Client<F, C>
 where
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -115,7 +118,7 @@ impl<F, C> Clone for Client<F, C> {
 impl<F, C> ClientHook for Client<F, C>
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -184,7 +187,7 @@ impl<F, C> Request<F, C> {
 impl<F, C> RequestHook for Request<F, C>
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -205,19 +208,15 @@ where
 
     fn tail_send(
         self: Box<Self>,
-    ) -> Option<(
-        u32,
-        Promise<(), capnp::Error>,
-        Box<dyn capnp::private::capability::PipelineHook>,
-    )> {
-        todo!()
+    ) -> Result<capnp::private::capability::TailSend, Box<dyn RequestHook>> {
+        Err(self)
     }
 }
 
 pub fn auto_reconnect<F, C>(mut connect: F) -> capnp::Result<(C, Box<dyn SetTarget<C>>)>
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -231,7 +230,7 @@ where
 pub fn lazy_auto_reconnect<F, C>(connect: F) -> (C, Box<dyn SetTarget<C>>)
 where
     F: FnMut() -> capnp::Result<C>,
-    F: 'static,
+    F: MaybeSend + 'static,
     C: FromClientHook,
     C: 'static,
 {
@@ -19,35 +19,36 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+use super::capnp;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use capnp::any_pointer;
-use capnp::capability::Promise;
-use capnp::private::capability::{
+use super::capnp::any_pointer;
+use super::capnp::capability::{MaybeSend, Promise};
+use super::capnp::private::capability::{
     ClientHook, ParamsHook, PipelineHook, PipelineOp, RequestHook, ResponseHook, ResultsHook,
+    TailSend,
 };
-use capnp::Error;
+use super::capnp::Error;
 
 use futures::channel::oneshot;
 use futures::{future, Future, FutureExt, TryFutureExt};
 
-use std::cell::{Cell, RefCell};
+use super::shared::{BoxFuture, Cell, Rc, RefCell, Weak};
 use std::cmp::Reverse;
 use std::collections::binary_heap::BinaryHeap;
 use std::collections::hash_map::HashMap;
 use std::mem;
-use std::rc::{Rc, Weak};
 use std::vec::Vec;
 
+use super::local::ResultsDoneHook;
+use super::task_set::TaskSet;
+use super::{broken, local, queued};
 use crate::attach::Attach;
-use crate::local::ResultsDoneHook;
 use crate::rpc_capnp::{
     bootstrap, call, cap_descriptor, disembargo, exception, finish, message, message_target,
     payload, promised_answer, resolve, return_,
 };
-use crate::task_set::TaskSet;
-use crate::{broken, local, queued};
 
 pub type QuestionId = u32;
 pub type AnswerId = QuestionId;
@@ -150,7 +151,6 @@ where
     #[allow(dead_code)]
     param_exports: Vec<ExportId>,
 
-    #[allow(dead_code)]
     is_tail_call: bool,
 
     /// The local QuestionRef, set to None when it is destroyed.
@@ -338,8 +338,8 @@ impl Embargo {
 }
 
 fn to_pipeline_ops(
-    ops: ::capnp::struct_list::Reader<promised_answer::op::Owned>,
-) -> ::capnp::Result<Vec<PipelineOp>> {
+    ops: capnp::struct_list::Reader<promised_answer::op::Owned>,
+) -> capnp::Result<Vec<PipelineOp>> {
     let mut result = Vec::new();
     for op in ops {
         match op.which()? {
@@ -357,25 +357,23 @@ fn to_pipeline_ops(
 fn from_error(error: &Error, mut builder: exception::Builder) {
     builder.set_reason(&error.description);
     let typ = match error.kind {
-        ::capnp::ErrorKind::Failed => exception::Type::Failed,
-        ::capnp::ErrorKind::Overloaded => exception::Type::Overloaded,
-        ::capnp::ErrorKind::Disconnected => exception::Type::Disconnected,
-        ::capnp::ErrorKind::Unimplemented => exception::Type::Unimplemented,
+        capnp::ErrorKind::Failed => exception::Type::Failed,
+        capnp::ErrorKind::Overloaded => exception::Type::Overloaded,
+        capnp::ErrorKind::Disconnected => exception::Type::Disconnected,
+        capnp::ErrorKind::Unimplemented => exception::Type::Unimplemented,
     };
     builder.set_type(typ);
 }
 
 fn remote_exception_to_error(exception: exception::Reader) -> Error {
     let (kind, reason) = match (exception.get_type(), exception.get_reason()) {
-        (Ok(exception::Type::Failed), Ok(reason)) => (::capnp::ErrorKind::Failed, reason),
-        (Ok(exception::Type::Overloaded), Ok(reason)) => (::capnp::ErrorKind::Overloaded, reason),
-        (Ok(exception::Type::Disconnected), Ok(reason)) => {
-            (::capnp::ErrorKind::Disconnected, reason)
-        }
+        (Ok(exception::Type::Failed), Ok(reason)) => (capnp::ErrorKind::Failed, reason),
+        (Ok(exception::Type::Overloaded), Ok(reason)) => (capnp::ErrorKind::Overloaded, reason),
+        (Ok(exception::Type::Disconnected), Ok(reason)) => (capnp::ErrorKind::Disconnected, reason),
         (Ok(exception::Type::Unimplemented), Ok(reason)) => {
-            (::capnp::ErrorKind::Unimplemented, reason)
+            (capnp::ErrorKind::Unimplemented, reason)
         }
-        _ => (::capnp::ErrorKind::Failed, "(malformed error)"),
+        _ => (capnp::ErrorKind::Failed, "(malformed error)"),
     };
     Error {
         description: format!("remote exception: {reason}"),
@@ -396,8 +394,8 @@ impl<VatId> ConnectionErrorHandler<VatId> {
     }
 }
 
-impl<VatId> crate::task_set::TaskReaper<capnp::Error> for ConnectionErrorHandler<VatId> {
-    fn task_failed(&mut self, error: ::capnp::Error) {
+impl<VatId> super::task_set::TaskReaper<capnp::Error> for ConnectionErrorHandler<VatId> {
+    fn task_failed(&mut self, error: capnp::Error) {
         if let Some(state) = self.weak_state.upgrade() {
             state.disconnect(error)
         }
@@ -418,8 +416,8 @@ where
 
     embargoes: RefCell<ExportTable<Embargo>>,
 
-    tasks: RefCell<Option<crate::task_set::TaskSetHandle<capnp::Error>>>,
-    connection: RefCell<::std::result::Result<Box<dyn crate::Connection<VatId>>, ::capnp::Error>>,
+    tasks: RefCell<Option<super::task_set::TaskSetHandle<capnp::Error>>>,
+    connection: RefCell<::std::result::Result<Box<dyn super::Connection<VatId>>, capnp::Error>>,
     disconnect_fulfiller: RefCell<Option<oneshot::Sender<Promise<(), Error>>>>,
 
     client_downcast_map: RefCell<HashMap<usize, WeakClient<VatId>>>,
@@ -428,7 +426,7 @@ where
 impl<VatId> ConnectionState<VatId> {
     pub fn new(
         bootstrap_cap: Box<dyn ClientHook>,
-        connection: Box<dyn crate::Connection<VatId>>,
+        connection: Box<dyn super::Connection<VatId>>,
         disconnect_fulfiller: oneshot::Sender<Promise<(), Error>>,
     ) -> (TaskSet<Error>, Rc<Self>) {
         let state = Rc::new(Self {
@@ -455,14 +453,14 @@ impl<VatId> ConnectionState<VatId> {
     fn new_outgoing_message(
         &self,
         first_segment_words: u32,
-    ) -> capnp::Result<Box<dyn crate::OutgoingMessage>> {
+    ) -> capnp::Result<Box<dyn super::OutgoingMessage>> {
         match self.connection.borrow_mut().as_mut() {
             Err(e) => Err(e.clone()),
             Ok(c) => Ok(c.new_outgoing_message(first_segment_words)),
         }
     }
 
-    fn disconnect(&self, error: ::capnp::Error) {
+    fn disconnect(&self, error: capnp::Error) {
         if self.connection.borrow().is_err() {
             // Already disconnected.
             return;
@@ -472,7 +470,7 @@ impl<VatId> ConnectionState<VatId> {
         // destructors could come back and mess with the tables.
         let mut pipelines_to_release = Vec::new();
         let mut clients_to_release = Vec::new();
-        //let mut tail_calls_to_release = Vec::new();
+        let mut tail_calls_to_release = Vec::new();
         let mut resolve_ops_to_release = Vec::new();
 
         for q in self.questions.borrow().iter() {
@@ -486,8 +484,8 @@ impl<VatId> ConnectionState<VatId> {
         {
             let answer_slots = &mut self.answers.borrow_mut().slots;
             for (_, ref mut answer) in answer_slots.iter_mut() {
-                // TODO tail call
-                pipelines_to_release.push(answer.pipeline.take())
+                pipelines_to_release.push(answer.pipeline.take());
+                tail_calls_to_release.push(answer.redirected_results.take());
             }
         }
 
@@ -529,7 +527,7 @@ impl<VatId> ConnectionState<VatId> {
         drop(pipelines_to_release);
         drop(clients_to_release);
         drop(resolve_ops_to_release);
-        // TODO drop tail calls
+        drop(tail_calls_to_release);
 
         match *self.connection.borrow_mut() {
             Ok(ref mut c) => {
@@ -554,7 +552,7 @@ impl<VatId> ConnectionState<VatId> {
                 let promise = c.shutdown(Err(error)).then(|r| match r {
                     Ok(()) => Promise::ok(()),
                     Err(e) => {
-                        if e.kind != ::capnp::ErrorKind::Disconnected {
+                        if e.kind != capnp::ErrorKind::Disconnected {
                             // Don't report disconnects as an error.
                             Promise::err(e)
                         } else {
@@ -579,18 +577,18 @@ impl<VatId> ConnectionState<VatId> {
     // Dropping the returned promise cancels the computation.
     fn eagerly_evaluate<T, F>(&self, task: F) -> Promise<T, Error>
     where
-        F: Future<Output = Result<T, Error>> + 'static + Unpin,
-        T: 'static,
+        F: Future<Output = Result<T, Error>> + MaybeSend + 'static + Unpin,
+        T: MaybeSend + 'static,
     {
         let (tx, rx) = oneshot::channel::<Result<T, Error>>();
         let (tx2, rx2) = oneshot::channel::<()>();
         let f1 = Box::pin(task.map(move |r| {
             let _ = tx.send(r);
-        })) as Pin<Box<dyn Future<Output = ()> + Unpin>>;
-        let f2 = Box::pin(rx2.map(drop)) as Pin<Box<dyn Future<Output = ()> + Unpin>>;
+        })) as BoxFuture<()>;
+        let f2 = Box::pin(rx2.map(drop)) as BoxFuture<()>;
 
         self.add_task(future::select(f1, f2).map(|_| Ok(())));
-        Promise::from_future(rx.map_err(crate::canceled_to_error).map(|r| {
+        Promise::from_future(rx.map_err(super::canceled_to_error).map(|r| {
             drop(tx2);
             r?
         }))
@@ -598,18 +596,39 @@ impl<VatId> ConnectionState<VatId> {
 
     fn add_task<F>(&self, task: F)
     where
-        F: Future<Output = Result<(), Error>> + 'static,
+        F: Future<Output = Result<(), Error>> + MaybeSend + 'static,
     {
         if let Some(ref mut tasks) = *self.tasks.borrow_mut() {
             tasks.add(task);
         }
     }
 
+    /// Sends a streaming call through `flow_controller`, creating it first if need be.
+    fn send_streaming(
+        &self,
+        flow_controller: &mut Option<Box<dyn super::FlowController>>,
+        message: Box<dyn super::OutgoingMessage>,
+        ack: Promise<(), Error>,
+    ) -> Promise<(), Error> {
+        if flow_controller.is_none() {
+            let (new_flow_controller, driver) = match self.connection.borrow_mut().as_mut() {
+                Err(e) => return Promise::err(e.clone()),
+                Ok(c) => c.new_stream(),
+            };
+            self.add_task(driver);
+            *flow_controller = Some(new_flow_controller);
+        }
+        match flow_controller {
+            Some(flow_controller) => flow_controller.send(message, ack),
+            None => unreachable!(),
+        }
+    }
+
     pub fn bootstrap(state: &Rc<Self>) -> Box<dyn ClientHook> {
         let question_id = state.questions.borrow_mut().push(Question::new());
 
         let (fulfiller, promise) = oneshot::channel();
-        let promise = promise.map_err(crate::canceled_to_error);
+        let promise = promise.map_err(super::canceled_to_error);
         let promise = promise.and_then(|response_promise| response_promise);
         let question_ref = Rc::new(RefCell::new(QuestionRef::new(
             state.clone(),
@@ -677,7 +696,7 @@ impl<VatId> ConnectionState<VatId> {
 
     fn send_unimplemented(
         connection_state: &Rc<Self>,
-        message: &Box<dyn crate::IncomingMessage>,
+        message: &Box<dyn super::IncomingMessage>,
     ) -> capnp::Result<()> {
         let mut out_message = connection_state.new_outgoing_message(50)?; // XXX size hint
         {
@@ -727,7 +746,7 @@ impl<VatId> ConnectionState<VatId> {
         connection_state: &Rc<Self>,
         bootstrap: bootstrap::Reader,
     ) -> capnp::Result<()> {
-        use ::capnp::traits::ImbueMut;
+        use capnp::traits::ImbueMut;
 
         let answer_id = bootstrap.get_question_id();
         if connection_state.connection.borrow().is_err() {
@@ -889,8 +908,8 @@ impl<VatId> ConnectionState<VatId> {
 
     fn handle_message(
         weak_state: &Weak<Self>,
-        message: Box<dyn crate::IncomingMessage>,
-    ) -> ::capnp::Result<()> {
+        message: Box<dyn super::IncomingMessage>,
+    ) -> capnp::Result<()> {
         let Some(connection_state) = weak_state.upgrade() else {
             return Err(Error::disconnected(
                 "handle_message() cannot continue without a connection".into(),
@@ -946,7 +965,7 @@ impl<VatId> ConnectionState<VatId> {
                 let answer = Answer::new();
 
                 let (results_inner_fulfiller, results_inner_promise) = oneshot::channel();
-                let results_inner_promise = results_inner_promise.map_err(crate::canceled_to_error);
+                let results_inner_promise = results_inner_promise.map_err(super::canceled_to_error);
                 let results = Results::new(
                     &connection_state,
                     question_id,
@@ -958,7 +977,7 @@ impl<VatId> ConnectionState<VatId> {
                 let (redirected_results_done_promise, redirected_results_done_fulfiller) =
                     if redirect_results {
                         let (f, p) = oneshot::channel::<Result<Response<VatId>, Error>>();
-                        let p = p.map_err(crate::canceled_to_error).and_then(future::ready);
+                        let p = p.map_err(super::canceled_to_error).and_then(future::ready);
                         (Some(Promise::from_future(p)), Some(f))
                     } else {
                         (None, None)
@@ -1007,11 +1026,9 @@ impl<VatId> ConnectionState<VatId> {
                             answer.pipeline = Some(Box::new(pipeline));
                             if redirect_results {
                                 answer.redirected_results = redirected_results_done_promise;
-                                // More to do here?
-                            } else {
-                                answer.call_completion_promise =
-                                    Some(connection_state.eagerly_evaluate(fork));
                             }
+                            answer.call_completion_promise =
+                                Some(connection_state.eagerly_evaluate(fork));
                         }
                         None => unreachable!(),
                     }
@@ -1053,7 +1070,19 @@ impl<VatId> ConnectionState<VatId> {
                                         Self::send_unimplemented(&connection_state, &message)?;
                                     }
                                     return_::ResultsSentElsewhere(_) => {
-                                        Self::send_unimplemented(&connection_state, &message)?;
+                                        if !question.is_tail_call {
+                                            return Err(Error::failed(
+                                                "`Return` had `resultsSentElsewhere` but this was \
+                                                 not a tail call."
+                                                    .to_string(),
+                                            ));
+                                        }
+                                        // The results went to whoever took them from this
+                                        // question, so there is nothing to pass on.
+                                        let tmp =
+                                            question_ref.upgrade().expect("dangling question ref?");
+                                        tmp.borrow_mut()
+                                            .fulfill(Promise::ok(Response::sent_elsewhere()));
                                     }
                                     return_::TakeFromOtherQuestion(id) => {
                                         if let Some(answer) =
@@ -1149,7 +1178,7 @@ impl<VatId> ConnectionState<VatId> {
                 | message::ObsoleteSave(_)
                 | message::ObsoleteDelete(_),
             )
-            | Err(::capnp::NotInSchema(_)) => {
+            | Err(capnp::NotInSchema(_)) => {
                 Self::send_unimplemented(&connection_state, &message)?;
             }
         }
@@ -1175,7 +1204,7 @@ impl<VatId> ConnectionState<VatId> {
         }
     }
 
-    fn release_export(&self, id: ExportId, refcount: u32) -> ::capnp::Result<()> {
+    fn release_export(&self, id: ExportId, refcount: u32) -> capnp::Result<()> {
         let mut erase_export = false;
         let mut client_ptr = 0;
         match self.exports.borrow_mut().find(id) {
@@ -1205,7 +1234,7 @@ impl<VatId> ConnectionState<VatId> {
         Ok(())
     }
 
-    fn release_exports(&self, exports: &[ExportId]) -> ::capnp::Result<()> {
+    fn release_exports(&self, exports: &[ExportId]) -> capnp::Result<()> {
         for &export_id in exports {
             self.release_export(export_id, 1)?;
         }
@@ -1219,7 +1248,7 @@ impl<VatId> ConnectionState<VatId> {
     fn get_message_target(
         &self,
         target: message_target::Reader,
-    ) -> ::capnp::Result<Box<dyn ClientHook>> {
+    ) -> capnp::Result<Box<dyn ClientHook>> {
         match target.which()? {
             message_target::ImportedCap(export_id) => {
                 match self.exports.borrow().slots.get(export_id as usize) {
@@ -1375,7 +1404,7 @@ impl<VatId> ConnectionState<VatId> {
         state: &Rc<Self>,
         cap: &Box<dyn ClientHook>,
         mut descriptor: cap_descriptor::Builder,
-    ) -> ::capnp::Result<Option<ExportId>> {
+    ) -> capnp::Result<Option<ExportId>> {
         // Find the innermost wrapped capability.
         let mut inner = cap.clone();
         while let Some(resolved) = inner.get_resolved() {
@@ -1530,7 +1559,7 @@ impl<VatId> ConnectionState<VatId> {
     fn receive_cap(
         state: &Rc<Self>,
         descriptor: cap_descriptor::Reader,
-    ) -> ::capnp::Result<Option<Box<dyn ClientHook>>> {
+    ) -> capnp::Result<Option<Box<dyn ClientHook>>> {
         match descriptor.which()? {
             cap_descriptor::None(()) => Ok(None),
             cap_descriptor::SenderHosted(sender_hosted) => {
@@ -1571,8 +1600,8 @@ impl<VatId> ConnectionState<VatId> {
 
     fn receive_caps(
         state: &Rc<Self>,
-        cap_table: ::capnp::struct_list::Reader<cap_descriptor::Owned>,
-    ) -> ::capnp::Result<Vec<Option<Box<dyn ClientHook>>>> {
+        cap_table: capnp::struct_list::Reader<cap_descriptor::Owned>,
+    ) -> capnp::Result<Vec<Option<Box<dyn ClientHook>>>> {
         let mut result = Vec::new();
         for idx in 0..cap_table.len() {
             result.push(Self::receive_cap(state, cap_table.get(idx))?);
@@ -1609,7 +1638,7 @@ impl<VatId> Disconnector<VatId> {
     }
     fn disconnect(&self) {
         if let Some(ref state) = *(self.connection_state.borrow()) {
-            state.disconnect(::capnp::Error::disconnected(
+            state.disconnect(capnp::Error::disconnected(
                 "client requested disconnect".to_owned(),
             ));
         }
@@ -1653,7 +1682,7 @@ where
     VatId: 'static,
 {
     _connection_state: Rc<ConnectionState<VatId>>,
-    message: Box<dyn crate::IncomingMessage>,
+    message: Box<dyn super::IncomingMessage>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
     _question_ref: Rc<RefCell<QuestionRef<VatId>>>,
 }
@@ -1664,6 +1693,9 @@ where
 {
     Rpc(ResponseState<VatId>),
     LocallyRedirected(Box<dyn ResultsDoneHook>),
+
+    /// The response to a tail call, whose results were sent elsewhere.
+    SentElsewhere,
 }
 
 struct Response<VatId>
@@ -1677,7 +1709,7 @@ impl<VatId> Response<VatId> {
     fn new(
         connection_state: Rc<ConnectionState<VatId>>,
         question_ref: Rc<RefCell<QuestionRef<VatId>>>,
-        message: Box<dyn crate::IncomingMessage>,
+        message: Box<dyn super::IncomingMessage>,
         cap_table_array: Vec<Option<Box<dyn ClientHook>>>,
     ) -> Self {
         Self {
@@ -1694,6 +1726,11 @@ impl<VatId> Response<VatId> {
             variant: Rc::new(ResponseVariant::LocallyRedirected(results_done)),
         }
     }
+    fn sent_elsewhere() -> Self {
+        Self {
+            variant: Rc::new(ResponseVariant::SentElsewhere),
+        }
+    }
 }
 
 impl<VatId> Clone for Response<VatId> {
@@ -1705,7 +1742,7 @@ impl<VatId> Clone for Response<VatId> {
 }
 
 impl<VatId> ResponseHook for Response<VatId> {
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
         match *self.variant {
             ResponseVariant::Rpc(ref state) => {
                 match state
@@ -1715,10 +1752,11 @@ impl<VatId> ResponseHook for Response<VatId> {
                     .which()?
                 {
                     message::Return(Ok(ret)) => match ret.which()? {
-                        return_::Results(Ok(mut payload)) => {
-                            use ::capnp::traits::Imbue;
-                            payload.imbue(&state.cap_table);
-                            Ok(payload.get_content())
+                        return_::Results(Ok(payload)) => {
+                            use capnp::traits::Imbue;
+                            let mut content = payload.get_content();
+                            content.imbue(&state.cap_table);
+                            Ok(content)
                         }
                         _ => unreachable!(),
                     },
@@ -1726,6 +1764,9 @@ impl<VatId> ResponseHook for Response<VatId> {
                 }
             }
             ResponseVariant::LocallyRedirected(ref results_done) => results_done.get(),
+            ResponseVariant::SentElsewhere => Err(Error::failed(
+                "Tail call results were sent elsewhere.".to_string(),
+            )),
         }
     }
 }
@@ -1736,11 +1777,11 @@ where
 {
     connection_state: Rc<ConnectionState<VatId>>,
     target: Client<VatId>,
-    message: Box<dyn crate::OutgoingMessage>,
+    message: Box<dyn super::OutgoingMessage>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
 }
 
-fn get_call(message: &mut Box<dyn crate::OutgoingMessage>) -> ::capnp::Result<call::Builder> {
+fn get_call(message: &mut Box<dyn super::OutgoingMessage>) -> capnp::Result<call::Builder> {
     let message_root: message::Builder = message.get_body()?.get_as()?;
     match message_root.which()? {
         message::Call(call) => call,
@@ -1750,15 +1791,21 @@ fn get_call(message: &mut Box<dyn crate::OutgoingMessage>) -> ::capnp::Result<ca
     }
 }
 
+/// A question that has been asked, and a promise for its answer.
+type SentQuestion<VatId> = (
+    Rc<RefCell<QuestionRef<VatId>>>,
+    Promise<Response<VatId>, Error>,
+);
+
 impl<VatId> Request<VatId>
 where
     VatId: 'static,
 {
     fn new(
         connection_state: Rc<ConnectionState<VatId>>,
-        _size_hint: Option<::capnp::MessageSize>,
+        _size_hint: Option<capnp::MessageSize>,
         target: Client<VatId>,
-    ) -> ::capnp::Result<Self> {
+    ) -> capnp::Result<Self> {
         let message = connection_state.new_outgoing_message(100)?;
         Ok(Self {
             connection_state,
@@ -1775,18 +1822,28 @@ where
 
     fn send_internal(
         connection_state: &Rc<ConnectionState<VatId>>,
-        mut message: Box<dyn crate::OutgoingMessage>,
+        mut message: Box<dyn super::OutgoingMessage>,
+        cap_table: &[Option<Box<dyn ClientHook>>],
+        is_tail_call: bool,
+    ) -> SentQuestion<VatId> {
+        let (question_ref, promise) =
+            Self::prepare_send(connection_state, &mut message, cap_table, is_tail_call);
+        let _ = message.send();
+        (question_ref, promise)
+    }
+
+    /// Sets up the question for a call, leaving it to the caller to send the message.
+    fn prepare_send(
+        connection_state: &Rc<ConnectionState<VatId>>,
+        message: &mut Box<dyn super::OutgoingMessage>,
         cap_table: &[Option<Box<dyn ClientHook>>],
         is_tail_call: bool,
-    ) -> (
-        Rc<RefCell<QuestionRef<VatId>>>,
-        Promise<Response<VatId>, Error>,
-    ) {
+    ) -> SentQuestion<VatId> {
         // Build the cap table.
         let exports = ConnectionState::write_descriptors(
             connection_state,
             cap_table,
-            get_call(&mut message).unwrap().get_params().unwrap(),
+            get_call(message).unwrap().get_params().unwrap(),
         );
 
         // Init the question table.  Do this after writing descriptors to avoid interference.
@@ -1797,17 +1854,16 @@ where
 
         let question_id = connection_state.questions.borrow_mut().push(question);
         {
-            let mut call_builder: call::Builder = get_call(&mut message).unwrap();
+            let mut call_builder: call::Builder = get_call(message).unwrap();
             // Finish and send.
             call_builder.reborrow().set_question_id(question_id);
             if is_tail_call {
                 call_builder.get_send_results_to().set_yourself(());
             }
         }
-        let _ = message.send();
         // Make the result promise.
         let (fulfiller, promise) = oneshot::channel::<Promise<Response<VatId>, Error>>();
-        let promise = promise.map_err(crate::canceled_to_error).and_then(|x| x);
+        let promise = promise.map_err(super::canceled_to_error).and_then(|x| x);
         let question_ref = Rc::new(RefCell::new(QuestionRef::new(
             connection_state.clone(),
             question_id,
@@ -1830,7 +1886,7 @@ where
 
 impl<VatId> RequestHook for Request<VatId> {
     fn get(&mut self) -> any_pointer::Builder {
-        use ::capnp::traits::ImbueMut;
+        use capnp::traits::ImbueMut;
         let mut builder = get_call(&mut self.message)
             .unwrap()
             .get_params()
@@ -1842,7 +1898,7 @@ impl<VatId> RequestHook for Request<VatId> {
     fn get_brand<'a>(&self) -> usize {
         self.connection_state.get_brand()
     }
-    fn send(self: Box<Self>) -> ::capnp::capability::RemotePromise<any_pointer::Owned> {
+    fn send(self: Box<Self>) -> capnp::capability::RemotePromise<any_pointer::Owned> {
         let tmp = *self;
         let Self {
             connection_state,
@@ -1895,17 +1951,17 @@ impl<VatId> RequestHook for Request<VatId> {
 
                 let app_promise = Promise::from_future(
                     forked_promise2
-                        .map_ok(|response| ::capnp::capability::Response::new(Box::new(response))),
+                        .map_ok(|response| capnp::capability::Response::new(Box::new(response))),
                 );
 
-                ::capnp::capability::RemotePromise {
+                capnp::capability::RemotePromise {
                     promise: app_promise,
                     pipeline: any_pointer::Pipeline::new(Box::new(pipeline)),
                 }
             }
         }
     }
-    fn tail_send(self: Box<Self>) -> Option<(u32, Promise<(), Error>, Box<dyn PipelineHook>)> {
+    fn send_streaming(self: Box<Self>) -> Promise<(), Error> {
         let tmp = *self;
         let Self {
             connection_state,
@@ -1915,10 +1971,62 @@ impl<VatId> RequestHook for Request<VatId> {
         } = tmp;
 
         if connection_state.connection.borrow().is_err() {
+            // Disconnected; there's no flow controller to go through.
+            let request: Box<dyn RequestHook> = Box::new(Self {
+                connection_state,
+                target,
+                message,
+                cap_table,
+            });
+            return Promise::from_future(request.send().promise.map_ok(|_| ()));
+        }
+
+        let write_target_result = {
+            let call_builder: call::Builder = get_call(&mut message).unwrap();
+            target.write_target(call_builder.get_target().unwrap())
+        };
+        match write_target_result {
+            Some(redirect) => {
+                // As in send(), the request has to be copied over to the new target.
+                let mut call_builder: call::Builder = get_call(&mut message).unwrap();
+                let mut replacement = redirect.new_call(
+                    call_builder.reborrow().get_interface_id(),
+                    call_builder.reborrow().get_method_id(),
+                    None,
+                );
+                replacement
+                    .set(
+                        call_builder
+                            .get_params()
+                            .unwrap()
+                            .get_content()
+                            .into_reader(),
+                    )
+                    .unwrap();
+                replacement.hook.send_streaming()
+            }
+            None => {
+                let (_question_ref, promise) =
+                    Self::prepare_send(&connection_state, &mut message, &cap_table, false);
+                target.send_streaming(message, Promise::from_future(promise.map_ok(|_| ())))
+            }
+        }
+    }
+
+    fn tail_send(self: Box<Self>) -> Result<TailSend, Box<dyn RequestHook>> {
+        if self.connection_state.connection.borrow().is_err() {
             // Disconnected; fall back to a regular send() which will fail appropriately.
-            return None;
+            return Err(self);
         }
 
+        let tmp = *self;
+        let Self {
+            connection_state,
+            target,
+            mut message,
+            cap_table,
+        } = tmp;
+
         let write_target_result = {
             let call_builder: crate::rpc_capnp::call::Builder = get_call(&mut message).unwrap();
             target.write_target(call_builder.get_target().unwrap())
@@ -1926,21 +2034,24 @@ impl<VatId> RequestHook for Request<VatId> {
 
         let (question_ref, promise) = match write_target_result {
             Some(_redirect) => {
-                return None;
+                // The target now lives elsewhere, so it cannot return the results to our caller.
+                return Err(Box::new(Self {
+                    connection_state,
+                    target,
+                    message,
+                    cap_table,
+                }));
             }
             None => Self::send_internal(&connection_state, message, &cap_table, true),
         };
 
-        let promise = promise.map_ok(|_response| {
-            // Response should be null if `Return` handling code is correct.
-
-            unimplemented!()
-        });
+        // The results went straight to our caller. The `Return` only says that they did.
+        let promise = promise.map_ok(|_response| ());
 
         let question_id = question_ref.borrow().id;
         let pipeline = Pipeline::never_done(connection_state, question_ref);
 
-        Some((
+        Ok((
             question_id,
             Promise::from_future(promise),
             Box::new(pipeline),
@@ -1963,19 +2074,19 @@ where
 {
     variant: PipelineVariant<VatId>,
     redirect_later:
-        Option<RefCell<futures::future::Shared<Promise<Response<VatId>, ::capnp::Error>>>>,
+        Option<RefCell<futures::future::Shared<Promise<Response<VatId>, capnp::Error>>>>,
     connection_state: Rc<ConnectionState<VatId>>,
 
     #[allow(dead_code)]
     resolve_self_promise: Promise<(), Error>,
 
     promise_clients_to_resolve: RefCell<
-        crate::sender_queue::SenderQueue<
+        super::sender_queue::SenderQueue<
             (Weak<RefCell<PromiseClient<VatId>>>, Vec<PipelineOp>),
             (),
         >,
     >,
-    resolution_waiters: crate::sender_queue::SenderQueue<(), ()>,
+    resolution_waiters: super::sender_queue::SenderQueue<(), ()>,
 }
 
 impl<VatId> PipelineState<VatId>
@@ -2025,15 +2136,15 @@ impl<VatId> Pipeline<VatId> {
     fn new(
         connection_state: &Rc<ConnectionState<VatId>>,
         question_ref: Rc<RefCell<QuestionRef<VatId>>>,
-        redirect_later: Option<Promise<Response<VatId>, ::capnp::Error>>,
+        redirect_later: Option<Promise<Response<VatId>, capnp::Error>>,
     ) -> Self {
         let state = Rc::new(RefCell::new(PipelineState {
             variant: PipelineVariant::Waiting(question_ref),
             connection_state: connection_state.clone(),
             redirect_later: None,
             resolve_self_promise: Promise::from_future(future::pending()),
-            promise_clients_to_resolve: RefCell::new(crate::sender_queue::SenderQueue::new()),
-            resolution_waiters: crate::sender_queue::SenderQueue::new(),
+            promise_clients_to_resolve: RefCell::new(super::sender_queue::SenderQueue::new()),
+            resolution_waiters: super::sender_queue::SenderQueue::new(),
         }));
         if let Some(redirect_later_promise) = redirect_later {
             let fork = redirect_later_promise.shared();
@@ -2066,8 +2177,8 @@ impl<VatId> Pipeline<VatId> {
             connection_state,
             redirect_later: None,
             resolve_self_promise: Promise::from_future(future::pending()),
-            promise_clients_to_resolve: RefCell::new(crate::sender_queue::SenderQueue::new()),
-            resolution_waiters: crate::sender_queue::SenderQueue::new(),
+            promise_clients_to_resolve: RefCell::new(super::sender_queue::SenderQueue::new()),
+            resolution_waiters: super::sender_queue::SenderQueue::new(),
         }));
 
         Self { state }
@@ -2127,13 +2238,13 @@ impl<VatId> PipelineHook for Pipeline<VatId> {
 }
 
 pub struct Params {
-    request: Box<dyn crate::IncomingMessage>,
+    request: Box<dyn super::IncomingMessage>,
     cap_table: Vec<Option<Box<dyn ClientHook>>>,
 }
 
 impl Params {
     fn new(
-        request: Box<dyn crate::IncomingMessage>,
+        request: Box<dyn super::IncomingMessage>,
         cap_table: Vec<Option<Box<dyn ClientHook>>>,
     ) -> Self {
         Self { request, cap_table }
@@ -2141,11 +2252,11 @@ impl Params {
 }
 
 impl ParamsHook for Params {
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
         let root: message::Reader = self.request.get_body()?.get_as()?;
         match root.which()? {
             message::Call(call) => {
-                use ::capnp::traits::Imbue;
+                use capnp::traits::Imbue;
                 let mut content = call?.get_params()?.get_content();
                 content.imbue(&self.cap_table);
                 Ok(content)
@@ -2159,13 +2270,17 @@ impl ParamsHook for Params {
 
 enum ResultsVariant {
     Rpc(
-        Box<dyn crate::OutgoingMessage>,
+        Box<dyn super::OutgoingMessage>,
         Vec<Option<Box<dyn ClientHook>>>,
     ),
     LocallyRedirected(
-        ::capnp::message::Builder<::capnp::message::HeapAllocator>,
+        capnp::message::Builder<capnp::message::HeapAllocator>,
         Vec<Option<Box<dyn ClientHook>>>,
     ),
+
+    /// The call ended with a tail call, whose results the caller was told to take. Holds the
+    /// pipeline of the tail call.
+    TailCall(Box<dyn PipelineHook>),
 }
 
 struct ResultsInner<VatId>
@@ -2203,7 +2318,7 @@ where
                 }
                 _ => {
                     self.variant = Some(ResultsVariant::LocallyRedirected(
-                        ::capnp::message::Builder::new_default(),
+                        capnp::message::Builder::new_default(),
                         Vec::new(),
                     ));
                 }
@@ -2258,8 +2373,8 @@ impl<VatId> Drop for Results<VatId> {
 }
 
 impl<VatId> ResultsHook for Results<VatId> {
-    fn get(&mut self) -> ::capnp::Result<any_pointer::Builder> {
-        use ::capnp::traits::ImbueMut;
+    fn get(&mut self) -> capnp::Result<any_pointer::Builder> {
+        use capnp::traits::ImbueMut;
         if let Some(ref mut inner) = self.inner {
             inner.ensure_initialized();
             match inner.variant {
@@ -2287,51 +2402,68 @@ impl<VatId> ResultsHook for Results<VatId> {
                     result.imbue_mut(cap_table);
                     Ok(result)
                 }
+                Some(ResultsVariant::TailCall(_)) => unreachable!(),
             }
         } else {
             unreachable!()
         }
     }
 
-    fn tail_call(self: Box<Self>, _request: Box<dyn RequestHook>) -> Promise<(), Error> {
-        unimplemented!()
+    fn tail_call(self: Box<Self>, request: Box<dyn RequestHook>) -> Promise<(), Error> {
+        self.direct_tail_call(request).0
     }
 
     fn direct_tail_call(
         mut self: Box<Self>,
         request: Box<dyn RequestHook>,
     ) -> (Promise<(), Error>, Box<dyn PipelineHook>) {
-        if let (Some(inner), Some(fulfiller)) =
-            (self.inner.take(), self.results_done_fulfiller.take())
-        {
-            let state = inner.connection_state.clone();
-            if request.get_brand() == state.get_brand() && !inner.redirect_results {
-                // The tail call is headed towards the peer that called us in the first place, so we can
-                // optimize out the return trip.
-                if let Some((question_id, promise, pipeline)) = request.tail_send() {
-                    let mut message = state.new_outgoing_message(100).expect("no connection?"); // size hint?
-
-                    {
-                        let root: message::Builder = message.get_body().unwrap().init_as();
-                        let mut ret = root.init_return();
-                        ret.set_answer_id(inner.answer_id);
-                        ret.set_release_param_caps(false);
-                        ret.set_take_from_other_question(question_id);
+        let is_same_peer = match self.inner {
+            Some(ref inner) => {
+                request.get_brand() == inner.connection_state.get_brand() && !inner.redirect_results
+            }
+            None => unreachable!(),
+        };
+        let request = if is_same_peer {
+            // The tail call is headed towards the peer that called us in the first place, so we can
+            // optimize out the return trip.
+            match request.tail_send() {
+                Ok((question_id, promise, pipeline)) => {
+                    let (Some(mut inner), Some(fulfiller)) =
+                        (self.inner.take(), self.results_done_fulfiller.take())
+                    else {
+                        unreachable!()
+                    };
+                    if let Ok(mut message) = inner.connection_state.new_outgoing_message(50) {
+                        {
+                            let root: message::Builder = message.get_body().unwrap().init_as();
+                            let mut ret = root.init_return();
+                            ret.set_answer_id(inner.answer_id);
+                            ret.set_release_param_caps(false);
+                            ret.set_take_from_other_question(question_id);
+                        }
+                        let _ = message.send();
                     }
-                    let _ = message.send();
-
-                    // TODO cleanupanswertable
+                    inner
+                        .connection_state
+                        .answer_has_sent_return(inner.answer_id, Vec::new());
 
-                    let _ = fulfiller.send(inner); // ??
+                    inner.variant = Some(ResultsVariant::TailCall(pipeline.add_ref()));
+                    let _ = fulfiller.send(inner);
                     return (promise, pipeline);
                 }
-                unimplemented!()
-            } else {
-                unimplemented!()
+                Err(request) => request,
             }
         } else {
-            unreachable!();
-        }
+            request
+        };
+
+        // Wait for the results and copy them into ours.
+        let capnp::capability::RemotePromise { promise, pipeline } = request.send();
+        let promise = promise.and_then(move |response| {
+            pry!(pry!(self.get()).set_as(pry!(response.get())));
+            Promise::ok(())
+        });
+        (Promise::from_future(promise), pipeline.hook)
     }
 
     fn allow_cancellation(&self) {
@@ -2341,11 +2473,11 @@ impl<VatId> ResultsHook for Results<VatId> {
 
 enum ResultsDoneVariant {
     Rpc(
-        Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>,
+        Rc<capnp::message::Builder<capnp::message::HeapAllocator>>,
         Vec<Option<Box<dyn ClientHook>>>,
     ),
     LocallyRedirected(
-        ::capnp::message::Builder<::capnp::message::HeapAllocator>,
+        capnp::message::Builder<capnp::message::HeapAllocator>,
         Vec<Option<Box<dyn ClientHook>>>,
     ),
 }
@@ -2365,7 +2497,7 @@ impl ResultsDone {
     {
         match results_inner {
             Err(e) => {
-                pipeline_sender.complete(Box::new(crate::broken::Pipeline::new(e.clone())));
+                pipeline_sender.complete(Box::new(super::broken::Pipeline::new(e.clone())));
                 Err(e)
             }
             Ok(mut results_inner) => {
@@ -2456,26 +2588,57 @@ impl ResultsDone {
                                 connection_state.answer_has_sent_return(answer_id, Vec::new());
 
                                 pipeline_sender
-                                    .complete(Box::new(crate::broken::Pipeline::new(e.clone())));
+                                    .complete(Box::new(super::broken::Pipeline::new(e.clone())));
 
                                 Err(e)
                             }
                         }
                     }
                     Some(ResultsVariant::LocallyRedirected(results_done, cap_table)) => {
+                        // Let the caller know that the results are waiting to be taken by a
+                        // `Return.takeFromOtherQuestion`, or that there are none.
+                        if let Ok(connection) = connection_state.connection.borrow_mut().as_mut() {
+                            let mut message = connection.new_outgoing_message(50); // XXX size hint
+                            {
+                                let root: message::Builder = message.get_body()?.get_as()?;
+                                let mut ret = root.init_return();
+                                ret.set_answer_id(answer_id);
+                                ret.set_release_param_caps(false);
+                                match call_status {
+                                    Ok(()) => ret.set_results_sent_elsewhere(()),
+                                    Err(ref e) => from_error(e, ret.init_exception()),
+                                }
+                            }
+                            let _ = message.send();
+                        }
+                        connection_state.answer_has_sent_return(answer_id, Vec::new());
+
+                        if let Err(e) = call_status {
+                            pipeline_sender
+                                .complete(Box::new(super::broken::Pipeline::new(e.clone())));
+                            return Err(e);
+                        }
                         let hook = Box::new(Self::redirected(results_done, cap_table))
                             as Box<dyn ResultsDoneHook>;
                         pipeline_sender
-                            .complete(Box::new(crate::local::Pipeline::new(hook.clone())));
+                            .complete(Box::new(super::local::Pipeline::new(hook.clone())));
                         Ok(hook)
                     }
+                    Some(ResultsVariant::TailCall(pipeline)) => {
+                        // The `Return` was sent when the tail call was made.
+                        pipeline_sender.complete(pipeline);
+
+                        // Only redirected calls look at the results, and those never end with a
+                        // tail call that the caller can take the results of.
+                        Err(Error::failed("Results were sent elsewhere.".to_string()))
+                    }
                 }
             }
         }
     }
 
     fn rpc(
-        message: Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>,
+        message: Rc<capnp::message::Builder<capnp::message::HeapAllocator>>,
         cap_table: Vec<Option<Box<dyn ClientHook>>>,
     ) -> Self {
         Self {
@@ -2484,7 +2647,7 @@ impl ResultsDone {
     }
 
     fn redirected(
-        message: ::capnp::message::Builder<::capnp::message::HeapAllocator>,
+        message: capnp::message::Builder<capnp::message::HeapAllocator>,
         cap_table: Vec<Option<Box<dyn ClientHook>>>,
     ) -> Self {
         Self {
@@ -2499,8 +2662,8 @@ impl ResultsDoneHook for ResultsDone {
             inner: self.inner.clone(),
         })
     }
-    fn get(&self) -> ::capnp::Result<any_pointer::Reader> {
-        use ::capnp::traits::Imbue;
+    fn get(&self) -> capnp::Result<any_pointer::Reader> {
+        use capnp::traits::Imbue;
         match *self.inner {
             ResultsDoneVariant::Rpc(ref message, ref cap_table) => {
                 let root: message::Reader = message.get_root_as_reader()?;
@@ -2593,6 +2756,9 @@ where
 
     /// Number of times we've received this import from the peer.
     remote_ref_count: u32,
+
+    /// Paces streaming calls to this capability. Created on the first such call.
+    flow_controller: Option<Box<dyn super::FlowController>>,
 }
 
 impl<VatId> Drop for ImportClient<VatId> {
@@ -2650,6 +2816,7 @@ where
             connection_state: connection_state.clone(),
             import_id,
             remote_ref_count: 0,
+            flow_controller: None,
         }))
     }
 
@@ -2673,6 +2840,9 @@ where
     connection_state: Rc<ConnectionState<VatId>>,
     question_ref: Rc<RefCell<QuestionRef<VatId>>>,
     ops: Vec<PipelineOp>,
+
+    /// Paces streaming calls to this capability. Created on the first such call.
+    flow_controller: Option<Box<dyn super::FlowController>>,
 }
 
 impl<VatId> PipelineClient<VatId>
@@ -2688,6 +2858,7 @@ where
             connection_state: connection_state.clone(),
             question_ref,
             ops,
+            flow_controller: None,
         }))
     }
 }
@@ -2721,7 +2892,7 @@ where
     cap: Box<dyn ClientHook>,
     import_id: Option<ImportId>,
     received_call: bool,
-    resolution_waiters: crate::sender_queue::SenderQueue<(), Box<dyn ClientHook>>,
+    resolution_waiters: super::sender_queue::SenderQueue<(), Box<dyn ClientHook>>,
 }
 
 impl<VatId> PromiseClient<VatId> {
@@ -2736,7 +2907,7 @@ impl<VatId> PromiseClient<VatId> {
             cap: initial,
             import_id,
             received_call: false,
-            resolution_waiters: crate::sender_queue::SenderQueue::new(),
+            resolution_waiters: super::sender_queue::SenderQueue::new(),
         }))
     }
 
@@ -2759,7 +2930,7 @@ impl<VatId> PromiseClient<VatId> {
             // a `Disembargo` to echo through the peer.
             let (fulfiller, promise) = oneshot::channel::<Result<(), Error>>();
             let promise = promise
-                .map_err(crate::canceled_to_error)
+                .map_err(super::canceled_to_error)
                 .and_then(future::ready);
             let embargo = Embargo::new(fulfiller);
             let embargo_id = connection_state.embargoes.borrow_mut().push(embargo);
@@ -2916,7 +3087,7 @@ impl<VatId> Client<VatId> {
                 let mut transform =
                     builder.init_transform(pipeline_client.borrow().ops.len() as u32);
                 for idx in 0..pipeline_client.borrow().ops.len() {
-                    if let ::capnp::private::capability::PipelineOp::GetPointerField(ordinal) =
+                    if let capnp::private::capability::PipelineOp::GetPointerField(ordinal) =
                         pipeline_client.borrow().ops[idx]
                     {
                         transform
@@ -2938,6 +3109,36 @@ impl<VatId> Client<VatId> {
         }
     }
 
+    /// Sends a streaming call whose target has been written by `write_target()`.
+    fn send_streaming(
+        &self,
+        message: Box<dyn super::OutgoingMessage>,
+        ack: Promise<(), Error>,
+    ) -> Promise<(), Error> {
+        match &self.variant {
+            ClientVariant::Import(import_client) => self.connection_state.send_streaming(
+                &mut import_client.borrow_mut().flow_controller,
+                message,
+                ack,
+            ),
+            ClientVariant::Pipeline(pipeline_client) => self.connection_state.send_streaming(
+                &mut pipeline_client.borrow_mut().flow_controller,
+                message,
+                ack,
+            ),
+            ClientVariant::Promise(promise_client) => {
+                let cap = promise_client.borrow().cap.add_ref();
+                match Self::from_ptr(cap.get_ptr(), &self.connection_state) {
+                    Some(c) => c.send_streaming(message, ack),
+                    None => unreachable!(),
+                }
+            }
+            _ => {
+                unimplemented!()
+            }
+        }
+    }
+
     fn write_descriptor(&self, mut descriptor: cap_descriptor::Builder) -> Option<u32> {
         match &self.variant {
             ClientVariant::Import(import_client) => {
@@ -2951,7 +3152,7 @@ impl<VatId> Client<VatId> {
                 let mut transform =
                     promised_answer.init_transform(pipeline_client.borrow().ops.len() as u32);
                 for idx in 0..pipeline_client.borrow().ops.len() {
-                    if let ::capnp::private::capability::PipelineOp::GetPointerField(ordinal) =
+                    if let capnp::private::capability::PipelineOp::GetPointerField(ordinal) =
                         pipeline_client.borrow().ops[idx]
                     {
                         transform
@@ -3009,8 +3210,8 @@ impl<VatId> ClientHook for Client<VatId> {
         &self,
         interface_id: u64,
         method_id: u16,
-        size_hint: Option<::capnp::MessageSize>,
-    ) -> ::capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
+        size_hint: Option<capnp::MessageSize>,
+    ) -> capnp::capability::Request<any_pointer::Owned, any_pointer::Owned> {
         let request: Box<dyn RequestHook> =
             match Request::new(self.connection_state.clone(), size_hint, self.clone()) {
                 Ok(mut request) => {
@@ -3024,7 +3225,7 @@ impl<VatId> ClientHook for Client<VatId> {
                 Err(e) => Box::new(broken::Request::new(e, None)),
             };
 
-        ::capnp::capability::Request::new(request)
+        capnp::capability::Request::new(request)
     }
 
     fn call(
@@ -3032,9 +3233,9 @@ impl<VatId> ClientHook for Client<VatId> {
         interface_id: u64,
         method_id: u16,
         params: Box<dyn ParamsHook>,
-        mut results: Box<dyn ResultsHook>,
+        results: Box<dyn ResultsHook>,
     ) -> Promise<(), Error> {
-        // Implement call() by copying params and results messages.
+        // Implement call() by copying the params and making a tail call.
 
         let maybe_request = params.get().and_then(|p| {
             let mut request = p
@@ -3046,23 +3247,9 @@ impl<VatId> ClientHook for Client<VatId> {
 
         match maybe_request {
             Err(e) => Promise::err(e),
-            Ok(request) => {
-                let ::capnp::capability::RemotePromise { promise, .. } = request.send();
-
-                let promise = promise.and_then(move |response| {
-                    pry!(pry!(results.get()).set_as(pry!(response.get())));
-                    Promise::ok(())
-                });
-
-                Promise::from_future(promise)
-            }
+            // TODO: We can and should propagate cancellation.
+            Ok(request) => results.tail_call(request.hook),
         }
-        // TODO implement this in terms of direct tail call.
-        // We can and should propagate cancellation.
-        // (TODO ?)
-        // context -> allowCancellation();
-
-        //results.direct_tail_call(request.hook)
     }
 
     fn get_ptr(&self) -> usize {
@@ -22,18 +22,17 @@
 use futures::channel::oneshot;
 use futures::{FutureExt, TryFutureExt};
 
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use super::shared::{Rc, RefCell, Weak};
 
-use capnp::capability::Promise;
-use capnp::Error;
+use super::capnp::capability::{MaybeSend, Promise};
+use super::capnp::Error;
 
 use std::collections::BTreeMap;
 
 struct Inner<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     next_id: u64,
     map: BTreeMap<u64, (In, oneshot::Sender<Out>)>,
@@ -43,16 +42,16 @@ where
 /// type `Out`.
 pub struct SenderQueue<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     inner: Rc<RefCell<Inner<In, Out>>>,
 }
 
 pub struct Remover<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     id: u64,
     inner: Weak<RefCell<Inner<In, Out>>>,
@@ -60,8 +59,8 @@ where
 
 impl<In, Out> Drop for Remover<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     fn drop(&mut self) {
         if let Some(inner) = self.inner.upgrade() {
@@ -73,8 +72,8 @@ where
 
 impl<In, Out> SenderQueue<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     pub fn new() -> Self {
         Self {
@@ -142,16 +141,16 @@ where
 
 pub struct Drain<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     iter: ::std::collections::btree_map::IntoIter<u64, (In, oneshot::Sender<Out>)>,
 }
 
 impl<In, Out> ::std::iter::Iterator for Drain<In, Out>
 where
-    In: 'static,
-    Out: 'static,
+    In: MaybeSend + 'static,
+    Out: MaybeSend + 'static,
 {
     type Item = (In, oneshot::Sender<Out>);
     fn next(&mut self) -> Option<Self::Item> {
@@ -21,8 +21,7 @@
 
 use futures::{Future, FutureExt};
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use super::shared::{Rc, RefCell};
 
 pub fn split<F, T1, T2, E>(
     f: F,
@@ -0,0 +1,62 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! A thread-safe version of the RPC system, enabled by the `sync` feature.
+//!
+//! It serves and calls clients and servers generated with `capnpc::CompilerCommand::sync_rpc()`,
+//! which are built on the capability types in `capnp::sync`. Clients are `Send` and `Sync`, so
+//! capabilities can be shared and called from any thread. Servers, the promises that they return,
+//! and the streams passed to [`twoparty::VatNetwork`] must be `Send`. Shared server state, such as
+//! the objects returned by [`CapabilityServerSet`], lives in the types of [`shared`].
+//!
+//! This module has the same API as the crate root, and is the same code compiled on top of
+//! `capnp::sync` rather than `capnp`. The generated code for `rpc.capnp` and `rpc-twoparty.capnp`,
+//! which involves no capabilities, is shared by both.
+
+use ::capnp::sync as capnp;
+
+pub use crate::{pry, rpc_capnp, rpc_twoparty_capnp};
+
+#[path = "broken.rs"]
+mod broken;
+#[path = "flow_control.rs"]
+pub mod flow_control;
+#[path = "local.rs"]
+mod local;
+#[path = "queued.rs"]
+mod queued;
+#[path = "reconnect.rs"]
+mod reconnect;
+#[path = "rpc.rs"]
+mod rpc;
+#[path = "sender_queue.rs"]
+mod sender_queue;
+pub mod shared;
+#[path = "split.rs"]
+mod split;
+#[path = "system.rs"]
+mod system;
+#[path = "task_set.rs"]
+mod task_set;
+#[path = "twoparty.rs"]
+pub mod twoparty;
+
+pub use self::system::*;
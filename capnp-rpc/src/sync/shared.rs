@@ -0,0 +1,227 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The shared-ownership and interior-mutability types that the `sync` version of the RPC system
+//! is built on. They have the same interface as the single-threaded ones from `std::rc` and
+//! `std::cell`: `Rc` is `Arc`, and `RefCell` and `Cell` are backed by a `Mutex`. They appear in the
+//! public API as the server objects returned by
+//! [`CapabilityServerSet`](crate::sync::CapabilityServerSet).
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+pub use std::sync::{Arc as Rc, Weak};
+
+pub(crate) type BoxFuture<T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send>>;
+
+/// Identifies the current thread. Never zero.
+fn thread_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+    thread_local! {
+        static ID: usize = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
+struct BorrowState {
+    /// The thread holding the borrows, or zero if there are none.
+    owner: usize,
+    /// The number of shared borrows, or -1 for a mutable borrow.
+    borrows: isize,
+}
+
+/// A mutable memory location that can be shared between threads.
+///
+/// While any borrow is held, the cell belongs to the borrowing thread, and other threads
+/// that try to borrow it wait until all of that thread's borrows have ended. Within the
+/// owning thread, borrows follow the rules of `std::cell::RefCell`, and breaking them panics
+/// rather than deadlocking.
+pub struct RefCell<T: ?Sized> {
+    state: Mutex<BorrowState>,
+    released: Condvar,
+    value: UnsafeCell<T>,
+}
+
+// Access to `value` is guarded by `state`, as in `Mutex<T>`.
+unsafe impl<T: ?Sized + Send> Send for RefCell<T> {}
+unsafe impl<T: ?Sized + Send> Sync for RefCell<T> {}
+
+impl<T> RefCell<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(BorrowState {
+                owner: 0,
+                borrows: 0,
+            }),
+            released: Condvar::new(),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> RefCell<T> {
+    fn lock_state(&self) -> MutexGuard<'_, BorrowState> {
+        // The state is only updated by code that cannot panic.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Waits until no other thread holds a borrow, then updates the borrow count.
+    fn acquire(&self, mutable: bool) {
+        let id = thread_id();
+        let mut state = self.lock_state();
+        while state.owner != 0 && state.owner != id {
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if mutable {
+            if state.borrows != 0 {
+                panic!("already borrowed: BorrowMutError");
+            }
+            state.borrows = -1;
+        } else {
+            if state.borrows < 0 {
+                panic!("already mutably borrowed: BorrowError");
+            }
+            state.borrows += 1;
+        }
+        state.owner = id;
+    }
+
+    fn release(&self) {
+        let mut state = self.lock_state();
+        if state.borrows > 0 {
+            state.borrows -= 1;
+        } else {
+            state.borrows = 0;
+        }
+        if state.borrows == 0 {
+            state.owner = 0;
+            self.released.notify_all();
+        }
+    }
+
+    /// Immutably borrows the value, waiting for any borrows held by other threads to end.
+    ///
+    /// Panics if the current thread holds a mutable borrow.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.acquire(false);
+        Ref {
+            cell: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Mutably borrows the value, waiting for any borrows held by other threads to end.
+    ///
+    /// Panics if the current thread holds any borrow.
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.acquire(true);
+        RefMut {
+            cell: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns a raw pointer to the underlying value.
+    pub fn as_ptr(&self) -> *mut T {
+        self.value.get()
+    }
+}
+
+impl<T: Default> Default for RefCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// A shared borrow of the value in a [`RefCell`].
+pub struct Ref<'a, T: ?Sized> {
+    cell: &'a RefCell<T>,
+    // Borrows are tied to the thread that made them.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T: ?Sized> Deref for Ref<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for Ref<'_, T> {
+    fn drop(&mut self) {
+        self.cell.release();
+    }
+}
+
+/// A mutable borrow of the value in a [`RefCell`].
+pub struct RefMut<'a, T: ?Sized> {
+    cell: &'a RefCell<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T: ?Sized> Deref for RefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RefMut<'_, T> {
+    fn drop(&mut self) {
+        self.cell.release();
+    }
+}
+
+/// A `Copy` value that can be shared between threads.
+pub struct Cell<T: Copy> {
+    value: Mutex<T>,
+}
+
+impl<T: Copy> Cell<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: Mutex::new(value),
+        }
+    }
+
+    pub fn get(&self) -> T {
+        *self.value.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set(&self, value: T) {
+        *self.value.lock().unwrap_or_else(|e| e.into_inner()) = value;
+    }
+}
+
+impl<T: Copy + Default> Default for Cell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
@@ -24,16 +24,16 @@ use futures::{Future, FutureExt, Stream};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use super::capnp::capability::MaybeSend;
+use super::shared::{BoxFuture, Rc, RefCell};
 
 enum EnqueuedTask<E> {
-    Task(Pin<Box<dyn Future<Output = Result<(), E>>>>),
+    Task(BoxFuture<Result<(), E>>),
     Terminate(Result<(), E>),
 }
 
 enum TaskInProgress<E> {
-    Task(Pin<Box<dyn Future<Output = ()>>>),
+    Task(BoxFuture<()>),
     Terminate(Option<Result<(), E>>),
 }
 
@@ -104,7 +104,7 @@ where
 {
     pub fn add<F>(&mut self, f: F)
     where
-        F: Future<Output = Result<(), E>> + 'static,
+        F: Future<Output = Result<(), E>> + MaybeSend + 'static,
     {
         let _ = self.sender.unbounded_send(EnqueuedTask::Task(Box::pin(f)));
     }
@@ -114,7 +114,7 @@ where
     }
 }
 
-pub trait TaskReaper<E>
+pub trait TaskReaper<E>: MaybeSend
 where
     E: 'static,
 {
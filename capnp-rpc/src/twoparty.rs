@@ -21,51 +21,51 @@
 
 //! An implementation of `VatNetwork` for the common case of a client-server connection.
 
-use capnp::capability::Promise;
-use capnp::message::ReaderOptions;
+use super::capnp;
+use super::capnp::capability::{MaybeSend, Promise};
+use super::capnp::message::ReaderOptions;
 use futures::channel::oneshot;
 use futures::{AsyncRead, AsyncWrite, FutureExt, TryFutureExt};
 
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use super::shared::{Rc, RefCell, Weak};
 
 pub type VatId = crate::rpc_twoparty_capnp::Side;
 
 struct IncomingMessage {
-    message: ::capnp::message::Reader<capnp::serialize::OwnedSegments>,
+    message: capnp::message::Reader<capnp::serialize::OwnedSegments>,
 }
 
 impl IncomingMessage {
-    pub fn new(message: ::capnp::message::Reader<capnp::serialize::OwnedSegments>) -> Self {
+    pub fn new(message: capnp::message::Reader<capnp::serialize::OwnedSegments>) -> Self {
         Self { message }
     }
 }
 
-impl crate::IncomingMessage for IncomingMessage {
-    fn get_body(&self) -> ::capnp::Result<::capnp::any_pointer::Reader> {
+impl super::IncomingMessage for IncomingMessage {
+    fn get_body(&self) -> capnp::Result<capnp::any_pointer::Reader> {
         self.message.get_root()
     }
 }
 
 struct OutgoingMessage {
-    message: ::capnp::message::Builder<::capnp::message::HeapAllocator>,
-    sender: ::capnp_futures::Sender<Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>>,
+    message: capnp::message::Builder<capnp::message::HeapAllocator>,
+    sender: ::capnp_futures::Sender<Rc<capnp::message::Builder<capnp::message::HeapAllocator>>>,
 }
 
-impl crate::OutgoingMessage for OutgoingMessage {
-    fn get_body(&mut self) -> ::capnp::Result<::capnp::any_pointer::Builder> {
+impl super::OutgoingMessage for OutgoingMessage {
+    fn get_body(&mut self) -> capnp::Result<capnp::any_pointer::Builder> {
         self.message.get_root()
     }
 
-    fn get_body_as_reader(&self) -> ::capnp::Result<::capnp::any_pointer::Reader> {
+    fn get_body_as_reader(&self) -> capnp::Result<capnp::any_pointer::Reader> {
         self.message.get_root_as_reader()
     }
 
     fn send(
         self: Box<Self>,
     ) -> (
-        Promise<Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>, ::capnp::Error>,
-        Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>,
+        Promise<Rc<capnp::message::Builder<capnp::message::HeapAllocator>>, capnp::Error>,
+        Rc<capnp::message::Builder<capnp::message::HeapAllocator>>,
     ) {
         let tmp = *self;
         let Self {
@@ -76,7 +76,7 @@ impl crate::OutgoingMessage for OutgoingMessage {
         (Promise::from_future(sender.send(m.clone())), m)
     }
 
-    fn take(self: Box<Self>) -> ::capnp::message::Builder<::capnp::message::HeapAllocator> {
+    fn take(self: Box<Self>) -> capnp::message::Builder<capnp::message::HeapAllocator> {
         self.message
     }
 }
@@ -86,7 +86,7 @@ where
     T: AsyncRead + 'static,
 {
     input_stream: Rc<RefCell<Option<T>>>,
-    sender: ::capnp_futures::Sender<Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>>,
+    sender: ::capnp_futures::Sender<Rc<capnp::message::Builder<capnp::message::HeapAllocator>>>,
     side: crate::rpc_twoparty_capnp::Side,
     receive_options: ReaderOptions,
     on_disconnect_fulfiller: Option<oneshot::Sender<()>>,
@@ -120,9 +120,7 @@ where
 {
     fn new(
         input_stream: T,
-        sender: ::capnp_futures::Sender<
-            Rc<::capnp::message::Builder<::capnp::message::HeapAllocator>>,
-        >,
+        sender: ::capnp_futures::Sender<Rc<capnp::message::Builder<capnp::message::HeapAllocator>>>,
         side: crate::rpc_twoparty_capnp::Side,
         receive_options: ReaderOptions,
         on_disconnect_fulfiller: oneshot::Sender<()>,
@@ -139,9 +137,9 @@ where
     }
 }
 
-impl<T> crate::Connection<crate::rpc_twoparty_capnp::Side> for Connection<T>
+impl<T> super::Connection<crate::rpc_twoparty_capnp::Side> for Connection<T>
 where
-    T: AsyncRead + Unpin,
+    T: AsyncRead + Unpin + MaybeSend,
 {
     fn get_peer_vat_id(&self) -> crate::rpc_twoparty_capnp::Side {
         self.inner.borrow().side
@@ -150,16 +148,16 @@ where
     fn new_outgoing_message(
         &mut self,
         _first_segment_word_size: u32,
-    ) -> Box<dyn crate::OutgoingMessage> {
+    ) -> Box<dyn super::OutgoingMessage> {
         Box::new(OutgoingMessage {
-            message: ::capnp::message::Builder::new_default(),
+            message: capnp::message::Builder::new_default(),
             sender: self.inner.borrow().sender.clone(),
         })
     }
 
     fn receive_incoming_message(
         &mut self,
-    ) -> Promise<Option<Box<dyn crate::IncomingMessage + 'static>>, ::capnp::Error> {
+    ) -> Promise<Option<Box<dyn super::IncomingMessage + 'static>>, capnp::Error> {
         let inner = self.inner.borrow_mut();
 
         let maybe_input_stream = ::std::mem::replace(&mut *inner.input_stream.borrow_mut(), None);
@@ -173,12 +171,12 @@ where
                             .await?;
                     *return_it_here.borrow_mut() = Some(s);
                     Ok(maybe_message.map(|message| {
-                        Box::new(IncomingMessage::new(message)) as Box<dyn crate::IncomingMessage>
+                        Box::new(IncomingMessage::new(message)) as Box<dyn super::IncomingMessage>
                     }))
                 })
             }
             None => {
-                Promise::err(::capnp::Error::failed(
+                Promise::err(capnp::Error::failed(
                     "this should not be possible".to_string(),
                 ))
                 //   unreachable!(),
@@ -186,7 +184,7 @@ where
         }
     }
 
-    fn shutdown(&mut self, result: ::capnp::Result<()>) -> Promise<(), ::capnp::Error> {
+    fn shutdown(&mut self, result: capnp::Result<()>) -> Promise<(), capnp::Error> {
         Promise::from_future(self.inner.borrow_mut().sender.terminate(result))
     }
 }
@@ -194,7 +192,7 @@ where
 /// A vat network with two parties, the client and the server.
 pub struct VatNetwork<T>
 where
-    T: AsyncRead + 'static + Unpin,
+    T: AsyncRead + 'static + Unpin + MaybeSend,
 {
     // connection handle that we will return on accept()
     connection: Option<Connection<T>>,
@@ -202,13 +200,13 @@ where
     // connection handle that we will return on connect()
     weak_connection_inner: Weak<RefCell<ConnectionInner<T>>>,
 
-    execution_driver: futures::future::Shared<Promise<(), ::capnp::Error>>,
+    execution_driver: futures::future::Shared<Promise<(), capnp::Error>>,
     side: crate::rpc_twoparty_capnp::Side,
 }
 
 impl<T> VatNetwork<T>
 where
-    T: AsyncRead + Unpin,
+    T: AsyncRead + Unpin + MaybeSend,
 {
     /// Creates a new two-party vat network that will receive data on `input_stream` and send data on
     /// `output_stream`.
@@ -226,11 +224,11 @@ where
         receive_options: ReaderOptions,
     ) -> Self
     where
-        U: AsyncWrite + 'static + Unpin,
+        U: AsyncWrite + 'static + Unpin + MaybeSend,
     {
         let (fulfiller, disconnect_promise) = oneshot::channel();
         let disconnect_promise =
-            disconnect_promise.map_err(|_| ::capnp::Error::disconnected("disconnected".into()));
+            disconnect_promise.map_err(|_| capnp::Error::disconnected("disconnected".into()));
 
         let (execution_driver, sender) = {
             let (tx, write_queue) = ::capnp_futures::write_queue(output_stream);
@@ -259,11 +257,11 @@ where
     }
 }
 
-impl<T> crate::VatNetwork<VatId> for VatNetwork<T>
+impl<T> super::VatNetwork<VatId> for VatNetwork<T>
 where
-    T: AsyncRead + Unpin,
+    T: AsyncRead + Unpin + MaybeSend,
 {
-    fn connect(&mut self, host_id: VatId) -> Option<Box<dyn crate::Connection<VatId>>> {
+    fn connect(&mut self, host_id: VatId) -> Option<Box<dyn super::Connection<VatId>>> {
         if host_id == self.side {
             None
         } else {
@@ -278,15 +276,15 @@ where
         }
     }
 
-    fn accept(&mut self) -> Promise<Box<dyn crate::Connection<VatId>>, ::capnp::Error> {
+    fn accept(&mut self) -> Promise<Box<dyn super::Connection<VatId>>, capnp::Error> {
         let connection = ::std::mem::replace(&mut self.connection, None);
         match connection {
-            Some(c) => Promise::ok(Box::new(c) as Box<dyn crate::Connection<VatId>>),
+            Some(c) => Promise::ok(Box::new(c) as Box<dyn super::Connection<VatId>>),
             None => Promise::from_future(::futures::future::pending()),
         }
     }
 
-    fn drive_until_shutdown(&mut self) -> Promise<(), ::capnp::Error> {
+    fn drive_until_shutdown(&mut self) -> Promise<(), capnp::Error> {
         Promise::from_future(self.execution_driver.clone())
     }
 }
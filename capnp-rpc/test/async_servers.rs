@@ -0,0 +1,116 @@
+//! Servers generated with `CompilerCommand::async_servers()`, whose methods take `self: Rc<Self>`.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use capnp::capability::Promise;
+use capnp::Error;
+use capnp_rpc::pry;
+use futures::channel::oneshot;
+
+pub mod test_capnp {
+    include!(concat!(env!("OUT_DIR"), "/async_servers/test_capnp.rs"));
+}
+
+use self::test_capnp::{test_extends, test_interface};
+
+/// Holds `foo()` until `bar()` is called.
+struct GatedServer {
+    gate: RefCell<Option<oneshot::Receiver<()>>>,
+    opener: RefCell<Option<oneshot::Sender<()>>>,
+    call_count: Cell<u64>,
+}
+
+impl GatedServer {
+    fn new() -> Self {
+        let (opener, gate) = oneshot::channel();
+        Self {
+            gate: RefCell::new(Some(gate)),
+            opener: RefCell::new(Some(opener)),
+            call_count: Cell::new(0),
+        }
+    }
+
+    fn increment_call_count(&self) {
+        self.call_count.set(self.call_count.get() + 1);
+    }
+}
+
+impl test_interface::Server for GatedServer {
+    fn foo(
+        self: Rc<Self>,
+        params: test_interface::FooParams,
+        mut results: test_interface::FooResults,
+    ) -> Promise<(), Error> {
+        let i = pry!(params.get()).get_i();
+        Promise::from_future(async move {
+            let gate = self.gate.borrow_mut().take();
+            if let Some(gate) = gate {
+                gate.await
+                    .map_err(|_| Error::failed("gate was dropped".to_string()))?;
+            }
+            self.increment_call_count();
+            results
+                .get()
+                .set_x(&format!("{i}:{}", self.call_count.get())[..]);
+            Ok(())
+        })
+    }
+
+    fn bar(
+        self: Rc<Self>,
+        _params: test_interface::BarParams,
+        _results: test_interface::BarResults,
+    ) -> Promise<(), Error> {
+        self.increment_call_count();
+        if let Some(opener) = self.opener.borrow_mut().take() {
+            let _ = opener.send(());
+        }
+        Promise::ok(())
+    }
+}
+
+impl test_extends::Server for GatedServer {
+    fn qux(
+        self: Rc<Self>,
+        _params: test_extends::QuxParams,
+        _results: test_extends::QuxResults,
+    ) -> Promise<(), Error> {
+        self.increment_call_count();
+        Promise::ok(())
+    }
+}
+
+#[test]
+fn calls_run_concurrently() {
+    let client: test_extends::Client = capnp_rpc::new_client(GatedServer::new());
+    let base = test_interface::Client {
+        client: client.clone().client,
+    };
+
+    let mut request = base.foo_request();
+    request.get().set_i(7);
+    let foo = request.send().promise;
+    let qux = client.qux_request().send().promise;
+    let bar = base.bar_request().send().promise;
+
+    let (foo, qux, bar) = futures::executor::block_on(async { futures::join!(foo, qux, bar) });
+    qux.unwrap();
+    bar.unwrap();
+    // `qux()` and `bar()` both completed while `foo()` was waiting.
+    assert_eq!(foo.unwrap().get().unwrap().get_x().unwrap(), "7:3");
+}
+
+#[test]
+fn local_server_is_mutable_when_idle() {
+    let mut set: capnp_rpc::CapabilityServerSet<GatedServer, test_interface::Client> =
+        capnp_rpc::CapabilityServerSet::new();
+    let client = set.new_client(GatedServer::new());
+    futures::executor::block_on(client.bar_request().send().promise).unwrap();
+
+    let server = futures::executor::block_on(set.get_local_server(&client)).unwrap();
+    let mut dispatch = server.borrow_mut();
+    // Goes through `DerefMut`, which needs the only reference to the server.
+    dispatch.call_count = Cell::new(10);
+    assert_eq!(dispatch.call_count.get(), 10);
+}
@@ -1,6 +1,26 @@
 fn main() {
     ::capnpc::CompilerCommand::new()
+        .builtin_compiler()
         .file("test.capnp")
         .run()
         .unwrap();
+
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    ::capnpc::CompilerCommand::new()
+        .builtin_compiler()
+        .file("test.capnp")
+        .output_path(out_dir.join("async_servers"))
+        .default_parent_module(vec!["async_servers".into()])
+        .async_servers()
+        .run()
+        .unwrap();
+
+    ::capnpc::CompilerCommand::new()
+        .builtin_compiler()
+        .file("test.capnp")
+        .output_path(out_dir.join("sync"))
+        .default_parent_module(vec!["sync".into()])
+        .sync_rpc()
+        .run()
+        .unwrap();
 }
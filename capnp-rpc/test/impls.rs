@@ -19,19 +19,21 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use crate::test_capnp::{
+use super::test_capnp::{
     bootstrap, test_call_order, test_capability_server_set, test_extends, test_handle,
-    test_interface, test_more_stuff, test_pipeline,
+    test_interface, test_more_stuff, test_pipeline, test_streaming, test_tail_callee,
+    test_tail_caller,
 };
 
-use capnp::capability::Promise;
-use capnp::Error;
-use capnp_rpc::pry;
+use super::capnp::capability::Promise;
+use super::capnp::Error;
+use super::capnp_rpc::pry;
+use super::{capnp, capnp_rpc};
 
+use futures::channel::oneshot;
 use futures::{FutureExt, TryFutureExt};
 
-use std::cell::{Cell, RefCell};
-use std::rc::Rc;
+use super::{Cell, Rc, RefCell};
 
 pub struct Bootstrap;
 
@@ -113,6 +115,14 @@ impl bootstrap::Server for Bootstrap {
             .set_cap(capnp_rpc::new_client(TestCapabilityServerSet::new()));
         Promise::ok(())
     }
+    fn test_tail_caller(
+        &mut self,
+        _params: bootstrap::TestTailCallerParams,
+        mut results: bootstrap::TestTailCallerResults,
+    ) -> Promise<(), Error> {
+        results.get().set_cap(capnp_rpc::new_client(TestTailCaller));
+        Promise::ok(())
+    }
 }
 
 #[derive(Default)]
@@ -168,12 +178,12 @@ impl test_interface::Server for TestInterface {
         _results: test_interface::BazResults,
     ) -> Promise<(), Error> {
         self.increment_call_count();
-        crate::test_util::CheckTestMessage::check_test_message(pry!(pry!(params.get()).get_s()));
+        super::test_util::CheckTestMessage::check_test_message(pry!(pry!(params.get()).get_s()));
         Promise::ok(())
     }
 }
 
-struct TestExtends;
+pub struct TestExtends;
 
 impl test_interface::Server for TestExtends {
     fn foo(
@@ -234,7 +244,7 @@ impl test_extends::Server for TestExtends {
         _params: test_extends::GraultParams,
         mut results: test_extends::GraultResults,
     ) -> Promise<(), Error> {
-        crate::test_util::init_test_message(results.get());
+        super::test_util::init_test_message(results.get());
         Promise::ok(())
     }
 }
@@ -304,6 +314,52 @@ impl test_call_order::Server for TestCallOrder {
     }
 }
 
+#[derive(Default)]
+pub struct TestTailCallee {
+    call_count: Rc<Cell<u64>>,
+}
+
+impl TestTailCallee {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get_call_count(&self) -> Rc<Cell<u64>> {
+        self.call_count.clone()
+    }
+}
+
+impl test_tail_callee::Server for TestTailCallee {
+    fn foo(
+        &mut self,
+        params: test_tail_callee::FooParams,
+        mut results: test_tail_callee::FooResults,
+    ) -> Promise<(), Error> {
+        self.call_count.set(self.call_count.get() + 1);
+        let params = pry!(params.get());
+        let mut results = results.get();
+        results.set_i(params.get_i() as u32);
+        results.set_t(pry!(params.get_t()));
+        results.set_c(capnp_rpc::new_client(TestCallOrder::new()));
+        Promise::ok(())
+    }
+}
+
+pub struct TestTailCaller;
+
+impl test_tail_caller::Server for TestTailCaller {
+    fn foo(
+        &mut self,
+        params: test_tail_caller::FooParams,
+        results: test_tail_caller::FooResults,
+    ) -> Promise<(), Error> {
+        let params = pry!(params.get());
+        let mut request = pry!(params.get_callee()).foo_request();
+        request.get().set_i(params.get_i());
+        request.get().set_t("from TestTailCaller");
+        results.tail_call(request)
+    }
+}
+
 #[derive(Default)]
 pub struct TestMoreStuff {
     call_count: u32,
@@ -656,3 +712,74 @@ impl test_capability_server_set::Server for TestCapabilityServerSet {
         })
     }
 }
+
+#[derive(Default)]
+pub struct StreamingState {
+    pub total_i: u32,
+    pub total_j: u32,
+
+    /// While set, stream calls do not return until they are released.
+    pub hold: bool,
+    pub held: Vec<oneshot::Sender<()>>,
+}
+
+impl StreamingState {
+    pub fn release(&mut self) {
+        self.hold = false;
+        for sender in self.held.drain(..) {
+            let _ = sender.send(());
+        }
+    }
+
+    fn returned(&mut self) -> Promise<(), Error> {
+        if !self.hold {
+            return Promise::ok(());
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.held.push(sender);
+        Promise::from_future(receiver.map_err(super::test::canceled_to_error))
+    }
+}
+
+#[derive(Default)]
+pub struct TestStreaming {
+    state: Rc<RefCell<StreamingState>>,
+}
+
+impl TestStreaming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get_state(&self) -> Rc<RefCell<StreamingState>> {
+        self.state.clone()
+    }
+}
+
+impl test_streaming::Server for TestStreaming {
+    fn do_stream_i(&mut self, params: test_streaming::DoStreamIParams) -> Promise<(), Error> {
+        let i = pry!(params.get()).get_i();
+        if i == 0 {
+            return Promise::err(Error::failed("i must not be zero".to_string()));
+        }
+        let mut state = self.state.borrow_mut();
+        state.total_i += i;
+        state.returned()
+    }
+
+    fn do_stream_j(&mut self, params: test_streaming::DoStreamJParams) -> Promise<(), Error> {
+        let mut state = self.state.borrow_mut();
+        state.total_j += pry!(params.get()).get_j();
+        state.returned()
+    }
+
+    fn finish_stream(
+        &mut self,
+        _params: test_streaming::FinishStreamParams,
+        mut results: test_streaming::FinishStreamResults,
+    ) -> Promise<(), Error> {
+        let state = self.state.borrow();
+        results.get().set_total_i(state.total_i);
+        results.get().set_total_j(state.total_j);
+        Promise::ok(())
+    }
+}
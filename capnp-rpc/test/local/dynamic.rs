@@ -0,0 +1,191 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The dynamic API only works with the single-threaded RPC system, so these tests are not part of
+//! the suite that also runs against `capnp_rpc::sync`.
+
+use capnp::capability::{FromClientHook, Promise};
+use capnp::Error;
+use capnp_rpc::pry;
+
+use super::impls;
+use super::test::rpc_top_level;
+use super::test_capnp::{self, test_interface};
+
+/// Implements `TestInterface` through the dynamic API.
+pub struct DynamicTestInterface {
+    schema: capnp::schema::InterfaceSchema,
+}
+
+impl DynamicTestInterface {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for DynamicTestInterface {
+    fn default() -> Self {
+        let capnp::introspect::TypeVariant::Capability(schema) =
+            <test_interface::Client as capnp::introspect::Introspect>::introspect().which()
+        else {
+            panic!("expected an interface");
+        };
+        Self {
+            schema: schema.into(),
+        }
+    }
+}
+
+impl capnp_rpc::DynamicServer for DynamicTestInterface {
+    fn get_schema(&self) -> capnp::schema::InterfaceSchema {
+        self.schema
+    }
+
+    fn call(
+        &mut self,
+        method: capnp::schema::Method,
+        params: capnp::dynamic_capability::Params,
+        mut results: capnp::dynamic_capability::Results,
+    ) -> Promise<(), Error> {
+        match pry!(method.get_proto().get_name()) {
+            "foo" => {
+                let params = pry!(params.get());
+                let i: u32 = pry!(params.get_named("i")).downcast();
+                if i != 123 {
+                    return Promise::err(Error::failed("expected i to equal 123".to_string()));
+                }
+                let j: bool = pry!(params.get_named("j")).downcast();
+                if !j {
+                    return Promise::err(Error::failed("expected j to be true".to_string()));
+                }
+                pry!(results.get().set_named("x", "foo".into()));
+                Promise::ok(())
+            }
+            _ => Promise::err(Error::unimplemented("not implemented".to_string())),
+        }
+    }
+}
+
+fn dynamic_client<T>(client: T) -> capnp::dynamic_capability::Client
+where
+    T: FromClientHook + capnp::introspect::Introspect,
+{
+    let capnp::introspect::TypeVariant::Capability(schema) = T::introspect().which() else {
+        panic!("expected an interface");
+    };
+    capnp::dynamic_capability::Client::from_hook(client.into_client_hook(), schema.into())
+}
+
+#[test]
+fn dynamic_rpc_calls() {
+    rpc_top_level(|_spawner, client| async move {
+        let response = client.test_interface_request().send().promise.await?;
+        let client = dynamic_client(response.get()?.get_cap()?);
+
+        let mut request = client.new_call_named("foo")?;
+        request.get().set_named("i", 123u32.into())?;
+        request.get().set_named("j", true.into())?;
+        let response = request.send().promise.await?;
+        let x: capnp::text::Reader = response.get()?.get_named("x")?.downcast();
+        assert_eq!(x, "foo");
+
+        assert!(client.new_call_named("bar")?.send().promise.await.is_err());
+        assert!(client.new_call_named("nonexistent").is_err());
+        Ok(())
+    });
+}
+
+#[test]
+fn dynamic_local_client_calls() {
+    let client: test_capnp::test_extends::Client = capnp_rpc::new_client(impls::TestExtends);
+    let client = dynamic_client(client);
+
+    // `foo` is inherited from `TestInterface`.
+    let mut request = client.new_call_named("foo").unwrap();
+    request.get().set_named("i", 321u32.into()).unwrap();
+    request.get().set_named("j", false.into()).unwrap();
+    let response = futures::executor::block_on(request.send().promise).unwrap();
+    let x: capnp::text::Reader = response.get().unwrap().get_named("x").unwrap().downcast();
+    assert_eq!(x, "bar");
+
+    // Pass the dynamic client as a capability param of a generated interface.
+    let more_stuff: test_capnp::test_more_stuff::Client =
+        capnp_rpc::new_client(impls::TestMoreStuff::new());
+    let more_stuff = dynamic_client(more_stuff);
+    let server: test_capnp::test_interface::Client =
+        capnp_rpc::new_client(impls::TestInterface::new());
+    let mut request = more_stuff.new_call_named("callFoo").unwrap();
+    let server = dynamic_client(server);
+    request.get().set_named("cap", (&server).into()).unwrap();
+    let cap: capnp::dynamic_capability::Reader = request.get().get_named("cap").unwrap().downcast();
+    assert!(cap.get_client().unwrap() == server);
+    let response = futures::executor::block_on(request.send().promise).unwrap();
+    let s: capnp::text::Reader = response.get().unwrap().get_named("s").unwrap().downcast();
+    assert_eq!(s, "bar");
+
+    // A `TestExtends` can be downcast to its superclass.
+    let _: test_capnp::test_interface::Client = client.downcast();
+}
+
+#[test]
+fn dynamic_server() {
+    rpc_top_level(|_spawner, client| async move {
+        let response = client.test_more_stuff_request().send().promise.await?;
+        let more_stuff = response.get()?.get_cap()?;
+
+        let server: capnp::capability::Client = capnp_rpc::new_client(DynamicTestInterface::new());
+        let server: test_capnp::test_interface::Client = server.cast_to();
+
+        // Called directly.
+        let mut request = server.foo_request();
+        request.get().set_i(123);
+        request.get().set_j(true);
+        let response = request.send().promise.await?;
+        assert_eq!(response.get()?.get_x()?, "foo");
+        assert!(server.bar_request().send().promise.await.is_err());
+
+        // Called by the remote side.
+        let mut request = more_stuff.call_foo_request();
+        request.get().set_cap(server);
+        let response = request.send().promise.await?;
+        assert_eq!(response.get()?.get_s()?, "bar");
+        Ok(())
+    });
+}
+
+#[test]
+fn dynamic_server_set() {
+    use capnp_rpc::CapabilityServerSet;
+    use DynamicTestInterface;
+    let mut set: CapabilityServerSet<DynamicTestInterface, capnp::capability::Client> =
+        CapabilityServerSet::new();
+    let client = set.new_client(DynamicTestInterface::new());
+    let typed: test_capnp::test_interface::Client =
+        capnp::capability::Client::new(client.hook.add_ref()).cast_to();
+
+    let mut request = typed.foo_request();
+    request.get().set_i(123);
+    request.get().set_j(true);
+    let response = futures::executor::block_on(request.send().promise).unwrap();
+    assert_eq!(response.get().unwrap().get_x().unwrap(), "foo");
+
+    assert!(set.get_local_server_of_resolved(&client).is_some());
+}
@@ -1,10 +1,8 @@
-use std::cell::RefCell;
 use std::future::Future;
-use std::rc::Rc;
 
-use capnp::capability::{Promise, Response};
-use capnp::Error;
-use capnp_rpc::{
+use super::capnp::capability::{Promise, Response};
+use super::capnp::Error;
+use super::capnp_rpc::{
     auto_reconnect, lazy_auto_reconnect, new_client, new_promise_client, pry, rpc_twoparty_capnp,
     twoparty, RpcSystem,
 };
@@ -14,8 +12,9 @@ use futures::future::Shared;
 use futures::task::LocalSpawnExt;
 use futures::FutureExt;
 
-use crate::spawn;
-use crate::test_capnp::{self, test_interface};
+use super::test::spawn;
+use super::test_capnp::{self, test_interface};
+use super::{capnp, capnp_rpc, Rc, RefCell};
 
 struct TestInterfaceInner {
     error: Option<Error>,
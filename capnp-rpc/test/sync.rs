@@ -0,0 +1,41 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The RPC suite, run against `capnp_rpc::sync`.
+
+use ::capnp::sync as capnp;
+use ::capnp_rpc::sync as capnp_rpc;
+
+pub use capnp_rpc::shared::{Cell, Rc, RefCell};
+
+pub mod test_capnp {
+    include!(concat!(env!("OUT_DIR"), "/sync/test_capnp.rs"));
+}
+
+#[path = "impls.rs"]
+pub mod impls;
+#[path = "reconnect_test.rs"]
+pub mod reconnect_test;
+#[path = "test.rs"]
+mod test;
+#[path = "test_util.rs"]
+pub mod test_util;
+mod threads;
@@ -0,0 +1,111 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Calls to capabilities from threads other than the ones driving them.
+
+use futures::executor::block_on;
+use futures::FutureExt;
+
+use super::capnp::capability::{self, Promise};
+use super::capnp::Error;
+use super::capnp_rpc::{self, rpc_twoparty_capnp, twoparty, RpcSystem};
+use super::impls;
+use super::test_capnp::test_call_order;
+
+fn assert_send<T: Send>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+/// Makes `count` calls to `client` from each of four threads.
+fn call_from_threads(client: &test_call_order::Client, count: u32) {
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            std::thread::spawn(move || {
+                for _ in 0..count {
+                    let request = client.get_call_sequence_request();
+                    block_on(request.send().promise).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+fn next_call_sequence(client: &test_call_order::Client) -> u32 {
+    let response = block_on(client.get_call_sequence_request().send().promise).unwrap();
+    response.get().unwrap().get_n()
+}
+
+#[test]
+fn rpc_types_are_thread_safe() {
+    assert_send_sync::<capability::Client>();
+    assert_send_sync::<test_call_order::Client>();
+    assert_send::<RpcSystem<rpc_twoparty_capnp::Side>>();
+    assert_send::<Promise<(), Error>>();
+}
+
+#[test]
+fn local_calls_from_many_threads() {
+    let client: test_call_order::Client = capnp_rpc::new_client(impls::TestCallOrder::new());
+    call_from_threads(&client, 25);
+    assert_eq!(next_call_sequence(&client), 100);
+}
+
+#[test]
+fn remote_calls_from_many_threads() {
+    let (client_writer, server_reader) = async_byte_channel::channel();
+    let (server_writer, client_reader) = async_byte_channel::channel();
+
+    let server_network = twoparty::VatNetwork::new(
+        server_reader,
+        server_writer,
+        rpc_twoparty_capnp::Side::Server,
+        Default::default(),
+    );
+    let bootstrap: test_call_order::Client = capnp_rpc::new_client(impls::TestCallOrder::new());
+    let server_system = RpcSystem::new(Box::new(server_network), Some(bootstrap.client));
+
+    let client_network = twoparty::VatNetwork::new(
+        client_reader,
+        client_writer,
+        rpc_twoparty_capnp::Side::Client,
+        Default::default(),
+    );
+    let mut client_system = RpcSystem::new(Box::new(client_network), None);
+    let client: test_call_order::Client = client_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+
+    // Each vat is driven by its own thread, and the calls are made from still other threads.
+    let server_thread = std::thread::spawn(move || block_on(server_system));
+    let (stop, stopped) = futures::channel::oneshot::channel::<()>();
+    let client_thread = std::thread::spawn(move || {
+        block_on(futures::future::select(client_system, stopped.map(drop)));
+    });
+
+    call_from_threads(&client, 25);
+    assert_eq!(next_call_sequence(&client), 100);
+
+    drop(client);
+    let _ = stop.send(());
+    client_thread.join().unwrap();
+    server_thread.join().unwrap().unwrap();
+}
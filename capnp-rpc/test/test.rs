@@ -19,25 +19,17 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-#![cfg(test)]
 #![allow(clippy::bool_assert_comparison)]
 
-use capnp::capability::{FromClientHook, Promise};
-use capnp::Error;
-use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use super::capnp::capability::{FromClientHook, Promise};
+use super::capnp::Error;
+use super::capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use super::{capnp, capnp_rpc, impls, test_capnp, test_util};
 
 use futures::channel::oneshot;
 use futures::{Future, FutureExt, TryFutureExt};
 
-pub mod test_capnp {
-    include!(concat!(env!("OUT_DIR"), "/test_capnp.rs"));
-}
-
-pub mod impls;
-pub mod reconnect_test;
-pub mod test_util;
-
-fn canceled_to_error(_e: futures::channel::oneshot::Canceled) -> Error {
+pub(super) fn canceled_to_error(_e: futures::channel::oneshot::Canceled) -> Error {
     Error::failed("oneshot was canceled".to_string())
 }
 
@@ -83,7 +75,7 @@ fn disconnector_setup() -> (
     (client_rpc_system, server_rpc_system)
 }
 
-fn spawn<F>(spawner: &mut futures::executor::LocalSpawner, task: F)
+pub(super) fn spawn<F>(spawner: &mut futures::executor::LocalSpawner, task: F)
 where
     F: Future<Output = Result<(), Error>> + 'static,
 {
@@ -109,7 +101,7 @@ fn drop_import_client_after_disconnect() {
     spawn(&mut spawner, client_rpc_system);
 
     let (tx, rx) = oneshot::channel::<()>();
-    let rx = rx.map_err(crate::canceled_to_error);
+    let rx = rx.map_err(canceled_to_error);
     spawn(
         &mut spawner,
         futures::future::try_join(rx, server_rpc_system).map(|_| Ok(())),
@@ -125,14 +117,14 @@ fn drop_import_client_after_disconnect() {
         drop(tx);
 
         match client.test_interface_request().send().promise.await {
-            Err(ref e) if e.kind == ::capnp::ErrorKind::Disconnected => (),
+            Err(ref e) if e.kind == capnp::ErrorKind::Disconnected => (),
             Err(e) => panic!("wrong kind of error: {:?}", e),
             _ => panic!("Should have gotten a 'disconnected' error."),
         }
 
         // At one point, attempting to call again would cause a panic.
         match client.test_interface_request().send().promise.await {
-            Err(ref e) if e.kind == ::capnp::ErrorKind::Disconnected => (),
+            Err(ref e) if e.kind == capnp::ErrorKind::Disconnected => (),
             _ => panic!("Should have gotten a 'disconnected' error."),
         }
 
@@ -180,13 +172,13 @@ fn disconnector_disconnects() {
 
         //make sure we can't use client any more (because the server is disconnected)
         match client.test_interface_request().send().promise.await {
-            Err(ref e) if e.kind == ::capnp::ErrorKind::Disconnected => (),
+            Err(ref e) if e.kind == capnp::ErrorKind::Disconnected => (),
             _ => panic!("Should have gotten a 'disconnected' error."),
         }
     });
 }
 
-fn rpc_top_level<F, G>(main: F)
+pub(super) fn rpc_top_level<F, G>(main: F)
 where
     F: FnOnce(futures::executor::LocalSpawner, test_capnp::bootstrap::Client) -> G,
     F: Send + 'static,
@@ -259,7 +251,7 @@ fn basic_rpc_calls() {
 
         let mut request2 = client.baz_request();
 
-        crate::test_util::init_test_message(request2.get().get_s()?);
+        test_util::init_test_message(request2.get().get_s()?);
         let promise2 = request2.send();
 
         let response1 = promise1.promise.await?;
@@ -297,7 +289,7 @@ fn basic_pipelining() {
         let pipeline_promise = pipeline_request.send();
 
         let pipeline_request2 = {
-            let extends_client = crate::test_capnp::test_extends::Client {
+            let extends_client = test_capnp::test_extends::Client {
                 client: promise.pipeline.get_out_box().get_cap().client,
             };
             extends_client.grault_request()
@@ -319,7 +311,7 @@ fn basic_pipelining() {
         }
 
         let response2 = pipeline_promise2.promise.await?;
-        crate::test_util::CheckTestMessage::check_test_message(response2.get()?);
+        test_util::CheckTestMessage::check_test_message(response2.get()?);
         assert_eq!(chained_call_count.get(), 1);
         Ok(())
     });
@@ -354,8 +346,8 @@ fn pipelining_return_null() {
 
 #[test]
 fn null_capability() {
-    let mut message = ::capnp::message::Builder::new_default();
-    let root: crate::test_capnp::test_all_types::Builder = message.get_root().unwrap();
+    let mut message = capnp::message::Builder::new_default();
+    let root: test_capnp::test_all_types::Builder = message.get_root().unwrap();
 
     // In capnproto-c++, this would return a BrokenCap. Here, it returns a decode error.
     // Would it be worthwhile to try to match the C++ behavior here? We would need something
@@ -370,7 +362,7 @@ fn release_simple() {
         let client = response.get()?.get_cap()?;
 
         let handle1 = client.get_handle_request().send().promise;
-        let ::capnp::capability::RemotePromise { promise, pipeline } =
+        let capnp::capability::RemotePromise { promise, pipeline } =
             client.get_handle_request().send();
         let handle2 = promise.await?.get()?.get_handle()?;
 
@@ -456,8 +448,8 @@ fn promise_resolve() {
         let mut request2 = client.call_foo_when_resolved_request();
 
         let (paf_fulfiller, paf_promise) = oneshot::channel();
-        let cap: crate::test_capnp::test_interface::Client =
-            ::capnp_rpc::new_promise_client(paf_promise.map_err(canceled_to_error));
+        let cap: test_capnp::test_interface::Client =
+            capnp_rpc::new_promise_client(paf_promise.map_err(canceled_to_error));
         request.get().set_cap(cap.clone());
         request2.get().set_cap(cap);
 
@@ -466,15 +458,14 @@ fn promise_resolve() {
 
         // Make sure getCap() has been called on the server side by sending another call and waiting
         // for it.
-        let client2 = crate::test_capnp::test_call_order::Client {
+        let client2 = test_capnp::test_call_order::Client {
             client: client.clone().client,
         };
         let _response = client2.get_call_sequence_request().send().promise.await?;
 
         let server = impls::TestInterface::new();
-        let _ = paf_fulfiller.send(
-            capnp_rpc::new_client::<crate::test_capnp::test_interface::Client, _>(server).client,
-        );
+        let _ = paf_fulfiller
+            .send(capnp_rpc::new_client::<test_capnp::test_interface::Client, _>(server).client);
 
         let response = promise.await?;
         if response.get()?.get_s()? != "bar" {
@@ -490,8 +481,7 @@ fn promise_resolve() {
 
 #[test]
 fn retain_and_release() {
-    use std::cell::Cell;
-    use std::rc::Rc;
+    use super::{Cell, Rc};
 
     rpc_top_level(|mut spawner, client| async move {
         let (fulfiller, promise) = oneshot::channel::<()>();
@@ -524,7 +514,7 @@ fn retain_and_release() {
                 request.send().promise.await?;
             }
 
-            let client1: crate::test_capnp::test_call_order::Client = client.clone().cast_to();
+            let client1: test_capnp::test_call_order::Client = client.clone().cast_to();
 
             let response = client1.get_call_sequence_request().send().promise.await?;
             if response.get()?.get_n() != 1 {
@@ -599,8 +589,7 @@ fn retain_and_release() {
 
 #[test]
 fn cancel_releases_params() {
-    use std::cell::Cell;
-    use std::rc::Rc;
+    use super::{Cell, Rc};
 
     rpc_top_level(|mut spawner, client| async move {
         let response = client.test_more_stuff_request().send().promise.await?;
@@ -635,7 +624,7 @@ fn cancel_releases_params() {
 
                 // Allow some time to settle.
 
-                let client: crate::test_capnp::test_call_order::Client = client.cast_to();
+                let client: test_capnp::test_call_order::Client = client.cast_to();
                 let response = client.get_call_sequence_request().send().promise.await?;
                 if response.get()?.get_n() != 1 {
                     return Err(Error::failed("N should equal 1.".to_string()));
@@ -666,8 +655,8 @@ fn dont_hold() {
         let client = response.get()?.get_cap()?;
 
         let (fulfiller, promise) = oneshot::channel();
-        let cap: crate::test_capnp::test_interface::Client =
-            ::capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
+        let cap: test_capnp::test_interface::Client =
+            capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
 
         let mut request = client.dont_hold_request();
         request.get().set_cap(cap.clone());
@@ -688,11 +677,10 @@ fn dont_hold() {
 }
 
 fn get_call_sequence(
-    client: &crate::test_capnp::test_call_order::Client,
+    client: &test_capnp::test_call_order::Client,
     expected: u32,
-) -> ::capnp::capability::RemotePromise<
-    crate::test_capnp::test_call_order::get_call_sequence_results::Owned,
-> {
+) -> capnp::capability::RemotePromise<test_capnp::test_call_order::get_call_sequence_results::Owned>
+{
     let mut req = client.get_call_sequence_request();
     req.get().set_expected(expected);
     req.send()
@@ -704,9 +692,9 @@ fn embargo_success() {
         let response = client.test_more_stuff_request().send().promise.await?;
         let client = response.get()?.get_cap()?;
 
-        let server = crate::impls::TestCallOrder::new();
+        let server = impls::TestCallOrder::new();
 
-        let client2: crate::test_capnp::test_call_order::Client = client.clone().cast_to();
+        let client2: test_capnp::test_call_order::Client = client.clone().cast_to();
         let early_call = client2.get_call_sequence_request().send();
         drop(client2);
 
@@ -766,10 +754,10 @@ fn embargo_error() {
         let client = response.get()?.get_cap()?;
 
         let (fulfiller, promise) = oneshot::channel();
-        let cap: crate::test_capnp::test_call_order::Client =
-            ::capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
+        let cap: test_capnp::test_call_order::Client =
+            capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
 
-        let client2: crate::test_capnp::test_call_order::Client = client.clone().cast_to();
+        let client2: test_capnp::test_call_order::Client = client.clone().cast_to();
         let early_call = client2.get_call_sequence_request().send();
         drop(client2);
 
@@ -811,10 +799,10 @@ fn echo_destruction() {
         let client = response.get()?.get_cap()?;
 
         let (fulfiller, promise) = oneshot::channel();
-        let cap: crate::test_capnp::test_call_order::Client =
-            ::capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
+        let cap: test_capnp::test_call_order::Client =
+            capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
 
-        let client2: crate::test_capnp::test_call_order::Client = client.clone().cast_to();
+        let client2: test_capnp::test_call_order::Client = client.clone().cast_to();
         let early_call = client2.get_call_sequence_request().send();
         drop(client2);
 
@@ -839,10 +827,10 @@ fn echo_destruction() {
 
 #[test]
 fn local_client_call_not_immediate() {
-    let server = crate::impls::TestInterface::new();
+    let server = impls::TestInterface::new();
     let call_count = server.get_call_count();
     assert_eq!(call_count.get(), 0);
-    let client: crate::test_capnp::test_interface::Client = capnp_rpc::new_client(server);
+    let client: test_capnp::test_interface::Client = capnp_rpc::new_client(server);
     let mut req = client.foo_request();
     req.get().set_i(123);
     req.get().set_j(true);
@@ -857,9 +845,9 @@ fn local_client_call_not_immediate() {
 
 #[test]
 fn local_client_send_cap() {
-    let server1 = crate::impls::TestMoreStuff::new();
-    let server2 = crate::impls::TestInterface::new();
-    let client1: crate::test_capnp::test_more_stuff::Client = capnp_rpc::new_client(server1);
+    let server1 = impls::TestMoreStuff::new();
+    let server2 = impls::TestInterface::new();
+    let client1: test_capnp::test_more_stuff::Client = capnp_rpc::new_client(server1);
     let client2 = capnp_rpc::new_client(server2);
 
     let mut req = client1.call_foo_request();
@@ -870,8 +858,8 @@ fn local_client_send_cap() {
 
 #[test]
 fn local_client_return_cap() {
-    let server = crate::impls::Bootstrap;
-    let client: crate::test_capnp::bootstrap::Client = capnp_rpc::new_client(server);
+    let server = impls::Bootstrap;
+    let client: test_capnp::bootstrap::Client = capnp_rpc::new_client(server);
     let response =
         futures::executor::block_on(client.test_interface_request().send().promise).unwrap();
     let client1 = response.get().unwrap().get_cap().unwrap();
@@ -889,15 +877,15 @@ fn capability_list() {
         let response = client.test_more_stuff_request().send().promise.await?;
         let client = response.get()?.get_cap()?;
 
-        let server1 = crate::impls::TestInterface::new();
+        let server1 = impls::TestInterface::new();
         let call_count1 = server1.get_call_count();
         assert_eq!(call_count1.get(), 0);
-        let client1: crate::test_capnp::test_interface::Client = capnp_rpc::new_client(server1);
+        let client1: test_capnp::test_interface::Client = capnp_rpc::new_client(server1);
 
-        let server2 = crate::impls::TestInterface::new();
+        let server2 = impls::TestInterface::new();
         let call_count2 = server2.get_call_count();
         assert_eq!(call_count2.get(), 0);
-        let client2: crate::test_capnp::test_interface::Client = capnp_rpc::new_client(server2);
+        let client2: test_capnp::test_interface::Client = capnp_rpc::new_client(server2);
 
         let mut request = client.call_each_capability_request();
         {
@@ -914,9 +902,9 @@ fn capability_list() {
 
 #[test]
 fn capability_server_set() {
-    use crate::impls;
-    use crate::test_capnp::test_interface;
-    use capnp_rpc::CapabilityServerSet;
+    use super::capnp_rpc::CapabilityServerSet;
+    use super::test_capnp::test_interface;
+    use super::Rc;
     let mut set1: CapabilityServerSet<impls::TestInterface, test_interface::Client> =
         CapabilityServerSet::new();
     let mut set2: CapabilityServerSet<impls::TestInterface, test_interface::Client> =
@@ -938,16 +926,16 @@ fn capability_server_set() {
 
     // Getting the local server using the correct set works.
     let own_server1_again = futures::executor::block_on(set1.get_local_server(&client1)).unwrap();
-    assert_eq!(
-        own_server1_again.borrow().get_call_count().as_ptr(),
-        own_server1_counter.as_ptr()
-    );
+    assert!(Rc::ptr_eq(
+        &own_server1_again.borrow().get_call_count(),
+        &own_server1_counter
+    ));
 
     let own_server2_again = futures::executor::block_on(set2.get_local_server(&client2)).unwrap();
-    assert_eq!(
-        own_server2_again.borrow().get_call_count().as_ptr(),
-        own_server2_counter.as_ptr()
-    );
+    assert!(Rc::ptr_eq(
+        &own_server2_again.borrow().get_call_count(),
+        &own_server2_counter
+    ));
 
     // Getting the local server using the wrong set doesn't work.
     assert!(futures::executor::block_on(set1.get_local_server(&client2)).is_none());
@@ -958,21 +946,21 @@ fn capability_server_set() {
     // Also works if the client is a promise.
     let (fulfiller, promise) = oneshot::channel();
     let client_promise: test_interface::Client =
-        ::capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
+        capnp_rpc::new_promise_client(promise.map_err(canceled_to_error));
 
     let client_promise2: test_interface::Client = client_promise.clone();
 
     let (error_fulfiller, error_promise) = oneshot::channel();
     let error_promise: test_interface::Client =
-        ::capnp_rpc::new_promise_client(error_promise.map_err(canceled_to_error));
+        capnp_rpc::new_promise_client(error_promise.map_err(canceled_to_error));
 
     assert!(fulfiller.send(client1.client).is_ok());
     let own_server1_again2 =
         futures::executor::block_on(set1.get_local_server(&client_promise)).unwrap();
-    assert_eq!(
-        own_server1_again2.borrow().get_call_count().as_ptr(),
-        own_server1_counter.as_ptr()
-    );
+    assert!(Rc::ptr_eq(
+        &own_server1_again2.borrow().get_call_count(),
+        &own_server1_counter
+    ));
 
     // Wrong set; returns None.
     assert!(futures::executor::block_on(set2.get_local_server(&client_promise2)).is_none());
@@ -1016,3 +1004,180 @@ fn capability_server_set_rpc() {
         Ok(())
     })
 }
+
+fn do_stream_i(client: &test_capnp::test_streaming::Client, i: u32) -> Promise<(), Error> {
+    let mut request = client.do_stream_i_request();
+    request.get().set_i(i);
+    request.send()
+}
+
+/// Serves `server` over a two-party connection whose ends are both driven by `spawner`.
+fn streaming_setup(
+    spawner: &mut futures::executor::LocalSpawner,
+    server: impls::TestStreaming,
+) -> test_capnp::test_streaming::Client {
+    let (client_writer, server_reader) = async_byte_channel::channel();
+    let (server_writer, client_reader) = async_byte_channel::channel();
+
+    let server_network = Box::new(twoparty::VatNetwork::new(
+        server_reader,
+        server_writer,
+        rpc_twoparty_capnp::Side::Server,
+        Default::default(),
+    ));
+    let bootstrap: test_capnp::test_streaming::Client = capnp_rpc::new_client(server);
+    let server_rpc_system = RpcSystem::new(server_network, Some(bootstrap.client));
+    spawn(spawner, server_rpc_system);
+
+    let client_network = Box::new(twoparty::VatNetwork::new(
+        client_reader,
+        client_writer,
+        rpc_twoparty_capnp::Side::Client,
+        Default::default(),
+    ));
+    let mut client_rpc_system = RpcSystem::new(client_network, None);
+    let client = client_rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+    spawn(spawner, client_rpc_system);
+    client
+}
+
+#[test]
+fn local_streaming_call_blocks_later_calls() {
+    let server = impls::TestStreaming::new();
+    let state = server.get_state();
+    state.borrow_mut().hold = true;
+    let client: test_capnp::test_streaming::Client = capnp_rpc::new_client(server);
+
+    let mut stream = do_stream_i(&client, 1);
+    let mut finish = client.finish_stream_request().send().promise;
+    assert!((&mut stream).now_or_never().is_none());
+    assert!((&mut finish).now_or_never().is_none());
+    assert_eq!(state.borrow().total_i, 1);
+
+    state.borrow_mut().release();
+    futures::executor::block_on(stream).unwrap();
+    let response = futures::executor::block_on(finish).unwrap();
+    assert_eq!(response.get().unwrap().get_total_i(), 1);
+}
+
+#[test]
+fn local_streaming_error_fails_later_calls() {
+    let server = impls::TestStreaming::new();
+    let state = server.get_state();
+    let client: test_capnp::test_streaming::Client = capnp_rpc::new_client(server);
+
+    futures::executor::block_on(async move {
+        do_stream_i(&client, 1).await.unwrap();
+        let failed = do_stream_i(&client, 0);
+        let later = do_stream_i(&client, 2);
+        let finish = client.finish_stream_request().send().promise;
+        assert!(failed.await.is_err());
+        assert!(later.await.is_err());
+        assert!(finish.await.is_err());
+    });
+    assert_eq!(state.borrow().total_i, 1);
+}
+
+#[test]
+fn streaming_calls_are_flow_controlled() {
+    let mut pool = futures::executor::LocalPool::new();
+    let mut spawner = pool.spawner();
+    let server = impls::TestStreaming::new();
+    let state = server.get_state();
+    state.borrow_mut().hold = true;
+    let client = streaming_setup(&mut spawner, server);
+    // Wait for the bootstrap capability to resolve, so that the stream goes to a single target.
+    let resolved = client.finish_stream_request().send().promise;
+    pool.run_until(resolved).unwrap();
+
+    // Send until the window fills up. Only the first call is delivered, and it is held.
+    let mut sent = 0;
+    let mut blocked = loop {
+        let mut stream = do_stream_i(&client, 1);
+        pool.run_until_stalled();
+        sent += 1;
+        if (&mut stream).now_or_never().is_none() {
+            break stream;
+        }
+        assert!(sent < 10000, "streaming calls were never blocked");
+    };
+    assert!(sent > 1);
+    assert_eq!(state.borrow().total_i, 1);
+
+    // Calls that are not part of the stream wait for it too.
+    let finish = client.finish_stream_request().send().promise;
+
+    state.borrow_mut().release();
+    pool.run_until(&mut blocked).unwrap();
+    let response = pool.run_until(finish).unwrap();
+    assert_eq!(response.get().unwrap().get_total_i(), sent);
+}
+
+#[test]
+fn streaming_error_fails_later_calls() {
+    let mut pool = futures::executor::LocalPool::new();
+    let mut spawner = pool.spawner();
+    let server = impls::TestStreaming::new();
+    let state = server.get_state();
+    let client = streaming_setup(&mut spawner, server);
+
+    pool.run_until(do_stream_i(&client, 1)).unwrap();
+
+    // These are sent before the failure is known, so they are within the window and succeed.
+    let failed = do_stream_i(&client, 0);
+    let mut request = client.do_stream_j_request();
+    request.get().set_j(2);
+    let later = request.send();
+    let finish = client.finish_stream_request().send().promise;
+    pool.run_until(failed).unwrap();
+    pool.run_until(later).unwrap();
+
+    // But the server fails them, and the failure is reported to later sends.
+    assert!(pool.run_until(finish).is_err());
+    pool.run_until_stalled();
+    assert!(pool.run_until(do_stream_i(&client, 3)).is_err());
+    assert_eq!(state.borrow().total_i, 1);
+    assert_eq!(state.borrow().total_j, 0);
+}
+
+async fn check_tail_call(caller: test_capnp::test_tail_caller::Client) -> Result<(), Error> {
+    let callee = impls::TestTailCallee::new();
+    let callee_call_count = callee.get_call_count();
+
+    let mut request = caller.foo_request();
+    request.get().set_i(456);
+    request.get().set_callee(capnp_rpc::new_client(callee));
+    let promise = request.send();
+
+    // Pipelined calls should go to the results of the tail call.
+    let dependent_call0 = get_call_sequence(&promise.pipeline.get_c(), 0);
+
+    let response = promise.promise.await?;
+    assert_eq!(response.get()?.get_i(), 456);
+    assert_eq!(response.get()?.get_t()?, "from TestTailCaller");
+
+    let dependent_call1 = get_call_sequence(&promise.pipeline.get_c(), 1);
+    let dependent_call2 = get_call_sequence(&response.get()?.get_c()?, 2);
+
+    assert_eq!(dependent_call0.promise.await?.get()?.get_n(), 0);
+    assert_eq!(dependent_call1.promise.await?.get()?.get_n(), 1);
+    assert_eq!(dependent_call2.promise.await?.get()?.get_n(), 2);
+    assert_eq!(callee_call_count.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn tail_call() {
+    // The caller lives in the server vat and the callee in the client vat, so the caller's
+    // results can be taken straight from its call to the callee.
+    rpc_top_level(|_spawner, client| async move {
+        let response = client.test_tail_caller_request().send().promise.await?;
+        check_tail_call(response.get()?.get_cap()?).await
+    });
+}
+
+#[test]
+fn local_tail_call() {
+    let caller: test_capnp::test_tail_caller::Client = capnp_rpc::new_client(impls::TestTailCaller);
+    futures::executor::block_on(check_tail_call(caller)).unwrap();
+}
@@ -22,14 +22,17 @@
 //! Untyped pointer that can be cast to any struct, list, or capability type.
 
 use alloc::boxed::Box;
-use alloc::vec::Vec;
 
 use crate::capability::FromClientHook;
-use crate::private::capability::{ClientHook, PipelineHook, PipelineOp};
-use crate::private::layout::{PointerBuilder, PointerReader};
+use crate::dynamic_struct;
+use crate::orphan::{Orphan, Orphanage};
+use crate::private::layout::{CapHook, PipelineOp, PointerBuilder, PointerReader};
 use crate::traits::{FromPointerBuilder, FromPointerReader, SetPointerBuilder};
 use crate::Result;
 
+mod pipeline;
+pub use self::pipeline::Pipeline;
+
 #[derive(Copy, Clone)]
 pub struct Owned(());
 
@@ -44,10 +47,6 @@ impl crate::introspect::Introspect for Owned {
     }
 }
 
-impl crate::traits::Pipelined for Owned {
-    type Pipeline = Pipeline;
-}
-
 #[derive(Copy, Clone)]
 pub struct Reader<'a> {
     pub(crate) reader: PointerReader<'a>,
@@ -77,9 +76,42 @@ impl<'a> Reader<'a> {
         Ok(FromClientHook::new(self.reader.get_capability()?))
     }
 
+    /// Interprets the pointer as a struct described by `schema`. Useful for types that
+    /// have no generated code, such as those loaded by a `schema_loader::SchemaLoader`.
+    pub fn get_as_struct(
+        &self,
+        schema: crate::schema::StructSchema,
+    ) -> Result<dynamic_struct::Reader<'a>> {
+        Ok(dynamic_struct::Reader::new(
+            self.reader.get_struct(None)?,
+            schema,
+        ))
+    }
+
+    /// Interprets the pointer as a struct or capability of the type with id `type_id`, whose
+    /// schema is looked up in the global `schema::registry()`. The type must have been
+    /// registered beforehand, as generated types are not registered automatically.
+    #[cfg(feature = "std")]
+    pub fn get_as_dynamic(&self, type_id: u64) -> Result<crate::dynamic_value::Reader<'a>> {
+        use crate::introspect::TypeVariant;
+        match crate::schema::registry().get(type_id).map(|ty| ty.which()) {
+            Some(TypeVariant::Struct(raw)) => Ok(self.get_as_struct(raw.into())?.into()),
+            Some(TypeVariant::Capability(raw)) => {
+                Ok(crate::dynamic_capability::Reader::new(self.reader, raw.into()).into())
+            }
+            Some(_) => Err(crate::Error::failed(format!(
+                "type {type_id:#x} cannot be the target of a pointer"
+            ))),
+            None => Err(crate::Error::failed(format!(
+                "no type with id {type_id:#x} is registered; generated types must be registered \
+                 with `schema::Registry::register_all()` first"
+            ))),
+        }
+    }
+
     //# Used by RPC system to implement pipelining. Applications
     //# generally shouldn't use this directly.
-    pub fn get_pipelined_cap(&self, ops: &[PipelineOp]) -> Result<Box<dyn ClientHook>> {
+    pub fn get_pipelined_cap<H: CapHook + ?Sized>(&self, ops: &[PipelineOp]) -> Result<Box<H>> {
         let mut pointer = self.reader;
 
         for op in ops {
@@ -124,8 +156,32 @@ impl<'a> crate::traits::Imbue<'a> for Reader<'a> {
     }
 }
 
+#[cfg(feature = "sync_rpc")]
+impl<'a> crate::traits::Imbue<'a, crate::private::layout::SyncCapTable> for Reader<'a> {
+    fn imbue(&mut self, cap_table: &'a crate::private::layout::SyncCapTable) {
+        self.reader
+            .imbue(crate::private::layout::CapTableReader::Sync(cap_table));
+    }
+}
+
+/// Compares the pointed-to objects by value, independently of how they are laid out in their
+/// messages. Objects that fail to decode are equal to nothing, themselves included.
+impl<'a> PartialEq for Reader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+    }
+}
+
+impl<'a> Eq for Reader<'a> {}
+
+impl<'a> core::hash::Hash for Reader<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state);
+    }
+}
+
 pub struct Builder<'a> {
-    builder: PointerBuilder<'a>,
+    pub(crate) builder: PointerBuilder<'a>,
 }
 
 impl<'a> Builder<'a> {
@@ -160,12 +216,36 @@ impl<'a> Builder<'a> {
         FromPointerBuilder::init_pointer(self.builder, size)
     }
 
+    /// Like `get_as()`, but for a struct described by `schema` rather than by a generated type.
+    pub fn get_as_struct(
+        self,
+        schema: crate::schema::StructSchema,
+    ) -> Result<dynamic_struct::Builder<'a>> {
+        let size = dynamic_struct::struct_size_from_schema(schema)?;
+        Ok(dynamic_struct::Builder::new(
+            self.builder.get_struct(size, None)?,
+            schema,
+        ))
+    }
+
+    /// Like `init_as()`, but for a struct described by `schema` rather than by a generated type.
+    pub fn init_as_struct(
+        self,
+        schema: crate::schema::StructSchema,
+    ) -> Result<dynamic_struct::Builder<'a>> {
+        let size = dynamic_struct::struct_size_from_schema(schema)?;
+        Ok(dynamic_struct::Builder::new(
+            self.builder.init_struct(size),
+            schema,
+        ))
+    }
+
     pub fn set_as<From: SetPointerBuilder>(&mut self, value: From) -> Result<()> {
         SetPointerBuilder::set_pointer_builder(self.builder.reborrow(), value, false)
     }
 
     // XXX value should be a user client.
-    pub fn set_as_capability(&mut self, value: Box<dyn ClientHook>) {
+    pub fn set_as_capability<H: CapHook + ?Sized>(&mut self, value: Box<H>) {
         self.builder.set_capability(value);
     }
 
@@ -174,6 +254,22 @@ impl<'a> Builder<'a> {
         self.builder.clear()
     }
 
+    /// Detaches the pointed-to object from this pointer, leaving the pointer null.
+    pub fn disown_as<T: crate::traits::Owned>(&mut self) -> Orphan<'a, T> {
+        self.builder.disown().into()
+    }
+
+    /// Makes this pointer point at `orphan`, which must live in the same message.
+    /// Any object previously pointed to is discarded.
+    pub fn adopt<T: crate::traits::Owned>(&mut self, orphan: Orphan<'_, T>) -> Result<()> {
+        self.builder.adopt(orphan.into())
+    }
+
+    /// Gets an `Orphanage` for allocating new orphans in the same message as this pointer.
+    pub fn get_orphanage(&mut self) -> Orphanage<'a> {
+        self.builder.get_orphanage()
+    }
+
     pub fn into_reader(self) -> Reader<'a> {
         Reader {
             reader: self.builder.into_reader(),
@@ -206,48 +302,11 @@ impl<'a> crate::traits::ImbueMut<'a> for Builder<'a> {
     }
 }
 
-pub struct Pipeline {
-    // XXX this should not be public
-    pub hook: Box<dyn PipelineHook>,
-
-    ops: Vec<PipelineOp>,
-}
-
-impl Pipeline {
-    pub fn new(hook: Box<dyn PipelineHook>) -> Self {
-        Self {
-            hook,
-            ops: Vec::new(),
-        }
-    }
-
-    pub fn noop(&self) -> Self {
-        Self {
-            hook: self.hook.add_ref(),
-            ops: self.ops.clone(),
-        }
-    }
-
-    pub fn get_pointer_field(&self, pointer_index: u16) -> Self {
-        let mut new_ops = Vec::with_capacity(self.ops.len() + 1);
-        for op in &self.ops {
-            new_ops.push(*op)
-        }
-        new_ops.push(PipelineOp::GetPointerField(pointer_index));
-        Self {
-            hook: self.hook.add_ref(),
-            ops: new_ops,
-        }
-    }
-
-    pub fn as_cap(&self) -> Box<dyn ClientHook> {
-        self.hook.get_pipelined_cap(&self.ops)
-    }
-}
-
-impl crate::capability::FromTypelessPipeline for Pipeline {
-    fn new(typeless: Pipeline) -> Self {
-        typeless
+#[cfg(feature = "sync_rpc")]
+impl<'a> crate::traits::ImbueMut<'a, crate::private::layout::SyncCapTable> for Builder<'a> {
+    fn imbue_mut(&mut self, cap_table: &'a mut crate::private::layout::SyncCapTable) {
+        self.builder
+            .imbue(crate::private::layout::CapTableBuilder::Sync(cap_table));
     }
 }
 
@@ -287,3 +346,187 @@ fn init_clears_value() {
         assert_eq!(*byte, 0u8);
     }
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn equality_ignores_layout() {
+    use crate::schema_capnp::node;
+    use std::hash::{BuildHasher, RandomState};
+
+    fn init_node(mut node: node::Builder) {
+        node.set_id(0x1234);
+        node.set_display_name("foo.capnp:Foo");
+        let mut nested = node.init_nested_nodes(3);
+        for idx in 0..3 {
+            nested.reborrow().get(idx).set_id(u64::from(idx));
+        }
+    }
+
+    let mut message1 = crate::message::Builder::new_default();
+    init_node(message1.init_root());
+    let reader1: node::Reader = message1.get_root_as_reader().unwrap();
+
+    // Far pointers everywhere.
+    let mut message2 = crate::message::Builder::new(
+        crate::message::HeapAllocator::new()
+            .first_segment_words(1)
+            .allocation_strategy(crate::message::AllocationStrategy::FixedSize),
+    );
+    init_node(message2.init_root());
+    let reader2: node::Reader = message2.get_root_as_reader().unwrap();
+    assert!(message2.get_segments_for_output().len() > 1);
+
+    // Truncated data and pointer sections.
+    let mut message3 = crate::message::Builder::new_default();
+    message3.set_root_canonical(reader1).unwrap();
+    let reader3: node::Reader = message3.get_root_as_reader().unwrap();
+
+    let hasher = RandomState::new();
+    for (message, reader) in [(&message2, reader2), (&message3, reader3)] {
+        assert!(reader1 == reader);
+        assert_eq!(hasher.hash_one(reader1), hasher.hash_one(reader));
+
+        let any1: Reader = message1.get_root_as_reader().unwrap();
+        let any2: Reader = message.get_root_as_reader().unwrap();
+        assert!(any1 == any2);
+        assert_eq!(hasher.hash_one(any1), hasher.hash_one(any2));
+
+        let dynamic1 = crate::dynamic_value::Reader::from(reader1);
+        let dynamic2 = crate::dynamic_value::Reader::from(reader);
+        assert!(dynamic1 == dynamic2);
+        assert_eq!(hasher.hash_one(dynamic1), hasher.hash_one(dynamic2));
+    }
+
+    let mut message4 = crate::message::Builder::new_default();
+    message4.set_root(reader1).unwrap();
+    message4
+        .get_root::<node::Builder>()
+        .unwrap()
+        .get_nested_nodes()
+        .unwrap()
+        .get(2)
+        .set_id(7);
+    let reader4: node::Reader = message4.get_root_as_reader().unwrap();
+    assert!(reader1 != reader4);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn malformed_objects_are_equal_to_nothing() {
+    use crate::message::{ReaderOptions, SegmentArray};
+
+    // Root structs whose only pointer points out of bounds. The second one also has a data word.
+    let segment1 = [
+        crate::word(0, 0, 0, 0, 0, 0, 1, 0),
+        crate::word(0x90, 0x01, 0, 0, 1, 0, 0, 0),
+    ];
+    let segment2 = [
+        crate::word(0, 0, 0, 0, 1, 0, 1, 0),
+        crate::word(5, 0, 0, 0, 0, 0, 0, 0),
+        crate::word(0x90, 0x01, 0, 0, 1, 0, 0, 0),
+    ];
+    let segments1 = [crate::Word::words_to_bytes(&segment1)];
+    let segments2 = [crate::Word::words_to_bytes(&segment2)];
+    let message1 = crate::message::Reader::new(SegmentArray::new(&segments1), ReaderOptions::new());
+    let message2 = crate::message::Reader::new(SegmentArray::new(&segments2), ReaderOptions::new());
+    let malformed1: Reader = message1.get_root().unwrap();
+    let malformed2: Reader = message2.get_root().unwrap();
+
+    let mut message3 = crate::message::Builder::new_default();
+    message3.init_root::<crate::schema_capnp::brand::Builder>();
+    let well_formed: Reader = message3.get_root_as_reader().unwrap();
+
+    let same_object = malformed1;
+    assert!(malformed1 != same_object);
+    assert!(malformed1 != malformed2);
+    assert!(malformed1 != well_formed);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn equality_does_not_use_up_traversal_limit() {
+    use crate::message::ReaderOptions;
+    use crate::schema_capnp::node;
+    use std::hash::{BuildHasher, RandomState};
+
+    fn node_bytes(id: u64, display_name: &str) -> Vec<u8> {
+        let mut message = crate::message::Builder::new_default();
+        let mut node: node::Builder = message.init_root();
+        node.set_id(id);
+        node.set_display_name(display_name);
+        crate::serialize::write_message_to_words(&message)
+    }
+
+    let mut options = ReaderOptions::new();
+    options.traversal_limit_in_words(Some(40));
+    let bytes1 = node_bytes(1, "foo.capnp:Foo");
+    let bytes2 = node_bytes(2, "foo.capnp:Bar");
+    let message1 = crate::serialize::read_message_from_flat_slice(&mut &bytes1[..], options).unwrap();
+    let message2 = crate::serialize::read_message_from_flat_slice(&mut &bytes2[..], options).unwrap();
+    let message3 = crate::serialize::read_message_from_flat_slice(&mut &bytes1[..], options).unwrap();
+    let reader1: node::Reader = message1.get_root().unwrap();
+    let reader2: node::Reader = message2.get_root().unwrap();
+    let reader3: node::Reader = message3.get_root().unwrap();
+
+    let hasher = RandomState::new();
+    let hash1 = hasher.hash_one(reader1);
+    for _ in 0..20 {
+        assert!(reader1 != reader2);
+        assert!(reader1 == reader3);
+        assert_eq!(hasher.hash_one(reader1), hash1);
+        assert_eq!(hasher.hash_one(reader3), hash1);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn group_equality_only_covers_the_group() {
+    use crate::schema_capnp::node;
+    use std::hash::{BuildHasher, RandomState};
+
+    fn init_node(mut node: node::Builder, display_name: &str) {
+        node.set_display_name(display_name);
+        node.init_struct().set_data_word_count(3);
+    }
+
+    let mut message1 = crate::message::Builder::new_default();
+    init_node(message1.init_root(), "foo.capnp:Foo");
+    let reader1: node::Reader = message1.get_root_as_reader().unwrap();
+    let mut message2 = crate::message::Builder::new_default();
+    init_node(message2.init_root(), "foo.capnp:Bar");
+    let reader2: node::Reader = message2.get_root_as_reader().unwrap();
+    assert!(reader1 != reader2);
+
+    let (Ok(node::Struct(group1)), Ok(node::Struct(group2))) = (reader1.which(), reader2.which())
+    else {
+        panic!("expected struct nodes")
+    };
+    let hasher = RandomState::new();
+    assert!(group1 == group2);
+    assert_eq!(hasher.hash_one(group1), hasher.hash_one(group2));
+
+    let mut message3 = crate::message::Builder::new_default();
+    message3.set_root(reader1).unwrap();
+    let Ok(node::Struct(mut struct_node)) = message3.get_root::<node::Builder>().unwrap().which()
+    else {
+        panic!("expected a struct node")
+    };
+    struct_node.set_data_word_count(4);
+    let reader3: node::Reader = message3.get_root_as_reader().unwrap();
+    let Ok(node::Struct(group3)) = reader3.which() else {
+        panic!("expected a struct node")
+    };
+    assert!(group1 != group3);
+}
+
+#[test]
+fn null_is_not_empty_struct() {
+    let mut message1 = crate::message::Builder::new_default();
+    message1.init_root::<Builder>();
+    let mut message2 = crate::message::Builder::new_default();
+    message2.init_root::<crate::schema_capnp::brand::Builder>();
+    let null: Reader = message1.get_root_as_reader().unwrap();
+    let empty: Reader = message2.get_root_as_reader().unwrap();
+    assert!(null == Reader::new(PointerReader::new_default()));
+    assert!(null != empty);
+}
@@ -31,9 +31,13 @@ use core::ops::Try;
 use core::pin::Pin;
 use core::task::Poll;
 
-use crate::private::capability::{ClientHook, ParamsHook, RequestHook, ResponseHook, ResultsHook};
-use crate::traits::{Owned, Pipelined};
-use crate::{any_pointer, Error, MessageSize};
+use super::any_pointer;
+use super::flavor::BoxFuture;
+use super::private::capability::{ClientHook, ParamsHook, RequestHook, ResponseHook, ResultsHook};
+use super::traits::{Owned, Pipelined};
+use crate::{Error, MessageSize};
+
+pub use super::flavor::{MaybeSend, MaybeSync};
 
 /// A computation that might eventually resolve to a value of type `T` or to an error
 ///  of type `E`. Dropping the promise cancels the computation.
@@ -44,7 +48,7 @@ pub struct Promise<T, E> {
 
 enum PromiseInner<T, E> {
     Immediate(Result<T, E>),
-    Deferred(Pin<Box<dyn Future<Output = core::result::Result<T, E>> + 'static>>),
+    Deferred(BoxFuture<core::result::Result<T, E>>),
     Empty,
 }
 
@@ -66,7 +70,7 @@ impl<T, E> Promise<T, E> {
 
     pub fn from_future<F>(f: F) -> Self
     where
-        F: Future<Output = core::result::Result<T, E>> + 'static,
+        F: Future<Output = core::result::Result<T, E>> + MaybeSend + 'static,
     {
         Self {
             inner: PromiseInner::Deferred(Box::pin(f)),
@@ -115,6 +119,12 @@ impl<T> std::ops::FromResidual for Promise<T, crate::Error> {
     }
 }
 
+impl<T, E> crate::traits::FromError<E> for Promise<T, E> {
+    fn from_error(error: E) -> Self {
+        Self::err(error)
+    }
+}
+
 /// A promise for a result from a method call.
 #[must_use]
 pub struct RemotePromise<Results>
@@ -194,6 +204,39 @@ where
     }
 }
 
+/// A call to a method declared with `-> stream`, which has not been sent yet.
+pub struct StreamingRequest<Params> {
+    pub marker: PhantomData<Params>,
+    pub hook: Box<dyn RequestHook>,
+}
+
+impl<Params> StreamingRequest<Params>
+where
+    Params: Owned,
+{
+    pub fn new(hook: Box<dyn RequestHook>) -> Self {
+        Self {
+            hook,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get(&mut self) -> Params::Builder<'_> {
+        self.hook.get().get_as().unwrap()
+    }
+
+    pub fn set(&mut self, from: Params::Reader<'_>) -> crate::Result<()> {
+        self.hook.get().set_as(from)
+    }
+
+    /// Sends the call. The returned promise resolves once the flow control window has room
+    /// for another call, which may be well before this one has completed. If an earlier call
+    /// on the same stream failed, it fails with that error instead.
+    pub fn send(self) -> Promise<(), Error> {
+        self.hook.send_streaming()
+    }
+}
+
 /// The values of the parameters passed to a method call, as seen by the server.
 pub struct Params<T> {
     pub marker: PhantomData<T>,
@@ -239,6 +282,15 @@ where
     pub fn set(&mut self, other: T::Reader<'_>) -> crate::Result<()> {
         self.hook.get().unwrap().set_as(other)
     }
+
+    /// Ends the call by sending `request` in its place: the caller gets the results of `request`
+    /// as the results of this call. Where possible, the results are not copied, e.g. the RPC
+    /// system lets the caller take them straight from `request` when both calls go through the
+    /// same connection. The returned promise resolves when `request` has completed, and should
+    /// be returned from the method.
+    pub fn tail_call<SubParams>(self, request: Request<SubParams, T>) -> Promise<(), Error> {
+        self.hook.tail_call(request.hook)
+    }
 }
 
 pub trait FromTypelessPipeline {
@@ -290,6 +342,19 @@ impl Client {
         }
     }
 
+    pub fn new_streaming_call<Params>(
+        &self,
+        interface_id: u64,
+        method_id: u16,
+        size_hint: Option<MessageSize>,
+    ) -> StreamingRequest<Params> {
+        let typeless = self.hook.new_call(interface_id, method_id, size_hint);
+        StreamingRequest {
+            hook: typeless.hook,
+            marker: PhantomData,
+        }
+    }
+
     /// If the capability is actually only a promise, the returned promise resolves once the
     /// capability itself has resolved to its final destination (or propagates the exception if
     /// the capability promise is rejected).  This is mainly useful for error-checking in the case
@@ -300,15 +365,55 @@ impl Client {
     }
 }
 
+impl FromClientHook for Client {
+    fn new(hook: Box<dyn ClientHook>) -> Self {
+        Self { hook }
+    }
+
+    fn into_client_hook(self) -> Box<dyn ClientHook> {
+        self.hook
+    }
+
+    fn as_client_hook(&self) -> &dyn ClientHook {
+        &*self.hook
+    }
+}
+
 /// An untyped server.
-pub trait Server {
+pub trait Server: MaybeSend {
     fn dispatch_call(
         &mut self,
         interface_id: u64,
         method_id: u16,
         params: Params<any_pointer::Owned>,
         results: Results<any_pointer::Owned>,
-    ) -> Promise<(), Error>;
+    ) -> DispatchCallResult;
+}
+
+/// The result of `Server::dispatch_call()`.
+pub struct DispatchCallResult {
+    /// Resolves when the call has completed.
+    pub promise: Promise<(), Error>,
+
+    /// Whether the method was declared with `-> stream`. The RPC system does not dispatch any
+    /// further calls to the server until a streaming call has completed, and if it fails, all
+    /// later calls fail with the same error.
+    pub is_streaming: bool,
+}
+
+impl DispatchCallResult {
+    pub fn new(promise: Promise<(), Error>, is_streaming: bool) -> Self {
+        Self {
+            promise,
+            is_streaming,
+        }
+    }
+}
+
+impl From<Promise<(), Error>> for DispatchCallResult {
+    fn from(promise: Promise<(), Error>) -> Self {
+        Self::new(promise, false)
+    }
 }
 
 /// Trait to track the relationship between generated Server traits and Client structs.
@@ -23,8 +23,8 @@
 use alloc::boxed::Box;
 use core::marker::PhantomData;
 
-use crate::capability::FromClientHook;
-use crate::private::capability::ClientHook;
+use super::capability::FromClientHook;
+use super::private::capability::ClientHook;
 use crate::private::layout::{ListBuilder, ListReader, Pointer, PointerBuilder, PointerReader};
 use crate::traits::{FromPointerBuilder, FromPointerReader, IndexMove, ListIter};
 use crate::Result;
@@ -39,16 +39,16 @@ where
 
 impl<T> crate::introspect::Introspect for Owned<T>
 where
-    T: FromClientHook,
+    T: FromClientHook + crate::introspect::Introspect,
 {
     fn introspect() -> crate::introspect::Type {
-        crate::introspect::Type::list_of(crate::introspect::TypeVariant::Capability.into())
+        crate::introspect::Type::list_of(T::introspect())
     }
 }
 
 impl<T> crate::traits::Owned for Owned<T>
 where
-    T: FromClientHook,
+    T: FromClientHook + crate::introspect::Introspect,
 {
     type Reader<'a> = Reader<'a, T>;
     type Builder<'a> = Builder<'a, T>;
@@ -280,20 +280,24 @@ where
     }
 }
 
-impl<'a, T: FromClientHook> From<Reader<'a, T>> for crate::dynamic_value::Reader<'a> {
+impl<'a, T: FromClientHook + crate::introspect::Introspect> From<Reader<'a, T>>
+    for crate::dynamic_value::Reader<'a>
+{
     fn from(t: Reader<'a, T>) -> crate::dynamic_value::Reader<'a> {
         crate::dynamic_value::Reader::List(crate::dynamic_list::Reader::new(
             t.reader,
-            crate::introspect::TypeVariant::Capability.into(),
+            T::introspect(),
         ))
     }
 }
 
-impl<'a, T: FromClientHook> From<Builder<'a, T>> for crate::dynamic_value::Builder<'a> {
+impl<'a, T: FromClientHook + crate::introspect::Introspect> From<Builder<'a, T>>
+    for crate::dynamic_value::Builder<'a>
+{
     fn from(t: Builder<'a, T>) -> crate::dynamic_value::Builder<'a> {
         crate::dynamic_value::Builder::List(crate::dynamic_list::Builder::new(
             t.builder,
-            crate::introspect::TypeVariant::Capability.into(),
+            T::introspect(),
         ))
     }
 }
@@ -0,0 +1,464 @@
+//! Dynamically-typed capabilities.
+//!
+//! A [`Client`] pairs a capability with the [`InterfaceSchema`] of its interface, so that methods
+//! can be called by name and their params and results accessed through [`dynamic_struct`]
+//! without generated code for the interface. Conversely, a [`Server`] implements an interface
+//! that is only known at runtime. A [`Reader`] is how a capability appears within a
+//! `dynamic_value::Reader`; it is `Copy`, and makes a `Client` on request.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::ToString;
+
+use crate::capability::{DispatchCallResult, FromClientHook, FromServer, Promise};
+use crate::introspect::{Introspect, TypeVariant};
+use crate::private::capability::{ClientHook, ParamsHook, RequestHook, ResponseHook, ResultsHook};
+use crate::private::layout;
+use crate::schema::{InterfaceSchema, Method, StructSchema};
+use crate::{any_pointer, dynamic_struct, dynamic_value, Error, Result};
+
+/// A capability client whose interface is only known at runtime.
+pub struct Client {
+    hook: Option<Box<dyn ClientHook>>,
+    schema: InterfaceSchema,
+}
+
+impl Client {
+    /// Wraps an untyped client, which is assumed to implement the interface of `schema`.
+    pub fn new(client: crate::capability::Client, schema: InterfaceSchema) -> Self {
+        Self::from_hook(client.hook, schema)
+    }
+
+    /// Like `new()`, but takes the client hook directly.
+    pub fn from_hook(hook: Box<dyn ClientHook>, schema: InterfaceSchema) -> Self {
+        Self {
+            hook: Some(hook),
+            schema,
+        }
+    }
+
+    /// A client for a capability pointer that was null. Calls on it fail.
+    pub fn null(schema: InterfaceSchema) -> Self {
+        Self { hook: None, schema }
+    }
+
+    fn from_reader(pointer: layout::PointerReader<'_>, schema: InterfaceSchema) -> Result<Self> {
+        if pointer.is_null() {
+            Ok(Self::null(schema))
+        } else {
+            Ok(Self::from_hook(pointer.get_capability()?, schema))
+        }
+    }
+
+    /// Writes this capability to `pointer`, or clears the pointer if this client is null.
+    pub(crate) fn set_pointer(&self, mut pointer: layout::PointerBuilder<'_>) {
+        match &self.hook {
+            Some(hook) => pointer.set_capability(hook.add_ref()),
+            None => pointer.clear(),
+        }
+    }
+
+    pub fn get_schema(&self) -> InterfaceSchema {
+        self.schema
+    }
+
+    /// Returns true if this client came from a null capability pointer.
+    pub fn is_null(&self) -> bool {
+        self.hook.is_none()
+    }
+
+    pub fn get_hook(&self) -> Option<&dyn ClientHook> {
+        self.hook.as_deref()
+    }
+
+    /// Starts a call to `method`, which must belong to this client's interface or to one of its
+    /// superclasses.
+    pub fn new_call(&self, method: Method) -> Result<Request> {
+        let interface = method.get_containing_interface();
+        let interface_id = interface.get_proto().get_id();
+        if interface_id != self.schema.get_proto().get_id() && !self.schema.extends(interface)? {
+            return Err(Error::failed(format!(
+                "method {} does not belong to this capability's interface",
+                method.get_proto().get_name()?
+            )));
+        }
+        let Some(hook) = &self.hook else {
+            return Err(Error::failed("called null capability".to_string()));
+        };
+        let request = hook.new_call(interface_id, method.get_ordinal(), None);
+        Ok(Request {
+            hook: request.hook,
+            params: method.get_param_type(),
+            results: method.get_result_type(),
+        })
+    }
+
+    /// Starts a call to the method named `name`, looking in superclasses if necessary.
+    pub fn new_call_named(&self, name: &str) -> Result<Request> {
+        self.new_call(self.schema.get_method_by_name(name)?)
+    }
+
+    /// Converts to a generated client type. Panics if the capability's interface is not `T`'s
+    /// interface or one that extends it, or if the capability is null.
+    pub fn downcast<T: FromClientHook + Introspect>(self) -> T {
+        let TypeVariant::Capability(raw) = T::introspect().which() else {
+            panic!("error downcasting to non-interface type");
+        };
+        if check_type(self.schema, raw.into()).is_err() {
+            panic!("error downcasting to incompatible interface");
+        }
+        let Some(hook) = self.hook else {
+            panic!("error downcasting null capability");
+        };
+        T::new(hook)
+    }
+}
+
+impl Clone for Client {
+    fn clone(&self) -> Self {
+        Self {
+            hook: self.hook.as_ref().map(|hook| hook.add_ref()),
+            schema: self.schema,
+        }
+    }
+}
+
+/// Clients are equal if they refer to the same capability.
+impl PartialEq for Client {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.hook, &other.hook) {
+            (Some(a), Some(b)) => a.get_ptr() == b.get_ptr(),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Client {}
+
+impl core::hash::Hash for Client {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.hook.as_ref().map(|hook| hook.get_ptr()).hash(state);
+    }
+}
+
+/// Checks that a capability whose interface is `actual` can be stored where a capability of type
+/// `expected` is expected.
+fn check_type(actual: InterfaceSchema, expected: InterfaceSchema) -> Result<()> {
+    if expected.get_proto().get_id() == actual.get_proto().get_id() || actual.extends(expected)? {
+        Ok(())
+    } else {
+        Err(Error::failed("capability type mismatch".to_string()))
+    }
+}
+
+/// A capability within a message, or a borrowed [`Client`], along with the schema of its
+/// interface.
+#[derive(Clone, Copy)]
+pub struct Reader<'a> {
+    source: Source<'a>,
+    schema: InterfaceSchema,
+}
+
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Pointer(layout::PointerReader<'a>),
+    Client(&'a Client),
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(pointer: layout::PointerReader<'a>, schema: InterfaceSchema) -> Self {
+        Self {
+            source: Source::Pointer(pointer),
+            schema,
+        }
+    }
+
+    /// A null capability.
+    pub fn null(schema: InterfaceSchema) -> Self {
+        Self::new(layout::PointerReader::new_default(), schema)
+    }
+
+    pub fn get_schema(&self) -> InterfaceSchema {
+        self.schema
+    }
+
+    /// Returns true if the capability pointer was null.
+    pub fn is_null(&self) -> bool {
+        match self.source {
+            Source::Pointer(pointer) => pointer.is_null(),
+            Source::Client(client) => client.is_null(),
+        }
+    }
+
+    /// Gets a client for the capability, which is null if the capability pointer was null.
+    pub fn get_client(&self) -> Result<Client> {
+        match self.source {
+            Source::Pointer(pointer) => Client::from_reader(pointer, self.schema),
+            Source::Client(client) => Ok(client.clone()),
+        }
+    }
+
+    /// Converts to a generated client type. Panics under the same conditions as
+    /// `Client::downcast()`, or if the capability can't be read from its message.
+    pub fn downcast<T: FromClientHook + Introspect>(self) -> T {
+        self.get_client().unwrap().downcast()
+    }
+
+    /// Checks that this capability can be stored where a capability of type `expected` is
+    /// expected.
+    pub(crate) fn check_type(&self, expected: InterfaceSchema) -> Result<()> {
+        check_type(self.schema, expected)
+    }
+
+    /// Writes this capability to `pointer`, or clears the pointer if the capability is null.
+    pub(crate) fn set_pointer(&self, mut pointer: layout::PointerBuilder<'_>) -> Result<()> {
+        match self.source {
+            Source::Pointer(source) if source.is_null() => pointer.clear(),
+            Source::Pointer(source) => {
+                pointer.set_capability(source.get_capability::<dyn ClientHook>()?)
+            }
+            Source::Client(client) => client.set_pointer(pointer),
+        }
+        Ok(())
+    }
+
+    /// Identifies the capability, for comparisons. `None` stands for a null capability.
+    fn get_ptr(&self) -> Result<Option<usize>> {
+        match self.source {
+            Source::Pointer(pointer) if pointer.is_null() => Ok(None),
+            Source::Pointer(pointer) => Ok(Some(pointer.get_capability::<dyn ClientHook>()?.get_ptr())),
+            Source::Client(client) => Ok(client.hook.as_ref().map(|hook| hook.get_ptr())),
+        }
+    }
+}
+
+impl<'a> From<&'a Client> for Reader<'a> {
+    fn from(client: &'a Client) -> Self {
+        Self {
+            source: Source::Client(client),
+            schema: client.schema,
+        }
+    }
+}
+
+/// Capabilities are equal if they refer to the same capability. Like structs that fail to decode,
+/// capabilities that can't be read from their message are equal to nothing.
+impl<'a> PartialEq for Reader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        matches!((self.get_ptr(), other.get_ptr()), (Ok(a), Ok(b)) if a == b)
+    }
+}
+
+impl<'a> Eq for Reader<'a> {}
+
+impl<'a> core::hash::Hash for Reader<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.get_ptr().ok().hash(state);
+    }
+}
+
+impl<'a> From<Reader<'a>> for dynamic_value::Reader<'a> {
+    fn from(c: Reader<'a>) -> dynamic_value::Reader<'a> {
+        dynamic_value::Reader::Capability(c)
+    }
+}
+
+impl<'a> From<&'a Client> for dynamic_value::Reader<'a> {
+    fn from(c: &'a Client) -> dynamic_value::Reader<'a> {
+        dynamic_value::Reader::Capability(c.into())
+    }
+}
+
+impl<'a> From<Reader<'a>> for dynamic_value::Builder<'a> {
+    fn from(c: Reader<'a>) -> dynamic_value::Builder<'a> {
+        dynamic_value::Builder::Capability(c)
+    }
+}
+
+/// A method call that has not been sent yet.
+pub struct Request {
+    hook: Box<dyn RequestHook>,
+    params: StructSchema,
+    results: StructSchema,
+}
+
+impl Request {
+    /// Gets the params of the call, for filling in.
+    pub fn get(&mut self) -> dynamic_struct::Builder<'_> {
+        self.hook.get().get_as_struct(self.params).unwrap()
+    }
+
+    pub fn set(&mut self, from: dynamic_struct::Reader<'_>) -> Result<()> {
+        if from.get_schema().get_proto().get_id() != self.params.get_proto().get_id() {
+            return Err(Error::failed("params type mismatch".to_string()));
+        }
+        self.hook.get().set_as(from)
+    }
+
+    pub fn send(self) -> RemotePromise {
+        let results = self.results;
+        let crate::capability::RemotePromise { promise, pipeline } = self.hook.send();
+        RemotePromise {
+            promise: Promise::from_future(async move {
+                Ok(Response {
+                    hook: promise.await?.hook,
+                    schema: results,
+                })
+            }),
+            pipeline,
+        }
+    }
+}
+
+/// A promise for a result from a method call.
+#[must_use]
+pub struct RemotePromise {
+    pub promise: Promise<Response, Error>,
+    pub pipeline: any_pointer::Pipeline,
+}
+
+/// A response from a method call, as seen by the client.
+pub struct Response {
+    hook: Box<dyn ResponseHook>,
+    schema: StructSchema,
+}
+
+impl Response {
+    pub fn get(&self) -> Result<dynamic_struct::Reader<'_>> {
+        self.hook.get()?.get_as_struct(self.schema)
+    }
+
+    pub fn get_schema(&self) -> StructSchema {
+        self.schema
+    }
+}
+
+/// A server for an interface that is only known at runtime. Turn it into a client with
+/// `capnp_rpc::new_client()`, which for these servers produces an untyped
+/// [`capability::Client`](crate::capability::Client).
+pub trait Server {
+    /// The interface that this server implements. Calls to methods of the interface's
+    /// superclasses are also delivered to `call()`.
+    fn get_schema(&self) -> InterfaceSchema;
+
+    /// Handles a call of `method`. As with generated servers, `params` and `results` may be
+    /// moved into the returned promise in order to finish the call asynchronously.
+    fn call(&mut self, method: Method, params: Params, results: Results) -> Promise<(), Error>;
+}
+
+/// Adapts a [`Server`] to [`capability::Server`](crate::capability::Server), by looking up the
+/// method that each call refers to.
+pub struct ServerDispatch<S> {
+    pub server: S,
+}
+
+impl<S: Server> crate::capability::Server for ServerDispatch<S> {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: crate::capability::Params<any_pointer::Owned>,
+        results: crate::capability::Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        let method = match find_method(self.server.get_schema(), interface_id, method_id) {
+            Ok(Some(method)) => method,
+            Ok(None) => {
+                return Promise::err(Error::unimplemented("Method not implemented.".to_string()))
+                    .into()
+            }
+            Err(e) => return Promise::err(e).into(),
+        };
+        let params = Params {
+            hook: params.hook,
+            schema: method.get_param_type(),
+        };
+        let result_type = method.get_result_type();
+        let is_streaming = result_type.get_proto().get_id()
+            == <crate::stream_capnp::stream_result::Reader as crate::traits::HasTypeId>::TYPE_ID;
+        let results = Results {
+            hook: results.hook,
+            schema: result_type,
+        };
+        DispatchCallResult::new(self.server.call(method, params, results), is_streaming)
+    }
+}
+
+/// Finds method `method_id` of interface `interface_id`, which is either `schema` or one of its
+/// superclasses.
+fn find_method(
+    schema: InterfaceSchema,
+    interface_id: u64,
+    method_id: u16,
+) -> Result<Option<Method>> {
+    if schema.get_proto().get_id() == interface_id {
+        let methods = schema.get_methods()?;
+        return Ok((method_id < methods.len()).then(|| methods.get(method_id)));
+    }
+    for superclass in schema.get_superclasses()? {
+        if let Some(method) = find_method(superclass, interface_id, method_id)? {
+            return Ok(Some(method));
+        }
+    }
+    Ok(None)
+}
+
+impl<S> core::ops::Deref for ServerDispatch<S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        &self.server
+    }
+}
+
+impl<S> core::ops::DerefMut for ServerDispatch<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.server
+    }
+}
+
+impl<S: Server + 'static> FromServer<S> for crate::capability::Client {
+    type Dispatch = ServerDispatch<S>;
+
+    fn from_server(s: S) -> ServerDispatch<S> {
+        ServerDispatch { server: s }
+    }
+}
+
+/// The params of a method call, as seen by a [`Server`].
+pub struct Params {
+    hook: Box<dyn ParamsHook>,
+    schema: StructSchema,
+}
+
+impl Params {
+    pub fn get(&self) -> Result<dynamic_struct::Reader<'_>> {
+        self.hook.get()?.get_as_struct(self.schema)
+    }
+
+    pub fn get_schema(&self) -> StructSchema {
+        self.schema
+    }
+}
+
+/// The results of a method call, written in-place by a [`Server`].
+pub struct Results {
+    hook: Box<dyn ResultsHook>,
+    schema: StructSchema,
+}
+
+impl Results {
+    pub fn get(&mut self) -> dynamic_struct::Builder<'_> {
+        self.hook.get().unwrap().get_as_struct(self.schema).unwrap()
+    }
+
+    pub fn set(&mut self, from: dynamic_struct::Reader<'_>) -> Result<()> {
+        if from.get_schema().get_proto().get_id() != self.schema.get_proto().get_id() {
+            return Err(Error::failed("results type mismatch".to_string()));
+        }
+        self.hook.get()?.set_as(from)
+    }
+
+    pub fn get_schema(&self) -> StructSchema {
+        self.schema
+    }
+}
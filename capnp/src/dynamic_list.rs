@@ -1,10 +1,10 @@
 //! Dynamically-typed lists.
 
-use crate::dynamic_value;
 use crate::introspect::{Type, TypeVariant};
 use crate::private::layout::{self, PrimitiveElement};
 use crate::traits::{IndexMove, ListIter};
 use crate::Result;
+use crate::{dynamic_capability, dynamic_value};
 
 /// A read-only dynamically-typed list.
 #[derive(Copy, Clone)]
@@ -19,6 +19,23 @@ impl<'a> From<Reader<'a>> for dynamic_value::Reader<'a> {
     }
 }
 
+/// Lists are equal if they have the same element type and their elements are equal,
+/// independently of how they are laid out in their messages. Lists that fail to decode are
+/// equal to nothing, themselves included.
+impl<'a> PartialEq for Reader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.element_type == other.element_type && self.reader.total_eq(&other.reader)
+    }
+}
+
+impl<'a> Eq for Reader<'a> {}
+
+impl<'a> core::hash::Hash for Reader<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state);
+    }
+}
+
 impl<'a> Reader<'a> {
     pub(crate) fn new(reader: layout::ListReader<'a>, element_type: Type) -> Self {
         Self {
@@ -115,9 +132,11 @@ impl<'a> Reader<'a> {
             TypeVariant::AnyPointer => {
                 Ok(crate::any_pointer::Reader::new(self.reader.get_pointer_element(index)).into())
             }
-            TypeVariant::Capability => {
-                Ok(dynamic_value::Reader::Capability(dynamic_value::Capability))
-            }
+            TypeVariant::Capability(schema) => Ok(dynamic_capability::Reader::new(
+                self.reader.get_pointer_element(index),
+                schema.into(),
+            )
+            .into()),
         }
     }
 
@@ -253,9 +272,11 @@ impl<'a> Builder<'a> {
                 self.builder.get_pointer_element(index),
             )
             .into()),
-            TypeVariant::Capability => Ok(dynamic_value::Builder::Capability(
-                dynamic_value::Capability,
-            )),
+            TypeVariant::Capability(schema) => Ok(dynamic_capability::Reader::new(
+                self.builder.get_pointer_element(index).into_reader(),
+                schema.into(),
+            )
+            .into()),
         }
     }
 
@@ -324,9 +345,10 @@ impl<'a> Builder<'a> {
             (TypeVariant::AnyPointer, _) => Err(crate::Error::failed(
                 "List(AnyPointer) not supported".into(),
             )),
-            (TypeVariant::Capability, dynamic_value::Reader::Capability(_)) => Err(
-                crate::Error::failed("List(Capability) not supported".into()),
-            ),
+            (TypeVariant::Capability(schema), dynamic_value::Reader::Capability(c)) => {
+                c.check_type(schema.into())?;
+                c.set_pointer(self.builder.reborrow().get_pointer_element(index))
+            }
             (_, _) => Err(crate::Error::failed("Type mismatch".into())),
         }
     }
@@ -348,7 +370,7 @@ impl<'a> Builder<'a> {
             | TypeVariant::Float64
             | TypeVariant::Enum(_)
             | TypeVariant::Struct(_)
-            | TypeVariant::Capability => {
+            | TypeVariant::Capability(_) => {
                 Err(crate::Error::failed("Expected a list or blob.".into()))
             }
             TypeVariant::Text => Ok(self
@@ -385,6 +407,32 @@ impl<'a> Builder<'a> {
     }
 }
 
+/// Replaces the list at `pointer` with a new list of `len` elements of type `element_type`.
+/// `fill` receives the old list, which reads as empty if the pointer was null, along with the
+/// new list, and is expected to populate the latter.
+pub(crate) fn rebuild(
+    mut pointer: layout::PointerBuilder<'_>,
+    element_type: Type,
+    len: u32,
+    fill: impl FnOnce(Reader<'_>, Builder<'_>) -> Result<()>,
+) -> Result<()> {
+    let orphan = pointer.disown();
+    let old = Reader::new(
+        orphan
+            .as_pointer_reader()
+            .get_list(element_type.expected_element_size(), None)?,
+        element_type,
+    );
+    let new = match element_type.which() {
+        TypeVariant::Struct(schema) => pointer.init_struct_list(
+            len,
+            crate::dynamic_struct::struct_size_from_schema(schema.into())?,
+        ),
+        _ => pointer.init_list(element_type.expected_element_size(), len),
+    };
+    fill(old, Builder::new(new, element_type))
+}
+
 impl<'a> crate::traits::SetPointerBuilder for Reader<'a> {
     fn set_pointer_builder<'b>(
         mut pointer: crate::private::layout::PointerBuilder<'b>,
@@ -1,11 +1,16 @@
 //! Dynamically-typed structs.
 
+use alloc::vec::Vec;
+
 use crate::introspect::TypeVariant;
 use crate::private::layout;
 use crate::schema::{Field, StructSchema};
 use crate::schema_capnp::{field, node, value};
 use crate::Result;
-use crate::{dynamic_list, dynamic_value};
+use crate::{dynamic_capability, dynamic_list, dynamic_value};
+
+mod mask;
+pub use mask::{copy_masked, FieldMask};
 
 fn has_discriminant_value(reader: field::Reader) -> bool {
     reader.get_discriminant_value() != field::NO_DISCRIMINANT
@@ -35,6 +40,49 @@ impl<'a> From<Reader<'a>> for dynamic_value::Reader<'a> {
     }
 }
 
+/// Structs are equal if they have the same type and their contents are equal, independently
+/// of how they are laid out in their messages. Structs that fail to decode are equal to
+/// nothing, themselves included. A group is laid out within its parent struct, so only the
+/// members of the group are compared.
+impl<'a> PartialEq for Reader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.schema.proto.get_id() != other.schema.proto.get_id() {
+            false
+        } else if self.is_group() {
+            self.reader.with_unmetered(|reader| {
+                other.reader.with_unmetered(|other_reader| {
+                    let values = Reader::new(reader, self.schema).group_values();
+                    let other_values = Reader::new(other_reader, other.schema).group_values();
+                    values.len() == other_values.len()
+                        && values
+                            .iter()
+                            .zip(&other_values)
+                            .all(|pair| matches!(pair, (Ok(a), Ok(b)) if a == b))
+                })
+            })
+        } else {
+            self.reader.total_eq(&other.reader)
+        }
+    }
+}
+
+impl<'a> Eq for Reader<'a> {}
+
+impl<'a> core::hash::Hash for Reader<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.schema.proto.get_id());
+        if self.is_group() {
+            self.reader.with_unmetered(|reader| {
+                for value in Reader::new(reader, self.schema).group_values() {
+                    value.ok().hash(state);
+                }
+            });
+        } else {
+            self.reader.total_hash(state);
+        }
+    }
+}
+
 impl<'a> Reader<'a> {
     pub fn new(reader: layout::StructReader<'a>, schema: StructSchema) -> Self {
         Self { reader, schema }
@@ -157,8 +205,9 @@ impl<'a> Reader<'a> {
                         };
                         Ok(dynamic_value::Reader::AnyPointer(a1))
                     }
-                    (TypeVariant::Capability, value::Interface(())) => {
-                        Ok(dynamic_value::Reader::Capability(dynamic_value::Capability))
+                    (TypeVariant::Capability(schema), value::Interface(())) => {
+                        let p = self.reader.get_pointer_field(offset as usize);
+                        Ok(dynamic_capability::Reader::new(p, schema.into()).into())
                     }
                     _ => Err(crate::Error::failed("field and default mismatch".into())),
                 }
@@ -180,6 +229,34 @@ impl<'a> Reader<'a> {
         self.get(self.schema.get_field_by_name(field_name)?)
     }
 
+    fn is_group(&self) -> bool {
+        matches!(self.schema.proto.which(), Ok(node::Struct(st)) if st.get_is_group())
+    }
+
+    /// The values that make up a group: its union discriminant, if it has a union, followed by
+    /// its non-union fields and its active union member.
+    fn group_values(self) -> Vec<Result<dynamic_value::Reader<'a>>> {
+        let mut values = Vec::new();
+        if let Ok(node::Struct(st)) = self.schema.proto.which() {
+            if st.get_discriminant_count() > 0 {
+                let discriminant = self
+                    .reader
+                    .get_data_field::<u16>(st.get_discriminant_offset() as usize);
+                values.push(Ok(dynamic_value::Reader::UInt16(discriminant)));
+            }
+        }
+        match self.schema.get_non_union_fields() {
+            Ok(fields) => values.extend(fields.into_iter().map(|field| self.get(field))),
+            Err(e) => values.push(Err(e)),
+        }
+        match self.which() {
+            Ok(Some(field)) => values.push(self.get(field)),
+            Ok(None) => (),
+            Err(e) => values.push(Err(e)),
+        }
+        values
+    }
+
     /// If this struct has union fields, returns the one that is currently active.
     /// Otherwise, returns None.
     pub fn which(&self) -> Result<Option<Field>> {
@@ -232,12 +309,78 @@ impl<'a> Reader<'a> {
         let field = self.schema.get_field_by_name(field_name)?;
         self.has(field)
     }
+
+    /// Returns `true` if the non-pointer field `field` holds something other than its default.
+    fn is_scalar_set(&self, field: Field) -> Result<bool> {
+        let default = Reader::new(layout::StructReader::new_default(), self.schema);
+        Ok(self.get(field)? != default.get(field)?)
+    }
+
+    /// Returns `true` if the active union member `field` was explicitly set, that is, if it is
+    /// not the member with discriminant zero still in its default state.
+    pub(crate) fn is_union_set(&self, field: Field) -> Result<bool> {
+        Ok(field.get_proto().get_discriminant_value() != 0 || self.is_set(field)?)
+    }
+
+    /// Returns `true` if `field` is a non-null pointer, a non-default scalar, or a group
+    /// containing any such field.
+    pub(crate) fn is_set(&self, field: Field) -> Result<bool> {
+        match field.get_proto().which()? {
+            field::Group(_) => {
+                let dynamic_value::Reader::Struct(group) = self.get(field)? else {
+                    return Err(crate::Error::failed("not a struct".into()))
+                };
+                if let Some(union_field) = group.which()? {
+                    if group.is_union_set(union_field)? {
+                        return Ok(true);
+                    }
+                }
+                for group_field in group.schema.get_non_union_fields()? {
+                    if group.is_set(group_field)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            field::Slot(_) if field.get_type().is_pointer_type() => self.has(field),
+            field::Slot(_) => self.is_scalar_set(field),
+        }
+    }
+}
+
+/// Controls how [`Builder::merge_from_with_options()`] treats list fields.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListMerge {
+    /// Elements of the source list are appended to the destination list.
+    #[default]
+    Append,
+
+    /// The source list replaces the destination list.
+    Replace,
+}
+
+/// Options for [`Builder::merge_from_with_options()`].
+///
+/// Merging copies fields from a source struct into a destination struct of the same type:
+///
+///   * Non-pointer fields are copied only if they hold something other than their default
+///     value in the source.
+///   * Pointer fields are copied only if they are non-null in the source. Text, data, and
+///     capability fields overwrite the destination.
+///   * Struct fields and groups are merged recursively if the destination already has a value.
+///   * List fields are appended to or replace the destination list, depending on `lists`.
+///   * If the source's union has a member other than its first one active, or has the first one
+///     active with a non-default value, the destination switches to that member. If it was
+///     already active, the two values are merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    pub lists: ListMerge,
 }
 
 /// A mutable dynamically-typed struct.
 pub struct Builder<'a> {
-    builder: layout::StructBuilder<'a>,
-    schema: StructSchema,
+    pub(crate) builder: layout::StructBuilder<'a>,
+    pub(crate) schema: StructSchema,
 }
 
 impl<'a> From<Builder<'a>> for dynamic_value::Builder<'a> {
@@ -389,9 +532,10 @@ impl<'a> Builder<'a> {
                         )
                         .into())
                     }
-                    (TypeVariant::Capability, value::Interface(())) => Ok(
-                        dynamic_value::Builder::Capability(dynamic_value::Capability),
-                    ),
+                    (TypeVariant::Capability(schema), value::Interface(())) => {
+                        let p = self.builder.get_pointer_field(offset as usize).into_reader();
+                        Ok(dynamic_capability::Reader::new(p, schema.into()).into())
+                    }
                     _ => Err(crate::Error::failed("field and default mismatch".into())),
                 }
             }
@@ -509,7 +653,7 @@ impl<'a> Builder<'a> {
                         let mut p = self.builder.reborrow().get_pointer_field(offset);
                         p.set_struct(&v.reader, false)
                     }
-                    (TypeVariant::AnyPointer, _, _) => {
+                    (TypeVariant::AnyPointer, value, _) => {
                         let mut target = crate::any_pointer::Builder::new(
                             self.builder.reborrow().get_pointer_field(offset),
                         );
@@ -518,19 +662,16 @@ impl<'a> Builder<'a> {
                             dynamic_value::Reader::Data(t) => target.set_as(t),
                             dynamic_value::Reader::Struct(s) => target.set_as(s),
                             dynamic_value::Reader::List(l) => target.set_as(l),
-                            dynamic_value::Reader::Capability(_) => {
-                                Err(crate::Error::unimplemented(
-                                    "setting dynamic capabilities is unsupported".into(),
-                                ))
-                            }
+                            dynamic_value::Reader::Capability(c) => c.set_pointer(target.builder),
                             _ => Err(crate::Error::failed(
                                 "cannot set AnyPointer field to a primitive value".into(),
                             )),
                         }
                     }
-                    (TypeVariant::Capability, _, _) => Err(crate::Error::unimplemented(
-                        "setting dynamic capabilities is unsupported".into(),
-                    )),
+                    (TypeVariant::Capability(schema), dynamic_value::Reader::Capability(c), _) => {
+                        c.check_type(schema.into())?;
+                        c.set_pointer(self.builder.reborrow().get_pointer_field(offset))
+                    }
                     _ => Err(crate::Error::failed("type mismatch".into())),
                 }
             }
@@ -682,7 +823,7 @@ impl<'a> Builder<'a> {
                     | TypeVariant::Struct(_)
                     | TypeVariant::List(_)
                     | TypeVariant::AnyPointer
-                    | TypeVariant::Capability => {
+                    | TypeVariant::Capability(_) => {
                         Ok(self.builder.reborrow().get_pointer_field(offset).clear())
                     }
                 }
@@ -713,7 +854,120 @@ impl<'a> Builder<'a> {
         self.clear(field)
     }
 
-    fn set_in_union(&mut self, field: Field) -> Result<()> {
+    /// If `field` is a union member and a different member is currently active, clears the
+    /// active member so that switching variants does not leave its data behind.
+    pub(crate) fn clear_inactive_variant(&mut self, field: Field) -> Result<()> {
+        if !has_discriminant_value(field.get_proto()) {
+            return Ok(());
+        }
+        match self.which()? {
+            Some(active) if active.get_index() != field.get_index() => self.clear(active),
+            _ => Ok(()),
+        }
+    }
+
+    /// Merges `other` into this struct, following the rules described on [`MergeOptions`].
+    /// Uses the default options, under which lists are appended.
+    pub fn merge_from(&mut self, other: Reader<'_>) -> Result<()> {
+        self.merge_from_with_options(other, MergeOptions::default())
+    }
+
+    /// Merges `other` into this struct, following the rules described on [`MergeOptions`].
+    pub fn merge_from_with_options(
+        &mut self,
+        other: Reader<'_>,
+        options: MergeOptions,
+    ) -> Result<()> {
+        if self.schema.get_proto().get_id() != other.schema.get_proto().get_id() {
+            return Err(crate::Error::failed(
+                "cannot merge structs of different types".into(),
+            ));
+        }
+        if let Some(union_field) = other.which()? {
+            if other.is_union_set(union_field)? {
+                let field = self.schema.get_fields()?.get(union_field.get_index());
+                if self.which()?.map(|f| f.get_index()) == Some(field.get_index()) {
+                    self.merge_field(field, other, union_field, options, true)?;
+                } else {
+                    self.clear_inactive_variant(field)?;
+                    self.set(field, other.get(union_field)?)?;
+                }
+            }
+        }
+        for other_field in other.schema.get_non_union_fields()? {
+            let field = self.schema.get_fields()?.get(other_field.get_index());
+            self.merge_field(field, other, other_field, options, false)?;
+        }
+        Ok(())
+    }
+
+    fn merge_field(
+        &mut self,
+        field: Field,
+        other: Reader<'_>,
+        other_field: Field,
+        options: MergeOptions,
+        overwrite_scalars: bool,
+    ) -> Result<()> {
+        let slot = match field.get_proto().which()? {
+            field::Group(_) => {
+                let dynamic_value::Builder::Struct(mut group) = self.reborrow().get(field)? else {
+                    return Err(crate::Error::failed("not a struct".into()))
+                };
+                let dynamic_value::Reader::Struct(other_group) = other.get(other_field)? else {
+                    return Err(crate::Error::failed("not a struct".into()))
+                };
+                return group.merge_from_with_options(other_group, options);
+            }
+            field::Slot(slot) => slot,
+        };
+        let ty = field.get_type();
+        if !ty.is_pointer_type() {
+            return if overwrite_scalars || other.is_scalar_set(other_field)? {
+                self.set(field, other.get(other_field)?)
+            } else {
+                Ok(())
+            };
+        }
+        if !other.has(other_field)? {
+            return Ok(());
+        }
+        if !self.has(field)? {
+            return self.set(field, other.get(other_field)?);
+        }
+        match (ty.which(), other.get(other_field)?) {
+            (TypeVariant::Struct(_), dynamic_value::Reader::Struct(other_struct)) => {
+                let dynamic_value::Builder::Struct(mut dst) = self.reborrow().get(field)? else {
+                    return Err(crate::Error::failed("not a struct".into()))
+                };
+                dst.merge_from_with_options(other_struct, options)
+            }
+            (TypeVariant::List(element_type), dynamic_value::Reader::List(other_list))
+                if options.lists == ListMerge::Append =>
+            {
+                let pointer = self
+                    .builder
+                    .reborrow()
+                    .get_pointer_field(slot.get_offset() as usize);
+                let old_len = pointer
+                    .as_reader()
+                    .get_list(element_type.expected_element_size(), None)?
+                    .len();
+                let new_len = old_len
+                    .checked_add(other_list.len())
+                    .ok_or_else(|| crate::Error::failed("merged list is too long".into()))?;
+                dynamic_list::rebuild(pointer, element_type, new_len, |old, mut new| {
+                    for (idx, value) in old.iter().chain(other_list.iter()).enumerate() {
+                        new.set(idx as u32, value?)?;
+                    }
+                    Ok(())
+                })
+            }
+            (_, value) => self.set(field, value),
+        }
+    }
+
+    pub(crate) fn set_in_union(&mut self, field: Field) -> Result<()> {
         if has_discriminant_value(field.get_proto()) {
             let node::Struct(st) = self.schema.get_proto().which()? else {
                 return Err(crate::Error::failed("not a struct".into()))
@@ -736,3 +990,143 @@ impl<'a> crate::traits::SetPointerBuilder for Reader<'a> {
         pointer.set_struct(&value.reader, canonicalize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ListMerge, MergeOptions};
+    use crate::message;
+    use crate::schema_capnp::{code_generator_request, node};
+
+    fn names(node: node::Reader<'_>) -> Vec<&str> {
+        node.get_nested_nodes()
+            .unwrap()
+            .iter()
+            .map(|n| n.get_name().unwrap())
+            .collect()
+    }
+
+    fn set_names(node: node::Builder<'_>, names: &[&str]) {
+        let mut nested = node.init_nested_nodes(names.len() as u32);
+        for (idx, name) in names.iter().enumerate() {
+            nested.reborrow().get(idx as u32).set_name(name);
+        }
+    }
+
+    #[test]
+    fn merge_scalars_only_when_set() {
+        let mut dst = message::Builder::new_default();
+        let mut node = dst.init_root::<node::Builder>();
+        node.set_id(1);
+        node.set_display_name("dst");
+        node.set_is_generic(true);
+
+        let mut src = message::Builder::new_default();
+        let mut src_node = src.init_root::<node::Builder>();
+        src_node.set_scope_id(5);
+
+        node.merge_from(src_node.reborrow_as_reader()).unwrap();
+        assert_eq!(node.reborrow_as_reader().get_id(), 1);
+        assert_eq!(node.reborrow_as_reader().get_scope_id(), 5);
+        assert_eq!(node.reborrow_as_reader().get_display_name().unwrap(), "dst");
+        assert!(node.reborrow_as_reader().get_is_generic());
+
+        src_node.set_id(2);
+        src_node.set_display_name("src");
+        node.merge_from(src_node.into_reader()).unwrap();
+        assert_eq!(node.reborrow_as_reader().get_id(), 2);
+        assert_eq!(node.into_reader().get_display_name().unwrap(), "src");
+    }
+
+    #[test]
+    fn merge_structs_recursively() {
+        let mut dst = message::Builder::new_default();
+        let mut request = dst.init_root::<code_generator_request::Builder>();
+        {
+            let mut version = request.reborrow().init_capnp_version();
+            version.set_major(1);
+            version.set_minor(2);
+        }
+
+        let mut src = message::Builder::new_default();
+        let mut src_request = src.init_root::<code_generator_request::Builder>();
+        src_request.reborrow().init_capnp_version().set_micro(3);
+
+        request.merge_from(src_request.into_reader()).unwrap();
+        let version = request.into_reader().get_capnp_version().unwrap();
+        assert_eq!(version.get_major(), 1);
+        assert_eq!(version.get_minor(), 2);
+        assert_eq!(version.get_micro(), 3);
+    }
+
+    #[test]
+    fn merge_lists() {
+        let mut src = message::Builder::new_default();
+        set_names(src.init_root(), &["c", "d"]);
+        let src = src.get_root_as_reader::<node::Reader>().unwrap();
+
+        let mut dst = message::Builder::new_default();
+        let mut node = dst.init_root::<node::Builder>();
+        set_names(node.reborrow(), &["a", "b"]);
+        node.merge_from(src).unwrap();
+        assert_eq!(names(node.reborrow_as_reader()), ["a", "b", "c", "d"]);
+
+        let replace = MergeOptions {
+            lists: ListMerge::Replace,
+        };
+        node.merge_from_with_options(src, replace).unwrap();
+        assert_eq!(names(node.reborrow_as_reader()), ["c", "d"]);
+
+        // A null list in the source leaves the destination alone.
+        let mut empty = message::Builder::new_default();
+        empty.init_root::<node::Builder>();
+        node.merge_from(empty.get_root_as_reader().unwrap())
+            .unwrap();
+        assert_eq!(names(node.into_reader()), ["c", "d"]);
+    }
+
+    #[test]
+    fn merge_unions() {
+        let mut dst = message::Builder::new_default();
+        let mut node = dst.init_root::<node::Builder>();
+        node.reborrow().init_enum().init_enumerants(2);
+
+        // The source's union is in its default state, so it is not merged.
+        let mut src = message::Builder::new_default();
+        src.init_root::<node::Builder>();
+        node.merge_from(src.get_root_as_reader().unwrap()).unwrap();
+        assert!(matches!(
+            node.reborrow_as_reader().which().unwrap(),
+            node::Enum(_)
+        ));
+
+        let mut src = message::Builder::new_default();
+        {
+            let mut st = src.init_root::<node::Builder>().init_struct();
+            st.set_data_word_count(2);
+            st.set_pointer_count(1);
+        }
+        node.merge_from(src.get_root_as_reader().unwrap()).unwrap();
+
+        let mut expected = message::Builder::new_default();
+        expected
+            .set_root(src.get_root_as_reader::<node::Reader>().unwrap())
+            .unwrap();
+        assert_eq!(
+            node.reborrow_as_reader(),
+            expected.get_root_as_reader::<node::Reader>().unwrap()
+        );
+
+        // Merging into the same variant merges the variants' fields.
+        let mut src = message::Builder::new_default();
+        src.init_root::<node::Builder>()
+            .init_struct()
+            .set_discriminant_count(3);
+        node.merge_from(src.get_root_as_reader().unwrap()).unwrap();
+        let node::Struct(st) = node.into_reader().which().unwrap() else {
+            panic!("expected a struct node");
+        };
+        assert_eq!(st.get_data_word_count(), 2);
+        assert_eq!(st.get_pointer_count(), 1);
+        assert_eq!(st.get_discriminant_count(), 3);
+    }
+}
@@ -0,0 +1,429 @@
+//! Copying selected fields of a struct.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+
+use crate::dynamic_struct::{Builder, Reader};
+use crate::introspect::{Type, TypeVariant};
+use crate::schema::StructSchema;
+use crate::schema_capnp::field;
+use crate::{dynamic_list, dynamic_value, Error, Result};
+
+/// A set of field paths within a struct type, such as `people.*.name`.
+///
+/// Path elements are separated by dots. Each element names a field of the struct or group
+/// reached so far, except that a list is descended into with `*`, which stands for every
+/// element of the list. Selecting a field selects everything underneath it.
+///
+/// ```
+/// use capnp::dynamic_struct::{self, copy_masked, FieldMask};
+/// use capnp::{dynamic_value, message, schema_capnp::node};
+///
+/// let mut src = message::Builder::new_default();
+/// let mut node = src.init_root::<node::Builder>();
+/// node.set_display_name("foo.capnp:Foo");
+/// node.reborrow().init_nested_nodes(1).get(0).set_name("Bar");
+/// let src: dynamic_struct::Reader = dynamic_value::Reader::from(node.into_reader()).downcast();
+///
+/// let mask = FieldMask::from_paths(src.get_schema(), ["nestedNodes.*.name"]).unwrap();
+/// let mut dst = message::Builder::new_default();
+/// let node = dst.init_root::<node::Builder>();
+/// copy_masked(src, dynamic_value::Builder::from(node).downcast(), &mask).unwrap();
+///
+/// let node = dst.get_root_as_reader::<node::Reader>().unwrap();
+/// assert!(!node.has_display_name());
+/// assert_eq!(node.get_nested_nodes().unwrap().get(0).get_name().unwrap(), "Bar");
+/// ```
+#[derive(Clone)]
+pub struct FieldMask {
+    schema: StructSchema,
+    root: MaskNode,
+}
+
+#[derive(Clone, Default)]
+struct MaskNode {
+    /// The whole value is selected.
+    whole: bool,
+
+    /// Selected fields of a struct or group, by field index.
+    fields: BTreeMap<u16, MaskNode>,
+
+    /// Selected parts of each element of a list.
+    elements: Option<Box<MaskNode>>,
+}
+
+impl FieldMask {
+    /// Creates a mask for structs of type `schema` that selects nothing.
+    pub fn new(schema: StructSchema) -> Self {
+        Self {
+            schema,
+            root: MaskNode::default(),
+        }
+    }
+
+    /// Creates a mask for structs of type `schema` that selects `paths`. Returns an error if
+    /// any of the paths does not exist in the schema.
+    pub fn from_paths<I, S>(schema: StructSchema, paths: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut mask = Self::new(schema);
+        for path in paths {
+            mask.add_path(path.as_ref())?;
+        }
+        Ok(mask)
+    }
+
+    pub fn get_schema(&self) -> StructSchema {
+        self.schema
+    }
+
+    /// Adds `path` to the mask. Returns an error if the path does not exist in the schema.
+    pub fn add_path(&mut self, path: &str) -> Result<()> {
+        let mut node = &mut self.root;
+        let mut ty = Type::from(TypeVariant::Struct(self.schema.raw));
+        for element in path.split('.') {
+            let invalid = |reason| {
+                Err(Error::failed(format!(
+                    "invalid field mask path \"{path}\": {reason} \"{element}\""
+                )))
+            };
+            match ty.which() {
+                TypeVariant::Struct(schema) => {
+                    let schema = StructSchema::from(schema);
+                    let Some(field) = schema.find_field_by_name(element)? else {
+                        return invalid("unknown field");
+                    };
+                    ty = field.get_type();
+                    node = node.fields.entry(field.get_index()).or_default();
+                }
+                TypeVariant::List(element_type) => {
+                    if element != "*" {
+                        return invalid("expected \"*\" for list elements, found");
+                    }
+                    ty = element_type;
+                    node = node.elements.get_or_insert_with(Default::default);
+                }
+                _ => return invalid("cannot select a member of a non-struct value with"),
+            }
+        }
+        node.whole = true;
+        Ok(())
+    }
+}
+
+/// Copies the fields of `src` selected by `mask` into `dst`. Fields that are not selected are
+/// left untouched in `dst`, while a selected pointer field that is null in `src` is cleared in
+/// `dst`. A selected union member is copied only if it is the active one in `src`.
+pub fn copy_masked(src: Reader<'_>, dst: Builder<'_>, mask: &FieldMask) -> Result<()> {
+    let id = mask.schema.get_proto().get_id();
+    if src.get_schema().get_proto().get_id() != id || dst.get_schema().get_proto().get_id() != id {
+        return Err(Error::failed(
+            "field mask does not match the type of the struct".into(),
+        ));
+    }
+    copy_struct(src, dst, &mask.root)
+}
+
+fn copy_struct(src: Reader<'_>, mut dst: Builder<'_>, node: &MaskNode) -> Result<()> {
+    let fields = src.get_schema().get_fields()?;
+    let active = src.which()?.map(|f| f.get_index());
+    for (&index, child) in &node.fields {
+        let field = fields.get(index);
+        let is_union_member = field.get_proto().get_discriminant_value() != field::NO_DISCRIMINANT;
+        if is_union_member && active != Some(index) {
+            continue;
+        }
+        if child.whole {
+            if src.has(field)? {
+                dst.set(field, src.get(field)?)?;
+            } else {
+                dst.clear(field)?;
+            }
+            continue;
+        }
+        // Only part of the field is selected, so whatever `dst` already holds in it is kept,
+        // unless it has to be replaced to match the shape of `src`.
+        match (field.get_proto().which()?, src.get(field)?) {
+            (field::Group(_), dynamic_value::Reader::Struct(src_group)) => {
+                let group = if is_union_member && !dst.has(field)? {
+                    dst.reborrow().init(field)?
+                } else {
+                    dst.reborrow().get(field)?
+                };
+                copy_struct(src_group, group.downcast(), child)?;
+            }
+            _ if !src.has(field)? => {}
+            (field::Slot(_), dynamic_value::Reader::Struct(src_struct)) => {
+                let dst_struct = if dst.has(field)? {
+                    dst.reborrow().get(field)?
+                } else {
+                    dst.reborrow().init(field)?
+                };
+                copy_struct(src_struct, dst_struct.downcast(), child)?;
+            }
+            (field::Slot(_), dynamic_value::Reader::List(src_list)) => {
+                let dst_list = if dst.has(field)?
+                    && same_len(dst.reborrow_as_reader().get(field)?, src_list)
+                {
+                    dst.reborrow().get(field)?
+                } else {
+                    dst.reborrow().initn(field, src_list.len())?
+                };
+                copy_list(src_list, dst_list.downcast(), child)?;
+            }
+            _ => return Err(Error::failed("field mask does not match the schema".into())),
+        }
+    }
+    Ok(())
+}
+
+fn copy_list(
+    src: dynamic_list::Reader<'_>,
+    mut dst: dynamic_list::Builder<'_>,
+    node: &MaskNode,
+) -> Result<()> {
+    let Some(elements) = &node.elements else {
+        return Ok(());
+    };
+    for index in 0..src.len() {
+        if elements.whole {
+            dst.set(index, src.get(index)?)?;
+            continue;
+        }
+        match src.get(index)? {
+            dynamic_value::Reader::Struct(src_struct) => {
+                copy_struct(src_struct, dst.reborrow().get(index)?.downcast(), elements)?;
+            }
+            dynamic_value::Reader::List(src_list) => {
+                let dst_list = if same_len(dst.reborrow().into_reader().get(index)?, src_list) {
+                    dst.reborrow().get(index)?
+                } else {
+                    dst.reborrow().init(index, src_list.len())?
+                };
+                copy_list(src_list, dst_list.downcast(), elements)?;
+            }
+            _ => return Err(Error::failed("field mask does not match the schema".into())),
+        }
+    }
+    Ok(())
+}
+
+/// Whether `dst` is a list that can take a partial copy of `src` in place.
+fn same_len(dst: dynamic_value::Reader<'_>, src: dynamic_list::Reader<'_>) -> bool {
+    matches!(dst, dynamic_value::Reader::List(dst) if dst.len() == src.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_masked, FieldMask};
+    use crate::schema_capnp::node;
+    use crate::{dynamic_struct, dynamic_value, message};
+
+    fn schema() -> crate::schema::StructSchema {
+        let message = message::Builder::new_default();
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        dynamic_value::Reader::from(node)
+            .downcast::<dynamic_struct::Reader>()
+            .get_schema()
+    }
+
+    fn copy(src: node::Reader<'_>, paths: &[&str]) -> message::Builder<message::HeapAllocator> {
+        let mask = FieldMask::from_paths(schema(), paths).unwrap();
+        let mut dst = message::Builder::new_default();
+        let root = dst.init_root::<node::Builder>();
+        copy_masked(
+            dynamic_value::Reader::from(src).downcast(),
+            dynamic_value::Builder::from(root).downcast(),
+            &mask,
+        )
+        .unwrap();
+        dst
+    }
+
+    #[test]
+    fn invalid_paths() {
+        for path in [
+            "",
+            "nope",
+            "displayName.length",
+            "nestedNodes.name",
+            "nestedNodes.*.nope",
+            "struct.*",
+        ] {
+            assert!(
+                FieldMask::from_paths(schema(), [path]).is_err(),
+                "{path} should be rejected"
+            );
+        }
+        assert!(FieldMask::from_paths(schema(), ["struct.fields.*.slot.type"]).is_ok());
+    }
+
+    #[test]
+    fn copy_list_elements() {
+        let mut src = message::Builder::new_default();
+        let mut node = src.init_root::<node::Builder>();
+        node.set_id(7);
+        node.set_display_name("foo.capnp:Foo");
+        {
+            let mut nested = node.reborrow().init_nested_nodes(2);
+            for idx in 0..2 {
+                let mut entry = nested.reborrow().get(idx);
+                entry.set_id(u64::from(idx) + 1);
+                entry.set_name(if idx == 0 { "A" } else { "B" });
+            }
+        }
+
+        let dst = copy(node.into_reader(), &["id", "nestedNodes.*.name"]);
+        let node = dst.get_root_as_reader::<node::Reader>().unwrap();
+        assert_eq!(node.get_id(), 7);
+        assert!(!node.has_display_name());
+        let nested = node.get_nested_nodes().unwrap();
+        assert_eq!(nested.len(), 2);
+        assert_eq!(nested.get(0).get_name().unwrap(), "A");
+        assert_eq!(nested.get(1).get_name().unwrap(), "B");
+        assert_eq!(nested.get(0).get_id(), 0);
+        assert_eq!(nested.get(1).get_id(), 0);
+    }
+
+    #[test]
+    fn copy_active_union_member_only() {
+        let mut src = message::Builder::new_default();
+        {
+            let mut st = src.init_root::<node::Builder>().init_struct();
+            st.set_data_word_count(2);
+            st.set_pointer_count(3);
+        }
+        let src = src.get_root_as_reader::<node::Reader>().unwrap();
+
+        let dst = copy(src, &["enum", "struct.dataWordCount"]);
+        let node::Struct(st) = dst
+            .get_root_as_reader::<node::Reader>()
+            .unwrap()
+            .which()
+            .unwrap()
+        else {
+            panic!("expected a struct node");
+        };
+        assert_eq!(st.get_data_word_count(), 2);
+        assert_eq!(st.get_pointer_count(), 0);
+
+        let dst = copy(src, &["enum.enumerants"]);
+        let node = dst.get_root_as_reader::<node::Reader>().unwrap();
+        assert!(matches!(node.which().unwrap(), node::File(())));
+    }
+
+    #[test]
+    fn partial_copy_keeps_unselected_fields() {
+        use crate::schema_capnp::code_generator_request;
+
+        let mut src = message::Builder::new_default();
+        {
+            let mut request = src.init_root::<code_generator_request::Builder>();
+            request.reborrow().init_capnp_version().set_major(9);
+            request
+                .init_requested_files(1)
+                .get(0)
+                .set_filename("foo.capnp");
+        }
+        let src = src
+            .get_root_as_reader::<code_generator_request::Reader>()
+            .unwrap();
+
+        let mut dst = message::Builder::new_default();
+        {
+            let mut request = dst.init_root::<code_generator_request::Builder>();
+            let mut version = request.reborrow().init_capnp_version();
+            version.set_minor(2);
+            version.set_micro(3);
+            request.init_requested_files(1).get(0).set_id(7);
+        }
+        let mask = FieldMask::from_paths(
+            dynamic_value::Reader::from(src)
+                .downcast::<dynamic_struct::Reader>()
+                .get_schema(),
+            ["capnpVersion.major", "requestedFiles.*.filename"],
+        )
+        .unwrap();
+        copy_masked(
+            dynamic_value::Reader::from(src).downcast(),
+            dynamic_value::Builder::from(
+                dst.get_root::<code_generator_request::Builder>().unwrap(),
+            )
+            .downcast(),
+            &mask,
+        )
+        .unwrap();
+
+        let request = dst
+            .get_root_as_reader::<code_generator_request::Reader>()
+            .unwrap();
+        let version = request.get_capnp_version().unwrap();
+        assert_eq!(
+            (
+                version.get_major(),
+                version.get_minor(),
+                version.get_micro()
+            ),
+            (9, 2, 3)
+        );
+        let file = request.get_requested_files().unwrap().get(0);
+        assert_eq!(file.get_filename().unwrap(), "foo.capnp");
+        assert_eq!(file.get_id(), 7);
+
+        let mut src = message::Builder::new_default();
+        src.init_root::<node::Builder>()
+            .init_struct()
+            .set_data_word_count(2);
+        let mut dst = message::Builder::new_default();
+        dst.init_root::<node::Builder>()
+            .init_struct()
+            .set_pointer_count(4);
+        let mask = FieldMask::from_paths(schema(), ["struct.dataWordCount"]).unwrap();
+        copy_masked(
+            dynamic_value::Reader::from(src.get_root_as_reader::<node::Reader>().unwrap())
+                .downcast(),
+            dynamic_value::Builder::from(dst.get_root::<node::Builder>().unwrap()).downcast(),
+            &mask,
+        )
+        .unwrap();
+        let node::Struct(st) = dst
+            .get_root_as_reader::<node::Reader>()
+            .unwrap()
+            .which()
+            .unwrap()
+        else {
+            panic!("expected a struct node");
+        };
+        assert_eq!(st.get_data_word_count(), 2);
+        assert_eq!(st.get_pointer_count(), 4);
+    }
+
+    #[test]
+    fn null_selected_field_is_cleared() {
+        let mut src = message::Builder::new_default();
+        src.init_root::<node::Builder>().set_id(7);
+        let src = src.get_root_as_reader::<node::Reader>().unwrap();
+
+        let mut dst = message::Builder::new_default();
+        {
+            let mut node = dst.init_root::<node::Builder>();
+            node.set_display_name("foo.capnp:Foo");
+            node.reborrow().init_nested_nodes(1).get(0).set_name("Bar");
+            node.init_annotations(1).get(0).set_id(3);
+        }
+        let mask = FieldMask::from_paths(schema(), ["displayName", "nestedNodes"]).unwrap();
+        copy_masked(
+            dynamic_value::Reader::from(src).downcast(),
+            dynamic_value::Builder::from(dst.get_root::<node::Builder>().unwrap()).downcast(),
+            &mask,
+        )
+        .unwrap();
+
+        let node = dst.get_root_as_reader::<node::Reader>().unwrap();
+        assert!(!node.has_display_name());
+        assert!(!node.has_nested_nodes());
+        assert_eq!(node.get_annotations().unwrap().get(0).get_id(), 3);
+    }
+}
@@ -3,7 +3,12 @@
 use crate::introspect::{self, TypeVariant};
 use crate::schema_capnp::value;
 use crate::Result;
-use crate::{dynamic_list, dynamic_struct};
+use crate::{dynamic_capability, dynamic_list, dynamic_struct};
+
+mod diff;
+mod query;
+pub use diff::{diff, Change, ChangeKind, FieldPath, PathElement};
+pub use query::Query;
 
 /// A dynamically-typed read-only value.
 #[derive(Clone, Copy)]
@@ -26,7 +31,7 @@ pub enum Reader<'a> {
     Struct(dynamic_struct::Reader<'a>),
     List(dynamic_list::Reader<'a>),
     AnyPointer(crate::any_pointer::Reader<'a>),
-    Capability(Capability),
+    Capability(dynamic_capability::Reader<'a>),
 }
 
 impl<'a> Reader<'a> {
@@ -57,7 +62,9 @@ impl<'a> Reader<'a> {
                     element_type,
                 )))
             }
-            (value::Interface(()), TypeVariant::Capability) => Ok(Capability.into()),
+            (value::Interface(()), TypeVariant::Capability(schema)) => Ok(Reader::Capability(
+                dynamic_capability::Reader::null(schema.into()),
+            )),
             (value::AnyPointer(a), TypeVariant::AnyPointer) => Ok(a.into()),
             _ => Err(crate::Error::failed("type mismatch".into())),
         }
@@ -70,6 +77,64 @@ impl<'a> Reader<'a> {
     }
 }
 
+/// Values are equal if they have the same type and the same content. Floating point values are
+/// compared bitwise, as they are on the wire, so that `NaN == NaN` and `0.0 != -0.0`.
+impl<'a> PartialEq for Reader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Reader::Void, Reader::Void) => true,
+            (Reader::Bool(a), Reader::Bool(b)) => a == b,
+            (Reader::Int8(a), Reader::Int8(b)) => a == b,
+            (Reader::Int16(a), Reader::Int16(b)) => a == b,
+            (Reader::Int32(a), Reader::Int32(b)) => a == b,
+            (Reader::Int64(a), Reader::Int64(b)) => a == b,
+            (Reader::UInt8(a), Reader::UInt8(b)) => a == b,
+            (Reader::UInt16(a), Reader::UInt16(b)) => a == b,
+            (Reader::UInt32(a), Reader::UInt32(b)) => a == b,
+            (Reader::UInt64(a), Reader::UInt64(b)) => a == b,
+            (Reader::Float32(a), Reader::Float32(b)) => a.to_bits() == b.to_bits(),
+            (Reader::Float64(a), Reader::Float64(b)) => a.to_bits() == b.to_bits(),
+            (Reader::Enum(a), Reader::Enum(b)) => a == b,
+            (Reader::Text(a), Reader::Text(b)) => a == b,
+            (Reader::Data(a), Reader::Data(b)) => a == b,
+            (Reader::Struct(a), Reader::Struct(b)) => a == b,
+            (Reader::List(a), Reader::List(b)) => a == b,
+            (Reader::AnyPointer(a), Reader::AnyPointer(b)) => a == b,
+            (Reader::Capability(a), Reader::Capability(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Eq for Reader<'a> {}
+
+impl<'a> core::hash::Hash for Reader<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Reader::Void => {}
+            Reader::Bool(x) => x.hash(state),
+            Reader::Int8(x) => x.hash(state),
+            Reader::Int16(x) => x.hash(state),
+            Reader::Int32(x) => x.hash(state),
+            Reader::Int64(x) => x.hash(state),
+            Reader::UInt8(x) => x.hash(state),
+            Reader::UInt16(x) => x.hash(state),
+            Reader::UInt32(x) => x.hash(state),
+            Reader::UInt64(x) => x.hash(state),
+            Reader::Float32(x) => x.to_bits().hash(state),
+            Reader::Float64(x) => x.to_bits().hash(state),
+            Reader::Enum(x) => x.hash(state),
+            Reader::Text(x) => x.hash(state),
+            Reader::Data(x) => x.hash(state),
+            Reader::Struct(x) => x.hash(state),
+            Reader::List(x) => x.hash(state),
+            Reader::AnyPointer(x) => x.hash(state),
+            Reader::Capability(x) => x.hash(state),
+        }
+    }
+}
+
 impl<'a> From<()> for Reader<'a> {
     fn from((): ()) -> Reader<'a> {
         Reader::Void
@@ -135,6 +200,7 @@ downcast_reader_impl!(crate::data::Reader<'a>, Data, "data");
 downcast_reader_impl!(dynamic_list::Reader<'a>, List, "list");
 downcast_reader_impl!(dynamic_struct::Reader<'a>, Struct, "struct");
 downcast_reader_impl!(crate::any_pointer::Reader<'a>, AnyPointer, "anypointer");
+downcast_reader_impl!(dynamic_capability::Reader<'a>, Capability, "capability");
 
 /// A dynamically-typed value with mutable interior.
 pub enum Builder<'a> {
@@ -156,7 +222,7 @@ pub enum Builder<'a> {
     Struct(dynamic_struct::Builder<'a>),
     List(dynamic_list::Builder<'a>),
     AnyPointer(crate::any_pointer::Builder<'a>),
-    Capability(Capability),
+    Capability(dynamic_capability::Reader<'a>),
 }
 
 impl<'a> Builder<'a> {
@@ -254,6 +320,11 @@ downcast_builder_impl!(crate::data::Builder<'a>, Data, "data");
 downcast_builder_impl!(dynamic_list::Builder<'a>, List, "list");
 downcast_builder_impl!(dynamic_struct::Builder<'a>, Struct, "struct");
 downcast_builder_impl!(crate::any_pointer::Builder<'a>, AnyPointer, "anypointer");
+downcast_builder_impl!(dynamic_capability::Reader<'a>, Capability, "capability");
+
+/// The former placeholder for capabilities in dynamic values.
+#[deprecated(note = "use dynamic_capability::Client, or dynamic_capability::Reader for values")]
+pub type Capability = dynamic_capability::Client;
 
 /// A dynamically-typed enum value.
 #[derive(Clone, Copy)]
@@ -262,6 +333,21 @@ pub struct Enum {
     schema: crate::schema::EnumSchema,
 }
 
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.schema.proto.get_id() == other.schema.proto.get_id()
+    }
+}
+
+impl Eq for Enum {}
+
+impl core::hash::Hash for Enum {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+        self.schema.proto.get_id().hash(state);
+    }
+}
+
 impl Enum {
     pub fn new(value: u16, schema: crate::schema::EnumSchema) -> Self {
         Self { value, schema }
@@ -294,20 +380,3 @@ impl<'a> From<Enum> for Builder<'a> {
         Builder::Enum(e)
     }
 }
-
-/// A dynamic capability. Currently, this is just a stub and does not support calling
-/// of methods.
-#[derive(Clone, Copy)]
-pub struct Capability;
-
-impl<'a> From<Capability> for Reader<'a> {
-    fn from(c: Capability) -> Reader<'a> {
-        Reader::Capability(c)
-    }
-}
-
-impl<'a> From<Capability> for Builder<'a> {
-    fn from(c: Capability) -> Builder<'a> {
-        Builder::Capability(c)
-    }
-}
@@ -0,0 +1,768 @@
+//! Structural diffs between dynamically-typed structs.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
+
+use crate::dynamic_value::{Builder, Reader};
+use crate::introspect::{Type, TypeVariant};
+use crate::private::layout::PointerBuilder;
+use crate::schema::Field;
+use crate::schema_capnp::field;
+use crate::{dynamic_list, dynamic_struct, Error, Result};
+
+/// One step of a [`FieldPath`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    /// A field of a struct or group, by name.
+    Field(String),
+
+    /// An element of a list, by index.
+    Index(u32),
+}
+
+/// The location of a value within a struct, such as `people[3].phones[0].number`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FieldPath {
+    elements: Vec<PathElement>,
+}
+
+impl FieldPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn elements(&self) -> &[PathElement] {
+        &self.elements
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn push_field(&mut self, name: &str) {
+        self.elements.push(PathElement::Field(name.into()));
+    }
+
+    pub fn push_index(&mut self, index: u32) {
+        self.elements.push(PathElement::Index(index));
+    }
+
+    pub fn pop(&mut self) -> Option<PathElement> {
+        self.elements.pop()
+    }
+}
+
+impl From<Vec<PathElement>> for FieldPath {
+    fn from(elements: Vec<PathElement>) -> Self {
+        Self { elements }
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, element) in self.elements.iter().enumerate() {
+            match element {
+                PathElement::Field(name) if idx == 0 => f.write_str(name)?,
+                PathElement::Field(name) => write!(f, ".{name}")?,
+                PathElement::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What happened at the location given by [`Change::path`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind<'a> {
+    /// A pointer field that was null is now set, or a union switched to this variant.
+    Set(Reader<'a>),
+
+    /// A pointer field that was set is now null, or a union switched to this variant and left
+    /// it null. Holds the old value, which for a union is the value of the previous variant.
+    Cleared(Reader<'a>),
+
+    /// A field or list element holds a different value.
+    Changed { old: Reader<'a>, new: Reader<'a> },
+
+    /// A list element was inserted at the index that ends the path.
+    Inserted(Reader<'a>),
+
+    /// The list element at the index that ends the path was removed. Holds the old value.
+    Removed(Reader<'a>),
+}
+
+/// A single difference found by [`diff()`].
+///
+/// List indices in the path refer to the list as it looks after all previous changes in the
+/// same diff have been applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change<'a> {
+    pub path: FieldPath,
+    pub kind: ChangeKind<'a>,
+}
+
+/// Compares two structs of the same type field by field, and returns the changes that turn `a`
+/// into `b`, in the order in which they need to be applied by
+/// [`dynamic_struct::Builder::apply_patch()`].
+///
+/// Nested structs and lists are compared recursively. List elements are matched up by value,
+/// so inserting or removing an element in the middle of a list is reported as such rather than
+/// as a change to every element that follows it.
+pub fn diff<'a>(
+    a: dynamic_struct::Reader<'a>,
+    b: dynamic_struct::Reader<'a>,
+) -> Result<Vec<Change<'a>>> {
+    if a.get_schema().get_proto().get_id() != b.get_schema().get_proto().get_id() {
+        return Err(Error::failed(
+            "cannot diff structs of different types".into(),
+        ));
+    }
+    let mut differ = Differ {
+        path: FieldPath::new(),
+        changes: Vec::new(),
+    };
+    differ.diff_struct(a, b)?;
+    Ok(differ.changes)
+}
+
+// Lists whose differing middle sections have more than this many pairs of elements are not
+// aligned element-by-element, to bound the memory used by the alignment table.
+const MAX_ALIGNMENT_CELLS: usize = 1 << 20;
+
+struct Differ<'a> {
+    path: FieldPath,
+    changes: Vec<Change<'a>>,
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, kind: ChangeKind<'a>) {
+        self.changes.push(Change {
+            path: self.path.clone(),
+            kind,
+        });
+    }
+
+    fn diff_struct(
+        &mut self,
+        a: dynamic_struct::Reader<'a>,
+        b: dynamic_struct::Reader<'a>,
+    ) -> Result<()> {
+        match (a.which()?, b.which()?) {
+            (Some(a_field), Some(b_field)) if a_field.get_index() != b_field.get_index() => {
+                self.path.push_field(b_field.get_proto().get_name()?);
+                if b.has(b_field)? || !b_field.get_type().is_pointer_type() {
+                    self.push(ChangeKind::Set(b.get(b_field)?));
+                } else {
+                    self.push(ChangeKind::Cleared(a.get(a_field)?));
+                }
+                self.path.pop();
+            }
+            (_, Some(field)) => self.diff_field(a, b, field)?,
+            (_, None) => {}
+        }
+        for field in b.get_schema().get_non_union_fields()? {
+            self.diff_field(a, b, field)?;
+        }
+        Ok(())
+    }
+
+    fn diff_field(
+        &mut self,
+        a: dynamic_struct::Reader<'a>,
+        b: dynamic_struct::Reader<'a>,
+        b_field: Field,
+    ) -> Result<()> {
+        let a_field = a.get_schema().get_fields()?.get(b_field.get_index());
+        self.path.push_field(b_field.get_proto().get_name()?);
+        match b_field.get_proto().which()? {
+            field::Group(_) => self.diff_value(a.get(a_field)?, b.get(b_field)?)?,
+            field::Slot(_) => {
+                if b_field.get_type().is_pointer_type() {
+                    match (a.has(a_field)?, b.has(b_field)?) {
+                        (false, false) => {}
+                        (false, true) => self.push(ChangeKind::Set(b.get(b_field)?)),
+                        (true, false) => self.push(ChangeKind::Cleared(a.get(a_field)?)),
+                        (true, true) => self.diff_value(a.get(a_field)?, b.get(b_field)?)?,
+                    }
+                } else {
+                    self.diff_value(a.get(a_field)?, b.get(b_field)?)?;
+                }
+            }
+        }
+        self.path.pop();
+        Ok(())
+    }
+
+    fn diff_value(&mut self, a: Reader<'a>, b: Reader<'a>) -> Result<()> {
+        match (a, b) {
+            (Reader::Struct(a), Reader::Struct(b)) => self.diff_struct(a, b),
+            (Reader::List(a), Reader::List(b)) => self.diff_list(a, b),
+            (a, b) => {
+                if a != b {
+                    self.push(ChangeKind::Changed { old: a, new: b });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn diff_list(
+        &mut self,
+        a: dynamic_list::Reader<'a>,
+        b: dynamic_list::Reader<'a>,
+    ) -> Result<()> {
+        let a = a.iter().collect::<Result<Vec<_>>>()?;
+        let b = b.iter().collect::<Result<Vec<_>>>()?;
+        let a_hashes: Vec<u64> = a.iter().map(hash_of).collect();
+        let b_hashes: Vec<u64> = b.iter().map(hash_of).collect();
+        let same = |i: usize, j: usize| a_hashes[i] == b_hashes[j] && a[i] == b[j];
+
+        let shorter = core::cmp::min(a.len(), b.len());
+        let prefix = (0..shorter).take_while(|&i| same(i, i)).count();
+        let suffix = (0..shorter - prefix)
+            .take_while(|&k| same(a.len() - 1 - k, b.len() - 1 - k))
+            .count();
+        let a_end = a.len() - suffix;
+        let b_end = b.len() - suffix;
+
+        // The stretches of elements between the matched ones.
+        let mut runs = Vec::new();
+        let (mut a_start, mut b_start) = (prefix, prefix);
+        for (i, j) in align(prefix..a_end, prefix..b_end, same) {
+            runs.push((a_start..i, b_start..j));
+            a_start = i + 1;
+            b_start = j + 1;
+        }
+        runs.push((a_start..a_end, b_start..b_end));
+
+        // Within a run, elements are paired up by position and changed in place. Those changes
+        // come first, while every element is still at its old index, so that all the insertions
+        // and removals of the list end up next to each other.
+        for (a_run, b_run) in &runs {
+            for (i, j) in a_run.clone().zip(b_run.clone()) {
+                self.path.push_index(i as u32);
+                self.diff_value(a[i], b[j])?;
+                self.path.pop();
+            }
+        }
+        // Whatever is left over was removed or inserted. By the time a run is reached, the
+        // elements in front of it look like those of `b`.
+        for (a_run, b_run) in runs {
+            let paired = core::cmp::min(a_run.len(), b_run.len());
+            let position = b_run.start + paired;
+            for i in a_run.skip(paired) {
+                self.path.push_index(position as u32);
+                self.push(ChangeKind::Removed(a[i]));
+                self.path.pop();
+            }
+            for (offset, j) in b_run.skip(paired).enumerate() {
+                self.path.push_index((position + offset) as u32);
+                self.push(ChangeKind::Inserted(b[j]));
+                self.path.pop();
+            }
+        }
+        Ok(())
+    }
+}
+
+// Hashes a list element, so that elements can be told apart without comparing them in full.
+fn hash_of(value: &Reader<'_>) -> u64 {
+    let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+// Finds a longest common subsequence of the elements in ranges `a` and `b` of the two lists,
+// where `same(i, j)` tells whether element `i` of the first list equals element `j` of the
+// second, and returns the indices of the matched elements. Ranges of the same length are not
+// aligned at all, so that elements that changed in place are reported as changes rather than as
+// removals followed by insertions.
+fn align(
+    a: Range<usize>,
+    b: Range<usize>,
+    same: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    if a.len() == b.len() || a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_ALIGNMENT_CELLS {
+        return Vec::new();
+    }
+
+    // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..],
+    // relative to the starts of the ranges.
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if same(a.start + i, b.start + j) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                core::cmp::max(lengths[(i + 1) * width + j], lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(a.start + i, b.start + j) {
+            result.push((a.start + i, b.start + j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+impl<'a> dynamic_struct::Builder<'a> {
+    /// Applies `changes`, as produced by [`diff()`], in order. Applying `diff(a, b)` to a copy
+    /// of `a` makes it equal to `b`.
+    pub fn apply_patch(&mut self, changes: &[Change<'_>]) -> Result<()> {
+        let mut rest = changes;
+        while let Some(change) = rest.first() {
+            // Consecutive insertions into and removals from the same list are applied together,
+            // so that the list is rebuilt only once.
+            let splices = rest
+                .iter()
+                .take_while(|other| is_splice(other) && list_path(other) == list_path(change))
+                .count();
+            if splices > 0 {
+                apply_splices(self.reborrow(), &rest[..splices])?;
+                rest = &rest[splices..];
+            } else {
+                apply_change(self.reborrow(), change)?;
+                rest = &rest[1..];
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_splice(change: &Change<'_>) -> bool {
+    matches!(
+        change.kind,
+        ChangeKind::Inserted(_) | ChangeKind::Removed(_)
+    )
+}
+
+// The path of the list that a list element change refers to.
+fn list_path<'c>(change: &'c Change<'_>) -> &'c [PathElement] {
+    let elements = change.path.elements();
+    &elements[..elements.len().saturating_sub(1)]
+}
+
+fn path_mismatch(path: &FieldPath) -> Error {
+    Error::failed(format!("path {path} does not match the struct's schema"))
+}
+
+fn apply_change(root: dynamic_struct::Builder<'_>, change: &Change<'_>) -> Result<()> {
+    let path = &change.path;
+    let Some((last, parents)) = path.elements().split_last() else {
+        return Err(Error::failed(
+            "cannot apply a change with an empty path".into(),
+        ));
+    };
+    match &change.kind {
+        ChangeKind::Inserted(_) | ChangeKind::Removed(_) => {
+            apply_splices(root, core::slice::from_ref(change))
+        }
+        ChangeKind::Set(value) | ChangeKind::Changed { new: value, .. } => {
+            match (navigate(root.into(), parents, path)?, last) {
+                (Builder::Struct(mut parent), PathElement::Field(name)) => {
+                    parent.clear_inactive_variant(parent.schema.get_field_by_name(name)?)?;
+                    parent.set_named(name, *value)
+                }
+                (Builder::List(mut parent), PathElement::Index(index)) if *index < parent.len() => {
+                    parent.set(*index, *value)
+                }
+                _ => Err(path_mismatch(path)),
+            }
+        }
+        ChangeKind::Cleared(_) => match (navigate(root.into(), parents, path)?, last) {
+            (Builder::Struct(mut parent), PathElement::Field(name)) => {
+                parent.clear_inactive_variant(parent.schema.get_field_by_name(name)?)?;
+                parent.clear_named(name)
+            }
+            _ => Err(path_mismatch(path)),
+        },
+    }
+}
+
+// Applies `changes`, which are insertions into and removals from a single list.
+fn apply_splices(root: dynamic_struct::Builder<'_>, changes: &[Change<'_>]) -> Result<()> {
+    let path = &changes[0].path;
+    let Some((last, parents)) = path.elements().split_last() else {
+        return Err(Error::failed(
+            "cannot apply a change with an empty path".into(),
+        ));
+    };
+    let (PathElement::Index(_), Some((list, container))) = (last, parents.split_last()) else {
+        return Err(Error::failed(format!(
+            "path {path} of a list insertion or removal must end in a list index"
+        )));
+    };
+    let (pointer, element_type) = match (navigate(root.into(), container, path)?, list) {
+        (Builder::Struct(mut container), PathElement::Field(name)) => {
+            let field = container.schema.get_field_by_name(name)?;
+            let (field::Slot(slot), TypeVariant::List(element_type)) =
+                (field.get_proto().which()?, field.get_type().which())
+            else {
+                return Err(path_mismatch(path));
+            };
+            container.set_in_union(field)?;
+            (
+                container
+                    .builder
+                    .get_pointer_field(slot.get_offset() as usize),
+                element_type,
+            )
+        }
+        (Builder::List(container), PathElement::Index(list_index)) => {
+            let TypeVariant::List(element_type) = container.element_type.which() else {
+                return Err(path_mismatch(path));
+            };
+            if *list_index >= container.len() {
+                return Err(path_mismatch(path));
+            }
+            (
+                container.builder.get_pointer_element(*list_index),
+                element_type,
+            )
+        }
+        _ => return Err(path_mismatch(path)),
+    };
+    splice_list(pointer, element_type, changes)
+}
+
+fn navigate<'b>(
+    mut value: Builder<'b>,
+    elements: &[PathElement],
+    path: &FieldPath,
+) -> Result<Builder<'b>> {
+    for element in elements {
+        value = match (value, element) {
+            (Builder::Struct(parent), PathElement::Field(name)) => parent.get_named(name)?,
+            (Builder::List(parent), PathElement::Index(index)) if *index < parent.len() => {
+                parent.get(*index)?
+            }
+            _ => return Err(path_mismatch(path)),
+        };
+    }
+    Ok(value)
+}
+
+// Where an element of a spliced list comes from.
+enum Element<'v> {
+    Old(u32),
+    Inserted(Reader<'v>),
+}
+
+// Replaces the list at `pointer` with a copy that has `changes`, which are insertions into and
+// removals from that list, applied in order.
+fn splice_list(
+    pointer: PointerBuilder<'_>,
+    element_type: Type,
+    changes: &[Change<'_>],
+) -> Result<()> {
+    let old_len = pointer
+        .as_reader()
+        .get_list(element_type.expected_element_size(), None)?
+        .len();
+    let mut elements: Vec<Element> = (0..old_len).map(Element::Old).collect();
+    for change in changes {
+        let len = elements.len();
+        let index = match change.path.elements().last() {
+            Some(PathElement::Index(index)) => *index as usize,
+            _ => return Err(path_mismatch(&change.path)),
+        };
+        match &change.kind {
+            ChangeKind::Inserted(value) if index <= len => {
+                elements.insert(index, Element::Inserted(*value))
+            }
+            ChangeKind::Removed(_) if index < len => {
+                elements.remove(index);
+            }
+            _ => {
+                return Err(Error::failed(format!(
+                    "list index {index} is out of bounds for a list of length {len}"
+                )))
+            }
+        }
+    }
+    dynamic_list::rebuild(
+        pointer,
+        element_type,
+        elements.len() as u32,
+        |old, mut new| {
+            for (new_index, element) in elements.iter().enumerate() {
+                let value = match element {
+                    Element::Old(old_index) => old.get(*old_index)?,
+                    Element::Inserted(value) => *value,
+                };
+                new.set(new_index as u32, value)?;
+            }
+            Ok(())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, ChangeKind, PathElement};
+    use crate::schema_capnp::node;
+    use crate::{dynamic_struct, dynamic_value, message};
+
+    fn dynamic(builder: &message::Builder<message::HeapAllocator>) -> dynamic_struct::Reader<'_> {
+        let node = builder.get_root_as_reader::<node::Reader>().unwrap();
+        dynamic_value::Reader::from(node).downcast()
+    }
+
+    fn set_nested_nodes(mut node: node::Builder<'_>, names: &[&str]) {
+        let mut nested = node.reborrow().init_nested_nodes(names.len() as u32);
+        for (idx, name) in names.iter().enumerate() {
+            let mut entry = nested.reborrow().get(idx as u32);
+            entry.set_name(name);
+            entry.set_id(idx as u64);
+        }
+    }
+
+    // Applies diff(a, b) to a copy of a and checks that the result equals b.
+    fn check_round_trip(
+        a: &message::Builder<message::HeapAllocator>,
+        b: &message::Builder<message::HeapAllocator>,
+    ) {
+        let changes = diff(dynamic(a), dynamic(b)).unwrap();
+        let mut patched = message::Builder::new_default();
+        patched
+            .set_root(a.get_root_as_reader::<node::Reader>().unwrap())
+            .unwrap();
+        {
+            let root = patched.get_root::<node::Builder>().unwrap();
+            let mut root: dynamic_struct::Builder = dynamic_value::Builder::from(root).downcast();
+            root.apply_patch(&changes).unwrap();
+        }
+        assert_eq!(
+            dynamic_value::Reader::from(dynamic(&patched)),
+            dynamic_value::Reader::from(dynamic(b))
+        );
+    }
+
+    #[test]
+    fn equal_structs_have_no_changes() {
+        let mut a = message::Builder::new_default();
+        let mut node = a.init_root::<node::Builder>();
+        node.set_id(7);
+        node.set_display_name("foo.capnp:Foo");
+        let mut b = message::Builder::new_default();
+        b.set_root(a.get_root_as_reader::<node::Reader>().unwrap())
+            .unwrap();
+        assert!(diff(dynamic(&a), dynamic(&b)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn scalar_and_pointer_fields() {
+        let mut a = message::Builder::new_default();
+        let mut node = a.init_root::<node::Builder>();
+        node.set_id(1);
+        node.set_display_name("foo.capnp:Foo");
+
+        let mut b = message::Builder::new_default();
+        let mut node = b.init_root::<node::Builder>();
+        node.set_id(2);
+        set_nested_nodes(node, &["Bar"]);
+
+        let changes = diff(dynamic(&a), dynamic(&b)).unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.path.to_string(), &change.kind))
+            .collect();
+        assert_eq!(summary.len(), 3);
+        assert_eq!(summary[0].0, "id");
+        assert_eq!(
+            summary[0].1,
+            &ChangeKind::Changed {
+                old: 1u64.into(),
+                new: 2u64.into()
+            }
+        );
+        assert_eq!(summary[1].0, "displayName");
+        assert!(matches!(summary[1].1, ChangeKind::Cleared(_)));
+        assert_eq!(summary[2].0, "nestedNodes");
+        assert!(matches!(summary[2].1, ChangeKind::Set(_)));
+
+        check_round_trip(&a, &b);
+    }
+
+    #[test]
+    fn list_insertions_and_removals() {
+        let mut a = message::Builder::new_default();
+        set_nested_nodes(a.init_root(), &["A", "B", "C", "D"]);
+        let mut b = message::Builder::new_default();
+        set_nested_nodes(b.init_root(), &["A", "C", "D", "E", "F"]);
+        // Ids are assigned by position, so every element after the first has moved.
+        {
+            let root = a.get_root::<node::Builder>().unwrap();
+            let mut nested = root.get_nested_nodes().unwrap();
+            nested.reborrow().get(2).set_id(1);
+            nested.reborrow().get(3).set_id(2);
+        }
+
+        let changes = diff(dynamic(&a), dynamic(&b)).unwrap();
+        let paths: Vec<_> = changes.iter().map(|c| c.path.to_string()).collect();
+        assert_eq!(
+            paths,
+            ["nestedNodes[1]", "nestedNodes[3]", "nestedNodes[4]"]
+        );
+        assert!(matches!(changes[0].kind, ChangeKind::Removed(_)));
+        assert!(matches!(changes[1].kind, ChangeKind::Inserted(_)));
+        assert!(matches!(changes[2].kind, ChangeKind::Inserted(_)));
+        assert_eq!(
+            changes[0].path.elements(),
+            [
+                PathElement::Field("nestedNodes".into()),
+                PathElement::Index(1)
+            ]
+        );
+
+        check_round_trip(&a, &b);
+        check_round_trip(&b, &a);
+    }
+
+    #[test]
+    fn changed_list_elements_recurse() {
+        let mut a = message::Builder::new_default();
+        set_nested_nodes(a.init_root(), &["A", "B", "C"]);
+        let mut b = message::Builder::new_default();
+        set_nested_nodes(b.init_root(), &["A", "X", "C"]);
+
+        let changes = diff(dynamic(&a), dynamic(&b)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path.to_string(), "nestedNodes[1].name");
+        check_round_trip(&a, &b);
+    }
+
+    #[test]
+    fn list_is_rebuilt_once_per_patch() {
+        fn words(builder: &message::Builder<message::HeapAllocator>) -> usize {
+            let segments = builder.get_segments_for_output();
+            segments.iter().map(|segment| segment.len()).sum::<usize>() / 8
+        }
+
+        let mut a = message::Builder::new_default();
+        set_nested_nodes(a.init_root(), &["A", "B", "C", "P", "D", "E"]);
+        let mut b = message::Builder::new_default();
+        set_nested_nodes(b.init_root(), &["A", "C", "Q", "R", "D", "E", "S"]);
+        {
+            let root = b.get_root::<node::Builder>().unwrap();
+            let mut nested = root.get_nested_nodes().unwrap();
+            for (idx, id) in [0, 2, 3, 8, 4, 5, 9].into_iter().enumerate() {
+                nested.reborrow().get(idx as u32).set_id(id);
+            }
+        }
+
+        // The in-place change comes before the insertions and removals, which are applied
+        // together.
+        let changes = diff(dynamic(&a), dynamic(&b)).unwrap();
+        let paths: Vec<_> = changes.iter().map(|c| c.path.to_string()).collect();
+        assert_eq!(
+            paths,
+            [
+                "nestedNodes[3].name",
+                "nestedNodes[1]",
+                "nestedNodes[3]",
+                "nestedNodes[6]"
+            ]
+        );
+
+        let mut patched = message::Builder::new_default();
+        patched
+            .set_root(a.get_root_as_reader::<node::Reader>().unwrap())
+            .unwrap();
+        let before = words(&patched);
+        {
+            let root = patched.get_root::<node::Builder>().unwrap();
+            let mut root: dynamic_struct::Builder = dynamic_value::Builder::from(root).downcast();
+            root.apply_patch(&changes).unwrap();
+        }
+        assert_eq!(
+            dynamic_value::Reader::from(dynamic(&patched)),
+            dynamic_value::Reader::from(dynamic(&b))
+        );
+        // Every rebuild of the list leaves the old copy behind, so this would not hold if the
+        // list was rebuilt for each change.
+        assert!(words(&patched) - before < words(&b));
+    }
+
+    #[test]
+    fn union_variants() {
+        let mut a = message::Builder::new_default();
+        {
+            let node = a.init_root::<node::Builder>();
+            let mut st = node.init_struct();
+            st.set_data_word_count(3);
+            st.set_is_group(true);
+        }
+        let mut b = message::Builder::new_default();
+        {
+            let node = b.init_root::<node::Builder>();
+            let mut st = node.init_struct();
+            st.set_data_word_count(4);
+            st.set_is_group(true);
+        }
+        let mut c = message::Builder::new_default();
+        {
+            let node = c.init_root::<node::Builder>();
+            let mut enumerants = node.init_enum().init_enumerants(1);
+            enumerants.reborrow().get(0).set_name("foo");
+        }
+
+        let changes = diff(dynamic(&a), dynamic(&b)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path.to_string(), "struct.dataWordCount");
+
+        let changes = diff(dynamic(&a), dynamic(&c)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path.to_string(), "enum");
+        assert!(matches!(changes[0].kind, ChangeKind::Set(_)));
+
+        check_round_trip(&a, &b);
+        check_round_trip(&a, &c);
+        check_round_trip(&c, &a);
+    }
+
+    #[test]
+    fn different_types_is_error() {
+        let mut a = message::Builder::new_default();
+        a.init_root::<node::Builder>();
+        let mut b = message::Builder::new_default();
+        b.init_root::<crate::schema_capnp::field::Builder>();
+        let b: dynamic_struct::Reader = dynamic_value::Reader::from(
+            b.get_root_as_reader::<crate::schema_capnp::field::Reader>()
+                .unwrap(),
+        )
+        .downcast();
+        assert!(diff(dynamic(&a), b).is_err());
+    }
+}
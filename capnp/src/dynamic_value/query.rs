@@ -0,0 +1,511 @@
+//! Selecting values within a struct or list by path, such as
+//! `people[*].phones[?type==mobile].number`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::dynamic_value::Reader;
+use crate::introspect::{Type, TypeVariant};
+use crate::schema::{Field, StructSchema};
+use crate::schema_capnp::field;
+use crate::{dynamic_struct, Error, Result};
+
+impl<'a> Reader<'a> {
+    /// Finds the values within this struct or list that are selected by `query`.
+    ///
+    /// A query is a sequence of steps, each applied to every value selected by the steps before
+    /// it:
+    ///
+    /// * `name` or `.name` selects a field of a struct or group. A union member is selected only
+    ///   if it is the active one.
+    /// * `[3]` selects an element of a list, if the list is long enough.
+    /// * `[*]` selects every element of a list.
+    /// * `[?path==literal]` and `[?path!=literal]` select the elements of a list of structs
+    ///   whose field at the dotted `path` does or does not equal `literal`. The literal is a
+    ///   number, `true` or `false`, an enumerant name, or a string, which may be quoted with `"`
+    ///   or `'`. An element never matches if `path` passes through a union member that is not
+    ///   the active one.
+    ///
+    /// An empty query selects the value itself. The query is checked against the schema
+    /// up front, so misspelled field names are reported even if there is nothing to select.
+    ///
+    /// ```
+    /// use capnp::{dynamic_value, message, schema_capnp::node};
+    ///
+    /// let mut message = message::Builder::new_default();
+    /// let mut node = message.init_root::<node::Builder>();
+    /// let mut nested = node.reborrow().init_nested_nodes(3);
+    /// for (idx, name) in ["a", "b", "c"].into_iter().enumerate() {
+    ///     nested.reborrow().get(idx as u32).set_name(name);
+    ///     nested.reborrow().get(idx as u32).set_id(idx as u64);
+    /// }
+    ///
+    /// let root = dynamic_value::Reader::from(node.into_reader());
+    /// let names = root
+    ///     .query("nestedNodes[?id!=1].name")
+    ///     .unwrap()
+    ///     .map(|name| name.unwrap().downcast::<capnp::text::Reader>().to_string())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(names, ["a", "c"]);
+    /// ```
+    pub fn query(&self, query: &str) -> Result<Query<'a>> {
+        let ty = match self {
+            Reader::Struct(s) => Some(TypeVariant::Struct(s.get_schema().raw).into()),
+            Reader::List(l) => Some(Type::list_of(l.element_type())),
+            _ => None,
+        };
+        let steps = Parser { query, position: 0 }
+            .parse(ty)
+            .map_err(|reason| Error::failed(format!("invalid query `{query}`: {reason}")))?;
+        Ok(Query {
+            steps,
+            pending: alloc::vec![(*self, 0)],
+        })
+    }
+}
+
+/// An iterator over the values selected by [`Reader::query()`], in the order in which they
+/// appear in the message.
+pub struct Query<'a> {
+    steps: Vec<Step>,
+
+    /// Values that remain to be visited, along with the index of the next step to apply to
+    /// each one. The top of the stack is visited first.
+    pending: Vec<(Reader<'a>, usize)>,
+}
+
+enum Step {
+    Field(Field),
+    Index(u32),
+    All,
+    Filter(Condition),
+}
+
+struct Condition {
+    path: Vec<Field>,
+    equal: bool,
+    literal: Literal,
+}
+
+enum Literal {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Enumerant(u16),
+    Text(String),
+}
+
+impl<'a> Iterator for Query<'a> {
+    type Item = Result<Reader<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((value, step)) = self.pending.pop() {
+            if step == self.steps.len() {
+                return Some(Ok(value));
+            }
+            if let Err(e) = self.apply(value, step) {
+                self.pending.clear();
+                return Some(Err(e));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Query<'a> {
+    /// Applies `steps[step]` to `value`, pushing whatever it selects onto `pending`.
+    fn apply(&mut self, value: Reader<'a>, step: usize) -> Result<()> {
+        match (&self.steps[step], value) {
+            (Step::Field(field), Reader::Struct(s)) => {
+                if let Some(value) = get_active(s, *field)? {
+                    self.pending.push((value, step + 1));
+                }
+            }
+            (Step::Index(index), Reader::List(l)) => {
+                if *index < l.len() {
+                    self.pending.push((l.get(*index)?, step + 1));
+                }
+            }
+            (Step::All, Reader::List(l)) => {
+                for index in (0..l.len()).rev() {
+                    self.pending.push((l.get(index)?, step + 1));
+                }
+            }
+            (Step::Filter(condition), Reader::List(l)) => {
+                let mut matches = Vec::new();
+                for element in l.iter() {
+                    let element = element?;
+                    if condition.matches(element)? {
+                        matches.push((element, step + 1));
+                    }
+                }
+                self.pending.extend(matches.into_iter().rev());
+            }
+            _ => {
+                return Err(Error::failed(
+                    "query does not match the value's type".into(),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Gets a field of a struct, unless it is a union member other than the active one.
+fn get_active<'a>(s: dynamic_struct::Reader<'a>, field: Field) -> Result<Option<Reader<'a>>> {
+    if field.get_proto().get_discriminant_value() != field::NO_DISCRIMINANT {
+        match s.which()? {
+            Some(active) if active.get_index() == field.get_index() => {}
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(s.get(field)?))
+}
+
+impl Condition {
+    fn matches(&self, element: Reader<'_>) -> Result<bool> {
+        let mut value = element;
+        for field in &self.path {
+            let Reader::Struct(s) = value else {
+                return Err(Error::failed(
+                    "query does not match the value's type".into(),
+                ));
+            };
+            match get_active(s, *field)? {
+                Some(v) => value = v,
+                None => return Ok(false),
+            }
+        }
+        let equal = match (&self.literal, value) {
+            (Literal::Bool(l), Reader::Bool(v)) => *l == v,
+            (Literal::Int(l), Reader::Int8(v)) => *l == i64::from(v),
+            (Literal::Int(l), Reader::Int16(v)) => *l == i64::from(v),
+            (Literal::Int(l), Reader::Int32(v)) => *l == i64::from(v),
+            (Literal::Int(l), Reader::Int64(v)) => *l == v,
+            (Literal::UInt(l), Reader::UInt8(v)) => *l == u64::from(v),
+            (Literal::UInt(l), Reader::UInt16(v)) => *l == u64::from(v),
+            (Literal::UInt(l), Reader::UInt32(v)) => *l == u64::from(v),
+            (Literal::UInt(l), Reader::UInt64(v)) => *l == v,
+            (Literal::Float(l), Reader::Float32(v)) => *l == f64::from(v),
+            (Literal::Float(l), Reader::Float64(v)) => *l == v,
+            (Literal::Enumerant(l), Reader::Enum(v)) => *l == v.get_value(),
+            (Literal::Text(l), Reader::Text(v)) => v == l,
+            _ => {
+                return Err(Error::failed(
+                    "query does not match the value's type".into(),
+                ))
+            }
+        };
+        Ok(equal == self.equal)
+    }
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    position: usize,
+}
+
+impl<'q> Parser<'q> {
+    /// Parses the query, checking each step against the type of the values that it will be
+    /// applied to. `ty` is `None` if the root value is neither a struct nor a list.
+    fn parse(mut self, mut ty: Option<Type>) -> core::result::Result<Vec<Step>, String> {
+        let mut steps = Vec::new();
+        while let Some(c) = self.peek() {
+            let step = if c == '[' {
+                self.position += 1;
+                let element_type = match ty.map(|t| t.which()) {
+                    Some(TypeVariant::List(element_type)) => element_type,
+                    _ => return Err(format!("{} is not a list", self.location())),
+                };
+                let step = match self.peek() {
+                    Some('*') => {
+                        self.position += 1;
+                        Step::All
+                    }
+                    Some('?') => {
+                        self.position += 1;
+                        Step::Filter(self.parse_condition(element_type)?)
+                    }
+                    _ => {
+                        let digits = self.take_while(|c| c.is_ascii_digit());
+                        Step::Index(
+                            digits
+                                .parse()
+                                .map_err(|_| format!("expected an index at {}", self.position))?,
+                        )
+                    }
+                };
+                self.expect(']')?;
+                ty = Some(element_type);
+                step
+            } else {
+                if c == '.' {
+                    self.position += 1;
+                }
+                let field = self.parse_field(ty)?;
+                ty = Some(field.get_type());
+                Step::Field(field)
+            };
+            steps.push(step);
+        }
+        Ok(steps)
+    }
+
+    fn parse_condition(&mut self, ty: Type) -> core::result::Result<Condition, String> {
+        let mut path = Vec::new();
+        let mut ty = Some(ty);
+        loop {
+            let field = self.parse_field(ty)?;
+            ty = Some(field.get_type());
+            path.push(field);
+            self.skip_spaces();
+            if self.peek() == Some('.') {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        let equal = if self.rest().starts_with("==") {
+            true
+        } else if self.rest().starts_with("!=") {
+            false
+        } else {
+            return Err(format!("expected `==` or `!=` at {}", self.position));
+        };
+        self.position += 2;
+        self.skip_spaces();
+
+        let text = match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.position += 1;
+                let text = self.take_while(|c| c != quote).to_string();
+                self.expect(quote)?;
+                self.skip_spaces();
+                text
+            }
+            _ => self.take_while(|c| c != ']').trim_end().to_string(),
+        };
+        let literal = match ty.map(|t| t.which()) {
+            Some(TypeVariant::Bool) => match &*text {
+                "true" => Literal::Bool(true),
+                "false" => Literal::Bool(false),
+                _ => return Err(format!("expected `true` or `false`, found `{text}`")),
+            },
+            Some(
+                TypeVariant::Int8 | TypeVariant::Int16 | TypeVariant::Int32 | TypeVariant::Int64,
+            ) => Literal::Int(
+                text.parse()
+                    .map_err(|_| format!("expected an integer, found `{text}`"))?,
+            ),
+            Some(
+                TypeVariant::UInt8
+                | TypeVariant::UInt16
+                | TypeVariant::UInt32
+                | TypeVariant::UInt64,
+            ) => Literal::UInt(
+                text.parse()
+                    .map_err(|_| format!("expected an unsigned integer, found `{text}`"))?,
+            ),
+            Some(TypeVariant::Float32 | TypeVariant::Float64) => Literal::Float(
+                text.parse()
+                    .map_err(|_| format!("expected a number, found `{text}`"))?,
+            ),
+            Some(TypeVariant::Text) => Literal::Text(text),
+            Some(TypeVariant::Enum(raw)) => {
+                let schema = crate::schema::EnumSchema::from(raw);
+                let enumerants = schema.get_enumerants().map_err(|e| e.to_string())?;
+                let mut found = None;
+                for enumerant in enumerants {
+                    if enumerant
+                        .get_proto()
+                        .get_name()
+                        .map_err(|e| e.to_string())?
+                        == text
+                    {
+                        found = Some(enumerant.get_ordinal());
+                    }
+                }
+                Literal::Enumerant(found.ok_or_else(|| format!("no enumerant named `{text}`"))?)
+            }
+            _ => return Err("only numbers, booleans, enums, and text can be compared".into()),
+        };
+        Ok(Condition {
+            path,
+            equal,
+            literal,
+        })
+    }
+
+    /// Parses a field name and looks it up in `ty`, which must be a struct.
+    fn parse_field(&mut self, ty: Option<Type>) -> core::result::Result<Field, String> {
+        self.skip_spaces();
+        let location = self.location();
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if name.is_empty() {
+            return Err(format!("expected a field name at {}", self.position));
+        }
+        let Some(TypeVariant::Struct(raw)) = ty.map(|t| t.which()) else {
+            return Err(format!("{location} is not a struct"));
+        };
+        StructSchema::from(raw)
+            .find_field_by_name(name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("no field named `{name}` in {location}"))
+    }
+
+    /// Describes the value reached by the part of the query before the current position.
+    fn location(&self) -> String {
+        let before = self.query[..self.position].trim_end_matches(['[', '.', '?', ' ']);
+        if before.is_empty() {
+            "the root value".into()
+        } else {
+            format!("`{before}`")
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'q str {
+        &self.query[self.position..]
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'q str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn skip_spaces(&mut self) {
+        self.take_while(|c| c == ' ');
+    }
+
+    fn expect(&mut self, c: char) -> core::result::Result<(), String> {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            Ok(())
+        } else {
+            Err(format!("expected `{c}` at {}", self.position))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use crate::schema_capnp::{code_generator_request, ElementSize};
+    use crate::{dynamic_value, message};
+
+    fn request() -> message::Builder<message::HeapAllocator> {
+        let mut message = message::Builder::new_default();
+        let request = message.init_root::<code_generator_request::Builder<'_>>();
+        let mut nodes = request.init_nodes(3);
+        {
+            let mut node = nodes.reborrow().get(0);
+            node.set_id(1);
+            node.set_display_name("foo.capnp:Foo");
+            node.reborrow()
+                .init_nested_nodes(2)
+                .get(1)
+                .set_name("Inner");
+            node.init_struct()
+                .set_preferred_list_encoding(ElementSize::InlineComposite);
+        }
+        {
+            let mut node = nodes.reborrow().get(1);
+            node.set_id(2);
+            node.set_display_name("foo.capnp:Bar");
+            node.init_enum();
+        }
+        {
+            let mut node = nodes.reborrow().get(2);
+            node.set_id(3);
+            node.set_display_name("foo.capnp:Baz baz");
+            node.init_struct()
+                .set_preferred_list_encoding(ElementSize::Pointer);
+        }
+        message
+    }
+
+    fn run(message: &message::Builder<message::HeapAllocator>, query: &str) -> Vec<String> {
+        let root: code_generator_request::Reader<'_> = message.get_root_as_reader().unwrap();
+        dynamic_value::Reader::from(root)
+            .query(query)
+            .unwrap()
+            .map(|value| format!("{:?}", value.unwrap()))
+            .collect()
+    }
+
+    fn error(message: &message::Builder<message::HeapAllocator>, query: &str) -> String {
+        let root: code_generator_request::Reader<'_> = message.get_root_as_reader().unwrap();
+        match dynamic_value::Reader::from(root).query(query) {
+            Ok(_) => panic!("query `{query}` should have failed"),
+            Err(e) => e.description,
+        }
+    }
+
+    #[test]
+    fn fields_and_indices() {
+        let message = request();
+        assert_eq!(run(&message, "nodes[*].id"), ["1", "2", "3"]);
+        assert_eq!(
+            run(&message, ".nodes[1].displayName"),
+            ["\"foo.capnp:Bar\""]
+        );
+        assert_eq!(run(&message, "nodes[0].nestedNodes[1].name"), ["\"Inner\""]);
+        assert!(run(&message, "nodes[5].id").is_empty());
+        assert_eq!(run(&message, "").len(), 1);
+
+        // Only the active member of a union is selected.
+        assert_eq!(run(&message, "nodes[*].struct.dataWordCount"), ["0", "0"]);
+    }
+
+    #[test]
+    fn filters() {
+        let message = request();
+        assert_eq!(run(&message, "nodes[?id!=2].id"), ["1", "3"]);
+        assert_eq!(
+            run(
+                &message,
+                "nodes[?struct.preferredListEncoding==inlineComposite].id"
+            ),
+            ["1"]
+        );
+        // The enum node has no `struct` group, so it matches neither condition.
+        assert_eq!(
+            run(
+                &message,
+                "nodes[?struct.preferredListEncoding!=inlineComposite].id"
+            ),
+            ["3"]
+        );
+        assert_eq!(
+            run(&message, "nodes[?displayName == 'foo.capnp:Baz baz'].id"),
+            ["3"]
+        );
+        assert_eq!(run(&message, "nodes[?displayName==\"nope\"].id").len(), 0);
+    }
+
+    #[test]
+    fn checked_against_schema() {
+        let message = request();
+        assert_eq!(
+            error(&message, "nodes[*].nmae"),
+            "invalid query `nodes[*].nmae`: no field named `nmae` in `nodes[*]`"
+        );
+        assert!(error(&message, "nodes.id").contains("`nodes` is not a struct"));
+        assert!(error(&message, "nodes[0][1]").contains("`nodes[0]` is not a list"));
+        assert!(error(&message, "nodes[?id==x]").contains("expected an unsigned integer"));
+        assert!(error(&message, "nodes[?struct.preferredListEncoding==big]")
+            .contains("no enumerant named `big`"));
+        assert!(error(&message, "nodes[0").contains("expected `]`"));
+        assert!(error(&message, "nodes[?nestedNodes==x]").contains("can be compared"));
+    }
+}
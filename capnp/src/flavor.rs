@@ -0,0 +1,48 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The few items in which the single-threaded capability types differ from the thread-safe
+//! ones in `capnp::sync`, which has its own version of this module. The modules that
+//! define those types are compiled once for each, and refer to these items by relative path.
+
+use alloc::boxed::Box;
+use core::future::Future;
+use core::pin::Pin;
+
+/// Implemented by every type. The thread-safe version, `capnp::sync::capability::MaybeSend`,
+/// is `Send`.
+///
+/// Hooks and servers are bounded by this trait, so that the code defining them, including
+/// generated code, works with both versions of the capability types.
+pub trait MaybeSend {}
+
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Implemented by every type. The thread-safe version, `capnp::sync::capability::MaybeSync`,
+/// is `Sync`.
+pub trait MaybeSync {}
+
+impl<T: ?Sized> MaybeSync for T {}
+
+pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + 'static>>;
+
+/// Holds servers generated with `async_servers` enabled.
+pub use alloc::rc::Rc;
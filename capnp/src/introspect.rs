@@ -63,7 +63,7 @@ impl Type {
                 BaseType::Enum(re) => TypeVariant::Enum(re),
                 BaseType::Struct(rs) => TypeVariant::Struct(rs),
                 BaseType::AnyPointer => TypeVariant::AnyPointer,
-                BaseType::Capability => TypeVariant::Capability,
+                BaseType::Capability(ri) => TypeVariant::Capability(ri),
             }
         }
     }
@@ -81,9 +81,10 @@ impl Type {
                 BaseType::Int16 | BaseType::UInt16 | BaseType::Enum(_) => ElementSize::TwoBytes,
                 BaseType::Int32 | BaseType::UInt32 | BaseType::Float32 => ElementSize::FourBytes,
                 BaseType::Int64 | BaseType::UInt64 | BaseType::Float64 => ElementSize::EightBytes,
-                BaseType::Text | BaseType::Data | BaseType::AnyPointer | BaseType::Capability => {
-                    ElementSize::Pointer
-                }
+                BaseType::Text
+                | BaseType::Data
+                | BaseType::AnyPointer
+                | BaseType::Capability(_) => ElementSize::Pointer,
                 BaseType::Struct(_) => ElementSize::InlineComposite,
             }
         }
@@ -99,7 +100,7 @@ impl Type {
                 | BaseType::Data
                 | BaseType::AnyPointer
                 | BaseType::Struct(_)
-                | BaseType::Capability => true,
+                | BaseType::Capability(_) => true,
                 _ => false,
             }
         }
@@ -126,7 +127,7 @@ pub enum TypeVariant {
     Data,
     Struct(RawBrandedStructSchema),
     AnyPointer,
-    Capability,
+    Capability(RawInterfaceSchema),
     Enum(RawEnumSchema),
     List(Type),
 }
@@ -150,7 +151,7 @@ impl From<TypeVariant> for Type {
             TypeVariant::Data => Type::new_base(BaseType::Data),
             TypeVariant::Struct(rbs) => Type::new_base(BaseType::Struct(rbs)),
             TypeVariant::AnyPointer => Type::new_base(BaseType::AnyPointer),
-            TypeVariant::Capability => Type::new_base(BaseType::Capability),
+            TypeVariant::Capability(ri) => Type::new_base(BaseType::Capability(ri)),
             TypeVariant::Enum(es) => Type::new_base(BaseType::Enum(es)),
             TypeVariant::List(list) => Type::list_of(list),
         }
@@ -176,7 +177,7 @@ enum BaseType {
     Data,
     Struct(RawBrandedStructSchema),
     AnyPointer,
-    Capability,
+    Capability(RawInterfaceSchema),
     Enum(RawEnumSchema),
 }
 
@@ -233,6 +234,27 @@ pub struct RawBrandedStructSchema {
     pub annotation_types: fn(Option<u16>, u32) -> Type,
 }
 
+impl RawBrandedStructSchema {
+    /// Gets the type of the field with the given index (not ordinal).
+    pub fn field_type(&self, index: u16) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::field_type(self, index) {
+            return ty;
+        }
+        (self.field_types)(index)
+    }
+
+    /// Gets the type of the value held by an annotation of the struct
+    /// (`child_index == None`) or of one of its fields.
+    pub fn annotation_type(&self, child_index: Option<u16>, index: u32) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::struct_annotation_type(self, child_index, index) {
+            return ty;
+        }
+        (self.annotation_types)(child_index, index)
+    }
+}
+
 impl core::cmp::PartialEq for RawBrandedStructSchema {
     fn eq(&self, other: &Self) -> bool {
         core::ptr::eq(self.generic, other.generic) && self.field_types == other.field_types
@@ -267,6 +289,18 @@ pub struct RawEnumSchema {
     pub annotation_types: fn(Option<u16>, u32) -> Type,
 }
 
+impl RawEnumSchema {
+    /// Gets the type of the value held by an annotation of the enum
+    /// (`child_index == None`) or of one of its enumerants.
+    pub fn annotation_type(&self, child_index: Option<u16>, index: u32) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::enum_annotation_type(self, child_index, index) {
+            return ty;
+        }
+        (self.annotation_types)(child_index, index)
+    }
+}
+
 impl core::cmp::PartialEq for RawEnumSchema {
     fn eq(&self, other: &Self) -> bool {
         ::core::ptr::eq(self.encoded_node, other.encoded_node)
@@ -280,3 +314,110 @@ impl core::fmt::Debug for RawEnumSchema {
         write!(f, "RawEnumSchema({:?})", self.encoded_node as *const _)
     }
 }
+
+/// Type information that gets included in the generated code for every
+/// user-defined Cap'n Proto interface.
+/// To use one of this, you will usually want to convert it to a `schema::InterfaceSchema`,
+/// which can be done via `into()`.
+#[derive(Clone, Copy)]
+pub struct RawInterfaceSchema {
+    /// The Node (as defined in schema.capnp), as a single segment message.
+    pub encoded_node: &'static [crate::Word],
+
+    /// Map from method index to the Type of the method's parameter struct.
+    pub param_types: fn(u16) -> Type,
+
+    /// Map from method index to the Type of the method's result struct.
+    pub result_types: fn(u16) -> Type,
+
+    /// Map from superclass index to the Type of that superclass.
+    pub superclass_types: fn(u32) -> Type,
+
+    /// Map from (maybe method index, annotation index) to the Type
+    /// of the value held by that annotation.
+    pub annotation_types: fn(Option<u16>, u32) -> Type,
+}
+
+impl RawInterfaceSchema {
+    /// Gets the type of the parameter struct of the method with the given index.
+    pub fn param_type(&self, method_index: u16) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::param_type(self, method_index) {
+            return ty;
+        }
+        (self.param_types)(method_index)
+    }
+
+    /// Gets the type of the result struct of the method with the given index.
+    pub fn result_type(&self, method_index: u16) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::result_type(self, method_index) {
+            return ty;
+        }
+        (self.result_types)(method_index)
+    }
+
+    /// Gets the type of the superclass with the given index.
+    pub fn superclass_type(&self, index: u32) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::superclass_type(self, index) {
+            return ty;
+        }
+        (self.superclass_types)(index)
+    }
+
+    /// Gets the type of the value held by an annotation of the interface
+    /// (`child_index == None`) or of one of its methods.
+    pub fn annotation_type(&self, child_index: Option<u16>, index: u32) -> Type {
+        #[cfg(feature = "std")]
+        if let Some(ty) = crate::schema_loader::interface_annotation_type(self, child_index, index)
+        {
+            return ty;
+        }
+        (self.annotation_types)(child_index, index)
+    }
+}
+
+/// Interfaces are equal if they come from the same node with the same brand. A loaded schema has a
+/// node of its own for each brand. Generated code instead binds the type parameters of a generic
+/// interface through the type functions, which can't be compared reliably, so for those the
+/// types that the functions resolve to are compared.
+impl core::cmp::PartialEq for RawInterfaceSchema {
+    fn eq(&self, other: &Self) -> bool {
+        if !core::ptr::eq(self.encoded_node, other.encoded_node) {
+            return false;
+        }
+        #[cfg(feature = "std")]
+        if crate::schema_loader::is_loaded(self) {
+            return true;
+        }
+        let proto = crate::schema::InterfaceSchema::new(*self).get_proto();
+        if !proto.get_is_generic() {
+            return true;
+        }
+        let Ok(crate::schema_capnp::node::Interface(interface)) = proto.which() else {
+            return true;
+        };
+        let method_count = interface.get_methods().map_or(0, |methods| methods.len());
+        let superclass_count = interface
+            .get_superclasses()
+            .map_or(0, |superclasses| superclasses.len());
+        (0..method_count as u16).all(|idx| {
+            self.param_type(idx) == other.param_type(idx)
+                && self.result_type(idx) == other.result_type(idx)
+        }) && (0..superclass_count)
+            .all(|idx| self.superclass_type(idx) == other.superclass_type(idx))
+    }
+}
+
+impl core::cmp::Eq for RawInterfaceSchema {}
+
+impl core::fmt::Debug for RawInterfaceSchema {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(
+            f,
+            "RawInterfaceSchema({:?}, {:?})",
+            self.encoded_node as *const _, self.param_types as *const fn(u16) -> Type
+        )
+    }
+}
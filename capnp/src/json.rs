@@ -0,0 +1,1082 @@
+//! Conversion between Cap'n Proto values and JSON, compatible with the C++ `capnp::JsonCodec`.
+//!
+//! Values are mapped as follows:
+//!
+//!   * Structs become objects. A field is written if it is non-null, and the active member
+//!     of a union is written even if it is null, unless it is the union's first member.
+//!   * 64-bit integers become strings, since many JSON implementations can't represent
+//!     them exactly. Other integers become numbers.
+//!   * Floats are written in the shortest form that reads back as the same value, with an
+//!     exponent for very large and very small magnitudes. NaN and infinite floats become the
+//!     strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+//!   * Enums become the names of their enumerants.
+//!   * Data becomes an array of byte values.
+//!   * `Void` becomes `null`.
+//!
+//! Decoding accepts the same forms, and additionally accepts numbers for 64-bit integers and
+//! enums, and strings for any numeric field. Fields that are not in the schema are ignored.
+//!
+//! The annotations declared in the C++ implementation's `json.capnp` are honored:
+//!
+//!   * `$name("...")` on a field or enumerant changes its JSON name.
+//!   * `$flatten(prefix = "...")` on a struct or group field merges its fields into the
+//!     enclosing object, with an optional prefix.
+//!   * `$discriminator(name = "...", valueName = "...")` on a struct with an anonymous union,
+//!     or on a group field containing a union, adds a field holding the name of the active
+//!     member, and optionally writes the member's value under a fixed name.
+//!   * `$base64` and `$hex` on a `Data` field encode it as a string.
+//!
+//! To use the annotations, compile `json.capnp` alongside your schema so that the annotation
+//! types are available, the same as for any other imported schema.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::dynamic_value;
+use crate::introspect::{Type, TypeVariant};
+use crate::schema::{AnnotationList, EnumSchema, Field, StructSchema};
+use crate::schema_capnp::field;
+use crate::{dynamic_list, dynamic_struct, Error, Result};
+
+mod value;
+use value::Value;
+
+// Ids of the annotations declared in json.capnp.
+const NAME_ANNOTATION_ID: u64 = 0xfa5b1fd61c2e7c3d;
+const FLATTEN_ANNOTATION_ID: u64 = 0x82d3e852af0336bf;
+const DISCRIMINATOR_ANNOTATION_ID: u64 = 0xcfa794e8d19a0162;
+const BASE64_ANNOTATION_ID: u64 = 0xd7d879450a253e4b;
+const HEX_ANNOTATION_ID: u64 = 0xf061e22f0ae5c7b5;
+
+/// Encodes and decodes JSON.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonCodec {
+    pretty_print: bool,
+    max_nesting_depth: u32,
+}
+
+impl Default for JsonCodec {
+    fn default() -> Self {
+        Self {
+            pretty_print: false,
+            max_nesting_depth: 64,
+        }
+    }
+}
+
+/// Encodes `value` as compact JSON with the default codec settings.
+pub fn to_string<'a>(value: impl Into<dynamic_value::Reader<'a>>) -> Result<String> {
+    JsonCodec::new().encode(value)
+}
+
+/// Decodes `input` into `output`, which must be a struct, with the default codec settings.
+pub fn from_str<'a>(input: &str, output: impl Into<dynamic_value::Builder<'a>>) -> Result<()> {
+    JsonCodec::new().decode(input, output)
+}
+
+impl JsonCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables whitespace in the output, laid out the same way as in the C++
+    /// implementation. Disabled by default.
+    pub fn set_pretty_print(&mut self, enabled: bool) {
+        self.pretty_print = enabled;
+    }
+
+    /// Sets how deeply nested values may be, both when encoding and when decoding.
+    /// Defaults to 64.
+    pub fn set_max_nesting_depth(&mut self, max_nesting_depth: u32) {
+        self.max_nesting_depth = max_nesting_depth;
+    }
+
+    /// Encodes `value` as JSON. Fails if `value` is or contains a capability or an `AnyPointer`.
+    pub fn encode<'a>(&self, value: impl Into<dynamic_value::Reader<'a>>) -> Result<String> {
+        let value = self.encode_value(value.into(), 0)?;
+        Ok(value::write(&value, self.pretty_print))
+    }
+
+    /// Decodes `input` into `output`, which must be a struct.
+    pub fn decode<'a>(
+        &self,
+        input: &str,
+        output: impl Into<dynamic_value::Builder<'a>>,
+    ) -> Result<()> {
+        let dynamic_value::Builder::Struct(output) = output.into() else {
+            return Err(Error::failed(
+                "JSON can only be decoded into a struct".into(),
+            ));
+        };
+        let value = value::parse(input, self.max_nesting_depth)?;
+        let info = StructInfo::new(output.get_schema(), None)?;
+        self.decode_struct(&value, output, &info, 0)
+    }
+
+    fn check_depth(&self, depth: u32) -> Result<()> {
+        if depth > self.max_nesting_depth {
+            Err(Error::failed("JSON message nesting is too deep".into()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn encode_value(&self, value: dynamic_value::Reader<'_>, depth: u32) -> Result<Value> {
+        self.check_depth(depth)?;
+        Ok(match value {
+            dynamic_value::Reader::Void => Value::Null,
+            dynamic_value::Reader::Bool(b) => Value::Bool(b),
+            dynamic_value::Reader::Int8(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::Int16(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::Int32(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::Int64(x) => Value::String(x.to_string()),
+            dynamic_value::Reader::UInt8(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::UInt16(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::UInt32(x) => Value::Number(x.to_string()),
+            dynamic_value::Reader::UInt64(x) => Value::String(x.to_string()),
+            dynamic_value::Reader::Float32(x) => encode_float(x),
+            dynamic_value::Reader::Float64(x) => encode_float(x),
+            dynamic_value::Reader::Enum(e) => match e.get_enumerant()? {
+                Some(enumerant) => Value::String(json_name(
+                    enumerant.get_proto().get_name()?,
+                    enumerant.get_annotations()?,
+                )?),
+                None => Value::Number(e.get_value().to_string()),
+            },
+            dynamic_value::Reader::Text(t) => Value::String(t.into()),
+            dynamic_value::Reader::Data(d) => encode_data(d, DataEncoding::Array),
+            dynamic_value::Reader::Struct(s) => {
+                let info = StructInfo::new(s.get_schema(), None)?;
+                self.encode_struct(s, &info, depth)?
+            }
+            dynamic_value::Reader::List(l) => {
+                let mut elements = Vec::with_capacity(l.len() as usize);
+                if let TypeVariant::Struct(schema) = l.element_type().which() {
+                    let info = StructInfo::new(schema.into(), None)?;
+                    for element in l.iter() {
+                        let element: dynamic_struct::Reader = element?.downcast();
+                        elements.push(self.encode_struct(element, &info, depth + 1)?);
+                    }
+                } else {
+                    for element in l.iter() {
+                        elements.push(self.encode_value(element?, depth + 1)?);
+                    }
+                }
+                Value::Array(elements)
+            }
+            dynamic_value::Reader::AnyPointer(_) => {
+                return Err(Error::failed(
+                    "don't know how to JSON-encode AnyPointer".into(),
+                ))
+            }
+            dynamic_value::Reader::Capability(_) => {
+                return Err(Error::failed(
+                    "don't know how to JSON-encode capabilities".into(),
+                ))
+            }
+        })
+    }
+
+    fn encode_struct(
+        &self,
+        reader: dynamic_struct::Reader<'_>,
+        info: &StructInfo,
+        depth: u32,
+    ) -> Result<Value> {
+        self.check_depth(depth)?;
+        let mut fields = Vec::new();
+        self.gather_fields(reader, info, "", &mut fields, depth)?;
+        Ok(Value::Object(fields))
+    }
+
+    // Collects the JSON fields of `reader`, including those of flattened members.
+    fn gather_fields(
+        &self,
+        reader: dynamic_struct::Reader<'_>,
+        info: &StructInfo,
+        prefix: &str,
+        out: &mut Vec<(String, Value)>,
+        depth: u32,
+    ) -> Result<()> {
+        let active = reader.which()?;
+        let tag_name = match &info.discriminator {
+            Some(Discriminator {
+                tag_name: Some(tag_name),
+                ..
+            }) => Some(tag_name),
+            _ => None,
+        };
+        if let (Some(active), Some(tag_name)) = (active, tag_name) {
+            let name = &info.fields[active.get_index() as usize].name;
+            out.push((format!("{prefix}{tag_name}"), Value::String(name.clone())));
+        }
+        for field_info in &info.fields {
+            let field = field_info.field;
+            if field_info.is_union_member
+                && tag_name.is_some()
+                && matches!(field.get_type().which(), TypeVariant::Void)
+            {
+                // The tag already says everything there is to say about a Void member.
+                continue;
+            }
+            if !reader.has(field)? {
+                // The active member of a union is written as null if it is not the default
+                // one, since otherwise there would be no way to tell which member is active.
+                let is_active = active.map(|f| f.get_index()) == Some(field.get_index());
+                if is_active
+                    && field.get_proto().get_discriminant_value() != 0
+                    && info.discriminator.is_none()
+                {
+                    out.push((format!("{prefix}{}", field_info.name), Value::Null));
+                }
+                continue;
+            }
+            let value = reader.get(field)?;
+            match &field_info.flatten {
+                Some(flatten_prefix) => {
+                    let dynamic_value::Reader::Struct(child) = value else {
+                        return Err(Error::failed("only structs can be flattened".into()));
+                    };
+                    let child_info = field_info.child_info()?;
+                    self.check_depth(depth + 1)?;
+                    self.gather_fields(
+                        child,
+                        &child_info,
+                        &format!("{prefix}{flatten_prefix}"),
+                        out,
+                        depth + 1,
+                    )?;
+                }
+                None => {
+                    let name = match &info.discriminator {
+                        Some(Discriminator {
+                            value_name: Some(value_name),
+                            ..
+                        }) if field_info.is_union_member => value_name,
+                        _ => &field_info.name,
+                    };
+                    let value = match (field_info.data_encoding, value) {
+                        (encoding, dynamic_value::Reader::Data(d)) => encode_data(d, encoding),
+                        (_, dynamic_value::Reader::Struct(s)) => {
+                            self.encode_struct(s, &field_info.child_info()?, depth + 1)?
+                        }
+                        (_, value) => self.encode_value(value, depth + 1)?,
+                    };
+                    out.push((format!("{prefix}{name}"), value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_struct(
+        &self,
+        value: &Value,
+        mut dst: dynamic_struct::Builder<'_>,
+        info: &StructInfo,
+        depth: u32,
+    ) -> Result<()> {
+        self.check_depth(depth)?;
+        let Value::Object(fields) = value else {
+            return Err(Error::failed(format!(
+                "expected an object, found {}",
+                value.kind()
+            )));
+        };
+        // Union discriminators are decoded first, so that it is known which member is active
+        // by the time the member's value is decoded.
+        for discriminators in [true, false] {
+            for (name, value) in fields {
+                self.decode_member(name, value, dst.reborrow(), info, discriminators, depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Decodes one JSON field into `dst`, if it is a discriminator and `discriminators` is
+    // true, or if it is anything else and `discriminators` is false.
+    fn decode_member(
+        &self,
+        name: &str,
+        value: &Value,
+        mut dst: dynamic_struct::Builder<'_>,
+        info: &StructInfo,
+        discriminators: bool,
+        depth: u32,
+    ) -> Result<()> {
+        let active = dst.which()?.map(|f| f.get_index());
+        let Some(target) = info.resolve(name, active)? else {
+            // Unknown fields are ignored, to allow for schema evolution.
+            return Ok(());
+        };
+        if target.is_discriminator != discriminators {
+            return Ok(());
+        }
+        match target.kind {
+            TargetKind::Discriminator => {
+                let Value::String(tag) = value else {
+                    return Err(Error::failed(format!(
+                        "union discriminator \"{name}\" must be a string"
+                    )));
+                };
+                let Some(member) = info
+                    .fields
+                    .iter()
+                    .find(|f| f.is_union_member && f.name == *tag)
+                else {
+                    return Err(Error::failed(format!("unknown union member \"{tag}\"")));
+                };
+                dst.clear_inactive_variant(member.field)?;
+                dst.clear(member.field)
+            }
+            TargetKind::UnionValue => {
+                let Some(active) = dst.which()? else {
+                    return Err(Error::failed(format!(
+                        "union value \"{name}\" found, but the struct has no union"
+                    )));
+                };
+                let member = &info.fields[active.get_index() as usize];
+                self.decode_field(member, value, dst, depth + 1)
+            }
+            TargetKind::Field(index) => {
+                self.decode_field(&info.fields[index], value, dst, depth + 1)
+            }
+            TargetKind::Flattened(index, prefix_len) => {
+                let member = &info.fields[index];
+                let field = member.field;
+                let child = match field.get_proto().which()? {
+                    field::Group(_) => dst.get(field)?,
+                    field::Slot(_) if dst.has(field)? => dst.get(field)?,
+                    field::Slot(_) => dst.init(field)?,
+                };
+                self.check_depth(depth + 1)?;
+                self.decode_member(
+                    &name[prefix_len..],
+                    value,
+                    child.downcast(),
+                    &member.child_info()?,
+                    discriminators,
+                    depth + 1,
+                )
+            }
+        }
+    }
+
+    fn decode_field(
+        &self,
+        info: &FieldInfo,
+        value: &Value,
+        mut dst: dynamic_struct::Builder<'_>,
+        depth: u32,
+    ) -> Result<()> {
+        self.check_depth(depth)?;
+        let field = info.field;
+        let ty = field.get_type();
+        dst.clear_inactive_variant(field)?;
+        if let Value::Null = value {
+            // Null selects a union member and decodes NaN floats, but otherwise leaves the
+            // field at its default value.
+            return match ty.which() {
+                TypeVariant::Float32 | TypeVariant::Float64 => {
+                    dst.set(field, decode_scalar(ty, value)?)
+                }
+                _ if info.is_union_member => dst.clear(field),
+                _ => Ok(()),
+            };
+        }
+        match (field.get_proto().which()?, ty.which()) {
+            (field::Group(_), _) => {
+                let group = if info.is_union_member {
+                    dst.init(field)?
+                } else {
+                    dst.get(field)?
+                };
+                self.decode_struct(value, group.downcast(), &info.child_info()?, depth)
+            }
+            (field::Slot(_), TypeVariant::Struct(_)) => {
+                let child = dst.init(field)?;
+                self.decode_struct(value, child.downcast(), &info.child_info()?, depth)
+            }
+            (field::Slot(_), TypeVariant::List(element_type)) => {
+                let Value::Array(elements) = value else {
+                    return Err(type_error("an array", value));
+                };
+                let list = dst.initn(field, elements.len() as u32)?;
+                self.decode_list(elements, list.downcast(), element_type, depth)
+            }
+            (field::Slot(_), TypeVariant::Text) => {
+                let Value::String(s) = value else {
+                    return Err(type_error("a string", value));
+                };
+                dst.set(field, dynamic_value::Reader::Text(s))
+            }
+            (field::Slot(_), TypeVariant::Data) => {
+                let bytes = decode_data(value, info.data_encoding)?;
+                dst.set(field, dynamic_value::Reader::Data(&bytes))
+            }
+            (field::Slot(_), _) => dst.set(field, decode_scalar(ty, value)?),
+        }
+    }
+
+    fn decode_list(
+        &self,
+        elements: &[Value],
+        mut list: dynamic_list::Builder<'_>,
+        element_type: Type,
+        depth: u32,
+    ) -> Result<()> {
+        self.check_depth(depth)?;
+        let struct_info = match element_type.which() {
+            TypeVariant::Struct(schema) => Some(StructInfo::new(schema.into(), None)?),
+            _ => None,
+        };
+        for (index, element) in elements.iter().enumerate() {
+            let index = index as u32;
+            match element_type.which() {
+                TypeVariant::Struct(_) => {
+                    let dst = list.reborrow().get(index)?.downcast();
+                    self.decode_struct(element, dst, struct_info.as_ref().unwrap(), depth + 1)?;
+                }
+                TypeVariant::List(inner_type) => {
+                    let Value::Array(inner) = element else {
+                        return Err(type_error("an array", element));
+                    };
+                    let dst = list.reborrow().init(index, inner.len() as u32)?.downcast();
+                    self.decode_list(inner, dst, inner_type, depth + 1)?;
+                }
+                TypeVariant::Text => {
+                    let Value::String(s) = element else {
+                        return Err(type_error("a string", element));
+                    };
+                    list.set(index, dynamic_value::Reader::Text(s))?;
+                }
+                TypeVariant::Data => {
+                    let bytes = decode_data(element, DataEncoding::Array)?;
+                    list.set(index, dynamic_value::Reader::Data(&bytes))?;
+                }
+                _ => list.set(index, decode_scalar(element_type, element)?)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a `Data` field is represented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DataEncoding {
+    Array,
+    Base64,
+    Hex,
+}
+
+/// The settings of a `$discriminator` annotation.
+struct Discriminator {
+    /// The name of the field that holds the name of the active union member.
+    tag_name: Option<String>,
+
+    /// The name under which the active member's value is written, instead of its own name.
+    value_name: Option<String>,
+}
+
+/// A field of a struct, along with the settings of its JSON annotations.
+struct FieldInfo {
+    field: Field,
+    name: String,
+    flatten: Option<String>,
+    data_encoding: DataEncoding,
+    is_union_member: bool,
+
+    /// For groups, the discriminator given by an annotation on the group field.
+    discriminator: Option<Discriminator>,
+}
+
+impl FieldInfo {
+    fn new(field: Field) -> Result<Self> {
+        let proto = field.get_proto();
+        let mut info = Self {
+            field,
+            name: proto.get_name()?.into(),
+            flatten: None,
+            data_encoding: DataEncoding::Array,
+            is_union_member: proto.get_discriminant_value() != field::NO_DISCRIMINANT,
+            discriminator: None,
+        };
+        for annotation in field.get_annotations()? {
+            match annotation.get_id() {
+                NAME_ANNOTATION_ID => {
+                    let dynamic_value::Reader::Text(name) = annotation.get_value()? else {
+                        return Err(Error::failed("$name annotation must be text".into()));
+                    };
+                    info.name = name.into();
+                }
+                FLATTEN_ANNOTATION_ID => {
+                    if !matches!(field.get_type().which(), TypeVariant::Struct(_)) {
+                        return Err(Error::failed(format!(
+                            "only struct fields and groups can be flattened, but \"{}\" is not",
+                            info.name
+                        )));
+                    }
+                    let options = annotation_struct(annotation.get_value()?)?;
+                    info.flatten = Some(optional_text(options, "prefix")?.unwrap_or_default());
+                }
+                DISCRIMINATOR_ANNOTATION_ID => {
+                    if !matches!(proto.which()?, field::Group(_)) {
+                        return Err(Error::failed("only unions can have a discriminator".into()));
+                    }
+                    let options = annotation_struct(annotation.get_value()?)?;
+                    info.discriminator = Some(Discriminator {
+                        tag_name: Some(match optional_text(options, "name")? {
+                            Some(name) => name,
+                            None => proto.get_name()?.into(),
+                        }),
+                        value_name: optional_text(options, "valueName")?,
+                    });
+                }
+                id @ (BASE64_ANNOTATION_ID | HEX_ANNOTATION_ID) => {
+                    if !matches!(field.get_type().which(), TypeVariant::Data) {
+                        return Err(Error::failed(
+                            "only Data fields can be encoded as base64 or hex".into(),
+                        ));
+                    }
+                    info.data_encoding = if id == BASE64_ANNOTATION_ID {
+                        DataEncoding::Base64
+                    } else {
+                        DataEncoding::Hex
+                    };
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Gets the JSON settings of this field's struct type.
+    fn child_info(&self) -> Result<StructInfo> {
+        let TypeVariant::Struct(schema) = self.field.get_type().which() else {
+            return Err(Error::failed("not a struct".into()));
+        };
+        let discriminator = self.discriminator.as_ref().map(|d| Discriminator {
+            tag_name: d.tag_name.clone(),
+            value_name: d.value_name.clone(),
+        });
+        StructInfo::new(schema.into(), discriminator)
+    }
+}
+
+/// The fields of a struct, along with the settings of its JSON annotations.
+struct StructInfo {
+    fields: Vec<FieldInfo>,
+    discriminator: Option<Discriminator>,
+}
+
+/// What a JSON field name refers to in a struct.
+struct Target {
+    kind: TargetKind,
+
+    /// Whether this is a union discriminator, possibly of a flattened member.
+    is_discriminator: bool,
+}
+
+enum TargetKind {
+    Discriminator,
+    UnionValue,
+    Field(usize),
+
+    /// A field of a flattened member, whose name begins with a prefix of the given length.
+    Flattened(usize, usize),
+}
+
+impl StructInfo {
+    fn new(schema: StructSchema, discriminator: Option<Discriminator>) -> Result<Self> {
+        let discriminator = match discriminator {
+            Some(d) => Some(d),
+            None => match schema.get_annotations()?.find(DISCRIMINATOR_ANNOTATION_ID) {
+                Some(annotation) => {
+                    let options = annotation_struct(annotation.get_value()?)?;
+                    Some(Discriminator {
+                        tag_name: optional_text(options, "name")?,
+                        value_name: optional_text(options, "valueName")?,
+                    })
+                }
+                None => None,
+            },
+        };
+        let fields = schema
+            .get_fields()?
+            .iter()
+            .map(FieldInfo::new)
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            fields,
+            discriminator,
+        })
+    }
+
+    /// Finds the member that the JSON field `name` maps to. Flattened union members other
+    /// than `active` are skipped, since several of them may contain fields of the same name.
+    /// Passing `None` considers all of them.
+    fn resolve(&self, name: &str, active: Option<u16>) -> Result<Option<Target>> {
+        if let Some(d) = &self.discriminator {
+            if d.tag_name.as_deref() == Some(name) {
+                return Ok(Some(Target {
+                    kind: TargetKind::Discriminator,
+                    is_discriminator: true,
+                }));
+            }
+            if d.value_name.as_deref() == Some(name) {
+                return Ok(Some(Target {
+                    kind: TargetKind::UnionValue,
+                    is_discriminator: false,
+                }));
+            }
+        }
+        for (index, info) in self.fields.iter().enumerate() {
+            // Flattened members never appear under their own name, which may instead be the
+            // discriminator of a flattened union.
+            if info.name == name && info.flatten.is_none() {
+                return Ok(Some(Target {
+                    kind: TargetKind::Field(index),
+                    is_discriminator: false,
+                }));
+            }
+        }
+        for (index, info) in self.fields.iter().enumerate() {
+            let Some(prefix) = &info.flatten else {
+                continue;
+            };
+            if info.is_union_member && active.map_or(false, |a| a != info.field.get_index()) {
+                continue;
+            }
+            let Some(rest) = name.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            if let Some(target) = info.child_info()?.resolve(rest, None)? {
+                return Ok(Some(Target {
+                    kind: TargetKind::Flattened(index, prefix.len()),
+                    is_discriminator: target.is_discriminator,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn annotation_struct(value: dynamic_value::Reader<'_>) -> Result<dynamic_struct::Reader<'_>> {
+    match value {
+        dynamic_value::Reader::Struct(s) => Ok(s),
+        _ => Err(Error::failed("expected a struct annotation value".into())),
+    }
+}
+
+fn optional_text(options: dynamic_struct::Reader<'_>, name: &str) -> Result<Option<String>> {
+    if !options.has_named(name)? {
+        return Ok(None);
+    }
+    match options.get_named(name)? {
+        dynamic_value::Reader::Text(t) => Ok(Some(t.into())),
+        _ => Err(Error::failed(format!("expected \"{name}\" to be text"))),
+    }
+}
+
+/// Gets the JSON name of a field or enumerant that has the given annotations.
+fn json_name(name: &str, annotations: AnnotationList) -> Result<String> {
+    match annotations.find(NAME_ANNOTATION_ID) {
+        Some(annotation) => match annotation.get_value()? {
+            dynamic_value::Reader::Text(t) => Ok(t.into()),
+            _ => Err(Error::failed("$name annotation must be text".into())),
+        },
+        None => Ok(name.into()),
+    }
+}
+
+fn type_error(expected: &str, found: &Value) -> Error {
+    Error::failed(format!("expected {expected}, found {}", found.kind()))
+}
+
+// Floats are written in the shortest form that reads back as the same value of their own width,
+// so that `0.1f32` becomes `0.1` rather than the digits of its widened `f64` value. Like
+// JavaScript, very large and very small magnitudes use exponent notation.
+fn encode_float<F>(x: F) -> Value
+where
+    F: Copy + Into<f64> + core::fmt::Display + core::fmt::LowerExp,
+{
+    let wide: f64 = x.into();
+    if wide.is_nan() {
+        Value::String("NaN".into())
+    } else if wide.is_infinite() {
+        Value::String(if wide > 0.0 { "Infinity" } else { "-Infinity" }.into())
+    } else if wide != 0.0 && !(1e-6..1e21).contains(&wide.abs()) {
+        Value::Number(format!("{x:e}"))
+    } else {
+        Value::Number(x.to_string())
+    }
+}
+
+fn encode_data(data: &[u8], encoding: DataEncoding) -> Value {
+    match encoding {
+        DataEncoding::Array => {
+            Value::Array(data.iter().map(|b| Value::Number(b.to_string())).collect())
+        }
+        DataEncoding::Base64 => Value::String(encode_base64(data)),
+        DataEncoding::Hex => {
+            const DIGITS: &[u8; 16] = b"0123456789abcdef";
+            let mut result = String::with_capacity(data.len() * 2);
+            for b in data {
+                result.push(DIGITS[usize::from(b >> 4)] as char);
+                result.push(DIGITS[usize::from(b & 0xf)] as char);
+            }
+            Value::String(result)
+        }
+    }
+}
+
+fn decode_data(value: &Value, encoding: DataEncoding) -> Result<Vec<u8>> {
+    match (encoding, value) {
+        (DataEncoding::Array, Value::Array(elements)) => {
+            elements.iter().map(decode_integer::<u8>).collect()
+        }
+        (DataEncoding::Array, _) => Err(type_error("an array of bytes", value)),
+        (DataEncoding::Base64, Value::String(s)) => decode_base64(s),
+        (DataEncoding::Hex, Value::String(s)) => {
+            if s.len() % 2 != 0 {
+                return Err(Error::failed("hex data must have an even length".into()));
+            }
+            (0..s.len())
+                .step_by(2)
+                .map(|i| {
+                    s.get(i..i + 2)
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                        .ok_or_else(|| Error::failed("invalid hex data".into()))
+                })
+                .collect()
+        }
+        (_, _) => Err(type_error("a string", value)),
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut result = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::failed("invalid base64 data".into());
+    let digits = s.trim_end_matches('=').as_bytes();
+    if s.len() - digits.len() > 2 || digits.len() % 4 == 1 {
+        return Err(invalid());
+    }
+    let mut result = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut bits = 0u32;
+        for (i, digit) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|c| c == digit)
+                .ok_or_else(invalid)?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            result.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(result)
+}
+
+// The largest magnitude up to which every integer can be represented exactly as an f64.
+const MAX_EXACT: f64 = 9007199254740992.0;
+
+fn decode_integer<T>(value: &Value) -> Result<T>
+where
+    T: TryFrom<i64> + TryFrom<u64>,
+{
+    let out_of_range = || Error::failed("integer value out of range".into());
+    let text = match value {
+        Value::Number(n) => n.as_str(),
+        Value::String(s) => s.trim(),
+        _ => return Err(type_error("an integer", value)),
+    };
+    if let Ok(x) = text.parse::<i64>() {
+        return T::try_from(x).map_err(|_| out_of_range());
+    }
+    if let Ok(x) = text.parse::<u64>() {
+        return T::try_from(x).map_err(|_| out_of_range());
+    }
+    // Numbers like `1e3` or `5.0` are integers too, as long as they are exact.
+    match text.parse::<f64>() {
+        Ok(x) if (-MAX_EXACT..=MAX_EXACT).contains(&x) && x == (x as i64) as f64 => {
+            T::try_from(x as i64).map_err(|_| out_of_range())
+        }
+        Ok(_) => Err(Error::failed(format!("expected an integer, found {text}"))),
+        Err(_) => Err(Error::failed(format!("invalid integer \"{text}\""))),
+    }
+}
+
+fn decode_float(value: &Value) -> Result<f64> {
+    match value {
+        Value::Null => Ok(f64::NAN),
+        Value::Number(n) => n
+            .parse()
+            .map_err(|_| Error::failed(format!("invalid number {n}"))),
+        Value::String(s) => match s.trim() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            s => s
+                .parse()
+                .map_err(|_| Error::failed(format!("invalid number \"{s}\""))),
+        },
+        _ => Err(type_error("a number", value)),
+    }
+}
+
+fn decode_enum(schema: EnumSchema, value: &Value) -> Result<dynamic_value::Enum> {
+    let number = match value {
+        Value::String(name) => {
+            let mut found = None;
+            for enumerant in schema.get_enumerants()? {
+                if json_name(
+                    enumerant.get_proto().get_name()?,
+                    enumerant.get_annotations()?,
+                )? == *name
+                {
+                    found = Some(enumerant.get_ordinal());
+                    break;
+                }
+            }
+            found.ok_or_else(|| Error::failed(format!("unknown enumerant \"{name}\"")))?
+        }
+        _ => decode_integer(value)?,
+    };
+    Ok(dynamic_value::Enum::new(number, schema))
+}
+
+fn decode_scalar(ty: Type, value: &Value) -> Result<dynamic_value::Reader<'static>> {
+    Ok(match ty.which() {
+        TypeVariant::Void => dynamic_value::Reader::Void,
+        TypeVariant::Bool => match value {
+            Value::Bool(b) => dynamic_value::Reader::Bool(*b),
+            _ => return Err(type_error("a boolean", value)),
+        },
+        TypeVariant::Int8 => dynamic_value::Reader::Int8(decode_integer(value)?),
+        TypeVariant::Int16 => dynamic_value::Reader::Int16(decode_integer(value)?),
+        TypeVariant::Int32 => dynamic_value::Reader::Int32(decode_integer(value)?),
+        TypeVariant::Int64 => dynamic_value::Reader::Int64(decode_integer(value)?),
+        TypeVariant::UInt8 => dynamic_value::Reader::UInt8(decode_integer(value)?),
+        TypeVariant::UInt16 => dynamic_value::Reader::UInt16(decode_integer(value)?),
+        TypeVariant::UInt32 => dynamic_value::Reader::UInt32(decode_integer(value)?),
+        TypeVariant::UInt64 => dynamic_value::Reader::UInt64(decode_integer(value)?),
+        TypeVariant::Float32 => dynamic_value::Reader::Float32(decode_float(value)? as f32),
+        TypeVariant::Float64 => dynamic_value::Reader::Float64(decode_float(value)?),
+        TypeVariant::Enum(schema) => decode_enum(schema.into(), value)?.into(),
+        TypeVariant::AnyPointer => {
+            return Err(Error::failed(
+                "don't know how to JSON-decode AnyPointer".into(),
+            ))
+        }
+        TypeVariant::Capability(_) => {
+            return Err(Error::failed(
+                "don't know how to JSON-decode capabilities".into(),
+            ))
+        }
+        _ => return Err(Error::failed("unexpected type".into())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::value::Value;
+    use super::{
+        decode_base64, decode_float, encode_base64, encode_float, from_str, to_string, JsonCodec,
+    };
+    use crate::message;
+    use crate::schema_capnp::{node, type_};
+
+    #[test]
+    fn base64() {
+        for (data, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\xfe\xfd\xfc", "//79/A=="),
+        ] {
+            assert_eq!(encode_base64(data), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), data);
+        }
+        assert_eq!(decode_base64("Zm9vYg").unwrap(), b"foob");
+        assert!(decode_base64("Zm9vY").is_err());
+        assert!(decode_base64("Zm9v!A==").is_err());
+    }
+
+    #[test]
+    fn floats() {
+        let number = |s: &str| Value::Number(s.into());
+        assert_eq!(encode_float(0.1f32), number("0.1"));
+        assert_eq!(encode_float(-16777216f32), number("-16777216"));
+        assert_eq!(encode_float(3.4028235e38f32), number("3.4028235e38"));
+        assert_eq!(encode_float(1e-7f32), number("1e-7"));
+        assert_eq!(encode_float(0.1f64), number("0.1"));
+        assert_eq!(encode_float(-0.0f64), number("-0"));
+        assert_eq!(encode_float(1e20f64), number("100000000000000000000"));
+        assert_eq!(encode_float(1e300f64), number("1e300"));
+        assert_eq!(encode_float(-2.5e-300f64), number("-2.5e-300"));
+        assert_eq!(encode_float(f64::NAN), Value::String("NaN".into()));
+
+        for x in [0.1f32, 1e-7, 3.4028235e38, f32::MIN_POSITIVE, 1.0e-45] {
+            assert_eq!(decode_float(&encode_float(x)).unwrap() as f32, x);
+        }
+        for x in [0.1f64, 1e300, 5e-324, f64::MAX, 123456.789] {
+            assert_eq!(decode_float(&encode_float(x)).unwrap(), x);
+        }
+    }
+
+    #[test]
+    fn encode_node() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_id(0xffff_ffff_ffff_ffff);
+        node.set_display_name("foo.capnp:Foo");
+        node.set_display_name_prefix_length(10);
+        {
+            let mut nested = node.reborrow().init_nested_nodes(1);
+            nested.reborrow().get(0).set_name("Bar");
+            nested.reborrow().get(0).set_id(12);
+        }
+        node.reborrow().init_enum();
+
+        assert_eq!(
+            to_string(node.into_reader()).unwrap(),
+            concat!(
+                r#"{"id":"18446744073709551615","displayName":"foo.capnp:Foo","#,
+                r#""displayNamePrefixLength":10,"scopeId":"0","#,
+                r#""nestedNodes":[{"name":"Bar","id":"12"}],"enum":{},"isGeneric":false}"#
+            )
+        );
+    }
+
+    #[test]
+    fn encode_void_union_member() {
+        let mut ty = message::Builder::new_default();
+        {
+            let mut ty = ty.init_root::<type_::Builder>();
+            ty.reborrow()
+                .init_list()
+                .init_element_type()
+                .set_float32(());
+        }
+        let ty = ty.get_root_as_reader::<type_::Reader>().unwrap();
+        assert_eq!(
+            to_string(ty).unwrap(),
+            r#"{"list":{"elementType":{"float32":null}}}"#
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_id(0x8000_0000_0000_0001);
+        node.set_display_name("quote\" and \\ and \u{1}");
+        {
+            let mut st = node.reborrow().init_struct();
+            st.set_data_word_count(3);
+            st.set_preferred_list_encoding(crate::schema_capnp::ElementSize::InlineComposite);
+            let mut fields = st.init_fields(1);
+            let mut field = fields.reborrow().get(0);
+            field.set_name("x");
+            field.set_code_order(1);
+            let mut slot = field.init_slot();
+            slot.reborrow().init_type().set_float64(());
+            slot.set_had_explicit_default(true);
+        }
+        let json = to_string(node.reborrow_as_reader()).unwrap();
+        assert!(
+            json.contains(r#""preferredListEncoding":"inlineComposite""#),
+            "{json}"
+        );
+
+        let mut decoded = message::Builder::new_default();
+        from_str(&json, decoded.init_root::<node::Builder>()).unwrap();
+        assert_eq!(
+            decoded.get_root_as_reader::<node::Reader>().unwrap(),
+            node.into_reader()
+        );
+    }
+
+    #[test]
+    fn decode_lenient_forms() {
+        let mut message = message::Builder::new_default();
+        from_str(
+            r#"{"id": 12, "displayNamePrefixLength": "7", "unknownField": [1, 2, 3],
+                "const": {"value": {"float32": "-Infinity"}}}"#,
+            message.init_root::<node::Builder>(),
+        )
+        .unwrap();
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        assert_eq!(node.get_id(), 12);
+        assert_eq!(node.get_display_name_prefix_length(), 7);
+        let node::Const(c) = node.which().unwrap() else {
+            panic!("expected a const node");
+        };
+        let crate::schema_capnp::value::Float32(x) = c.get_value().unwrap().which().unwrap() else {
+            panic!("expected a float32 value");
+        };
+        assert_eq!(x, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn decode_errors() {
+        for input in [
+            r#"[]"#,
+            r#"{"id": true}"#,
+            r#"{"id": -1}"#,
+            r#"{"displayNamePrefixLength": 1.5}"#,
+            r#"{"isGeneric": 1}"#,
+            r#"{"nestedNodes": {}}"#,
+            r#"{"struct": {"preferredListEncoding": "huge"}}"#,
+        ] {
+            let mut message = message::Builder::new_default();
+            assert!(
+                from_str(input, message.init_root::<node::Builder>()).is_err(),
+                "{input} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn pretty_print_and_depth() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_display_name("x");
+        let mut codec = JsonCodec::new();
+        codec.set_pretty_print(true);
+        assert_eq!(
+            codec.encode(node.reborrow_as_reader()).unwrap(),
+            concat!(
+                r#"{"id": "0", "displayName": "x", "displayNamePrefixLength": 0, "#,
+                r#""scopeId": "0", "file": null, "isGeneric": false}"#
+            )
+        );
+
+        codec.set_max_nesting_depth(0);
+        assert!(codec.encode(node.reborrow_as_reader()).is_err());
+        assert!(codec.decode("{}", node).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+//! A minimal JSON syntax tree, with a parser and a printer whose output matches the C++
+//! implementation.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{Error, Result};
+
+/// A parsed JSON value. Numbers are kept as they appear in the input, so that they can be
+/// converted to 64-bit integers without losing precision.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        }
+    }
+}
+
+/// Parses `input` as a single JSON value, rejecting arrays and objects nested more than
+/// `max_depth` levels deep.
+pub(crate) fn parse(input: &str, max_depth: u32) -> Result<Value> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        max_depth,
+    };
+    parser.skip_whitespace();
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error("unexpected input after JSON value"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    max_depth: u32,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        let consumed = &self.input[..self.pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        Error::failed(format!(
+            "JSON parse error at line {line}, column {column}: {message}"
+        ))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c as char)))
+        }
+    }
+
+    fn parse_value(&mut self, depth: u32) -> Result<Value> {
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(b'[') => {
+                self.enter(depth)?;
+                let mut elements = Vec::new();
+                self.parse_sequence(b']', |parser| {
+                    elements.push(parser.parse_value(depth + 1)?);
+                    Ok(())
+                })?;
+                Ok(Value::Array(elements))
+            }
+            Some(b'{') => {
+                self.enter(depth)?;
+                let mut fields = Vec::new();
+                self.parse_sequence(b'}', |parser| {
+                    if parser.peek() != Some(b'"') {
+                        return Err(parser.error("expected a string as object key"));
+                    }
+                    let name = parser.parse_string()?;
+                    parser.skip_whitespace();
+                    parser.expect(b':')?;
+                    parser.skip_whitespace();
+                    fields.push((name, parser.parse_value(depth + 1)?));
+                    Ok(())
+                })?;
+                Ok(Value::Object(fields))
+            }
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn enter(&mut self, depth: u32) -> Result<()> {
+        if depth >= self.max_depth {
+            return Err(self.error("nesting is too deep"));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        Ok(())
+    }
+
+    // Parses comma-separated elements up to `close`. The opening bracket has been consumed.
+    fn parse_sequence(
+        &mut self,
+        close: u8,
+        mut element: impl FnMut(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            element(self)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                }
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close as char))),
+            }
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let start = parser.pos;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.pos += 1;
+            }
+            parser.pos > start
+        };
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else if !digits(self) {
+            return Err(self.error("expected digits"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !digits(self) {
+                return Err(self.error("expected digits after decimal point"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected digits in exponent"));
+            }
+        }
+        Ok(Value::Number(self.input[start..self.pos].into()))
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut result = String::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let Some(end) = rest.find(|c: char| c == '"' || c == '\\' || c < ' ') else {
+                return Err(self.error("unterminated string"));
+            };
+            result.push_str(&rest[..end]);
+            self.pos += end;
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\x08',
+                        Some(b'f') => '\x0c',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.pos += 1;
+                            result.push(self.parse_unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    self.pos += 1;
+                    result.push(escaped);
+                }
+                _ => return Err(self.error("control character in string")),
+            }
+        }
+    }
+
+    // Parses the hex digits of a `\u` escape, plus a second escape if the first one is the
+    // leading half of a surrogate pair.
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let first = self.parse_hex4()?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if !self.input[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            self.pos += 2;
+            let second = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+/// Serializes `value`. With `pretty_print`, uses the same layout as the C++ implementation:
+/// short arrays and objects stay on one line, and longer ones put each element on its own line.
+pub(crate) fn write(value: &Value, pretty_print: bool) -> String {
+    let mut multiline = false;
+    Writer { pretty_print }.write(value, 0, &mut multiline, false)
+}
+
+struct Writer {
+    pretty_print: bool,
+}
+
+impl Writer {
+    fn write(
+        &self,
+        value: &Value,
+        indent: usize,
+        multiline: &mut bool,
+        has_prefix: bool,
+    ) -> String {
+        match value {
+            Value::Null => "null".into(),
+            Value::Bool(true) => "true".into(),
+            Value::Bool(false) => "false".into(),
+            Value::Number(n) => n.clone(),
+            Value::String(s) => write_string(s),
+            Value::Array(elements) => {
+                let mut child_multiline = false;
+                let elements = elements
+                    .iter()
+                    .map(|e| self.write(e, indent + 1, &mut child_multiline, false))
+                    .collect();
+                format!(
+                    "[{}]",
+                    self.write_list(elements, child_multiline, indent, multiline, has_prefix)
+                )
+            }
+            Value::Object(fields) => {
+                let colon = if self.pretty_print { ": " } else { ":" };
+                let mut child_multiline = false;
+                let elements = fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = self.write(value, indent + 1, &mut child_multiline, true);
+                        format!("{}{colon}{value}", write_string(name))
+                    })
+                    .collect();
+                format!(
+                    "{{{}}}",
+                    self.write_list(elements, child_multiline, indent, multiline, has_prefix)
+                )
+            }
+        }
+    }
+
+    fn write_list(
+        &self,
+        elements: Vec<String>,
+        has_multiline_element: bool,
+        indent: usize,
+        multiline: &mut bool,
+        has_prefix: bool,
+    ) -> String {
+        let max_element_size = elements.iter().map(String::len).max().unwrap_or(0);
+        if !self.pretty_print {
+            elements.join(",")
+        } else if elements.len() > 1 && (has_multiline_element || max_element_size > 50) {
+            *multiline = true;
+            let indent_space = " ".repeat((indent + 1) * 2);
+            // If something precedes the list on its first line, start the list on the next one.
+            let prefix = if has_prefix {
+                format!("\n{indent_space}")
+            } else {
+                " ".into()
+            };
+            format!("{prefix}{} ", elements.join(&format!(",\n{indent_space}")))
+        } else {
+            elements.join(", ")
+        }
+    }
+}
+
+fn write_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\x08' => result.push_str("\\b"),
+            '\x0c' => result.push_str("\\f"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c < ' ' => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, write, Value};
+
+    #[test]
+    fn parse_values() {
+        let value = parse(
+            r#" { "a": [1, -2.5e3, true, null], "b\n\u00e9\ud83d\ude00": "x" } "#,
+            64,
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                (
+                    "a".into(),
+                    Value::Array(vec![
+                        Value::Number("1".into()),
+                        Value::Number("-2.5e3".into()),
+                        Value::Bool(true),
+                        Value::Null,
+                    ])
+                ),
+                ("b\n\u{e9}\u{1f600}".into(), Value::String("x".into())),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for input in [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "01",
+            "\"abc",
+            "[1] 2",
+            "\"\\x\"",
+            "tru",
+        ] {
+            assert!(parse(input, 64).is_err(), "{input:?} should be rejected");
+        }
+        assert!(parse("[[[]]]", 3).is_ok());
+        assert!(parse("[[[[]]]]", 3).is_err());
+
+        let error = parse("{\n  \"a\": ?}", 64).unwrap_err();
+        assert!(error.to_string().contains("line 2, column 8"), "{error}");
+    }
+
+    #[test]
+    fn write_compact() {
+        let value = Value::Object(vec![
+            ("a".into(), Value::Number("1".into())),
+            (
+                "b".into(),
+                Value::Array(vec![Value::Null, Value::String("\"\u{1}".into())]),
+            ),
+        ]);
+        assert_eq!(write(&value, false), r#"{"a":1,"b":[null,"\"\u0001"]}"#);
+        assert_eq!(write(&value, true), r#"{"a": 1, "b": [null, "\"\u0001"]}"#);
+    }
+
+    #[test]
+    fn write_pretty_multiline() {
+        let long = Value::String("x".repeat(60));
+        let value = Value::Object(vec![
+            ("a".into(), Value::Array(vec![long.clone(), long.clone()])),
+            ("b".into(), Value::Bool(false)),
+        ]);
+        let x = "x".repeat(60);
+        assert_eq!(
+            write(&value, true),
+            format!("{{ \"a\": [\n    \"{x}\",\n    \"{x}\" ],\n  \"b\": false }}")
+        );
+    }
+}
@@ -36,6 +36,11 @@ extern crate alloc;
 /// [schema.capnp](https://github.com/capnproto/capnproto/blob/master/c%2B%2B/src/capnp/schema.capnp).
 pub mod schema_capnp;
 
+/// Code generated from
+/// [stream.capnp](https://github.com/capnproto/capnproto/blob/master/c%2B%2B/src/capnp/stream.capnp).
+/// Its `StreamResult` is the result type of methods declared with `-> stream`.
+pub mod stream_capnp;
+
 pub mod any_pointer;
 pub mod any_pointer_list;
 pub mod capability;
@@ -43,25 +48,38 @@ pub mod capability_list;
 pub mod constant;
 pub mod data;
 pub mod data_list;
+pub mod dynamic_capability;
 pub mod dynamic_list;
 pub mod dynamic_struct;
 pub mod dynamic_value;
 pub mod enum_list;
+mod flavor;
 pub mod introspect;
 pub mod io;
+pub mod json;
 pub mod list_list;
 pub mod message;
+pub mod orphan;
 pub mod primitive_list;
 pub mod private;
 pub mod raw;
 pub mod schema;
+#[cfg(feature = "std")]
+pub mod schema_loader;
+#[cfg(feature = "serde")]
+pub mod serde;
 pub mod serialize;
 pub mod serialize_packed;
-pub(crate) mod stringify;
+pub mod stringify;
 pub mod struct_list;
+#[cfg(feature = "sync_rpc")]
+#[allow(clippy::duplicate_mod)] // `sync` compiles the capability modules a second time.
+pub mod sync;
 pub mod text;
+pub mod text_format;
 pub mod text_list;
 pub mod traits;
+pub mod visit;
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -403,6 +403,9 @@ where
 
 unsafe impl<A> Send for Builder<A> where A: Send + Allocator {}
 
+// The `&self` methods of `Builder` only read from the arena.
+unsafe impl<A> Sync for Builder<A> where A: Sync + Allocator {}
+
 fn _assert_kinds() {
     fn _assert_send<T: Send>() {}
     fn _assert_reader<S: ReaderSegments + Send>() {
@@ -459,14 +462,7 @@ where
         if self.arena.is_empty() {
             any_pointer::Reader::new(layout::PointerReader::new_default()).get_as()
         } else {
-            let (segment_start, _segment_len) = self.arena.get_segment(0)?;
-            let pointer_reader = layout::PointerReader::get_root(
-                self.arena.as_reader(),
-                0,
-                segment_start,
-                0x7fffffff,
-            )?;
-            let root = any_pointer::Reader::new(pointer_reader);
+            let root = any_pointer::Reader::new(self.get_root_pointer_reader()?);
             root.get_as()
         }
     }
@@ -488,12 +484,62 @@ where
             self.arena.allocate(0, 1).expect("allocate root pointer");
         }
         let (seg_start, _seg_len) = self.arena.get_segment_mut(0);
-        let pointer = layout::PointerBuilder::get_root(&mut self.arena, 0, seg_start);
+        let pointer = layout::PointerBuilder::get_root(&self.arena, 0, seg_start);
         SetPointerBuilder::set_pointer_builder(pointer, value, true)?;
         assert_eq!(self.get_segments_for_output().len(), 1);
         Ok(())
     }
 
+    /// Gets an `Orphanage` for allocating objects in this message that are not (yet) reachable
+    /// from the root. Note that the returned value borrows the whole message; to use orphans
+    /// together with the root, get the orphanage from the root instead, via
+    /// `any_pointer::Builder::get_orphanage()`.
+    pub fn get_orphanage(&mut self) -> crate::orphan::Orphanage<'_> {
+        self.get_root_internal().get_orphanage()
+    }
+
+    /// Returns a copy of this message in which the objects reachable from the root have been
+    /// re-packed into a single segment. Space left behind by overwritten or cleared fields is
+    /// not carried over. Capability pointers keep their indices, so the copy can be used with
+    /// the same capability table as the original.
+    pub fn compacted_copy(&self) -> Result<Builder<HeapAllocator>> {
+        let mut result = Builder::new_default();
+        if !self.arena.is_empty() {
+            let root = self.get_root_pointer_reader()?;
+            let size = root.total_size()?.word_count + 1;
+            result
+                .arena
+                .allocate_segment(size.min(u64::from(u32::MAX)) as u32)?;
+            result.arena.allocate(0, 1).expect("allocate root pointer");
+            let (seg_start, _seg_len) = result.arena.get_segment_mut(0);
+            layout::PointerBuilder::get_root(&result.arena, 0, seg_start).compact_from(root)?;
+        }
+        Ok(result)
+    }
+
+    /// Re-packs the objects reachable from the root into as few segments as possible, reclaiming
+    /// the space left behind by overwritten or cleared fields. Capability pointers keep their
+    /// indices, so any capability table that has been imbued into this message remains valid.
+    pub fn compact(&mut self) -> Result<()> {
+        if self.arena.is_empty() {
+            return Ok(());
+        }
+        let copy = self.compacted_copy()?;
+        let root = copy.get_root_pointer_reader()?;
+        let size = root.total_size()?.word_count + 1;
+        self.arena.clear();
+        self.arena
+            .allocate_segment(size.min(u64::from(u32::MAX)) as u32)?;
+        self.arena.allocate(0, 1).expect("allocate root pointer");
+        let (seg_start, _seg_len) = self.arena.get_segment_mut(0);
+        layout::PointerBuilder::get_root(&self.arena, 0, seg_start).compact_from(root)
+    }
+
+    fn get_root_pointer_reader(&self) -> Result<layout::PointerReader<'_>> {
+        let (segment_start, _segment_len) = self.arena.get_segment(0)?;
+        layout::PointerReader::get_root(self.arena.as_reader(), 0, segment_start, 0x7fffffff)
+    }
+
     pub fn get_segments_for_output(&self) -> OutputSegments {
         self.arena.get_segments_for_output()
     }
@@ -743,6 +789,89 @@ impl Builder<HeapAllocator> {
     }
 }
 
+#[test]
+fn test_compact_reclaims_overwritten_space() {
+    use crate::schema_capnp::node;
+
+    let mut message = Builder::new_default();
+    {
+        let mut root: node::Builder = message.init_root();
+        root.set_id(0xabcd);
+        for idx in 0..100 {
+            root.set_display_name(&alloc::format!("node number {idx}"));
+        }
+    }
+    let words_before = message.get_segments_for_output()[0].len() / BYTES_PER_WORD;
+
+    message.compact().unwrap();
+
+    let segments = message.get_segments_for_output();
+    assert_eq!(segments.len(), 1);
+    // Root pointer, node struct, and the last display name.
+    let root: node::Reader = message.get_root_as_reader().unwrap();
+    let expected_words = 1 + root.total_size().unwrap().word_count as usize;
+    assert_eq!(segments[0].len() / BYTES_PER_WORD, expected_words);
+    assert!(expected_words < words_before);
+    assert_eq!(root.get_id(), 0xabcd);
+    assert_eq!(root.get_display_name().unwrap(), "node number 99");
+}
+
+#[test]
+fn test_compacted_copy_joins_segments() {
+    use crate::schema_capnp::node;
+
+    let mut message = Builder::new(
+        HeapAllocator::new()
+            .first_segment_words(1)
+            .allocation_strategy(AllocationStrategy::FixedSize),
+    );
+    {
+        let mut root: node::Builder = message.init_root();
+        root.set_display_name("foo.capnp:Foo");
+        let mut nested = root.init_nested_nodes(5);
+        for idx in 0..5 {
+            let mut nested_node = nested.reborrow().get(idx);
+            nested_node.set_id(u64::from(idx));
+            nested_node.set_name(&alloc::format!("Nested{idx}"));
+        }
+    }
+    assert!(message.get_segments_for_output().len() > 1);
+
+    let copy = message.compacted_copy().unwrap();
+    assert_eq!(copy.get_segments_for_output().len(), 1);
+
+    let original: node::Reader = message.get_root_as_reader().unwrap();
+    let root: node::Reader = copy.get_root_as_reader().unwrap();
+    assert_eq!(root.get_display_name().unwrap(), "foo.capnp:Foo");
+    let nested = root.get_nested_nodes().unwrap();
+    assert_eq!(nested.len(), 5);
+    for idx in 0..5 {
+        assert_eq!(nested.get(idx).get_id(), u64::from(idx));
+        assert_eq!(
+            nested.get(idx).get_name().unwrap(),
+            alloc::format!("Nested{idx}")
+        );
+    }
+    assert_eq!(
+        original.total_size().unwrap().word_count,
+        root.total_size().unwrap().word_count
+    );
+}
+
+#[test]
+fn test_compact_empty_message() {
+    let mut message = Builder::new_default();
+    message.compact().unwrap();
+    assert_eq!(
+        message
+            .compacted_copy()
+            .unwrap()
+            .get_segments_for_output()
+            .len(),
+        0
+    );
+}
+
 /// An Allocator whose first segment is a backed by a user-provided buffer.
 ///
 /// Recall that an `Allocator` implementation must ensure that allocated segments are
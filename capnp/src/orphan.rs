@@ -0,0 +1,319 @@
+//! Objects that live in a message but are not reachable from its root.
+//!
+//! An [`Orphan`] is created either by disowning a pointer field (via the generated
+//! `disown_*()` methods, or [`any_pointer::Builder::disown_as()`](crate::any_pointer::Builder::disown_as))
+//! or by allocating a fresh object through an [`Orphanage`]. It can later be adopted into
+//! any pointer field of the same message with the generated `adopt_*()` methods. This makes
+//! it possible to move subtrees around within a message without copying them.
+//!
+//! If an orphan is dropped without being adopted, its contents are zeroed out. The space
+//! it occupied is not reclaimed until the message itself is dropped.
+//!
+//! ```
+//! use capnp::{message, orphan::Orphanage, primitive_list, text};
+//!
+//! let mut message = message::Builder::new_default();
+//! let orphanage = message.get_orphanage();
+//! let mut list = orphanage.newn_orphan::<primitive_list::Owned<u32>>(3);
+//! list.get().unwrap().set(2, 42);
+//! list.truncate(5).unwrap();
+//! assert_eq!(list.get_reader().unwrap().len(), 5);
+//! assert_eq!(list.get_reader().unwrap().get(2), 42);
+//!
+//! let greeting = orphanage.new_orphan_copy::<text::Owned>("hello").unwrap();
+//! assert_eq!(greeting.get_reader().unwrap(), "hello");
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::introspect::TypeVariant;
+use crate::private::arena::BuilderArena;
+use crate::private::layout::{CapTableBuilder, OrphanBuilder};
+use crate::traits::{FromPointerBuilder, FromPointerReader, Owned, SetPointerBuilder};
+use crate::Result;
+
+/// A handle for allocating new orphans in a particular message.
+///
+/// The orphanage and its orphans borrow the message for `'a`, like the builder the orphanage was
+/// obtained from.
+#[derive(Clone, Copy)]
+pub struct Orphanage<'a> {
+    arena: &'a dyn BuilderArena,
+    segment_id: u32,
+    cap_table: CapTableBuilder,
+}
+
+impl<'a> Orphanage<'a> {
+    pub(crate) fn new(
+        arena: &'a dyn BuilderArena,
+        segment_id: u32,
+        cap_table: CapTableBuilder,
+    ) -> Self {
+        Self {
+            arena,
+            segment_id,
+            cap_table,
+        }
+    }
+
+    fn new_orphan_builder(&self) -> OrphanBuilder<'a> {
+        OrphanBuilder::new(self.arena, self.segment_id, self.cap_table)
+    }
+
+    /// Allocates a new orphan of type `T`. Use this for structs; for lists, text, and data,
+    /// use `newn_orphan()` to specify a length.
+    pub fn new_orphan<T: Owned>(&self) -> Orphan<'a, T> {
+        self.newn_orphan(0)
+    }
+
+    /// Allocates a new orphan of type `T` with the given length.
+    pub fn newn_orphan<T: Owned>(&self, length: u32) -> Orphan<'a, T> {
+        let mut builder = self.new_orphan_builder();
+        let _: T::Builder<'_> =
+            FromPointerBuilder::init_pointer(builder.as_pointer_builder(), length);
+        builder.into()
+    }
+
+    /// Allocates a new orphan holding a deep copy of `value`, which may come from any message.
+    pub fn new_orphan_copy<T: Owned>(&self, value: T::Reader<'_>) -> Result<Orphan<'a, T>> {
+        let mut builder = self.new_orphan_builder();
+        SetPointerBuilder::set_pointer_builder(builder.as_pointer_builder(), value, false)?;
+        Ok(builder.into())
+    }
+}
+
+/// An object of type `T` that is not currently reachable from the root of its message.
+pub struct Orphan<'a, T: Owned> {
+    builder: OrphanBuilder<'a>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Owned> Orphan<'a, T> {
+    /// Gets a builder for the orphaned object.
+    pub fn get(&mut self) -> Result<T::Builder<'_>> {
+        FromPointerBuilder::get_from_pointer(self.builder.as_pointer_builder(), None)
+    }
+
+    /// Gets a reader for the orphaned object.
+    pub fn get_reader(&self) -> Result<T::Reader<'_>> {
+        FromPointerReader::get_from_pointer(&self.builder.as_pointer_reader(), None)
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.builder.is_null()
+    }
+
+    /// Resizes an orphaned list, text, or data blob to `size` elements. Shrinking happens in
+    /// place and zeroes out the removed elements; growing moves the existing elements into a new
+    /// allocation and leaves the new elements set to their default values.
+    ///
+    /// Returns an error if `T` is not a list, text, or data type.
+    pub fn truncate(&mut self, size: u32) -> Result<()> {
+        let is_text = match T::introspect().which() {
+            TypeVariant::Text => true,
+            TypeVariant::Data | TypeVariant::List(_) => false,
+            _ => {
+                return Err(crate::Error::failed(
+                    "truncate() is only supported on lists, text, and data".into(),
+                ))
+            }
+        };
+        if self.builder.is_null() {
+            if size > 0 {
+                let _: T::Builder<'_> =
+                    FromPointerBuilder::init_pointer(self.builder.as_pointer_builder(), size);
+            }
+            Ok(())
+        } else {
+            self.builder.truncate(size, is_text)
+        }
+    }
+}
+
+impl<'a, T: Owned> From<OrphanBuilder<'a>> for Orphan<'a, T> {
+    fn from(builder: OrphanBuilder<'a>) -> Self {
+        Self {
+            builder,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Owned> From<Orphan<'a, T>> for OrphanBuilder<'a> {
+    fn from(orphan: Orphan<'a, T>) -> Self {
+        orphan.builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema_capnp::node;
+    use crate::{any_pointer, any_pointer_list, message, primitive_list, struct_list, text};
+
+    #[test]
+    fn disown_and_adopt() {
+        let mut message = message::Builder::new_default();
+        let mut root: any_pointer::Builder = message.init_root();
+        root.set_as("hello").unwrap();
+
+        let orphan = root.disown_as::<text::Owned>();
+        assert!(root.is_null());
+        assert_eq!(orphan.get_reader().unwrap(), "hello");
+
+        let mut list: any_pointer_list::Builder = root.initn_as(2);
+        list.reborrow().get(1).adopt(orphan).unwrap();
+
+        let reader = message
+            .get_root_as_reader::<any_pointer_list::Reader>()
+            .unwrap();
+        assert!(reader.get(0).is_null());
+        assert_eq!(reader.get(1).get_as::<text::Reader>().unwrap(), "hello");
+    }
+
+    #[test]
+    fn adopt_new_orphan() {
+        let mut message = message::Builder::new_default();
+        let mut root: any_pointer::Builder = message.init_root();
+        let orphanage = root.get_orphanage();
+
+        let mut orphan = orphanage.new_orphan::<node::Owned>();
+        {
+            let mut node = orphan.get().unwrap();
+            node.set_id(0x1234);
+            node.set_display_name("foo.capnp:Bar");
+        }
+        root.adopt(orphan).unwrap();
+
+        let node = root.into_reader().get_as::<node::Reader>().unwrap();
+        assert_eq!(node.get_id(), 0x1234);
+        assert_eq!(node.get_display_name().unwrap(), "foo.capnp:Bar");
+    }
+
+    #[test]
+    fn adopt_from_other_message() {
+        let mut message1 = message::Builder::new_default();
+        let mut message2 = message::Builder::new_default();
+        let orphan = message1
+            .get_orphanage()
+            .new_orphan_copy::<text::Owned>("hello")
+            .unwrap();
+        let mut root: any_pointer::Builder = message2.init_root();
+        assert!(root.adopt(orphan).is_err());
+        assert!(root.is_null());
+    }
+
+    #[test]
+    fn dropped_orphan_is_zeroed() {
+        let mut message = message::Builder::new_default();
+        {
+            let mut root: any_pointer::Builder = message.init_root();
+            let mut list: primitive_list::Builder<u64> = root.reborrow().initn_as(4);
+            for idx in 0..4 {
+                list.set(idx, 0xffff_ffff_ffff_ffff);
+            }
+            let _ = root.disown_as::<primitive_list::Owned<u64>>();
+        }
+        let segments = message.get_segments_for_output();
+        assert!(segments[0].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn truncate_primitive_list() {
+        let mut message = message::Builder::new_default();
+        let orphanage = message.get_orphanage();
+        let mut orphan = orphanage.newn_orphan::<primitive_list::Owned<u16>>(4);
+        {
+            let mut list = orphan.get().unwrap();
+            for idx in 0..4 {
+                list.set(idx, idx as u16 + 1);
+            }
+        }
+
+        orphan.truncate(2).unwrap();
+        let list = orphan.get_reader().unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.get(1), 2);
+
+        orphan.truncate(6).unwrap();
+        let list = orphan.get_reader().unwrap();
+        assert_eq!(list.len(), 6);
+        assert_eq!(list.get(0), 1);
+        assert_eq!(list.get(1), 2);
+        assert_eq!(list.get(2), 0);
+        assert_eq!(list.get(5), 0);
+    }
+
+    #[test]
+    fn truncate_bool_list() {
+        let mut message = message::Builder::new_default();
+        let orphanage = message.get_orphanage();
+        let mut orphan = orphanage.newn_orphan::<primitive_list::Owned<bool>>(10);
+        {
+            let mut list = orphan.get().unwrap();
+            for idx in 0..10 {
+                list.set(idx, true);
+            }
+        }
+
+        orphan.truncate(3).unwrap();
+        orphan.truncate(10).unwrap();
+        let list = orphan.get_reader().unwrap();
+        for idx in 0..10 {
+            assert_eq!(list.get(idx), idx < 3);
+        }
+    }
+
+    #[test]
+    fn truncate_text() {
+        let mut message = message::Builder::new_default();
+        let orphanage = message.get_orphanage();
+        let mut orphan = orphanage
+            .new_orphan_copy::<text::Owned>("hello world")
+            .unwrap();
+
+        orphan.truncate(5).unwrap();
+        assert_eq!(orphan.get_reader().unwrap(), "hello");
+
+        orphan.truncate(7).unwrap();
+        assert_eq!(orphan.get_reader().unwrap(), "hello\0\0");
+    }
+
+    #[test]
+    fn truncate_struct_list() {
+        let mut message = message::Builder::new_default();
+        let orphanage = message.get_orphanage();
+        let mut orphan = orphanage.newn_orphan::<struct_list::Owned<node::Owned>>(3);
+        {
+            let mut list = orphan.get().unwrap();
+            for idx in 0..3 {
+                let mut node = list.reborrow().get(idx);
+                node.set_id(u64::from(idx));
+                node.set_display_name(&format!("node{idx}"));
+            }
+        }
+
+        orphan.truncate(2).unwrap();
+        assert_eq!(orphan.get_reader().unwrap().len(), 2);
+
+        orphan.truncate(4).unwrap();
+        let list = orphan.get_reader().unwrap();
+        assert_eq!(list.len(), 4);
+        for idx in 0..2 {
+            assert_eq!(list.get(idx).get_id(), u64::from(idx));
+            assert_eq!(
+                list.get(idx).get_display_name().unwrap(),
+                format!("node{idx}")
+            );
+        }
+        assert!(!list.get(2).has_display_name());
+        assert!(!list.get(3).has_display_name());
+    }
+
+    #[test]
+    fn truncate_struct_is_error() {
+        let mut message = message::Builder::new_default();
+        let orphanage = message.get_orphanage();
+        let mut orphan = orphanage.new_orphan::<node::Owned>();
+        assert!(orphan.truncate(1).is_err());
+    }
+}
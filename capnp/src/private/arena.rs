@@ -20,6 +20,7 @@
 
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::cell::UnsafeCell;
 use core::slice;
 use core::u64;
 
@@ -42,6 +43,8 @@ pub trait ReaderArena {
         offset_in_words: i32,
     ) -> Result<*const u8>;
     fn contains_interval(&self, segment_id: u32, start: *const u8, size: usize) -> Result<()>;
+    // like contains_interval(), but does not count the words against the traversal limit
+    fn check_interval(&self, segment_id: u32, start: *const u8, size: usize) -> Result<()>;
     fn amplified_read(&self, virtual_amount: u64) -> Result<()>;
 
     fn nesting_limit(&self) -> i32;
@@ -128,6 +131,11 @@ where
     }
 
     fn contains_interval(&self, id: u32, start: *const u8, size_in_words: usize) -> Result<()> {
+        self.check_interval(id, start, size_in_words)?;
+        self.read_limiter.can_read(size_in_words)
+    }
+
+    fn check_interval(&self, id: u32, start: *const u8, size_in_words: usize) -> Result<()> {
         let (segment_start, segment_len) = self.get_segment(id)?;
         let this_start: usize = segment_start as usize;
         let this_size: usize = segment_len as usize * BYTES_PER_WORD;
@@ -139,7 +147,7 @@ where
                 "message contained out-of-bounds pointer",
             )))
         } else {
-            self.read_limiter.can_read(size_in_words)
+            Ok(())
         }
     }
 
@@ -152,10 +160,15 @@ where
     }
 }
 
+/// The arena of a message that is being built.
+///
+/// Builders and orphans for the same message share the arena, so allocation goes through `&self`.
+/// Exclusive access to the message is instead enforced by the builders, all of which are derived
+/// from a mutable borrow of the `message::Builder`.
 pub trait BuilderArena: ReaderArena {
-    fn allocate(&mut self, segment_id: u32, amount: WordCount32) -> Option<u32>;
-    fn allocate_anywhere(&mut self, amount: u32) -> (SegmentId, u32);
-    fn get_segment_mut(&mut self, id: u32) -> (*mut u8, u32);
+    fn allocate(&self, segment_id: u32, amount: WordCount32) -> Option<u32>;
+    fn allocate_anywhere(&self, amount: u32) -> (SegmentId, u32);
+    fn get_segment_mut(&self, id: u32) -> (*mut u8, u32);
 
     fn as_reader(&self) -> &dyn ReaderArena;
 }
@@ -187,7 +200,7 @@ pub struct BuilderArenaImpl<A>
 where
     A: Allocator,
 {
-    inner: BuilderArenaImplInner<A>,
+    inner: UnsafeCell<BuilderArenaImplInner<A>>,
 }
 
 impl<A> BuilderArenaImpl<A>
@@ -196,20 +209,33 @@ where
 {
     pub fn new(allocator: A) -> Self {
         Self {
-            inner: BuilderArenaImplInner {
+            inner: UnsafeCell::new(BuilderArenaImplInner {
                 allocator: Some(allocator),
                 segments: Vec::new(),
-            },
+            }),
         }
     }
 
+    fn inner(&self) -> &BuilderArenaImplInner<A> {
+        // No `&mut` to the inner state outlives a call to one of the `BuilderArena` methods,
+        // and those never call back into the arena.
+        unsafe { &*self.inner.get() }
+    }
+
+    /// # Safety
+    /// No other reference to the inner state may be live while the result is in use.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn inner_mut(&self) -> &mut BuilderArenaImplInner<A> {
+        &mut *self.inner.get()
+    }
+
     /// Allocates a new segment with capacity for at least `minimum_size` words.
     pub fn allocate_segment(&mut self, minimum_size: u32) -> Result<()> {
-        self.inner.allocate_segment(minimum_size)
+        self.inner.get_mut().allocate_segment(minimum_size)
     }
 
     pub fn get_segments_for_output(&self) -> OutputSegments {
-        let reff = &self.inner;
+        let reff = self.inner();
         if reff.segments.len() == 1 {
             let seg = &reff.segments[0];
 
@@ -237,18 +263,26 @@ where
     }
 
     pub fn len(&self) -> usize {
-        self.inner.segments.len()
+        self.inner().segments.len()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    /// Deallocates all segments, leaving the arena empty but ready to be reused.
+    pub fn clear(&mut self) {
+        let inner = self.inner.get_mut();
+        inner.deallocate_all();
+        inner.segments.clear();
+    }
+
     /// Retrieves the underlying `Allocator`, deallocating all currently-allocated
     /// segments.
     pub fn into_allocator(mut self) -> A {
-        self.inner.deallocate_all();
-        self.inner.allocator.take().unwrap()
+        let inner = self.inner.get_mut();
+        inner.deallocate_all();
+        inner.allocator.take().unwrap()
     }
 }
 
@@ -257,7 +291,7 @@ where
     A: Allocator,
 {
     fn get_segment(&self, id: u32) -> Result<(*const u8, u32)> {
-        let seg = &self.inner.segments[id as usize];
+        let seg = &self.inner().segments[id as usize];
         Ok((seg.ptr, seg.allocated))
     }
 
@@ -274,6 +308,10 @@ where
         Ok(())
     }
 
+    fn check_interval(&self, _id: u32, _start: *const u8, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
     fn amplified_read(&self, _virtual_amount: u64) -> Result<()> {
         Ok(())
     }
@@ -341,7 +379,7 @@ where
         }
     }
 
-    fn get_segment_mut(&mut self, id: u32) -> (*mut u8, u32) {
+    fn get_segment_mut(&self, id: u32) -> (*mut u8, u32) {
         let seg = &self.segments[id as usize];
         (seg.ptr, seg.capacity)
     }
@@ -351,16 +389,16 @@ impl<A> BuilderArena for BuilderArenaImpl<A>
 where
     A: Allocator,
 {
-    fn allocate(&mut self, segment_id: u32, amount: WordCount32) -> Option<u32> {
-        self.inner.allocate(segment_id, amount)
+    fn allocate(&self, segment_id: u32, amount: WordCount32) -> Option<u32> {
+        unsafe { self.inner_mut().allocate(segment_id, amount) }
     }
 
-    fn allocate_anywhere(&mut self, amount: u32) -> (SegmentId, u32) {
-        self.inner.allocate_anywhere(amount)
+    fn allocate_anywhere(&self, amount: u32) -> (SegmentId, u32) {
+        unsafe { self.inner_mut().allocate_anywhere(amount) }
     }
 
-    fn get_segment_mut(&mut self, id: u32) -> (*mut u8, u32) {
-        self.inner.get_segment_mut(id)
+    fn get_segment_mut(&self, id: u32) -> (*mut u8, u32) {
+        self.inner().get_segment_mut(id)
     }
 
     fn as_reader(&self) -> &dyn ReaderArena {
@@ -397,6 +435,10 @@ impl ReaderArena for NullArena {
         Ok(())
     }
 
+    fn check_interval(&self, _id: u32, _start: *const u8, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
     fn amplified_read(&self, _virtual_amount: u64) -> Result<()> {
         Ok(())
     }
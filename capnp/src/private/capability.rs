@@ -22,28 +22,46 @@
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
-use crate::any_pointer;
-use crate::capability::{Params, Promise, RemotePromise, Request, Results};
+use super::super::any_pointer;
+use super::super::capability::{
+    MaybeSend, MaybeSync, Params, Promise, RemotePromise, Request, Results,
+};
 use crate::MessageSize;
 
-pub trait ResponseHook {
+pub use super::super::flavor::Rc;
+pub use super::layout::PipelineOp;
+
+pub trait ResponseHook: MaybeSend + MaybeSync {
     fn get(&self) -> crate::Result<any_pointer::Reader<'_>>;
 }
 
-pub trait RequestHook {
+/// A call sent by [`RequestHook::tail_send()`]: its question id, a promise that resolves when it
+/// has completed, and its pipeline.
+pub type TailSend = (u32, Promise<(), crate::Error>, Box<dyn PipelineHook>);
+
+pub trait RequestHook: MaybeSend {
     fn get(&mut self) -> any_pointer::Builder<'_>;
     fn get_brand(&self) -> usize;
     fn send(self: Box<Self>) -> RemotePromise<any_pointer::Owned>;
-    fn tail_send(
-        self: Box<Self>,
-    ) -> Option<(
-        u32,
-        crate::capability::Promise<(), crate::Error>,
-        Box<dyn PipelineHook>,
-    )>;
+
+    /// Sends the call as a tail call, asking the receiver to hold on to the results, to be picked
+    /// up by a `Return.takeFromOtherQuestion`. Gives the request back if it cannot be sent that
+    /// way, e.g. because its target has been redirected elsewhere.
+    fn tail_send(self: Box<Self>) -> Result<TailSend, Box<dyn RequestHook>>;
+
+    /// Sends a call to a method declared with `-> stream`. The returned promise resolves when the
+    /// caller may send the next call. By default that is when this call completes, which gives
+    /// correct results but no pipelining.
+    fn send_streaming(self: Box<Self>) -> Promise<(), crate::Error> {
+        let promise = self.send().promise;
+        Promise::from_future(async move {
+            promise.await?;
+            Ok(())
+        })
+    }
 }
 
-pub trait ClientHook {
+pub trait ClientHook: MaybeSend + MaybeSync {
     fn add_ref(&self) -> Box<dyn ClientHook>;
     fn new_call(
         &self,
@@ -58,7 +76,7 @@ pub trait ClientHook {
         method_id: u16,
         params: Box<dyn ParamsHook>,
         results: Box<dyn ResultsHook>,
-    ) -> crate::capability::Promise<(), crate::Error>;
+    ) -> Promise<(), crate::Error>;
 
     /// If this capability is associated with an rpc connection, then this method
     /// returns an identifier for that connection.
@@ -77,9 +95,7 @@ pub trait ClientHook {
     /// promise that eventually resolves to a new client that is closer to being the final, settled
     /// client (i.e. the value eventually returned by `getResolved()`).  Calling this repeatedly
     /// should eventually produce a settled client.
-    fn when_more_resolved(
-        &self,
-    ) -> Option<crate::capability::Promise<Box<dyn ClientHook>, crate::Error>>;
+    fn when_more_resolved(&self) -> Option<Promise<Box<dyn ClientHook>, crate::Error>>;
 
     /// Repeatedly calls whenMoreResolved() until it returns nullptr.
     fn when_resolved(&self) -> Promise<(), crate::Error>;
@@ -91,21 +107,23 @@ impl Clone for Box<dyn ClientHook> {
     }
 }
 
-pub trait ResultsHook {
+pub trait ResultsHook: MaybeSend {
     fn get(&mut self) -> crate::Result<any_pointer::Builder<'_>>;
     fn allow_cancellation(&self);
+
+    /// Completes the call with the results of `request`, which is sent in its place. The returned
+    /// promise resolves when `request` has completed.
     fn tail_call(self: Box<Self>, request: Box<dyn RequestHook>) -> Promise<(), crate::Error>;
+
+    /// Like `tail_call()`, but also returns the pipeline of `request`.
     fn direct_tail_call(
         self: Box<Self>,
         request: Box<dyn RequestHook>,
-    ) -> (
-        crate::capability::Promise<(), crate::Error>,
-        Box<dyn PipelineHook>,
-    );
+    ) -> (Promise<(), crate::Error>, Box<dyn PipelineHook>);
 }
 
-pub trait ParamsHook {
-    fn get(&self) -> crate::Result<crate::any_pointer::Reader<'_>>;
+pub trait ParamsHook: MaybeSend {
+    fn get(&self) -> crate::Result<any_pointer::Reader<'_>>;
 }
 
 // Where should this live?
@@ -130,7 +148,7 @@ pub fn internal_get_untyped_results<T>(typeful: Results<T>) -> Results<any_point
     }
 }
 
-pub trait PipelineHook {
+pub trait PipelineHook: MaybeSend + MaybeSync {
     fn add_ref(&self) -> Box<dyn PipelineHook>;
     fn get_pipelined_cap(&self, ops: &[PipelineOp]) -> Box<dyn ClientHook>;
 
@@ -146,9 +164,3 @@ impl Clone for Box<dyn PipelineHook> {
         self.add_ref()
     }
 }
-
-#[derive(Clone, Copy)]
-pub enum PipelineOp {
-    Noop,
-    GetPointerField(u16),
-}
@@ -23,6 +23,7 @@ use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::cell::Cell;
+use core::hash::Hasher;
 use core::mem;
 use core::ptr;
 
@@ -33,6 +34,8 @@ use crate::private::mask::Mask;
 use crate::private::primitive::{Primitive, WireValue};
 use crate::private::units::*;
 use crate::private::zero;
+#[cfg(feature = "sync_rpc")]
+use crate::sync::private::capability::ClientHook as SyncClientHook;
 use crate::text;
 use crate::{MessageSize, Result};
 
@@ -351,12 +354,11 @@ mod wire_helpers {
 
     use crate::data;
     use crate::private::arena::*;
-    use crate::private::capability::ClientHook;
     use crate::private::layout::ElementSize::*;
     use crate::private::layout::{data_bits_per_element, pointers_per_element};
     use crate::private::layout::{
-        CapTableBuilder, CapTableReader, ElementSize, ListBuilder, ListReader, StructBuilder,
-        StructReader, StructSize, WirePointer, WirePointerKind,
+        AnyClientHook, CapHook, CapTableBuilder, CapTableReader, ElementSize, ListBuilder,
+        ListReader, StructBuilder, StructReader, StructSize, WirePointer, WirePointerKind,
     };
     use crate::private::units::*;
     use crate::text;
@@ -408,7 +410,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn allocate(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         amount: WordCount32,
@@ -459,7 +461,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn follow_builder_fars(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         ref_target: *mut u8,
         segment_id: u32,
@@ -544,11 +546,7 @@ mod wire_helpers {
         }
     }
 
-    pub unsafe fn zero_object(
-        arena: &mut dyn BuilderArena,
-        segment_id: u32,
-        reff: *mut WirePointer,
-    ) {
+    pub unsafe fn zero_object(arena: &dyn BuilderArena, segment_id: u32, reff: *mut WirePointer) {
         //# Zero out the pointed-to object. Use when the pointer is
         //# about to be overwritten making the target object no longer
         //# reachable.
@@ -582,7 +580,7 @@ mod wire_helpers {
     }
 
     pub unsafe fn zero_object_helper(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         segment_id: u32,
         tag: *mut WirePointer,
         ptr: *mut u8,
@@ -664,7 +662,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn zero_pointer_and_fars(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         _segment_id: u32,
         reff: *mut WirePointer,
     ) -> Result<()> {
@@ -835,7 +833,7 @@ mod wire_helpers {
 
     // Helper for copy_message().
     unsafe fn copy_struct(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         segment_id: u32,
         cap_table: CapTableBuilder,
         dst: *mut u8,
@@ -862,7 +860,7 @@ mod wire_helpers {
     // Copies from a trusted message.
     // Returns (new_dst_ptr, new_dst, new_segment_id).
     pub unsafe fn copy_message(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         segment_id: u32,
         cap_table: CapTableBuilder,
         dst: *mut WirePointer,
@@ -996,8 +994,226 @@ mod wire_helpers {
         }
     }
 
+    // Deep-copies the object graph rooted at `src`, following far pointers and dropping any
+    // space that is not reachable from it. Unlike copy_pointer(), capability pointers are
+    // copied verbatim rather than going through the cap tables, so the copy keeps referring
+    // to the same cap table entries as the original.
+    pub unsafe fn compact_pointer(
+        dst_arena: &dyn BuilderArena,
+        dst_segment_id: u32,
+        dst: *mut WirePointer,
+        src_arena: &dyn ReaderArena,
+        src_segment_id: u32,
+        src: *const WirePointer,
+        nesting_limit: i32,
+    ) -> Result<()> {
+        if (*src).is_null() {
+            ptr::write_bytes(dst, 0, 1);
+            return Ok(());
+        }
+
+        if nesting_limit <= 0 {
+            return Err(Error::failed("Message is too deeply nested.".to_string()));
+        }
+
+        let (src_ptr, src, src_segment_id) = follow_fars(src_arena, src, src_segment_id)?;
+
+        match (*src).kind() {
+            WirePointerKind::Struct => {
+                let data_size = (*src).struct_data_size();
+                let pointer_count = (*src).struct_ptr_count();
+                bounds_check(
+                    src_arena,
+                    src_segment_id,
+                    src_ptr,
+                    (*src).struct_word_size() as usize,
+                    WirePointerKind::Struct,
+                )?;
+                let (dst_ptr, dst, dst_segment_id) = allocate(
+                    dst_arena,
+                    dst,
+                    dst_segment_id,
+                    (*src).struct_word_size(),
+                    WirePointerKind::Struct,
+                );
+                (*dst).set_struct_size_from_pieces(data_size, pointer_count);
+                compact_struct(
+                    dst_arena,
+                    dst_segment_id,
+                    dst_ptr,
+                    src_arena,
+                    src_segment_id,
+                    src_ptr,
+                    data_size,
+                    pointer_count,
+                    nesting_limit - 1,
+                )
+            }
+            WirePointerKind::List => match (*src).list_element_size() {
+                ElementSize::Void
+                | ElementSize::Bit
+                | ElementSize::Byte
+                | ElementSize::TwoBytes
+                | ElementSize::FourBytes
+                | ElementSize::EightBytes => {
+                    let word_count = round_bits_up_to_words(
+                        u64::from((*src).list_element_count())
+                            * u64::from(data_bits_per_element((*src).list_element_size())),
+                    );
+                    bounds_check(
+                        src_arena,
+                        src_segment_id,
+                        src_ptr,
+                        word_count as usize,
+                        WirePointerKind::List,
+                    )?;
+                    let (dst_ptr, dst, _) = allocate(
+                        dst_arena,
+                        dst,
+                        dst_segment_id,
+                        word_count,
+                        WirePointerKind::List,
+                    );
+                    ptr::copy_nonoverlapping(
+                        src_ptr,
+                        dst_ptr,
+                        word_count as usize * BYTES_PER_WORD,
+                    );
+                    (*dst).set_list_size_and_count(
+                        (*src).list_element_size(),
+                        (*src).list_element_count(),
+                    );
+                    Ok(())
+                }
+                ElementSize::Pointer => {
+                    let count = (*src).list_element_count();
+                    bounds_check(
+                        src_arena,
+                        src_segment_id,
+                        src_ptr,
+                        count as usize * WORDS_PER_POINTER,
+                        WirePointerKind::List,
+                    )?;
+                    let (dst_ptr, dst, dst_segment_id) =
+                        allocate(dst_arena, dst, dst_segment_id, count, WirePointerKind::List);
+                    (*dst).set_list_size_and_count(ElementSize::Pointer, count);
+                    let src_refs = src_ptr as *const WirePointer;
+                    let dst_refs = dst_ptr as *mut WirePointer;
+                    for ii in 0..count as usize {
+                        compact_pointer(
+                            dst_arena,
+                            dst_segment_id,
+                            dst_refs.add(ii),
+                            src_arena,
+                            src_segment_id,
+                            src_refs.add(ii),
+                            nesting_limit - 1,
+                        )?;
+                    }
+                    Ok(())
+                }
+                ElementSize::InlineComposite => {
+                    let word_count = (*src).list_inline_composite_word_count();
+                    bounds_check(
+                        src_arena,
+                        src_segment_id,
+                        src_ptr,
+                        word_count as usize + POINTER_SIZE_IN_WORDS,
+                        WirePointerKind::List,
+                    )?;
+                    let src_tag = src_ptr as *const WirePointer;
+                    if (*src_tag).kind() != WirePointerKind::Struct {
+                        return Err(Error::failed(
+                            "InlineComposite lists of non-STRUCT type are not supported."
+                                .to_string(),
+                        ));
+                    }
+                    let element_count = (*src_tag).inline_composite_list_element_count();
+                    let words_per_element = (*src_tag).struct_word_size();
+                    let actual_size = u64::from(words_per_element) * u64::from(element_count);
+                    if actual_size > u64::from(word_count) {
+                        return Err(Error::failed(
+                            "InlineComposite list's elements overrun its word count.".to_string(),
+                        ));
+                    }
+                    let actual_size = actual_size as u32;
+
+                    let (dst_ptr, dst, dst_segment_id) = allocate(
+                        dst_arena,
+                        dst,
+                        dst_segment_id,
+                        actual_size + POINTER_SIZE_IN_WORDS as u32,
+                        WirePointerKind::List,
+                    );
+                    (*dst).set_list_inline_composite(actual_size);
+                    ptr::copy_nonoverlapping(src_tag, dst_ptr as *mut WirePointer, 1);
+
+                    let data_size = (*src_tag).struct_data_size();
+                    let pointer_count = (*src_tag).struct_ptr_count();
+                    let mut src_element = src_ptr.add(BYTES_PER_WORD);
+                    let mut dst_element = dst_ptr.add(BYTES_PER_WORD);
+                    for _ in 0..element_count {
+                        compact_struct(
+                            dst_arena,
+                            dst_segment_id,
+                            dst_element,
+                            src_arena,
+                            src_segment_id,
+                            src_element,
+                            data_size,
+                            pointer_count,
+                            nesting_limit - 1,
+                        )?;
+                        src_element = src_element.add(words_per_element as usize * BYTES_PER_WORD);
+                        dst_element = dst_element.add(words_per_element as usize * BYTES_PER_WORD);
+                    }
+                    Ok(())
+                }
+            },
+            WirePointerKind::Far => Err(Error::failed("Malformed double-far pointer.".to_string())),
+            WirePointerKind::Other => {
+                if !(*src).is_capability() {
+                    return Err(Error::failed("Unknown pointer type.".to_string()));
+                }
+                ptr::copy_nonoverlapping(src, dst, 1);
+                Ok(())
+            }
+        }
+    }
+
+    // Helper for compact_pointer().
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn compact_struct(
+        dst_arena: &dyn BuilderArena,
+        dst_segment_id: u32,
+        dst: *mut u8,
+        src_arena: &dyn ReaderArena,
+        src_segment_id: u32,
+        src: *const u8,
+        data_size: WordCount16,
+        pointer_count: WirePointerCount16,
+        nesting_limit: i32,
+    ) -> Result<()> {
+        ptr::copy_nonoverlapping(src, dst, data_size as usize * BYTES_PER_WORD);
+
+        let src_refs = src.add(data_size as usize * BYTES_PER_WORD) as *const WirePointer;
+        let dst_refs = dst.add(data_size as usize * BYTES_PER_WORD) as *mut WirePointer;
+        for ii in 0..pointer_count as usize {
+            compact_pointer(
+                dst_arena,
+                dst_segment_id,
+                dst_refs.add(ii),
+                src_arena,
+                src_segment_id,
+                src_refs.add(ii),
+                nesting_limit,
+            )?;
+        }
+        Ok(())
+    }
+
     pub unsafe fn transfer_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         dst_segment_id: u32,
         dst: *mut WirePointer,
         src_segment_id: u32,
@@ -1033,7 +1249,7 @@ mod wire_helpers {
     }
 
     pub unsafe fn transfer_pointer_split(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         dst_segment_id: u32,
         dst: *mut WirePointer,
         src_segment_id: u32,
@@ -1107,7 +1323,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn init_struct_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1135,7 +1351,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn get_writable_struct_pointer<'a>(
-        arena: &'a mut dyn BuilderArena,
+        arena: &'a dyn BuilderArena,
         mut reff: *mut WirePointer,
         mut segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1251,7 +1467,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn init_list_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1287,7 +1503,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn init_struct_list_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1332,7 +1548,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn get_writable_list_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         mut orig_ref: *mut WirePointer,
         mut orig_segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1470,7 +1686,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn get_writable_struct_list_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         mut orig_ref: *mut WirePointer,
         mut orig_segment_id: u32,
         cap_table: CapTableBuilder,
@@ -1716,7 +1932,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn init_text_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         size: ByteCount32,
@@ -1745,7 +1961,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn set_text_pointer<'a>(
-        arena: &'a mut dyn BuilderArena,
+        arena: &'a dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         value: &str,
@@ -1759,7 +1975,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn get_writable_text_pointer<'a>(
-        arena: &'a mut dyn BuilderArena,
+        arena: &'a dyn BuilderArena,
         mut reff: *mut WirePointer,
         mut segment_id: u32,
         default: Option<&'a [crate::Word]>,
@@ -1815,7 +2031,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn init_data_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         size: ByteCount32,
@@ -1840,7 +2056,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn set_data_pointer<'a>(
-        arena: &'a mut dyn BuilderArena,
+        arena: &'a dyn BuilderArena,
         reff: *mut WirePointer,
         segment_id: u32,
         value: &[u8],
@@ -1852,7 +2068,7 @@ mod wire_helpers {
 
     #[inline]
     pub unsafe fn get_writable_data_pointer<'a>(
-        arena: &'a mut dyn BuilderArena,
+        arena: &'a dyn BuilderArena,
         mut reff: *mut WirePointer,
         mut segment_id: u32,
         default: Option<&'a [crate::Word]>,
@@ -1898,8 +2114,195 @@ mod wire_helpers {
         ))
     }
 
+    /// Resizes the list pointed to by `reff` so that it holds `new_count` elements. Shrinking
+    /// happens in place; growing moves the existing elements into a fresh allocation.
+    /// If `is_text` is true, the list is a byte list with a NUL terminator that is not
+    /// included in `new_count`.
+    pub unsafe fn truncate_list_pointer(
+        arena: &dyn BuilderArena,
+        reff: *mut WirePointer,
+        segment_id: u32,
+        cap_table: CapTableBuilder,
+        new_count: ElementCount32,
+        is_text: bool,
+    ) -> Result<()> {
+        let ref_target = WirePointer::mut_target(reff);
+        let (ptr, tag, old_segment_id) = follow_builder_fars(arena, reff, ref_target, segment_id)?;
+
+        if (*tag).kind() != WirePointerKind::List {
+            return Err(Error::failed(
+                "Called truncate() but existing pointer is not a list.".to_string(),
+            ));
+        }
+
+        let element_size = (*tag).list_element_size();
+        if is_text && element_size != Byte {
+            return Err(Error::failed(
+                "Called truncate() on text but existing list pointer is not byte-sized."
+                    .to_string(),
+            ));
+        }
+
+        if element_size == InlineComposite {
+            let element_tag: *mut WirePointer = ptr as *mut _;
+            if (*element_tag).kind() != WirePointerKind::Struct {
+                return Err(Error::failed(
+                    "InlineComposite list with non-STRUCT elements not supported.".to_string(),
+                ));
+            }
+            let old_count = (*element_tag).inline_composite_list_element_count();
+            let data_size = (*element_tag).struct_data_size();
+            let pointer_count = (*element_tag).struct_ptr_count();
+            let element_bytes = (*element_tag).struct_word_size() as usize * BYTES_PER_WORD;
+            let old_elements = ptr.add(BYTES_PER_WORD);
+
+            if new_count <= old_count {
+                for i in new_count as usize..old_count as usize {
+                    let element = old_elements.add(i * element_bytes);
+                    let pointers: *mut WirePointer =
+                        element.add(data_size as usize * BYTES_PER_WORD) as *mut _;
+                    for j in 0..pointer_count as usize {
+                        zero_object(arena, old_segment_id, pointers.add(j));
+                    }
+                    ptr::write_bytes(element, 0, element_bytes);
+                }
+                (*tag).set_list_inline_composite(new_count * (*element_tag).struct_word_size());
+                (*element_tag).set_kind_and_inline_composite_list_element_count(
+                    WirePointerKind::Struct,
+                    new_count,
+                );
+            } else {
+                //# Don't let allocate() zero out the object just yet.
+                zero_pointer_and_fars(arena, segment_id, reff)?;
+
+                let new_list = init_struct_list_pointer(
+                    arena,
+                    reff,
+                    segment_id,
+                    cap_table,
+                    new_count,
+                    StructSize {
+                        data: data_size,
+                        pointers: pointer_count,
+                    },
+                );
+                let new_elements = new_list.ptr;
+                let new_segment_id = new_list.segment_id;
+
+                for i in 0..old_count as usize {
+                    let src = old_elements.add(i * element_bytes);
+                    let dst = new_elements.add(i * element_bytes);
+                    ptr::copy_nonoverlapping(src, dst, data_size as usize * BYTES_PER_WORD);
+
+                    let src_pointers: *mut WirePointer =
+                        src.add(data_size as usize * BYTES_PER_WORD) as *mut _;
+                    let dst_pointers: *mut WirePointer =
+                        dst.add(data_size as usize * BYTES_PER_WORD) as *mut _;
+                    for j in 0..pointer_count as usize {
+                        transfer_pointer(
+                            arena,
+                            new_segment_id,
+                            dst_pointers.add(j),
+                            old_segment_id,
+                            src_pointers.add(j),
+                        );
+                    }
+                }
+
+                // Zero out the old location, including the tag word.
+                ptr::write_bytes(ptr, 0, BYTES_PER_WORD + old_count as usize * element_bytes);
+            }
+        } else {
+            let step = data_bits_per_element(element_size)
+                + pointers_per_element(element_size) * BITS_PER_POINTER as u32;
+            let old_raw_count = (*tag).list_element_count();
+            let (old_count, new_raw_count) = if is_text {
+                if old_raw_count == 0 {
+                    return Err(Error::failed(
+                        "Text blob missing NUL terminator.".to_string(),
+                    ));
+                }
+                (old_raw_count - 1, new_count + 1)
+            } else {
+                (old_raw_count, new_count)
+            };
+            let old_bits = u64::from(old_raw_count) * u64::from(step);
+
+            if new_count <= old_count {
+                if element_size == Pointer {
+                    for i in new_count as usize..old_count as usize {
+                        zero_object(
+                            arena,
+                            old_segment_id,
+                            ptr.add(i * BYTES_PER_WORD) as *mut WirePointer,
+                        );
+                    }
+                }
+
+                // Zero the trailing elements. For a bit list, the first of them may share a
+                // byte with elements that we are keeping.
+                let mut start_bit = u64::from(new_count) * u64::from(step);
+                let partial_bits = (start_bit % BITS_PER_BYTE as u64) as u8;
+                if partial_bits != 0 {
+                    let byte = ptr.add((start_bit / BITS_PER_BYTE as u64) as usize);
+                    *byte &= (1u8 << partial_bits) - 1;
+                    start_bit += u64::from(BITS_PER_BYTE as u8 - partial_bits);
+                }
+                let start_byte = (start_bit / BITS_PER_BYTE as u64) as usize;
+                let end_byte = round_bits_up_to_bytes(old_bits) as usize;
+                if end_byte > start_byte {
+                    ptr::write_bytes(ptr.add(start_byte), 0, end_byte - start_byte);
+                }
+
+                (*tag).set_list_size_and_count(element_size, new_raw_count);
+            } else {
+                //# Don't let allocate() zero out the object just yet.
+                zero_pointer_and_fars(arena, segment_id, reff)?;
+
+                let new_list = init_list_pointer(
+                    arena,
+                    reff,
+                    segment_id,
+                    cap_table,
+                    new_raw_count,
+                    element_size,
+                );
+                let new_ptr = new_list.ptr;
+                let new_segment_id = new_list.segment_id;
+
+                if element_size == Pointer {
+                    for i in 0..old_count as usize {
+                        transfer_pointer(
+                            arena,
+                            new_segment_id,
+                            new_ptr.add(i * BYTES_PER_WORD) as *mut WirePointer,
+                            old_segment_id,
+                            ptr.add(i * BYTES_PER_WORD) as *mut WirePointer,
+                        );
+                    }
+                } else {
+                    // For text, this leaves out the old NUL terminator. The new list is
+                    // zero-initialized, so it already ends with one.
+                    ptr::copy_nonoverlapping(
+                        ptr,
+                        new_ptr,
+                        round_bits_up_to_bytes(u64::from(old_count) * u64::from(step)) as usize,
+                    );
+                }
+
+                ptr::write_bytes(
+                    ptr,
+                    0,
+                    round_bits_up_to_words(old_bits) as usize * BYTES_PER_WORD,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     pub unsafe fn set_struct_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         segment_id: u32,
         cap_table: CapTableBuilder,
         reff: *mut WirePointer,
@@ -1983,20 +2386,22 @@ mod wire_helpers {
     }
 
     pub fn set_capability_pointer(
-        _arena: &mut dyn BuilderArena,
+        _arena: &dyn BuilderArena,
         _segment_id: u32,
         mut cap_table: CapTableBuilder,
         reff: *mut WirePointer,
-        cap: Box<dyn ClientHook>,
-    ) {
+        cap: AnyClientHook,
+    ) -> Result<()> {
         // TODO if ref is not null, zero object.
+        let index = cap_table.inject_cap(cap)?;
         unsafe {
-            (*reff).set_cap(cap_table.inject_cap(cap) as u32);
+            (*reff).set_cap(index as u32);
         }
+        Ok(())
     }
 
     pub unsafe fn set_list_pointer(
-        arena: &mut dyn BuilderArena,
+        arena: &dyn BuilderArena,
         segment_id: u32,
         cap_table: CapTableBuilder,
         reff: *mut WirePointer,
@@ -2165,7 +2570,7 @@ mod wire_helpers {
     }
 
     pub unsafe fn copy_pointer(
-        dst_arena: &mut dyn BuilderArena,
+        dst_arena: &dyn BuilderArena,
         dst_segment_id: u32,
         dst_cap_table: CapTableBuilder,
         dst: *mut WirePointer,
@@ -2344,7 +2749,7 @@ mod wire_helpers {
                 }
                 match src_cap_table.extract_cap((*src).cap_index() as usize) {
                     Some(cap) => {
-                        set_capability_pointer(dst_arena, dst_segment_id, dst_cap_table, dst, cap);
+                        set_capability_pointer(dst_arena, dst_segment_id, dst_cap_table, dst, cap)?;
                         Ok(SegmentAnd {
                             segment_id: dst_segment_id,
                             value: ptr::null_mut(),
@@ -2419,13 +2824,13 @@ mod wire_helpers {
     }
 
     #[inline]
-    pub unsafe fn read_capability_pointer(
+    pub unsafe fn read_capability_pointer<H: CapHook + ?Sized>(
         _arena: &dyn ReaderArena,
         _segment_id: u32,
         cap_table: CapTableReader,
         reff: *const WirePointer,
         _nesting_limit: i32,
-    ) -> Result<Box<dyn ClientHook>> {
+    ) -> Result<Box<H>> {
         if (*reff).is_null() {
             Err(Error::failed(
                 "Message contains null capability pointer.".to_string(),
@@ -2438,7 +2843,7 @@ mod wire_helpers {
         } else {
             let n = (*reff).cap_index() as usize;
             match cap_table.extract_cap(n) {
-                Some(client_hook) => Ok(client_hook),
+                Some(client_hook) => H::from_any(client_hook),
                 None => Err(Error::failed(format!(
                     "Message contains invalid capability pointer. Index: {n}"
                 ))),
@@ -2739,28 +3144,104 @@ static NULL_ARENA: NullArena = NullArena;
 
 pub type CapTable = Vec<Option<Box<dyn ClientHook>>>;
 
+/// The capability table of a message used with the thread-safe capability types in
+/// `crate::sync`.
+#[cfg(feature = "sync_rpc")]
+pub type SyncCapTable = Vec<Option<Box<dyn SyncClientHook>>>;
+
+/// A client hook taken out of, or about to be put into, a capability table.
+pub enum AnyClientHook {
+    Local(Box<dyn ClientHook>),
+    #[cfg(feature = "sync_rpc")]
+    Sync(Box<dyn SyncClientHook>),
+}
+
+/// Implemented by the kinds of client hook that a message can hold: `ClientHook` and, with the
+/// `sync_rpc` feature, its thread-safe counterpart in `crate::sync`. Lets the same (generated)
+/// code get and set capabilities of either kind.
+///
+/// A thread-safe capability read as a single-threaded one is wrapped in a hook that forwards
+/// calls to it. The other way around is an error, since the capability cannot leave its thread.
+pub trait CapHook {
+    fn from_any(hook: AnyClientHook) -> Result<Box<Self>>;
+    fn into_any(self: Box<Self>) -> AnyClientHook;
+}
+
+impl CapHook for dyn ClientHook {
+    fn from_any(hook: AnyClientHook) -> Result<Box<Self>> {
+        match hook {
+            AnyClientHook::Local(hook) => Ok(hook),
+            #[cfg(feature = "sync_rpc")]
+            AnyClientHook::Sync(hook) => Ok(crate::sync::local::new_client(hook)),
+        }
+    }
+
+    fn into_any(self: Box<Self>) -> AnyClientHook {
+        AnyClientHook::Local(self)
+    }
+}
+
+#[cfg(feature = "sync_rpc")]
+impl CapHook for dyn SyncClientHook {
+    fn from_any(hook: AnyClientHook) -> Result<Box<Self>> {
+        match hook {
+            AnyClientHook::Sync(hook) => Ok(hook),
+            AnyClientHook::Local(_) => Err(crate::Error::failed(String::from(
+                "a single-threaded capability cannot be used with the thread-safe capability types",
+            ))),
+        }
+    }
+
+    fn into_any(self: Box<Self>) -> AnyClientHook {
+        AnyClientHook::Sync(self)
+    }
+}
+
+/// A step on the path from a struct to one of its capabilities, for promise pipelining.
+#[derive(Clone, Copy)]
+pub enum PipelineOp {
+    Noop,
+    GetPointerField(u16),
+}
+
 #[derive(Copy, Clone)]
 pub enum CapTableReader {
     // At one point, we had a `Dummy` variant here, but that ended up
     // making values of this type take 16 bytes of memory. Now we instead
-    // represent a null CapTableReader with `Plain(ptr::null())`.
+    // represent a null CapTableReader with `Plain(ptr::null())`. The `Sync`
+    // variant has the same cost, so it only exists with the `sync_rpc` feature.
     Plain(*const Vec<Option<Box<dyn ClientHook>>>),
+    #[cfg(feature = "sync_rpc")]
+    Sync(*const SyncCapTable),
+}
+
+/// Takes a new reference to entry `index` of `hooks`, which may be null.
+unsafe fn extract_from<H: ?Sized>(
+    hooks: *const Vec<Option<Box<H>>>,
+    index: usize,
+    add_ref: impl FnOnce(&H) -> Box<H>,
+) -> Option<Box<H>> {
+    if hooks.is_null() {
+        return None;
+    }
+    let hooks: &Vec<Option<Box<H>>> = &*hooks;
+    if index >= hooks.len() {
+        None
+    } else {
+        hooks[index].as_deref().map(add_ref)
+    }
 }
 
 impl CapTableReader {
-    pub fn extract_cap(&self, index: usize) -> Option<Box<dyn ClientHook>> {
+    pub fn extract_cap(&self, index: usize) -> Option<AnyClientHook> {
         match *self {
-            Self::Plain(hooks) => {
-                if hooks.is_null() {
-                    return None;
-                }
-                let hooks: &Vec<Option<Box<dyn ClientHook>>> = unsafe { &*hooks };
-                if index >= hooks.len() {
-                    None
-                } else {
-                    hooks[index].as_ref().map(|hook| hook.add_ref())
-                }
-            }
+            Self::Plain(hooks) => unsafe {
+                extract_from(hooks, index, ClientHook::add_ref).map(AnyClientHook::Local)
+            },
+            #[cfg(feature = "sync_rpc")]
+            Self::Sync(hooks) => unsafe {
+                extract_from(hooks, index, SyncClientHook::add_ref).map(AnyClientHook::Sync)
+            },
         }
     }
 }
@@ -2771,43 +3252,56 @@ pub enum CapTableBuilder {
     // making values of this type take 16 bytes of memory. Now we instead
     // represent a null CapTableBuilder with `Plain(ptr::null_mut())`.
     Plain(*mut Vec<Option<Box<dyn ClientHook>>>),
+    #[cfg(feature = "sync_rpc")]
+    Sync(*mut SyncCapTable),
+}
+
+/// Gets the table behind `hooks`, panicking if it is null.
+unsafe fn table_mut<'a, T>(hooks: *mut Vec<T>, operation: &str) -> &'a mut Vec<T> {
+    if hooks.is_null() {
+        panic!(
+            "Called {operation}() on a null capability table. You need \
+                to call imbue_mut() on this message before adding capabilities."
+        );
+    }
+    &mut *hooks
 }
 
 impl CapTableBuilder {
     pub fn into_reader(self) -> CapTableReader {
         match self {
             Self::Plain(hooks) => CapTableReader::Plain(hooks),
+            #[cfg(feature = "sync_rpc")]
+            Self::Sync(hooks) => CapTableReader::Sync(hooks),
         }
     }
 
-    pub fn extract_cap(&self, index: usize) -> Option<Box<dyn ClientHook>> {
+    pub fn is_null(&self) -> bool {
         match *self {
-            Self::Plain(hooks) => {
-                if hooks.is_null() {
-                    return None;
-                }
-                let hooks: &Vec<Option<Box<dyn ClientHook>>> = unsafe { &*hooks };
-                if index >= hooks.len() {
-                    None
-                } else {
-                    hooks[index].as_ref().map(|hook| hook.add_ref())
-                }
-            }
+            Self::Plain(hooks) => hooks.is_null(),
+            #[cfg(feature = "sync_rpc")]
+            Self::Sync(hooks) => hooks.is_null(),
         }
     }
 
-    pub fn inject_cap(&mut self, cap: Box<dyn ClientHook>) -> usize {
+    pub fn extract_cap(&self, index: usize) -> Option<AnyClientHook> {
+        self.into_reader().extract_cap(index)
+    }
+
+    /// Adds `cap` to the table and returns its index. Fails if `cap` is a single-threaded
+    /// capability and this is the table of a thread-safe message.
+    pub fn inject_cap(&mut self, cap: AnyClientHook) -> Result<usize> {
         match *self {
             Self::Plain(hooks) => {
-                if hooks.is_null() {
-                    panic!(
-                        "Called inject_cap() on a null capability table. You need \
-                            to call imbue_mut() on this message before adding capabilities."
-                    );
-                }
-                let hooks: &mut Vec<Option<Box<dyn ClientHook>>> = unsafe { &mut *hooks };
-                hooks.push(Some(cap));
-                hooks.len() - 1
+                let hooks = unsafe { table_mut(hooks, "inject_cap") };
+                hooks.push(Some(CapHook::from_any(cap)?));
+                Ok(hooks.len() - 1)
+            }
+            #[cfg(feature = "sync_rpc")]
+            Self::Sync(hooks) => {
+                let hooks = unsafe { table_mut(hooks, "inject_cap") };
+                hooks.push(Some(CapHook::from_any(cap)?));
+                Ok(hooks.len() - 1)
             }
         }
     }
@@ -2815,13 +3309,14 @@ impl CapTableBuilder {
     pub fn drop_cap(&mut self, index: usize) {
         match *self {
             Self::Plain(hooks) => {
-                if hooks.is_null() {
-                    panic!(
-                        "Called drop_cap() on a null capability table. You need \
-                            to call imbue_mut() on this message before adding capabilities."
-                    );
+                let hooks = unsafe { table_mut(hooks, "drop_cap") };
+                if index < hooks.len() {
+                    hooks[index] = None;
                 }
-                let hooks: &mut Vec<Option<Box<dyn ClientHook>>> = unsafe { &mut *hooks };
+            }
+            #[cfg(feature = "sync_rpc")]
+            Self::Sync(hooks) => {
+                let hooks = unsafe { table_mut(hooks, "drop_cap") };
                 if index < hooks.len() {
                     hooks[index] = None;
                 }
@@ -2830,6 +3325,43 @@ impl CapTableBuilder {
     }
 }
 
+/// Reads through another arena without counting the words against its traversal limit.
+///
+/// `total_eq()` and `total_hash()` read through this, so that comparing and hashing readers
+/// gives the same answer no matter how often it is done.
+struct UnmeteredArena<'a>(&'a dyn ReaderArena);
+
+impl ReaderArena for UnmeteredArena<'_> {
+    fn get_segment(&self, id: u32) -> Result<(*const u8, u32)> {
+        self.0.get_segment(id)
+    }
+
+    unsafe fn check_offset(
+        &self,
+        segment_id: u32,
+        start: *const u8,
+        offset_in_words: i32,
+    ) -> Result<*const u8> {
+        unsafe { self.0.check_offset(segment_id, start, offset_in_words) }
+    }
+
+    fn contains_interval(&self, segment_id: u32, start: *const u8, size: usize) -> Result<()> {
+        self.0.check_interval(segment_id, start, size)
+    }
+
+    fn check_interval(&self, segment_id: u32, start: *const u8, size: usize) -> Result<()> {
+        self.0.check_interval(segment_id, start, size)
+    }
+
+    fn amplified_read(&self, _virtual_amount: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn nesting_limit(&self) -> i32 {
+        self.0.nesting_limit()
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct PointerReader<'a> {
     arena: &'a dyn ReaderArena,
@@ -2999,7 +3531,7 @@ impl<'a> PointerReader<'a> {
         unsafe { wire_helpers::read_data_pointer(self.arena, self.segment_id, reff, default) }
     }
 
-    pub fn get_capability(&self) -> Result<Box<dyn ClientHook>> {
+    pub fn get_capability<H: CapHook + ?Sized>(&self) -> Result<Box<H>> {
         let reff: *const WirePointer = if self.pointer.is_null() {
             zero_pointer()
         } else {
@@ -3066,10 +3598,77 @@ impl<'a> PointerReader<'a> {
             PointerType::Capability => Ok(false),
         }
     }
+
+    /// Checks whether `self` and `other` point to semantically equal objects. Segment layout,
+    /// far pointers, and the sizes of struct sections do not matter: a struct section that is
+    /// shorter than its counterpart compares as if it were padded with zeroes and null pointers.
+    /// Lists must have the same element encoding. Capabilities are equal if they resolve to the
+    /// same `ClientHook`.
+    pub fn equals(&self, other: &PointerReader) -> Result<bool> {
+        match (self.get_pointer_type()?, other.get_pointer_type()?) {
+            (PointerType::Null, PointerType::Null) => Ok(true),
+            (PointerType::Struct, PointerType::Struct) => {
+                self.get_struct(None)?.equals(&other.get_struct(None)?)
+            }
+            (PointerType::List, PointerType::List) => self
+                .get_list_any_size(ptr::null())?
+                .equals(&other.get_list_any_size(ptr::null())?),
+            (PointerType::Capability, PointerType::Capability) => {
+                let this: Box<dyn ClientHook> = self.get_capability()?;
+                let other: Box<dyn ClientHook> = other.get_capability()?;
+                Ok(this.get_ptr() == other.get_ptr())
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Feeds the pointed-to object into `state`, such that objects that are equal according to
+    /// `equals()` produce the same hash.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) -> Result<()> {
+        match self.get_pointer_type()? {
+            PointerType::Null => state.write_u8(0),
+            PointerType::Struct => {
+                state.write_u8(1);
+                self.get_struct(None)?.hash_into(state)?;
+            }
+            PointerType::List => {
+                state.write_u8(2);
+                self.get_list_any_size(ptr::null())?.hash_into(state)?;
+            }
+            PointerType::Capability => {
+                state.write_u8(3);
+                state.write_usize(self.get_capability::<dyn ClientHook>()?.get_ptr());
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `equals()`, but reads without counting against the traversal limit, and treats
+    /// readers that fail to decode as unequal to everything, themselves included.
+    pub fn total_eq(&self, other: &PointerReader) -> bool {
+        let arena = UnmeteredArena(self.arena);
+        let other_arena = UnmeteredArena(other.arena);
+        matches!(
+            self.unmetered(&arena).equals(&other.unmetered(&other_arena)),
+            Ok(true)
+        )
+    }
+
+    /// Like `hash_into()`, but reads without counting against the traversal limit. Since
+    /// readers that fail to decode are unequal to everything, whatever was fed into `state`
+    /// before the error is as good a hash as any.
+    pub fn total_hash<H: Hasher>(&self, state: &mut H) {
+        let arena = UnmeteredArena(self.arena);
+        let _ = self.unmetered(&arena).hash_into(state);
+    }
+
+    fn unmetered<'b>(&self, arena: &'b UnmeteredArena<'_>) -> PointerReader<'b> {
+        PointerReader { arena, ..*self }
+    }
 }
 
 pub struct PointerBuilder<'a> {
-    arena: &'a mut dyn BuilderArena,
+    arena: &'a dyn BuilderArena,
     segment_id: u32,
     cap_table: CapTableBuilder,
     pointer: *mut WirePointer,
@@ -3077,7 +3676,7 @@ pub struct PointerBuilder<'a> {
 
 impl<'a> PointerBuilder<'a> {
     #[inline]
-    pub fn get_root(arena: &'a mut dyn BuilderArena, segment_id: u32, location: *mut u8) -> Self {
+    pub fn get_root(arena: &'a dyn BuilderArena, segment_id: u32, location: *mut u8) -> Self {
         PointerBuilder {
             arena,
             cap_table: CapTableBuilder::Plain(ptr::null_mut()),
@@ -3184,7 +3783,7 @@ impl<'a> PointerBuilder<'a> {
         }
     }
 
-    pub fn get_capability(&self) -> Result<Box<dyn ClientHook>> {
+    pub fn get_capability<H: CapHook + ?Sized>(&self) -> Result<Box<H>> {
         unsafe {
             wire_helpers::read_capability_pointer(
                 self.arena.as_reader(),
@@ -3294,14 +3893,18 @@ impl<'a> PointerBuilder<'a> {
         }
     }
 
-    pub fn set_capability(&mut self, cap: Box<dyn ClientHook>) {
-        wire_helpers::set_capability_pointer(
+    /// Panics if `cap` is a single-threaded capability and this is a message of the thread-safe
+    /// capability types.
+    pub fn set_capability<H: CapHook + ?Sized>(&mut self, cap: Box<H>) {
+        if let Err(e) = wire_helpers::set_capability_pointer(
             self.arena,
             self.segment_id,
             self.cap_table,
             self.pointer,
-            cap,
-        );
+            cap.into_any(),
+        ) {
+            panic!("{e}");
+        }
     }
 
     pub fn copy_from(&mut self, other: PointerReader, canonicalize: bool) -> Result<()> {
@@ -3331,6 +3934,28 @@ impl<'a> PointerBuilder<'a> {
         Ok(())
     }
 
+    /// Like `copy_from()`, but leaves capability pointers pointing at their original cap table
+    /// indices. Used to compact a message that shares its cap table with the copy.
+    pub fn compact_from(&mut self, other: PointerReader) -> Result<()> {
+        if !self.is_null() {
+            self.clear();
+        }
+        if !other.is_null() {
+            unsafe {
+                wire_helpers::compact_pointer(
+                    self.arena,
+                    self.segment_id,
+                    self.pointer,
+                    other.arena,
+                    other.segment_id,
+                    other.pointer,
+                    other.nesting_limit,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn clear(&mut self) {
         unsafe {
             wire_helpers::zero_object(self.arena, self.segment_id, self.pointer);
@@ -3357,6 +3982,143 @@ impl<'a> PointerBuilder<'a> {
             nesting_limit: 0x7fffffff,
         }
     }
+
+    /// Detaches the pointed-to object from this pointer, which becomes null. The object stays in
+    /// the message as an orphan.
+    pub fn disown(&mut self) -> OrphanBuilder<'a> {
+        unsafe { OrphanBuilder::disown(self.arena, self.segment_id, self.cap_table, self.pointer) }
+    }
+
+    /// Makes this pointer point at `orphan`, which must live in the same message. The
+    /// previous target of the pointer, if any, is zeroed out.
+    pub fn adopt(&mut self, mut orphan: OrphanBuilder<'_>) -> Result<()> {
+        let arena: *const dyn BuilderArena = self.arena;
+        if orphan.arena as *const dyn BuilderArena as *const u8 != arena as *const u8 {
+            return Err(crate::Error::failed(
+                "Adopted object must live in the same message.".into(),
+            ));
+        }
+        unsafe {
+            wire_helpers::zero_object(self.arena, self.segment_id, self.pointer);
+            ptr::write_bytes(self.pointer, 0, 1);
+            wire_helpers::transfer_pointer(
+                self.arena,
+                self.segment_id,
+                self.pointer,
+                orphan.segment_id,
+                orphan.slot,
+            );
+            ptr::write_bytes(orphan.slot, 0, 1);
+        }
+        orphan.slot = ptr::null_mut();
+        Ok(())
+    }
+
+    pub(crate) fn get_orphanage(&mut self) -> crate::orphan::Orphanage<'a> {
+        crate::orphan::Orphanage::new(self.arena, self.segment_id, self.cap_table)
+    }
+}
+
+/// An object that lives in a message but is not reachable from the message's root.
+///
+/// Rather than tracking a detached tag like the C++ implementation does, an orphan owns a
+/// one-word slot in the arena that holds an ordinary pointer to the object. This lets all
+/// of the usual `PointerBuilder` machinery operate on orphans, at the cost of leaving that
+/// word unused once the orphan is adopted. When an orphan is dropped without having been
+/// adopted, its object is zeroed out.
+pub struct OrphanBuilder<'a> {
+    arena: &'a dyn BuilderArena,
+    segment_id: u32,
+    cap_table: CapTableBuilder,
+    slot: *mut WirePointer,
+}
+
+impl<'a> OrphanBuilder<'a> {
+    /// Allocates a null orphan, preferably in the segment `segment_id`.
+    pub fn new(arena: &'a dyn BuilderArena, segment_id: u32, cap_table: CapTableBuilder) -> Self {
+        let (segment_id, word_idx) = match arena.allocate(segment_id, 1) {
+            Some(word_idx) => (segment_id, word_idx),
+            None => arena.allocate_anywhere(1),
+        };
+        let (seg_start, _seg_len) = arena.get_segment_mut(segment_id);
+        let slot: *mut WirePointer =
+            unsafe { (seg_start as *mut WirePointer).offset(word_idx as isize) };
+        unsafe { ptr::write_bytes(slot, 0, 1) };
+        OrphanBuilder {
+            arena,
+            segment_id,
+            cap_table,
+            slot,
+        }
+    }
+
+    unsafe fn disown(
+        arena: &'a dyn BuilderArena,
+        segment_id: u32,
+        cap_table: CapTableBuilder,
+        pointer: *mut WirePointer,
+    ) -> Self {
+        let result = Self::new(arena, segment_id, cap_table);
+        wire_helpers::transfer_pointer(arena, result.segment_id, result.slot, segment_id, pointer);
+        ptr::write_bytes(pointer, 0, 1);
+        result
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.slot.is_null() || unsafe { (*self.slot).is_null() }
+    }
+
+    pub fn as_pointer_builder(&mut self) -> PointerBuilder<'_> {
+        PointerBuilder {
+            arena: self.arena,
+            segment_id: self.segment_id,
+            cap_table: self.cap_table,
+            pointer: self.slot,
+        }
+    }
+
+    pub fn as_pointer_reader(&self) -> PointerReader<'_> {
+        PointerReader {
+            arena: self.arena.as_reader(),
+            segment_id: self.segment_id,
+            cap_table: self.cap_table.into_reader(),
+            pointer: self.slot,
+            nesting_limit: 0x7fffffff,
+        }
+    }
+
+    /// Resizes the orphaned list, text, or data blob to `size` elements. If `is_text` is true,
+    /// the NUL terminator is kept in place after the last element.
+    pub fn truncate(&mut self, size: ElementCount32, is_text: bool) -> Result<()> {
+        unsafe {
+            wire_helpers::truncate_list_pointer(
+                self.arena,
+                self.slot,
+                self.segment_id,
+                self.cap_table,
+                size,
+                is_text,
+            )
+        }
+    }
+}
+
+impl<'a> Drop for OrphanBuilder<'a> {
+    fn drop(&mut self) {
+        if self.is_null() {
+            return;
+        }
+        unsafe {
+            if (*self.slot).is_capability() {
+                if !self.cap_table.is_null() {
+                    self.cap_table.drop_cap((*self.slot).cap_index() as usize);
+                }
+            } else {
+                wire_helpers::zero_object(self.arena, self.segment_id, self.slot);
+            }
+            ptr::write_bytes(self.slot, 0, 1);
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -3566,10 +4328,75 @@ impl<'a> StructReader<'a> {
 
         Ok(true)
     }
+
+    /// See `PointerReader::equals()`.
+    pub fn equals(&self, other: &StructReader) -> Result<bool> {
+        let data = self.get_data_section_as_blob();
+        let other_data = other.get_data_section_as_blob();
+        for idx in 0..core::cmp::max(data.len(), other_data.len()) {
+            if data.get(idx).copied().unwrap_or(0) != other_data.get(idx).copied().unwrap_or(0) {
+                return Ok(false);
+            }
+        }
+
+        let pointer_count = core::cmp::max(self.pointer_count, other.pointer_count);
+        for ptr_idx in 0..pointer_count as usize {
+            if !self
+                .get_pointer_field(ptr_idx)
+                .equals(&other.get_pointer_field(ptr_idx))?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// See `PointerReader::hash_into()`. Trailing zero bytes and null pointers are skipped,
+    /// so that the hash does not depend on the sizes of the struct sections.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) -> Result<()> {
+        let data = self.get_data_section_as_blob();
+        let data_len = data.iter().rposition(|b| *b != 0).map_or(0, |idx| idx + 1);
+        state.write_usize(data_len);
+        state.write(&data[..data_len]);
+
+        let pointer_count = (0..self.pointer_count as usize)
+            .rposition(|ptr_idx| !self.is_pointer_field_null(ptr_idx))
+            .map_or(0, |idx| idx + 1);
+        state.write_usize(pointer_count);
+        for ptr_idx in 0..pointer_count {
+            self.get_pointer_field(ptr_idx).hash_into(state)?;
+        }
+        Ok(())
+    }
+
+    /// See `PointerReader::total_eq()`.
+    pub fn total_eq(&self, other: &StructReader) -> bool {
+        let arena = UnmeteredArena(self.arena);
+        let other_arena = UnmeteredArena(other.arena);
+        matches!(
+            self.unmetered(&arena).equals(&other.unmetered(&other_arena)),
+            Ok(true)
+        )
+    }
+
+    /// See `PointerReader::total_hash()`.
+    pub fn total_hash<H: Hasher>(&self, state: &mut H) {
+        let arena = UnmeteredArena(self.arena);
+        let _ = self.unmetered(&arena).hash_into(state);
+    }
+
+    fn unmetered<'b>(&self, arena: &'b UnmeteredArena<'_>) -> StructReader<'b> {
+        StructReader { arena, ..*self }
+    }
+
+    /// Calls `f` with a copy of this reader that does not count against the traversal limit.
+    pub(crate) fn with_unmetered<R>(&self, f: impl FnOnce(StructReader<'_>) -> R) -> R {
+        f(self.unmetered(&UnmeteredArena(self.arena)))
+    }
 }
 
 pub struct StructBuilder<'a> {
-    arena: &'a mut dyn BuilderArena,
+    arena: &'a dyn BuilderArena,
     cap_table: CapTableBuilder,
     data: *mut u8,
     pointers: *mut WirePointer,
@@ -3685,6 +4512,18 @@ impl<'a> StructBuilder<'a> {
         }
     }
 
+    /// Like `get_pointer_field(ptr_index).disown()`, but leaves this builder usable.
+    pub fn disown_pointer_field(&mut self, ptr_index: WirePointerCount) -> OrphanBuilder<'a> {
+        unsafe {
+            OrphanBuilder::disown(
+                self.arena,
+                self.segment_id,
+                self.cap_table,
+                self.pointers.add(ptr_index),
+            )
+        }
+    }
+
     #[inline]
     pub fn get_pointer_field_mut(&mut self, ptr_index: WirePointerCount) -> PointerBuilder<'_> {
         PointerBuilder {
@@ -3990,10 +4829,105 @@ impl<'a> ListReader<'a> {
             }
         }
     }
+
+    /// See `PointerReader::equals()`.
+    pub fn equals(&self, other: &ListReader) -> Result<bool> {
+        if self.element_size != other.element_size || self.element_count != other.element_count {
+            return Ok(false);
+        }
+        match self.element_size {
+            ElementSize::InlineComposite => {
+                for idx in 0..self.element_count {
+                    if !self
+                        .get_struct_element(idx)
+                        .equals(&other.get_struct_element(idx))?
+                    {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            ElementSize::Pointer => {
+                for idx in 0..self.element_count {
+                    if !self
+                        .get_pointer_element(idx)
+                        .equals(&other.get_pointer_element(idx))?
+                    {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ => {
+                let (bytes, last) = self.primitive_list_bytes();
+                let (other_bytes, other_last) = other.primitive_list_bytes();
+                Ok(bytes == other_bytes && last == other_last)
+            }
+        }
+    }
+
+    /// See `PointerReader::hash_into()`.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) -> Result<()> {
+        state.write_u8(self.element_size as u8);
+        state.write_u32(self.element_count);
+        match self.element_size {
+            ElementSize::InlineComposite => {
+                for idx in 0..self.element_count {
+                    self.get_struct_element(idx).hash_into(state)?;
+                }
+            }
+            ElementSize::Pointer => {
+                for idx in 0..self.element_count {
+                    self.get_pointer_element(idx).hash_into(state)?;
+                }
+            }
+            _ => {
+                let (bytes, last) = self.primitive_list_bytes();
+                state.write(bytes);
+                state.write_u8(last);
+            }
+        }
+        Ok(())
+    }
+
+    /// See `PointerReader::total_eq()`.
+    pub fn total_eq(&self, other: &ListReader) -> bool {
+        let arena = UnmeteredArena(self.arena);
+        let other_arena = UnmeteredArena(other.arena);
+        matches!(
+            self.unmetered(&arena).equals(&other.unmetered(&other_arena)),
+            Ok(true)
+        )
+    }
+
+    /// See `PointerReader::total_hash()`.
+    pub fn total_hash<H: Hasher>(&self, state: &mut H) {
+        let arena = UnmeteredArena(self.arena);
+        let _ = self.unmetered(&arena).hash_into(state);
+    }
+
+    fn unmetered<'b>(&self, arena: &'b UnmeteredArena<'_>) -> ListReader<'b> {
+        ListReader { arena, ..*self }
+    }
+
+    // For lists of primitives, returns the bytes that are entirely covered by elements,
+    // followed by the partially-covered final byte (if any) with the unused bits masked out.
+    fn primitive_list_bytes(self) -> (&'a [u8], u8) {
+        let bits = u64::from(self.step) * u64::from(self.element_count);
+        let full_bytes = (bits / BITS_PER_BYTE as u64) as usize;
+        let leftover_bits = bits % BITS_PER_BYTE as u64;
+        let bytes = self.into_raw_bytes();
+        if leftover_bits > 0 {
+            let mask: u8 = (1 << leftover_bits as u8) - 1;
+            (&bytes[..full_bytes], bytes[full_bytes] & mask)
+        } else {
+            (&bytes[..full_bytes], 0)
+        }
+    }
 }
 
 pub struct ListBuilder<'a> {
-    arena: &'a mut dyn BuilderArena,
+    arena: &'a dyn BuilderArena,
     cap_table: CapTableBuilder,
     ptr: *mut u8,
     segment_id: u32,
@@ -4006,7 +4940,7 @@ pub struct ListBuilder<'a> {
 
 impl<'a> ListBuilder<'a> {
     #[inline]
-    pub fn new_default(arena: &mut dyn BuilderArena) -> ListBuilder<'_> {
+    pub fn new_default(arena: &dyn BuilderArena) -> ListBuilder<'_> {
         ListBuilder {
             arena,
             segment_id: 0,
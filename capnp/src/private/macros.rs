@@ -0,0 +1,62 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Support for the code expanded from the `capnp-macros` crate.
+
+use crate::introspect::{Introspect, TypeVariant};
+use crate::schema::EnumSchema;
+use crate::NotInSchema;
+
+/// Looks up the enumerant of `E` whose schema name is `name`.
+///
+/// Panics if there is no such enumerant.
+pub fn enumerant_by_name<E>(name: &str) -> E
+where
+    E: Introspect + TryFrom<u16, Error = NotInSchema>,
+{
+    let TypeVariant::Enum(raw) = E::introspect().which() else {
+        panic!("expected an enum type for enumerant `{name}`")
+    };
+    let schema = EnumSchema::new(raw);
+    for enumerant in schema.get_enumerants().expect("enum schema") {
+        if enumerant.get_proto().get_name().ok() == Some(name) {
+            return E::try_from(enumerant.get_ordinal()).expect("enumerant in schema");
+        }
+    }
+    panic!("no enumerant named `{name}`")
+}
+
+/// The return values of setters: `()`, or `Result<()>` for those that copy pointers.
+pub trait SetterResult {
+    fn check(self);
+}
+
+impl SetterResult for () {
+    fn check(self) {}
+}
+
+impl SetterResult for crate::Result<()> {
+    fn check(self) {
+        if let Err(e) = self {
+            panic!("failed to set field: {e}")
+        }
+    }
+}
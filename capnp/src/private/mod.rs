@@ -26,11 +26,45 @@
 pub mod arena;
 pub mod capability;
 pub mod layout;
+pub mod macros;
 mod mask;
 mod primitive;
 mod read_limiter;
 pub mod units;
 mod zero;
 
+#[cfg(feature = "serde")]
+pub use ::serde;
+
+/// Implements `serde::Serialize` for a generated struct `Reader`, if the `serde` feature is
+/// enabled. Generated code invokes this, because it cannot check this crate's features itself.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_serialize_for_reader {
+    ([$($params:tt)*] [$($where_clause:tt)*]) => {
+        impl<'a, $($params)*> $crate::private::serde::Serialize for Reader<'a, $($params)*>
+            $($where_clause)*
+        {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: $crate::private::serde::Serializer,
+            {
+                $crate::private::serde::Serialize::serialize(
+                    &$crate::dynamic_value::Reader::from(*self),
+                    serializer,
+                )
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_serialize_for_reader {
+    ($($tokens:tt)*) => {};
+}
+
 #[cfg(test)]
 mod layout_test;
@@ -1,9 +1,36 @@
 //! Convenience wrappers of the datatypes defined in schema.capnp.
+//!
+//! # Looking up types by id
+//!
+//! The global [`registry()`] maps type ids to schemas, so that a value known only by the id of
+//! its type, such as an `AnyPointer` in an envelope that carries a type id, can be read with
+//! [`any_pointer::Reader::get_as_dynamic()`](crate::any_pointer::Reader::get_as_dynamic).
+//!
+//! Generated types are *not* registered automatically: Rust gives a library no portable way to
+//! run code for every linked-in crate, so the registry starts out empty. Each generated file has
+//! a `schemas()` function listing its types, which must be registered once, typically at
+//! startup, for every file whose types should be found by id:
+//!
+//! ```
+//! # fn main() -> capnp::Result<()> {
+//! use capnp::schema::registry;
+//! use capnp::schema_capnp::node;
+//! use capnp::traits::HasTypeId;
+//!
+//! // For your own schemas, this is e.g. `registry().register_all(crate::foo_capnp::schemas())`.
+//! registry().register_all(capnp::schema_capnp::schemas())?;
+//! assert!(registry().get_struct(node::Reader::TYPE_ID).is_ok());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Types that are reached from a registered type through fields, methods, and superclasses are
+//! registered along with it, even if they are defined in another file.
 
 use crate::dynamic_value;
-use crate::introspect::{self, RawBrandedStructSchema, RawEnumSchema};
+use crate::introspect::{self, RawBrandedStructSchema, RawEnumSchema, RawInterfaceSchema};
 use crate::private::layout;
-use crate::schema_capnp::{annotation, enumerant, field, node};
+use crate::schema_capnp::{annotation, enumerant, field, method, node};
 use crate::struct_list;
 use crate::traits::{IndexMove, ListIter, ShortListIter};
 use crate::Result;
@@ -105,7 +132,7 @@ impl StructSchema {
         Ok(AnnotationList {
             annotations: self.proto.get_annotations()?,
             child_index: None,
-            get_annotation_type: self.raw.annotation_types,
+            annotation_types: AnnotationTypes::Struct(self.raw),
         })
     }
 }
@@ -130,7 +157,7 @@ impl Field {
     }
 
     pub fn get_type(&self) -> introspect::Type {
-        (self.parent.raw.field_types)(self.index)
+        self.parent.raw.field_type(self.index)
     }
 
     pub fn get_index(&self) -> u16 {
@@ -141,7 +168,7 @@ impl Field {
         Ok(AnnotationList {
             annotations: self.proto.get_annotations()?,
             child_index: Some(self.index),
-            get_annotation_type: self.parent.raw.annotation_types,
+            annotation_types: AnnotationTypes::Struct(self.parent.raw),
         })
     }
 }
@@ -268,7 +295,7 @@ impl EnumSchema {
         Ok(AnnotationList {
             annotations: self.proto.get_annotations()?,
             child_index: None,
-            get_annotation_type: self.raw.annotation_types,
+            annotation_types: AnnotationTypes::Enum(self.raw),
         })
     }
 }
@@ -304,7 +331,7 @@ impl Enumerant {
         Ok(AnnotationList {
             annotations: self.proto.get_annotations()?,
             child_index: Some(self.ordinal),
-            get_annotation_type: self.parent.raw.annotation_types,
+            annotation_types: AnnotationTypes::Enum(self.parent.raw),
         })
     }
 }
@@ -353,6 +380,242 @@ impl<'a> ::core::iter::IntoIterator for EnumerantList {
     }
 }
 
+/// An interface, with generics applied.
+#[derive(Clone, Copy)]
+pub struct InterfaceSchema {
+    pub(crate) raw: RawInterfaceSchema,
+    pub(crate) proto: node::Reader<'static>,
+}
+
+impl InterfaceSchema {
+    pub fn new(raw: RawInterfaceSchema) -> Self {
+        let proto = crate::any_pointer::Reader::new(unsafe {
+            layout::PointerReader::get_root_unchecked(raw.encoded_node.as_ptr() as *const u8)
+        })
+        .get_as()
+        .unwrap();
+        Self { raw, proto }
+    }
+
+    pub fn get_proto(self) -> node::Reader<'static> {
+        self.proto
+    }
+
+    /// Gets the methods declared directly on this interface, not including those
+    /// inherited from superclasses.
+    pub fn get_methods(self) -> Result<MethodList> {
+        if let node::Interface(i) = self.proto.which()? {
+            Ok(MethodList {
+                methods: i.get_methods()?,
+                parent: self,
+            })
+        } else {
+            panic!()
+        }
+    }
+
+    /// Looks up a method by name, searching superclasses too. Returns `None` if no matching
+    /// method is found.
+    pub fn find_method_by_name(&self, name: &str) -> Result<Option<Method>> {
+        for method in self.get_methods()? {
+            if method.get_proto().get_name()? == name {
+                return Ok(Some(method));
+            }
+        }
+        for superclass in self.get_superclasses()? {
+            if let Some(method) = superclass.find_method_by_name(name)? {
+                return Ok(Some(method));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `find_method_by_name()`, but returns an error if the method is not found.
+    pub fn get_method_by_name(&self, name: &str) -> Result<Method> {
+        if let Some(method) = self.find_method_by_name(name)? {
+            Ok(method)
+        } else {
+            Err(crate::Error::failed(format!(
+                "method \"{}\" not found",
+                name
+            )))
+        }
+    }
+
+    /// Gets the interfaces that this interface directly extends.
+    pub fn get_superclasses(self) -> Result<SuperclassList> {
+        if let node::Interface(i) = self.proto.which()? {
+            Ok(SuperclassList {
+                len: i.get_superclasses()?.len(),
+                parent: self,
+            })
+        } else {
+            panic!()
+        }
+    }
+
+    /// Returns whether this interface is `other` or inherits from it, directly or indirectly.
+    pub fn extends(&self, other: InterfaceSchema) -> Result<bool> {
+        if self.proto.get_id() == other.proto.get_id() {
+            return Ok(true);
+        }
+        for superclass in self.get_superclasses()? {
+            if superclass.extends(other)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn get_annotations(self) -> Result<AnnotationList> {
+        Ok(AnnotationList {
+            annotations: self.proto.get_annotations()?,
+            child_index: None,
+            annotation_types: AnnotationTypes::Interface(self.raw),
+        })
+    }
+}
+
+impl From<RawInterfaceSchema> for InterfaceSchema {
+    fn from(ri: RawInterfaceSchema) -> InterfaceSchema {
+        InterfaceSchema::new(ri)
+    }
+}
+
+/// A method of an interface, with generics applied.
+#[derive(Clone, Copy)]
+pub struct Method {
+    proto: method::Reader<'static>,
+    ordinal: u16,
+    parent: InterfaceSchema,
+}
+
+impl Method {
+    pub fn get_containing_interface(self) -> InterfaceSchema {
+        self.parent
+    }
+
+    /// Gets the ordinal of the method, which identifies it in RPC calls.
+    pub fn get_ordinal(self) -> u16 {
+        self.ordinal
+    }
+
+    pub fn get_proto(self) -> method::Reader<'static> {
+        self.proto
+    }
+
+    /// Gets the schema of the struct that holds the method's parameters.
+    pub fn get_param_type(self) -> StructSchema {
+        match self.parent.raw.param_type(self.ordinal).which() {
+            introspect::TypeVariant::Struct(rs) => rs.into(),
+            _ => panic!("method parameters must be a struct"),
+        }
+    }
+
+    /// Gets the schema of the struct that holds the method's results.
+    pub fn get_result_type(self) -> StructSchema {
+        match self.parent.raw.result_type(self.ordinal).which() {
+            introspect::TypeVariant::Struct(rs) => rs.into(),
+            _ => panic!("method results must be a struct"),
+        }
+    }
+
+    pub fn get_annotations(self) -> Result<AnnotationList> {
+        Ok(AnnotationList {
+            annotations: self.proto.get_annotations()?,
+            child_index: Some(self.ordinal),
+            annotation_types: AnnotationTypes::Interface(self.parent.raw),
+        })
+    }
+}
+
+/// A list of methods of an interface.
+#[derive(Clone, Copy)]
+pub struct MethodList {
+    methods: struct_list::Reader<'static, method::Owned>,
+    parent: InterfaceSchema,
+}
+
+impl MethodList {
+    pub fn len(&self) -> u16 {
+        self.methods.len() as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(self, ordinal: u16) -> Method {
+        Method {
+            proto: self.methods.get(ordinal as u32),
+            ordinal,
+            parent: self.parent,
+        }
+    }
+
+    pub fn iter(self) -> ShortListIter<Self, Method> {
+        ShortListIter::new(self, self.len())
+    }
+}
+
+impl IndexMove<u16, Method> for MethodList {
+    fn index_move(&self, index: u16) -> Method {
+        self.get(index)
+    }
+}
+
+impl ::core::iter::IntoIterator for MethodList {
+    type Item = Method;
+    type IntoIter = ShortListIter<Self, Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The interfaces that an interface directly extends, with generics applied.
+#[derive(Clone, Copy)]
+pub struct SuperclassList {
+    len: u32,
+    parent: InterfaceSchema,
+}
+
+impl SuperclassList {
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(self, index: u32) -> InterfaceSchema {
+        match self.parent.raw.superclass_type(index).which() {
+            introspect::TypeVariant::Capability(ri) => ri.into(),
+            _ => panic!("superclass must be an interface"),
+        }
+    }
+
+    pub fn iter(self) -> ListIter<Self, InterfaceSchema> {
+        ListIter::new(self, self.len())
+    }
+}
+
+impl IndexMove<u32, InterfaceSchema> for SuperclassList {
+    fn index_move(&self, index: u32) -> InterfaceSchema {
+        self.get(index)
+    }
+}
+
+impl ::core::iter::IntoIterator for SuperclassList {
+    type Item = InterfaceSchema;
+    type IntoIter = ListIter<Self, Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 /// An annotation.
 #[derive(Clone, Copy)]
 pub struct Annotation {
@@ -382,7 +645,15 @@ impl Annotation {
 pub struct AnnotationList {
     annotations: struct_list::Reader<'static, annotation::Owned>,
     child_index: Option<u16>,
-    get_annotation_type: fn(Option<u16>, u32) -> introspect::Type,
+    annotation_types: AnnotationTypes,
+}
+
+/// Where the types of the values in an `AnnotationList` come from.
+#[derive(Clone, Copy)]
+enum AnnotationTypes {
+    Struct(RawBrandedStructSchema),
+    Enum(RawEnumSchema),
+    Interface(RawInterfaceSchema),
 }
 
 impl AnnotationList {
@@ -396,7 +667,11 @@ impl AnnotationList {
 
     pub fn get(self, index: u32) -> Annotation {
         let proto = self.annotations.get(index);
-        let ty = (self.get_annotation_type)(self.child_index, index);
+        let ty = match self.annotation_types {
+            AnnotationTypes::Struct(raw) => raw.annotation_type(self.child_index, index),
+            AnnotationTypes::Enum(raw) => raw.annotation_type(self.child_index, index),
+            AnnotationTypes::Interface(raw) => raw.annotation_type(self.child_index, index),
+        };
         Annotation { proto, ty }
     }
 
@@ -430,3 +705,177 @@ impl<'a> ::core::iter::IntoIterator for AnnotationList {
         self.iter()
     }
 }
+
+// The types above hold readers of `'static`, immutable encoded nodes, which go through the
+// `NullArena` and have no read limit to update. That makes them as shareable as the raw schemas
+// they are built from.
+macro_rules! impl_send_sync {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl Send for $ty {}
+            unsafe impl Sync for $ty {}
+        )*
+    };
+}
+
+impl_send_sync!(
+    StructSchema,
+    Field,
+    FieldList,
+    FieldSubset,
+    EnumSchema,
+    Enumerant,
+    EnumerantList,
+    InterfaceSchema,
+    Method,
+    MethodList,
+    SuperclassList,
+    Annotation,
+    AnnotationList
+);
+
+/// Maps type ids to the schemas of generated types, so that a value whose type is only known by
+/// id, such as an `AnyPointer` tagged with a type id, can be read dynamically.
+///
+/// There is a single, global registry, which is returned by [`registry()`]. It only knows the
+/// types that have been registered with it; see [the module docs](self#looking-up-types-by-id).
+/// Every generated file has a `schemas()` function listing the types that it defines, to be
+/// passed to [`Registry::register_all()`]. Generic types are listed with their type parameters
+/// bound to `AnyPointer`.
+#[cfg(feature = "std")]
+pub struct Registry {
+    /// Sorted by id.
+    types: std::sync::RwLock<std::vec::Vec<(u64, introspect::Type)>>,
+}
+
+#[cfg(feature = "std")]
+static REGISTRY: Registry = Registry {
+    types: std::sync::RwLock::new(std::vec::Vec::new()),
+};
+
+/// Gets the global schema registry.
+#[cfg(feature = "std")]
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+#[cfg(feature = "std")]
+impl Registry {
+    /// Adds a struct, enum, or interface type, along with the non-generic types that it refers
+    /// to through fields, methods, and superclasses. Types that are already registered are left
+    /// as they are.
+    pub fn register(&self, ty: introspect::Type) -> Result<()> {
+        use introspect::TypeVariant;
+
+        let mut types = match self.types.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut visited = std::collections::HashSet::new();
+        let mut pending = std::vec![(ty, true)];
+        while let Some((ty, is_root)) = pending.pop() {
+            let proto = match ty.which() {
+                TypeVariant::Struct(raw) => {
+                    let schema = StructSchema::new(raw);
+                    if visited.insert(schema.proto.get_id()) {
+                        for field in schema.get_fields()? {
+                            pending.push((field.get_type(), false));
+                        }
+                    }
+                    schema.proto
+                }
+                TypeVariant::Enum(raw) => EnumSchema::new(raw).proto,
+                TypeVariant::Capability(raw) => {
+                    let schema = InterfaceSchema::new(raw);
+                    if visited.insert(schema.proto.get_id()) {
+                        for method in schema.get_methods()? {
+                            pending.push((
+                                TypeVariant::Struct(method.get_param_type().raw).into(),
+                                false,
+                            ));
+                            pending.push((
+                                TypeVariant::Struct(method.get_result_type().raw).into(),
+                                false,
+                            ));
+                        }
+                        for superclass in schema.get_superclasses()? {
+                            pending.push((TypeVariant::Capability(superclass.raw).into(), false));
+                        }
+                    }
+                    schema.proto
+                }
+                TypeVariant::List(element_type) => {
+                    pending.push((element_type, false));
+                    continue;
+                }
+                _ if is_root => {
+                    return Err(crate::Error::failed(
+                        "only struct, enum, and interface types can be registered".into(),
+                    ))
+                }
+                _ => continue,
+            };
+
+            // A generic type that was reached through a field may have its type parameters
+            // bound to anything, so only the type given by the caller is registered.
+            if proto.get_is_generic() && !is_root {
+                continue;
+            }
+            let id = proto.get_id();
+            if let Err(index) = types.binary_search_by_key(&id, |&(id, _)| id) {
+                types.insert(index, (id, ty));
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers each of `schemas`, which is typically the `schemas()` of a generated file.
+    pub fn register_all(&self, schemas: &[fn() -> introspect::Type]) -> Result<()> {
+        for schema in schemas {
+            self.register(schema())?;
+        }
+        Ok(())
+    }
+
+    /// Gets the type with the given id, if it has been registered.
+    pub fn get(&self, id: u64) -> Option<introspect::Type> {
+        let types = match self.types.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let index = types.binary_search_by_key(&id, |&(id, _)| id).ok()?;
+        Some(types[index].1)
+    }
+
+    /// Gets the schema of a registered struct.
+    pub fn get_struct(&self, id: u64) -> Result<StructSchema> {
+        match self.get(id).map(|ty| ty.which()) {
+            Some(introspect::TypeVariant::Struct(raw)) => Ok(raw.into()),
+            _ => Err(not_registered(id, "struct")),
+        }
+    }
+
+    /// Gets the schema of a registered enum.
+    pub fn get_enum(&self, id: u64) -> Result<EnumSchema> {
+        match self.get(id).map(|ty| ty.which()) {
+            Some(introspect::TypeVariant::Enum(raw)) => Ok(raw.into()),
+            _ => Err(not_registered(id, "enum")),
+        }
+    }
+
+    /// Gets the schema of a registered interface.
+    pub fn get_interface(&self, id: u64) -> Result<InterfaceSchema> {
+        match self.get(id).map(|ty| ty.which()) {
+            Some(introspect::TypeVariant::Capability(raw)) => Ok(raw.into()),
+            _ => Err(not_registered(id, "interface")),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn not_registered(id: u64, kind: &str) -> crate::Error {
+    crate::Error::failed(std::format!(
+        "no {kind} with id {id:#x} is registered; generated types must be registered with \
+         `Registry::register_all()` first"
+    ))
+}
@@ -40,6 +40,19 @@ pub mod node {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -204,6 +217,14 @@ pub mod node {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_id(self) -> u64 {
       self.builder.get_data_field::<u64>(0)
@@ -225,6 +246,14 @@ pub mod node {
       self.builder.get_pointer_field(0).init_text(size)
     }
     #[inline]
+    pub fn disown_display_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_display_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_display_name(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -257,6 +286,14 @@ pub mod node {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
     }
     #[inline]
+    pub fn disown_nested_nodes(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::nested_node::Owned>> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_nested_nodes(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::nested_node::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_nested_nodes(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -273,6 +310,14 @@ pub mod node {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(2), size)
     }
     #[inline]
+    pub fn disown_annotations(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>> {
+      self.builder.disown_pointer_field(2).into()
+    }
+    #[inline]
+    pub fn adopt_annotations(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(2).adopt(value.into())
+    }
+    #[inline]
     pub fn has_annotations(&self) -> bool {
       !self.builder.is_pointer_field_null(2)
     }
@@ -343,6 +388,14 @@ pub mod node {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(5), size)
     }
     #[inline]
+    pub fn disown_parameters(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::parameter::Owned>> {
+      self.builder.disown_pointer_field(5).into()
+    }
+    #[inline]
+    pub fn adopt_parameters(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::parameter::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(5).adopt(value.into())
+    }
+    #[inline]
     pub fn has_parameters(&self) -> bool {
       !self.builder.is_pointer_field_null(5)
     }
@@ -705,6 +758,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -793,6 +859,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_name(self) -> crate::Result<crate::text::Builder<'a>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -806,6 +880,14 @@ pub mod node {
         self.builder.get_pointer_field(0).init_text(size)
       }
       #[inline]
+      pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_name(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -910,6 +992,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -1002,6 +1097,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_name(self) -> crate::Result<crate::text::Builder<'a>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -1015,6 +1118,14 @@ pub mod node {
         self.builder.get_pointer_field(0).init_text(size)
       }
       #[inline]
+      pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_name(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -1143,6 +1254,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -1243,6 +1367,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_id(self) -> u64 {
         self.builder.get_data_field::<u64>(0)
@@ -1264,6 +1396,14 @@ pub mod node {
         self.builder.get_pointer_field(0).init_text(size)
       }
       #[inline]
+      pub fn disown_doc_comment(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_doc_comment(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_doc_comment(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -1280,6 +1420,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
       }
       #[inline]
+      pub fn disown_members(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::source_info::member::Owned>> {
+        self.builder.disown_pointer_field(1).into()
+      }
+      #[inline]
+      pub fn adopt_members(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::source_info::member::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+      }
+      #[inline]
       pub fn has_members(&self) -> bool {
         !self.builder.is_pointer_field_null(1)
       }
@@ -1423,6 +1571,19 @@ pub mod node {
         }
       }
 
+      impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+        fn eq(&self, other: &Self) -> bool {
+          self.reader.total_eq(&other.reader)
+        }
+      }
+      impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+      impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+        fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+          self.reader.total_hash(state)
+        }
+      }
+      crate::impl_serialize_for_reader!([] []);
+
       impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
         fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
           ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -1511,6 +1672,14 @@ pub mod node {
         pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
           self.builder.as_reader().total_size()
         }
+
+        pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+          self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+        }
+        pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+          let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+          dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+        }
         #[inline]
         pub fn get_doc_comment(self) -> crate::Result<crate::text::Builder<'a>> {
           crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -1524,6 +1693,14 @@ pub mod node {
           self.builder.get_pointer_field(0).init_text(size)
         }
         #[inline]
+        pub fn disown_doc_comment(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+          self.builder.disown_pointer_field(0).into()
+        }
+        #[inline]
+        pub fn adopt_doc_comment(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+          self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+        }
+        #[inline]
         pub fn has_doc_comment(&self) -> bool {
           !self.builder.is_pointer_field_null(0)
         }
@@ -1631,6 +1808,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -1743,6 +1933,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_data_word_count(self) -> u16 {
         self.builder.get_data_field::<u16>(7)
@@ -1804,6 +2002,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), size)
       }
       #[inline]
+      pub fn disown_fields(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::field::Owned>> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_fields(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::field::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_fields(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -2015,6 +2221,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -2103,6 +2322,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_enumerants(self) -> crate::Result<crate::struct_list::Builder<'a,crate::schema_capnp::enumerant::Owned>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::core::option::Option::None)
@@ -2116,6 +2343,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), size)
       }
       #[inline]
+      pub fn disown_enumerants(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::enumerant::Owned>> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_enumerants(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::enumerant::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_enumerants(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -2223,6 +2458,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -2319,6 +2567,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_methods(self) -> crate::Result<crate::struct_list::Builder<'a,crate::schema_capnp::method::Owned>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::core::option::Option::None)
@@ -2332,6 +2588,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), size)
       }
       #[inline]
+      pub fn disown_methods(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::method::Owned>> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_methods(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::method::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_methods(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -2348,6 +2612,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(4), size)
       }
       #[inline]
+      pub fn disown_superclasses(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::superclass::Owned>> {
+        self.builder.disown_pointer_field(4).into()
+      }
+      #[inline]
+      pub fn adopt_superclasses(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::superclass::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(4).adopt(value.into())
+      }
+      #[inline]
       pub fn has_superclasses(&self) -> bool {
         !self.builder.is_pointer_field_null(4)
       }
@@ -2476,6 +2748,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -2572,6 +2857,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type(self) -> crate::Result<crate::schema_capnp::type_::Builder<'a>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::core::option::Option::None)
@@ -2585,6 +2878,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), 0)
       }
       #[inline]
+      pub fn disown_type(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::type_::Owned> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_type(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::type_::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_type(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -2601,6 +2902,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(4), 0)
       }
       #[inline]
+      pub fn disown_value(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::value::Owned> {
+        self.builder.disown_pointer_field(4).into()
+      }
+      #[inline]
+      pub fn adopt_value(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::value::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(4).adopt(value.into())
+      }
+      #[inline]
       pub fn has_value(&self) -> bool {
         !self.builder.is_pointer_field_null(4)
       }
@@ -2725,6 +3034,19 @@ pub mod node {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -2861,6 +3183,14 @@ pub mod node {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type(self) -> crate::Result<crate::schema_capnp::type_::Builder<'a>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::core::option::Option::None)
@@ -2874,6 +3204,14 @@ pub mod node {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), 0)
       }
       #[inline]
+      pub fn disown_type(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::type_::Owned> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_type(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::type_::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_type(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -3286,6 +3624,19 @@ pub mod field {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -3410,6 +3761,14 @@ pub mod field {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_name(self) -> crate::Result<crate::text::Builder<'a>> {
       crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -3423,6 +3782,14 @@ pub mod field {
       self.builder.get_pointer_field(0).init_text(size)
     }
     #[inline]
+    pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_name(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -3447,6 +3814,14 @@ pub mod field {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
     }
     #[inline]
+    pub fn disown_annotations(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_annotations(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_annotations(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -3695,6 +4070,19 @@ pub mod field {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -3799,6 +4187,14 @@ pub mod field {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_offset(self) -> u32 {
         self.builder.get_data_field::<u32>(1)
@@ -3820,6 +4216,14 @@ pub mod field {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(2), 0)
       }
       #[inline]
+      pub fn disown_type(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::type_::Owned> {
+        self.builder.disown_pointer_field(2).into()
+      }
+      #[inline]
+      pub fn adopt_type(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::type_::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(2).adopt(value.into())
+      }
+      #[inline]
       pub fn has_type(&self) -> bool {
         !self.builder.is_pointer_field_null(2)
       }
@@ -3836,6 +4240,14 @@ pub mod field {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), 0)
       }
       #[inline]
+      pub fn disown_default_value(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::value::Owned> {
+        self.builder.disown_pointer_field(3).into()
+      }
+      #[inline]
+      pub fn adopt_default_value(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::value::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+      }
+      #[inline]
       pub fn has_default_value(&self) -> bool {
         !self.builder.is_pointer_field_null(3)
       }
@@ -4003,6 +4415,19 @@ pub mod field {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -4087,6 +4512,14 @@ pub mod field {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type_id(self) -> u64 {
         self.builder.get_data_field::<u64>(2)
@@ -4197,6 +4630,19 @@ pub mod field {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -4293,6 +4739,14 @@ pub mod field {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn set_implicit(&mut self, _value: ())  {
         self.builder.set_data_field::<u16>(5, 0);
@@ -4443,6 +4897,19 @@ pub mod enumerant {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -4543,6 +5010,14 @@ pub mod enumerant {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_name(self) -> crate::Result<crate::text::Builder<'a>> {
       crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -4556,6 +5031,14 @@ pub mod enumerant {
       self.builder.get_pointer_field(0).init_text(size)
     }
     #[inline]
+    pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_name(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -4580,6 +5063,14 @@ pub mod enumerant {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
     }
     #[inline]
+    pub fn disown_annotations(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_annotations(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_annotations(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -4721,6 +5212,19 @@ pub mod superclass {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -4813,6 +5317,14 @@ pub mod superclass {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_id(self) -> u64 {
       self.builder.get_data_field::<u64>(0)
@@ -4834,6 +5346,14 @@ pub mod superclass {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
     }
     #[inline]
+    pub fn disown_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_brand(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -4956,6 +5476,19 @@ pub mod method {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -5088,6 +5621,14 @@ pub mod method {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_name(self) -> crate::Result<crate::text::Builder<'a>> {
       crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -5101,6 +5642,14 @@ pub mod method {
       self.builder.get_pointer_field(0).init_text(size)
     }
     #[inline]
+    pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_name(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -5141,6 +5690,14 @@ pub mod method {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
     }
     #[inline]
+    pub fn disown_annotations(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_annotations(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::annotation::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_annotations(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -5157,6 +5714,14 @@ pub mod method {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(2), 0)
     }
     #[inline]
+    pub fn disown_param_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+      self.builder.disown_pointer_field(2).into()
+    }
+    #[inline]
+    pub fn adopt_param_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(2).adopt(value.into())
+    }
+    #[inline]
     pub fn has_param_brand(&self) -> bool {
       !self.builder.is_pointer_field_null(2)
     }
@@ -5173,6 +5738,14 @@ pub mod method {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), 0)
     }
     #[inline]
+    pub fn disown_result_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+      self.builder.disown_pointer_field(3).into()
+    }
+    #[inline]
+    pub fn adopt_result_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+    }
+    #[inline]
     pub fn has_result_brand(&self) -> bool {
       !self.builder.is_pointer_field_null(3)
     }
@@ -5189,6 +5762,14 @@ pub mod method {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(4), size)
     }
     #[inline]
+    pub fn disown_implicit_parameters(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::parameter::Owned>> {
+      self.builder.disown_pointer_field(4).into()
+    }
+    #[inline]
+    pub fn adopt_implicit_parameters(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::parameter::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(4).adopt(value.into())
+    }
+    #[inline]
     pub fn has_implicit_parameters(&self) -> bool {
       !self.builder.is_pointer_field_null(4)
     }
@@ -5429,6 +6010,19 @@ pub mod type_ {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -5610,6 +6204,14 @@ pub mod type_ {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn set_void(&mut self, _value: ())  {
       self.builder.set_data_field::<u16>(0, 0);
@@ -6181,6 +6783,19 @@ pub mod type_ {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -6269,6 +6884,14 @@ pub mod type_ {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_element_type(self) -> crate::Result<crate::schema_capnp::type_::Builder<'a>> {
         crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -6282,6 +6905,14 @@ pub mod type_ {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
       }
       #[inline]
+      pub fn disown_element_type(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::type_::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_element_type(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::type_::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_element_type(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -6388,6 +7019,19 @@ pub mod type_ {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -6480,6 +7124,14 @@ pub mod type_ {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type_id(self) -> u64 {
         self.builder.get_data_field::<u64>(1)
@@ -6501,6 +7153,14 @@ pub mod type_ {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
       }
       #[inline]
+      pub fn disown_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_brand(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -6622,6 +7282,19 @@ pub mod type_ {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -6714,6 +7387,14 @@ pub mod type_ {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type_id(self) -> u64 {
         self.builder.get_data_field::<u64>(1)
@@ -6735,6 +7416,14 @@ pub mod type_ {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
       }
       #[inline]
+      pub fn disown_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_brand(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -6857,6 +7546,19 @@ pub mod type_ {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -6949,6 +7651,14 @@ pub mod type_ {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_type_id(self) -> u64 {
         self.builder.get_data_field::<u64>(1)
@@ -6970,6 +7680,14 @@ pub mod type_ {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
       }
       #[inline]
+      pub fn disown_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_brand(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -7094,6 +7812,19 @@ pub mod type_ {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -7195,6 +7926,14 @@ pub mod type_ {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn init_unconstrained(self, ) -> crate::schema_capnp::type_::any_pointer::unconstrained::Builder<'a> {
         self.builder.set_data_field::<u16>(4, 0);
@@ -7358,6 +8097,19 @@ pub mod type_ {
         }
       }
 
+      impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+        fn eq(&self, other: &Self) -> bool {
+          ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+        }
+      }
+      impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+      impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+        fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+          ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+        }
+      }
+      crate::impl_serialize_for_reader!([] []);
+
       impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
         fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
           ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -7464,6 +8216,14 @@ pub mod type_ {
         pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
           self.builder.as_reader().total_size()
         }
+
+        pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+          self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+        }
+        pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+          let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+          dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+        }
         #[inline]
         pub fn set_any_kind(&mut self, _value: ())  {
           self.builder.set_data_field::<u16>(5, 0);
@@ -7665,6 +8425,19 @@ pub mod type_ {
         }
       }
 
+      impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+        fn eq(&self, other: &Self) -> bool {
+          ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+        }
+      }
+      impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+      impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+        fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+          ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+        }
+      }
+      crate::impl_serialize_for_reader!([] []);
+
       impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
         fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
           ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -7753,6 +8526,14 @@ pub mod type_ {
         pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
           self.builder.as_reader().total_size()
         }
+
+        pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+          self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+        }
+        pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+          let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+          dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+        }
         #[inline]
         pub fn get_scope_id(self) -> u64 {
           self.builder.get_data_field::<u64>(2)
@@ -7887,6 +8668,19 @@ pub mod type_ {
         }
       }
 
+      impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+        fn eq(&self, other: &Self) -> bool {
+          ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*other)
+        }
+      }
+      impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+      impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+        fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+          ::core::hash::Hash::hash(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), state)
+        }
+      }
+      crate::impl_serialize_for_reader!([] []);
+
       impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
         fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
           ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -7971,6 +8765,14 @@ pub mod type_ {
         pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
           self.builder.as_reader().total_size()
         }
+
+        pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+          self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+        }
+        pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+          let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+          dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+        }
         #[inline]
         pub fn get_parameter_index(self) -> u16 {
           self.builder.get_data_field::<u16>(5)
@@ -8085,6 +8887,19 @@ pub mod brand {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -8173,6 +8988,14 @@ pub mod brand {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_scopes(self) -> crate::Result<crate::struct_list::Builder<'a,crate::schema_capnp::brand::scope::Owned>> {
       crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -8186,6 +9009,14 @@ pub mod brand {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), size)
     }
     #[inline]
+    pub fn disown_scopes(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::brand::scope::Owned>> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_scopes(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::brand::scope::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_scopes(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -8300,6 +9131,19 @@ pub mod brand {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -8405,6 +9249,14 @@ pub mod brand {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_scope_id(self) -> u64 {
         self.builder.get_data_field::<u64>(0)
@@ -8424,6 +9276,16 @@ pub mod brand {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), size)
       }
       #[inline]
+      pub fn disown_bind(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::brand::binding::Owned>> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_bind(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::brand::binding::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+        self.builder.set_data_field::<u16>(4, 0);
+        Ok(())
+      }
+      #[inline]
       pub fn has_bind(&self) -> bool {
         if self.builder.get_data_field::<u16>(4) != 0 { return false; }
         !self.builder.is_pointer_field_null(0)
@@ -8593,6 +9455,19 @@ pub mod brand {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -8694,6 +9569,14 @@ pub mod brand {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn set_unbound(&mut self, _value: ())  {
         self.builder.set_data_field::<u16>(0, 0);
@@ -8709,6 +9592,16 @@ pub mod brand {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
       }
       #[inline]
+      pub fn disown_type(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::type_::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_type(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::type_::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+        self.builder.set_data_field::<u16>(0, 1);
+        Ok(())
+      }
+      #[inline]
       pub fn has_type(&self) -> bool {
         if self.builder.get_data_field::<u16>(0) != 1 { return false; }
         !self.builder.is_pointer_field_null(0)
@@ -8855,6 +9748,19 @@ pub mod value {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -9061,6 +9967,14 @@ pub mod value {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn set_void(&mut self, _value: ())  {
       self.builder.set_data_field::<u16>(0, 0);
@@ -9131,6 +10045,16 @@ pub mod value {
       self.builder.get_pointer_field(0).init_text(size)
     }
     #[inline]
+    pub fn disown_text(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_text(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+      self.builder.set_data_field::<u16>(0, 12);
+      Ok(())
+    }
+    #[inline]
     pub fn has_text(&self) -> bool {
       if self.builder.get_data_field::<u16>(0) != 12 { return false; }
       !self.builder.is_pointer_field_null(0)
@@ -9146,6 +10070,16 @@ pub mod value {
       self.builder.get_pointer_field(0).init_data(size)
     }
     #[inline]
+    pub fn disown_data(&mut self) -> crate::orphan::Orphan<'a, crate::data::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_data(&mut self, value: crate::orphan::Orphan<'_, crate::data::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+      self.builder.set_data_field::<u16>(0, 13);
+      Ok(())
+    }
+    #[inline]
     pub fn has_data(&self) -> bool {
       if self.builder.get_data_field::<u16>(0) != 13 { return false; }
       !self.builder.is_pointer_field_null(0)
@@ -9158,6 +10092,16 @@ pub mod value {
       result
     }
     #[inline]
+    pub fn disown_list(&mut self) -> crate::orphan::Orphan<'a, crate::any_pointer::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_list(&mut self, value: crate::orphan::Orphan<'_, crate::any_pointer::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+      self.builder.set_data_field::<u16>(0, 14);
+      Ok(())
+    }
+    #[inline]
     pub fn has_list(&self) -> bool {
       if self.builder.get_data_field::<u16>(0) != 14 { return false; }
       !self.builder.is_pointer_field_null(0)
@@ -9175,6 +10119,16 @@ pub mod value {
       result
     }
     #[inline]
+    pub fn disown_struct(&mut self) -> crate::orphan::Orphan<'a, crate::any_pointer::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_struct(&mut self, value: crate::orphan::Orphan<'_, crate::any_pointer::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+      self.builder.set_data_field::<u16>(0, 16);
+      Ok(())
+    }
+    #[inline]
     pub fn has_struct(&self) -> bool {
       if self.builder.get_data_field::<u16>(0) != 16 { return false; }
       !self.builder.is_pointer_field_null(0)
@@ -9191,6 +10145,16 @@ pub mod value {
       result
     }
     #[inline]
+    pub fn disown_any_pointer(&mut self) -> crate::orphan::Orphan<'a, crate::any_pointer::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_any_pointer(&mut self, value: crate::orphan::Orphan<'_, crate::any_pointer::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())?;
+      self.builder.set_data_field::<u16>(0, 18);
+      Ok(())
+    }
+    #[inline]
     pub fn has_any_pointer(&self) -> bool {
       if self.builder.get_data_field::<u16>(0) != 18 { return false; }
       !self.builder.is_pointer_field_null(0)
@@ -9709,6 +10673,19 @@ pub mod annotation {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -9809,6 +10786,14 @@ pub mod annotation {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_id(self) -> u64 {
       self.builder.get_data_field::<u64>(0)
@@ -9830,6 +10815,14 @@ pub mod annotation {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
     }
     #[inline]
+    pub fn disown_value(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::value::Owned> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_value(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::value::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_value(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -9846,6 +10839,14 @@ pub mod annotation {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), 0)
     }
     #[inline]
+    pub fn disown_brand(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::brand::Owned> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_brand(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::brand::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_brand(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -10091,6 +11092,19 @@ pub mod capnp_version {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -10183,6 +11197,14 @@ pub mod capnp_version {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_major(self) -> u16 {
       self.builder.get_data_field::<u16>(0)
@@ -10340,6 +11362,19 @@ pub mod code_generator_request {
     }
   }
 
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.total_eq(&other.reader)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      self.reader.total_hash(state)
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
   impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
     fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
       ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -10452,6 +11487,14 @@ pub mod code_generator_request {
     pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
       self.builder.as_reader().total_size()
     }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
     #[inline]
     pub fn get_nodes(self) -> crate::Result<crate::struct_list::Builder<'a,crate::schema_capnp::node::Owned>> {
       crate::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
@@ -10465,6 +11508,14 @@ pub mod code_generator_request {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), size)
     }
     #[inline]
+    pub fn disown_nodes(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::Owned>> {
+      self.builder.disown_pointer_field(0).into()
+    }
+    #[inline]
+    pub fn adopt_nodes(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+    }
+    #[inline]
     pub fn has_nodes(&self) -> bool {
       !self.builder.is_pointer_field_null(0)
     }
@@ -10481,6 +11532,14 @@ pub mod code_generator_request {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
     }
     #[inline]
+    pub fn disown_requested_files(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::code_generator_request::requested_file::Owned>> {
+      self.builder.disown_pointer_field(1).into()
+    }
+    #[inline]
+    pub fn adopt_requested_files(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::code_generator_request::requested_file::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+    }
+    #[inline]
     pub fn has_requested_files(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
@@ -10497,6 +11556,14 @@ pub mod code_generator_request {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(2), 0)
     }
     #[inline]
+    pub fn disown_capnp_version(&mut self) -> crate::orphan::Orphan<'a, crate::schema_capnp::capnp_version::Owned> {
+      self.builder.disown_pointer_field(2).into()
+    }
+    #[inline]
+    pub fn adopt_capnp_version(&mut self, value: crate::orphan::Orphan<'_, crate::schema_capnp::capnp_version::Owned>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(2).adopt(value.into())
+    }
+    #[inline]
     pub fn has_capnp_version(&self) -> bool {
       !self.builder.is_pointer_field_null(2)
     }
@@ -10513,6 +11580,14 @@ pub mod code_generator_request {
       crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(3), size)
     }
     #[inline]
+    pub fn disown_source_info(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::node::source_info::Owned>> {
+      self.builder.disown_pointer_field(3).into()
+    }
+    #[inline]
+    pub fn adopt_source_info(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::node::source_info::Owned>>) -> crate::Result<()> {
+      self.builder.reborrow().get_pointer_field(3).adopt(value.into())
+    }
+    #[inline]
     pub fn has_source_info(&self) -> bool {
       !self.builder.is_pointer_field_null(3)
     }
@@ -10687,6 +11762,19 @@ pub mod code_generator_request {
       }
     }
 
+    impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+      fn eq(&self, other: &Self) -> bool {
+        self.reader.total_eq(&other.reader)
+      }
+    }
+    impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+    impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+      fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.reader.total_hash(state)
+      }
+    }
+    crate::impl_serialize_for_reader!([] []);
+
     impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
       fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
         ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -10787,6 +11875,14 @@ pub mod code_generator_request {
       pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
         self.builder.as_reader().total_size()
       }
+
+      pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+        self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+      }
+      pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+        let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+        dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+      }
       #[inline]
       pub fn get_id(self) -> u64 {
         self.builder.get_data_field::<u64>(0)
@@ -10808,6 +11904,14 @@ pub mod code_generator_request {
         self.builder.get_pointer_field(0).init_text(size)
       }
       #[inline]
+      pub fn disown_filename(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+        self.builder.disown_pointer_field(0).into()
+      }
+      #[inline]
+      pub fn adopt_filename(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+      }
+      #[inline]
       pub fn has_filename(&self) -> bool {
         !self.builder.is_pointer_field_null(0)
       }
@@ -10824,6 +11928,14 @@ pub mod code_generator_request {
         crate::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(1), size)
       }
       #[inline]
+      pub fn disown_imports(&mut self) -> crate::orphan::Orphan<'a, crate::struct_list::Owned<crate::schema_capnp::code_generator_request::requested_file::import::Owned>> {
+        self.builder.disown_pointer_field(1).into()
+      }
+      #[inline]
+      pub fn adopt_imports(&mut self, value: crate::orphan::Orphan<'_, crate::struct_list::Owned<crate::schema_capnp::code_generator_request::requested_file::import::Owned>>) -> crate::Result<()> {
+        self.builder.reborrow().get_pointer_field(1).adopt(value.into())
+      }
+      #[inline]
       pub fn has_imports(&self) -> bool {
         !self.builder.is_pointer_field_null(1)
       }
@@ -10969,6 +12081,19 @@ pub mod code_generator_request {
         }
       }
 
+      impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+        fn eq(&self, other: &Self) -> bool {
+          self.reader.total_eq(&other.reader)
+        }
+      }
+      impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+      impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+        fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+          self.reader.total_hash(state)
+        }
+      }
+      crate::impl_serialize_for_reader!([] []);
+
       impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
         fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
           ::core::result::Result::Ok(reader.get_struct(default)?.into())
@@ -11061,6 +12186,14 @@ pub mod code_generator_request {
         pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
           self.builder.as_reader().total_size()
         }
+
+        pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+          self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+        }
+        pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+          let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+          dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+        }
         #[inline]
         pub fn get_id(self) -> u64 {
           self.builder.get_data_field::<u64>(0)
@@ -11082,6 +12215,14 @@ pub mod code_generator_request {
           self.builder.get_pointer_field(0).init_text(size)
         }
         #[inline]
+        pub fn disown_name(&mut self) -> crate::orphan::Orphan<'a, crate::text::Owned> {
+          self.builder.disown_pointer_field(0).into()
+        }
+        #[inline]
+        pub fn adopt_name(&mut self, value: crate::orphan::Orphan<'_, crate::text::Owned>) -> crate::Result<()> {
+          self.builder.reborrow().get_pointer_field(0).adopt(value.into())
+        }
+        #[inline]
         pub fn has_name(&self) -> bool {
           !self.builder.is_pointer_field_null(0)
         }
@@ -11171,3 +12312,30 @@ pub mod code_generator_request {
     }
   }
 }
+
+/// The types defined in this file. They are not registered automatically; pass them to
+/// `capnp::schema::Registry::register_all()` so that they can be looked up by id.
+pub fn schemas() -> &'static [fn() -> crate::introspect::Type] {
+  &[
+    <crate::schema_capnp::node::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::node::parameter::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::node::nested_node::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::node::source_info::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::node::source_info::member::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::field::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::enumerant::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::superclass::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::method::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::type_::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::brand::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::brand::scope::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::brand::binding::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::value::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::annotation::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::ElementSize as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::capnp_version::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::code_generator_request::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::code_generator_request::requested_file::Owned as crate::introspect::Introspect>::introspect,
+    <crate::schema_capnp::code_generator_request::requested_file::import::Owned as crate::introspect::Introspect>::introspect,
+  ]
+}
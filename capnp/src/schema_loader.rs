@@ -0,0 +1,1225 @@
+//! Loading of schemas at run time, for types that have no generated code.
+//!
+//! A [`SchemaLoader`] accepts `schema.capnp` nodes, either one at a time or as a whole
+//! `CodeGeneratorRequest`, and hands out [`StructSchema`] and [`EnumSchema`] values for them.
+//! Those schemas work anywhere a generated schema does, e.g. with `dynamic_struct::Reader`,
+//! `dynamic_struct::Builder`, `stringify`, or the JSON codec.
+//!
+//! Generic types are supported: a struct can be requested with a `Brand` that binds its type
+//! parameters, and field types are resolved against the bindings of the struct that contains
+//! them.
+//!
+//! Each loader owns the nodes that were loaded through it, so separate loaders can hold
+//! different definitions of the same node id. Schemas are `'static`, though, so the memory that
+//! a schema points to can't be freed along with its loader. To keep that memory bounded, node
+//! encodings are shared across the process: loading a node that is identical to one that was
+//! loaded before, by any loader, reuses the existing copy. The rest of a loader's state is freed
+//! when its last handle is dropped, after which the types that its schemas refer to resolve to
+//! `AnyPointer`.
+//!
+//! ```
+//! use capnp::schema_capnp::node;
+//! use capnp::schema_loader::SchemaLoader;
+//!
+//! # fn main() -> capnp::Result<()> {
+//! // Pretend that this node describing `struct Point { x @0 :Int32; }` arrived over the
+//! // network.
+//! let mut message = capnp::message::Builder::new_default();
+//! let mut point = message.init_root::<node::Builder>();
+//! point.set_id(0xd0e1_7c27_9f41_8c3a);
+//! point.set_display_name("geo.capnp:Point");
+//! let mut st = point.init_struct();
+//! st.set_data_word_count(1);
+//! let mut x = st.init_fields(1).get(0);
+//! x.set_name("x");
+//! let mut slot = x.init_slot();
+//! slot.reborrow().init_type().set_int32(());
+//! slot.init_default_value().set_int32(0);
+//!
+//! let loader = SchemaLoader::new();
+//! loader.load(message.get_root_as_reader()?)?;
+//! let schema = loader.get_struct(0xd0e1_7c27_9f41_8c3a)?;
+//!
+//! let mut data = capnp::message::Builder::new_default();
+//! let mut root = data.init_root::<capnp::any_pointer::Builder>().init_as_struct(schema)?;
+//! root.set_named("x", capnp::dynamic_value::Reader::Int32(-7))?;
+//! assert_eq!(format!("{:?}", capnp::dynamic_value::Reader::from(root.into_reader())), "(x = -7)");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::boxed::Box;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::vec::Vec;
+
+use crate::introspect::{
+    RawBrandedStructSchema, RawEnumSchema, RawInterfaceSchema, RawStructSchema,
+};
+use crate::introspect::{Type, TypeVariant};
+use crate::private::layout;
+use crate::schema::{EnumSchema, InterfaceSchema, StructSchema};
+use crate::schema_capnp::{annotation, brand, code_generator_request, field, node, type_, value};
+use crate::{Error, Result, Word};
+
+/// Resolved generic parameter bindings: a list of (scope id, parameter types), sorted by scope
+/// id. Scopes whose parameters are all `AnyPointer` are omitted, so that equal brands compare
+/// equal.
+type Bindings = Vec<(u64, Vec<Type>)>;
+
+/// The encodings of all the nodes loaded in the process, keyed by their bytes.
+static ENCODINGS: Mutex<Option<HashMap<&'static [u8], Encoding>>> = Mutex::new(None);
+
+/// The brands handed out by the live loaders, keyed by the address that identifies each one's
+/// schema (see `Handle`).
+static BRANDS: Mutex<Option<HashMap<usize, Arc<LoadedBrand>>>> = Mutex::new(None);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Everything behind these mutexes is only mutated by inserting or removing complete
+    // entries, so it is never left inconsistent by a panic.
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Builds `StructSchema` and `EnumSchema` values from `schema.capnp` nodes that are supplied at
+/// run time. Cloning a `SchemaLoader` yields another handle to the same set of nodes.
+#[derive(Clone)]
+pub struct SchemaLoader {
+    store: Arc<Mutex<Store>>,
+}
+
+impl SchemaLoader {
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new_cyclic(|this| {
+                Mutex::new(Store {
+                    nodes: HashMap::new(),
+                    this: this.clone(),
+                })
+            }),
+        }
+    }
+
+    /// Adds a node to the loader. Loading a node that is identical to one that was already
+    /// loaded has no effect; loading a different node with the same id is an error. Nodes loaded
+    /// by other loaders make no difference.
+    ///
+    /// Nodes may be loaded in any order, but all the nodes that a struct depends on must be
+    /// loaded before the struct is requested.
+    pub fn load(&self, node: node::Reader<'_>) -> Result<()> {
+        let mut message = crate::message::Builder::new_default();
+        message.set_root_canonical(node)?;
+        let segments = message.get_segments_for_output();
+        lock(&self.store).insert(node.get_id(), segments[0])
+    }
+
+    /// Loads every node of a `CodeGeneratorRequest`, i.e. the requested files and everything
+    /// they import.
+    pub fn load_request(&self, request: code_generator_request::Reader<'_>) -> Result<()> {
+        for node in request.get_nodes()? {
+            self.load(node)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the node with the given id, if it has been loaded.
+    pub fn get_node(&self, id: u64) -> Option<node::Reader<'static>> {
+        let store = lock(&self.store);
+        store.nodes.get(&id).map(|node| decode(node.encoded))
+    }
+
+    /// Gets the schema of a struct. Any type parameters of the struct are bound to `AnyPointer`.
+    pub fn get_struct(&self, id: u64) -> Result<StructSchema> {
+        let mut store = lock(&self.store);
+        store.validate(vec![id])?;
+        Ok(store.intern_struct(id, Vec::new())?.into())
+    }
+
+    /// Gets the schema of a struct with its type parameters bound according to `brand`, as found
+    /// in a `Type.struct` of some other node. Scopes that the brand inherits are left unbound.
+    pub fn get_struct_with_brand(&self, id: u64, brand: brand::Reader<'_>) -> Result<StructSchema> {
+        let mut store = lock(&self.store);
+        let mut pending = vec![id];
+        store.check_brand(brand, &mut pending)?;
+        store.validate(pending)?;
+        let bindings = store.resolve_brand(brand, &[])?;
+        Ok(store.intern_struct(id, bindings)?.into())
+    }
+
+    /// Gets the schema of an enum.
+    pub fn get_enum(&self, id: u64) -> Result<EnumSchema> {
+        Ok(lock(&self.store).intern_enum(id)?.into())
+    }
+
+    /// Gets the schema of an interface. Any type parameters of the interface are bound to
+    /// `AnyPointer`.
+    pub fn get_interface(&self, id: u64) -> Result<InterfaceSchema> {
+        let mut store = lock(&self.store);
+        store.validate(vec![id])?;
+        Ok(store.intern_interface(id, Vec::new())?.into())
+    }
+
+    /// Resolves a `Type` from some node into an `introspect::Type`. Type parameters that are not
+    /// bound by a brand within `ty` resolve to `AnyPointer`.
+    pub fn get_type(&self, ty: type_::Reader<'_>) -> Result<Type> {
+        let mut store = lock(&self.store);
+        let mut pending = Vec::new();
+        store.check_type(ty, &mut pending)?;
+        store.validate(pending)?;
+        store.resolve_type(ty, &[])
+    }
+}
+
+impl Default for SchemaLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves the type of a field of `schema`, if it was handed out by a loader.
+pub(crate) fn field_type(schema: &RawBrandedStructSchema, index: u16) -> Option<Type> {
+    if schema.field_types as *const () != unresolved_field_type as *const () {
+        return None;
+    }
+    Some(resolve(Handle::Struct(schema.generic), |store, brand| {
+        store.field_type(brand, index)
+    }))
+}
+
+/// Resolves the type of an annotation of `schema`, if it was handed out by a loader.
+pub(crate) fn struct_annotation_type(
+    schema: &RawBrandedStructSchema,
+    child_index: Option<u16>,
+    index: u32,
+) -> Option<Type> {
+    if schema.annotation_types as *const () != unresolved_annotation_type as *const () {
+        return None;
+    }
+    Some(resolve(Handle::Struct(schema.generic), |store, brand| {
+        store.annotation_type(brand, child_index, index)
+    }))
+}
+
+/// Resolves the type of an annotation of `schema`, if it was handed out by a loader.
+pub(crate) fn enum_annotation_type(
+    schema: &RawEnumSchema,
+    child_index: Option<u16>,
+    index: u32,
+) -> Option<Type> {
+    if schema.annotation_types as *const () != unresolved_annotation_type as *const () {
+        return None;
+    }
+    Some(resolve(
+        Handle::Node(schema.encoded_node),
+        |store, brand| store.annotation_type(brand, child_index, index),
+    ))
+}
+
+/// Whether `schema` was handed out by a loader.
+pub(crate) fn is_loaded(schema: &RawInterfaceSchema) -> bool {
+    schema.param_types as *const () == unresolved_method_type as *const ()
+}
+
+/// Resolves the type of the parameters of a method of `schema`, if it was handed out by a loader.
+pub(crate) fn param_type(schema: &RawInterfaceSchema, method_index: u16) -> Option<Type> {
+    if !is_loaded(schema) {
+        return None;
+    }
+    Some(resolve(
+        Handle::Node(schema.encoded_node),
+        |store, brand| store.method_type(brand, method_index, false),
+    ))
+}
+
+/// Resolves the type of the results of a method of `schema`, if it was handed out by a loader.
+pub(crate) fn result_type(schema: &RawInterfaceSchema, method_index: u16) -> Option<Type> {
+    if !is_loaded(schema) {
+        return None;
+    }
+    Some(resolve(
+        Handle::Node(schema.encoded_node),
+        |store, brand| store.method_type(brand, method_index, true),
+    ))
+}
+
+/// Resolves the type of a superclass of `schema`, if it was handed out by a loader.
+pub(crate) fn superclass_type(schema: &RawInterfaceSchema, index: u32) -> Option<Type> {
+    if !is_loaded(schema) {
+        return None;
+    }
+    Some(resolve(
+        Handle::Node(schema.encoded_node),
+        |store, brand| store.superclass_type(brand, index),
+    ))
+}
+
+/// Resolves the type of an annotation of `schema`, if it was handed out by a loader.
+pub(crate) fn interface_annotation_type(
+    schema: &RawInterfaceSchema,
+    child_index: Option<u16>,
+    index: u32,
+) -> Option<Type> {
+    if !is_loaded(schema) {
+        return None;
+    }
+    Some(resolve(
+        Handle::Node(schema.encoded_node),
+        |store, brand| store.annotation_type(brand, child_index, index),
+    ))
+}
+
+/// Resolves a type of the schema identified by `handle`, using the loader that handed it out.
+fn resolve(handle: Handle, f: impl FnOnce(&mut Store, &LoadedBrand) -> Result<Type>) -> Type {
+    let brand = lock(&BRANDS)
+        .as_ref()
+        .and_then(|brands| brands.get(&handle.key()).cloned());
+    let loader = brand.as_ref().and_then(|brand| brand.loader.upgrade());
+    let ty = match (brand, &loader) {
+        (Some(brand), Some(loader)) => f(&mut lock(loader), &brand).ok(),
+        _ => None,
+    };
+    // Dependencies were checked when the schema was handed out, so this can only fail if the
+    // node itself is malformed, or if the loader has been dropped.
+    ty.unwrap_or_else(|| TypeVariant::AnyPointer.into())
+}
+
+/// A node, as a canonical single segment message, shared by all the loaders that load it.
+#[derive(Clone, Copy)]
+struct Encoding {
+    encoded: &'static [Word],
+
+    /// Set for struct nodes.
+    raw_struct: Option<&'static RawStructSchema>,
+}
+
+/// Gets the shared encoding of a node, given as a canonical single segment message.
+fn intern_encoding(bytes: &[u8]) -> Result<Encoding> {
+    let mut encodings = lock(&ENCODINGS);
+    let encodings = encodings.get_or_insert_with(HashMap::new);
+    if let Some(encoding) = encodings.get(bytes) {
+        return Ok(*encoding);
+    }
+
+    let mut words = Word::allocate_zeroed_vec(bytes.len() / 8);
+    Word::words_to_bytes_mut(&mut words).copy_from_slice(bytes);
+    let encoded: &'static [Word] = Box::leak(words.into_boxed_slice());
+    let raw_struct = match decode(encoded).which()? {
+        node::Struct(st) => Some(raw_struct_schema(encoded, st)?),
+        _ => None,
+    };
+    let encoding = Encoding {
+        encoded,
+        raw_struct,
+    };
+    encodings.insert(Word::words_to_bytes(encoded), encoding);
+    Ok(encoding)
+}
+
+struct Store {
+    nodes: HashMap<u64, LoadedNode>,
+
+    /// The store itself, for brands to refer back to.
+    this: Weak<Mutex<Store>>,
+}
+
+impl Drop for Store {
+    fn drop(&mut self) {
+        if let Some(brands) = lock(&BRANDS).as_mut() {
+            for node in self.nodes.values() {
+                for (handle, _) in &node.brands {
+                    brands.remove(&handle.key());
+                }
+            }
+        }
+    }
+}
+
+struct LoadedNode {
+    /// The node, as a canonical single segment message.
+    encoded: &'static [Word],
+
+    /// Set for struct nodes.
+    raw_struct: Option<&'static RawStructSchema>,
+
+    /// Whether this node and all the nodes it depends on are known to have been loaded.
+    validated: bool,
+
+    /// Every brand of this node that has been handed out. Enums have at most one.
+    brands: Vec<(Handle, Arc<LoadedBrand>)>,
+}
+
+/// A node with generic parameter bindings applied.
+struct LoadedBrand {
+    /// The loader that handed out the brand.
+    loader: Weak<Mutex<Store>>,
+    encoded: &'static [Word],
+    bindings: Bindings,
+}
+
+/// The allocation that tells the schemas of a brand apart from those of every other brand: the
+/// `generic` of a struct schema, or the `encoded_node` of an enum or interface schema. Like node
+/// encodings, these are never freed.
+#[derive(Clone, Copy)]
+enum Handle {
+    Struct(&'static RawStructSchema),
+    Node(&'static [Word]),
+}
+
+impl Handle {
+    fn key(self) -> usize {
+        match self {
+            Self::Struct(generic) => generic as *const RawStructSchema as usize,
+            Self::Node(encoded) => encoded.as_ptr() as usize,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Struct,
+    Enum,
+    Interface,
+}
+
+impl Store {
+    /// Adds a node, given as a canonical single segment message, unless it is already present.
+    fn insert(&mut self, id: u64, bytes: &[u8]) -> Result<()> {
+        if let Some(existing) = self.nodes.get(&id) {
+            if Word::words_to_bytes(existing.encoded) == bytes {
+                return Ok(());
+            }
+            return Err(Error::failed(format!(
+                "conflicting definitions for node {id:#x}"
+            )));
+        }
+
+        let Encoding {
+            encoded,
+            raw_struct,
+        } = intern_encoding(bytes)?;
+        self.nodes.insert(
+            id,
+            LoadedNode {
+                encoded,
+                raw_struct,
+                validated: false,
+                brands: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    fn get(&self, id: u64) -> Result<&LoadedNode> {
+        self.nodes
+            .get(&id)
+            .ok_or_else(|| Error::failed(format!("node {id:#x} has not been loaded")))
+    }
+
+    /// Checks that node `id` has been loaded and is of the expected kind.
+    fn expect(&self, id: u64, kind: Kind) -> Result<node::Reader<'static>> {
+        let proto = decode(self.get(id)?.encoded);
+        let (actual, description) = match proto.which()? {
+            node::Struct(_) => (Some(Kind::Struct), "a struct"),
+            node::Enum(_) => (Some(Kind::Enum), "an enum"),
+            node::Interface(_) => (Some(Kind::Interface), "an interface"),
+            _ => (None, "a type"),
+        };
+        if actual != Some(kind) {
+            let expected = match kind {
+                Kind::Struct => "a struct",
+                Kind::Enum => "an enum",
+                Kind::Interface => "an interface",
+            };
+            return Err(Error::failed(format!(
+                "node {id:#x} is {description}, not {expected}"
+            )));
+        }
+        Ok(proto)
+    }
+
+    /// Checks that each struct or interface in `pending`, and everything that their fields,
+    /// methods, and superclasses refer to, has been loaded.
+    fn validate(&mut self, mut pending: Vec<u64>) -> Result<()> {
+        let mut seen = HashSet::new();
+        while let Some(id) = pending.pop() {
+            if self.get(id)?.validated || !seen.insert(id) {
+                continue;
+            }
+            match decode(self.get(id)?.encoded).which()? {
+                node::Struct(st) => {
+                    for field in st.get_fields()? {
+                        match field.which()? {
+                            field::Slot(slot) => self.check_type(slot.get_type()?, &mut pending)?,
+                            field::Group(group) => {
+                                self.expect(group.get_type_id(), Kind::Struct)?;
+                                pending.push(group.get_type_id());
+                            }
+                        }
+                    }
+                }
+                node::Interface(interface) => {
+                    for method in interface.get_methods()? {
+                        self.expect(method.get_param_struct_type(), Kind::Struct)?;
+                        pending.push(method.get_param_struct_type());
+                        self.check_brand(method.get_param_brand()?, &mut pending)?;
+                        self.expect(method.get_result_struct_type(), Kind::Struct)?;
+                        pending.push(method.get_result_struct_type());
+                        self.check_brand(method.get_result_brand()?, &mut pending)?;
+                    }
+                    for superclass in interface.get_superclasses()? {
+                        self.expect(superclass.get_id(), Kind::Interface)?;
+                        pending.push(superclass.get_id());
+                        self.check_brand(superclass.get_brand()?, &mut pending)?;
+                    }
+                }
+                _ => {
+                    return Err(Error::failed(format!(
+                        "node {id:#x} is not a struct or interface"
+                    )))
+                }
+            }
+        }
+        for id in seen {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.validated = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_type(&self, ty: type_::Reader<'_>, pending: &mut Vec<u64>) -> Result<()> {
+        match ty.which()? {
+            type_::List(list) => self.check_type(list.get_element_type()?, pending),
+            type_::Enum(e) => {
+                self.expect(e.get_type_id(), Kind::Enum)?;
+                self.check_brand(e.get_brand()?, pending)
+            }
+            type_::Struct(s) => {
+                self.expect(s.get_type_id(), Kind::Struct)?;
+                pending.push(s.get_type_id());
+                self.check_brand(s.get_brand()?, pending)
+            }
+            type_::Interface(i) => {
+                self.expect(i.get_type_id(), Kind::Interface)?;
+                pending.push(i.get_type_id());
+                self.check_brand(i.get_brand()?, pending)
+            }
+            type_::AnyPointer(a) => {
+                a.which()?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_brand(&self, brand: brand::Reader<'_>, pending: &mut Vec<u64>) -> Result<()> {
+        for scope in brand.get_scopes()? {
+            if let brand::scope::Bind(bindings) = scope.which()? {
+                for binding in bindings? {
+                    if let brand::binding::Type(ty) = binding.which()? {
+                        self.check_type(ty?, pending)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn intern_struct(&mut self, id: u64, bindings: Bindings) -> Result<RawBrandedStructSchema> {
+        self.expect(id, Kind::Struct)?;
+        let Handle::Struct(generic) = self.intern_brand(id, bindings)? else {
+            unreachable!()
+        };
+        Ok(RawBrandedStructSchema {
+            generic,
+            field_types: unresolved_field_type,
+            annotation_types: unresolved_annotation_type,
+        })
+    }
+
+    fn intern_enum(&mut self, id: u64) -> Result<RawEnumSchema> {
+        self.expect(id, Kind::Enum)?;
+        let Handle::Node(encoded_node) = self.intern_brand(id, Vec::new())? else {
+            unreachable!()
+        };
+        Ok(RawEnumSchema {
+            encoded_node,
+            annotation_types: unresolved_annotation_type,
+        })
+    }
+
+    fn intern_interface(&mut self, id: u64, bindings: Bindings) -> Result<RawInterfaceSchema> {
+        self.expect(id, Kind::Interface)?;
+        let Handle::Node(encoded_node) = self.intern_brand(id, bindings)? else {
+            unreachable!()
+        };
+        Ok(RawInterfaceSchema {
+            encoded_node,
+            param_types: unresolved_method_type,
+            result_types: unresolved_method_type,
+            superclass_types: unresolved_superclass_type,
+            annotation_types: unresolved_annotation_type,
+        })
+    }
+
+    /// Gets the handle of a brand of node `id`, creating it if it has not been handed out yet.
+    fn intern_brand(&mut self, id: u64, bindings: Bindings) -> Result<Handle> {
+        let loader = self.this.clone();
+        let node = self
+            .nodes
+            .get_mut(&id)
+            .ok_or_else(|| Error::failed(format!("node {id:#x} has not been loaded")))?;
+        if let Some((handle, _)) = node.brands.iter().find(|(_, b)| b.bindings == bindings) {
+            return Ok(*handle);
+        }
+        let handle = match node.raw_struct {
+            Some(generic) => Handle::Struct(Box::leak(Box::new(*generic))),
+            None => Handle::Node(Box::leak(node.encoded.to_vec().into_boxed_slice())),
+        };
+        let brand = Arc::new(LoadedBrand {
+            loader,
+            encoded: node.encoded,
+            bindings,
+        });
+        lock(&BRANDS)
+            .get_or_insert_with(HashMap::new)
+            .insert(handle.key(), brand.clone());
+        node.brands.push((handle, brand));
+        Ok(handle)
+    }
+
+    fn resolve_type(
+        &mut self,
+        ty: type_::Reader<'_>,
+        bindings: &[(u64, Vec<Type>)],
+    ) -> Result<Type> {
+        Ok(match ty.which()? {
+            type_::Void(()) => TypeVariant::Void.into(),
+            type_::Bool(()) => TypeVariant::Bool.into(),
+            type_::Int8(()) => TypeVariant::Int8.into(),
+            type_::Int16(()) => TypeVariant::Int16.into(),
+            type_::Int32(()) => TypeVariant::Int32.into(),
+            type_::Int64(()) => TypeVariant::Int64.into(),
+            type_::Uint8(()) => TypeVariant::UInt8.into(),
+            type_::Uint16(()) => TypeVariant::UInt16.into(),
+            type_::Uint32(()) => TypeVariant::UInt32.into(),
+            type_::Uint64(()) => TypeVariant::UInt64.into(),
+            type_::Float32(()) => TypeVariant::Float32.into(),
+            type_::Float64(()) => TypeVariant::Float64.into(),
+            type_::Text(()) => TypeVariant::Text.into(),
+            type_::Data(()) => TypeVariant::Data.into(),
+            type_::List(list) => {
+                Type::list_of(self.resolve_type(list.get_element_type()?, bindings)?)
+            }
+            type_::Enum(e) => TypeVariant::Enum(self.intern_enum(e.get_type_id())?).into(),
+            type_::Struct(s) => {
+                let brand = self.resolve_brand(s.get_brand()?, bindings)?;
+                TypeVariant::Struct(self.intern_struct(s.get_type_id(), brand)?).into()
+            }
+            type_::Interface(i) => {
+                let brand = self.resolve_brand(i.get_brand()?, bindings)?;
+                TypeVariant::Capability(self.intern_interface(i.get_type_id(), brand)?).into()
+            }
+            type_::AnyPointer(a) => match a.which()? {
+                type_::any_pointer::Parameter(p) => {
+                    lookup_parameter(bindings, p.get_scope_id(), p.get_parameter_index())
+                }
+                type_::any_pointer::Unconstrained(_)
+                | type_::any_pointer::ImplicitMethodParameter(_) => TypeVariant::AnyPointer.into(),
+            },
+        })
+    }
+
+    /// Resolves `brand` in the context of the bindings of the node in which it appears.
+    fn resolve_brand(
+        &mut self,
+        brand: brand::Reader<'_>,
+        bindings: &[(u64, Vec<Type>)],
+    ) -> Result<Bindings> {
+        let mut result = Vec::new();
+        for scope in brand.get_scopes()? {
+            let scope_id = scope.get_scope_id();
+            match scope.which()? {
+                brand::scope::Bind(params) => {
+                    let mut types = Vec::new();
+                    for binding in params? {
+                        types.push(match binding.which()? {
+                            brand::binding::Unbound(()) => TypeVariant::AnyPointer.into(),
+                            brand::binding::Type(ty) => self.resolve_type(ty?, bindings)?,
+                        });
+                    }
+                    result.push((scope_id, types));
+                }
+                brand::scope::Inherit(()) => {
+                    if let Some(inherited) = bindings.iter().find(|(id, _)| *id == scope_id) {
+                        result.push(inherited.clone());
+                    }
+                }
+            }
+        }
+        let any_pointer: Type = TypeVariant::AnyPointer.into();
+        result.retain(|(_, types)| types.iter().any(|ty| *ty != any_pointer));
+        result.sort_by_key(|(scope_id, _)| *scope_id);
+        result.dedup_by_key(|(scope_id, _)| *scope_id);
+        Ok(result)
+    }
+
+    fn field_type(&mut self, brand: &LoadedBrand, index: u16) -> Result<Type> {
+        let node::Struct(st) = decode(brand.encoded).which()? else {
+            return Err(Error::failed("not a struct".into()));
+        };
+        let fields = st.get_fields()?;
+        if u32::from(index) >= fields.len() {
+            return Err(Error::failed(format!("field index {index} out of bounds")));
+        }
+        match fields.get(index.into()).which()? {
+            field::Slot(slot) => self.resolve_type(slot.get_type()?, &brand.bindings),
+            field::Group(group) => Ok(TypeVariant::Struct(
+                self.intern_struct(group.get_type_id(), brand.bindings.clone())?,
+            )
+            .into()),
+        }
+    }
+
+    fn method_type(
+        &mut self,
+        brand: &LoadedBrand,
+        method_index: u16,
+        results: bool,
+    ) -> Result<Type> {
+        let node::Interface(interface) = decode(brand.encoded).which()? else {
+            return Err(Error::failed("not an interface".into()));
+        };
+        let method = interface
+            .get_methods()?
+            .try_get(method_index.into())
+            .ok_or_else(|| Error::failed(format!("method index {method_index} out of bounds")))?;
+        let (id, method_brand) = if results {
+            (method.get_result_struct_type(), method.get_result_brand()?)
+        } else {
+            (method.get_param_struct_type(), method.get_param_brand()?)
+        };
+        let bindings = self.resolve_brand(method_brand, &brand.bindings)?;
+        Ok(TypeVariant::Struct(self.intern_struct(id, bindings)?).into())
+    }
+
+    fn superclass_type(&mut self, brand: &LoadedBrand, index: u32) -> Result<Type> {
+        let node::Interface(interface) = decode(brand.encoded).which()? else {
+            return Err(Error::failed("not an interface".into()));
+        };
+        let superclass = interface
+            .get_superclasses()?
+            .try_get(index)
+            .ok_or_else(|| Error::failed(format!("superclass index {index} out of bounds")))?;
+        let bindings = self.resolve_brand(superclass.get_brand()?, &brand.bindings)?;
+        let interface = self.intern_interface(superclass.get_id(), bindings)?;
+        Ok(TypeVariant::Capability(interface).into())
+    }
+
+    fn annotation_type(
+        &mut self,
+        brand: &LoadedBrand,
+        child_index: Option<u16>,
+        index: u32,
+    ) -> Result<Type> {
+        let proto = decode(brand.encoded);
+        let annotations = match (child_index, proto.which()?) {
+            (None, _) => proto.get_annotations()?,
+            (Some(i), node::Struct(st)) => st
+                .get_fields()?
+                .try_get(i.into())
+                .ok_or_else(out_of_bounds)?
+                .get_annotations()?,
+            (Some(i), node::Enum(e)) => e
+                .get_enumerants()?
+                .try_get(i.into())
+                .ok_or_else(out_of_bounds)?
+                .get_annotations()?,
+
+            (Some(i), node::Interface(interface)) => interface
+                .get_methods()?
+                .try_get(i.into())
+                .ok_or_else(out_of_bounds)?
+                .get_annotations()?,
+            (Some(_), _) => return Err(out_of_bounds()),
+        };
+        let annotation = annotations.try_get(index).ok_or_else(out_of_bounds)?;
+        let declared = self
+            .nodes
+            .get(&annotation.get_id())
+            .map(|node| decode(node.encoded).which());
+        match declared {
+            Some(Ok(node::Annotation(declaration))) => {
+                let bindings = self.resolve_brand(annotation.get_brand()?, &brand.bindings)?;
+                self.resolve_type(declaration.get_type()?, &bindings)
+            }
+            _ => Ok(type_of_value(annotation)?),
+        }
+    }
+}
+
+fn out_of_bounds() -> Error {
+    Error::failed("annotation index out of bounds".into())
+}
+
+fn lookup_parameter(bindings: &[(u64, Vec<Type>)], scope_id: u64, index: u16) -> Type {
+    bindings
+        .iter()
+        .find(|(id, _)| *id == scope_id)
+        .and_then(|(_, types)| types.get(usize::from(index)))
+        .copied()
+        .unwrap_or_else(|| TypeVariant::AnyPointer.into())
+}
+
+/// Infers the type of an annotation whose declaration has not been loaded. Only works for
+/// values that do not need a schema to be interpreted.
+fn type_of_value(annotation: annotation::Reader<'_>) -> Result<Type> {
+    Ok(match annotation.get_value()?.which()? {
+        value::Void(()) => TypeVariant::Void.into(),
+        value::Bool(_) => TypeVariant::Bool.into(),
+        value::Int8(_) => TypeVariant::Int8.into(),
+        value::Int16(_) => TypeVariant::Int16.into(),
+        value::Int32(_) => TypeVariant::Int32.into(),
+        value::Int64(_) => TypeVariant::Int64.into(),
+        value::Uint8(_) => TypeVariant::UInt8.into(),
+        value::Uint16(_) => TypeVariant::UInt16.into(),
+        value::Uint32(_) => TypeVariant::UInt32.into(),
+        value::Uint64(_) => TypeVariant::UInt64.into(),
+        value::Float32(_) => TypeVariant::Float32.into(),
+        value::Float64(_) => TypeVariant::Float64.into(),
+        value::Text(_) => TypeVariant::Text.into(),
+        value::Data(_) => TypeVariant::Data.into(),
+        value::List(_)
+        | value::Enum(_)
+        | value::Struct(_)
+        | value::AnyPointer(_)
+        | value::Interface(()) => TypeVariant::AnyPointer.into(),
+    })
+}
+
+fn raw_struct_schema(
+    encoded: &'static [Word],
+    st: node::struct_::Reader<'_>,
+) -> Result<&'static RawStructSchema> {
+    let mut nonunion_members = Vec::new();
+    let mut union_members = Vec::new();
+    for (index, field) in st.get_fields()?.iter().enumerate() {
+        let discriminant = field.get_discriminant_value();
+        if discriminant == field::NO_DISCRIMINANT {
+            nonunion_members.push(index as u16);
+        } else {
+            union_members.push((discriminant, index as u16));
+        }
+    }
+    union_members.sort();
+    let mut members_by_discriminant = Vec::new();
+    for (expected, (discriminant, index)) in union_members.into_iter().enumerate() {
+        if usize::from(discriminant) != expected {
+            return Err(Error::failed(format!(
+                "union discriminants are not contiguous: expected {expected}, got {discriminant}"
+            )));
+        }
+        members_by_discriminant.push(index);
+    }
+    Ok(Box::leak(Box::new(RawStructSchema {
+        encoded_node: encoded,
+        nonunion_members: Box::leak(nonunion_members.into_boxed_slice()),
+        members_by_discriminant: Box::leak(members_by_discriminant.into_boxed_slice()),
+    })))
+}
+
+fn decode(encoded: &'static [Word]) -> node::Reader<'static> {
+    // `encoded` was produced by `set_root_canonical()` from a validated reader, so it can be
+    // read without bounds checks, just like the nodes embedded in generated code.
+    crate::any_pointer::Reader::new(unsafe {
+        layout::PointerReader::get_root_unchecked(encoded.as_ptr() as *const u8)
+    })
+    .get_as()
+    .unwrap()
+}
+
+fn unresolved_field_type(_index: u16) -> Type {
+    panic!("types of loaded schemas must be looked up with RawBrandedStructSchema::field_type()")
+}
+
+fn unresolved_method_type(_method_index: u16) -> Type {
+    panic!("types of loaded schemas must be looked up with param_type() or result_type()")
+}
+
+fn unresolved_superclass_type(_index: u32) -> Type {
+    panic!("types of loaded schemas must be looked up with RawInterfaceSchema::superclass_type()")
+}
+
+fn unresolved_annotation_type(_child_index: Option<u16>, _index: u32) -> Type {
+    panic!("types of loaded schemas must be looked up with annotation_type()")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SchemaLoader;
+    use crate::introspect::{Introspect, TypeVariant};
+    use crate::schema::StructSchema;
+    use crate::schema_capnp::{field, node, type_};
+    use crate::{dynamic_value, message};
+
+    const BOX_ID: u64 = 0xa6c4_9d4e_3b2f_1a01;
+    const HOLDER_ID: u64 = 0xa6c4_9d4e_3b2f_1a02;
+    const BASE_ID: u64 = 0xa6c4_9d4e_3b2f_1a03;
+    const DERIVED_ID: u64 = 0xa6c4_9d4e_3b2f_1a04;
+
+    /// Loads every node reachable from a generated struct.
+    fn load_generated(loader: &SchemaLoader, schema: StructSchema) {
+        let id = schema.get_proto().get_id();
+        if loader.get_node(id).is_some() {
+            return;
+        }
+        loader.load(schema.get_proto()).unwrap();
+        for field in schema.get_fields().unwrap() {
+            let mut ty = field.get_type();
+            while let TypeVariant::List(element) = ty.which() {
+                ty = element;
+            }
+            match ty.which() {
+                TypeVariant::Struct(rbs) => load_generated(loader, rbs.into()),
+                TypeVariant::Enum(re) => loader
+                    .load(crate::schema::EnumSchema::from(re).get_proto())
+                    .unwrap(),
+                _ => (),
+            }
+        }
+    }
+
+    /// `struct Box(T) { value @0 :T; }` and
+    /// `struct Holder { box @0 :Box(Text); plain @1 :Box; }`.
+    fn generic_nodes() -> message::Builder<message::HeapAllocator> {
+        let mut message = message::Builder::new_default();
+        let mut nodes = message.initn_root::<crate::struct_list::Builder<node::Owned>>(2);
+        {
+            let mut boxed = nodes.reborrow().get(0);
+            boxed.set_id(BOX_ID);
+            boxed.set_display_name("test.capnp:Box");
+            boxed.set_is_generic(true);
+            boxed.reborrow().init_parameters(1).get(0).set_name("T");
+            let mut st = boxed.init_struct();
+            st.set_pointer_count(1);
+            let mut value = st.init_fields(1).get(0);
+            value.set_name("value");
+            let mut param = value
+                .init_slot()
+                .init_type()
+                .init_any_pointer()
+                .init_parameter();
+            param.set_scope_id(BOX_ID);
+            param.set_parameter_index(0);
+        }
+        {
+            let mut holder = nodes.get(1);
+            holder.set_id(HOLDER_ID);
+            holder.set_display_name("test.capnp:Holder");
+            let mut st = holder.init_struct();
+            st.set_pointer_count(2);
+            let mut fields = st.init_fields(2);
+            let mut boxed = fields.reborrow().get(0);
+            boxed.set_name("box");
+            let mut ty = boxed.init_slot().init_type().init_struct();
+            ty.set_type_id(BOX_ID);
+            let mut scope = ty.init_brand().init_scopes(1).get(0);
+            scope.set_scope_id(BOX_ID);
+            scope.init_bind(1).get(0).init_type().set_text(());
+            let mut plain = fields.get(1);
+            plain.set_name("plain");
+            plain.set_code_order(1);
+            let mut slot = plain.init_slot();
+            slot.set_offset(1);
+            slot.init_type().init_struct().set_type_id(BOX_ID);
+        }
+        message
+    }
+
+    /// `interface Base { get @0 Holder -> Box(Text); }` and `interface Derived extends(Base) {}`.
+    fn interface_nodes() -> message::Builder<message::HeapAllocator> {
+        let mut message = message::Builder::new_default();
+        let mut nodes = message.initn_root::<crate::struct_list::Builder<node::Owned>>(2);
+        {
+            let mut base = nodes.reborrow().get(0);
+            base.set_id(BASE_ID);
+            base.set_display_name("test.capnp:Base");
+            let mut method = base.init_interface().init_methods(1).get(0);
+            method.set_name("get");
+            method.set_param_struct_type(HOLDER_ID);
+            method.set_result_struct_type(BOX_ID);
+            let mut scope = method.init_result_brand().init_scopes(1).get(0);
+            scope.set_scope_id(BOX_ID);
+            scope.init_bind(1).get(0).init_type().set_text(());
+        }
+        {
+            let mut derived = nodes.get(1);
+            derived.set_id(DERIVED_ID);
+            derived.set_display_name("test.capnp:Derived");
+            let mut interface = derived.init_interface();
+            interface.reborrow().init_methods(0);
+            interface.init_superclasses(1).get(0).set_id(BASE_ID);
+        }
+        message
+    }
+
+    fn load_all(loader: &SchemaLoader, message: &message::Builder<message::HeapAllocator>) {
+        let nodes = message
+            .get_root_as_reader::<crate::struct_list::Reader<node::Owned>>()
+            .unwrap();
+        for node in nodes {
+            loader.load(node).unwrap();
+        }
+    }
+
+    #[test]
+    fn loaded_schema_matches_generated() {
+        let generated: StructSchema = match node::Owned::introspect().which() {
+            TypeVariant::Struct(rbs) => rbs.into(),
+            _ => panic!(),
+        };
+        let loader = SchemaLoader::new();
+        load_generated(&loader, generated);
+        let loaded = loader.get_struct(generated.get_proto().get_id()).unwrap();
+
+        let mut message = message::Builder::new_default();
+        let mut root = message.init_root::<node::Builder>();
+        root.set_id(42);
+        root.set_display_name("foo.capnp:Bar");
+        let mut st = root.reborrow().init_struct();
+        st.set_data_word_count(2);
+        st.set_preferred_list_encoding(crate::schema_capnp::ElementSize::EightBytes);
+        let mut field = st.init_fields(1).get(0);
+        field.set_name("baz");
+        field
+            .init_slot()
+            .init_type()
+            .init_list()
+            .init_element_type()
+            .set_float64(());
+
+        let expected = format!(
+            "{:?}",
+            dynamic_value::Reader::from(root.reborrow_as_reader())
+        );
+        let any = message
+            .get_root_as_reader::<crate::any_pointer::Reader>()
+            .unwrap();
+        let actual = any.get_as_struct(loaded).unwrap();
+        assert_eq!(
+            format!("{:?}", dynamic_value::Reader::from(actual)),
+            expected
+        );
+        assert!(expected.contains("preferredListEncoding = eightBytes"));
+    }
+
+    #[test]
+    fn branded_generics() {
+        let loader = SchemaLoader::new();
+        load_all(&loader, &generic_nodes());
+        let holder = loader.get_struct(HOLDER_ID).unwrap();
+
+        let box_of_text = holder.get_field_by_name("box").unwrap().get_type();
+        let TypeVariant::Struct(rbs) = box_of_text.which() else {
+            panic!()
+        };
+        let value_type = StructSchema::from(rbs)
+            .get_field_by_name("value")
+            .unwrap()
+            .get_type();
+        assert!(value_type.which() == TypeVariant::Text);
+
+        let plain = holder.get_field_by_name("plain").unwrap().get_type();
+        let TypeVariant::Struct(rbs) = plain.which() else {
+            panic!()
+        };
+        let value_type = StructSchema::from(rbs)
+            .get_field_by_name("value")
+            .unwrap()
+            .get_type();
+        assert!(value_type.which() == TypeVariant::AnyPointer);
+        assert!(box_of_text != plain);
+
+        // Equal brands resolve to equal types.
+        assert!(holder.get_field_by_name("box").unwrap().get_type() == box_of_text);
+
+        let mut message = message::Builder::new_default();
+        let root = message
+            .init_root::<crate::any_pointer::Builder>()
+            .init_as_struct(holder)
+            .unwrap();
+        let dynamic_value::Builder::Struct(mut boxed) = root.init_named("box").unwrap() else {
+            panic!()
+        };
+        boxed.set_named("value", "hello".into()).unwrap();
+        let reader = message
+            .get_root_as_reader::<crate::any_pointer::Reader>()
+            .unwrap()
+            .get_as_struct(holder)
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", dynamic_value::Reader::from(reader)),
+            "(box = (value = \"hello\"))"
+        );
+    }
+
+    #[test]
+    fn get_struct_with_brand() {
+        let loader = SchemaLoader::new();
+        let nodes = generic_nodes();
+        load_all(&loader, &nodes);
+
+        let holder = loader.get_node(HOLDER_ID).unwrap();
+        let node::Struct(st) = holder.which().unwrap() else {
+            panic!()
+        };
+        let field::Slot(slot) = st.get_fields().unwrap().get(0).which().unwrap() else {
+            panic!()
+        };
+        let ty = slot.get_type().unwrap();
+        let type_::Struct(s) = ty.which().unwrap() else {
+            panic!()
+        };
+        let schema = loader
+            .get_struct_with_brand(BOX_ID, s.get_brand().unwrap())
+            .unwrap();
+        let field_type = schema.get_field_by_name("value").unwrap().get_type();
+        assert!(field_type.which() == TypeVariant::Text);
+        assert!(
+            loader.get_type(ty).unwrap()
+                == loader
+                    .get_struct(HOLDER_ID)
+                    .unwrap()
+                    .get_field_by_name("box")
+                    .unwrap()
+                    .get_type()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let loader = SchemaLoader::new();
+        let nodes = generic_nodes();
+        let reader = nodes
+            .get_root_as_reader::<crate::struct_list::Reader<node::Owned>>()
+            .unwrap();
+        loader.load(reader.get(1)).unwrap();
+        assert!(loader.get_struct(BOX_ID).is_err());
+        // Holder depends on Box, which is missing.
+        assert!(loader.get_struct(HOLDER_ID).is_err());
+
+        loader.load(reader.get(0)).unwrap();
+        assert!(loader.get_struct(HOLDER_ID).is_ok());
+        assert!(loader.get_enum(HOLDER_ID).is_err());
+
+        // Loading the same node twice is fine; redefining it is not.
+        loader.load(reader.get(0)).unwrap();
+        let mut message = message::Builder::new_default();
+        message.set_root(reader.get(0)).unwrap();
+        let mut changed = message.get_root::<node::Builder>().unwrap();
+        changed.set_display_name("test.capnp:Crate");
+        assert!(loader.load(changed.into_reader()).is_err());
+    }
+
+    #[test]
+    fn interfaces() {
+        let loader = SchemaLoader::new();
+        load_all(&loader, &interface_nodes());
+        // Base refers to Holder and Box, which are missing.
+        assert!(loader.get_interface(DERIVED_ID).is_err());
+        load_all(&loader, &generic_nodes());
+        assert!(loader.get_struct(BASE_ID).is_err());
+
+        let derived = loader.get_interface(DERIVED_ID).unwrap();
+        assert!(derived.get_methods().unwrap().is_empty());
+        let superclasses = derived.get_superclasses().unwrap();
+        assert_eq!(superclasses.len(), 1);
+        let base = superclasses.get(0);
+        assert_eq!(base.get_proto().get_id(), BASE_ID);
+        assert!(derived.extends(base).unwrap());
+        assert!(!base.extends(derived).unwrap());
+
+        let method = derived.get_method_by_name("get").unwrap();
+        assert_eq!(method.get_ordinal(), 0);
+        assert_eq!(
+            method.get_containing_interface().get_proto().get_id(),
+            BASE_ID
+        );
+        assert_eq!(method.get_param_type().get_proto().get_id(), HOLDER_ID);
+        let results = method.get_result_type();
+        assert_eq!(results.get_proto().get_id(), BOX_ID);
+        let value = results.get_field_by_name("value").unwrap();
+        assert!(matches!(value.get_type().which(), TypeVariant::Text));
+
+        let loaded_base = loader.get_interface(BASE_ID).unwrap();
+        assert!(
+            loaded_base
+                .get_methods()
+                .unwrap()
+                .get(0)
+                .get_result_type()
+                .raw
+                == results.raw
+        );
+        assert!(loader.get_interface(HOLDER_ID).is_err());
+    }
+
+    #[test]
+    fn loaders_are_independent() {
+        let nodes = generic_nodes();
+        let loader1 = SchemaLoader::new();
+        load_all(&loader1, &nodes);
+        let loader2 = SchemaLoader::new();
+        load_all(&loader2, &nodes);
+
+        // Identical nodes share their encoding, but each loader hands out schemas of its own.
+        let schema1 = loader1.get_struct(HOLDER_ID).unwrap();
+        let schema2 = loader2.get_struct(HOLDER_ID).unwrap();
+        assert!(core::ptr::eq(
+            schema1.raw.generic.encoded_node,
+            schema2.raw.generic.encoded_node
+        ));
+        assert!(schema1.raw != schema2.raw);
+
+        // A loader only sees the nodes that were loaded through it, and may define them
+        // differently.
+        let loader3 = SchemaLoader::new();
+        assert!(loader3.get_node(HOLDER_ID).is_none());
+        assert!(loader3.get_struct(HOLDER_ID).is_err());
+        let reader = nodes
+            .get_root_as_reader::<crate::struct_list::Reader<node::Owned>>()
+            .unwrap();
+        loader3.load(reader.get(1)).unwrap();
+        assert!(loader3.get_struct(HOLDER_ID).is_err());
+        let mut message = message::Builder::new_default();
+        message.set_root(reader.get(0)).unwrap();
+        let mut changed = message.get_root::<node::Builder>().unwrap();
+        changed.set_display_name("test.capnp:Crate");
+        loader3.load(changed.into_reader()).unwrap();
+
+        let box_name = |schema: StructSchema| {
+            let TypeVariant::Struct(rbs) =
+                schema.get_field_by_name("box").unwrap().get_type().which()
+            else {
+                panic!()
+            };
+            StructSchema::from(rbs)
+                .get_proto()
+                .get_display_name()
+                .unwrap()
+        };
+        assert_eq!(
+            box_name(loader3.get_struct(HOLDER_ID).unwrap()),
+            "test.capnp:Crate"
+        );
+        assert_eq!(box_name(schema1), "test.capnp:Box");
+
+        // Once its loader is gone, the types that a schema refers to can no longer be resolved.
+        drop(loader1);
+        let ty = schema1.get_field_by_name("box").unwrap().get_type();
+        assert!(ty.which() == TypeVariant::AnyPointer);
+        assert_eq!(box_name(schema2), "test.capnp:Box");
+    }
+}
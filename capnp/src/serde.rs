@@ -0,0 +1,796 @@
+//! Integration with [serde](https://serde.rs), enabled by the `serde` feature.
+//!
+//! [`dynamic_value::Reader`], [`dynamic_struct::Reader`], [`dynamic_list::Reader`] and every
+//! generated struct `Reader` implement `serde::Serialize`, so that messages can be written in
+//! any format that serde supports. Values map onto the serde data model as follows:
+//!
+//! * Structs and groups become serde structs whose fields have the names given in the schema.
+//!   As in the text format, null pointer fields and inactive union members are left out. An
+//!   active union member whose pointer is null is written as a unit.
+//! * Lists become sequences and `Data` becomes bytes.
+//! * Enums become unit variants named after their enumerants, or plain `u16` values if they are
+//!   not in the schema.
+//! * `Void` becomes a unit.
+//!
+//! `AnyPointer` and capability fields cannot be serialized.
+//!
+//! In the other direction, [`deserialize_into()`] fills in a [`dynamic_struct::Builder`] from
+//! any self-describing serde format, guided by the schema. It accepts the output of the
+//! serializer, and is lenient where formats differ: integers may be given as strings, enums as
+//! names or numbers, and `Data` as bytes, strings or sequences of bytes. A null pointer field
+//! is cleared.
+
+use ::serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use ::serde::ser::{Error as _, SerializeSeq, SerializeStruct};
+use ::serde::{Serialize, Serializer};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::dynamic_value::FieldPath;
+use crate::introspect::{Type, TypeVariant};
+use crate::schema::Field;
+use crate::schema_capnp::field;
+use crate::{any_pointer, dynamic_list, dynamic_struct, dynamic_value, message, Result};
+
+impl<'a> Serialize for dynamic_value::Reader<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match *self {
+            dynamic_value::Reader::Void => serializer.serialize_unit(),
+            dynamic_value::Reader::Bool(b) => serializer.serialize_bool(b),
+            dynamic_value::Reader::Int8(x) => serializer.serialize_i8(x),
+            dynamic_value::Reader::Int16(x) => serializer.serialize_i16(x),
+            dynamic_value::Reader::Int32(x) => serializer.serialize_i32(x),
+            dynamic_value::Reader::Int64(x) => serializer.serialize_i64(x),
+            dynamic_value::Reader::UInt8(x) => serializer.serialize_u8(x),
+            dynamic_value::Reader::UInt16(x) => serializer.serialize_u16(x),
+            dynamic_value::Reader::UInt32(x) => serializer.serialize_u32(x),
+            dynamic_value::Reader::UInt64(x) => serializer.serialize_u64(x),
+            dynamic_value::Reader::Float32(x) => serializer.serialize_f32(x),
+            dynamic_value::Reader::Float64(x) => serializer.serialize_f64(x),
+            dynamic_value::Reader::Enum(e) => match e.get_enumerant().map_err(S::Error::custom)? {
+                Some(enumerant) => {
+                    let schema = enumerant.get_containing_enum();
+                    serializer.serialize_unit_variant(
+                        short_name(schema.get_proto()).map_err(S::Error::custom)?,
+                        u32::from(enumerant.get_ordinal()),
+                        enumerant.get_proto().get_name().map_err(S::Error::custom)?,
+                    )
+                }
+                None => serializer.serialize_u16(e.get_value()),
+            },
+            dynamic_value::Reader::Text(t) => serializer.serialize_str(t),
+            dynamic_value::Reader::Data(d) => serializer.serialize_bytes(d),
+            dynamic_value::Reader::List(l) => l.serialize(serializer),
+            dynamic_value::Reader::Struct(s) => s.serialize(serializer),
+            dynamic_value::Reader::AnyPointer(_) => {
+                Err(S::Error::custom("cannot serialize AnyPointer values"))
+            }
+            dynamic_value::Reader::Capability(_) => {
+                Err(S::Error::custom("cannot serialize capabilities"))
+            }
+        }
+    }
+}
+
+impl<'a> Serialize for dynamic_list::Reader<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len() as usize))?;
+        for element in self.iter() {
+            seq.serialize_element(&element.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'a> Serialize for dynamic_struct::Reader<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let fields = present_fields(self).map_err(S::Error::custom)?;
+        let name = short_name(self.get_schema().get_proto()).map_err(S::Error::custom)?;
+        let mut st = serializer.serialize_struct(name, fields.len())?;
+        for field in fields {
+            let name = field.get_proto().get_name().map_err(S::Error::custom)?;
+            if self.has(field).map_err(S::Error::custom)? {
+                st.serialize_field(name, &self.get(field).map_err(S::Error::custom)?)?;
+            } else {
+                st.serialize_field(name, &())?;
+            }
+        }
+        st.end()
+    }
+}
+
+/// Returns the fields of `reader` that get serialized, in code order.
+fn present_fields(reader: &dynamic_struct::Reader<'_>) -> Result<Vec<Field>> {
+    let active = reader.which()?;
+    let mut fields = Vec::new();
+    for field in reader.get_schema().get_fields()? {
+        let is_active = active.map(|f| f.get_index()) == Some(field.get_index());
+        let present = if field.get_proto().get_discriminant_value() == field::NO_DISCRIMINANT {
+            reader.has(field)?
+        } else {
+            // The active member always gets written, unless it is the default one and
+            // would not be written otherwise, so that the reader can tell which one it is.
+            is_active && (field.get_proto().get_discriminant_value() != 0 || reader.has(field)?)
+        };
+        if present {
+            fields.push(field);
+        }
+    }
+    Ok(fields)
+}
+
+/// Gets the name of a node without the scope prefix, e.g. `Foo` for `foo.capnp:Outer.Foo`.
+fn short_name(node: crate::schema_capnp::node::Reader<'static>) -> Result<&'static str> {
+    let display_name = node.get_display_name()?;
+    Ok(&display_name[node.get_display_name_prefix_length() as usize..])
+}
+
+/// Deserializes a map from `deserializer` into `builder`, setting each field that the map
+/// names. Fields that it does not mention are left untouched.
+///
+/// Errors name the path of the offending field, like `nestedNodes[2].id`.
+///
+/// ```
+/// use capnp::{dynamic_struct, dynamic_value, message};
+/// use capnp::schema_capnp::node;
+///
+/// let mut message = message::Builder::new_default();
+/// let root: dynamic_value::Builder<'_> = message.init_root::<node::Builder>().into();
+/// let mut deserializer = serde_json::Deserializer::from_str(
+///     r#"{"id": 7, "nestedNodes": [{"name": "Bar", "id": 8}]}"#,
+/// );
+/// capnp::serde::deserialize_into(&mut deserializer, root.downcast::<dynamic_struct::Builder<'_>>())
+///     .unwrap();
+///
+/// let node = message.get_root_as_reader::<node::Reader>().unwrap();
+/// assert_eq!(node.get_id(), 7);
+/// assert_eq!(node.get_nested_nodes().unwrap().get(0).get_name().unwrap(), "Bar");
+/// ```
+pub fn deserialize_into<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    builder: dynamic_struct::Builder<'_>,
+) -> core::result::Result<(), D::Error> {
+    let mut path = FieldPath::new();
+    deserializer.deserialize_map(StructVisitor {
+        builder,
+        path: &mut path,
+    })
+}
+
+fn capnp_error<E: de::Error>(error: impl fmt::Display, path: &FieldPath) -> E {
+    if path.is_empty() {
+        E::custom(error)
+    } else {
+        E::custom(format_args!("{error} at `{path}`"))
+    }
+}
+
+/// Describes what a visitor expects, including where in the message it is.
+fn expecting(f: &mut fmt::Formatter, what: &str, path: &FieldPath) -> fmt::Result {
+    f.write_str(what)?;
+    if !path.is_empty() {
+        write!(f, " for `{path}`")?;
+    }
+    Ok(())
+}
+
+/// Where a deserialized value goes.
+enum Slot<'b> {
+    Field(dynamic_struct::Builder<'b>, Field),
+    Element(dynamic_list::Builder<'b>, u32),
+}
+
+impl<'b> Slot<'b> {
+    fn set(&mut self, value: dynamic_value::Reader<'_>) -> Result<()> {
+        match self {
+            Slot::Field(builder, field) => builder.set(*field, value),
+            Slot::Element(list, index) => list.set(*index, value),
+        }
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        match self {
+            Slot::Field(builder, field) => builder.clear(*field),
+            // Pointer elements of a new list are already null.
+            Slot::Element(..) => Ok(()),
+        }
+    }
+
+    fn init_struct(self) -> Result<dynamic_struct::Builder<'b>> {
+        Ok(match self {
+            Slot::Field(builder, field) => {
+                let is_union_member =
+                    field.get_proto().get_discriminant_value() != field::NO_DISCRIMINANT;
+                match field.get_proto().which()? {
+                    field::Group(_) if !is_union_member => builder.get(field)?,
+                    _ => builder.init(field)?,
+                }
+                .downcast()
+            }
+            Slot::Element(list, index) => list.get(index)?.downcast(),
+        })
+    }
+
+    fn init_list(self, len: u32) -> Result<dynamic_list::Builder<'b>> {
+        Ok(match self {
+            Slot::Field(builder, field) => builder.initn(field, len)?.downcast(),
+            Slot::Element(list, index) => list.init(index, len)?.downcast(),
+        })
+    }
+}
+
+/// Deserializes a value of type `ty` into `slot`.
+struct ValueSeed<'p, 'b> {
+    slot: Slot<'b>,
+    ty: Type,
+    path: &'p mut FieldPath,
+}
+
+impl<'de, 'p, 'b> DeserializeSeed<'de> for ValueSeed<'p, 'b> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> core::result::Result<(), D::Error> {
+        let is_group = match &self.slot {
+            Slot::Field(_, field) => matches!(
+                field
+                    .get_proto()
+                    .which()
+                    .map_err(|e| capnp_error(e, self.path))?,
+                field::Group(_)
+            ),
+            Slot::Element(..) => false,
+        };
+        let ValueSeed { mut slot, ty, path } = self;
+        let visitor = ScalarVisitor { ty, path };
+        let value = match ty.which() {
+            TypeVariant::Struct(_) if is_group => {
+                let builder = slot.init_struct().map_err(|e| capnp_error(e, path))?;
+                return deserializer.deserialize_map(StructVisitor { builder, path });
+            }
+            TypeVariant::Struct(_)
+            | TypeVariant::List(_)
+            | TypeVariant::Text
+            | TypeVariant::Data => {
+                return deserializer.deserialize_option(PointerVisitor { slot, ty, path })
+            }
+            TypeVariant::AnyPointer | TypeVariant::Capability(_) => {
+                return Err(de::Error::custom(format_args!(
+                    "cannot deserialize AnyPointer or capability fields like `{path}`"
+                )))
+            }
+            TypeVariant::Void => deserializer.deserialize_unit(visitor)?,
+            TypeVariant::Bool => deserializer.deserialize_bool(visitor)?,
+            // Numbers and enums may be written in more than one way, so let the input decide.
+            _ => deserializer.deserialize_any(visitor)?,
+        };
+        slot.set(value).map_err(|e| capnp_error(e, path))
+    }
+}
+
+/// Deserializes a struct, list, text or data value, which may be null.
+struct PointerVisitor<'p, 'b> {
+    slot: Slot<'b>,
+    ty: Type,
+    path: &'p mut FieldPath,
+}
+
+impl<'de, 'p, 'b> Visitor<'de> for PointerVisitor<'p, 'b> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.ty.which() {
+            TypeVariant::Struct(_) => "a map",
+            TypeVariant::List(_) => "a sequence",
+            TypeVariant::Text => "a string",
+            _ => "bytes",
+        };
+        expecting(f, what, self.path)
+    }
+
+    fn visit_none<E: de::Error>(mut self) -> core::result::Result<(), E> {
+        self.slot.clear().map_err(|e| capnp_error(e, self.path))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> core::result::Result<(), E> {
+        self.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> core::result::Result<(), D::Error> {
+        let PointerVisitor { mut slot, ty, path } = self;
+        match ty.which() {
+            TypeVariant::Struct(_) => {
+                let builder = slot.init_struct().map_err(|e| capnp_error(e, path))?;
+                deserializer.deserialize_map(StructVisitor { builder, path })
+            }
+            TypeVariant::List(element_type) => deserializer.deserialize_seq(ListVisitor {
+                slot,
+                element_type,
+                path,
+            }),
+            TypeVariant::Text => {
+                let text = deserializer.deserialize_string(TextVisitor { path })?;
+                slot.set(dynamic_value::Reader::Text(&text))
+                    .map_err(|e| capnp_error(e, path))
+            }
+            _ => {
+                let data = deserializer.deserialize_byte_buf(DataVisitor { path })?;
+                slot.set(dynamic_value::Reader::Data(&data))
+                    .map_err(|e| capnp_error(e, path))
+            }
+        }
+    }
+}
+
+struct StructVisitor<'p, 'b> {
+    builder: dynamic_struct::Builder<'b>,
+    path: &'p mut FieldPath,
+}
+
+impl<'de, 'p, 'b> Visitor<'de> for StructVisitor<'p, 'b> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        expecting(f, "a map", self.path)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> core::result::Result<(), A::Error> {
+        let schema = self.builder.get_schema();
+        while let Some(name) = map.next_key::<String>()? {
+            self.path.push_field(&name);
+            let field = schema
+                .find_field_by_name(&name)
+                .map_err(|e| capnp_error(e, self.path))?;
+            let Some(field) = field else {
+                return Err(de::Error::custom(format_args!(
+                    "unknown field `{}`",
+                    self.path
+                )));
+            };
+            map.next_value_seed(ValueSeed {
+                slot: Slot::Field(self.builder.reborrow(), field),
+                ty: field.get_type(),
+                path: self.path,
+            })?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a sequence into a list. Since the length of a sequence is generally not known
+/// up front, the elements are first collected in a scratch message.
+struct ListVisitor<'p, 'b> {
+    slot: Slot<'b>,
+    element_type: Type,
+    path: &'p mut FieldPath,
+}
+
+/// Gets the list at the root of `scratch`.
+fn scratch_list(
+    scratch: &mut message::Builder<message::HeapAllocator>,
+    element_type: Type,
+) -> Result<dynamic_list::Builder<'_>> {
+    let pointer = scratch.get_root::<any_pointer::Builder>()?.builder;
+    let list = match element_type.which() {
+        TypeVariant::Struct(schema) => pointer.get_struct_list(
+            dynamic_struct::struct_size_from_schema(schema.into())?,
+            None,
+        )?,
+        _ => pointer.get_list(element_type.expected_element_size(), None)?,
+    };
+    Ok(dynamic_list::Builder::new(list, element_type))
+}
+
+/// Replaces the list at the root of `scratch` with one of `capacity` elements, keeping the
+/// first `len`.
+fn grow_scratch_list(
+    scratch: &mut message::Builder<message::HeapAllocator>,
+    element_type: Type,
+    len: u32,
+    capacity: u32,
+) -> Result<()> {
+    let pointer = scratch.get_root::<any_pointer::Builder>()?.builder;
+    dynamic_list::rebuild(pointer, element_type, capacity, |old, mut new| {
+        for index in 0..len {
+            new.set(index, old.get(index)?)?;
+        }
+        Ok(())
+    })
+}
+
+impl<'de, 'p, 'b> Visitor<'de> for ListVisitor<'p, 'b> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        expecting(f, "a sequence", self.path)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<(), A::Error> {
+        let mut scratch = message::Builder::new_default();
+        // Don't trust the size hint too much, since it may come from untrusted input.
+        let mut capacity = seq.size_hint().unwrap_or(0).clamp(4, 1024) as u32;
+        let mut len = 0;
+        grow_scratch_list(&mut scratch, self.element_type, 0, capacity)
+            .map_err(|e| capnp_error(e, self.path))?;
+        loop {
+            if len == capacity {
+                capacity *= 2;
+                grow_scratch_list(&mut scratch, self.element_type, len, capacity)
+                    .map_err(|e| capnp_error(e, self.path))?;
+            }
+            let list = scratch_list(&mut scratch, self.element_type)
+                .map_err(|e| capnp_error(e, self.path))?;
+            self.path.push_index(len);
+            let element = seq.next_element_seed(ValueSeed {
+                slot: Slot::Element(list, len),
+                ty: self.element_type,
+                path: self.path,
+            })?;
+            self.path.pop();
+            if element.is_none() {
+                break;
+            }
+            len += 1;
+        }
+
+        let ListVisitor {
+            slot,
+            element_type,
+            path,
+        } = self;
+        let mut list = slot.init_list(len).map_err(|e| capnp_error(e, path))?;
+        let elements = scratch_list(&mut scratch, element_type)
+            .map_err(|e| capnp_error(e, path))?
+            .into_reader();
+        for index in 0..len {
+            elements
+                .get(index)
+                .and_then(|element| list.set(index, element))
+                .map_err(|e| capnp_error(e, path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a scalar value of type `ty`.
+struct ScalarVisitor<'p> {
+    ty: Type,
+    path: &'p FieldPath,
+}
+
+impl<'p> ScalarVisitor<'p> {
+    fn integer<E: de::Error>(
+        &self,
+        value: i128,
+    ) -> core::result::Result<dynamic_value::Reader<'static>, E> {
+        fn convert<T: TryFrom<i128>, E: de::Error>(
+            value: i128,
+            path: &FieldPath,
+        ) -> core::result::Result<T, E> {
+            T::try_from(value).map_err(|_| {
+                E::custom(format_args!("integer {value} is out of range for `{path}`"))
+            })
+        }
+        Ok(match self.ty.which() {
+            TypeVariant::Int8 => dynamic_value::Reader::Int8(convert(value, self.path)?),
+            TypeVariant::Int16 => dynamic_value::Reader::Int16(convert(value, self.path)?),
+            TypeVariant::Int32 => dynamic_value::Reader::Int32(convert(value, self.path)?),
+            TypeVariant::Int64 => dynamic_value::Reader::Int64(convert(value, self.path)?),
+            TypeVariant::UInt8 => dynamic_value::Reader::UInt8(convert(value, self.path)?),
+            TypeVariant::UInt16 => dynamic_value::Reader::UInt16(convert(value, self.path)?),
+            TypeVariant::UInt32 => dynamic_value::Reader::UInt32(convert(value, self.path)?),
+            TypeVariant::UInt64 => dynamic_value::Reader::UInt64(convert(value, self.path)?),
+            TypeVariant::Float32 => dynamic_value::Reader::Float32(value as f32),
+            TypeVariant::Float64 => dynamic_value::Reader::Float64(value as f64),
+            TypeVariant::Enum(schema) => {
+                dynamic_value::Enum::new(convert(value, self.path)?, schema.into()).into()
+            }
+            _ => {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other("an integer"),
+                    self,
+                ))
+            }
+        })
+    }
+}
+
+impl<'de, 'p> Visitor<'de> for ScalarVisitor<'p> {
+    type Value = dynamic_value::Reader<'static>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.ty.which() {
+            TypeVariant::Void => "a unit",
+            TypeVariant::Bool => "a boolean",
+            TypeVariant::Float32 | TypeVariant::Float64 => "a number",
+            TypeVariant::Enum(_) => "an enumerant name",
+            _ => "an integer",
+        };
+        expecting(f, what, self.path)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> core::result::Result<Self::Value, E> {
+        match self.ty.which() {
+            TypeVariant::Void => Ok(dynamic_value::Reader::Void),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Unit, &self)),
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> core::result::Result<Self::Value, E> {
+        match self.ty.which() {
+            TypeVariant::Bool => Ok(dynamic_value::Reader::Bool(v)),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Bool(v), &self)),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> core::result::Result<Self::Value, E> {
+        self.integer(i128::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> core::result::Result<Self::Value, E> {
+        self.integer(i128::from(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> core::result::Result<Self::Value, E> {
+        match self.ty.which() {
+            TypeVariant::Float32 => Ok(dynamic_value::Reader::Float32(v as f32)),
+            TypeVariant::Float64 => Ok(dynamic_value::Reader::Float64(v)),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Float(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> core::result::Result<Self::Value, E> {
+        match self.ty.which() {
+            TypeVariant::Enum(schema) => {
+                let schema: crate::schema::EnumSchema = schema.into();
+                for enumerant in schema
+                    .get_enumerants()
+                    .map_err(|e| capnp_error(e, self.path))?
+                {
+                    let name = enumerant
+                        .get_proto()
+                        .get_name()
+                        .map_err(|e| capnp_error(e, self.path))?;
+                    if name == v {
+                        return Ok(dynamic_value::Enum::new(enumerant.get_ordinal(), schema).into());
+                    }
+                }
+                Err(E::custom(format_args!(
+                    "unknown enumerant `{v}` for `{}`",
+                    self.path
+                )))
+            }
+            TypeVariant::Float32 | TypeVariant::Float64 => match v.parse::<f64>() {
+                Ok(x) => self.visit_f64(x),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(v), &self)),
+            },
+            TypeVariant::Void | TypeVariant::Bool => {
+                Err(de::Error::invalid_type(de::Unexpected::Str(v), &self))
+            }
+            // Formats without 64-bit integers, like our JSON codec, write them as strings.
+            _ => match v.parse::<i128>() {
+                Ok(x) => self.integer(x),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(v), &self)),
+            },
+        }
+    }
+}
+
+struct TextVisitor<'p> {
+    path: &'p FieldPath,
+}
+
+impl<'de, 'p> Visitor<'de> for TextVisitor<'p> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        expecting(f, "a string", self.path)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> core::result::Result<String, E> {
+        Ok(v.into())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> core::result::Result<String, E> {
+        Ok(v)
+    }
+}
+
+struct DataVisitor<'p> {
+    path: &'p FieldPath,
+}
+
+impl<'de, 'p> Visitor<'de> for DataVisitor<'p> {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        expecting(f, "bytes", self.path)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> core::result::Result<Vec<u8>, E> {
+        Ok(v.into())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> core::result::Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> core::result::Result<Vec<u8>, E> {
+        Ok(v.as_bytes().into())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::deserialize_into;
+    use crate::schema_capnp::{node, value, ElementSize};
+    use crate::{dynamic_value, message};
+
+    fn deserialize_node(json: &str) -> crate::Result<message::Builder<message::HeapAllocator>> {
+        let mut message = message::Builder::new_default();
+        let root: dynamic_value::Builder<'_> = message.init_root::<node::Builder>().into();
+        let mut deserializer = ::serde_json::Deserializer::from_str(json);
+        deserialize_into(&mut deserializer, root.downcast())
+            .map_err(|e| crate::Error::failed(e.to_string()))?;
+        Ok(message)
+    }
+
+    #[test]
+    fn serialize_generated_reader() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_id(7);
+        node.set_display_name("foo.capnp:Foo");
+        node.reborrow().init_nested_nodes(1).get(0).set_name("Bar");
+        let mut st = node.init_struct();
+        st.set_preferred_list_encoding(ElementSize::EightBytes);
+        st.init_fields(1).get(0).set_name("a");
+
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        let json: ::serde_json::Value = ::serde_json::to_value(node).unwrap();
+        assert_eq!(json["id"], 7);
+        assert_eq!(json["displayName"], "foo.capnp:Foo");
+        assert_eq!(json["nestedNodes"][0]["name"], "Bar");
+        assert_eq!(json["nestedNodes"][0]["id"], 0);
+        assert_eq!(json["struct"]["preferredListEncoding"], "eightBytes");
+        assert_eq!(json["struct"]["fields"][0]["name"], "a");
+        assert_eq!(
+            json["struct"]["fields"][0]["slot"]["hadExplicitDefault"],
+            false
+        );
+        assert!(json.get("file").is_none());
+        assert!(json.get("parameters").is_none());
+    }
+
+    #[test]
+    fn serialize_union_members() {
+        let mut message = message::Builder::new_default();
+        message.init_root::<value::Builder>().set_void(());
+        let value = message.get_root_as_reader::<value::Reader>().unwrap();
+        assert_eq!(::serde_json::to_string(&value).unwrap(), r#"{"void":null}"#);
+
+        let mut message = message::Builder::new_default();
+        message.init_root::<value::Builder>().set_data(&[1, 2]);
+        let value = message.get_root_as_reader::<value::Reader>().unwrap();
+        assert_eq!(
+            ::serde_json::to_string(&value).unwrap(),
+            r#"{"data":[1,2]}"#
+        );
+
+        let mut message = message::Builder::new_default();
+        message.init_root::<value::Builder>().init_struct();
+        let value = message.get_root_as_reader::<value::Reader>().unwrap();
+        assert_eq!(
+            ::serde_json::to_string(&value).unwrap(),
+            r#"{"struct":null}"#
+        );
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_id(0xffff_ffff_ffff_ffff);
+        node.set_display_name("foo.capnp:Foo");
+        {
+            let mut nested = node.reborrow().init_nested_nodes(10);
+            for i in 0..10 {
+                nested.reborrow().get(i).set_id(u64::from(i));
+            }
+        }
+        let mut st = node.init_struct();
+        st.set_preferred_list_encoding(ElementSize::Pointer);
+        let mut field = st.init_fields(1).get(0);
+        field.set_name("a");
+        field.init_group().set_type_id(5);
+
+        let expected = message.get_root_as_reader::<node::Reader>().unwrap();
+        let json = ::serde_json::to_string(&expected).unwrap();
+        let parsed = deserialize_node(&json).unwrap();
+        let parsed = parsed.get_root_as_reader::<node::Reader>().unwrap();
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.get_nested_nodes().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn deserialize_lenient_forms() {
+        let message = deserialize_node(
+            r#"{"id": "12", "displayName": null, "parameters": [{"name": "T"}],
+                "struct": {"preferredListEncoding": 3, "fields": null}}"#,
+        )
+        .unwrap();
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        assert_eq!(node.get_id(), 12);
+        assert!(!node.has_display_name());
+        assert_eq!(
+            node.get_parameters().unwrap().get(0).get_name().unwrap(),
+            "T"
+        );
+        match node.which().unwrap() {
+            node::Struct(st) => {
+                assert_eq!(
+                    st.get_preferred_list_encoding().unwrap(),
+                    ElementSize::TwoBytes
+                );
+                assert!(!st.has_fields());
+            }
+            _ => panic!("expected a struct node"),
+        }
+
+        let mut message = message::Builder::new_default();
+        let root: dynamic_value::Builder<'_> = message.init_root::<value::Builder>().into();
+        let mut deserializer = ::serde_json::Deserializer::from_str(r#"{"data": "abc"}"#);
+        deserialize_into(&mut deserializer, root.downcast()).unwrap();
+        match message
+            .get_root_as_reader::<value::Reader>()
+            .unwrap()
+            .which()
+            .unwrap()
+        {
+            value::Data(d) => assert_eq!(d.unwrap(), b"abc"),
+            _ => panic!("expected data"),
+        }
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        for (json, message) in [
+            (r#"{"bogus": 1}"#, "unknown field `bogus`"),
+            (r#"{"id": -1}"#, "integer -1 is out of range for `id`"),
+            (
+                r#"{"nestedNodes": [{}, {"id": true}]}"#,
+                "invalid type: boolean `true`, expected an integer for `nestedNodes[1].id`",
+            ),
+            (
+                r#"{"struct": {"preferredListEncoding": "huge"}}"#,
+                "unknown enumerant `huge` for `struct.preferredListEncoding`",
+            ),
+            (
+                r#"{"displayName": 5}"#,
+                "invalid type: integer `5`, expected a string for `displayName`",
+            ),
+            (r#"[1]"#, "invalid type: sequence, expected a map"),
+        ] {
+            let error = deserialize_node(json).err().expect(json);
+            assert!(
+                error.to_string().contains(message),
+                "{json}: {error} does not contain {message}"
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// @generated by the capnpc-rust plugin to the Cap'n Proto schema compiler.
+// DO NOT EDIT.
+// source: stream.capnp
+
+
+pub mod stream_result {
+  #[derive(Copy, Clone)]
+  pub struct Owned(());
+  impl crate::introspect::Introspect for Owned { fn introspect() -> crate::introspect::Type { crate::introspect::TypeVariant::Struct(crate::introspect::RawBrandedStructSchema { generic: &_private::RAW_SCHEMA, field_types: _private::get_field_types, annotation_types: _private::get_annotation_types }).into() } }
+  impl crate::traits::Owned for Owned { type Reader<'a> = Reader<'a>; type Builder<'a> = Builder<'a>; }
+  impl crate::traits::OwnedStruct for Owned { type Reader<'a> = Reader<'a>; type Builder<'a> = Builder<'a>; }
+  impl crate::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+  pub struct Reader<'a> { reader: crate::private::layout::StructReader<'a> }
+  impl <'a,> ::core::marker::Copy for Reader<'a,>  {}
+  impl <'a,> ::core::clone::Clone for Reader<'a,>  {
+    fn clone(&self) -> Self { *self }
+  }
+
+  impl <'a,> crate::traits::HasTypeId for Reader<'a,>  {
+    const TYPE_ID: u64 = _private::TYPE_ID;
+  }
+  impl <'a,> ::core::convert::From<crate::private::layout::StructReader<'a>> for Reader<'a,>  {
+    fn from(reader: crate::private::layout::StructReader<'a>) -> Self {
+      Self { reader,  }
+    }
+  }
+
+  impl <'a,> ::core::convert::From<Reader<'a,>> for crate::dynamic_value::Reader<'a>  {
+    fn from(reader: Reader<'a,>) -> Self {
+      Self::Struct(crate::dynamic_struct::Reader::new(reader.reader, crate::schema::StructSchema::new(crate::introspect::RawBrandedStructSchema { generic: &_private::RAW_SCHEMA, field_types: _private::get_field_types::<>, annotation_types: _private::get_annotation_types::<>})))
+    }
+  }
+
+  impl <'a,> ::core::fmt::Debug for Reader<'a,>  {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::result::Result<(), ::core::fmt::Error> {
+      core::fmt::Debug::fmt(&::core::convert::Into::<crate::dynamic_value::Reader<'_>>::into(*self), f)
+    }
+  }
+
+  impl <'a,> ::core::cmp::PartialEq for Reader<'a,>  {
+    fn eq(&self, other: &Self) -> bool {
+      self.reader.equals(&other.reader).unwrap_or(false)
+    }
+  }
+  impl <'a,> ::core::cmp::Eq for Reader<'a,>  {}
+  impl <'a,> ::core::hash::Hash for Reader<'a,>  {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+      let _ = self.reader.hash_into(state);
+    }
+  }
+  crate::impl_serialize_for_reader!([] []);
+
+  impl <'a,> crate::traits::FromPointerReader<'a> for Reader<'a,>  {
+    fn get_from_pointer(reader: &crate::private::layout::PointerReader<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
+      ::core::result::Result::Ok(reader.get_struct(default)?.into())
+    }
+  }
+
+  impl <'a,> crate::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+    fn into_internal_struct_reader(self) -> crate::private::layout::StructReader<'a> {
+      self.reader
+    }
+  }
+
+  impl <'a,> crate::traits::Imbue<'a> for Reader<'a,>  {
+    fn imbue(&mut self, cap_table: &'a crate::private::layout::CapTable) {
+      self.reader.imbue(crate::private::layout::CapTableReader::Plain(cap_table))
+    }
+  }
+
+  impl <'a,> Reader<'a,>  {
+    pub fn reborrow(&self) -> Reader<'_,> {
+      Self { .. *self }
+    }
+
+    pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
+      self.reader.total_size()
+    }
+  }
+
+  pub struct Builder<'a> { builder: crate::private::layout::StructBuilder<'a> }
+  impl <'a,> crate::traits::HasStructSize for Builder<'a,>  {
+    const STRUCT_SIZE: crate::private::layout::StructSize = crate::private::layout::StructSize { data: 0, pointers: 0 };
+  }
+  impl <'a,> crate::traits::HasTypeId for Builder<'a,>  {
+    const TYPE_ID: u64 = _private::TYPE_ID;
+  }
+  impl <'a,> ::core::convert::From<crate::private::layout::StructBuilder<'a>> for Builder<'a,>  {
+    fn from(builder: crate::private::layout::StructBuilder<'a>) -> Self {
+      Self { builder,  }
+    }
+  }
+
+  impl <'a,> ::core::convert::From<Builder<'a,>> for crate::dynamic_value::Builder<'a>  {
+    fn from(builder: Builder<'a,>) -> Self {
+      Self::Struct(crate::dynamic_struct::Builder::new(builder.builder, crate::schema::StructSchema::new(crate::introspect::RawBrandedStructSchema { generic: &_private::RAW_SCHEMA, field_types: _private::get_field_types::<>, annotation_types: _private::get_annotation_types::<>})))
+    }
+  }
+
+  impl <'a,> crate::traits::ImbueMut<'a> for Builder<'a,>  {
+    fn imbue_mut(&mut self, cap_table: &'a mut crate::private::layout::CapTable) {
+      self.builder.imbue(crate::private::layout::CapTableBuilder::Plain(cap_table))
+    }
+  }
+
+  impl <'a,> crate::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+    fn init_pointer(builder: crate::private::layout::PointerBuilder<'a>, _size: u32) -> Self {
+      builder.init_struct(<Self as crate::traits::HasStructSize>::STRUCT_SIZE).into()
+    }
+    fn get_from_pointer(builder: crate::private::layout::PointerBuilder<'a>, default: ::core::option::Option<&'a [crate::Word]>) -> crate::Result<Self> {
+      ::core::result::Result::Ok(builder.get_struct(<Self as crate::traits::HasStructSize>::STRUCT_SIZE, default)?.into())
+    }
+  }
+
+  impl <'a,> crate::traits::SetPointerBuilder for Reader<'a,>  {
+    fn set_pointer_builder(mut pointer: crate::private::layout::PointerBuilder<'_>, value: Self, canonicalize: bool) -> crate::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+  }
+
+  impl <'a,> Builder<'a,>  {
+    pub fn into_reader(self) -> Reader<'a,> {
+      self.builder.into_reader().into()
+    }
+    pub fn reborrow(&mut self) -> Builder<'_,> {
+      Builder { builder: self.builder.reborrow() }
+    }
+    pub fn reborrow_as_reader(&self) -> Reader<'_,> {
+      self.builder.as_reader().into()
+    }
+
+    pub fn total_size(&self) -> crate::Result<crate::MessageSize> {
+      self.builder.as_reader().total_size()
+    }
+
+    pub fn merge_from(&mut self, other: Reader<'_,>) -> crate::Result<()> {
+      self.merge_from_with_options(other, crate::dynamic_struct::MergeOptions::default())
+    }
+    pub fn merge_from_with_options(&mut self, other: Reader<'_,>, options: crate::dynamic_struct::MergeOptions) -> crate::Result<()> {
+      let mut dst: crate::dynamic_struct::Builder<'_> = crate::dynamic_value::Builder::from(self.reborrow()).downcast();
+      dst.merge_from_with_options(crate::dynamic_value::Reader::from(other).downcast(), options)
+    }
+  }
+
+  pub struct Pipeline { _typeless: crate::any_pointer::Pipeline }
+  impl crate::capability::FromTypelessPipeline for Pipeline {
+    fn new(typeless: crate::any_pointer::Pipeline) -> Self {
+      Self { _typeless: typeless,  }
+    }
+  }
+  impl Pipeline  {
+  }
+  mod _private {
+    pub static ENCODED_NODE: [crate::Word; 17] = [
+      crate::word(0, 0, 0, 0, 5, 0, 6, 0),
+      crate::word(110, 177, 192, 119, 51, 154, 95, 153),
+      crate::word(13, 0, 0, 0, 1, 0, 0, 0),
+      crate::word(248, 243, 147, 19, 169, 102, 195, 134),
+      crate::word(0, 0, 7, 0, 0, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(21, 0, 0, 0, 210, 0, 0, 0),
+      crate::word(33, 0, 0, 0, 7, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(115, 116, 114, 101, 97, 109, 46, 99),
+      crate::word(97, 112, 110, 112, 58, 83, 116, 114),
+      crate::word(101, 97, 109, 82, 101, 115, 117, 108),
+      crate::word(116, 0, 0, 0, 0, 0, 0, 0),
+      crate::word(0, 0, 0, 0, 1, 0, 1, 0),
+    ];
+    pub fn get_field_types(index: u16) -> crate::introspect::Type {
+      panic!("invalid field index {}", index)
+    }
+    pub fn get_annotation_types(child_index: Option<u16>, index: u32) -> crate::introspect::Type {
+      panic!("invalid annotation indices ({:?}, {}) ", child_index, index)
+    }
+    pub static RAW_SCHEMA: crate::introspect::RawStructSchema = crate::introspect::RawStructSchema {
+      encoded_node: &ENCODED_NODE,
+      nonunion_members: NONUNION_MEMBERS,
+      members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
+    };
+    pub static NONUNION_MEMBERS : &[u16] = &[];
+    pub static MEMBERS_BY_DISCRIMINANT : &[u16] = &[];
+    pub const TYPE_ID: u64 = 0x995f_9a33_77c0_b16e;
+  }
+}
+
+/// The types defined in this file. They are not registered automatically; pass them to
+/// `capnp::schema::Registry::register_all()` so that they can be looked up by id.
+pub fn schemas() -> &'static [fn() -> crate::introspect::Type] {
+  &[
+    <crate::stream_capnp::stream_result::Owned as crate::introspect::Introspect>::introspect,
+  ]
+}
@@ -1,48 +1,152 @@
+//! Printing of dynamic values in the Cap'n Proto text format.
+//!
+//! The `Debug` implementation of [`dynamic_value::Reader`] prints with the default
+//! [`StringifyOptions`], or with an indent width of 2 for `{:#?}`. Other settings are
+//! available through [`StringifyOptions::display()`].
+
 use crate::dynamic_value;
 use core::fmt::{self, Formatter};
 
+/// How `Data` values are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataFormat {
+    /// As a hex literal, like `0x"01ff"`.
+    #[default]
+    Hex,
+
+    /// As a list of byte values, like `[1, 255]`.
+    List,
+}
+
+/// Options controlling how values are printed.
+///
+/// ```
+/// use capnp::message;
+/// use capnp::schema_capnp::node;
+/// use capnp::stringify::StringifyOptions;
+///
+/// let mut message = message::Builder::new_default();
+/// let mut node = message.init_root::<node::Builder>();
+/// node.set_id(7);
+/// node.reborrow().init_nested_nodes(10);
+///
+/// let mut options = StringifyOptions::new();
+/// options.omit_defaults(true).max_list_elements(Some(2));
+/// assert_eq!(
+///     options.display(node.into_reader()).to_string(),
+///     "(id = 7, nestedNodes = [(), (), ... (8 more)])"
+/// );
+/// ```
 #[derive(Clone, Copy, Debug, Default)]
-pub(crate) struct Indent {
-    /// None means everything gets printed on a single line.
-    indent: Option<usize>,
+pub struct StringifyOptions {
+    /// How many spaces to indent each level of nesting by. `None` prints everything on a single
+    /// line.
+    pub indent_width: Option<usize>,
+
+    /// How many levels of structs and lists to print. The value being printed is at level zero,
+    /// and anything nested deeper than the limit is printed as `(...)` or `[...]`.
+    pub max_depth: Option<usize>,
+
+    /// How many elements of a list, or bytes of a `Data` value, to print. The rest are replaced
+    /// by a count, like `... (8 more)`.
+    pub max_list_elements: Option<usize>,
+
+    /// Whether to leave out fields that hold their default value. Null pointers are always left
+    /// out.
+    pub omit_defaults: bool,
+
+    /// How to print `Data` values.
+    pub data_format: DataFormat,
+
+    /// Whether to print enums as their numeric values rather than as enumerant names.
+    pub numeric_enums: bool,
 }
 
-impl Indent {
-    fn no_indent() -> Self {
-        Self { indent: None }
+impl StringifyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn indent_width(&mut self, value: Option<usize>) -> &mut Self {
+        self.indent_width = value;
+        self
+    }
+
+    pub fn max_depth(&mut self, value: Option<usize>) -> &mut Self {
+        self.max_depth = value;
+        self
+    }
+
+    pub fn max_list_elements(&mut self, value: Option<usize>) -> &mut Self {
+        self.max_list_elements = value;
+        self
+    }
+
+    pub fn omit_defaults(&mut self, value: bool) -> &mut Self {
+        self.omit_defaults = value;
+        self
+    }
+
+    pub fn data_format(&mut self, value: DataFormat) -> &mut Self {
+        self.data_format = value;
+        self
     }
 
-    fn enabled() -> Self {
-        Self { indent: Some(0) }
+    pub fn numeric_enums(&mut self, value: bool) -> &mut Self {
+        self.numeric_enums = value;
+        self
     }
 
-    fn next(self) -> Self {
-        match self.indent {
-            None => self,
-            Some(x) => Self {
-                indent: Some(x + 1),
-            },
+    /// Returns an object that prints `value` with these options when formatted with `{}`.
+    pub fn display<'a>(&self, value: impl Into<dynamic_value::Reader<'a>>) -> Stringified<'a> {
+        Stringified {
+            value: value.into(),
+            options: *self,
         }
     }
+}
 
-    fn maybe_newline(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
-        match self.indent {
-            None => Ok(()),
-            Some(indent) => {
-                formatter.write_str("\n")?;
-                for _ in 0..indent {
-                    formatter.write_str("  ")?;
-                }
-                Ok(())
+/// A value together with the options to print it with. Created by
+/// [`StringifyOptions::display()`].
+#[derive(Clone, Copy)]
+pub struct Stringified<'a> {
+    value: dynamic_value::Reader<'a>,
+    options: StringifyOptions,
+}
+
+impl<'a> fmt::Display for Stringified<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        print(self.value, f, &self.options, 0)
+    }
+}
+
+impl<'a> fmt::Debug for Stringified<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        print(self.value, f, &self.options, 0)
+    }
+}
+
+fn maybe_newline(
+    formatter: &mut Formatter,
+    options: &StringifyOptions,
+    depth: usize,
+) -> Result<(), fmt::Error> {
+    match options.indent_width {
+        None => Ok(()),
+        Some(width) => {
+            formatter.write_str("\n")?;
+            for _ in 0..depth * width {
+                formatter.write_str(" ")?;
             }
+            Ok(())
         }
     }
+}
 
-    fn comma(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
-        match self.indent {
-            None => formatter.write_str(", "),
-            Some(_) => formatter.write_str(","),
-        }
+fn comma(formatter: &mut Formatter, options: &StringifyOptions) -> Result<(), fmt::Error> {
+    match options.indent_width {
+        None => formatter.write_str(", "),
+        Some(_) => formatter.write_str(","),
     }
 }
 
@@ -53,10 +157,42 @@ fn cvt<T>(r: crate::Result<T>) -> Result<T, fmt::Error> {
     }
 }
 
+/// Returns `true` if a struct or list at `depth` should be elided.
+fn too_deep(options: &StringifyOptions, depth: usize) -> bool {
+    options.max_depth.map_or(false, |max| depth >= max)
+}
+
+/// Prints the elements of a list, each of which is at `depth + 1`, truncating as configured.
+fn print_elements<T>(
+    formatter: &mut Formatter,
+    options: &StringifyOptions,
+    depth: usize,
+    len: usize,
+    elements: impl Iterator<Item = T>,
+    mut print_element: impl FnMut(&mut Formatter, T) -> Result<(), fmt::Error>,
+) -> Result<(), fmt::Error> {
+    let shown = options.max_list_elements.map_or(len, |max| max.min(len));
+    formatter.write_str("[")?;
+    for (idx, element) in elements.take(shown).enumerate() {
+        maybe_newline(formatter, options, depth + 1)?;
+        print_element(formatter, element)?;
+        if idx + 1 < len {
+            comma(formatter, options)?;
+        }
+    }
+    if shown < len {
+        maybe_newline(formatter, options, depth + 1)?;
+        formatter.write_fmt(format_args!("... ({} more)", len - shown))?;
+    }
+    maybe_newline(formatter, options, depth)?;
+    formatter.write_str("]")
+}
+
 pub(crate) fn print(
     value: dynamic_value::Reader,
     formatter: &mut Formatter,
-    indent: Indent,
+    options: &StringifyOptions,
+    depth: usize,
 ) -> Result<(), fmt::Error> {
     match value {
         dynamic_value::Reader::Void => formatter.write_str("()"),
@@ -71,33 +207,51 @@ pub(crate) fn print(
         dynamic_value::Reader::UInt64(x) => formatter.write_fmt(format_args!("{x}")),
         dynamic_value::Reader::Float32(x) => formatter.write_fmt(format_args!("{x}")),
         dynamic_value::Reader::Float64(x) => formatter.write_fmt(format_args!("{x}")),
+        dynamic_value::Reader::Enum(e) if options.numeric_enums => {
+            formatter.write_fmt(format_args!("{}", e.get_value()))
+        }
         dynamic_value::Reader::Enum(e) => match cvt(e.get_enumerant())? {
             Some(enumerant) => formatter.write_str(cvt(enumerant.get_proto().get_name())?),
             None => formatter.write_fmt(format_args!("{}", e.get_value())),
         },
         dynamic_value::Reader::Text(t) => formatter.write_fmt(format_args!("{t:?}")),
-        dynamic_value::Reader::Data(d) => {
-            formatter.write_str("0x\"")?;
-            for b in d {
-                formatter.write_fmt(format_args!("{:02x}", *b))?;
+        dynamic_value::Reader::Data(d) => match options.data_format {
+            DataFormat::Hex => {
+                let shown = options
+                    .max_list_elements
+                    .map_or(d.len(), |max| max.min(d.len()));
+                formatter.write_str("0x\"")?;
+                for b in &d[..shown] {
+                    formatter.write_fmt(format_args!("{:02x}", *b))?;
+                }
+                formatter.write_str("\"")?;
+                if shown < d.len() {
+                    formatter.write_fmt(format_args!("... ({} more)", d.len() - shown))?;
+                }
+                Ok(())
             }
-            formatter.write_str("\"")
-        }
+            DataFormat::List if d.is_empty() => formatter.write_str("[]"),
+            DataFormat::List if too_deep(options, depth) => formatter.write_str("[...]"),
+            DataFormat::List => {
+                print_elements(formatter, options, depth, d.len(), d.iter(), |f, b| {
+                    f.write_fmt(format_args!("{b}"))
+                })
+            }
+        },
         dynamic_value::Reader::List(list) => {
             if list.is_empty() {
                 formatter.write_str("[]")
+            } else if too_deep(options, depth) {
+                formatter.write_str("[...]")
             } else {
-                formatter.write_str("[")?;
-                let indent2 = indent.next();
-                for (idx, value) in list.iter().enumerate() {
-                    indent2.maybe_newline(formatter)?;
-                    print(cvt(value)?, formatter, indent2)?;
-                    if idx + 1 < list.len() as usize {
-                        indent2.comma(formatter)?;
-                    }
-                }
-                indent.maybe_newline(formatter)?;
-                formatter.write_str("]")
+                print_elements(
+                    formatter,
+                    options,
+                    depth,
+                    list.len() as usize,
+                    list.iter(),
+                    |f, value| print(cvt(value)?, f, options, depth + 1),
+                )
             }
         }
         dynamic_value::Reader::Struct(st) => {
@@ -107,13 +261,20 @@ pub(crate) fn print(
             if union_fields.len() + non_union_fields.len() == 0 {
                 return formatter.write_str("()");
             }
+            if too_deep(options, depth) {
+                return formatter.write_str("(...)");
+            }
             formatter.write_str("(")?;
-            let indent2 = indent.next();
             let mut union_field = match cvt(st.which())? {
                 None => None,
                 Some(field) => {
-                    // If it's not the default descriminant, then we always need to print it.
-                    if field.get_proto().get_discriminant_value() != 0 || cvt(st.has(field))? {
+                    let print_it = if options.omit_defaults {
+                        cvt(st.is_union_set(field))?
+                    } else {
+                        // If it's not the default descriminant, then we always need to print it.
+                        field.get_proto().get_discriminant_value() != 0 || cvt(st.has(field))?
+                    };
+                    if print_it {
                         Some(field)
                     } else {
                         None
@@ -128,38 +289,43 @@ pub(crate) fn print(
                         if first {
                             first = false
                         } else {
-                            indent2.comma(formatter)?;
+                            comma(formatter, options)?;
                         }
-                        indent2.maybe_newline(formatter)?;
+                        maybe_newline(formatter, options, depth + 1)?;
                         formatter.write_str(cvt(ff.get_proto().get_name())?)?;
                         formatter.write_str(" = ")?;
-                        print(cvt(st.get(ff))?, formatter, indent2)?;
+                        print(cvt(st.get(ff))?, formatter, options, depth + 1)?;
                         union_field = None;
                     }
                 }
-                if cvt(st.has(field))? {
+                let print_it = if options.omit_defaults {
+                    cvt(st.is_set(field))?
+                } else {
+                    cvt(st.has(field))?
+                };
+                if print_it {
                     if first {
                         first = false
                     } else {
-                        indent2.comma(formatter)?;
+                        comma(formatter, options)?;
                     }
-                    indent2.maybe_newline(formatter)?;
+                    maybe_newline(formatter, options, depth + 1)?;
                     formatter.write_str(cvt(field.get_proto().get_name())?)?;
                     formatter.write_str(" = ")?;
-                    print(cvt(st.get(field))?, formatter, indent2)?;
+                    print(cvt(st.get(field))?, formatter, options, depth + 1)?;
                 }
             }
             if let Some(ff) = union_field {
                 // Union field comes last.
                 if !first {
-                    indent2.comma(formatter)?;
+                    comma(formatter, options)?;
                 }
-                indent2.maybe_newline(formatter)?;
+                maybe_newline(formatter, options, depth + 1)?;
                 formatter.write_str(cvt(ff.get_proto().get_name())?)?;
                 formatter.write_str(" = ")?;
-                print(cvt(st.get(ff))?, formatter, indent2)?;
+                print(cvt(st.get(ff))?, formatter, options, depth + 1)?;
             }
-            indent.maybe_newline(formatter)?;
+            maybe_newline(formatter, options, depth)?;
             formatter.write_str(")")
         }
         dynamic_value::Reader::AnyPointer(_) => formatter.write_str("<opaque pointer>"),
@@ -169,11 +335,90 @@ pub(crate) fn print(
 
 impl<'a> fmt::Debug for dynamic_value::Reader<'a> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        let indent = if f.alternate() {
-            Indent::enabled()
-        } else {
-            Indent::no_indent()
-        };
-        print(*self, f, indent)
+        let mut options = StringifyOptions::new();
+        if f.alternate() {
+            options.indent_width(Some(2));
+        }
+        print(*self, f, &options, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataFormat, StringifyOptions};
+    use crate::message;
+    use crate::schema_capnp::{node, value, ElementSize};
+
+    fn init_node(node: node::Builder<'_>) {
+        let mut node = node;
+        node.set_id(7);
+        node.set_display_name("foo.capnp:Foo");
+        let mut st = node.init_struct();
+        st.set_preferred_list_encoding(ElementSize::EightBytes);
+        let mut fields = st.init_fields(2);
+        fields.reborrow().get(0).set_name("a");
+        fields.reborrow().get(1).set_name("b");
+    }
+
+    #[test]
+    fn default_options_match_debug() {
+        let mut message = message::Builder::new_default();
+        init_node(message.init_root());
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        let value = crate::dynamic_value::Reader::from(node);
+        let mut options = StringifyOptions::new();
+        assert_eq!(options.display(node).to_string(), format!("{value:?}"));
+        options.indent_width(Some(2));
+        assert_eq!(options.display(node).to_string(), format!("{value:#?}"));
+    }
+
+    #[test]
+    fn omit_defaults_and_limits() {
+        let mut message = message::Builder::new_default();
+        init_node(message.init_root());
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+
+        let mut options = StringifyOptions::new();
+        options.omit_defaults(true).numeric_enums(true);
+        assert_eq!(
+            options.display(node).to_string(),
+            "(id = 7, displayName = \"foo.capnp:Foo\", \
+             struct = (preferredListEncoding = 5, fields = [(name = \"a\"), (name = \"b\")]))"
+        );
+
+        options.max_depth(Some(2)).max_list_elements(Some(1));
+        assert_eq!(
+            options.display(node).to_string(),
+            "(id = 7, displayName = \"foo.capnp:Foo\", \
+             struct = (preferredListEncoding = 5, fields = [...]))"
+        );
+
+        options.max_depth(None).indent_width(Some(4));
+        assert_eq!(
+            options.display(node).to_string(),
+            "(\n    id = 7,\n    displayName = \"foo.capnp:Foo\",\n    struct = (\n        \
+             preferredListEncoding = 5,\n        fields = [\n            (\n                \
+             name = \"a\"\n            ),\n            ... (1 more)\n        ]\n    )\n)"
+        );
+    }
+
+    #[test]
+    fn data() {
+        let mut message = message::Builder::new_default();
+        message.init_root::<value::Builder>().set_data(&[1, 2, 255]);
+        let value = message.get_root_as_reader::<value::Reader>().unwrap();
+
+        let mut options = StringifyOptions::new();
+        assert_eq!(options.display(value).to_string(), "(data = 0x\"0102ff\")");
+        options.max_list_elements(Some(2));
+        assert_eq!(
+            options.display(value).to_string(),
+            "(data = 0x\"0102\"... (1 more))"
+        );
+        options.data_format(DataFormat::List);
+        assert_eq!(
+            options.display(value).to_string(),
+            "(data = [1, 2, ... (1 more)])"
+        );
     }
 }
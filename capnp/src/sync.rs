@@ -0,0 +1,80 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Thread-safe versions of the capability types, enabled by the `sync_rpc` feature.
+//!
+//! This module mirrors the crate root. The modules that involve capabilities (`capability`,
+//! `capability_list`, `any_pointer::Pipeline`, and the hooks in `private::capability`) are
+//! compiled a second time here, with every hook, server and `Promise` required to be `Send`,
+//! and client and pipeline hooks to also be `Sync`. Everything else is re-exported unchanged.
+//!
+//! Code generated with `capnpc::CompilerCommand::sync_rpc()` refers to the `capnp` crate
+//! through this module, so its clients and servers are built on these types, and can be
+//! served and called by the `sync` version of `capnp_rpc` from any thread. Messages can hold
+//! capabilities of either kind. A thread-safe capability read as a single-threaded one, e.g.
+//! through the dynamic API, forwards its calls to the original.
+
+pub use crate::*;
+
+#[path = "capability.rs"]
+pub mod capability;
+
+#[path = "capability_list.rs"]
+pub mod capability_list;
+
+pub mod any_pointer;
+pub(crate) mod local;
+pub mod private;
+
+pub mod traits {
+    pub use crate::traits::*;
+
+    /// Relates a type of results to the pipeline of a call that returns them.
+    pub trait Pipelined {
+        type Pipeline;
+    }
+}
+
+mod flavor {
+    use alloc::boxed::Box;
+    use core::future::Future;
+    use core::pin::Pin;
+
+    /// `Send`. The single-threaded version, [`crate::capability::MaybeSend`], is implemented by
+    /// every type.
+    ///
+    /// Hooks and servers are bounded by this trait, so that the code defining them, including
+    /// generated code, works with both versions of the capability types.
+    pub trait MaybeSend: Send {}
+
+    impl<T: Send + ?Sized> MaybeSend for T {}
+
+    /// `Sync`. The single-threaded version, [`crate::capability::MaybeSync`], is implemented by
+    /// every type.
+    pub trait MaybeSync: Sync {}
+
+    impl<T: Sync + ?Sized> MaybeSync for T {}
+
+    pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+    /// Holds servers generated with `async_servers` enabled.
+    pub use alloc::sync::Arc as Rc;
+}
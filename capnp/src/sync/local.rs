@@ -0,0 +1,165 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Single-threaded hooks that forward to thread-safe ones, for reading the capabilities of a
+//! thread-safe message as single-threaded ones.
+
+use alloc::boxed::Box;
+
+use crate::capability::{Promise, RemotePromise, Request};
+use crate::private::capability::{
+    ClientHook, ParamsHook, PipelineHook, PipelineOp, RequestHook, ResponseHook, ResultsHook,
+    TailSend,
+};
+use crate::{any_pointer, Error, MessageSize};
+
+use super::private::capability as sync;
+
+/// Wraps `hook` in a single-threaded client hook that forwards to it.
+pub(crate) fn new_client(hook: Box<dyn sync::ClientHook>) -> Box<dyn ClientHook> {
+    Box::new(Client { hook })
+}
+
+struct Client {
+    hook: Box<dyn sync::ClientHook>,
+}
+
+impl ClientHook for Client {
+    fn add_ref(&self) -> Box<dyn ClientHook> {
+        new_client(self.hook.add_ref())
+    }
+
+    fn new_call(
+        &self,
+        interface_id: u64,
+        method_id: u16,
+        size_hint: Option<MessageSize>,
+    ) -> Request<any_pointer::Owned, any_pointer::Owned> {
+        let request = self.hook.new_call(interface_id, method_id, size_hint);
+        Request::new(Box::new(RequestForwarder { hook: request.hook }))
+    }
+
+    fn call(
+        &self,
+        interface_id: u64,
+        method_id: u16,
+        params: Box<dyn ParamsHook>,
+        mut results: Box<dyn ResultsHook>,
+    ) -> Promise<(), Error> {
+        let mut request = self.hook.new_call(interface_id, method_id, None);
+        if let Err(e) = params.get().and_then(|p| request.hook.get().set_as(p)) {
+            return Promise::err(e);
+        }
+        drop(params);
+        let promise = request.hook.send().promise;
+        Promise::from_future(async move {
+            let response = promise.await?;
+            results.get()?.set_as(response.hook.get()?)
+        })
+    }
+
+    fn get_brand(&self) -> usize {
+        self.hook.get_brand()
+    }
+
+    fn get_ptr(&self) -> usize {
+        self.hook.get_ptr()
+    }
+
+    fn get_resolved(&self) -> Option<Box<dyn ClientHook>> {
+        self.hook.get_resolved().map(new_client)
+    }
+
+    fn when_more_resolved(&self) -> Option<Promise<Box<dyn ClientHook>, Error>> {
+        let promise = self.hook.when_more_resolved()?;
+        Some(Promise::from_future(async move {
+            Ok(new_client(promise.await?))
+        }))
+    }
+
+    fn when_resolved(&self) -> Promise<(), Error> {
+        Promise::from_future(self.hook.when_resolved())
+    }
+}
+
+struct RequestForwarder {
+    hook: Box<dyn sync::RequestHook>,
+}
+
+impl RequestHook for RequestForwarder {
+    fn get(&mut self) -> any_pointer::Builder<'_> {
+        self.hook.get()
+    }
+
+    fn get_brand(&self) -> usize {
+        self.hook.get_brand()
+    }
+
+    fn send(self: Box<Self>) -> RemotePromise<any_pointer::Owned> {
+        let remote = self.hook.send();
+        let promise = remote.promise;
+        RemotePromise {
+            promise: Promise::from_future(async move {
+                let hook: Box<dyn ResponseHook> = Box::new(Response {
+                    hook: promise.await?.hook,
+                });
+                Ok(crate::capability::Response::new(hook))
+            }),
+            pipeline: any_pointer::Pipeline::new(Box::new(Pipeline {
+                hook: remote.pipeline.hook,
+            })),
+        }
+    }
+
+    fn tail_send(self: Box<Self>) -> Result<TailSend, Box<dyn RequestHook>> {
+        Err(self)
+    }
+
+    fn send_streaming(self: Box<Self>) -> Promise<(), Error> {
+        Promise::from_future(self.hook.send_streaming())
+    }
+}
+
+struct Response {
+    hook: Box<dyn sync::ResponseHook>,
+}
+
+impl ResponseHook for Response {
+    fn get(&self) -> crate::Result<any_pointer::Reader<'_>> {
+        self.hook.get()
+    }
+}
+
+struct Pipeline {
+    hook: Box<dyn sync::PipelineHook>,
+}
+
+impl PipelineHook for Pipeline {
+    fn add_ref(&self) -> Box<dyn PipelineHook> {
+        Box::new(Self {
+            hook: self.hook.add_ref(),
+        })
+    }
+
+    fn get_pipelined_cap(&self, ops: &[PipelineOp]) -> Box<dyn ClientHook> {
+        new_client(self.hook.get_pipelined_cap(ops))
+    }
+}
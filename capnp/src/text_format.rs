@@ -0,0 +1,813 @@
+//! Parsing of the Cap'n Proto text format.
+//!
+//! This is the syntax that the `Debug` implementation of [`dynamic_value::Reader`] prints, and
+//! the one that the `capnp encode` tool reads:
+//!
+//! ```text
+//! (id = 0x1234, displayName = "foo.capnp:Foo", nestedNodes = [(name = "Bar", id = 12)],
+//!  enum = (enumerants = [(name = "red"), (name = "green")]))
+//! ```
+//!
+//! Structs and groups are written as parenthesized lists of `name = value` assignments,
+//! lists in square brackets, enums by the names of their enumerants, and `Data` as hex
+//! literals like `0x"01ab"`. Void is written as `()`. Strings support C and Rust style escapes.
+//! Comments start with `#` and run to the end of the line.
+//!
+//! Parsing the output of the printer gives back an equal message.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::introspect::{Type, TypeVariant};
+use crate::schema::{EnumSchema, Field};
+use crate::schema_capnp::field;
+use crate::{dynamic_list, dynamic_struct, dynamic_value, Error, Result};
+
+/// How deeply structs and lists may be nested. Matches the default nesting limit of
+/// [`crate::message::ReaderOptions`].
+const MAX_NESTING_DEPTH: u32 = 64;
+
+/// Parses `input` and writes the fields that it assigns into `builder`. Fields that `input`
+/// does not mention are left untouched.
+///
+/// The fields may be enclosed in parentheses, as the printer writes them, or be given as a
+/// bare comma-separated list.
+///
+/// ```
+/// use capnp::{dynamic_struct, dynamic_value, message, text_format};
+/// use capnp::schema_capnp::node;
+///
+/// let mut message = message::Builder::new_default();
+/// let root: dynamic_value::Builder<'_> = message.init_root::<node::Builder>().into();
+/// text_format::parse_into(
+///     root.downcast::<dynamic_struct::Builder<'_>>(),
+///     r#"id = 7, displayName = "foo.capnp", nestedNodes = [(name = "Bar", id = 8)]"#,
+/// )
+/// .unwrap();
+///
+/// let node = message.get_root_as_reader::<node::Reader>().unwrap();
+/// assert_eq!(node.get_id(), 7);
+/// assert_eq!(node.get_nested_nodes().unwrap().get(0).get_name().unwrap(), "Bar");
+/// ```
+pub fn parse_into(builder: dynamic_struct::Builder<'_>, input: &str) -> Result<()> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_whitespace();
+    let fields = if parser.peek() == Some(b'(') {
+        let node = parser.parse_value(0)?;
+        parser.skip_whitespace();
+        if parser.pos < input.len() {
+            return Err(parser.error("unexpected input after struct"));
+        }
+        let Value::Struct(fields) = node.value else {
+            unreachable!()
+        };
+        fields
+    } else {
+        parser.parse_fields(None, 0)?
+    };
+    parser.apply_struct(&fields, builder)
+}
+
+/// A parsed value, along with the byte offset at which it starts.
+struct Node {
+    pos: usize,
+    value: Value,
+}
+
+enum Value {
+    /// A bare word, like `true`, `inf` or the name of an enumerant.
+    Identifier(String),
+
+    /// A number, as it appears in the input, so that it can be converted to the field's type
+    /// without losing precision.
+    Number(String),
+    String(Vec<u8>),
+    Data(Vec<u8>),
+    List(Vec<Node>),
+    Struct(Vec<Assignment>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Identifier(_) => "an identifier",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Data(_) => "a data literal",
+            Value::List(_) => "a list",
+            Value::Struct(_) => "a struct",
+        }
+    }
+}
+
+/// A `name = value` pair in a struct.
+struct Assignment {
+    pos: usize,
+    name: String,
+    value: Node,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: &str) -> Error {
+        let consumed = &self.input[..pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        Error::failed(format!(
+            "parse error at line {line}, column {column}: {message}"
+        ))
+    }
+
+    fn type_error(&self, node: &Node, expected: &str) -> Error {
+        self.error_at(
+            node.pos,
+            &format!("expected {expected}, found {}", node.value.kind()),
+        )
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.as_bytes().get(self.pos + offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c as char)))
+        }
+    }
+
+    fn check_depth(&self, depth: u32) -> Result<()> {
+        if depth >= MAX_NESTING_DEPTH {
+            Err(self.error("nesting limit exceeded"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn parse_value(&mut self, depth: u32) -> Result<Node> {
+        self.skip_whitespace();
+        let pos = self.pos;
+        let value = match self.peek() {
+            Some(b'(') => {
+                self.check_depth(depth)?;
+                self.pos += 1;
+                Value::Struct(self.parse_fields(Some(b')'), depth + 1)?)
+            }
+            Some(b'[') => {
+                self.check_depth(depth)?;
+                self.pos += 1;
+                Value::List(self.parse_elements(depth + 1)?)
+            }
+            Some(b'"') => Value::String(self.parse_string()?),
+            Some(b'0') if self.peek_at(1) == Some(b'x') && self.peek_at(2) == Some(b'"') => {
+                Value::Data(self.parse_data()?)
+            }
+            Some(b'-' | b'0'..=b'9') => Value::Number(self.parse_number()?),
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') => Value::Identifier(self.parse_identifier()),
+            Some(_) => {
+                let c = self.input[self.pos..].chars().next().unwrap();
+                return Err(self.error(&format!("unexpected character '{c}'")));
+            }
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Node { pos, value })
+    }
+
+    /// Parses `name = value` pairs up to and including `close`, or up to the end of the input
+    /// if `close` is `None`.
+    fn parse_fields(&mut self, close: Option<u8>, depth: u32) -> Result<Vec<Assignment>> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == close {
+                self.pos += 1;
+                return Ok(fields);
+            }
+            let pos = self.pos;
+            if !matches!(self.peek(), Some(b'a'..=b'z' | b'A'..=b'Z' | b'_')) {
+                return Err(self.error("expected a field name"));
+            }
+            let name = self.parse_identifier();
+            self.skip_whitespace();
+            self.expect(b'=')?;
+            let value = self.parse_value(depth)?;
+            fields.push(Assignment { pos, name, value });
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                c if c == close => {}
+                _ => {
+                    return Err(self.error(match close {
+                        Some(_) => "expected ',' or ')'",
+                        None => "expected ',' or end of input",
+                    }))
+                }
+            }
+        }
+    }
+
+    /// Parses list elements up to and including the closing bracket.
+    fn parse_elements(&mut self, depth: u32) -> Result<Vec<Node>> {
+        let mut elements = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                self.pos += 1;
+                return Ok(elements);
+            }
+            elements.push(self.parse_value(depth)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {}
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self) -> String {
+        let start = self.pos;
+        while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') = self.peek() {
+            self.pos += 1;
+        }
+        self.input[start..self.pos].into()
+    }
+
+    fn parse_number(&mut self) -> Result<String> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+            if let Some(b'a'..=b'z' | b'A'..=b'Z') = self.peek() {
+                // Only `-inf` and friends.
+                let word = self.parse_identifier();
+                if !word.eq_ignore_ascii_case("inf") && !word.eq_ignore_ascii_case("infinity") {
+                    return Err(self.error_at(start, "expected a number"));
+                }
+                return Ok(self.input[start..self.pos].into());
+            }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("expected a digit"));
+            }
+        }
+        let is_hex = matches!(self.peek_at(1), Some(b'x' | b'X'));
+        while let Some(c @ (b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'.')) = self.peek() {
+            self.pos += 1;
+            if !is_hex && matches!(c, b'e' | b'E') && matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+        }
+        Ok(self.input[start..self.pos].into())
+    }
+
+    fn parse_string(&mut self) -> Result<Vec<u8>> {
+        self.expect(b'"')?;
+        let mut result = Vec::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                b'"' => return Ok(result),
+                b'\\' => self.parse_escape(&mut result)?,
+                c => result.push(c),
+            }
+        }
+    }
+
+    /// Parses the part of an escape sequence after the backslash.
+    fn parse_escape(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        let start = self.pos - 1;
+        let Some(c) = self.peek() else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += 1;
+        let byte = match c {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'\\' | b'\'' | b'"' | b'?' => c,
+            b'0'..=b'7' => {
+                let mut value = (c - b'0') as u32;
+                for _ in 0..2 {
+                    match self.peek() {
+                        Some(d @ b'0'..=b'7') => {
+                            value = value * 8 + (d - b'0') as u32;
+                            self.pos += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                if value > 0xff {
+                    return Err(self.error_at(start, "octal escape out of range"));
+                }
+                value as u8
+            }
+            b'x' => {
+                let digits = self.input.get(self.pos..self.pos + 2).unwrap_or("");
+                let Ok(value) = u8::from_str_radix(digits, 16) else {
+                    return Err(self.error_at(start, "expected two hex digits after \\x"));
+                };
+                self.pos += 2;
+                value
+            }
+            b'u' => {
+                self.expect(b'{')?;
+                let digits_start = self.pos;
+                while let Some(b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') = self.peek() {
+                    self.pos += 1;
+                }
+                let digits = &self.input[digits_start..self.pos];
+                self.expect(b'}')?;
+                let Some(c) = u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                else {
+                    return Err(self.error_at(start, "invalid unicode escape"));
+                };
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                return Ok(());
+            }
+            _ => return Err(self.error_at(start, "unknown escape sequence")),
+        };
+        out.push(byte);
+        Ok(())
+    }
+
+    fn parse_data(&mut self) -> Result<Vec<u8>> {
+        self.pos += 2;
+        self.expect(b'"')?;
+        let mut result = Vec::new();
+        let mut high: Option<u8> = None;
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated data literal"));
+            };
+            let digit = match c {
+                b'"' if high.is_none() => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                b'"' => return Err(self.error("odd number of hex digits in data literal")),
+                b' ' | b'\t' | b'\n' | b'\r' => {
+                    self.pos += 1;
+                    continue;
+                }
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                b'A'..=b'F' => c - b'A' + 10,
+                _ => return Err(self.error("expected a hex digit")),
+            };
+            self.pos += 1;
+            match high.take() {
+                None => high = Some(digit),
+                Some(h) => result.push(h << 4 | digit),
+            }
+        }
+    }
+
+    fn apply_struct(
+        &self,
+        fields: &[Assignment],
+        mut dst: dynamic_struct::Builder<'_>,
+    ) -> Result<()> {
+        let schema = dst.get_schema();
+        let mut assigned: Vec<u16> = Vec::new();
+        let mut union_member: Option<&str> = None;
+        for assignment in fields {
+            let name = assignment.name.as_str();
+            let Some(field) = schema.find_field_by_name(name)? else {
+                return Err(self.error_at(assignment.pos, &format!("unknown field \"{name}\"")));
+            };
+            if assigned.contains(&field.get_index()) {
+                return Err(self.error_at(
+                    assignment.pos,
+                    &format!("field \"{name}\" is assigned more than once"),
+                ));
+            }
+            assigned.push(field.get_index());
+            if field.get_proto().get_discriminant_value() != field::NO_DISCRIMINANT {
+                if let Some(other) = union_member {
+                    return Err(self.error_at(
+                        assignment.pos,
+                        &format!("\"{name}\" and \"{other}\" are members of the same union"),
+                    ));
+                }
+                union_member = Some(name);
+            }
+            self.apply_field(field, &assignment.value, dst.reborrow())?;
+        }
+        Ok(())
+    }
+
+    fn apply_field(
+        &self,
+        field: Field,
+        node: &Node,
+        mut dst: dynamic_struct::Builder<'_>,
+    ) -> Result<()> {
+        let ty = field.get_type();
+        match (field.get_proto().which()?, ty.which()) {
+            (field::Group(_), _) => {
+                let Value::Struct(fields) = &node.value else {
+                    return Err(self.type_error(node, "a group"));
+                };
+                let group = if field.get_proto().get_discriminant_value() != field::NO_DISCRIMINANT
+                {
+                    dst.init(field)?
+                } else {
+                    dst.get(field)?
+                };
+                self.apply_struct(fields, group.downcast())
+            }
+            (field::Slot(_), TypeVariant::Struct(_)) => {
+                let Value::Struct(fields) = &node.value else {
+                    return Err(self.type_error(node, "a struct"));
+                };
+                self.apply_struct(fields, dst.init(field)?.downcast())
+            }
+            (field::Slot(_), TypeVariant::List(element_type)) => {
+                let Value::List(elements) = &node.value else {
+                    return Err(self.type_error(node, "a list"));
+                };
+                let list = dst.initn(field, elements.len() as u32)?;
+                self.apply_list(elements, list.downcast(), element_type)
+            }
+            (field::Slot(_), TypeVariant::Text) => {
+                dst.set(field, dynamic_value::Reader::Text(self.text(node)?))
+            }
+            (field::Slot(_), TypeVariant::Data) => {
+                dst.set(field, dynamic_value::Reader::Data(self.data(node)?))
+            }
+            (field::Slot(_), _) => dst.set(field, self.scalar(ty, node)?),
+        }
+    }
+
+    fn apply_list(
+        &self,
+        elements: &[Node],
+        mut list: dynamic_list::Builder<'_>,
+        element_type: Type,
+    ) -> Result<()> {
+        for (index, element) in elements.iter().enumerate() {
+            let index = index as u32;
+            match element_type.which() {
+                TypeVariant::Struct(_) => {
+                    let Value::Struct(fields) = &element.value else {
+                        return Err(self.type_error(element, "a struct"));
+                    };
+                    self.apply_struct(fields, list.reborrow().get(index)?.downcast())?;
+                }
+                TypeVariant::List(inner_type) => {
+                    let Value::List(inner) = &element.value else {
+                        return Err(self.type_error(element, "a list"));
+                    };
+                    let dst = list.reborrow().init(index, inner.len() as u32)?;
+                    self.apply_list(inner, dst.downcast(), inner_type)?;
+                }
+                TypeVariant::Text => {
+                    list.set(index, dynamic_value::Reader::Text(self.text(element)?))?
+                }
+                TypeVariant::Data => {
+                    list.set(index, dynamic_value::Reader::Data(self.data(element)?))?
+                }
+                _ => list.set(index, self.scalar(element_type, element)?)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn text<'n>(&self, node: &'n Node) -> Result<&'n str> {
+        let Value::String(bytes) = &node.value else {
+            return Err(self.type_error(node, "a string"));
+        };
+        core::str::from_utf8(bytes)
+            .map_err(|_| self.error_at(node.pos, "string is not valid UTF-8"))
+    }
+
+    fn data<'n>(&self, node: &'n Node) -> Result<&'n [u8]> {
+        match &node.value {
+            Value::Data(bytes) | Value::String(bytes) => Ok(bytes),
+            _ => Err(self.type_error(node, "a data literal")),
+        }
+    }
+
+    fn scalar(&self, ty: Type, node: &Node) -> Result<dynamic_value::Reader<'static>> {
+        Ok(match ty.which() {
+            TypeVariant::Void => match &node.value {
+                Value::Struct(fields) if fields.is_empty() => dynamic_value::Reader::Void,
+                Value::Identifier(word) if word == "void" => dynamic_value::Reader::Void,
+                _ => return Err(self.type_error(node, "()")),
+            },
+            TypeVariant::Bool => match &node.value {
+                Value::Identifier(word) if word == "true" => dynamic_value::Reader::Bool(true),
+                Value::Identifier(word) if word == "false" => dynamic_value::Reader::Bool(false),
+                _ => return Err(self.type_error(node, "true or false")),
+            },
+            TypeVariant::Int8 => dynamic_value::Reader::Int8(self.integer(node)?),
+            TypeVariant::Int16 => dynamic_value::Reader::Int16(self.integer(node)?),
+            TypeVariant::Int32 => dynamic_value::Reader::Int32(self.integer(node)?),
+            TypeVariant::Int64 => dynamic_value::Reader::Int64(self.integer(node)?),
+            TypeVariant::UInt8 => dynamic_value::Reader::UInt8(self.integer(node)?),
+            TypeVariant::UInt16 => dynamic_value::Reader::UInt16(self.integer(node)?),
+            TypeVariant::UInt32 => dynamic_value::Reader::UInt32(self.integer(node)?),
+            TypeVariant::UInt64 => dynamic_value::Reader::UInt64(self.integer(node)?),
+            TypeVariant::Float32 => dynamic_value::Reader::Float32(self.float(node)?),
+            TypeVariant::Float64 => dynamic_value::Reader::Float64(self.float(node)?),
+            TypeVariant::Enum(schema) => self.enumerant(schema.into(), node)?.into(),
+            TypeVariant::AnyPointer => {
+                return Err(self.error_at(node.pos, "cannot parse AnyPointer values"))
+            }
+            TypeVariant::Capability(_) => {
+                return Err(self.error_at(node.pos, "cannot parse capabilities"))
+            }
+            _ => return Err(Error::failed("unexpected type".into())),
+        })
+    }
+
+    fn integer<T: TryFrom<i128>>(&self, node: &Node) -> Result<T> {
+        let Value::Number(literal) = &node.value else {
+            return Err(self.type_error(node, "an integer"));
+        };
+        let (negative, digits) = match literal.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, literal.as_str()),
+        };
+        let magnitude = match digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => digits.parse::<u64>(),
+        };
+        let Ok(magnitude) = magnitude else {
+            return Err(self.error_at(node.pos, &format!("invalid integer \"{literal}\"")));
+        };
+        let value = if negative {
+            -(magnitude as i128)
+        } else {
+            magnitude as i128
+        };
+        T::try_from(value)
+            .map_err(|_| self.error_at(node.pos, &format!("integer {literal} is out of range")))
+    }
+
+    fn float<T: core::str::FromStr>(&self, node: &Node) -> Result<T> {
+        let literal = match &node.value {
+            Value::Number(literal) | Value::Identifier(literal) => literal,
+            _ => return Err(self.type_error(node, "a number")),
+        };
+        let digits = literal.strip_prefix('-').unwrap_or(literal);
+        if digits.starts_with("0x") || digits.starts_with("0X") {
+            let value: i64 = self.integer(node)?;
+            return value
+                .to_string()
+                .parse()
+                .map_err(|_| self.error_at(node.pos, "invalid number"));
+        }
+        // Rust accepts these names case-insensitively, but nothing else made of letters.
+        if matches!(node.value, Value::Identifier(_))
+            && !["inf", "infinity", "nan"]
+                .iter()
+                .any(|name| digits.eq_ignore_ascii_case(name))
+        {
+            return Err(self.type_error(node, "a number"));
+        }
+        literal
+            .parse()
+            .map_err(|_| self.error_at(node.pos, &format!("invalid number \"{literal}\"")))
+    }
+
+    fn enumerant(&self, schema: EnumSchema, node: &Node) -> Result<dynamic_value::Enum> {
+        match &node.value {
+            Value::Identifier(name) => {
+                for enumerant in schema.get_enumerants()? {
+                    if enumerant.get_proto().get_name()? == name {
+                        return Ok(dynamic_value::Enum::new(enumerant.get_ordinal(), schema));
+                    }
+                }
+                Err(self.error_at(node.pos, &format!("unknown enumerant \"{name}\"")))
+            }
+            // Values that are not in the schema are printed as numbers.
+            Value::Number(_) => Ok(dynamic_value::Enum::new(self.integer(node)?, schema)),
+            _ => Err(self.type_error(node, "an enumerant")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_into;
+    use crate::schema_capnp::{code_generator_request, node, value, ElementSize};
+    use crate::{dynamic_struct, dynamic_value, message};
+
+    fn parse_node(input: &str) -> crate::Result<message::Builder<message::HeapAllocator>> {
+        let mut message = message::Builder::new_default();
+        let root: dynamic_value::Builder<'_> = message.init_root::<node::Builder>().into();
+        parse_into(root.downcast(), input)?;
+        Ok(message)
+    }
+
+    /// The compact and the multi-line forms that the printer produces.
+    fn printed(value: dynamic_value::Reader<'_>) -> [String; 2] {
+        [format!("{value:?}"), format!("{value:#?}")]
+    }
+
+    #[test]
+    fn round_trip_node() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder>();
+        node.set_id(0xffff_ffff_ffff_ffff);
+        node.set_display_name("foo.capnp:\"Foo\"\n\t\\ \u{1} \u{e9} \u{1f600}");
+        node.set_display_name_prefix_length(10);
+        {
+            let mut nested = node.reborrow().init_nested_nodes(2);
+            nested.reborrow().get(0).set_name("Bar");
+            nested.reborrow().get(1).set_id(12);
+        }
+        {
+            let mut st = node.reborrow().init_struct();
+            st.set_data_word_count(3);
+            st.set_preferred_list_encoding(ElementSize::EightBytes);
+            st.set_is_group(true);
+            let mut fields = st.init_fields(2);
+            fields.reborrow().get(0).set_name("a");
+            fields.reborrow().get(0).init_group().set_type_id(77);
+            fields.reborrow().get(1).set_code_order(1);
+        }
+        node.reborrow().init_parameters(0);
+
+        let reader = message.get_root_as_reader::<node::Reader>().unwrap();
+        let expected = dynamic_value::Reader::from(reader);
+        for printed in printed(expected) {
+            let parsed = parse_node(&printed).unwrap();
+            let parsed = parsed.get_root_as_reader::<node::Reader>().unwrap();
+            assert_eq!(dynamic_value::Reader::from(parsed), expected, "{printed}");
+        }
+    }
+
+    #[test]
+    fn round_trip_values() {
+        for init in [
+            (|v: value::Builder| v.init_data(3).copy_from_slice(b"\x00\x7f\xff"))
+                as fn(value::Builder),
+            |mut v| v.set_float32(f32::MIN_POSITIVE),
+            |mut v| v.set_float32(-0.1),
+            |mut v| v.set_float64(1e300),
+            |mut v| v.set_float64(-0.0),
+            |mut v| v.set_float64(f64::NAN),
+            |mut v| v.set_float64(f64::NEG_INFINITY),
+            |mut v| v.set_int64(i64::MIN),
+            |mut v| v.set_void(()),
+            |mut v| v.set_text(""),
+        ] {
+            let mut message = message::Builder::new_default();
+            init(message.init_root());
+            let reader = message.get_root_as_reader::<value::Reader>().unwrap();
+            let expected = dynamic_value::Reader::from(reader);
+            for printed in printed(expected) {
+                let mut parsed = message::Builder::new_default();
+                let root: dynamic_value::Builder<'_> = parsed.init_root::<value::Builder>().into();
+                parse_into(root.downcast(), &printed).unwrap();
+                let parsed = parsed.get_root_as_reader::<value::Reader>().unwrap();
+                assert_eq!(dynamic_value::Reader::from(parsed), expected, "{printed}");
+            }
+        }
+    }
+
+    #[test]
+    fn syntax() {
+        let message = parse_node(
+            r#"
+            # Comments run to the end of the line.
+            id = 0x10, displayName = "a\x41\101\u{42}", scopeId = 3,
+            nestedNodes = [(name = "x",), (id = 5)],
+            annotation = (targetsFile = true),
+            "#,
+        )
+        .unwrap();
+        let node = message.get_root_as_reader::<node::Reader>().unwrap();
+        assert_eq!(node.get_id(), 16);
+        assert_eq!(node.get_display_name().unwrap(), "aAAB");
+        assert_eq!(node.get_scope_id(), 3);
+        assert_eq!(node.get_nested_nodes().unwrap().len(), 2);
+        assert_eq!(node.get_nested_nodes().unwrap().get(1).get_id(), 5);
+        match node.which().unwrap() {
+            node::Annotation(a) => assert!(a.get_targets_file()),
+            _ => panic!("expected an annotation"),
+        }
+
+        let mut message = message::Builder::new_default();
+        let root: dynamic_value::Builder<'_> = message
+            .init_root::<code_generator_request::Builder>()
+            .into();
+        parse_into(
+            root.downcast::<dynamic_struct::Builder<'_>>(),
+            r#"(requestedFiles = [(id = 1, imports = [(id = 2, name = "b.capnp")])],
+                capnpVersion = (major = 1, minor = 0, micro = 2))"#,
+        )
+        .unwrap();
+        let request = message
+            .get_root_as_reader::<code_generator_request::Reader>()
+            .unwrap();
+        let file = request.get_requested_files().unwrap().get(0);
+        assert_eq!(
+            file.get_imports().unwrap().get(0).get_name().unwrap(),
+            "b.capnp"
+        );
+        assert_eq!(request.get_capnp_version().unwrap().get_micro(), 2);
+    }
+
+    #[test]
+    fn errors() {
+        for (input, message) in [
+            (
+                "id = 1,\n  bogus = 2",
+                "line 2, column 3: unknown field \"bogus\"",
+            ),
+            (
+                "(id = 1) x",
+                "line 1, column 10: unexpected input after struct",
+            ),
+            ("id = -1", "line 1, column 6: integer -1 is out of range"),
+            ("id = 1.5", "line 1, column 6: invalid integer \"1.5\""),
+            (
+                "displayName = 5",
+                "line 1, column 15: expected a string, found a number",
+            ),
+            (
+                "displayName = \"abc",
+                "line 1, column 19: unterminated string",
+            ),
+            (
+                "displayName = \"\\q\"",
+                "line 1, column 16: unknown escape sequence",
+            ),
+            (
+                "id = 1 id = 2",
+                "line 1, column 8: expected ',' or end of input",
+            ),
+            (
+                "id = 1, id = 2",
+                "line 1, column 9: field \"id\" is assigned more than once",
+            ),
+            (
+                "file = (), enum = ()",
+                "line 1, column 12: \"enum\" and \"file\" are members of the same union",
+            ),
+            (
+                "struct = (preferredListEncoding = huge)",
+                "line 1, column 35: unknown enumerant \"huge\"",
+            ),
+            (
+                "nestedNodes = [(name = \"x\"]",
+                "line 1, column 27: expected ',' or ')'",
+            ),
+        ] {
+            let error = parse_node(input).err().expect(input);
+            assert!(
+                error.to_string().contains(message),
+                "{input}: {error} does not contain {message}"
+            );
+        }
+
+        let nested = "[".repeat(100);
+        let error = parse_node(&format!("nestedNodes = {nested}"))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("nesting limit exceeded"));
+    }
+}
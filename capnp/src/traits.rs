@@ -87,12 +87,22 @@ pub trait SetPointerBuilder {
     ) -> Result<()>;
 }
 
-pub trait Imbue<'a> {
-    fn imbue(&mut self, caps: &'a CapTable);
+/// Attaches a capability table to a reader. `Table` is a `CapTable` for the single-threaded
+/// capability types, and a `SyncCapTable` for the thread-safe ones in `capnp::sync`.
+pub trait Imbue<'a, Table = CapTable> {
+    fn imbue(&mut self, caps: &'a Table);
 }
 
-pub trait ImbueMut<'a> {
-    fn imbue_mut(&mut self, caps: &'a mut CapTable);
+/// Attaches a capability table to a builder. See [`Imbue`].
+pub trait ImbueMut<'a, Table = CapTable> {
+    fn imbue_mut(&mut self, caps: &'a mut Table);
+}
+
+/// Constructs a value that stands for a failure with `error`. Implemented by the `Promise` types
+/// of both `capability` and `sync::capability`, so that macros like `capnp_rpc::pry!` can return
+/// errors from the methods of either kind of server.
+pub trait FromError<E> {
+    fn from_error(error: E) -> Self;
 }
 
 pub trait HasTypeId {
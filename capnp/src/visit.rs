@@ -0,0 +1,381 @@
+//! Walking a struct recursively, with callbacks for every struct, list, and field reached.
+//!
+//! A [`Visitor`] implements only the callbacks that it is interested in; [`walk()`] calls them
+//! in document order, along with the [`FieldPath`] of the value at hand.
+//!
+//! ```
+//! use capnp::dynamic_value::{self, FieldPath};
+//! use capnp::schema::Field;
+//! use capnp::visit::{walk, Visitor};
+//! use capnp::{message, schema_capnp::node};
+//!
+//! /// Collects the paths of all text fields.
+//! struct Texts(Vec<String>);
+//!
+//! impl<'a> Visitor<'a> for Texts {
+//!     fn enter_field(
+//!         &mut self,
+//!         path: &FieldPath,
+//!         _field: Field,
+//!         value: &dynamic_value::Reader<'a>,
+//!     ) -> capnp::Result<()> {
+//!         if let dynamic_value::Reader::Text(_) = value {
+//!             self.0.push(path.to_string());
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut message = message::Builder::new_default();
+//! let mut node = message.init_root::<node::Builder>();
+//! node.set_display_name("foo.capnp:Foo");
+//! node.reborrow().init_nested_nodes(1).get(0).set_name("Bar");
+//!
+//! let mut texts = Texts(Vec::new());
+//! walk(dynamic_value::Reader::from(node.into_reader()).downcast(), &mut texts).unwrap();
+//! assert_eq!(texts.0, ["displayName", "nestedNodes[0].name"]);
+//! ```
+
+use alloc::format;
+
+use crate::schema::Field;
+use crate::schema_capnp::field;
+use crate::{dynamic_list, dynamic_struct, dynamic_value, Error, Result};
+
+pub use crate::dynamic_value::{FieldPath, PathElement};
+
+/// Callbacks for [`walk()`]. Each one does nothing by default. An error returned by a callback
+/// stops the walk, and is passed on with the path at which it occurred appended to its
+/// description.
+pub trait Visitor<'a> {
+    /// Called before the fields of a struct or group are visited. The root struct has an empty
+    /// path.
+    fn enter_struct(
+        &mut self,
+        _path: &FieldPath,
+        _value: dynamic_struct::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the fields of a struct or group have been visited.
+    fn leave_struct(
+        &mut self,
+        _path: &FieldPath,
+        _value: dynamic_struct::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the elements of a list are visited.
+    fn enter_list(&mut self, _path: &FieldPath, _value: dynamic_list::Reader<'a>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the elements of a list have been visited.
+    fn leave_list(&mut self, _path: &FieldPath, _value: dynamic_list::Reader<'a>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for each non-union field of a struct, and for the active member of its union if
+    /// it has one. Unset pointer fields are reported with their default values, but are not
+    /// descended into.
+    fn enter_field(
+        &mut self,
+        _path: &FieldPath,
+        _field: Field,
+        _value: &dynamic_value::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after a field, and anything underneath it, has been visited.
+    fn leave_field(
+        &mut self,
+        _path: &FieldPath,
+        _field: Field,
+        _value: &dynamic_value::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for the active member of a union, before `enter_field()` is called for it.
+    fn enter_union_variant(
+        &mut self,
+        _path: &FieldPath,
+        _field: Field,
+        _value: &dynamic_value::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for the active member of a union, after `leave_field()` is called for it.
+    fn leave_union_variant(
+        &mut self,
+        _path: &FieldPath,
+        _field: Field,
+        _value: &dynamic_value::Reader<'a>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Visits `root` and everything underneath it, depth first.
+///
+/// Values are read through `root`, so the walk is subject to the traversal limit and nesting
+/// limit of the message that `root` belongs to; exceeding either one ends the walk with an
+/// error.
+pub fn walk<'a, V: Visitor<'a> + ?Sized>(
+    root: dynamic_struct::Reader<'a>,
+    visitor: &mut V,
+) -> Result<()> {
+    let mut walker = Walker {
+        visitor,
+        path: FieldPath::new(),
+    };
+    walker.walk_struct(root)
+}
+
+struct Walker<'v, V: ?Sized> {
+    visitor: &'v mut V,
+    path: FieldPath,
+}
+
+impl<'v, 'a, V: Visitor<'a> + ?Sized> Walker<'v, V> {
+    fn walk_struct(&mut self, value: dynamic_struct::Reader<'a>) -> Result<()> {
+        at(&self.path, self.visitor.enter_struct(&self.path, value))?;
+        let schema = value.get_schema();
+        for field in at(&self.path, schema.get_non_union_fields())? {
+            self.walk_field(value, field, false)?;
+        }
+        if let Some(field) = at(&self.path, value.which())? {
+            self.walk_field(value, field, true)?;
+        }
+        at(&self.path, self.visitor.leave_struct(&self.path, value))
+    }
+
+    fn walk_field(
+        &mut self,
+        parent: dynamic_struct::Reader<'a>,
+        field: Field,
+        is_union_member: bool,
+    ) -> Result<()> {
+        self.path
+            .push_field(at(&self.path, field.get_proto().get_name())?);
+        let value = at(&self.path, parent.get(field))?;
+        if is_union_member {
+            at(
+                &self.path,
+                self.visitor.enter_union_variant(&self.path, field, &value),
+            )?;
+        }
+        at(
+            &self.path,
+            self.visitor.enter_field(&self.path, field, &value),
+        )?;
+
+        let is_set = match at(&self.path, field.get_proto().which().map_err(Error::from))? {
+            field::Group(_) => true,
+            field::Slot(_) => at(&self.path, parent.has(field))?,
+        };
+        if is_set {
+            self.walk_value(value)?;
+        }
+
+        at(
+            &self.path,
+            self.visitor.leave_field(&self.path, field, &value),
+        )?;
+        if is_union_member {
+            at(
+                &self.path,
+                self.visitor.leave_union_variant(&self.path, field, &value),
+            )?;
+        }
+        self.path.pop();
+        Ok(())
+    }
+
+    fn walk_value(&mut self, value: dynamic_value::Reader<'a>) -> Result<()> {
+        match value {
+            dynamic_value::Reader::Struct(s) => self.walk_struct(s),
+            dynamic_value::Reader::List(l) => self.walk_list(l),
+            _ => Ok(()),
+        }
+    }
+
+    fn walk_list(&mut self, value: dynamic_list::Reader<'a>) -> Result<()> {
+        at(&self.path, self.visitor.enter_list(&self.path, value))?;
+        for index in 0..value.len() {
+            self.path.push_index(index);
+            let element = at(&self.path, value.get(index))?;
+            self.walk_value(element)?;
+            self.path.pop();
+        }
+        at(&self.path, self.visitor.leave_list(&self.path, value))
+    }
+}
+
+/// Adds `path` to the description of an error.
+fn at<T>(path: &FieldPath, result: Result<T>) -> Result<T> {
+    result.map_err(|e| {
+        if path.is_empty() {
+            e
+        } else {
+            Error {
+                kind: e.kind,
+                description: format!("{} at `{path}`", e.description),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use super::{walk, FieldPath, Visitor};
+    use crate::schema::Field;
+    use crate::schema_capnp::node;
+    use crate::{dynamic_list, dynamic_struct, dynamic_value, message, Error, Result};
+
+    /// Records the callbacks that it receives.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        fail_at: Option<&'static str>,
+    }
+
+    impl Recorder {
+        fn record(&mut self, event: &str, path: &FieldPath) -> Result<()> {
+            self.events.push(format!("{event} {path}"));
+            if self.fail_at == Some(event) {
+                return Err(Error::failed("rejected".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    impl<'a> Visitor<'a> for Recorder {
+        fn enter_struct(&mut self, path: &FieldPath, _: dynamic_struct::Reader<'a>) -> Result<()> {
+            self.record("enter_struct", path)
+        }
+        fn leave_struct(&mut self, path: &FieldPath, _: dynamic_struct::Reader<'a>) -> Result<()> {
+            self.record("leave_struct", path)
+        }
+        fn enter_list(&mut self, path: &FieldPath, _: dynamic_list::Reader<'a>) -> Result<()> {
+            self.record("enter_list", path)
+        }
+        fn leave_list(&mut self, path: &FieldPath, _: dynamic_list::Reader<'a>) -> Result<()> {
+            self.record("leave_list", path)
+        }
+        fn enter_field(
+            &mut self,
+            path: &FieldPath,
+            _: Field,
+            _: &dynamic_value::Reader<'a>,
+        ) -> Result<()> {
+            self.record("enter_field", path)
+        }
+        fn enter_union_variant(
+            &mut self,
+            path: &FieldPath,
+            _: Field,
+            _: &dynamic_value::Reader<'a>,
+        ) -> Result<()> {
+            self.record("enter_union_variant", path)
+        }
+        fn leave_union_variant(
+            &mut self,
+            path: &FieldPath,
+            _: Field,
+            _: &dynamic_value::Reader<'a>,
+        ) -> Result<()> {
+            self.record("leave_union_variant", path)
+        }
+    }
+
+    fn init_node(mut node: node::Builder<'_>) {
+        node.set_display_name("foo.capnp:Foo");
+        node.reborrow().init_nested_nodes(2).get(1).set_name("Bar");
+        node.init_enum().init_enumerants(1).get(0).set_name("baz");
+    }
+
+    #[test]
+    fn paths() {
+        let mut message = message::Builder::new_default();
+        init_node(message.init_root());
+        let root: node::Reader<'_> = message.get_root_as_reader().unwrap();
+
+        let mut recorder = Recorder::default();
+        walk(dynamic_value::Reader::from(root).downcast(), &mut recorder).unwrap();
+        let events = recorder.events;
+        assert_eq!(events.first().unwrap(), "enter_struct ");
+        assert_eq!(events.last().unwrap(), "leave_struct ");
+        for expected in [
+            "enter_field displayName",
+            "enter_list nestedNodes",
+            "enter_struct nestedNodes[1]",
+            "enter_field nestedNodes[1].name",
+            "leave_list nestedNodes",
+            "enter_union_variant enum",
+            "enter_struct enum",
+            "enter_field enum.enumerants[0].name",
+            "leave_union_variant enum",
+        ] {
+            assert!(
+                events.iter().any(|e| e == expected),
+                "missing {expected:?} in {events:?}"
+            );
+        }
+
+        // The variant callbacks surround the field callbacks.
+        let position = |event: &str| events.iter().position(|e| e == event).unwrap();
+        assert!(position("enter_union_variant enum") < position("enter_field enum"));
+
+        // Unset pointer fields are not descended into.
+        assert!(events.iter().any(|e| e == "enter_field parameters"));
+        assert!(!events.iter().any(|e| e == "enter_list parameters"));
+    }
+
+    #[test]
+    fn visitor_error_has_path() {
+        let mut message = message::Builder::new_default();
+        init_node(message.init_root());
+        let root: node::Reader<'_> = message.get_root_as_reader().unwrap();
+
+        let mut recorder = Recorder {
+            fail_at: Some("enter_list"),
+            ..Recorder::default()
+        };
+        let error = walk(dynamic_value::Reader::from(root).downcast(), &mut recorder).unwrap_err();
+        assert_eq!(error.description, "rejected at `nestedNodes`");
+    }
+
+    #[test]
+    fn traversal_limit() {
+        let mut message = message::Builder::new_default();
+        let mut node = message.init_root::<node::Builder<'_>>();
+        let mut nested = node.reborrow().init_nested_nodes(100);
+        for idx in 0..100 {
+            nested.reborrow().get(idx).set_name("name");
+        }
+        let segments = message.get_segments_for_output();
+        let options = *message::ReaderOptions::new().traversal_limit_in_words(Some(260));
+        let reader = message::Reader::new(message::SegmentArray::new(&segments), options);
+        let root: node::Reader<'_> = reader.get_root().unwrap();
+
+        let error = walk(
+            dynamic_value::Reader::from(root).downcast(),
+            &mut Recorder::default(),
+        )
+        .unwrap_err();
+        assert!(
+            error.description.contains(" at `nestedNodes["),
+            "{}",
+            error.description
+        );
+    }
+}
@@ -28,7 +28,9 @@ use capnp::schema_capnp;
 use capnp::Error;
 
 use self::FormattedText::{BlankLine, Branch, Indent, Line};
-use crate::codegen_types::{do_branding, Leaf, RustNodeInfo, RustTypeInfo, TypeParameterTexts};
+use crate::codegen_types::{
+    do_branding, get_type_parameters, Leaf, RustNodeInfo, RustTypeInfo, TypeParameterTexts,
+};
 use crate::convert_io_err;
 use crate::pointer_constants::generate_pointer_constant;
 
@@ -38,6 +40,9 @@ pub struct CodeGenerationCommand {
     default_parent_module: Vec<String>,
     raw_code_generator_request_path: Option<PathBuf>,
     capnp_root: String,
+    native_types: bool,
+    async_servers: bool,
+    sync_rpc: bool,
 }
 
 impl Default for CodeGenerationCommand {
@@ -47,6 +52,9 @@ impl Default for CodeGenerationCommand {
             default_parent_module: Vec::new(),
             raw_code_generator_request_path: None,
             capnp_root: "::capnp".into(),
+            native_types: false,
+            async_servers: false,
+            sync_rpc: false,
         }
     }
 }
@@ -93,6 +101,44 @@ impl CodeGenerationCommand {
         self
     }
 
+    /// Sets whether to generate a plain owned type `Native` for each struct and group, alongside
+    /// its `Reader` and `Builder`. `Native::from_reader()` (or `TryFrom<Reader>`) copies a message
+    /// into it, and `Native::write_to()` copies it back into a builder.
+    ///
+    /// Text and Data fields become `String` and `Vec<u8>`, lists become `Vec`s, and unions become
+    /// enums. Fields annotated with `$Rust.option`, and capability fields, become `Option`s.
+    /// Generic structs, and fields of `AnyPointer` or generic struct types, have no native
+    /// representation and are skipped.
+    pub fn native_types(&mut self, enabled: bool) -> &mut Self {
+        self.native_types = enabled;
+        self
+    }
+
+    /// Sets whether the methods of generated `Server` traits take `self: Rc<Self>` rather than
+    /// `&mut self`. The returned `Promise` can then hold on to the server for as long as the call
+    /// runs, without cloning state out of it first. Any mutable state must live in `Cell`s or
+    /// `RefCell`s, since concurrent calls share the server.
+    ///
+    /// `ServerDispatch` keeps the server in an `Rc`, and its `DerefMut` impl (used by
+    /// `capnp_rpc::CapabilityServerSet`, for example) panics while any call is in progress.
+    /// With [`sync_rpc()`](Self::sync_rpc), the `Rc` is an `Arc`, and servers must be `Sync`.
+    pub fn async_servers(&mut self, enabled: bool) -> &mut Self {
+        self.async_servers = enabled;
+        self
+    }
+
+    /// Sets whether the generated code is built on the thread-safe capability types in
+    /// `capnp::sync`, rather than the single-threaded ones. Clients are then `Send` and `Sync`,
+    /// servers and the promises they return must be `Send`, and both are served and called by
+    /// `capnp_rpc::sync`. Requires the `sync_rpc` feature of `capnp`.
+    ///
+    /// Code generated for a schema file refers to the types of the files it imports, so those
+    /// must be generated with the same setting.
+    pub fn sync_rpc(&mut self, enabled: bool) -> &mut Self {
+        self.sync_rpc = enabled;
+        self
+    }
+
     /// Generates Rust code according to a `schema_capnp::code_generator_request` read from `inp`.
     pub fn run<T>(&mut self, inp: T) -> ::capnp::Result<()>
     where
@@ -185,6 +231,14 @@ pub struct GeneratorContext<'a> {
 
     /// Root path for referencing things in the `capnp` crate from the generated code.
     pub capnp_root: String,
+
+    /// Whether to generate plain owned types for structs. See
+    /// [`CodeGenerationCommand::native_types()`].
+    pub native_types: bool,
+
+    /// Whether server methods take `self: Rc<Self>`. See
+    /// [`CodeGenerationCommand::async_servers()`].
+    pub async_servers: bool,
 }
 
 impl<'a> GeneratorContext<'a> {
@@ -207,7 +261,13 @@ impl<'a> GeneratorContext<'a> {
             node_map: collections::hash_map::HashMap::<u64, schema_capnp::node::Reader<'a>>::new(),
             scope_map: collections::hash_map::HashMap::<u64, Vec<String>>::new(),
             node_parents: collections::hash_map::HashMap::new(),
-            capnp_root: code_generation_command.capnp_root.clone(),
+            capnp_root: if code_generation_command.sync_rpc {
+                format!("{}::sync", code_generation_command.capnp_root)
+            } else {
+                code_generation_command.capnp_root.clone()
+            },
+            native_types: code_generation_command.native_types,
+            async_servers: code_generation_command.async_servers,
         };
 
         for node in ctx.request.get_nodes()? {
@@ -257,6 +317,16 @@ impl<'a> GeneratorContext<'a> {
                 id,
             )?;
         }
+
+        // The result type of streaming methods is provided by the capnp crate.
+        ctx.scope_map.insert(
+            STREAM_RESULT_ID,
+            vec![
+                ctx.capnp_root.clone(),
+                "stream_capnp".into(),
+                "stream_result".into(),
+            ],
+        );
         Ok(ctx)
     }
 
@@ -377,7 +447,7 @@ fn snake_to_upper_case(s: &str) -> String {
     result_chars.into_iter().collect()
 }
 
-fn camel_to_snake_case(s: &str) -> String {
+pub(crate) fn camel_to_snake_case(s: &str) -> String {
     let mut result_chars: Vec<char> = Vec::new();
     let mut first_char = true;
     for c in s.chars() {
@@ -390,7 +460,7 @@ fn camel_to_snake_case(s: &str) -> String {
     result_chars.into_iter().collect()
 }
 
-fn capitalize_first_letter(s: &str) -> String {
+pub(crate) fn capitalize_first_letter(s: &str) -> String {
     let mut result_chars: Vec<char> = Vec::new();
     for c in s.chars() {
         result_chars.push(c)
@@ -492,7 +562,7 @@ const RUST_KEYWORDS: [&str; 53] = [
     "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
 ];
 
-fn module_name(camel_case: &str) -> String {
+pub(crate) fn module_name(camel_case: &str) -> String {
     let mut name = camel_to_snake_case(camel_case);
     if RUST_KEYWORDS.contains(&&*name) {
         name.push('_');
@@ -505,6 +575,9 @@ const NAME_ANNOTATION_ID: u64 = 0xc2fe4c6d100166d0;
 const PARENT_MODULE_ANNOTATION_ID: u64 = 0xabee386cd1450364;
 const OPTION_ANNOTATION_ID: u64 = 0xabfef22c4ee1964e;
 
+/// The id of `StreamResult` in stream.capnp, the result type of methods declared `-> stream`.
+const STREAM_RESULT_ID: u64 = 0x995f9a3377c0b16e;
+
 fn name_annotation_value(annotation: schema_capnp::annotation::Reader) -> capnp::Result<&str> {
     if let schema_capnp::value::Text(t) = annotation.get_value()?.which()? {
         let name = t?;
@@ -524,7 +597,7 @@ fn name_annotation_value(annotation: schema_capnp::annotation::Reader) -> capnp:
     }
 }
 
-fn get_field_name(field: schema_capnp::field::Reader) -> capnp::Result<&str> {
+pub(crate) fn get_field_name(field: schema_capnp::field::Reader) -> capnp::Result<&str> {
     for annotation in field.get_annotations()? {
         if annotation.get_id() == NAME_ANNOTATION_ID {
             return name_annotation_value(annotation);
@@ -568,7 +641,7 @@ fn capnp_name_to_rust_name(capnp_name: &str, name_kind: NameKind) -> String {
     }
 }
 
-fn is_option_field(field: schema_capnp::field::Reader) -> capnp::Result<bool> {
+pub(crate) fn is_option_field(field: schema_capnp::field::Reader) -> capnp::Result<bool> {
     use capnp::schema_capnp::*;
 
     let enabled = field
@@ -977,6 +1050,7 @@ fn generate_setter(
     let mut initter_mut = false;
     let mut initn_interior = Vec::new();
     let mut initter_params = Vec::new();
+    let mut adopter_interior = Vec::new();
 
     let discriminant_value = field.get_discriminant_value();
     if discriminant_value != field::NO_DISCRIMINANT {
@@ -989,11 +1063,13 @@ fn generate_setter(
             discriminant_offset as usize, discriminant_value as usize
         ));
         initter_interior.push(init_discrim.clone());
+        adopter_interior.push(init_discrim.clone());
         initn_interior.push(init_discrim);
     }
 
     let mut return_result = false;
     let mut result = Vec::new();
+    let mut orphan_owned_type = None;
 
     let (maybe_reader_type, maybe_builder_type): (Option<String>, Option<String>) = match field
         .which()?
@@ -1021,6 +1097,9 @@ fn generate_setter(
         field::Slot(reg_field) => {
             let offset = reg_field.get_offset() as usize;
             let typ = reg_field.get_type()?;
+            if typ.is_pointer()? {
+                orphan_owned_type = Some((typ.type_string(ctx, Leaf::Owned)?, offset));
+            }
             match typ.which().expect("unrecognized type") {
                 type_::Void(()) => {
                     setter_param = "_value".to_string();
@@ -1219,6 +1298,35 @@ fn generate_setter(
         result.push(indent(initter_interior));
         result.push(line("}"));
     }
+    if let Some((owned_type, offset)) = orphan_owned_type {
+        result.push(line("#[inline]"));
+        result.push(Line(fmt!(
+            ctx,
+            "pub fn disown_{styled_name}(&mut self) -> {capnp}::orphan::Orphan<'a, {owned_type}> {{"
+        )));
+        result.push(indent(Line(format!(
+            "self.builder.disown_pointer_field({offset}).into()"
+        ))));
+        result.push(line("}"));
+
+        // Set the discriminant only once the adoption has succeeded, so that a failed adoption
+        // leaves the union as it was.
+        let adopt =
+            format!("self.builder.reborrow().get_pointer_field({offset}).adopt(value.into())");
+        if adopter_interior.is_empty() {
+            adopter_interior.push(Line(adopt));
+        } else {
+            adopter_interior.insert(0, Line(format!("{adopt}?;")));
+            adopter_interior.push(line("Ok(())"));
+        }
+        result.push(line("#[inline]"));
+        result.push(Line(fmt!(
+            ctx,
+            "pub fn adopt_{styled_name}(&mut self, value: {capnp}::orphan::Orphan<'_, {owned_type}>) -> {capnp}::Result<()> {{"
+        )));
+        result.push(indent(adopter_interior));
+        result.push(line("}"));
+    }
     Ok(Branch(result))
 }
 
@@ -1674,6 +1782,54 @@ fn generate_get_field_types(
     }
 }
 
+/// Generates a function `{name}(index: {index_type}) -> Type` that maps each index to the
+/// corresponding entry of `types`.
+fn generate_get_types_fn(
+    ctx: &GeneratorContext,
+    node_reader: schema_capnp::node::Reader,
+    name: &str,
+    index_type: &str,
+    types: &[String],
+) -> FormattedText {
+    let mut branches: Vec<FormattedText> = types
+        .iter()
+        .enumerate()
+        .map(|(index, typ)| {
+            Line(fmt!(
+                ctx,
+                "{} => <{} as {capnp}::introspect::Introspect>::introspect(),",
+                index,
+                typ
+            ))
+        })
+        .collect();
+    let body = if branches.is_empty() {
+        Line("panic!(\"invalid index {}\", index)".into())
+    } else {
+        branches.push(Line("_ => panic!(\"invalid index {}\", index),".into()));
+        Branch(vec![
+            Line("match index {".into()),
+            indent(branches),
+            Line("}".into()),
+        ])
+    };
+    let signature = if !node_reader.get_is_generic() {
+        fmt!(
+            ctx,
+            "pub fn {name}(index: {index_type}) -> {capnp}::introspect::Type {{"
+        )
+    } else {
+        let params = node_reader.parameters_texts(ctx);
+        fmt!(
+            ctx,
+            "pub fn {name}<{0}>(index: {index_type}) -> {capnp}::introspect::Type {1} {{",
+            params.params,
+            params.where_clause
+        )
+    };
+    Branch(vec![Line(signature), indent(body), Line("}".into())])
+}
+
 fn annotation_branch(
     ctx: &GeneratorContext,
     annotation: schema_capnp::annotation::Reader,
@@ -1741,6 +1897,18 @@ fn generate_get_annotation_types(
                 }
             }
         }
+        node::Interface(i) => {
+            for (midx, method) in i.get_methods()?.iter().enumerate() {
+                for (idx, annotation) in method.get_annotations()?.iter().enumerate() {
+                    branches.push(annotation_branch(
+                        ctx,
+                        annotation,
+                        Some(midx as u16),
+                        idx as u32,
+                    )?);
+                }
+            }
+        }
         _ => (),
     }
 
@@ -1915,6 +2083,66 @@ fn get_ty_params_of_brand_helper(
     Ok(())
 }
 
+/// Generates a `schemas()` function listing the types defined in a file, for registering with
+/// `capnp::schema::Registry`.
+fn generate_schemas_fn(
+    ctx: &GeneratorContext,
+    file_node: schema_capnp::node::Reader,
+) -> ::capnp::Result<FormattedText> {
+    use capnp::schema_capnp::node;
+
+    let mut entries = Vec::new();
+    let mut pending: Vec<u64> = file_node
+        .get_nested_nodes()?
+        .iter()
+        .map(|n| n.get_id())
+        .collect();
+    pending.reverse();
+    while let Some(id) = pending.pop() {
+        let node_reader = &ctx.node_map[&id];
+        let mut nested: Vec<u64> = node_reader
+            .get_nested_nodes()?
+            .iter()
+            .map(|n| n.get_id())
+            .collect();
+        nested.reverse();
+        pending.extend(nested);
+
+        let the_mod = ctx.get_qualified_module(id);
+        let typ = match node_reader.which()? {
+            node::Enum(_) => the_mod,
+            node::Struct(_) | node::Interface(_) => {
+                let params = get_type_parameters(ctx, id);
+                if params.is_empty() {
+                    format!("{the_mod}::Owned")
+                } else {
+                    let any = fmt!(ctx, "{capnp}::any_pointer::Owned");
+                    format!("{the_mod}::Owned<{}>", vec![any; params.len()].join(","))
+                }
+            }
+            _ => continue,
+        };
+        entries.push(Line(fmt!(
+            ctx,
+            "<{typ} as {capnp}::introspect::Introspect>::introspect,"
+        )));
+    }
+
+    Ok(Branch(vec![
+        BlankLine,
+        Line("/// The types defined in this file. They are not registered automatically; pass them to".into()),
+        Line("/// `capnp::schema::Registry::register_all()` so that they can be looked up by id.".into()),
+        Line(fmt!(
+            ctx,
+            "pub fn schemas() -> &'static [fn() -> {capnp}::introspect::Type] {{"
+        )),
+        indent(Line("&[".into())),
+        indent(indent(entries)),
+        indent(Line("]".into())),
+        Line("}".into()),
+    ]))
+}
+
 fn generate_node(
     ctx: &GeneratorContext,
     node_id: u64,
@@ -1935,6 +2163,7 @@ fn generate_node(
     match node_reader.which()? {
         node::File(()) => {
             output.push(Branch(nested_output));
+            output.push(generate_schemas_fn(ctx, *node_reader)?);
         }
         node::Struct(struct_reader) => {
             let params = node_reader.parameters_texts(ctx);
@@ -1966,6 +2195,7 @@ fn generate_node(
 
             let data_size = struct_reader.get_data_word_count();
             let pointer_size = struct_reader.get_pointer_count();
+            let is_group = struct_reader.get_is_group();
             let discriminant_count = struct_reader.get_discriminant_count();
             let discriminant_offset = struct_reader.get_discriminant_offset();
 
@@ -2186,6 +2416,35 @@ fn generate_node(
                     line("}")
                 ]),
                 line("}"),
+                BlankLine,
+                Line(format!("impl <'a,{0}> ::core::cmp::PartialEq for Reader<'a,{0}> {1} {{",
+                            params.params, params.where_clause)),
+                indent(vec![
+                    line("fn eq(&self, other: &Self) -> bool {"),
+                    indent(if is_group {
+                        Line(fmt!(ctx,"::core::convert::Into::<{capnp}::dynamic_value::Reader<'_>>::into(*self) == ::core::convert::Into::<{capnp}::dynamic_value::Reader<'_>>::into(*other)"))
+                    } else {
+                        line("self.reader.total_eq(&other.reader)")
+                    }),
+                    line("}")
+                ]),
+                line("}"),
+                Line(format!("impl <'a,{0}> ::core::cmp::Eq for Reader<'a,{0}> {1} {{}}",
+                            params.params, params.where_clause)),
+                Line(format!("impl <'a,{0}> ::core::hash::Hash for Reader<'a,{0}> {1} {{",
+                            params.params, params.where_clause)),
+                indent(vec![
+                    line("fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {"),
+                    indent(if is_group {
+                        Line(fmt!(ctx,"::core::hash::Hash::hash(&::core::convert::Into::<{capnp}::dynamic_value::Reader<'_>>::into(*self), state)"))
+                    } else {
+                        line("self.reader.total_hash(state)")
+                    }),
+                    line("}")
+                ]),
+                line("}"),
+                Line(fmt!(ctx,"{capnp}::impl_serialize_for_reader!([{0}] [{1}]);",
+                            params.params, params.where_clause)),
 
                 BlankLine,
 
@@ -2304,6 +2563,17 @@ fn generate_node(
                         BlankLine,
                         Line(fmt!(ctx,"pub fn total_size(&self) -> {capnp}::Result<{capnp}::MessageSize> {{")),
                         indent(line("self.builder.as_reader().total_size()")),
+                        line("}"),
+
+                        BlankLine,
+                        Line(fmt!(ctx,"pub fn merge_from(&mut self, other: Reader<'_,{0}>) -> {capnp}::Result<()> {{", params.params)),
+                        indent(Line(fmt!(ctx,"self.merge_from_with_options(other, {capnp}::dynamic_struct::MergeOptions::default())"))),
+                        line("}"),
+                        Line(fmt!(ctx,"pub fn merge_from_with_options(&mut self, other: Reader<'_,{0}>, options: {capnp}::dynamic_struct::MergeOptions) -> {capnp}::Result<()> {{", params.params)),
+                        indent(vec![
+                            Line(fmt!(ctx,"let mut dst: {capnp}::dynamic_struct::Builder<'_> = {capnp}::dynamic_value::Builder::from(self.reborrow()).downcast();")),
+                            Line(fmt!(ctx,"dst.merge_from_with_options({capnp}::dynamic_value::Reader::from(other).downcast(), options)")),
+                        ]),
                         line("}")
                         ]),
                 indent(builder_members),
@@ -2336,9 +2606,16 @@ fn generate_node(
                 line("}"),
             ];
 
+            let native_type = if ctx.native_types && !is_generic {
+                crate::native_types::generate_native_type(ctx, node_id)?
+            } else {
+                Branch(Vec::new())
+            };
+
             output.push(indent(vec![
                 Branch(accessors),
                 Branch(which_enums),
+                native_type,
                 Branch(nested_output),
             ]));
             output.push(line("}"));
@@ -2469,6 +2746,33 @@ fn generate_node(
 
             mod_interior.push(line("#![allow(unused_variables)]"));
 
+            private_mod_interior.push(crate::pointer_constants::node_word_array_declaration(
+                ctx,
+                "ENCODED_NODE",
+                *node_reader,
+                crate::pointer_constants::WordArrayDeclarationOptions { public: true },
+            )?);
+
+            let mut param_types = Vec::new();
+            let mut result_types = Vec::new();
+            let self_param = if ctx.async_servers {
+                fmt!(ctx, "self: {capnp}::private::capability::Rc<Self>")
+            } else {
+                "&mut self".to_string()
+            };
+            let (server_field, new_server, server_ref, server_ref_type) = if ctx.async_servers {
+                let rc = fmt!(ctx, "{capnp}::private::capability::Rc<_T>");
+                let new_rc = fmt!(ctx, "{capnp}::private::capability::Rc::new(s)");
+                (rc.clone(), new_rc, "&self.server", format!("&{rc}"))
+            } else {
+                (
+                    "_T".to_string(),
+                    "s".to_string(),
+                    "&mut self.server",
+                    "&mut _T".to_string(),
+                )
+            };
+
             let methods = interface.get_methods()?;
             for (ordinal, method) in methods.into_iter().enumerate() {
                 let name = method.get_name()?;
@@ -2517,9 +2821,25 @@ fn generate_node(
                     &result_scopes.join("::"),
                 )?;
 
+                // Streaming methods have no results, and the client only waits for flow control.
+                let is_streaming = result_id == STREAM_RESULT_ID;
+
+                let receiver = if ctx.async_servers {
+                    fmt!(ctx, "{capnp}::private::capability::Rc::clone(server)")
+                } else {
+                    "server".to_string()
+                };
+                let typed_results = if is_streaming {
+                    "".to_string()
+                } else {
+                    fmt!(
+                        ctx,
+                        ", {capnp}::private::capability::internal_get_typed_results(results)"
+                    )
+                };
                 dispatch_arms.push(
                     Line(fmt!(ctx,
-                        "{ordinal} => server.{}({capnp}::private::capability::internal_get_typed_params(params), {capnp}::private::capability::internal_get_typed_results(results)),",
+                        "{ordinal} => {capnp}::capability::DispatchCallResult::new({receiver}.{}({capnp}::private::capability::internal_get_typed_params(params){typed_results}), {is_streaming}),",
                         module_name(name))));
                 mod_interior.push(Line(fmt!(
                     ctx,
@@ -2528,42 +2848,103 @@ fn generate_node(
                     params_ty_params,
                     param_type
                 )));
-                mod_interior.push(Line(fmt!(
-                    ctx,
-                    "pub type {}Results<{}> = {capnp}::capability::Results<{}>;",
-                    capitalize_first_letter(name),
-                    results_ty_params,
-                    result_type
-                )));
+                let results_param = if is_streaming {
+                    "".to_string()
+                } else {
+                    mod_interior.push(Line(fmt!(
+                        ctx,
+                        "pub type {}Results<{}> = {capnp}::capability::Results<{}>;",
+                        capitalize_first_letter(name),
+                        results_ty_params,
+                        result_type
+                    )));
+                    format!(
+                        ", _: {}Results<{}>",
+                        capitalize_first_letter(name),
+                        results_ty_params
+                    )
+                };
                 server_interior.push(
                     Line(fmt!(ctx,
-                        "fn {}(&mut self, _: {}Params<{}>, _: {}Results<{}>) -> {capnp}::capability::Promise<(), {capnp}::Error> {{ {capnp}::capability::Promise::err({capnp}::Error::unimplemented(\"method {}::Server::{} not implemented\".to_string())) }}",
+                        "fn {}({self_param}, _: {}Params<{}>{results_param}) -> {capnp}::capability::Promise<(), {capnp}::Error> {{ {capnp}::capability::Promise::err({capnp}::Error::unimplemented(\"method {}::Server::{} not implemented\".to_string())) }}",
                         module_name(name),
                         capitalize_first_letter(name), params_ty_params,
-                        capitalize_first_letter(name), results_ty_params,
                         node_name, module_name(name)
                     )));
 
-                client_impl_interior.push(Line(fmt!(
-                    ctx,
-                    "pub fn {}_request(&self) -> {capnp}::capability::Request<{},{}> {{",
-                    camel_to_snake_case(name),
-                    param_type,
-                    result_type
-                )));
-
-                client_impl_interior.push(indent(Line(format!(
-                    "self.client.new_call(_private::TYPE_ID, {ordinal}, ::core::option::Option::None)"
-                ))));
+                if is_streaming {
+                    client_impl_interior.push(Line(fmt!(
+                        ctx,
+                        "pub fn {}_request(&self) -> {capnp}::capability::StreamingRequest<{}> {{",
+                        camel_to_snake_case(name),
+                        param_type
+                    )));
+                    client_impl_interior.push(indent(Line(format!(
+                        "self.client.new_streaming_call(_private::TYPE_ID, {ordinal}, ::core::option::Option::None)"
+                    ))));
+                } else {
+                    client_impl_interior.push(Line(fmt!(
+                        ctx,
+                        "pub fn {}_request(&self) -> {capnp}::capability::Request<{},{}> {{",
+                        camel_to_snake_case(name),
+                        param_type,
+                        result_type
+                    )));
+                    client_impl_interior.push(indent(Line(format!(
+                        "self.client.new_call(_private::TYPE_ID, {ordinal}, ::core::option::Option::None)"
+                    ))));
+                }
                 client_impl_interior.push(line("}"));
 
                 method.get_annotations()?;
+                param_types.push(param_type);
+                result_types.push(result_type);
             }
 
+            let mut superclass_types = Vec::new();
+            for superclass in interface.get_superclasses()? {
+                let id = superclass.get_id();
+                superclass_types.push(do_branding(
+                    ctx,
+                    id,
+                    superclass.get_brand()?,
+                    Leaf::Owned,
+                    &ctx.get_qualified_module(id),
+                )?);
+            }
+
+            private_mod_interior.push(generate_get_types_fn(
+                ctx,
+                *node_reader,
+                "get_param_types",
+                "u16",
+                &param_types,
+            ));
+            private_mod_interior.push(generate_get_types_fn(
+                ctx,
+                *node_reader,
+                "get_result_types",
+                "u16",
+                &result_types,
+            ));
+            private_mod_interior.push(generate_get_types_fn(
+                ctx,
+                *node_reader,
+                "get_superclass_types",
+                "u32",
+                &superclass_types,
+            ));
+            private_mod_interior.push(generate_get_annotation_types(ctx, *node_reader)?);
+
             let mut base_dispatch_arms = Vec::new();
 
             let server_base = {
-                let mut base_traits = Vec::new();
+                // With the thread-safe capability types, servers must be `Send`, and `Sync` too
+                // if they are shared through an `Arc`.
+                let mut base_traits = vec![fmt!(ctx, "{capnp}::capability::MaybeSend")];
+                if ctx.async_servers {
+                    base_traits.push(fmt!(ctx, "{capnp}::capability::MaybeSync"));
+                }
 
                 fn find_super_interfaces<'a>(
                     interface: schema_capnp::node::interface::Reader<'a>,
@@ -2592,16 +2973,12 @@ fn generate_node(
                     let the_mod = ctx.get_qualified_module(type_id);
 
                     base_dispatch_arms.push(Line(format!(
-                        "0x{type_id:x} => {}::dispatch_call_internal(&mut self.server, method_id, params, results),",
+                        "0x{type_id:x} => {}::dispatch_call_internal({server_ref}, method_id, params, results),",
                         do_branding(
                             ctx, type_id, brand, Leaf::ServerDispatch, &the_mod)?)));
                     base_traits.push(do_branding(ctx, type_id, brand, Leaf::Server, &the_mod)?);
                 }
-                if !extends.is_empty() {
-                    format!(": {}", base_traits.join(" + "))
-                } else {
-                    "".to_string()
-                }
+                format!(": {}", base_traits.join(" + "))
             };
 
             mod_interior.push(BlankLine);
@@ -2632,7 +3009,7 @@ fn generate_node(
                 Branch(vec![
                     Line("#[derive(Copy, Clone)]".into()),
                     line("pub struct Owned(());"),
-                    Line(fmt!(ctx,"impl {capnp}::introspect::Introspect for Owned {{ fn introspect() -> {capnp}::introspect::Type {{ {capnp}::introspect::TypeVariant::Capability.into() }} }}")),
+                    Line(fmt!(ctx,"impl {capnp}::introspect::Introspect for Owned {{ fn introspect() -> {capnp}::introspect::Type {{ {capnp}::introspect::TypeVariant::Capability({capnp}::introspect::RawInterfaceSchema {{ encoded_node: &_private::ENCODED_NODE, param_types: _private::get_param_types, result_types: _private::get_result_types, superclass_types: _private::get_superclass_types, annotation_types: _private::get_annotation_types }}).into() }} }}")),
                     line("impl ::capnp::traits::Owned for Owned { type Reader<'a> = Client; type Builder<'a> = Client; }"),
                     Line(fmt!(ctx,"impl {capnp}::traits::Pipelined for Owned {{ type Pipeline = Client; }}"))])
             } else {
@@ -2642,7 +3019,7 @@ fn generate_node(
                     indent(Line(params.phantom_data_type.clone())),
                     line("}"),
                     Line(fmt!(ctx,
-                              "impl <{0}> {capnp}::introspect::Introspect for Owned <{0}> {1} {{ fn introspect() -> {capnp}::introspect::Type {{ {capnp}::introspect::TypeVariant::Capability.into() }} }}",
+                              "impl <{0}> {capnp}::introspect::Introspect for Owned <{0}> {1} {{ fn introspect() -> {capnp}::introspect::Type {{ {capnp}::introspect::TypeVariant::Capability({capnp}::introspect::RawInterfaceSchema {{ encoded_node: &_private::ENCODED_NODE, param_types: _private::get_param_types::<{0}>, result_types: _private::get_result_types::<{0}>, superclass_types: _private::get_superclass_types::<{0}>, annotation_types: _private::get_annotation_types::<{0}> }}).into() }} }}",
                               params.params, params.where_clause)),
                     Line(fmt!(ctx,
                         "impl <{0}> {capnp}::traits::Owned for Owned <{0}> {1} {{ type Reader<'a> = Client<{0}>; type Builder<'a> = Client<{0}>; }}",
@@ -2697,6 +3074,15 @@ fn generate_node(
                 line("}"),
             ]));
 
+            mod_interior.push(Branch(vec![
+                Line(fmt!(ctx,
+                    "impl {bracketed_params} {capnp}::introspect::Introspect for Client{bracketed_params} {} {{",
+                    params.where_clause)),
+                indent(Line(fmt!(ctx,
+                    "fn introspect() -> {capnp}::introspect::Type {{ <Owned{bracketed_params} as {capnp}::introspect::Introspect>::introspect() }}"))),
+                line("}"),
+            ]));
+
             mod_interior.push(
                 Branch(vec![
                     Line(format!("impl {bracketed_params} Clone for Client{bracketed_params} {{")),
@@ -2727,7 +3113,7 @@ fn generate_node(
                     "pub struct ServerDispatch<_T,{}> {{",
                     params.params
                 )),
-                indent(line("pub server: _T,")),
+                indent(Line(format!("pub server: {server_field},"))),
                 indent(if is_generic {
                     vec![Line(params.phantom_data_type.clone())]
                 } else {
@@ -2743,7 +3129,7 @@ fn generate_node(
                 indent(vec![
                     Line(format!("type Dispatch = ServerDispatch<_S, {}>;", params.params)),
                     Line(format!("fn from_server(s: _S) -> ServerDispatch<_S, {}> {{", params.params)),
-                    indent(Line(format!("ServerDispatch {{ server: s, {} }}", params.phantom_data_value))),
+                    indent(Line(format!("ServerDispatch {{ server: {new_server}, {} }}", params.phantom_data_value))),
                     line("}"),
                 ]),
                 line("}"),
@@ -2768,7 +3154,11 @@ fn generate_node(
                     } else {
                         line("impl <_T: Server> ::core::ops::DerefMut for ServerDispatch<_T> {")
                     }),
-                    indent(line("fn deref_mut(&mut self) -> &mut _T { &mut self.server}")),
+                    indent(if ctx.async_servers {
+                        Line(fmt!(ctx,"fn deref_mut(&mut self) -> &mut _T {{ {capnp}::private::capability::Rc::get_mut(&mut self.server).expect(\"server has calls in progress\") }}"))
+                    } else {
+                        line("fn deref_mut(&mut self) -> &mut _T { &mut self.server}")
+                    }),
                     line("}"),
                     ]));
 
@@ -2779,11 +3169,11 @@ fn generate_node(
                     } else {
                         Line(fmt!(ctx,"impl <_T: Server> {capnp}::capability::Server for ServerDispatch<_T> {{"))
                     }),
-                    indent(Line(fmt!(ctx,"fn dispatch_call(&mut self, interface_id: u64, method_id: u16, params: {capnp}::capability::Params<{capnp}::any_pointer::Owned>, results: {capnp}::capability::Results<{capnp}::any_pointer::Owned>) -> {capnp}::capability::Promise<(), {capnp}::Error> {{"))),
+                    indent(Line(fmt!(ctx,"fn dispatch_call(&mut self, interface_id: u64, method_id: u16, params: {capnp}::capability::Params<{capnp}::any_pointer::Owned>, results: {capnp}::capability::Results<{capnp}::any_pointer::Owned>) -> {capnp}::capability::DispatchCallResult {{"))),
                     indent(indent(line("match interface_id {"))),
-                    indent(indent(indent(line("_private::TYPE_ID => Self::dispatch_call_internal(&mut self.server, method_id, params, results),")))),
+                    indent(indent(indent(Line(format!("_private::TYPE_ID => Self::dispatch_call_internal({server_ref}, method_id, params, results),"))))),
                     indent(indent(indent(base_dispatch_arms))),
-                    indent(indent(indent(Line(fmt!(ctx,"_ => {{ {capnp}::capability::Promise::err({capnp}::Error::unimplemented(\"Method not implemented.\".to_string())) }}"))))),
+                    indent(indent(indent(Line(fmt!(ctx,"_ => {{ {capnp}::capability::DispatchCallResult::new({capnp}::capability::Promise::err({capnp}::Error::unimplemented(\"Method not implemented.\".to_string())), false) }}"))))),
                     indent(indent(line("}"))),
                     indent(line("}")),
                     line("}")]));
@@ -2795,10 +3185,10 @@ fn generate_node(
                     } else {
                         line("impl <_T :Server> ServerDispatch<_T> {")
                     }),
-                    indent(Line(fmt!(ctx,"pub fn dispatch_call_internal(server: &mut _T, method_id: u16, params: {capnp}::capability::Params<{capnp}::any_pointer::Owned>, results: {capnp}::capability::Results<{capnp}::any_pointer::Owned>) -> {capnp}::capability::Promise<(), {capnp}::Error> {{"))),
+                    indent(Line(fmt!(ctx,"pub fn dispatch_call_internal(server: {server_ref_type}, method_id: u16, params: {capnp}::capability::Params<{capnp}::any_pointer::Owned>, results: {capnp}::capability::Results<{capnp}::any_pointer::Owned>) -> {capnp}::capability::DispatchCallResult {{"))),
                     indent(indent(line("match method_id {"))),
                     indent(indent(indent(dispatch_arms))),
-                    indent(indent(indent(Line(fmt!(ctx,"_ => {{ ::capnp::capability::Promise::err({capnp}::Error::unimplemented(\"Method not implemented.\".to_string())) }}"))))),
+                    indent(indent(indent(Line(fmt!(ctx,"_ => {{ {capnp}::capability::DispatchCallResult::new({capnp}::capability::Promise::err({capnp}::Error::unimplemented(\"Method not implemented.\".to_string())), false) }}"))))),
                     indent(indent(line("}"))),
                     indent(line("}")),
                     line("}")]));
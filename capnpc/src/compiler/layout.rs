@@ -0,0 +1,400 @@
+//! Assigns offsets to the fields of a struct.
+//!
+//! Fields are added in ordinal order, each one going into the smallest hole that fits it, so
+//! that a field's position never depends on fields with higher ordinals. Members of a union
+//! share space: each member gets its own `Group`, and groups of the same `Union` reuse the data
+//! and pointer locations that the union has already claimed from its parent before claiming
+//! more.
+
+/// The number of distinct data sizes that a hole can have: 1, 2, 4, 8, 16, and 32 bits.
+const HOLE_SIZES: u32 = 6;
+
+/// A set of unused spaces within an allocated region, at most one of each power-of-two size.
+///
+/// `holes[n]` is the offset of the hole of size `2^n` bits, in multiples of that size, or zero if
+/// there is no such hole. Offset zero is always allocated before any holes can exist, so it never
+/// needs to denote a hole.
+#[derive(Clone, Copy, Default)]
+struct HoleSet {
+    holes: [u32; HOLE_SIZES as usize],
+}
+
+impl HoleSet {
+    fn try_allocate(&mut self, lg_size: u32) -> Option<u32> {
+        if lg_size >= HOLE_SIZES {
+            None
+        } else if self.holes[lg_size as usize] != 0 {
+            let result = self.holes[lg_size as usize];
+            self.holes[lg_size as usize] = 0;
+            Some(result)
+        } else {
+            let next = self.try_allocate(lg_size + 1)?;
+            let result = next * 2;
+            self.holes[lg_size as usize] = result + 1;
+            Some(result)
+        }
+    }
+
+    /// Records holes of sizes `lg_size` up to (but not including) `limit_lg_size`, which are left
+    /// over after allocating a field of size `lg_size` at the start of a space of size
+    /// `limit_lg_size`. `offset` is the position of the first hole.
+    fn add_holes_at_end(&mut self, mut lg_size: u32, mut offset: u32, limit_lg_size: u32) {
+        while lg_size < limit_lg_size {
+            self.holes[lg_size as usize] = offset;
+            lg_size += 1;
+            offset = offset / 2 + offset % 2;
+        }
+    }
+
+    /// Tries to grow the allocation at `old_offset` to `2^expansion_factor` times its size, by
+    /// merging it with the holes that follow it.
+    fn try_expand(&mut self, old_lg_size: u32, old_offset: u32, expansion_factor: u32) -> bool {
+        if expansion_factor == 0 {
+            return true;
+        }
+        if old_lg_size == HOLE_SIZES || self.holes[old_lg_size as usize] != old_offset + 1 {
+            return false;
+        }
+        if self.try_expand(old_lg_size + 1, old_offset >> 1, expansion_factor - 1) {
+            self.holes[old_lg_size as usize] = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn smallest_at_least(&self, lg_size: u32) -> Option<u32> {
+        (lg_size..HOLE_SIZES).find(|&i| self.holes[i as usize] != 0)
+    }
+}
+
+/// Somewhere that fields can be added: either the struct itself, or a member of a union.
+#[derive(Clone, Copy, Debug)]
+pub enum Scope {
+    Top,
+    Group(usize),
+}
+
+#[derive(Clone, Copy)]
+struct DataLocation {
+    lg_size: u32,
+    offset: u32,
+}
+
+struct Union {
+    parent: Scope,
+    group_count: u32,
+    discriminant_offset: Option<u32>,
+    data_locations: Vec<DataLocation>,
+    pointer_locations: Vec<u32>,
+}
+
+/// How much of one of its union's data locations a group has used.
+#[derive(Clone, Copy, Default)]
+struct DataLocationUsage {
+    is_used: bool,
+    lg_size_used: u32,
+    holes: HoleSet,
+}
+
+struct Group {
+    union: usize,
+    has_members: bool,
+    data_usage: Vec<DataLocationUsage>,
+    pointer_usage: usize,
+}
+
+#[derive(Default)]
+pub struct StructLayout {
+    pub data_word_count: u32,
+    pub pointer_count: u32,
+    holes: HoleSet,
+    unions: Vec<Union>,
+    groups: Vec<Group>,
+}
+
+impl StructLayout {
+    pub fn new_union(&mut self, parent: Scope) -> usize {
+        self.unions.push(Union {
+            parent,
+            group_count: 0,
+            discriminant_offset: None,
+            data_locations: Vec::new(),
+            pointer_locations: Vec::new(),
+        });
+        self.unions.len() - 1
+    }
+
+    /// Adds a member to `union`, returning the scope in which the member's fields go.
+    pub fn new_group(&mut self, union: usize) -> Scope {
+        self.groups.push(Group {
+            union,
+            has_members: false,
+            data_usage: Vec::new(),
+            pointer_usage: 0,
+        });
+        Scope::Group(self.groups.len() - 1)
+    }
+
+    pub fn discriminant_offset(&self, union: usize) -> Option<u32> {
+        self.unions[union].discriminant_offset
+    }
+
+    /// Allocates the union's discriminant, unless that has already happened. Returns whether
+    /// the discriminant was allocated by this call.
+    pub fn add_discriminant(&mut self, union: usize) -> bool {
+        if self.unions[union].discriminant_offset.is_some() {
+            return false;
+        }
+        let offset = self.add_data(self.unions[union].parent, 4);
+        self.unions[union].discriminant_offset = Some(offset);
+        true
+    }
+
+    pub fn add_void(&mut self, scope: Scope) {
+        if let Scope::Group(group) = scope {
+            self.add_member(group);
+            // The enclosing groups have members now too, so that their unions get discriminants.
+            let union = self.groups[group].union;
+            self.add_void(self.unions[union].parent);
+        }
+    }
+
+    /// Allocates a data field of size `2^lg_size` bits, returning its offset in multiples of its
+    /// size.
+    pub fn add_data(&mut self, scope: Scope, lg_size: u32) -> u32 {
+        match scope {
+            Scope::Top => match self.holes.try_allocate(lg_size) {
+                Some(offset) => offset,
+                None => {
+                    let offset = self.data_word_count << (6 - lg_size);
+                    self.data_word_count += 1;
+                    self.holes.add_holes_at_end(lg_size, offset + 1, HOLE_SIZES);
+                    offset
+                }
+            },
+            Scope::Group(group) => self.add_group_data(group, lg_size),
+        }
+    }
+
+    pub fn add_pointer(&mut self, scope: Scope) -> u32 {
+        match scope {
+            Scope::Top => {
+                self.pointer_count += 1;
+                self.pointer_count - 1
+            }
+            Scope::Group(group) => {
+                self.add_member(group);
+                let union = self.groups[group].union;
+                let index = self.groups[group].pointer_usage;
+                self.groups[group].pointer_usage += 1;
+                if let Some(&offset) = self.unions[union].pointer_locations.get(index) {
+                    offset
+                } else {
+                    let offset = self.add_pointer(self.unions[union].parent);
+                    self.unions[union].pointer_locations.push(offset);
+                    offset
+                }
+            }
+        }
+    }
+
+    fn try_expand_data(
+        &mut self,
+        scope: Scope,
+        old_lg_size: u32,
+        old_offset: u32,
+        expansion_factor: u32,
+    ) -> bool {
+        let group = match scope {
+            Scope::Top => {
+                return self
+                    .holes
+                    .try_expand(old_lg_size, old_offset, expansion_factor)
+            }
+            Scope::Group(group) => group,
+        };
+        if old_lg_size + expansion_factor > 6 || old_offset & ((1 << expansion_factor) - 1) != 0 {
+            return false;
+        }
+        let union = self.groups[group].union;
+        for index in 0..self.groups[group].data_usage.len() {
+            let location = self.unions[union].data_locations[index];
+            if location.lg_size >= old_lg_size
+                && old_offset >> (location.lg_size - old_lg_size) == location.offset
+            {
+                let local_offset =
+                    old_offset - (location.offset << (location.lg_size - old_lg_size));
+                let mut usage = self.groups[group].data_usage[index];
+                let result = if local_offset == 0 && usage.lg_size_used == old_lg_size {
+                    // The location holds exactly this field, so the whole location can grow.
+                    self.try_expand_usage(
+                        &mut usage,
+                        union,
+                        index,
+                        old_lg_size + expansion_factor,
+                        false,
+                    )
+                } else {
+                    usage
+                        .holes
+                        .try_expand(old_lg_size, local_offset, expansion_factor)
+                };
+                self.groups[group].data_usage[index] = usage;
+                return result;
+            }
+        }
+        panic!("tried to expand a field that was never allocated");
+    }
+
+    fn add_member(&mut self, group: usize) {
+        if !self.groups[group].has_members {
+            self.groups[group].has_members = true;
+            let union = self.groups[group].union;
+            self.unions[union].group_count += 1;
+            if self.unions[union].group_count == 2 {
+                self.add_discriminant(union);
+            }
+        }
+    }
+
+    fn add_group_data(&mut self, group: usize, lg_size: u32) -> u32 {
+        self.add_member(group);
+        let union = self.groups[group].union;
+
+        // Look for the smallest hole that fits, among the locations the union already has.
+        let mut best: Option<(u32, usize)> = None;
+        for index in 0..self.unions[union].data_locations.len() {
+            if self.groups[group].data_usage.len() == index {
+                self.groups[group]
+                    .data_usage
+                    .push(DataLocationUsage::default());
+            }
+            let location = self.unions[union].data_locations[index];
+            let usage = &self.groups[group].data_usage[index];
+            let hole = if !usage.is_used {
+                (lg_size <= location.lg_size).then_some(location.lg_size)
+            } else if lg_size >= usage.lg_size_used {
+                (lg_size < location.lg_size).then_some(lg_size)
+            } else if let Some(hole) = usage.holes.smallest_at_least(lg_size) {
+                Some(hole)
+            } else {
+                (usage.lg_size_used < location.lg_size).then_some(usage.lg_size_used)
+            };
+            if let Some(hole) = hole {
+                if !matches!(best, Some((best_size, _)) if best_size <= hole) {
+                    best = Some((hole, index));
+                }
+            }
+        }
+        if let Some((_, index)) = best {
+            let location = self.unions[union].data_locations[index];
+            let usage = &mut self.groups[group].data_usage[index];
+            let location_offset = location.offset << (location.lg_size - lg_size);
+            return if !usage.is_used {
+                usage.is_used = true;
+                usage.lg_size_used = lg_size;
+                location_offset
+            } else if lg_size >= usage.lg_size_used {
+                usage.holes.add_holes_at_end(usage.lg_size_used, 1, lg_size);
+                usage.lg_size_used = lg_size + 1;
+                location_offset + 1
+            } else if let Some(hole) = usage.holes.try_allocate(lg_size) {
+                location_offset + hole
+            } else {
+                let result = 1 << (usage.lg_size_used - lg_size);
+                usage
+                    .holes
+                    .add_holes_at_end(lg_size, result + 1, usage.lg_size_used);
+                usage.lg_size_used += 1;
+                location_offset + result
+            };
+        }
+
+        // Nothing fits as is, so try growing each location in turn.
+        for index in 0..self.unions[union].data_locations.len() {
+            let mut usage = self.groups[group].data_usage[index];
+            let result = if !usage.is_used {
+                if self.location_try_expand_to(union, index, lg_size) {
+                    usage.is_used = true;
+                    usage.lg_size_used = lg_size;
+                    let location = self.unions[union].data_locations[index];
+                    Some(location.offset << (location.lg_size - lg_size))
+                } else {
+                    None
+                }
+            } else {
+                let new_size = usage.lg_size_used.max(lg_size) + 1;
+                if self.try_expand_usage(&mut usage, union, index, new_size, true) {
+                    let hole = usage
+                        .holes
+                        .try_allocate(lg_size)
+                        .expect("expanded location should have a hole");
+                    let location = self.unions[union].data_locations[index];
+                    Some((location.offset << (location.lg_size - lg_size)) + hole)
+                } else {
+                    None
+                }
+            };
+            self.groups[group].data_usage[index] = usage;
+            if let Some(result) = result {
+                return result;
+            }
+        }
+
+        // Still nothing, so claim a new location for the union.
+        let offset = self.add_data(self.unions[union].parent, lg_size);
+        self.unions[union]
+            .data_locations
+            .push(DataLocation { lg_size, offset });
+        self.groups[group].data_usage.push(DataLocationUsage {
+            is_used: true,
+            lg_size_used: lg_size,
+            holes: HoleSet::default(),
+        });
+        offset
+    }
+
+    fn try_expand_usage(
+        &mut self,
+        usage: &mut DataLocationUsage,
+        union: usize,
+        index: usize,
+        desired_usage: u32,
+        new_holes: bool,
+    ) -> bool {
+        if desired_usage > self.unions[union].data_locations[index].lg_size
+            && !self.location_try_expand_to(union, index, desired_usage)
+        {
+            return false;
+        }
+        if new_holes {
+            usage
+                .holes
+                .add_holes_at_end(usage.lg_size_used, 1, desired_usage);
+        }
+        usage.lg_size_used = desired_usage;
+        true
+    }
+
+    fn location_try_expand_to(&mut self, union: usize, index: usize, new_lg_size: u32) -> bool {
+        let location = self.unions[union].data_locations[index];
+        if new_lg_size <= location.lg_size {
+            return true;
+        }
+        let factor = new_lg_size - location.lg_size;
+        if self.try_expand_data(
+            self.unions[union].parent,
+            location.lg_size,
+            location.offset,
+            factor,
+        ) {
+            let location = &mut self.unions[union].data_locations[index];
+            location.offset >>= factor;
+            location.lg_size = new_lg_size;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Splits schema source text into statements of tokens.
+//!
+//! A statement is a sequence of tokens ended either by `;` or by a `{ ... }` block of further
+//! statements. Parenthesized and bracketed lists are lexed as single tokens, so that the parser
+//! never has to match up delimiters itself.
+
+use super::SyntaxError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    String(String),
+    Binary(Vec<u8>),
+    Integer(u64),
+    Float(f64),
+    Operator(String),
+    /// `( ... )`, split at top-level commas.
+    Parenthesized(Vec<Vec<Token>>),
+    /// `[ ... ]`, split at top-level commas.
+    Bracketed(Vec<Vec<Token>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    pub tokens: Vec<Token>,
+    /// The statements inside the `{ ... }` that ended this statement, if any.
+    pub block: Option<Vec<Statement>>,
+    pub doc_comment: Option<String>,
+    pub start: usize,
+    pub end: usize,
+}
+
+const OPERATOR_CHARS: &[u8] = b"!$%&*+-./:<=>?@^|~";
+
+/// Lexes a whole file.
+pub fn lex(source: &str) -> Result<Vec<Statement>, SyntaxError> {
+    let mut lexer = Lexer {
+        source: source.as_bytes(),
+        pos: 0,
+    };
+    let statements = lexer.statements()?;
+    if lexer.pos < lexer.source.len() {
+        return Err(lexer.error_here("Unexpected '}'."));
+    }
+    Ok(statements)
+}
+
+struct Lexer<'a> {
+    source: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source.get(self.pos + offset).copied()
+    }
+
+    fn error_here(&self, message: &str) -> SyntaxError {
+        SyntaxError {
+            start: self.pos,
+            end: (self.pos + 1).min(self.source.len()),
+            message: message.into(),
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' => self.pos += 1,
+                b'#' => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_line_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Reads the comment lines that directly follow a statement's terminator. They may start on
+    /// the same line as the terminator or on the next one, and end at the first line that is not
+    /// a comment.
+    fn doc_comment(&mut self) -> Option<String> {
+        self.skip_line_whitespace();
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+            self.skip_line_whitespace();
+        }
+        let mut result = String::new();
+        while self.peek() == Some(b'#') {
+            self.pos += 1;
+            if self.peek() == Some(b' ') {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while !matches!(self.peek(), None | Some(b'\n')) {
+                self.pos += 1;
+            }
+            let line = String::from_utf8_lossy(&self.source[start..self.pos]);
+            result.push_str(line.trim_end_matches('\r'));
+            result.push('\n');
+            if self.peek() == Some(b'\n') {
+                self.pos += 1;
+            }
+            let line_start = self.pos;
+            self.skip_line_whitespace();
+            if self.peek() != Some(b'#') {
+                self.pos = line_start;
+            }
+        }
+        if result.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Lexes statements up to the end of input or an unmatched `}`.
+    fn statements(&mut self) -> Result<Vec<Statement>, SyntaxError> {
+        let mut statements = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            match self.peek() {
+                None | Some(b'}') => return Ok(statements),
+                _ => {}
+            }
+            let start = self.pos;
+            let tokens = self.tokens(b";{")?;
+            match self.peek() {
+                Some(b';') => {
+                    self.pos += 1;
+                    let end = self.pos;
+                    statements.push(Statement {
+                        tokens,
+                        block: None,
+                        doc_comment: self.doc_comment(),
+                        start,
+                        end,
+                    });
+                }
+                Some(b'{') => {
+                    self.pos += 1;
+                    let doc_comment = self.doc_comment();
+                    let block = self.statements()?;
+                    if self.peek() != Some(b'}') {
+                        return Err(self.error_here("Missing '}'."));
+                    }
+                    self.pos += 1;
+                    statements.push(Statement {
+                        tokens,
+                        block: Some(block),
+                        doc_comment,
+                        start,
+                        end: self.pos,
+                    });
+                }
+                _ => return Err(self.error_here("Expected ';' or '{'.")),
+            }
+        }
+    }
+
+    /// Lexes tokens until one of `terminators` (or the end of input) is reached.
+    fn tokens(&mut self, terminators: &[u8]) -> Result<Vec<Token>, SyntaxError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            match self.peek() {
+                None => return Ok(tokens),
+                Some(c) if terminators.contains(&c) => return Ok(tokens),
+                Some(_) => tokens.push(self.token()?),
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<Token, SyntaxError> {
+        let start = self.pos;
+        let c = self.peek().unwrap();
+        let kind = match c {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') = self.peek() {
+                    self.pos += 1;
+                }
+                TokenKind::Identifier(self.text(start).into())
+            }
+            b'0'..=b'9' => self.number()?,
+            b'"' => TokenKind::String(self.string()?),
+            b'(' => TokenKind::Parenthesized(self.list(b')')?),
+            b'[' => TokenKind::Bracketed(self.list(b']')?),
+            c if OPERATOR_CHARS.contains(&c) => {
+                while let Some(c) = self.peek() {
+                    if !OPERATOR_CHARS.contains(&c) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                TokenKind::Operator(self.text(start).into())
+            }
+            _ => return Err(self.error_here("Parse error.")),
+        };
+        Ok(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn text(&self, start: usize) -> &str {
+        core::str::from_utf8(&self.source[start..self.pos]).unwrap()
+    }
+
+    /// Lexes a comma-separated list of token sequences, having just seen its opening delimiter.
+    fn list(&mut self, close: u8) -> Result<Vec<Vec<Token>>, SyntaxError> {
+        let open = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            let item = self.tokens(&[b',', close, b';', b'{', b'}'])?;
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    items.push(item);
+                }
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    // An empty last item is either an empty list or a trailing comma.
+                    if !item.is_empty() {
+                        items.push(item);
+                    }
+                    return Ok(items);
+                }
+                _ => {
+                    return Err(SyntaxError {
+                        start: open,
+                        end: open + 1,
+                        message: "Unmatched parenthesis or bracket.".into(),
+                    })
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<TokenKind, SyntaxError> {
+        let start = self.pos;
+        if self.peek() == Some(b'0') && matches!(self.peek_at(1), Some(b'x' | b'X')) {
+            self.pos += 2;
+            if self.peek() == Some(b'"') {
+                return self.binary();
+            }
+            while let Some(c) = self.peek() {
+                if !c.is_ascii_hexdigit() {
+                    break;
+                }
+                self.pos += 1;
+            }
+            let digits = &self.text(start)[2..];
+            return u64::from_str_radix(digits, 16)
+                .map(TokenKind::Integer)
+                .map_err(|_| self.number_error(start));
+        }
+
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        let mut is_float = false;
+        if self.peek() == Some(b'.') && matches!(self.peek_at(1), Some(b'0'..=b'9')) {
+            is_float = true;
+            self.pos += 1;
+            while let Some(b'0'..=b'9') = self.peek() {
+                self.pos += 1;
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            let exponent_start = self.pos;
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if let Some(b'0'..=b'9') = self.peek() {
+                is_float = true;
+                while let Some(b'0'..=b'9') = self.peek() {
+                    self.pos += 1;
+                }
+            } else {
+                self.pos = exponent_start;
+            }
+        }
+
+        let text = self.text(start);
+        if is_float {
+            text.parse()
+                .map(TokenKind::Float)
+                .map_err(|_| self.number_error(start))
+        } else if text.len() > 1 && text.starts_with('0') {
+            u64::from_str_radix(&text[1..], 8)
+                .map(TokenKind::Integer)
+                .map_err(|_| self.number_error(start))
+        } else {
+            text.parse()
+                .map(TokenKind::Integer)
+                .map_err(|_| self.number_error(start))
+        }
+    }
+
+    fn number_error(&self, start: usize) -> SyntaxError {
+        SyntaxError {
+            start,
+            end: self.pos,
+            message: "Invalid number literal.".into(),
+        }
+    }
+
+    /// Lexes the `"..."` of a `0x"..."` literal.
+    fn binary(&mut self) -> Result<TokenKind, SyntaxError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut digits = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) if c.is_ascii_hexdigit() => {
+                    digits.push((c as char).to_digit(16).unwrap() as u8);
+                    self.pos += 1;
+                }
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                _ => return Err(self.error_here("Invalid binary literal.")),
+            }
+        }
+        if digits.len() % 2 != 0 {
+            return Err(SyntaxError {
+                start,
+                end: self.pos,
+                message: "Binary literal must have an even number of hex digits.".into(),
+            });
+        }
+        Ok(TokenKind::Binary(
+            digits
+                .chunks(2)
+                .map(|pair| pair[0] << 4 | pair[1])
+                .collect(),
+        ))
+    }
+
+    fn string(&mut self) -> Result<String, SyntaxError> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error_here("Unterminated string literal."));
+            };
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\n' => return Err(self.error_here("Unterminated string literal.")),
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        return Err(self.error_here("Unterminated string literal."));
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'a' => bytes.push(7),
+                        b'b' => bytes.push(8),
+                        b'f' => bytes.push(12),
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'v' => bytes.push(11),
+                        b'\'' | b'"' | b'\\' | b'?' => bytes.push(escaped),
+                        b'x' => {
+                            let mut value = 0u32;
+                            let mut count = 0;
+                            while let Some(digit) =
+                                self.peek().and_then(|d| (d as char).to_digit(16))
+                            {
+                                if count == 2 {
+                                    break;
+                                }
+                                value = value * 16 + digit;
+                                count += 1;
+                                self.pos += 1;
+                            }
+                            if count == 0 {
+                                return Err(self.error_here("Invalid escape sequence."));
+                            }
+                            bytes.push(value as u8);
+                        }
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        _ => return Err(self.error_here("Invalid escape sequence.")),
+                    }
+                }
+                _ => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error_here("String literal is not valid UTF-8."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lex, TokenKind};
+
+    #[test]
+    fn statements_and_doc_comments() {
+        let statements = lex("@0xbf5147cbbecf40c1;\n\
+             struct Foo {  # The foo.\n\
+               # More foo.\n\
+               bar @0 :List(Int8) = [-1, 0x10];\n\
+               # The bar.\n\
+             \n\
+               # Not a doc comment.\n\
+             }\n")
+        .unwrap();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0].tokens[1].kind,
+            TokenKind::Integer(0xbf5147cbbecf40c1)
+        );
+        assert_eq!(
+            statements[1].doc_comment.as_deref(),
+            Some("The foo.\nMore foo.\n")
+        );
+        let block = statements[1].block.as_ref().unwrap();
+        assert_eq!(block.len(), 1);
+        assert_eq!(block[0].doc_comment.as_deref(), Some("The bar.\n"));
+        let TokenKind::Bracketed(items) = &block[0].tokens[7].kind else {
+            panic!("expected list")
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0][0].kind, TokenKind::Operator("-".into()));
+        assert_eq!(items[1][0].kind, TokenKind::Integer(16));
+    }
+
+    #[test]
+    fn literals() {
+        let statements = lex(r#"x 1.5e3 017 0x"de ad" "a\"\n\x41\101";"#).unwrap();
+        let kinds: Vec<_> = statements[0]
+            .tokens
+            .iter()
+            .map(|t| t.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::Identifier("x".into()),
+                TokenKind::Float(1500.0),
+                TokenKind::Integer(15),
+                TokenKind::Binary(vec![0xde, 0xad]),
+                TokenKind::String("a\"\nAA".into()),
+            ]
+        );
+    }
+}
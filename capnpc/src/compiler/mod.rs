@@ -0,0 +1,575 @@
+//! A schema compiler written in Rust, for building without the `capnp` executable.
+//!
+//! [`Compiler`] parses `.capnp` files and produces the same
+//! `schema_capnp::code_generator_request` that `capnp compile` sends to plugins, so the result
+//! can be passed to [`crate::codegen::CodeGenerationCommand::run`].
+//!
+//! ```ignore
+//! let message = capnpc::compiler::Compiler::new()
+//!     .src_prefix("schema")
+//!     .file("schema/foo.capnp")
+//!     .compile()?;
+//! ```
+
+mod layout;
+mod lexer;
+mod md5;
+mod parser;
+mod translate;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use capnp::message;
+
+use self::parser::{
+    AnnotationApplication, DeclKind, Declaration, Expression, ExpressionKind, ParamList,
+};
+
+/// The files under `/capnp/` that `capnp` installs alongside its headers, which schemas may import
+/// even when they aren't on the import path.
+const EMBEDDED_FILES: [(&str, &str); 2] = [
+    ("capnp/c++.capnp", include_str!("c++.capnp")),
+    ("capnp/stream.capnp", include_str!("stream.capnp")),
+];
+
+/// A syntax error, as a byte range of the source.
+#[derive(Debug)]
+struct SyntaxError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// An error in some loaded file.
+struct Diagnostic {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// A parsed file, along with the files it refers to.
+struct SourceFile {
+    pub display_name: String,
+    pub source: String,
+    pub root: Declaration,
+    /// Imports by the name they're written with, and their index among the loaded files.
+    pub imports: Vec<(String, usize)>,
+    pub embeds: Vec<(String, Vec<u8>)>,
+}
+
+/// A builder object for compiling schema files without the `capnp` executable.
+///
+/// The options mean the same as those of [`crate::CompilerCommand`].
+#[derive(Default)]
+pub struct Compiler {
+    files: Vec<PathBuf>,
+    src_prefixes: Vec<PathBuf>,
+    import_paths: Vec<PathBuf>,
+    no_standard_import: bool,
+}
+
+impl Compiler {
+    /// Creates a new, empty compiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to be compiled.
+    pub fn file<P>(&mut self, path: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// For all files specified for compilation that start with `prefix`, removes the prefix
+    /// when computing output filenames.
+    pub fn src_prefix<P>(&mut self, prefix: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.src_prefixes.push(prefix.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds `dir` to the list of directories searched for absolute imports.
+    pub fn import_path<P>(&mut self, dir: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.import_paths.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Don't search /usr/include and /usr/local/include for absolute imports.
+    pub fn no_standard_import(&mut self) -> &mut Self {
+        self.no_standard_import = true;
+        self
+    }
+
+    /// Compiles the files, returning a message whose root is a
+    /// `schema_capnp::code_generator_request` for them.
+    pub fn compile(&self) -> ::capnp::Result<message::Builder<message::HeapAllocator>> {
+        let mut loader = Loader {
+            compiler: self,
+            files: Vec::new(),
+            locations: HashMap::new(),
+            errors: Vec::new(),
+            stream_file: None,
+        };
+        let mut requested = Vec::new();
+        for file in &self.files {
+            let source = std::fs::read_to_string(file)
+                .map_err(|error| ::capnp::Error::failed(format!("{}: {error}", file.display())))?;
+            let display_name = self.display_name(file);
+            let location = match file.canonicalize() {
+                Ok(path) => Location::Disk(path),
+                Err(_) => Location::Disk(file.clone()),
+            };
+            requested.push(loader.load(location, display_name, source));
+        }
+
+        if loader.errors.is_empty() {
+            match translate::translate(&loader.files, &requested, loader.stream_file) {
+                Ok(message) => return Ok(message),
+                Err(errors) => loader.errors = errors,
+            }
+        }
+        let messages: Vec<String> = loader
+            .errors
+            .iter()
+            .map(|error| {
+                let file = &loader.files[error.file];
+                let (line, column) = line_and_column(&file.source, error.start);
+                let (end_line, end_column) = line_and_column(&file.source, error.end);
+                if end_line == line && end_column > column {
+                    format!(
+                        "{}:{line}:{column}-{end_column}: error: {}",
+                        file.display_name, error.message
+                    )
+                } else {
+                    format!(
+                        "{}:{line}:{column}: error: {}",
+                        file.display_name, error.message
+                    )
+                }
+            })
+            .collect();
+        Err(::capnp::Error::failed(messages.join("\n")))
+    }
+
+    /// The name of a file as it's given in the request, with the longest matching source prefix
+    /// removed.
+    fn display_name(&self, file: &Path) -> String {
+        let stripped = self
+            .src_prefixes
+            .iter()
+            .filter_map(|prefix| file.strip_prefix(prefix).ok())
+            .min_by_key(|rest| rest.components().count())
+            .unwrap_or(file);
+        let components: Vec<String> = stripped
+            .components()
+            .filter_map(|component| match component {
+                std::path::Component::CurDir => None,
+                component => Some(component.as_os_str().to_string_lossy().into_owned()),
+            })
+            .collect();
+        normalize(&components.join("/"))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Location {
+    Disk(PathBuf),
+    Embedded(&'static str),
+}
+
+struct Loader<'a> {
+    compiler: &'a Compiler,
+    files: Vec<SourceFile>,
+    locations: HashMap<Location, usize>,
+    errors: Vec<Diagnostic>,
+    stream_file: Option<usize>,
+}
+
+impl<'a> Loader<'a> {
+    /// Parses a file and everything it imports, returning its index.
+    fn load(&mut self, location: Location, display_name: String, source: String) -> usize {
+        if let Some(&index) = self.locations.get(&location) {
+            return index;
+        }
+        let root = match lexer::lex(&source)
+            .and_then(|statements| parser::parse_file(statements, source.len()))
+        {
+            Ok(root) => root,
+            Err(error) => {
+                self.errors.push(Diagnostic {
+                    file: self.files.len(),
+                    start: error.start,
+                    end: error.end,
+                    message: error.message,
+                });
+                parser::parse_file(Vec::new(), source.len()).expect("empty file is valid")
+            }
+        };
+        let index = self.files.len();
+        self.locations.insert(location.clone(), index);
+        self.files.push(SourceFile {
+            display_name,
+            source,
+            root,
+            imports: Vec::new(),
+            embeds: Vec::new(),
+        });
+
+        let mut references = References::default();
+        references.declaration(&self.files[index].root);
+        for (name, start, end) in references.imports {
+            if self.files[index].imports.iter().any(|(n, _)| *n == name) {
+                continue;
+            }
+            match self.import(&location, index, &name) {
+                Some(imported) => self.files[index].imports.push((name, imported)),
+                None => self.errors.push(Diagnostic {
+                    file: index,
+                    start,
+                    end,
+                    message: format!("Import failed: {name}"),
+                }),
+            }
+        }
+        for (name, start, end) in references.embeds {
+            if self.files[index].embeds.iter().any(|(n, _)| *n == name) {
+                continue;
+            }
+            let contents = self
+                .resolve(&location, index, &name)
+                .and_then(|(location, _)| match location {
+                    Location::Disk(path) => std::fs::read(path).ok(),
+                    Location::Embedded(name) => {
+                        embedded_file(name).map(|source| source.as_bytes().to_vec())
+                    }
+                });
+            match contents {
+                Some(contents) => self.files[index].embeds.push((name, contents)),
+                None => self.errors.push(Diagnostic {
+                    file: index,
+                    start,
+                    end,
+                    message: format!("Embed failed: {name}"),
+                }),
+            }
+        }
+        if let Some((start, end)) = references.stream {
+            if self.stream_file.is_none() {
+                match self.import(&location, index, "/capnp/stream.capnp") {
+                    Some(file) => self.stream_file = Some(file),
+                    None => self.errors.push(Diagnostic {
+                        file: index,
+                        start,
+                        end,
+                        message: "Import failed: /capnp/stream.capnp".into(),
+                    }),
+                }
+            }
+        }
+        index
+    }
+
+    fn import(&mut self, from: &Location, from_index: usize, name: &str) -> Option<usize> {
+        let (location, display_name) = self.resolve(from, from_index, name)?;
+        if let Some(&index) = self.locations.get(&location) {
+            return Some(index);
+        }
+        let source = match &location {
+            Location::Disk(path) => std::fs::read_to_string(path).ok()?,
+            Location::Embedded(name) => embedded_file(name)?.to_string(),
+        };
+        Some(self.load(location, display_name, source))
+    }
+
+    /// Finds the file that `name` refers to from the file at `from`, along with its display name.
+    fn resolve(
+        &self,
+        from: &Location,
+        from_index: usize,
+        name: &str,
+    ) -> Option<(Location, String)> {
+        if let Some(absolute) = name.strip_prefix('/') {
+            let absolute = normalize(absolute);
+            let standard: &[&str] = if self.compiler.no_standard_import {
+                &[]
+            } else {
+                &["/usr/local/include", "/usr/include"]
+            };
+            let dirs = self
+                .compiler
+                .import_paths
+                .iter()
+                .cloned()
+                .chain(standard.iter().map(PathBuf::from));
+            for dir in dirs {
+                let path = dir.join(&absolute);
+                if path.is_file() {
+                    let path = path.canonicalize().unwrap_or(path);
+                    return Some((Location::Disk(path), absolute));
+                }
+            }
+            let (embedded, _) = EMBEDDED_FILES.iter().find(|(path, _)| *path == absolute)?;
+            return Some((Location::Embedded(embedded), absolute));
+        }
+
+        let display_dir = match self.files[from_index].display_name.rfind('/') {
+            Some(i) => &self.files[from_index].display_name[..=i],
+            None => "",
+        };
+        let display_name = normalize(&format!("{display_dir}{name}"));
+        match from {
+            Location::Disk(path) => {
+                let path = path.parent().unwrap_or(Path::new("")).join(name);
+                if !path.is_file() {
+                    return None;
+                }
+                let path = path.canonicalize().unwrap_or(path);
+                Some((Location::Disk(path), display_name))
+            }
+            Location::Embedded(_) => {
+                let (embedded, _) = EMBEDDED_FILES
+                    .iter()
+                    .find(|(path, _)| *path == display_name)?;
+                Some((Location::Embedded(embedded), display_name))
+            }
+        }
+    }
+}
+
+fn embedded_file(name: &str) -> Option<&'static str> {
+    EMBEDDED_FILES
+        .iter()
+        .find(|(path, _)| *path == name)
+        .map(|(_, source)| *source)
+}
+
+/// Removes `.` components from a `/`-separated path, and resolves `..` components where possible.
+fn normalize(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." if matches!(components.last(), Some(last) if *last != "..") => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components.join("/")
+}
+
+/// Converts a byte offset into one-based line and column numbers.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// The other files a parsed file refers to, with the locations of the references.
+#[derive(Default)]
+struct References {
+    imports: Vec<(String, usize, usize)>,
+    embeds: Vec<(String, usize, usize)>,
+    /// The first method that returns `stream`.
+    stream: Option<(usize, usize)>,
+}
+
+impl References {
+    fn declaration(&mut self, decl: &Declaration) {
+        self.annotations(&decl.annotations);
+        match &decl.kind {
+            DeclKind::Using(target) => self.expression(target),
+            DeclKind::Const { type_, value } => {
+                self.expression(type_);
+                self.expression(value);
+            }
+            DeclKind::Field { type_, default } => {
+                self.expression(type_);
+                if let Some(default) = default {
+                    self.expression(default);
+                }
+            }
+            DeclKind::Interface { superclasses } => {
+                for superclass in superclasses {
+                    self.expression(superclass);
+                }
+            }
+            DeclKind::Method { params, results } => {
+                self.param_list(params);
+                if let Some(results) = results {
+                    self.param_list(results);
+                }
+            }
+            DeclKind::Annotation { type_, .. } => self.expression(type_),
+            DeclKind::File
+            | DeclKind::Enum
+            | DeclKind::Enumerant
+            | DeclKind::Struct
+            | DeclKind::Union
+            | DeclKind::Group => (),
+        }
+        for nested in &decl.nested {
+            self.declaration(nested);
+        }
+    }
+
+    fn param_list(&mut self, params: &ParamList) {
+        match params {
+            ParamList::Named(params) => {
+                for param in params {
+                    self.expression(&param.type_);
+                    if let Some(default) = &param.default {
+                        self.expression(default);
+                    }
+                    self.annotations(&param.annotations);
+                }
+            }
+            ParamList::Type(type_) => self.expression(type_),
+            ParamList::Stream(start, end) => {
+                if self.stream.is_none() {
+                    self.stream = Some((*start, *end));
+                }
+            }
+        }
+    }
+
+    fn annotations(&mut self, annotations: &[AnnotationApplication]) {
+        for annotation in annotations {
+            self.expression(&annotation.name);
+            if let Some(value) = &annotation.value {
+                self.expression(value);
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Import(name) => self.imports.push((name.clone(), expr.start, expr.end)),
+            ExpressionKind::Embed(name) => self.embeds.push((name.clone(), expr.start, expr.end)),
+            ExpressionKind::List(items) => {
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            ExpressionKind::Tuple(params) => {
+                for param in params {
+                    self.expression(&param.value);
+                }
+            }
+            ExpressionKind::Application(base, params) => {
+                self.expression(base);
+                for param in params {
+                    self.expression(&param.value);
+                }
+            }
+            ExpressionKind::Member(base, _) => self.expression(base),
+            ExpressionKind::PositiveInt(_)
+            | ExpressionKind::NegativeInt(_)
+            | ExpressionKind::Float(_)
+            | ExpressionKind::String(_)
+            | ExpressionKind::Binary(_)
+            | ExpressionKind::RelativeName(_)
+            | ExpressionKind::AbsoluteName(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compiler;
+    use capnp::schema_capnp::{code_generator_request, node};
+
+    fn write_schema(name: &str, source: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("capnpc-compiler-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn compile_struct() -> capnp::Result<()> {
+        let path = write_schema(
+            "foo.capnp",
+            "@0xd4ab2c4fa2f1fc51;\n\
+             struct Foo {\n\
+               a @0 :UInt16;\n\
+               b @1 :Text = \"b\";\n\
+               union {\n\
+                 c @2 :UInt64;\n\
+                 d @3 :Void;\n\
+               }\n\
+             }\n",
+        );
+        let message = Compiler::new()
+            .src_prefix(path.parent().unwrap())
+            .file(&path)
+            .compile()?;
+        let request = message.get_root_as_reader::<code_generator_request::Reader>()?;
+        let requested = request.get_requested_files()?.get(0);
+        assert_eq!(requested.get_id(), 0xd4ab2c4fa2f1fc51);
+        assert_eq!(requested.get_filename()?, "foo.capnp");
+
+        let foo = request
+            .get_nodes()?
+            .iter()
+            .find(|n| n.get_display_name().ok() == Some("foo.capnp:Foo"))
+            .unwrap();
+        assert_eq!(foo.get_display_name_prefix_length(), 10);
+        assert_eq!(foo.get_scope_id(), 0xd4ab2c4fa2f1fc51);
+        let node::Struct(foo) = foo.which()? else {
+            panic!("expected a struct");
+        };
+        assert_eq!(foo.get_data_word_count(), 2);
+        assert_eq!(foo.get_pointer_count(), 1);
+        assert_eq!(foo.get_discriminant_count(), 2);
+        assert_eq!(foo.get_discriminant_offset(), 1);
+        let fields = foo.get_fields()?;
+        assert_eq!(fields.len(), 4);
+        let capnp::schema_capnp::field::Slot(c) = fields.get(2).which()? else {
+            panic!("expected a slot");
+        };
+        assert_eq!(c.get_offset(), 1);
+        let capnp::schema_capnp::field::Slot(b) = fields.get(1).which()? else {
+            panic!("expected a slot");
+        };
+        assert!(b.get_had_explicit_default());
+        Ok(())
+    }
+
+    #[test]
+    fn compile_errors() {
+        let path = write_schema(
+            "errors.capnp",
+            "@0xd4ab2c4fa2f1fc52;\n\
+             struct Foo {\n\
+               a @0 :Bar;\n\
+               b @2 :UInt8 = 300;\n\
+             }\n",
+        );
+        let Err(error) = Compiler::new()
+            .src_prefix(path.parent().unwrap())
+            .file(&path)
+            .compile()
+        else {
+            panic!("expected errors");
+        };
+        assert_eq!(
+            error.description,
+            "errors.capnp:3:7-10: error: Not defined: Bar\n\
+             errors.capnp:4:4-5: error: Skipped ordinal @1.  Ordinals must be sequential with no holes.\n\
+             errors.capnp:4:15-18: error: Integer value out of range."
+        );
+    }
+}
@@ -0,0 +1,864 @@
+//! Parses lexed statements into declarations.
+
+use super::lexer::{Statement, Token, TokenKind};
+use super::SyntaxError;
+
+#[derive(Clone, Debug)]
+pub struct Located<T> {
+    pub value: T,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExpressionKind {
+    PositiveInt(u64),
+    NegativeInt(u64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    /// A name to look up in the enclosing scopes.
+    RelativeName(String),
+    /// `.name`, looked up at the top level of the file.
+    AbsoluteName(String),
+    Import(String),
+    Embed(String),
+    List(Vec<Expression>),
+    Tuple(Vec<Param>),
+    /// Generic parameters applied to a declaration, as in `List(Text)`.
+    Application(Box<Expression>, Vec<Param>),
+    Member(Box<Expression>, Located<String>),
+}
+
+/// An element of a tuple or application, which may be named as in `(a = 1, b = 2)`.
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: Option<Located<String>>,
+    pub value: Expression,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnnotationApplication {
+    pub name: Expression,
+    pub value: Option<Expression>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DeclId {
+    Unspecified,
+    /// `@0x...` on a node.
+    Uid(u64, usize, usize),
+    /// `@n` on a field, enumerant, method, or union.
+    Ordinal(u64, usize, usize),
+}
+
+#[derive(Debug)]
+pub struct Declaration {
+    pub name: Located<String>,
+    pub id: DeclId,
+    /// Generic parameters of a struct or interface, or implicit parameters of a method.
+    pub parameters: Vec<Located<String>>,
+    pub annotations: Vec<AnnotationApplication>,
+    pub doc_comment: Option<String>,
+    pub nested: Vec<Declaration>,
+    pub kind: DeclKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug)]
+pub enum DeclKind {
+    File,
+    Using(Expression),
+    Const {
+        type_: Expression,
+        value: Expression,
+    },
+    Enum,
+    Enumerant,
+    Struct,
+    Field {
+        type_: Expression,
+        default: Option<Expression>,
+    },
+    Union,
+    Group,
+    Interface {
+        superclasses: Vec<Expression>,
+    },
+    Method {
+        params: ParamList,
+        results: Option<ParamList>,
+    },
+    Annotation {
+        type_: Expression,
+        targets: Vec<Located<String>>,
+    },
+}
+
+#[derive(Debug)]
+pub enum ParamList {
+    Named(Vec<ParamDecl>),
+    Type(Expression),
+    Stream(usize, usize),
+}
+
+#[derive(Debug)]
+pub struct ParamDecl {
+    pub name: Located<String>,
+    pub type_: Expression,
+    pub default: Option<Expression>,
+    pub annotations: Vec<AnnotationApplication>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parses a file whose source is `len` bytes long.
+pub fn parse_file(statements: Vec<Statement>, len: usize) -> Result<Declaration, SyntaxError> {
+    let mut file = Declaration {
+        name: Located {
+            value: String::new(),
+            start: 0,
+            end: 0,
+        },
+        id: DeclId::Unspecified,
+        parameters: Vec::new(),
+        annotations: Vec::new(),
+        doc_comment: None,
+        nested: Vec::new(),
+        kind: DeclKind::File,
+        start: 0,
+        end: len,
+    };
+    for statement in statements {
+        let mut tokens = Tokens::new(&statement.tokens, statement.start, statement.end);
+        if tokens.at_operator("@") {
+            let id = tokens.id()?;
+            tokens.expect_end()?;
+            if !matches!(id, DeclId::Uid(..)) {
+                return Err(
+                    tokens.error("File ID must be a 64-bit hex number like @0xdbb9ad1f14bf0b36.")
+                );
+            }
+            if matches!(file.id, DeclId::Uid(..)) {
+                return Err(tokens.error("File can only have one ID."));
+            }
+            file.id = id;
+            if statement.doc_comment.is_some() {
+                file.doc_comment = statement.doc_comment;
+            }
+        } else if tokens.at_operator("$") {
+            file.annotations.extend(tokens.annotations()?);
+            tokens.expect_end()?;
+        } else {
+            file.nested.push(parse_declaration(statement, Scope::File)?);
+        }
+    }
+    Ok(file)
+}
+
+/// The kind of declaration whose body a statement appears in, which determines which kinds of
+/// declarations the statement may be.
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    File,
+    Struct,
+    Group,
+    Union,
+    Enum,
+    Interface,
+}
+
+fn parse_declaration(statement: Statement, scope: Scope) -> Result<Declaration, SyntaxError> {
+    let Statement {
+        tokens,
+        block,
+        doc_comment,
+        start,
+        end,
+    } = statement;
+    let mut tokens = Tokens::new(&tokens, start, end);
+
+    let has_block = block.is_some();
+    let (kind, name, id, parameters, nested_scope) = match tokens.peek_identifier() {
+        Some("struct") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let name = tokens.name()?;
+            let id = tokens.optional_id()?;
+            let parameters = tokens.optional_parameters()?;
+            (DeclKind::Struct, name, id, parameters, Some(Scope::Struct))
+        }
+        Some("interface") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let name = tokens.name()?;
+            let id = tokens.optional_id()?;
+            let parameters = tokens.optional_parameters()?;
+            let mut superclasses = Vec::new();
+            if tokens.peek_identifier() == Some("extends") {
+                tokens.next();
+                for item in tokens.parenthesized()? {
+                    superclasses.push(expression_from(&item, tokens.end)?);
+                }
+            }
+            (
+                DeclKind::Interface { superclasses },
+                name,
+                id,
+                parameters,
+                Some(Scope::Interface),
+            )
+        }
+        Some("enum") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let name = tokens.name()?;
+            let id = tokens.optional_id()?;
+            (DeclKind::Enum, name, id, Vec::new(), Some(Scope::Enum))
+        }
+        Some("const") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let name = tokens.name()?;
+            let id = tokens.optional_id()?;
+            tokens.expect_operator(":")?;
+            let type_ = tokens.expression()?;
+            tokens.expect_operator("=")?;
+            let value = tokens.expression()?;
+            (DeclKind::Const { type_, value }, name, id, Vec::new(), None)
+        }
+        Some("annotation") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let name = tokens.name()?;
+            let id = tokens.optional_id()?;
+            let mut targets = Vec::new();
+            for item in tokens.parenthesized()? {
+                match &item[..] {
+                    [Token {
+                        kind: TokenKind::Identifier(target) | TokenKind::Operator(target),
+                        start,
+                        end,
+                    }] => targets.push(Located {
+                        value: target.clone(),
+                        start: *start,
+                        end: *end,
+                    }),
+                    _ => return Err(error_at(&item, tokens.end, "Expected annotation target.")),
+                }
+            }
+            tokens.expect_operator(":")?;
+            let type_ = tokens.expression()?;
+            (
+                DeclKind::Annotation { type_, targets },
+                name,
+                id,
+                Vec::new(),
+                None,
+            )
+        }
+        Some("using") if scope_allows_nodes(scope) => {
+            tokens.next();
+            let (name, target) = if tokens.peek_operator_at(1) == Some("=") {
+                let name = tokens.name()?;
+                tokens.next();
+                (name, tokens.expression()?)
+            } else {
+                let target = tokens.expression()?;
+                let name = match &target.kind {
+                    ExpressionKind::RelativeName(name) | ExpressionKind::AbsoluteName(name) => {
+                        Located {
+                            value: name.clone(),
+                            start: target.start,
+                            end: target.end,
+                        }
+                    }
+                    ExpressionKind::Member(_, name) => name.clone(),
+                    _ => {
+                        return Err(SyntaxError {
+                            start: target.start,
+                            end: target.end,
+                            message: "'using' declaration without '=' must name a declaration."
+                                .into(),
+                        })
+                    }
+                };
+                (name, target)
+            };
+            (
+                DeclKind::Using(target),
+                name,
+                DeclId::Unspecified,
+                Vec::new(),
+                None,
+            )
+        }
+        Some("union") if scope_allows_members(scope) => {
+            tokens.next();
+            let name = Located {
+                value: String::new(),
+                start,
+                end: start,
+            };
+            (
+                DeclKind::Union,
+                name,
+                DeclId::Unspecified,
+                Vec::new(),
+                Some(Scope::Union),
+            )
+        }
+        Some(_) if scope == Scope::Enum => {
+            let name = tokens.name()?;
+            let id = tokens.ordinal()?;
+            (DeclKind::Enumerant, name, id, Vec::new(), None)
+        }
+        Some(_) if scope == Scope::Interface => {
+            let name = tokens.name()?;
+            let id = tokens.ordinal()?;
+            let mut parameters = Vec::new();
+            if let Some(TokenKind::Bracketed(items)) = tokens.peek_kind() {
+                let items = items.clone();
+                tokens.next();
+                for item in items {
+                    parameters.push(single_identifier(&item, tokens.end)?);
+                }
+            }
+            let params = tokens.param_list()?;
+            let results = if tokens.at_operator("->") {
+                tokens.next();
+                Some(tokens.param_list()?)
+            } else {
+                None
+            };
+            (
+                DeclKind::Method { params, results },
+                name,
+                id,
+                parameters,
+                None,
+            )
+        }
+        Some(_) if scope_allows_members(scope) => {
+            let name = tokens.name()?;
+            let id = if tokens.at_operator("@") {
+                let id = tokens.ordinal()?;
+                // A `!` after a union's ordinal is accepted for compatibility with old schemas.
+                if tokens.at_operator("!") {
+                    tokens.next();
+                }
+                id
+            } else {
+                DeclId::Unspecified
+            };
+            tokens.expect_operator(":")?;
+            match tokens.peek_identifier() {
+                Some("union") if has_block => {
+                    tokens.next();
+                    (DeclKind::Union, name, id, Vec::new(), Some(Scope::Union))
+                }
+                Some("group") if has_block => {
+                    tokens.next();
+                    if !matches!(id, DeclId::Unspecified) {
+                        return Err(tokens.error("Groups don't have ordinals."));
+                    }
+                    (DeclKind::Group, name, id, Vec::new(), Some(Scope::Group))
+                }
+                _ => {
+                    if !matches!(id, DeclId::Ordinal(..)) {
+                        return Err(SyntaxError {
+                            start: name.start,
+                            end: name.end,
+                            message: "Missing ordinal for field.".into(),
+                        });
+                    }
+                    let type_ = tokens.expression()?;
+                    let default = if tokens.at_operator("=") {
+                        tokens.next();
+                        Some(tokens.expression()?)
+                    } else {
+                        None
+                    };
+                    (
+                        DeclKind::Field { type_, default },
+                        name,
+                        id,
+                        Vec::new(),
+                        None,
+                    )
+                }
+            }
+        }
+        _ => return Err(tokens.error("Parse error.")),
+    };
+
+    let annotations = tokens.annotations()?;
+    tokens.expect_end()?;
+
+    let nested = match (nested_scope, block) {
+        (Some(nested_scope), Some(block)) => block
+            .into_iter()
+            .map(|statement| parse_declaration(statement, nested_scope))
+            .collect::<Result<Vec<_>, _>>()?,
+        (Some(_), None) => {
+            return Err(SyntaxError {
+                start,
+                end,
+                message: "Expected '{'.".into(),
+            })
+        }
+        (None, Some(_)) => {
+            return Err(SyntaxError {
+                start,
+                end,
+                message: "This kind of declaration doesn't have a body.".into(),
+            })
+        }
+        (None, None) => Vec::new(),
+    };
+
+    Ok(Declaration {
+        name,
+        id,
+        parameters,
+        annotations,
+        doc_comment,
+        nested,
+        kind,
+        start,
+        end,
+    })
+}
+
+fn scope_allows_nodes(scope: Scope) -> bool {
+    matches!(scope, Scope::File | Scope::Struct | Scope::Interface)
+}
+
+fn scope_allows_members(scope: Scope) -> bool {
+    matches!(scope, Scope::Struct | Scope::Group | Scope::Union)
+}
+
+fn error_at(tokens: &[Token], fallback: usize, message: &str) -> SyntaxError {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => SyntaxError {
+            start: first.start,
+            end: last.end,
+            message: message.into(),
+        },
+        _ => SyntaxError {
+            start: fallback,
+            end: fallback,
+            message: message.into(),
+        },
+    }
+}
+
+fn single_identifier(tokens: &[Token], fallback: usize) -> Result<Located<String>, SyntaxError> {
+    match tokens {
+        [Token {
+            kind: TokenKind::Identifier(name),
+            start,
+            end,
+        }] => Ok(Located {
+            value: name.clone(),
+            start: *start,
+            end: *end,
+        }),
+        _ => Err(error_at(tokens, fallback, "Expected identifier.")),
+    }
+}
+
+/// Parses a whole token sequence as one expression.
+fn expression_from(tokens: &[Token], fallback: usize) -> Result<Expression, SyntaxError> {
+    if tokens.is_empty() {
+        return Err(error_at(tokens, fallback, "Expected expression."));
+    }
+    let mut tokens = Tokens::new(tokens, fallback, fallback);
+    let expression = tokens.expression()?;
+    tokens.expect_end()?;
+    Ok(expression)
+}
+
+/// Parses the elements of a tuple, or the arguments of an application.
+fn params_from(items: &[Vec<Token>], fallback: usize) -> Result<Vec<Param>, SyntaxError> {
+    let mut params = Vec::new();
+    for item in items {
+        let mut tokens = Tokens::new(item, fallback, fallback);
+        let name = if tokens.peek_operator_at(1) == Some("=") {
+            let name = tokens.name()?;
+            tokens.next();
+            Some(name)
+        } else {
+            None
+        };
+        let value = tokens.expression()?;
+        tokens.expect_end()?;
+        params.push(Param { name, value });
+    }
+    Ok(params)
+}
+
+/// A cursor over the tokens of one statement or list element.
+struct Tokens<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    /// Where the enclosing statement starts and ends, for errors about missing tokens.
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(tokens: &'a [Token], start: usize, end: usize) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            start,
+            end,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_kind(&self) -> Option<&'a TokenKind> {
+        self.peek().map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_identifier(&self) -> Option<&'a str> {
+        match self.peek_kind() {
+            Some(TokenKind::Identifier(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn peek_operator_at(&self, offset: usize) -> Option<&'a str> {
+        match self.tokens.get(self.pos + offset).map(|t| &t.kind) {
+            Some(TokenKind::Operator(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn at_operator(&self, op: &str) -> bool {
+        self.peek_operator_at(0) == Some(op)
+    }
+
+    fn error(&self, message: &str) -> SyntaxError {
+        match self.peek() {
+            Some(token) => SyntaxError {
+                start: token.start,
+                end: token.end,
+                message: message.into(),
+            },
+            None => SyntaxError {
+                start: self.tokens.last().map_or(self.start, |t| t.start),
+                end: self.tokens.last().map_or(self.end, |t| t.end),
+                message: message.into(),
+            },
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), SyntaxError> {
+        if self.pos < self.tokens.len() {
+            Err(self.error("Parse error."))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn expect_operator(&mut self, op: &str) -> Result<(), SyntaxError> {
+        if self.at_operator(op) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{op}'.")))
+        }
+    }
+
+    fn name(&mut self) -> Result<Located<String>, SyntaxError> {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                start,
+                end,
+            }) => {
+                self.pos += 1;
+                Ok(Located {
+                    value: name.clone(),
+                    start: *start,
+                    end: *end,
+                })
+            }
+            _ => Err(self.error("Expected identifier.")),
+        }
+    }
+
+    /// Parses `@` followed by an integer.
+    fn id(&mut self) -> Result<DeclId, SyntaxError> {
+        self.expect_operator("@")?;
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Integer(value),
+                start,
+                end,
+            }) => {
+                self.pos += 1;
+                if *value >= 1 << 63 {
+                    Ok(DeclId::Uid(*value, *start, *end))
+                } else {
+                    Ok(DeclId::Ordinal(*value, *start, *end))
+                }
+            }
+            _ => Err(self.error("Expected integer.")),
+        }
+    }
+
+    fn optional_id(&mut self) -> Result<DeclId, SyntaxError> {
+        if !self.at_operator("@") {
+            return Ok(DeclId::Unspecified);
+        }
+        match self.id()? {
+            DeclId::Ordinal(_, start, end) => Err(SyntaxError {
+                start,
+                end,
+                message: "Invalid ID.  Please generate a new one with 'capnpc -i'.".into(),
+            }),
+            id => Ok(id),
+        }
+    }
+
+    fn ordinal(&mut self) -> Result<DeclId, SyntaxError> {
+        if !self.at_operator("@") {
+            return Err(self.error("Missing ordinal."));
+        }
+        match self.id()? {
+            DeclId::Uid(_, start, end) => Err(SyntaxError {
+                start,
+                end,
+                message: "Ordinal must be a 16-bit integer.".into(),
+            }),
+            id => Ok(id),
+        }
+    }
+
+    fn parenthesized(&mut self) -> Result<Vec<Vec<Token>>, SyntaxError> {
+        match self.peek_kind() {
+            Some(TokenKind::Parenthesized(items)) => {
+                self.pos += 1;
+                Ok(items.clone())
+            }
+            _ => Err(self.error("Expected '('.")),
+        }
+    }
+
+    fn optional_parameters(&mut self) -> Result<Vec<Located<String>>, SyntaxError> {
+        if !matches!(self.peek_kind(), Some(TokenKind::Parenthesized(_))) {
+            return Ok(Vec::new());
+        }
+        let end = self.end;
+        self.parenthesized()?
+            .iter()
+            .map(|item| single_identifier(item, end))
+            .collect()
+    }
+
+    fn annotations(&mut self) -> Result<Vec<AnnotationApplication>, SyntaxError> {
+        let mut annotations = Vec::new();
+        while self.at_operator("$") {
+            self.pos += 1;
+            let expression = self.expression()?;
+            annotations.push(match expression.kind {
+                // The value was parsed as an application of the annotation's name.
+                ExpressionKind::Application(function, mut params) => {
+                    let value = if params.len() == 1 && params[0].name.is_none() {
+                        params.pop().unwrap().value
+                    } else {
+                        let start = function.end;
+                        Expression {
+                            kind: ExpressionKind::Tuple(params),
+                            start,
+                            end: expression.end,
+                        }
+                    };
+                    AnnotationApplication {
+                        name: *function,
+                        value: Some(value),
+                    }
+                }
+                _ => AnnotationApplication {
+                    name: expression,
+                    value: None,
+                },
+            });
+        }
+        Ok(annotations)
+    }
+
+    fn param_list(&mut self) -> Result<ParamList, SyntaxError> {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Parenthesized(items),
+                ..
+            }) => {
+                self.pos += 1;
+                let mut params = Vec::new();
+                for item in items {
+                    let mut tokens = Tokens::new(item, self.start, self.end);
+                    let name = tokens.name()?;
+                    tokens.expect_operator(":")?;
+                    let type_ = tokens.expression()?;
+                    let default = if tokens.at_operator("=") {
+                        tokens.next();
+                        Some(tokens.expression()?)
+                    } else {
+                        None
+                    };
+                    let annotations = tokens.annotations()?;
+                    tokens.expect_end()?;
+                    params.push(ParamDecl {
+                        start: name.start,
+                        end: item.last().map_or(name.end, |t| t.end),
+                        name,
+                        type_,
+                        default,
+                        annotations,
+                    });
+                }
+                Ok(ParamList::Named(params))
+            }
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                start,
+                end,
+            }) if name == "stream" => {
+                self.pos += 1;
+                Ok(ParamList::Stream(*start, *end))
+            }
+            _ => Ok(ParamList::Type(self.expression()?)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, SyntaxError> {
+        let Some(token) = self.next() else {
+            return Err(self.error("Expected expression."));
+        };
+        let start = token.start;
+        let mut end = token.end;
+        let kind = match &token.kind {
+            TokenKind::Integer(value) => ExpressionKind::PositiveInt(*value),
+            TokenKind::Float(value) => ExpressionKind::Float(*value),
+            TokenKind::String(value) => ExpressionKind::String(value.clone()),
+            TokenKind::Binary(value) => ExpressionKind::Binary(value.clone()),
+            TokenKind::Operator(op) if op == "-" => {
+                let Some(next) = self.next() else {
+                    return Err(self.error("Expected number."));
+                };
+                end = next.end;
+                match &next.kind {
+                    TokenKind::Integer(value) => ExpressionKind::NegativeInt(*value),
+                    TokenKind::Float(value) => ExpressionKind::Float(-value),
+                    TokenKind::Identifier(name) if name == "inf" => {
+                        ExpressionKind::Float(f64::NEG_INFINITY)
+                    }
+                    _ => {
+                        return Err(SyntaxError {
+                            start: next.start,
+                            end: next.end,
+                            message: "Expected number.".into(),
+                        })
+                    }
+                }
+            }
+            TokenKind::Operator(op) if op == "." => {
+                let name = self.name()?;
+                end = name.end;
+                ExpressionKind::AbsoluteName(name.value)
+            }
+            TokenKind::Identifier(keyword) if keyword == "import" || keyword == "embed" => {
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::String(path),
+                        end: path_end,
+                        ..
+                    }) => {
+                        end = *path_end;
+                        if keyword == "import" {
+                            ExpressionKind::Import(path.clone())
+                        } else {
+                            ExpressionKind::Embed(path.clone())
+                        }
+                    }
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("Expected string literal."));
+                    }
+                }
+            }
+            TokenKind::Identifier(name) => ExpressionKind::RelativeName(name.clone()),
+            TokenKind::Bracketed(items) => ExpressionKind::List(
+                items
+                    .iter()
+                    .map(|item| expression_from(item, token.start))
+                    .collect::<Result<_, _>>()?,
+            ),
+            TokenKind::Parenthesized(items) => {
+                ExpressionKind::Tuple(params_from(items, token.start)?)
+            }
+            TokenKind::Operator(_) => {
+                self.pos -= 1;
+                return Err(self.error("Expected expression."));
+            }
+        };
+        let mut expression = Expression { kind, start, end };
+
+        // Suffixes: member access and application.
+        loop {
+            match self.peek() {
+                Some(Token {
+                    kind: TokenKind::Operator(op),
+                    ..
+                }) if op == "." => {
+                    self.pos += 1;
+                    let name = self.name()?;
+                    let end = name.end;
+                    expression = Expression {
+                        kind: ExpressionKind::Member(Box::new(expression), name),
+                        start,
+                        end,
+                    };
+                }
+                Some(Token {
+                    kind: TokenKind::Parenthesized(items),
+                    start: params_start,
+                    end,
+                }) if matches!(
+                    expression.kind,
+                    ExpressionKind::RelativeName(_)
+                        | ExpressionKind::AbsoluteName(_)
+                        | ExpressionKind::Member(..)
+                        | ExpressionKind::Import(_)
+                        | ExpressionKind::Application(..)
+                ) =>
+                {
+                    self.pos += 1;
+                    let params = params_from(items, *params_start)?;
+                    expression = Expression {
+                        kind: ExpressionKind::Application(Box::new(expression), params),
+                        start,
+                        end: *end,
+                    };
+                }
+                _ => return Ok(expression),
+            }
+        }
+    }
+}
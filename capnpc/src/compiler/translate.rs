@@ -0,0 +1,2834 @@
+//! Turns parsed files into `schema.capnp` nodes: resolves names and generic brands, assigns IDs,
+//! lays out structs, and evaluates values.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use capnp::message;
+use capnp::private::layout::{
+    ElementSize, PointerBuilder, PrimitiveElement, StructBuilder, StructSize,
+};
+use capnp::schema_capnp::{annotation, brand, code_generator_request, node, type_, value};
+use capnp::traits::FromPointerBuilder;
+use capnp::{any_pointer, struct_list};
+
+use super::layout::{self, StructLayout};
+use super::md5;
+use super::parser::{
+    AnnotationApplication, DeclId, DeclKind, Declaration, Expression, ExpressionKind, Located,
+    Param, ParamDecl, ParamList,
+};
+use super::{Diagnostic, SourceFile};
+
+/// Translates all of `files`, producing a request to generate code for the `requested` ones.
+pub fn translate(
+    files: &[SourceFile],
+    requested: &[usize],
+    stream_file: Option<usize>,
+) -> Result<message::Builder<message::HeapAllocator>, Vec<Diagnostic>> {
+    let mut translator = Translator {
+        files,
+        stream_file,
+        nodes: Vec::new(),
+        file_nodes: Vec::new(),
+        aliases: Vec::new(),
+        structs: HashMap::new(),
+        aux_nodes: HashMap::new(),
+        const_values: HashMap::new(),
+        annotation_types: HashMap::new(),
+        field_defaults: HashMap::new(),
+        errors: Vec::new(),
+    };
+    for file in 0..files.len() {
+        let node = translator.add_file(file);
+        translator.file_nodes.push(node);
+    }
+    for node in 0..translator.nodes.len() {
+        match translator.nodes[node].kind {
+            NodeKind::Struct => translator.translate_struct(node),
+            NodeKind::Interface => translator.translate_param_structs(node),
+            _ => (),
+        }
+    }
+    translator.compile_field_defaults();
+
+    let mut message = message::Builder::new_default();
+    let mut request = message.init_root::<code_generator_request::Builder>();
+    let count = translator.nodes.len() + translator.aux_nodes.values().map(Vec::len).sum::<usize>();
+    let mut nodes = request.reborrow().init_nodes(count as u32);
+    let mut source_infos = Vec::with_capacity(count);
+    for node in 0..translator.nodes.len() {
+        let mut source_info = SourceInfo::new(translator.nodes[node].id);
+        translator.write_node(
+            node,
+            nodes.reborrow().get(source_infos.len() as u32),
+            &mut source_info,
+        );
+        source_infos.push(source_info);
+        let aux = translator.aux_nodes.get(&node).cloned().unwrap_or_default();
+        for id in aux {
+            let mut source_info = SourceInfo::new(id);
+            translator.write_aux_node(
+                id,
+                nodes.reborrow().get(source_infos.len() as u32),
+                &mut source_info,
+            );
+            source_infos.push(source_info);
+        }
+    }
+    let mut list = request.reborrow().init_source_info(count as u32);
+    for (i, source_info) in source_infos.iter().enumerate() {
+        let mut builder = list.reborrow().get(i as u32);
+        builder.set_id(source_info.id);
+        if let Some(doc_comment) = source_info.doc_comment {
+            builder.set_doc_comment(doc_comment);
+        }
+        let mut members = builder.init_members(source_info.members.len() as u32);
+        for (j, doc_comment) in source_info.members.iter().enumerate() {
+            if let Some(doc_comment) = doc_comment {
+                members
+                    .reborrow()
+                    .get(j as u32)
+                    .set_doc_comment(doc_comment);
+            }
+        }
+    }
+
+    let mut requested_files = request.init_requested_files(requested.len() as u32);
+    for (i, &file) in requested.iter().enumerate() {
+        let mut builder = requested_files.reborrow().get(i as u32);
+        builder.set_id(translator.nodes[translator.file_nodes[file]].id);
+        builder.set_filename(&files[file].display_name);
+        let mut imports = builder.init_imports(files[file].imports.len() as u32);
+        for (j, (name, imported)) in files[file].imports.iter().enumerate() {
+            let mut import = imports.reborrow().get(j as u32);
+            import.set_id(translator.nodes[translator.file_nodes[*imported]].id);
+            import.set_name(name);
+        }
+    }
+
+    if translator.errors.is_empty() {
+        Ok(message)
+    } else {
+        Err(translator.errors)
+    }
+}
+
+fn id_from_bytes(bytes: &[u8]) -> u64 {
+    let digest = md5::digest(bytes);
+    let mut result = 0u64;
+    for byte in &digest[..8] {
+        result = (result << 8) | u64::from(*byte);
+    }
+    result | (1 << 63)
+}
+
+fn child_id(parent_id: u64, name: &str) -> u64 {
+    let mut bytes = parent_id.to_le_bytes().to_vec();
+    bytes.extend_from_slice(name.as_bytes());
+    id_from_bytes(&bytes)
+}
+
+fn group_id(parent_id: u64, index: u16) -> u64 {
+    let mut bytes = parent_id.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&index.to_le_bytes());
+    id_from_bytes(&bytes)
+}
+
+fn method_params_id(parent_id: u64, ordinal: u16, is_results: bool) -> u64 {
+    let mut bytes = parent_id.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&ordinal.to_le_bytes());
+    bytes.push(is_results as u8);
+    id_from_bytes(&bytes)
+}
+
+fn display_name_prefix_length(display_name: &str) -> u32 {
+    let dot = display_name.rfind('.').map_or(0, |i| i + 1);
+    let colon = display_name.rfind(':').map_or(0, |i| i + 1);
+    dot.max(colon) as u32
+}
+
+type NodeId = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    File,
+    Struct,
+    Enum,
+    Interface,
+    Const,
+    Annotation,
+}
+
+/// A declaration that gets its own node, other than groups and parameter lists.
+struct Node<'a> {
+    id: u64,
+    kind: NodeKind,
+    decl: &'a Declaration,
+    file: usize,
+    parent: Option<NodeId>,
+    display_name: String,
+    members: HashMap<&'a str, Member>,
+    nested: Vec<NodeId>,
+}
+
+#[derive(Clone, Copy)]
+enum Member {
+    Node(NodeId),
+    Alias(usize),
+}
+
+struct Alias<'a> {
+    target: &'a Expression,
+    parent: NodeId,
+    state: AliasState,
+}
+
+enum AliasState {
+    Pending,
+    InProgress,
+    Done(Option<BrandedDecl>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Builtin {
+    Void,
+    Bool,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
+    Text,
+    Data,
+    List,
+    AnyPointer,
+    AnyStruct,
+    AnyList,
+    Capability,
+}
+
+impl Builtin {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Void" => Self::Void,
+            "Bool" => Self::Bool,
+            "Int8" => Self::Int8,
+            "Int16" => Self::Int16,
+            "Int32" => Self::Int32,
+            "Int64" => Self::Int64,
+            "UInt8" => Self::UInt8,
+            "UInt16" => Self::UInt16,
+            "UInt32" => Self::UInt32,
+            "UInt64" => Self::UInt64,
+            "Float32" => Self::Float32,
+            "Float64" => Self::Float64,
+            "Text" => Self::Text,
+            "Data" => Self::Data,
+            "List" => Self::List,
+            "AnyPointer" => Self::AnyPointer,
+            "AnyStruct" => Self::AnyStruct,
+            "AnyList" => Self::AnyList,
+            "Capability" => Self::Capability,
+            _ => return None,
+        })
+    }
+}
+
+/// The generic parameter bindings in effect for a declaration and each of its lexical parents.
+struct BrandScope {
+    parent: Option<Rc<BrandScope>>,
+    leaf_id: u64,
+    leaf_param_count: usize,
+    /// Whether the parameters of this scope are whatever they are where the reference appears,
+    /// as is the case for references from within a generic declaration to itself.
+    inherited: bool,
+    params: Vec<BrandedDecl>,
+}
+
+impl BrandScope {
+    fn push(self: &Rc<Self>, leaf_id: u64, leaf_param_count: usize) -> Rc<Self> {
+        Rc::new(Self {
+            parent: Some(self.clone()),
+            leaf_id,
+            leaf_param_count,
+            inherited: false,
+            params: Vec::new(),
+        })
+    }
+
+    fn pop(self: &Rc<Self>, leaf_id: u64) -> Rc<Self> {
+        if self.leaf_id == leaf_id {
+            self.clone()
+        } else if let Some(parent) = &self.parent {
+            parent.pop(leaf_id)
+        } else {
+            Rc::new(Self {
+                parent: None,
+                leaf_id,
+                leaf_param_count: 0,
+                inherited: false,
+                params: Vec::new(),
+            })
+        }
+    }
+
+    /// Returns the binding of a parameter, or `None` if it should stay a parameter.
+    fn lookup_parameter(&self, scope_id: u64, index: u16) -> Option<BrandedDecl> {
+        if self.leaf_id == scope_id {
+            if let Some(param) = self.params.get(index as usize) {
+                Some(param.clone())
+            } else if self.inherited {
+                None
+            } else {
+                Some(BrandedDecl::Builtin(Builtin::AnyPointer))
+            }
+        } else {
+            self.parent.as_ref()?.lookup_parameter(scope_id, index)
+        }
+    }
+
+    /// Returns the bindings of a scope, or `None` if they are inherited.
+    fn get_params(&self, scope_id: u64) -> Option<&[BrandedDecl]> {
+        if self.leaf_id == scope_id {
+            (!self.inherited).then_some(&self.params[..])
+        } else {
+            self.parent.as_ref()?.get_params(scope_id)
+        }
+    }
+
+    /// The scopes that need to be mentioned in a `Brand`.
+    fn levels(&self) -> Vec<&BrandScope> {
+        let mut levels = Vec::new();
+        let mut scope = Some(self);
+        while let Some(s) = scope {
+            if !s.params.is_empty() || (s.inherited && s.leaf_param_count > 0) {
+                levels.push(s);
+            }
+            scope = s.parent.as_deref();
+        }
+        levels
+    }
+}
+
+/// What a name refers to, together with the generic bindings it was reached through.
+#[derive(Clone)]
+enum BrandedDecl {
+    Node(NodeId, Rc<BrandScope>),
+    Builtin(Builtin),
+    List(Box<BrandedDecl>),
+    Param(u64, u16),
+    ImplicitMethodParam(u16),
+}
+
+#[derive(Clone)]
+enum Type {
+    Void,
+    Bool,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
+    Text,
+    Data,
+    List(Box<Type>),
+    Enum(NodeId, Rc<BrandScope>),
+    Struct(NodeId, Rc<BrandScope>),
+    Interface(NodeId, Rc<BrandScope>),
+    AnyPointer(AnyPointer),
+}
+
+#[derive(Clone, Copy)]
+enum AnyPointer {
+    Any,
+    Struct,
+    List,
+    Capability,
+    Parameter(u64, u16),
+    ImplicitMethodParameter(u16),
+}
+
+/// Where a value of some type goes in a struct.
+enum DataSize {
+    Void,
+    /// A data field of `2^n` bits.
+    Data(u32),
+    Pointer,
+}
+
+impl Type {
+    fn data_size(&self) -> DataSize {
+        match self {
+            Self::Void => DataSize::Void,
+            Self::Bool => DataSize::Data(0),
+            Self::Int8 | Self::UInt8 => DataSize::Data(3),
+            Self::Int16 | Self::UInt16 | Self::Enum(..) => DataSize::Data(4),
+            Self::Int32 | Self::UInt32 | Self::Float32 => DataSize::Data(5),
+            Self::Int64 | Self::UInt64 | Self::Float64 => DataSize::Data(6),
+            _ => DataSize::Pointer,
+        }
+    }
+
+    fn int_range(&self) -> Option<(i128, i128)> {
+        Some(match self {
+            Self::Int8 => (i8::MIN.into(), i8::MAX.into()),
+            Self::Int16 => (i16::MIN.into(), i16::MAX.into()),
+            Self::Int32 => (i32::MIN.into(), i32::MAX.into()),
+            Self::Int64 => (i64::MIN.into(), i64::MAX.into()),
+            Self::UInt8 => (0, u8::MAX.into()),
+            Self::UInt16 => (0, u16::MAX.into()),
+            Self::UInt32 => (0, u32::MAX.into()),
+            Self::UInt64 => (0, u64::MAX.into()),
+            _ => return None,
+        })
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self, Self::Float32 | Self::Float64)
+    }
+
+    /// Whether a constant of type `self` can be used where `other` is expected.
+    fn matches(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::List(a), Self::List(b)) => a.matches(b),
+            (Self::Enum(a, _), Self::Enum(b, _))
+            | (Self::Struct(a, _), Self::Struct(b, _))
+            | (Self::Interface(a, _), Self::Interface(b, _)) => a == b,
+            (Self::AnyPointer(_), _) | (_, Self::AnyPointer(_)) => true,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Value {
+    Void,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Enum(u16),
+    Text(String),
+    Data(Vec<u8>),
+    /// The element type, and the elements.
+    List(Type, Vec<Value>),
+    /// The ID of a struct or group, and the values of some of its fields by index.
+    Struct(u64, Vec<(usize, Value)>),
+    Null,
+}
+
+impl Value {
+    fn default_for(type_: &Type) -> Self {
+        match type_ {
+            Type::Void => Self::Void,
+            Type::Bool => Self::Bool(false),
+            Type::Float32 | Type::Float64 => Self::Float(0.0),
+            Type::Enum(..) => Self::Enum(0),
+            t if t.int_range().is_some() => Self::Int(0),
+            _ => Self::Null,
+        }
+    }
+
+    /// The bits of a data field holding this value.
+    fn bits(&self, type_: &Type) -> u64 {
+        match (self, type_) {
+            (Self::Bool(b), _) => *b as u64,
+            (Self::Int(i), _) => *i as u64,
+            (Self::Float(f), Type::Float32) => u64::from((*f as f32).to_bits()),
+            (Self::Float(f), _) => f.to_bits(),
+            (Self::Enum(e), _) => u64::from(*e),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    File,
+    Const,
+    Enum,
+    Enumerant,
+    Struct,
+    Field,
+    Union,
+    Group,
+    Interface,
+    Method,
+    Param,
+    Annotation,
+}
+
+impl Target {
+    const ALL: [Target; 12] = [
+        Self::File,
+        Self::Const,
+        Self::Enum,
+        Self::Enumerant,
+        Self::Struct,
+        Self::Field,
+        Self::Union,
+        Self::Group,
+        Self::Interface,
+        Self::Method,
+        Self::Param,
+        Self::Annotation,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Const => "const",
+            Self::Enum => "enum",
+            Self::Enumerant => "enumerant",
+            Self::Struct => "struct",
+            Self::Field => "field",
+            Self::Union => "union",
+            Self::Group => "group",
+            Self::Interface => "interface",
+            Self::Method => "method",
+            Self::Param => "param",
+            Self::Annotation => "annotation",
+        }
+    }
+}
+
+/// Everything needed to resolve names that appear in some declaration.
+#[derive(Clone)]
+struct Ctx<'a> {
+    file: usize,
+    node: NodeId,
+    brand: Rc<BrandScope>,
+    implicit: Implicit<'a>,
+}
+
+/// The implicit generic parameters of a method, which its parameter lists can refer to.
+#[derive(Clone, Copy)]
+enum Implicit<'a> {
+    None,
+    /// Within a named parameter list, where they are parameters of the list's struct.
+    Struct(u64, &'a [Located<String>]),
+    /// Within a parameter list given as a type.
+    Method(&'a [Located<String>]),
+}
+
+struct StructInfo<'a> {
+    display_name: String,
+    scope_id: u64,
+    is_generic: bool,
+    is_group: bool,
+    /// The implicit parameters of the method, for parameter lists.
+    parameters: &'a [Located<String>],
+    doc_comment: Option<&'a str>,
+    data_word_count: u16,
+    pointer_count: u16,
+    discriminant_count: u16,
+    discriminant_offset: u32,
+    fields: Vec<FieldInfo<'a>>,
+    ctx: Ctx<'a>,
+}
+
+#[derive(Clone)]
+struct FieldInfo<'a> {
+    name: &'a str,
+    code_order: u16,
+    discriminant: Option<u16>,
+    ordinal: Option<u16>,
+    annotations: &'a [AnnotationApplication],
+    target: Target,
+    doc_comment: Option<&'a str>,
+    kind: FieldKind<'a>,
+}
+
+#[derive(Clone)]
+enum FieldKind<'a> {
+    Slot {
+        type_: Option<Type>,
+        offset: u32,
+        default: Option<&'a Expression>,
+    },
+    Group(u64),
+}
+
+/// Doc comments, which go in `CodeGeneratorRequest.sourceInfo` rather than in the nodes.
+struct SourceInfo<'a> {
+    id: u64,
+    doc_comment: Option<&'a str>,
+    members: Vec<Option<&'a str>>,
+}
+
+impl<'a> SourceInfo<'a> {
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            doc_comment: None,
+            members: Vec::new(),
+        }
+    }
+}
+
+struct CompiledAnnotation {
+    id: u64,
+    brand: Rc<BrandScope>,
+    type_: Type,
+    value: Value,
+}
+
+/// A field, union, or group while its struct is being laid out.
+struct MemberInfo<'a> {
+    parent: Option<usize>,
+    code_order: u16,
+    decl: Option<&'a Declaration>,
+    is_in_union: bool,
+    /// Where the member's data goes, for fields.
+    scope: layout::Scope,
+    /// The union this member contains, if any.
+    union: Option<usize>,
+    /// Whether the member has a node of its own, as groups and named unions do.
+    has_node: bool,
+    index: Option<u16>,
+    child_count: u16,
+    child_initialized_count: u16,
+    union_discriminant_count: u16,
+    discriminant: Option<u16>,
+    ordinal: Option<u16>,
+    slot: Option<(Option<Type>, u32)>,
+    id: u64,
+    display_name: String,
+}
+
+impl<'a> MemberInfo<'a> {
+    fn new(
+        parent: usize,
+        code_order: u16,
+        decl: &'a Declaration,
+        is_in_union: bool,
+        scope: layout::Scope,
+        has_node: bool,
+    ) -> Self {
+        Self {
+            parent: Some(parent),
+            code_order,
+            decl: Some(decl),
+            is_in_union,
+            scope,
+            union: None,
+            has_node,
+            index: None,
+            child_count: 0,
+            child_initialized_count: 0,
+            union_discriminant_count: 0,
+            discriminant: None,
+            ordinal: None,
+            slot: None,
+            id: 0,
+            display_name: String::new(),
+        }
+    }
+}
+
+struct StructTranslation<'a> {
+    members: Vec<MemberInfo<'a>>,
+    layout: StructLayout,
+    /// Members that have ordinals, with their ordinal and its location.
+    by_ordinal: Vec<(u64, usize, usize, usize)>,
+}
+
+impl<'a> StructTranslation<'a> {
+    fn add(&mut self, member: MemberInfo<'a>) -> usize {
+        if let Some(parent) = member.parent {
+            self.members[parent].child_count += 1;
+        }
+        self.members.push(member);
+        self.members.len() - 1
+    }
+}
+
+/// Checks that ordinals, visited in order, start at zero and have no duplicates or holes.
+#[derive(Default)]
+struct OrdinalChecker {
+    expected: u64,
+    last: Option<(u64, usize, usize)>,
+}
+
+struct Translator<'a> {
+    files: &'a [SourceFile],
+    stream_file: Option<usize>,
+    nodes: Vec<Node<'a>>,
+    file_nodes: Vec<NodeId>,
+    aliases: Vec<Alias<'a>>,
+    structs: HashMap<u64, StructInfo<'a>>,
+    /// The groups and parameter lists of each node, by ID.
+    aux_nodes: HashMap<NodeId, Vec<u64>>,
+    const_values: HashMap<NodeId, Option<Option<(Type, Value)>>>,
+    annotation_types: HashMap<NodeId, Option<Type>>,
+    field_defaults: HashMap<(u64, usize), Value>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Translator<'a> {
+    fn error(&mut self, file: usize, start: usize, end: usize, message: impl Into<String>) {
+        self.errors.push(Diagnostic {
+            file,
+            start,
+            end,
+            message: message.into(),
+        });
+    }
+
+    fn add_file(&mut self, file: usize) -> NodeId {
+        let source = &self.files[file];
+        let decl = &source.root;
+        let id = match decl.id {
+            DeclId::Uid(id, ..) => id,
+            _ => {
+                let id = child_id(0, &source.display_name);
+                self.error(
+                    file,
+                    0,
+                    0,
+                    format!(
+                        "File does not declare an ID.  I've generated one for you.  Add this \
+                         line to your file: @0x{id:016x};"
+                    ),
+                );
+                id
+            }
+        };
+        self.nodes.push(Node {
+            id,
+            kind: NodeKind::File,
+            decl,
+            file,
+            parent: None,
+            display_name: source.display_name.clone(),
+            members: HashMap::new(),
+            nested: Vec::new(),
+        });
+        let node = self.nodes.len() - 1;
+        self.add_nested(node);
+        node
+    }
+
+    fn add_nested(&mut self, parent: NodeId) {
+        let parent_decl = self.nodes[parent].decl;
+        let file = self.nodes[parent].file;
+        for decl in &parent_decl.nested {
+            let kind = match &decl.kind {
+                DeclKind::Struct => NodeKind::Struct,
+                DeclKind::Enum => NodeKind::Enum,
+                DeclKind::Interface { .. } => NodeKind::Interface,
+                DeclKind::Const { .. } => NodeKind::Const,
+                DeclKind::Annotation { .. } => NodeKind::Annotation,
+                DeclKind::Using(target) => {
+                    let name = &decl.name;
+                    if self.nodes[parent].members.contains_key(&name.value[..]) {
+                        self.error(
+                            file,
+                            name.start,
+                            name.end,
+                            format!("'{}' is already defined.", name.value),
+                        );
+                        continue;
+                    }
+                    self.aliases.push(Alias {
+                        target,
+                        parent,
+                        state: AliasState::Pending,
+                    });
+                    let alias = self.aliases.len() - 1;
+                    self.nodes[parent]
+                        .members
+                        .insert(&name.value, Member::Alias(alias));
+                    continue;
+                }
+                _ => continue,
+            };
+            let name = &decl.name;
+            if self.nodes[parent].members.contains_key(&name.value[..]) {
+                self.error(
+                    file,
+                    name.start,
+                    name.end,
+                    format!("'{}' is already defined.", name.value),
+                );
+                continue;
+            }
+            let id = match decl.id {
+                DeclId::Uid(id, ..) => id,
+                _ => child_id(self.nodes[parent].id, &name.value),
+            };
+            let separator = if self.nodes[parent].kind == NodeKind::File {
+                ':'
+            } else {
+                '.'
+            };
+            let display_name = format!(
+                "{}{}{}",
+                self.nodes[parent].display_name, separator, name.value
+            );
+            self.nodes.push(Node {
+                id,
+                kind,
+                decl,
+                file,
+                parent: Some(parent),
+                display_name,
+                members: HashMap::new(),
+                nested: Vec::new(),
+            });
+            let node = self.nodes.len() - 1;
+            self.nodes[parent]
+                .members
+                .insert(&name.value, Member::Node(node));
+            self.nodes[parent].nested.push(node);
+            if matches!(kind, NodeKind::Struct | NodeKind::Interface) {
+                self.add_nested(node);
+            }
+        }
+    }
+
+    fn param_count(&self, node: NodeId) -> usize {
+        match self.nodes[node].kind {
+            NodeKind::Struct | NodeKind::Interface => self.nodes[node].decl.parameters.len(),
+            _ => 0,
+        }
+    }
+
+    fn is_generic(&self, node: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(n) = current {
+            if self.param_count(n) > 0 {
+                return true;
+            }
+            current = self.nodes[n].parent;
+        }
+        false
+    }
+
+    /// The brand of a declaration as seen from inside itself, where all parameters are inherited.
+    fn lexical_brand(&self, node: NodeId) -> Rc<BrandScope> {
+        Rc::new(BrandScope {
+            parent: self.nodes[node].parent.map(|p| self.lexical_brand(p)),
+            leaf_id: self.nodes[node].id,
+            leaf_param_count: self.param_count(node),
+            inherited: true,
+            params: Vec::new(),
+        })
+    }
+
+    fn node_ctx(&self, node: NodeId) -> Ctx<'a> {
+        Ctx {
+            file: self.nodes[node].file,
+            node,
+            brand: self.lexical_brand(node),
+            implicit: Implicit::None,
+        }
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Name resolution
+
+    fn resolve_alias(&mut self, alias: usize) -> Option<BrandedDecl> {
+        match &self.aliases[alias].state {
+            AliasState::Done(result) => return result.clone(),
+            AliasState::InProgress => {
+                let target = self.aliases[alias].target;
+                let file = self.nodes[self.aliases[alias].parent].file;
+                self.error(
+                    file,
+                    target.start,
+                    target.end,
+                    "Declaration recursively refers to itself.",
+                );
+                return None;
+            }
+            AliasState::Pending => (),
+        }
+        self.aliases[alias].state = AliasState::InProgress;
+        let ctx = self.node_ctx(self.aliases[alias].parent);
+        let result = self.compile_decl_expression(self.aliases[alias].target, &ctx);
+        self.aliases[alias].state = AliasState::Done(result.clone());
+        result
+    }
+
+    /// Interprets a member of `node` that was reached through `brand`, whose leaf is `node`.
+    fn interpret_member(
+        &mut self,
+        node: NodeId,
+        name: &str,
+        brand: &Rc<BrandScope>,
+    ) -> Option<Option<BrandedDecl>> {
+        let parent_id = self.nodes[node].id;
+        let member = *self.nodes[node].members.get(name)?;
+        Some(match member {
+            Member::Node(member) => Some(BrandedDecl::Node(
+                member,
+                brand
+                    .pop(parent_id)
+                    .push(self.nodes[member].id, self.param_count(member)),
+            )),
+            Member::Alias(alias) => self
+                .resolve_alias(alias)
+                .map(|decl| substitute(&decl, &brand.pop(parent_id))),
+        })
+    }
+
+    fn resolve_relative(&mut self, ctx: &Ctx<'a>, name: &str) -> Option<Option<BrandedDecl>> {
+        let mut current = Some(ctx.node);
+        while let Some(node) = current {
+            if let Some(result) = self.interpret_member(node, name, &ctx.brand) {
+                return Some(result);
+            }
+            if let Some(index) = self.nodes[node]
+                .decl
+                .parameters
+                .iter()
+                .position(|p| p.value == name)
+            {
+                if self.param_count(node) > 0 {
+                    let id = self.nodes[node].id;
+                    return Some(Some(
+                        ctx.brand
+                            .lookup_parameter(id, index as u16)
+                            .unwrap_or(BrandedDecl::Param(id, index as u16)),
+                    ));
+                }
+            }
+            current = self.nodes[node].parent;
+        }
+        Builtin::from_name(name).map(|builtin| Some(BrandedDecl::Builtin(builtin)))
+    }
+
+    fn compile_decl_expression(
+        &mut self,
+        expr: &'a Expression,
+        ctx: &Ctx<'a>,
+    ) -> Option<BrandedDecl> {
+        match &expr.kind {
+            ExpressionKind::RelativeName(name) => {
+                match ctx.implicit {
+                    Implicit::Struct(id, params) => {
+                        if let Some(index) = params.iter().position(|p| p.value == *name) {
+                            return Some(BrandedDecl::Param(id, index as u16));
+                        }
+                    }
+                    Implicit::Method(params) => {
+                        if let Some(index) = params.iter().position(|p| p.value == *name) {
+                            return Some(BrandedDecl::ImplicitMethodParam(index as u16));
+                        }
+                    }
+                    Implicit::None => (),
+                }
+                match self.resolve_relative(ctx, name) {
+                    Some(result) => result,
+                    None => {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            format!("Not defined: {name}"),
+                        );
+                        None
+                    }
+                }
+            }
+            ExpressionKind::AbsoluteName(name) => {
+                let file = self.file_nodes[self.nodes[ctx.node].file];
+                match self.interpret_member(file, name, &ctx.brand) {
+                    Some(result) => result,
+                    None => {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            format!("Not defined: .{name}"),
+                        );
+                        None
+                    }
+                }
+            }
+            ExpressionKind::Import(path) => {
+                let (_, file) = self.files[ctx.file]
+                    .imports
+                    .iter()
+                    .find(|(name, _)| name == path)?;
+                let node = self.file_nodes[*file];
+                Some(BrandedDecl::Node(
+                    node,
+                    Rc::new(BrandScope {
+                        parent: None,
+                        leaf_id: self.nodes[node].id,
+                        leaf_param_count: 0,
+                        inherited: false,
+                        params: Vec::new(),
+                    }),
+                ))
+            }
+            ExpressionKind::Application(base, params) => {
+                let base = self.compile_decl_expression(base, ctx)?;
+                let mut args = Vec::new();
+                for param in params {
+                    if let Some(name) = &param.name {
+                        self.error(
+                            ctx.file,
+                            name.start,
+                            name.end,
+                            "Named parameter not allowed here.",
+                        );
+                        return None;
+                    }
+                    args.push(self.compile_decl_expression(&param.value, ctx)?);
+                }
+                self.apply_params(base, args, expr, ctx)
+            }
+            ExpressionKind::Member(base, name) => {
+                let decl = self.compile_decl_expression(base, ctx)?;
+                let result = match &decl {
+                    BrandedDecl::Node(node, brand) => {
+                        self.interpret_member(*node, &name.value, brand)
+                    }
+                    _ => None,
+                };
+                match result {
+                    Some(result) => result,
+                    None => {
+                        self.error(
+                            ctx.file,
+                            name.start,
+                            name.end,
+                            format!(
+                                "'{}' has no member named '{}'",
+                                expression_string(base),
+                                name.value
+                            ),
+                        );
+                        None
+                    }
+                }
+            }
+            _ => {
+                self.error(
+                    ctx.file,
+                    expr.start,
+                    expr.end,
+                    "Expected the name of a declaration.",
+                );
+                None
+            }
+        }
+    }
+
+    fn apply_params(
+        &mut self,
+        base: BrandedDecl,
+        mut args: Vec<BrandedDecl>,
+        expr: &Expression,
+        ctx: &Ctx<'a>,
+    ) -> Option<BrandedDecl> {
+        let message = match base {
+            BrandedDecl::Builtin(Builtin::List) => {
+                if args.len() != 1 {
+                    "'List' requires exactly one parameter."
+                } else {
+                    let element = args.pop().unwrap();
+                    match self.to_type(&element) {
+                        Err(message) => {
+                            self.error(ctx.file, expr.start, expr.end, message);
+                            return None;
+                        }
+                        Ok(Type::AnyPointer(AnyPointer::Any)) => {
+                            "'List(AnyPointer)' is not supported."
+                        }
+                        Ok(_) => return Some(BrandedDecl::List(Box::new(element))),
+                    }
+                }
+            }
+            BrandedDecl::Node(node, brand) => {
+                let count = brand.leaf_param_count;
+                if !brand.params.is_empty() {
+                    "Double-application of generic parameters."
+                } else if args.len() > count {
+                    if count == 0 {
+                        "Declaration does not accept generic parameters."
+                    } else {
+                        "Too many generic parameters."
+                    }
+                } else if args.len() < count {
+                    "Not enough generic parameters."
+                } else if !args.iter().all(|arg| self.is_pointer(arg)) {
+                    "Sorry, only pointer types can be used as generic parameters."
+                } else {
+                    return Some(BrandedDecl::Node(
+                        node,
+                        Rc::new(BrandScope {
+                            parent: brand.parent.clone(),
+                            leaf_id: brand.leaf_id,
+                            leaf_param_count: count,
+                            inherited: false,
+                            params: args,
+                        }),
+                    ));
+                }
+            }
+            BrandedDecl::Param(..) | BrandedDecl::ImplicitMethodParam(_) => {
+                "Cannot apply generic parameters to a generic parameter."
+            }
+            BrandedDecl::List(_) => "Double-application of generic parameters.",
+            BrandedDecl::Builtin(_) => "Declaration does not accept generic parameters.",
+        };
+        self.error(ctx.file, expr.start, expr.end, message);
+        None
+    }
+
+    fn is_pointer(&self, decl: &BrandedDecl) -> bool {
+        match decl {
+            BrandedDecl::Node(node, _) => {
+                matches!(
+                    self.nodes[*node].kind,
+                    NodeKind::Struct | NodeKind::Interface
+                )
+            }
+            BrandedDecl::Builtin(builtin) => matches!(
+                builtin,
+                Builtin::Text
+                    | Builtin::Data
+                    | Builtin::AnyPointer
+                    | Builtin::AnyStruct
+                    | Builtin::AnyList
+                    | Builtin::Capability
+            ),
+            BrandedDecl::List(_) | BrandedDecl::Param(..) | BrandedDecl::ImplicitMethodParam(_) => {
+                true
+            }
+        }
+    }
+
+    fn to_type(&self, decl: &BrandedDecl) -> Result<Type, String> {
+        Ok(match decl {
+            BrandedDecl::Node(node, brand) => match self.nodes[*node].kind {
+                NodeKind::Struct => Type::Struct(*node, brand.clone()),
+                NodeKind::Enum => Type::Enum(*node, brand.clone()),
+                NodeKind::Interface => Type::Interface(*node, brand.clone()),
+                _ => {
+                    return Err(format!(
+                        "'{}' is not a type.",
+                        self.nodes[*node].display_name
+                    ))
+                }
+            },
+            BrandedDecl::Builtin(builtin) => match builtin {
+                Builtin::Void => Type::Void,
+                Builtin::Bool => Type::Bool,
+                Builtin::Int8 => Type::Int8,
+                Builtin::Int16 => Type::Int16,
+                Builtin::Int32 => Type::Int32,
+                Builtin::Int64 => Type::Int64,
+                Builtin::UInt8 => Type::UInt8,
+                Builtin::UInt16 => Type::UInt16,
+                Builtin::UInt32 => Type::UInt32,
+                Builtin::UInt64 => Type::UInt64,
+                Builtin::Float32 => Type::Float32,
+                Builtin::Float64 => Type::Float64,
+                Builtin::Text => Type::Text,
+                Builtin::Data => Type::Data,
+                Builtin::List => return Err("'List' requires exactly one parameter.".into()),
+                Builtin::AnyPointer => Type::AnyPointer(AnyPointer::Any),
+                Builtin::AnyStruct => Type::AnyPointer(AnyPointer::Struct),
+                Builtin::AnyList => Type::AnyPointer(AnyPointer::List),
+                Builtin::Capability => Type::AnyPointer(AnyPointer::Capability),
+            },
+            BrandedDecl::List(element) => Type::List(Box::new(self.to_type(element)?)),
+            BrandedDecl::Param(scope_id, index) => {
+                Type::AnyPointer(AnyPointer::Parameter(*scope_id, *index))
+            }
+            BrandedDecl::ImplicitMethodParam(index) => {
+                Type::AnyPointer(AnyPointer::ImplicitMethodParameter(*index))
+            }
+        })
+    }
+
+    fn compile_type(&mut self, expr: &'a Expression, ctx: &Ctx<'a>) -> Option<Type> {
+        let decl = self.compile_decl_expression(expr, ctx)?;
+        match self.to_type(&decl) {
+            Ok(type_) => Some(type_),
+            Err(message) => {
+                self.error(ctx.file, expr.start, expr.end, message);
+                None
+            }
+        }
+    }
+
+    /// Replaces generic parameters in `type_` with their bindings in `brand`.
+    fn substitute_type(&self, type_: &Type, brand: &Rc<BrandScope>) -> Type {
+        match type_ {
+            Type::AnyPointer(AnyPointer::Parameter(scope_id, index)) => brand
+                .lookup_parameter(*scope_id, *index)
+                .and_then(|decl| self.to_type(&decl).ok())
+                .unwrap_or_else(|| type_.clone()),
+            Type::List(element) => Type::List(Box::new(self.substitute_type(element, brand))),
+            Type::Enum(node, scope) => Type::Enum(*node, substitute_scope(scope, brand)),
+            Type::Struct(node, scope) => Type::Struct(*node, substitute_scope(scope, brand)),
+            Type::Interface(node, scope) => Type::Interface(*node, substitute_scope(scope, brand)),
+            _ => type_.clone(),
+        }
+    }
+
+    fn type_name(&self, type_: &Type) -> String {
+        match type_ {
+            Type::Void => "Void".into(),
+            Type::Bool => "Bool".into(),
+            Type::Int8 => "Int8".into(),
+            Type::Int16 => "Int16".into(),
+            Type::Int32 => "Int32".into(),
+            Type::Int64 => "Int64".into(),
+            Type::UInt8 => "UInt8".into(),
+            Type::UInt16 => "UInt16".into(),
+            Type::UInt32 => "UInt32".into(),
+            Type::UInt64 => "UInt64".into(),
+            Type::Float32 => "Float32".into(),
+            Type::Float64 => "Float64".into(),
+            Type::Text => "Text".into(),
+            Type::Data => "Data".into(),
+            Type::List(element) => format!("List({})", self.type_name(element)),
+            Type::Enum(node, _) | Type::Struct(node, _) | Type::Interface(node, _) => {
+                self.nodes[*node].display_name.clone()
+            }
+            Type::AnyPointer(_) => "AnyPointer".into(),
+        }
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Structs
+
+    fn translate_struct(&mut self, node: NodeId) {
+        let decl = self.nodes[node].decl;
+        let ctx = self.node_ctx(node);
+        let mut st = StructTranslation {
+            members: Vec::new(),
+            layout: StructLayout::default(),
+            by_ordinal: Vec::new(),
+        };
+        st.members.push(MemberInfo {
+            parent: None,
+            code_order: 0,
+            decl: None,
+            is_in_union: false,
+            scope: layout::Scope::Top,
+            union: None,
+            has_node: true,
+            index: None,
+            child_count: 0,
+            child_initialized_count: 0,
+            union_discriminant_count: 0,
+            discriminant: None,
+            ordinal: None,
+            slot: None,
+            id: self.nodes[node].id,
+            display_name: self.nodes[node].display_name.clone(),
+        });
+        self.traverse_top_or_group(&mut st, &decl.nested, 0, layout::Scope::Top, &ctx);
+
+        // Lay out members in ordinal order, so that adding fields never moves existing ones.
+        st.by_ordinal.sort_by_key(|&(ordinal, ..)| ordinal);
+        let mut checker = OrdinalChecker::default();
+        for (ordinal, member, start, end) in std::mem::take(&mut st.by_ordinal) {
+            self.check_ordinal(&mut checker, ctx.file, ordinal, start, end);
+            get_schema(&mut st, member);
+            st.members[member].ordinal = Some(ordinal as u16);
+            let member_decl = st.members[member].decl.unwrap();
+            match &member_decl.kind {
+                DeclKind::Field { type_, .. } => {
+                    let type_ = self.compile_type(type_, &ctx);
+                    let scope = st.members[member].scope;
+                    let offset = match type_.as_ref().map(Type::data_size) {
+                        Some(DataSize::Data(lg_size)) => st.layout.add_data(scope, lg_size),
+                        Some(DataSize::Pointer) => st.layout.add_pointer(scope),
+                        Some(DataSize::Void) | None => {
+                            st.layout.add_void(scope);
+                            0
+                        }
+                    };
+                    st.members[member].slot = Some((type_, offset));
+                }
+                DeclKind::Union => {
+                    let union = st.members[member].union.unwrap();
+                    if !st.layout.add_discriminant(union) {
+                        self.error(
+                            ctx.file,
+                            start,
+                            end,
+                            "Union ordinal, if specified, must be greater than no more than one of \
+                             its member ordinals (i.e. there can only be one field retroactively \
+                             unionized).",
+                        );
+                    }
+                }
+                _ => unreachable!("only fields and unions have ordinals"),
+            }
+        }
+
+        for member in 0..st.members.len() {
+            if !st.members[member].has_node {
+                continue;
+            }
+            if let Some(union) = st.members[member].union {
+                st.layout.add_discriminant(union);
+            }
+            if let Some(parent) = st.members[member].parent {
+                get_schema(&mut st, member);
+                let index = st.members[member].index.unwrap();
+                st.members[member].id = group_id(st.members[parent].id, index);
+            }
+        }
+
+        // Members come after their parents, so parents' display names are always ready.
+        for member in 1..st.members.len() {
+            if st.members[member].has_node {
+                let parent = st.members[member].parent.unwrap();
+                let name = &st.members[member].decl.unwrap().name.value;
+                st.members[member].display_name =
+                    format!("{}.{name}", st.members[parent].display_name);
+            }
+        }
+
+        // Collect the fields of each node.
+        let mut fields: Vec<Vec<Option<FieldInfo<'a>>>> = st
+            .members
+            .iter()
+            .map(|m| vec![None; m.child_count as usize])
+            .collect();
+        for member in &st.members {
+            let (Some(parent), Some(index), Some(decl)) =
+                (member.parent, member.index, member.decl)
+            else {
+                continue;
+            };
+            let (target, kind) = match &decl.kind {
+                DeclKind::Field { default, .. } => {
+                    let (type_, offset) = member.slot.clone().unwrap_or((None, 0));
+                    (
+                        Target::Field,
+                        FieldKind::Slot {
+                            type_,
+                            offset,
+                            default: default.as_ref(),
+                        },
+                    )
+                }
+                DeclKind::Union => (Target::Union, FieldKind::Group(member.id)),
+                _ => (Target::Group, FieldKind::Group(member.id)),
+            };
+            fields[parent][index as usize] = Some(FieldInfo {
+                name: &decl.name.value,
+                code_order: member.code_order,
+                discriminant: member.discriminant,
+                ordinal: member.ordinal,
+                annotations: &decl.annotations,
+                target,
+                doc_comment: decl.doc_comment.as_deref(),
+                kind,
+            });
+        }
+
+        let data_word_count = st.layout.data_word_count as u16;
+        let pointer_count = st.layout.pointer_count as u16;
+        let is_generic = self.is_generic(node);
+        for (index, (member, fields)) in st.members.iter().zip(fields).enumerate() {
+            if !member.has_node {
+                continue;
+            }
+            let display_name = member.display_name.clone();
+            let (discriminant_count, discriminant_offset) = match member.union {
+                Some(union) => (
+                    member.union_discriminant_count,
+                    st.layout.discriminant_offset(union).unwrap_or(0),
+                ),
+                None => (0, 0),
+            };
+            let info = StructInfo {
+                display_name,
+                scope_id: match member.parent {
+                    Some(parent) => st.members[parent].id,
+                    None => self.nodes[node].parent.map_or(0, |p| self.nodes[p].id),
+                },
+                is_generic,
+                is_group: member.parent.is_some(),
+                parameters: &[],
+                doc_comment: member.decl.and_then(|d| d.doc_comment.as_deref()),
+                data_word_count,
+                pointer_count,
+                discriminant_count,
+                discriminant_offset,
+                fields: fields.into_iter().flatten().collect(),
+                ctx: ctx.clone(),
+            };
+            if index > 0 {
+                self.aux_nodes.entry(node).or_default().push(member.id);
+            }
+            self.structs.insert(member.id, info);
+        }
+    }
+
+    fn traverse_top_or_group(
+        &mut self,
+        st: &mut StructTranslation<'a>,
+        decls: &'a [Declaration],
+        parent: usize,
+        scope: layout::Scope,
+        ctx: &Ctx<'a>,
+    ) {
+        let mut code_order = 0;
+        for decl in decls {
+            match &decl.kind {
+                DeclKind::Field { .. } => {
+                    let member = st.add(MemberInfo::new(
+                        parent, code_order, decl, false, scope, false,
+                    ));
+                    code_order += 1;
+                    if let DeclId::Ordinal(ordinal, start, end) = decl.id {
+                        st.by_ordinal.push((ordinal, member, start, end));
+                    }
+                }
+                DeclKind::Union => {
+                    let union = st.layout.new_union(scope);
+                    if decl.name.value.is_empty() {
+                        if st.members[parent].union.is_some() {
+                            self.error(
+                                ctx.file,
+                                decl.start,
+                                decl.end,
+                                "An unnamed union is already defined in this scope.",
+                            );
+                            continue;
+                        }
+                        st.members[parent].union = Some(union);
+                        self.traverse_union(st, decl, parent, union, &mut code_order, ctx);
+                    } else {
+                        let member = st.add(MemberInfo::new(
+                            parent, code_order, decl, false, scope, true,
+                        ));
+                        code_order += 1;
+                        st.members[member].union = Some(union);
+                        self.traverse_union(st, decl, member, union, &mut 0, ctx);
+                        if let DeclId::Ordinal(ordinal, start, end) = decl.id {
+                            st.by_ordinal.push((ordinal, member, start, end));
+                        }
+                    }
+                }
+                DeclKind::Group => {
+                    let member = st.add(MemberInfo::new(
+                        parent, code_order, decl, false, scope, true,
+                    ));
+                    code_order += 1;
+                    self.traverse_group(st, decl, member, scope, ctx);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn traverse_union(
+        &mut self,
+        st: &mut StructTranslation<'a>,
+        decl: &'a Declaration,
+        parent: usize,
+        union: usize,
+        code_order: &mut u16,
+        ctx: &Ctx<'a>,
+    ) {
+        let member_count = decl
+            .nested
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.kind,
+                    DeclKind::Field { .. } | DeclKind::Union | DeclKind::Group
+                )
+            })
+            .count();
+        if member_count < 2 {
+            self.error(
+                ctx.file,
+                decl.start,
+                decl.end,
+                "Union must have at least two members.",
+            );
+        }
+        for member_decl in &decl.nested {
+            match &member_decl.kind {
+                DeclKind::Field { .. } => {
+                    let group = st.layout.new_group(union);
+                    let member = st.add(MemberInfo::new(
+                        parent,
+                        *code_order,
+                        member_decl,
+                        true,
+                        group,
+                        false,
+                    ));
+                    *code_order += 1;
+                    if let DeclId::Ordinal(ordinal, start, end) = member_decl.id {
+                        st.by_ordinal.push((ordinal, member, start, end));
+                    }
+                }
+                DeclKind::Union => {
+                    if member_decl.name.value.is_empty() {
+                        self.error(
+                            ctx.file,
+                            member_decl.start,
+                            member_decl.end,
+                            "Unions cannot contain unnamed unions.",
+                        );
+                        continue;
+                    }
+                    let group = st.layout.new_group(union);
+                    let sub_union = st.layout.new_union(group);
+                    let member = st.add(MemberInfo::new(
+                        parent,
+                        *code_order,
+                        member_decl,
+                        true,
+                        group,
+                        true,
+                    ));
+                    *code_order += 1;
+                    st.members[member].union = Some(sub_union);
+                    self.traverse_union(st, member_decl, member, sub_union, &mut 0, ctx);
+                    if let DeclId::Ordinal(ordinal, start, end) = member_decl.id {
+                        st.by_ordinal.push((ordinal, member, start, end));
+                    }
+                }
+                DeclKind::Group => {
+                    let group = st.layout.new_group(union);
+                    let member = st.add(MemberInfo::new(
+                        parent,
+                        *code_order,
+                        member_decl,
+                        true,
+                        group,
+                        true,
+                    ));
+                    *code_order += 1;
+                    self.traverse_group(st, member_decl, member, group, ctx);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn traverse_group(
+        &mut self,
+        st: &mut StructTranslation<'a>,
+        decl: &'a Declaration,
+        parent: usize,
+        scope: layout::Scope,
+        ctx: &Ctx<'a>,
+    ) {
+        let has_members = decl.nested.iter().any(|d| {
+            matches!(
+                d.kind,
+                DeclKind::Field { .. } | DeclKind::Union | DeclKind::Group
+            )
+        });
+        if !has_members {
+            self.error(
+                ctx.file,
+                decl.start,
+                decl.end,
+                "Group must have at least one member.",
+            );
+        }
+        self.traverse_top_or_group(st, &decl.nested, parent, scope, ctx);
+    }
+
+    fn check_ordinal(
+        &mut self,
+        checker: &mut OrdinalChecker,
+        file: usize,
+        ordinal: u64,
+        start: usize,
+        end: usize,
+    ) {
+        if ordinal < checker.expected {
+            self.error(file, start, end, "Duplicate ordinal number.");
+            if let Some((last, start, end)) = checker.last.take() {
+                self.error(
+                    file,
+                    start,
+                    end,
+                    format!("Ordinal @{last} originally used here."),
+                );
+            }
+        } else if ordinal > checker.expected {
+            self.error(
+                file,
+                start,
+                end,
+                format!(
+                    "Skipped ordinal @{}.  Ordinals must be sequential with no holes.",
+                    checker.expected
+                ),
+            );
+            checker.expected = ordinal + 1;
+        } else {
+            checker.expected += 1;
+            checker.last = Some((ordinal, start, end));
+        }
+    }
+
+    fn translate_param_structs(&mut self, node: NodeId) {
+        let decl = self.nodes[node].decl;
+        for method in &decl.nested {
+            let DeclKind::Method { params, results } = &method.kind else {
+                continue;
+            };
+            let ordinal = match method.id {
+                DeclId::Ordinal(ordinal, ..) => ordinal as u16,
+                _ => 0,
+            };
+            if let ParamList::Named(list) = params {
+                self.translate_param_struct(node, method, ordinal, false, list);
+            }
+            match results {
+                Some(ParamList::Named(list)) => {
+                    self.translate_param_struct(node, method, ordinal, true, list)
+                }
+                None => self.translate_param_struct(node, method, ordinal, true, &[]),
+                _ => (),
+            }
+        }
+    }
+
+    fn translate_param_struct(
+        &mut self,
+        node: NodeId,
+        method: &'a Declaration,
+        ordinal: u16,
+        is_results: bool,
+        params: &'a [ParamDecl],
+    ) {
+        let id = method_params_id(self.nodes[node].id, ordinal, is_results);
+        let suffix = if is_results { "Results" } else { "Params" };
+        let display_name = format!(
+            "{}.{}${suffix}",
+            self.nodes[node].display_name, method.name.value
+        );
+        let ctx = Ctx {
+            implicit: Implicit::Struct(id, &method.parameters),
+            ..self.node_ctx(node)
+        };
+        let mut layout = StructLayout::default();
+        let mut fields = Vec::new();
+        for (index, param) in params.iter().enumerate() {
+            if params[..index]
+                .iter()
+                .any(|p| p.name.value == param.name.value)
+            {
+                self.error(
+                    ctx.file,
+                    param.start,
+                    param.end,
+                    format!("'{}' is already defined.", param.name.value),
+                );
+            }
+            let type_ = self.compile_type(&param.type_, &ctx);
+            let offset = match type_.as_ref().map(Type::data_size) {
+                Some(DataSize::Data(lg_size)) => layout.add_data(layout::Scope::Top, lg_size),
+                Some(DataSize::Pointer) => layout.add_pointer(layout::Scope::Top),
+                Some(DataSize::Void) | None => 0,
+            };
+            fields.push(FieldInfo {
+                name: &param.name.value,
+                code_order: index as u16,
+                discriminant: None,
+                ordinal: Some(index as u16),
+                annotations: &param.annotations,
+                target: Target::Param,
+                doc_comment: None,
+                kind: FieldKind::Slot {
+                    type_,
+                    offset,
+                    default: param.default.as_ref(),
+                },
+            });
+        }
+        let info = StructInfo {
+            display_name,
+            scope_id: 0,
+            is_generic: self.is_generic(node) || !method.parameters.is_empty(),
+            is_group: false,
+            parameters: &method.parameters,
+            doc_comment: None,
+            data_word_count: layout.data_word_count as u16,
+            pointer_count: layout.pointer_count as u16,
+            discriminant_count: 0,
+            discriminant_offset: 0,
+            fields,
+            ctx,
+        };
+        self.structs.insert(id, info);
+        self.aux_nodes.entry(node).or_default().push(id);
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Values
+
+    fn compile_field_defaults(&mut self) {
+        let mut ids: Vec<u64> = self.structs.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let info = &self.structs[&id];
+            let ctx = info.ctx.clone();
+            let defaults: Vec<(usize, Type, &'a Expression)> = info
+                .fields
+                .iter()
+                .enumerate()
+                .filter_map(|(index, field)| match &field.kind {
+                    FieldKind::Slot {
+                        type_: Some(type_),
+                        default: Some(default),
+                        ..
+                    } => Some((index, type_.clone(), *default)),
+                    _ => None,
+                })
+                .collect();
+            for (index, type_, default) in defaults {
+                if let Some(value) = self.compile_value(default, &type_, &ctx) {
+                    self.field_defaults.insert((id, index), value);
+                }
+            }
+        }
+    }
+
+    fn const_value(&mut self, node: NodeId) -> Option<(Type, Value)> {
+        match self.const_values.get(&node) {
+            Some(Some(result)) => return result.clone(),
+            Some(None) => {
+                let decl = self.nodes[node].decl;
+                let file = self.nodes[node].file;
+                self.error(
+                    file,
+                    decl.name.start,
+                    decl.name.end,
+                    "Constant refers to itself.",
+                );
+                return None;
+            }
+            None => (),
+        }
+        self.const_values.insert(node, None);
+        let DeclKind::Const { type_, value } = &self.nodes[node].decl.kind else {
+            unreachable!("not a constant");
+        };
+        let ctx = self.node_ctx(node);
+        let result = self
+            .compile_type(type_, &ctx)
+            .and_then(|type_| Some((type_.clone(), self.compile_value(value, &type_, &ctx)?)));
+        self.const_values.insert(node, Some(result.clone()));
+        result
+    }
+
+    fn annotation_type(&mut self, node: NodeId) -> Option<Type> {
+        if let Some(result) = self.annotation_types.get(&node) {
+            return result.clone();
+        }
+        let DeclKind::Annotation { type_, .. } = &self.nodes[node].decl.kind else {
+            unreachable!("not an annotation");
+        };
+        let ctx = self.node_ctx(node);
+        let result = self.compile_type(type_, &ctx);
+        self.annotation_types.insert(node, result.clone());
+        result
+    }
+
+    fn enumerant_value(&self, node: NodeId, name: &str) -> Option<u16> {
+        self.nodes[node]
+            .decl
+            .nested
+            .iter()
+            .find_map(|decl| match (&decl.kind, decl.id) {
+                (DeclKind::Enumerant, DeclId::Ordinal(ordinal, ..)) if decl.name.value == name => {
+                    Some(ordinal as u16)
+                }
+                _ => None,
+            })
+    }
+
+    fn compile_value(
+        &mut self,
+        expr: &'a Expression,
+        type_: &Type,
+        ctx: &Ctx<'a>,
+    ) -> Option<Value> {
+        let mismatch = match &expr.kind {
+            ExpressionKind::RelativeName(name) => {
+                match (type_, name.as_str()) {
+                    (Type::Void, "void") => return Some(Value::Void),
+                    (Type::Bool, "true") => return Some(Value::Bool(true)),
+                    (Type::Bool, "false") => return Some(Value::Bool(false)),
+                    (t, "inf") if t.is_float() => return Some(Value::Float(f64::INFINITY)),
+                    (t, "nan") if t.is_float() => return Some(Value::Float(f64::NAN)),
+                    (Type::Enum(node, _), name) => {
+                        if let Some(value) = self.enumerant_value(*node, name) {
+                            return Some(Value::Enum(value));
+                        }
+                    }
+                    _ => (),
+                }
+                return self.compile_constant_reference(expr, type_, ctx);
+            }
+            ExpressionKind::AbsoluteName(_)
+            | ExpressionKind::Import(_)
+            | ExpressionKind::Application(..)
+            | ExpressionKind::Member(..) => {
+                return self.compile_constant_reference(expr, type_, ctx)
+            }
+            ExpressionKind::PositiveInt(value) => {
+                if let Some((_, max)) = type_.int_range() {
+                    if i128::from(*value) > max {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            "Integer value out of range.",
+                        );
+                        return None;
+                    }
+                    return Some(Value::Int(i128::from(*value)));
+                } else if type_.is_float() {
+                    return Some(Value::Float(*value as f64));
+                }
+                true
+            }
+            ExpressionKind::NegativeInt(value) => {
+                if let Some((min, _)) = type_.int_range() {
+                    if -i128::from(*value) < min {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            "Integer value out of range.",
+                        );
+                        return None;
+                    }
+                    return Some(Value::Int(-i128::from(*value)));
+                } else if type_.is_float() {
+                    return Some(Value::Float(-(*value as f64)));
+                }
+                true
+            }
+            ExpressionKind::Float(value) => {
+                if type_.is_float() {
+                    return Some(Value::Float(*value));
+                }
+                true
+            }
+            ExpressionKind::String(value) => match type_ {
+                Type::Text => return Some(Value::Text(value.clone())),
+                Type::Data => return Some(Value::Data(value.as_bytes().to_vec())),
+                _ => true,
+            },
+            ExpressionKind::Binary(value) => match type_ {
+                Type::Data => return Some(Value::Data(value.clone())),
+                _ => true,
+            },
+            ExpressionKind::Embed(path) => {
+                let contents = self.files[ctx.file]
+                    .embeds
+                    .iter()
+                    .find(|(name, _)| name == path)
+                    .map(|(_, contents)| contents.clone())?;
+                match type_ {
+                    Type::Data => return Some(Value::Data(contents)),
+                    Type::Text => match String::from_utf8(contents) {
+                        Ok(text) => return Some(Value::Text(text)),
+                        Err(_) => {
+                            self.error(
+                                ctx.file,
+                                expr.start,
+                                expr.end,
+                                "Embedded file is not valid UTF-8.",
+                            );
+                            return None;
+                        }
+                    },
+                    _ => true,
+                }
+            }
+            ExpressionKind::List(items) => match type_ {
+                Type::List(element) => {
+                    let mut values = Vec::new();
+                    for item in items {
+                        values.push(self.compile_value(item, element, ctx)?);
+                    }
+                    return Some(Value::List((**element).clone(), values));
+                }
+                _ => true,
+            },
+            ExpressionKind::Tuple(params) => match type_ {
+                Type::Struct(node, brand) => {
+                    let id = self.nodes[*node].id;
+                    return self.compile_struct_value(params, id, brand, ctx);
+                }
+                _ => true,
+            },
+        };
+        if mismatch {
+            self.error(
+                ctx.file,
+                expr.start,
+                expr.end,
+                format!("Type mismatch; expected {}.", self.type_name(type_)),
+            );
+        }
+        None
+    }
+
+    fn compile_constant_reference(
+        &mut self,
+        expr: &'a Expression,
+        type_: &Type,
+        ctx: &Ctx<'a>,
+    ) -> Option<Value> {
+        match self.compile_decl_expression(expr, ctx)? {
+            BrandedDecl::Node(node, _) if self.nodes[node].kind == NodeKind::Const => {
+                let (const_type, value) = self.const_value(node)?;
+                if !const_type.matches(type_) {
+                    self.error(
+                        ctx.file,
+                        expr.start,
+                        expr.end,
+                        format!(
+                            "Type mismatch; '{}' has type {}, expected {}.",
+                            expression_string(expr),
+                            self.type_name(&const_type),
+                            self.type_name(type_)
+                        ),
+                    );
+                    return None;
+                }
+                Some(value)
+            }
+            _ => {
+                self.error(
+                    ctx.file,
+                    expr.start,
+                    expr.end,
+                    format!(
+                        "'{}' does not refer to a constant.",
+                        expression_string(expr)
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn compile_struct_value(
+        &mut self,
+        params: &'a [Param],
+        id: u64,
+        brand: &Rc<BrandScope>,
+        ctx: &Ctx<'a>,
+    ) -> Option<Value> {
+        let mut values = Vec::new();
+        for param in params {
+            let Some(name) = &param.name else {
+                self.error(
+                    ctx.file,
+                    param.value.start,
+                    param.value.end,
+                    "Missing field name.",
+                );
+                continue;
+            };
+            let field = self.structs.get(&id).and_then(|info| {
+                info.fields
+                    .iter()
+                    .position(|f| f.name == name.value)
+                    .map(|index| (index, info.fields[index].kind.clone()))
+            });
+            let Some((index, kind)) = field else {
+                self.error(
+                    ctx.file,
+                    name.start,
+                    name.end,
+                    format!("Struct has no field named '{}'.", name.value),
+                );
+                continue;
+            };
+            match kind {
+                FieldKind::Slot {
+                    type_: Some(type_), ..
+                } => {
+                    let type_ = self.substitute_type(&type_, brand);
+                    if let Some(value) = self.compile_value(&param.value, &type_, ctx) {
+                        values.push((index, value));
+                    }
+                }
+                FieldKind::Slot { type_: None, .. } => (),
+                FieldKind::Group(group_id) => match &param.value.kind {
+                    ExpressionKind::Tuple(group_params) => {
+                        if let Some(value) =
+                            self.compile_struct_value(group_params, group_id, brand, ctx)
+                        {
+                            values.push((index, value));
+                        }
+                    }
+                    _ => self.error(
+                        ctx.file,
+                        param.value.start,
+                        param.value.end,
+                        "Type mismatch; expected group.",
+                    ),
+                },
+            }
+        }
+        Some(Value::Struct(id, values))
+    }
+
+    fn compile_annotations(
+        &mut self,
+        applications: &'a [AnnotationApplication],
+        target: Target,
+        ctx: &Ctx<'a>,
+    ) -> Vec<CompiledAnnotation> {
+        let mut result = Vec::new();
+        for application in applications {
+            let name = &application.name;
+            let Some(decl) = self.compile_decl_expression(name, ctx) else {
+                continue;
+            };
+            let (node, brand) = match decl {
+                BrandedDecl::Node(node, brand) if self.nodes[node].kind == NodeKind::Annotation => {
+                    (node, brand)
+                }
+                _ => {
+                    self.error(
+                        ctx.file,
+                        name.start,
+                        name.end,
+                        format!("'{}' is not an annotation.", expression_string(name)),
+                    );
+                    continue;
+                }
+            };
+            let DeclKind::Annotation { targets, .. } = &self.nodes[node].decl.kind else {
+                unreachable!("not an annotation");
+            };
+            if !targets
+                .iter()
+                .any(|t| t.value == "*" || t.value == target.name())
+            {
+                self.error(
+                    ctx.file,
+                    name.start,
+                    name.end,
+                    format!(
+                        "'{}' cannot be applied to this kind of declaration.",
+                        expression_string(name)
+                    ),
+                );
+                continue;
+            }
+            let Some(type_) = self.annotation_type(node) else {
+                continue;
+            };
+            let type_ = self.substitute_type(&type_, &brand);
+            let value = match &application.value {
+                Some(value) => match self.compile_value(value, &type_, ctx) {
+                    Some(value) => value,
+                    None => continue,
+                },
+                None if matches!(type_, Type::Void) => Value::Void,
+                None => {
+                    self.error(
+                        ctx.file,
+                        name.start,
+                        name.end,
+                        format!("'{}' requires a value.", expression_string(name)),
+                    );
+                    continue;
+                }
+            };
+            result.push(CompiledAnnotation {
+                id: self.nodes[node].id,
+                brand,
+                type_,
+                value,
+            });
+        }
+        result
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Output
+
+    fn write_node(
+        &mut self,
+        node: NodeId,
+        mut builder: node::Builder,
+        source_info: &mut SourceInfo<'a>,
+    ) {
+        let decl = self.nodes[node].decl;
+        let ctx = self.node_ctx(node);
+        let id = self.nodes[node].id;
+        builder.set_id(id);
+        builder.set_display_name(&self.nodes[node].display_name);
+        let prefix_length = match self.nodes[node].kind {
+            NodeKind::File => display_name_prefix_length(&self.nodes[node].display_name),
+            _ => (self.nodes[node].display_name.len() - decl.name.value.len()) as u32,
+        };
+        builder.set_display_name_prefix_length(prefix_length);
+        builder.set_scope_id(self.nodes[node].parent.map_or(0, |p| self.nodes[p].id));
+        if self.param_count(node) > 0 {
+            let mut parameters = builder
+                .reborrow()
+                .init_parameters(decl.parameters.len() as u32);
+            for (i, parameter) in decl.parameters.iter().enumerate() {
+                parameters
+                    .reborrow()
+                    .get(i as u32)
+                    .set_name(&parameter.value);
+            }
+        }
+        builder.set_is_generic(self.is_generic(node));
+        {
+            let nested = &self.nodes[node].nested;
+            let mut list = builder.reborrow().init_nested_nodes(nested.len() as u32);
+            for (i, &n) in nested.iter().enumerate() {
+                let mut nested_node = list.reborrow().get(i as u32);
+                nested_node.set_name(&self.nodes[n].decl.name.value);
+                nested_node.set_id(self.nodes[n].id);
+            }
+        }
+        source_info.doc_comment = decl.doc_comment.as_deref();
+
+        let target = match self.nodes[node].kind {
+            NodeKind::File => Target::File,
+            NodeKind::Struct => Target::Struct,
+            NodeKind::Enum => Target::Enum,
+            NodeKind::Interface => Target::Interface,
+            NodeKind::Const => Target::Const,
+            NodeKind::Annotation => Target::Annotation,
+        };
+        let annotations = self.compile_annotations(&decl.annotations, target, &ctx);
+        if !annotations.is_empty() {
+            self.write_annotations(
+                builder
+                    .reborrow()
+                    .init_annotations(annotations.len() as u32),
+                &annotations,
+            );
+        }
+
+        match self.nodes[node].kind {
+            NodeKind::File => builder.set_file(()),
+            NodeKind::Struct => self.write_struct(id, builder.init_struct(), source_info),
+            NodeKind::Enum => self.write_enum(node, builder, source_info, &ctx),
+            NodeKind::Interface => self.write_interface(node, builder, source_info, &ctx),
+            NodeKind::Const => {
+                let mut builder = builder.init_const();
+                if let Some((type_, value)) = self.const_value(node) {
+                    self.write_type(builder.reborrow().init_type(), &type_);
+                    self.write_value(builder.init_value(), &type_, &value);
+                }
+            }
+            NodeKind::Annotation => {
+                let mut builder = builder.init_annotation();
+                if let Some(type_) = self.annotation_type(node) {
+                    self.write_type(builder.reborrow().init_type(), &type_);
+                }
+                let DeclKind::Annotation { targets, .. } = &decl.kind else {
+                    unreachable!("not an annotation");
+                };
+                for target in targets {
+                    let all = target.value == "*";
+                    let matched: Vec<Target> = Target::ALL
+                        .iter()
+                        .copied()
+                        .filter(|t| all || t.name() == target.value)
+                        .collect();
+                    if matched.is_empty() {
+                        self.error(
+                            ctx.file,
+                            target.start,
+                            target.end,
+                            format!("'{}' is not a valid annotation target.", target.value),
+                        );
+                    }
+                    for t in matched {
+                        match t {
+                            Target::File => builder.set_targets_file(true),
+                            Target::Const => builder.set_targets_const(true),
+                            Target::Enum => builder.set_targets_enum(true),
+                            Target::Enumerant => builder.set_targets_enumerant(true),
+                            Target::Struct => builder.set_targets_struct(true),
+                            Target::Field => builder.set_targets_field(true),
+                            Target::Union => builder.set_targets_union(true),
+                            Target::Group => builder.set_targets_group(true),
+                            Target::Interface => builder.set_targets_interface(true),
+                            Target::Method => builder.set_targets_method(true),
+                            Target::Param => builder.set_targets_param(true),
+                            Target::Annotation => builder.set_targets_annotation(true),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_aux_node(
+        &mut self,
+        id: u64,
+        mut builder: node::Builder,
+        source_info: &mut SourceInfo<'a>,
+    ) {
+        let info = &self.structs[&id];
+        builder.set_id(id);
+        builder.set_display_name(&info.display_name);
+        let name_length =
+            info.display_name.len() - info.display_name.rfind('.').map_or(0, |i| i + 1);
+        builder.set_display_name_prefix_length((info.display_name.len() - name_length) as u32);
+        builder.set_scope_id(info.scope_id);
+        builder.set_is_generic(info.is_generic);
+        if !info.parameters.is_empty() {
+            let mut parameters = builder
+                .reborrow()
+                .init_parameters(info.parameters.len() as u32);
+            for (i, parameter) in info.parameters.iter().enumerate() {
+                parameters
+                    .reborrow()
+                    .get(i as u32)
+                    .set_name(&parameter.value);
+            }
+        }
+        source_info.doc_comment = info.doc_comment;
+        self.write_struct(id, builder.init_struct(), source_info);
+    }
+
+    fn write_struct(
+        &mut self,
+        id: u64,
+        mut builder: node::struct_::Builder,
+        source_info: &mut SourceInfo<'a>,
+    ) {
+        let info = &self.structs[&id];
+        builder.set_data_word_count(info.data_word_count);
+        builder.set_pointer_count(info.pointer_count);
+        builder.set_preferred_list_encoding(capnp::schema_capnp::ElementSize::InlineComposite);
+        builder.set_is_group(info.is_group);
+        builder.set_discriminant_count(info.discriminant_count);
+        builder.set_discriminant_offset(info.discriminant_offset);
+        let fields = info.fields.clone();
+        let ctx = info.ctx.clone();
+        if fields.is_empty() {
+            return;
+        }
+        let mut list = builder.init_fields(fields.len() as u32);
+        for (index, field) in fields.iter().enumerate() {
+            let mut builder = list.reborrow().get(index as u32);
+            builder.set_name(field.name);
+            builder.set_code_order(field.code_order);
+            if let Some(discriminant) = field.discriminant {
+                builder.set_discriminant_value(discriminant);
+            }
+            if let Some(ordinal) = field.ordinal {
+                builder.reborrow().init_ordinal().set_explicit(ordinal);
+            }
+            source_info.members.push(field.doc_comment);
+            let annotations = self.compile_annotations(field.annotations, field.target, &ctx);
+            if !annotations.is_empty() {
+                self.write_annotations(
+                    builder
+                        .reborrow()
+                        .init_annotations(annotations.len() as u32),
+                    &annotations,
+                );
+            }
+            match &field.kind {
+                FieldKind::Slot {
+                    type_,
+                    offset,
+                    default,
+                } => {
+                    let mut slot = builder.init_slot();
+                    slot.set_offset(*offset);
+                    let type_ = type_.clone().unwrap_or(Type::Void);
+                    self.write_type(slot.reborrow().init_type(), &type_);
+                    let value = match self.field_defaults.get(&(id, index)) {
+                        Some(value) => value.clone(),
+                        None => Value::default_for(&type_),
+                    };
+                    self.write_value(slot.reborrow().init_default_value(), &type_, &value);
+                    if default.is_some() {
+                        slot.set_had_explicit_default(true);
+                    }
+                }
+                FieldKind::Group(group_id) => builder.init_group().set_type_id(*group_id),
+            }
+        }
+    }
+
+    fn write_enum(
+        &mut self,
+        node: NodeId,
+        builder: node::Builder,
+        source_info: &mut SourceInfo<'a>,
+        ctx: &Ctx<'a>,
+    ) {
+        let decl = self.nodes[node].decl;
+        let mut enumerants: Vec<(u64, u16, &'a Declaration)> = Vec::new();
+        for member in &decl.nested {
+            if let (DeclKind::Enumerant, DeclId::Ordinal(ordinal, ..)) = (&member.kind, member.id) {
+                enumerants.push((ordinal, enumerants.len() as u16, member));
+            }
+        }
+        enumerants.sort_by_key(|&(ordinal, ..)| ordinal);
+        let mut checker = OrdinalChecker::default();
+        let mut list = builder.init_enum().init_enumerants(enumerants.len() as u32);
+        for (i, (ordinal, code_order, member)) in enumerants.into_iter().enumerate() {
+            if let DeclId::Ordinal(_, start, end) = member.id {
+                self.check_ordinal(&mut checker, ctx.file, ordinal, start, end);
+            }
+            let mut builder = list.reborrow().get(i as u32);
+            builder.set_name(&member.name.value);
+            builder.set_code_order(code_order);
+            let annotations = self.compile_annotations(&member.annotations, Target::Enumerant, ctx);
+            if !annotations.is_empty() {
+                self.write_annotations(
+                    builder.init_annotations(annotations.len() as u32),
+                    &annotations,
+                );
+            }
+            source_info.members.push(member.doc_comment.as_deref());
+        }
+    }
+
+    fn write_interface(
+        &mut self,
+        node: NodeId,
+        builder: node::Builder,
+        source_info: &mut SourceInfo<'a>,
+        ctx: &Ctx<'a>,
+    ) {
+        let decl = self.nodes[node].decl;
+        let DeclKind::Interface { superclasses } = &decl.kind else {
+            unreachable!("not an interface");
+        };
+        let mut builder = builder.init_interface();
+        {
+            let mut list = builder
+                .reborrow()
+                .init_superclasses(superclasses.len() as u32);
+            for (i, superclass) in superclasses.iter().enumerate() {
+                match self.compile_decl_expression(superclass, ctx) {
+                    Some(BrandedDecl::Node(n, brand))
+                        if self.nodes[n].kind == NodeKind::Interface =>
+                    {
+                        let mut builder = list.reborrow().get(i as u32);
+                        builder.set_id(self.nodes[n].id);
+                        self.write_brand(&brand, || builder.init_brand());
+                    }
+                    Some(_) => self.error(
+                        ctx.file,
+                        superclass.start,
+                        superclass.end,
+                        format!("'{}' is not an interface.", expression_string(superclass)),
+                    ),
+                    None => (),
+                }
+            }
+        }
+
+        let mut methods: Vec<(u64, u16, &'a Declaration)> = Vec::new();
+        for member in &decl.nested {
+            if let (DeclKind::Method { .. }, DeclId::Ordinal(ordinal, ..)) =
+                (&member.kind, member.id)
+            {
+                methods.push((ordinal, methods.len() as u16, member));
+            }
+        }
+        methods.sort_by_key(|&(ordinal, ..)| ordinal);
+        let mut checker = OrdinalChecker::default();
+        let mut list = builder.init_methods(methods.len() as u32);
+        for (i, (ordinal, code_order, method)) in methods.into_iter().enumerate() {
+            let DeclKind::Method { params, results } = &method.kind else {
+                unreachable!("not a method");
+            };
+            if let DeclId::Ordinal(_, start, end) = method.id {
+                self.check_ordinal(&mut checker, ctx.file, ordinal, start, end);
+            }
+            let mut builder = list.reborrow().get(i as u32);
+            builder.set_name(&method.name.value);
+            builder.set_code_order(code_order);
+            {
+                let mut implicit = builder
+                    .reborrow()
+                    .init_implicit_parameters(method.parameters.len() as u32);
+                for (j, parameter) in method.parameters.iter().enumerate() {
+                    implicit.reborrow().get(j as u32).set_name(&parameter.value);
+                }
+            }
+
+            if let ParamList::Stream(start, end) = params {
+                self.error(
+                    ctx.file,
+                    *start,
+                    *end,
+                    "'stream' can only appear after '->', not before.",
+                );
+            }
+            let (id, brand) =
+                self.param_list_type(node, method, ordinal as u16, false, Some(params), ctx);
+            builder.set_param_struct_type(id);
+            if let Some(brand) = brand {
+                self.write_brand(&brand, || builder.reborrow().init_param_brand());
+            }
+            let (id, brand) =
+                self.param_list_type(node, method, ordinal as u16, true, results.as_ref(), ctx);
+            builder.set_result_struct_type(id);
+            if let Some(brand) = brand {
+                self.write_brand(&brand, || builder.reborrow().init_result_brand());
+            }
+
+            let annotations = self.compile_annotations(&method.annotations, Target::Method, ctx);
+            if !annotations.is_empty() {
+                self.write_annotations(
+                    builder.init_annotations(annotations.len() as u32),
+                    &annotations,
+                );
+            }
+            source_info.members.push(method.doc_comment.as_deref());
+        }
+    }
+
+    /// Returns the struct type of a method's parameters or results, and its brand.
+    fn param_list_type(
+        &mut self,
+        node: NodeId,
+        method: &'a Declaration,
+        ordinal: u16,
+        is_results: bool,
+        params: Option<&'a ParamList>,
+        ctx: &Ctx<'a>,
+    ) -> (u64, Option<Rc<BrandScope>>) {
+        match params {
+            None | Some(ParamList::Named(_)) => {
+                let id = method_params_id(self.nodes[node].id, ordinal, is_results);
+                let count = method.parameters.len();
+                let mut brand = ctx.brand.push(id, count);
+                if count > 0 {
+                    brand = Rc::new(BrandScope {
+                        parent: brand.parent.clone(),
+                        leaf_id: id,
+                        leaf_param_count: count,
+                        inherited: false,
+                        params: (0..count as u16)
+                            .map(BrandedDecl::ImplicitMethodParam)
+                            .collect(),
+                    });
+                }
+                (id, Some(brand))
+            }
+            Some(ParamList::Type(expr)) => {
+                let ctx = Ctx {
+                    implicit: Implicit::Method(&method.parameters),
+                    ..ctx.clone()
+                };
+                match self.compile_decl_expression(expr, &ctx) {
+                    Some(BrandedDecl::Node(n, brand)) if self.nodes[n].kind == NodeKind::Struct => {
+                        (self.nodes[n].id, Some(brand))
+                    }
+                    Some(BrandedDecl::Param(..) | BrandedDecl::ImplicitMethodParam(_)) => {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            "Cannot use generic parameter as whole input or output of a method. \
+                             Instead, use a parameter/result list containing a field with this type.",
+                        );
+                        (0, None)
+                    }
+                    Some(_) => {
+                        self.error(
+                            ctx.file,
+                            expr.start,
+                            expr.end,
+                            format!("'{}' is not a struct type.", expression_string(expr)),
+                        );
+                        (0, None)
+                    }
+                    None => (0, None),
+                }
+            }
+            Some(ParamList::Stream(start, end)) => {
+                let stream_result = self.stream_file.and_then(|file| {
+                    self.nodes[self.file_nodes[file]]
+                        .members
+                        .get("StreamResult")
+                        .copied()
+                });
+                match stream_result {
+                    Some(Member::Node(n)) => (self.nodes[n].id, None),
+                    _ => {
+                        self.error(
+                            ctx.file,
+                            *start,
+                            *end,
+                            "The version of '/capnp/stream.capnp' found in your import path does not \
+                             appear to be correct.",
+                        );
+                        (0, None)
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_annotations(
+        &self,
+        mut list: struct_list::Builder<annotation::Owned>,
+        annotations: &[CompiledAnnotation],
+    ) {
+        for (i, annotation) in annotations.iter().enumerate() {
+            let mut builder = list.reborrow().get(i as u32);
+            builder.set_id(annotation.id);
+            self.write_brand(&annotation.brand, || builder.reborrow().init_brand());
+            self.write_value(builder.init_value(), &annotation.type_, &annotation.value);
+        }
+    }
+
+    /// Writes `scope` with the builder returned by `init`, unless it has no bindings to record.
+    fn write_brand<'b>(&self, scope: &BrandScope, init: impl FnOnce() -> brand::Builder<'b>) {
+        let levels = scope.levels();
+        if levels.is_empty() {
+            return;
+        }
+        let mut scopes = init().init_scopes(levels.len() as u32);
+        for (i, level) in levels.iter().enumerate() {
+            let mut builder = scopes.reborrow().get(i as u32);
+            builder.set_scope_id(level.leaf_id);
+            if level.inherited {
+                builder.set_inherit(());
+            } else {
+                let mut bindings = builder.init_bind(level.params.len() as u32);
+                for (j, param) in level.params.iter().enumerate() {
+                    let type_ = self
+                        .to_type(param)
+                        .unwrap_or(Type::AnyPointer(AnyPointer::Any));
+                    self.write_type(bindings.reborrow().get(j as u32).init_type(), &type_);
+                }
+            }
+        }
+    }
+
+    fn write_type(&self, mut builder: type_::Builder, type_: &Type) {
+        match type_ {
+            Type::Void => builder.set_void(()),
+            Type::Bool => builder.set_bool(()),
+            Type::Int8 => builder.set_int8(()),
+            Type::Int16 => builder.set_int16(()),
+            Type::Int32 => builder.set_int32(()),
+            Type::Int64 => builder.set_int64(()),
+            Type::UInt8 => builder.set_uint8(()),
+            Type::UInt16 => builder.set_uint16(()),
+            Type::UInt32 => builder.set_uint32(()),
+            Type::UInt64 => builder.set_uint64(()),
+            Type::Float32 => builder.set_float32(()),
+            Type::Float64 => builder.set_float64(()),
+            Type::Text => builder.set_text(()),
+            Type::Data => builder.set_data(()),
+            Type::List(element) => {
+                self.write_type(builder.init_list().init_element_type(), element)
+            }
+            Type::Enum(node, brand) => {
+                let mut builder = builder.init_enum();
+                builder.set_type_id(self.nodes[*node].id);
+                self.write_brand(brand, || builder.init_brand());
+            }
+            Type::Struct(node, brand) => {
+                let mut builder = builder.init_struct();
+                builder.set_type_id(self.nodes[*node].id);
+                self.write_brand(brand, || builder.init_brand());
+            }
+            Type::Interface(node, brand) => {
+                let mut builder = builder.init_interface();
+                builder.set_type_id(self.nodes[*node].id);
+                self.write_brand(brand, || builder.init_brand());
+            }
+            Type::AnyPointer(kind) => {
+                let builder = builder.init_any_pointer();
+                match kind {
+                    AnyPointer::Any => builder.init_unconstrained().set_any_kind(()),
+                    AnyPointer::Struct => builder.init_unconstrained().set_struct(()),
+                    AnyPointer::List => builder.init_unconstrained().set_list(()),
+                    AnyPointer::Capability => builder.init_unconstrained().set_capability(()),
+                    AnyPointer::Parameter(scope_id, index) => {
+                        let mut builder = builder.init_parameter();
+                        builder.set_scope_id(*scope_id);
+                        builder.set_parameter_index(*index);
+                    }
+                    AnyPointer::ImplicitMethodParameter(index) => builder
+                        .init_implicit_method_parameter()
+                        .set_parameter_index(*index),
+                }
+            }
+        }
+    }
+
+    fn write_value(&self, mut builder: value::Builder, type_: &Type, value: &Value) {
+        match type_ {
+            Type::Void => builder.set_void(()),
+            Type::Bool => builder.set_bool(value.bits(type_) != 0),
+            Type::Int8 => builder.set_int8(value.bits(type_) as i8),
+            Type::Int16 => builder.set_int16(value.bits(type_) as i16),
+            Type::Int32 => builder.set_int32(value.bits(type_) as i32),
+            Type::Int64 => builder.set_int64(value.bits(type_) as i64),
+            Type::UInt8 => builder.set_uint8(value.bits(type_) as u8),
+            Type::UInt16 => builder.set_uint16(value.bits(type_) as u16),
+            Type::UInt32 => builder.set_uint32(value.bits(type_) as u32),
+            Type::UInt64 => builder.set_uint64(value.bits(type_)),
+            Type::Float32 => builder.set_float32(f32::from_bits(value.bits(type_) as u32)),
+            Type::Float64 => builder.set_float64(f64::from_bits(value.bits(type_))),
+            Type::Enum(..) => builder.set_enum(value.bits(type_) as u16),
+            Type::Text => match value {
+                Value::Text(text) => builder.set_text(text),
+                _ => {
+                    // A null pointer, rather than an empty string.
+                    let null = builder.disown_text();
+                    let _ = builder.adopt_text(null);
+                }
+            },
+            Type::Data => match value {
+                Value::Data(data) => builder.set_data(data),
+                _ => {
+                    let null = builder.disown_data();
+                    let _ = builder.adopt_data(null);
+                }
+            },
+            Type::List(_) => self.write_pointer(raw_pointer(builder.init_list()), value),
+            Type::Struct(..) => self.write_pointer(raw_pointer(builder.init_struct()), value),
+            Type::Interface(..) => builder.set_interface(()),
+            Type::AnyPointer(_) => {
+                self.write_pointer(raw_pointer(builder.init_any_pointer()), value)
+            }
+        }
+    }
+
+    fn struct_size(&self, id: u64) -> StructSize {
+        self.structs.get(&id).map_or(
+            StructSize {
+                data: 0,
+                pointers: 0,
+            },
+            |info| StructSize {
+                data: info.data_word_count,
+                pointers: info.pointer_count,
+            },
+        )
+    }
+
+    fn write_pointer(&self, mut builder: PointerBuilder, value: &Value) {
+        match value {
+            Value::Text(text) => builder.set_text(text),
+            Value::Data(data) => builder.set_data(data),
+            Value::List(element, items) => self.write_list(builder, element, items),
+            Value::Struct(id, fields) => {
+                let mut builder = builder.init_struct(self.struct_size(*id));
+                self.write_struct_value(&mut builder, *id, fields);
+            }
+            _ => (),
+        }
+    }
+
+    fn write_list(&self, builder: PointerBuilder, element: &Type, items: &[Value]) {
+        let count = items.len() as u32;
+        let element_size = match (element, element.data_size()) {
+            (Type::Struct(node, _), _) => {
+                let id = self.nodes[*node].id;
+                let mut list = builder.init_struct_list(count, self.struct_size(id));
+                for (i, item) in items.iter().enumerate() {
+                    if let Value::Struct(id, fields) = item {
+                        self.write_struct_value(
+                            &mut list.reborrow().get_struct_element(i as u32),
+                            *id,
+                            fields,
+                        );
+                    }
+                }
+                return;
+            }
+            (_, DataSize::Void) => ElementSize::Void,
+            (_, DataSize::Data(0)) => ElementSize::Bit,
+            (_, DataSize::Data(3)) => ElementSize::Byte,
+            (_, DataSize::Data(4)) => ElementSize::TwoBytes,
+            (_, DataSize::Data(5)) => ElementSize::FourBytes,
+            (_, DataSize::Data(_)) => ElementSize::EightBytes,
+            (_, DataSize::Pointer) => ElementSize::Pointer,
+        };
+        let mut list = builder.init_list(element_size, count);
+        for (i, item) in items.iter().enumerate() {
+            let i = i as u32;
+            let bits = item.bits(element);
+            match element_size {
+                ElementSize::Bit => PrimitiveElement::set(&list, i, bits != 0),
+                ElementSize::Byte => PrimitiveElement::set(&list, i, bits as u8),
+                ElementSize::TwoBytes => PrimitiveElement::set(&list, i, bits as u16),
+                ElementSize::FourBytes => PrimitiveElement::set(&list, i, bits as u32),
+                ElementSize::EightBytes => PrimitiveElement::set(&list, i, bits),
+                ElementSize::Pointer => {
+                    self.write_pointer(list.reborrow().get_pointer_element(i), item)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Sets fields of a struct as `DynamicStruct` would, storing data fields XORed with their
+    /// defaults.
+    fn write_struct_value(&self, builder: &mut StructBuilder, id: u64, fields: &[(usize, Value)]) {
+        let Some(info) = self.structs.get(&id) else {
+            return;
+        };
+        for (index, value) in fields {
+            let field = &info.fields[*index];
+            if let Some(discriminant) = field.discriminant {
+                builder.set_data_field::<u16>(info.discriminant_offset as usize, discriminant);
+            }
+            match &field.kind {
+                FieldKind::Slot {
+                    type_: Some(type_),
+                    offset,
+                    ..
+                } => {
+                    let mask = self
+                        .field_defaults
+                        .get(&(id, *index))
+                        .map_or(0, |d| d.bits(type_));
+                    let bits = value.bits(type_) ^ mask;
+                    let offset = *offset as usize;
+                    match type_.data_size() {
+                        DataSize::Void => (),
+                        DataSize::Data(0) => builder.set_bool_field(offset, bits != 0),
+                        DataSize::Data(3) => builder.set_data_field::<u8>(offset, bits as u8),
+                        DataSize::Data(4) => builder.set_data_field::<u16>(offset, bits as u16),
+                        DataSize::Data(5) => builder.set_data_field::<u32>(offset, bits as u32),
+                        DataSize::Data(_) => builder.set_data_field::<u64>(offset, bits),
+                        DataSize::Pointer => {
+                            self.write_pointer(builder.reborrow().get_pointer_field(offset), value)
+                        }
+                    }
+                }
+                FieldKind::Slot { type_: None, .. } => (),
+                FieldKind::Group(_) => {
+                    if let Value::Struct(group_id, group_fields) = value {
+                        self.write_struct_value(builder, *group_id, group_fields);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Assigns a member its index within its parent, and its discriminant if it's in a union. This
+/// happens in ordinal order, so that the first member with the lowest ordinal comes first.
+fn get_schema(st: &mut StructTranslation, member: usize) {
+    if st.members[member].index.is_some() {
+        return;
+    }
+    let parent = st.members[member].parent.expect("root has no index");
+    if st.members[parent].child_initialized_count == 0 && st.members[parent].parent.is_some() {
+        // A group exists in its parent once its first member does.
+        get_schema(st, parent);
+    }
+    let parent_info = &mut st.members[parent];
+    let index = parent_info.child_initialized_count;
+    parent_info.child_initialized_count += 1;
+    let discriminant = if st.members[member].is_in_union {
+        let parent_info = &mut st.members[parent];
+        parent_info.union_discriminant_count += 1;
+        Some(parent_info.union_discriminant_count - 1)
+    } else {
+        None
+    };
+    st.members[member].index = Some(index);
+    st.members[member].discriminant = discriminant;
+}
+
+/// Rewrites a declaration that was resolved inside an alias, for use through `client`.
+fn substitute(decl: &BrandedDecl, client: &Rc<BrandScope>) -> BrandedDecl {
+    match decl {
+        BrandedDecl::Node(node, brand) => BrandedDecl::Node(*node, substitute_scope(brand, client)),
+        BrandedDecl::List(element) => BrandedDecl::List(Box::new(substitute(element, client))),
+        BrandedDecl::Param(scope_id, index) => client
+            .lookup_parameter(*scope_id, *index)
+            .unwrap_or_else(|| decl.clone()),
+        _ => decl.clone(),
+    }
+}
+
+fn substitute_scope(scope: &Rc<BrandScope>, client: &Rc<BrandScope>) -> Rc<BrandScope> {
+    let (inherited, params) = if scope.inherited {
+        match client.get_params(scope.leaf_id) {
+            Some(params) => (false, params.to_vec()),
+            None => (true, Vec::new()),
+        }
+    } else {
+        (
+            false,
+            scope.params.iter().map(|p| substitute(p, client)).collect(),
+        )
+    };
+    Rc::new(BrandScope {
+        parent: scope.parent.as_ref().map(|p| substitute_scope(p, client)),
+        leaf_id: scope.leaf_id,
+        leaf_param_count: scope.leaf_param_count,
+        inherited,
+        params,
+    })
+}
+
+fn expression_string(expr: &Expression) -> String {
+    match &expr.kind {
+        ExpressionKind::RelativeName(name) => name.clone(),
+        ExpressionKind::AbsoluteName(name) => format!(".{name}"),
+        ExpressionKind::Import(path) => format!("import \"{path}\""),
+        ExpressionKind::Member(base, name) => format!("{}.{}", expression_string(base), name.value),
+        ExpressionKind::Application(base, params) => {
+            let params: Vec<String> = params.iter().map(|p| expression_string(&p.value)).collect();
+            format!("{}({})", expression_string(base), params.join(", "))
+        }
+        _ => "<expression>".into(),
+    }
+}
+
+/// Gives access to the raw pointer of an `AnyPointer` field, for writing values of any type.
+struct RawPointer<'a>(PointerBuilder<'a>);
+
+impl<'a> FromPointerBuilder<'a> for RawPointer<'a> {
+    fn init_pointer(builder: PointerBuilder<'a>, _length: u32) -> Self {
+        Self(builder)
+    }
+
+    fn get_from_pointer(
+        builder: PointerBuilder<'a>,
+        _default: Option<&'a [capnp::Word]>,
+    ) -> capnp::Result<Self> {
+        Ok(Self(builder))
+    }
+}
+
+fn raw_pointer(builder: any_pointer::Builder) -> PointerBuilder {
+    builder.init_as::<RawPointer>().0
+}
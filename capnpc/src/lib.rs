@@ -23,9 +23,10 @@
 //!
 //! This library allows you to do
 //! [Cap'n Proto code generation](https://capnproto.org/otherlang.html#how-to-write-compiler-plugins)
-//! within a Cargo build. You still need the `capnp` binary (implemented in C++).
+//! within a Cargo build. By default this runs the `capnp` binary (implemented in C++).
 //! (If you use a package manager, try looking for a package called
-//! `capnproto`.)
+//! `capnproto`.) Alternatively, [`CompilerCommand::builtin_compiler`] parses the
+//! schema files in Rust, with no external tools needed.
 //!
 //! In your Cargo.toml:
 //!
@@ -69,6 +70,8 @@
 
 pub mod codegen;
 pub mod codegen_types;
+pub mod compiler;
+mod native_types;
 mod pointer_constants;
 
 use std::path::{Path, PathBuf};
@@ -119,6 +122,10 @@ pub struct CompilerCommand {
     output_path: Option<PathBuf>,
     default_parent_module: Vec<String>,
     raw_code_generator_request_path: Option<PathBuf>,
+    builtin_compiler: bool,
+    native_types: bool,
+    async_servers: bool,
+    sync_rpc: bool,
 }
 
 impl CompilerCommand {
@@ -182,6 +189,35 @@ impl CompilerCommand {
         self
     }
 
+    /// Compiles the schema files with the pure-Rust compiler in [`compiler`] rather than the
+    /// `capnp` executable, which then doesn't need to be installed.
+    pub fn builtin_compiler(&mut self) -> &mut Self {
+        self.builtin_compiler = true;
+        self
+    }
+
+    /// Generates a plain owned `Native` type for each struct, alongside its `Reader` and
+    /// `Builder`. See [`codegen::CodeGenerationCommand::native_types`] for details.
+    pub fn native_types(&mut self) -> &mut Self {
+        self.native_types = true;
+        self
+    }
+
+    /// Generates `Server` traits whose methods take `self: Rc<Self>`, so that a call can keep
+    /// using the server while it awaits. See [`codegen::CodeGenerationCommand::async_servers`].
+    pub fn async_servers(&mut self) -> &mut Self {
+        self.async_servers = true;
+        self
+    }
+
+    /// Generates clients and servers built on the thread-safe capability types in `capnp::sync`,
+    /// which can be served and called from any thread. See
+    /// [`codegen::CodeGenerationCommand::sync_rpc`].
+    pub fn sync_rpc(&mut self) -> &mut Self {
+        self.sync_rpc = true;
+        self
+    }
+
     /// Internal function for starting to build a capnp command.
     fn new_command(&self) -> ::std::process::Command {
         if let Some(executable) = &self.executable_path {
@@ -227,6 +263,10 @@ impl CompilerCommand {
     /// Runs the command.
     /// Returns an error if `OUT_DIR` or a custom output directory was not set, or if `capnp compile` fails.
     pub fn run(&mut self) -> ::capnp::Result<()> {
+        if self.builtin_compiler {
+            return self.run_builtin_compiler();
+        }
+
         match self.new_command().arg("--version").output() {
             Err(error) => {
                 return Err(::capnp::Error::failed(format!(
@@ -269,6 +309,46 @@ impl CompilerCommand {
             command.arg(&format!("--src-prefix={}", src_prefix.display()));
         }
 
+        self.stat_files()?;
+        for file in &self.files {
+            command.arg(file);
+        }
+
+        command.stdout(::std::process::Stdio::piped());
+        command.stderr(::std::process::Stdio::inherit());
+
+        let code_generation_command = self.code_generation_command()?;
+
+        run_command(command, code_generation_command).map_err(|error| {
+            ::capnp::Error::failed(format!(
+                "Error while trying to execute `capnp compile`: {error}."
+            ))
+        })
+    }
+
+    fn run_builtin_compiler(&self) -> ::capnp::Result<()> {
+        self.stat_files()?;
+        let mut code_generation_command = self.code_generation_command()?;
+
+        let mut compiler = compiler::Compiler::new();
+        for file in &self.files {
+            compiler.file(file);
+        }
+        for src_prefix in &self.src_prefixes {
+            compiler.src_prefix(src_prefix);
+        }
+        for import_path in &self.import_paths {
+            compiler.import_path(import_path);
+        }
+        if self.no_standard_import {
+            compiler.no_standard_import();
+        }
+        let message = compiler.compile()?;
+        let words = ::capnp::serialize::write_message_to_words(&message);
+        code_generation_command.run(&words[..])
+    }
+
+    fn stat_files(&self) -> ::capnp::Result<()> {
         for file in &self.files {
             std::fs::metadata(file).map_err(|error| {
                 let current_dir = match std::env::current_dir() {
@@ -284,10 +364,11 @@ impl CompilerCommand {
                     error
                 ))
             })?;
-
-            command.arg(file);
         }
+        Ok(())
+    }
 
+    fn code_generation_command(&self) -> ::capnp::Result<codegen::CodeGenerationCommand> {
         let output_path = if let Some(output_path) = &self.output_path {
             output_path.clone()
         } else {
@@ -301,26 +382,33 @@ impl CompilerCommand {
             })?)
         };
 
-        command.stdout(::std::process::Stdio::piped());
-        command.stderr(::std::process::Stdio::inherit());
-
         let mut code_generation_command = crate::codegen::CodeGenerationCommand::new();
         code_generation_command
             .output_directory(output_path)
-            .default_parent_module(self.default_parent_module.clone());
+            .default_parent_module(self.default_parent_module.clone())
+            .native_types(self.native_types)
+            .async_servers(self.async_servers)
+            .sync_rpc(self.sync_rpc);
         if let Some(raw_code_generator_request_path) = &self.raw_code_generator_request_path {
             code_generation_command
                 .raw_code_generator_request_path(raw_code_generator_request_path.clone());
         }
-
-        run_command(command, code_generation_command).map_err(|error| {
-            ::capnp::Error::failed(format!(
-                "Error while trying to execute `capnp compile`: {error}."
-            ))
-        })
+        Ok(code_generation_command)
     }
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn builtin_compiler_no_out_dir() {
+    std::env::remove_var("OUT_DIR");
+    let error = CompilerCommand::new()
+        .builtin_compiler()
+        .run()
+        .unwrap_err()
+        .description;
+    assert!(error.starts_with("Could not access `OUT_DIR` environment variable"));
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn compiler_command_new_no_out_dir() {
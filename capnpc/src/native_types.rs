@@ -0,0 +1,642 @@
+// Copyright (c) 2013-2023 Sandstorm Development Group, Inc. and contributors
+// Licensed under the MIT License:
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Generation of plain owned Rust types for structs, enabled by
+//! [`CodeGenerationCommand::native_types()`](crate::codegen::CodeGenerationCommand::native_types).
+//!
+//! Every non-generic struct (including groups) gets a `Native` type in its module, next to
+//! `Reader` and `Builder`. If the struct has an anonymous union, the union becomes an enum:
+//! `Native` itself when all fields are union members, otherwise a `NativeWhich` stored in
+//! `Native::which`.
+
+use std::collections::HashSet;
+
+use capnp::schema_capnp::{field, node, type_};
+
+use crate::codegen::FormattedText::{BlankLine, Branch, Line};
+use crate::codegen::{
+    camel_to_snake_case, capitalize_first_letter, fmt, get_field_name, indent, is_option_field,
+    line, module_name, FormattedText, GeneratorContext,
+};
+use crate::codegen_types::{Leaf, RustTypeInfo};
+
+/// Returns the fields of a struct node, in the order they were declared.
+fn fields_in_code_order<'a>(
+    ctx: &GeneratorContext<'a>,
+    node_id: u64,
+) -> capnp::Result<Vec<field::Reader<'a>>> {
+    let node::Struct(st) = ctx.node_map[&node_id].which()? else {
+        return Err(capnp::Error::failed(format!("not a struct: {node_id}")));
+    };
+    let mut fields: Vec<_> = st.get_fields()?.iter().collect();
+    fields.sort_by_key(|f| f.get_code_order());
+    Ok(fields)
+}
+
+/// The struct nodes whose native types are stored inline in the native type of `node_id`.
+fn inline_members(ctx: &GeneratorContext, node_id: u64) -> capnp::Result<Vec<u64>> {
+    let mut result = Vec::new();
+    for field in fields_in_code_order(ctx, node_id)? {
+        match field.which()? {
+            field::Group(group) => result.push(group.get_type_id()),
+            field::Slot(slot) => {
+                if let type_::Struct(st) = slot.get_type()?.which()? {
+                    if !ctx.node_map[&st.get_type_id()].get_is_generic() {
+                        result.push(st.get_type_id());
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn reaches(
+    ctx: &GeneratorContext,
+    from: u64,
+    goal: u64,
+    visited: &mut HashSet<u64>,
+) -> capnp::Result<bool> {
+    if from == goal {
+        return Ok(true);
+    }
+    if !visited.insert(from) {
+        return Ok(false);
+    }
+    for id in inline_members(ctx, from)? {
+        if reaches(ctx, id, goal, visited)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Whether a struct field of type `member_id` in the native type of `owner_id` is part of a
+/// cycle. Such fields are stored as `Option<Box<_>>`: the box keeps the type finitely large, and
+/// mapping null pointers to `None` (rather than to a default value) keeps conversions finite.
+fn is_recursive(ctx: &GeneratorContext, owner_id: u64, member_id: u64) -> capnp::Result<bool> {
+    reaches(ctx, member_id, owner_id, &mut HashSet::new())
+}
+
+fn type_has_capabilities(
+    ctx: &GeneratorContext,
+    ty: type_::Reader,
+    visited: &mut HashSet<u64>,
+) -> capnp::Result<bool> {
+    match ty.which()? {
+        type_::Interface(_) => Ok(true),
+        type_::List(ls) => type_has_capabilities(ctx, ls.get_element_type()?, visited),
+        type_::Struct(st) if !ctx.node_map[&st.get_type_id()].get_is_generic() => {
+            has_capabilities(ctx, st.get_type_id(), visited)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Capability clients implement neither `Debug` nor `PartialEq`, so native types that
+/// contain them can only derive `Clone`.
+fn has_capabilities(
+    ctx: &GeneratorContext,
+    node_id: u64,
+    visited: &mut HashSet<u64>,
+) -> capnp::Result<bool> {
+    if !visited.insert(node_id) {
+        return Ok(false);
+    }
+    for field in fields_in_code_order(ctx, node_id)? {
+        let found = match field.which()? {
+            field::Group(group) => has_capabilities(ctx, group.get_type_id(), visited)?,
+            field::Slot(slot) => type_has_capabilities(ctx, slot.get_type()?, visited)?,
+        };
+        if found {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The native type corresponding to `ty`, or `None` if there isn't one.
+fn native_type(ctx: &GeneratorContext, ty: type_::Reader) -> capnp::Result<Option<String>> {
+    Ok(Some(match ty.which()? {
+        type_::Text(()) => "String".into(),
+        type_::Data(()) => "Vec<u8>".into(),
+        type_::Enum(e) => ctx.get_qualified_module(e.get_type_id()),
+        type_::Struct(st) => {
+            if ctx.node_map[&st.get_type_id()].get_is_generic() {
+                return Ok(None);
+            }
+            format!("{}::Native", ctx.get_qualified_module(st.get_type_id()))
+        }
+        type_::Interface(_) => ty.type_string(ctx, Leaf::Client)?,
+        type_::List(ls) => match native_type(ctx, ls.get_element_type()?)? {
+            Some(element) => format!("Vec<{element}>"),
+            None => return Ok(None),
+        },
+        type_::AnyPointer(_) => return Ok(None),
+        _ => ty.type_string(ctx, Leaf::Owned)?,
+    }))
+}
+
+/// An expression converting `value`, which has the type returned by the reader getters for
+/// `ty` with any `Result` already unwrapped, into its native type.
+fn read_value(
+    ctx: &GeneratorContext,
+    ty: type_::Reader,
+    value: &str,
+    depth: usize,
+) -> capnp::Result<String> {
+    Ok(match ty.which()? {
+        type_::Text(()) => format!("String::from({value})"),
+        type_::Data(()) => format!("{value}.to_vec()"),
+        type_::Struct(st) => format!(
+            "{}::Native::from_reader({value})?",
+            ctx.get_qualified_module(st.get_type_id())
+        ),
+        type_::List(ls) => {
+            let element_type = ls.get_element_type()?;
+            let element = native_type(ctx, element_type)?
+                .ok_or_else(|| capnp::Error::failed("unsupported list element".into()))?;
+            match element_type.which()? {
+                _ if element_type.is_prim()? => {
+                    format!("{value}.iter().collect::<Vec<{element}>>()")
+                }
+                type_::Struct(_) => fmt!(
+                    ctx,
+                    "{value}.iter().map({element}::from_reader).collect::<{capnp}::Result<Vec<{element}>>>()?"
+                ),
+                _ => {
+                    // Everything else is read from the list as a `Result`.
+                    let e = format!("e{depth}");
+                    let read = read_value(ctx, element_type, &format!("{e}?"), depth + 1)?;
+                    fmt!(
+                        ctx,
+                        "{value}.iter().map(|{e}| -> {capnp}::Result<{element}> {{ ::core::result::Result::Ok({read}) }}).collect::<{capnp}::Result<Vec<{element}>>>()?"
+                    )
+                }
+            }
+        }
+        _ => value.to_string(),
+    })
+}
+
+/// How to refer to a native value when writing it: by method receiver, by reference, and
+/// by copy (for primitives).
+struct Value {
+    method: String,
+    reference: String,
+    copy: String,
+}
+
+impl Value {
+    fn field(name: &str) -> Self {
+        Self {
+            method: format!("self.{name}"),
+            reference: format!("&self.{name}"),
+            copy: format!("self.{name}"),
+        }
+    }
+
+    fn borrowed(name: &str) -> Self {
+        Self {
+            method: name.into(),
+            reference: name.into(),
+            copy: format!("*{name}"),
+        }
+    }
+}
+
+/// Where a value gets written: a field of `builder`, or element `i{depth}` of `list{depth}`.
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Field(&'a str),
+    Element(usize),
+}
+
+impl<'a> Target<'a> {
+    fn set(self, value: &str) -> String {
+        match self {
+            Target::Field(name) => format!("builder.set_{name}({value});"),
+            Target::Element(d) => format!("list{d}.set(i{d} as u32, {value});"),
+        }
+    }
+
+    fn init_struct(self) -> String {
+        match self {
+            Target::Field(name) => format!("builder.reborrow().init_{name}()"),
+            Target::Element(d) => format!("list{d}.reborrow().get(i{d} as u32)"),
+        }
+    }
+
+    fn init_list(self, len: &str) -> String {
+        match self {
+            Target::Field(name) => format!("builder.reborrow().init_{name}({len})"),
+            Target::Element(d) => format!("list{d}.reborrow().init(i{d} as u32, {len})"),
+        }
+    }
+}
+
+fn write_value(
+    ctx: &GeneratorContext,
+    ty: type_::Reader,
+    value: &Value,
+    target: Target,
+    depth: usize,
+) -> capnp::Result<FormattedText> {
+    Ok(match ty.which()? {
+        type_::Void(()) => line(target.set("()")),
+        type_::Text(()) | type_::Data(()) => line(target.set(&value.reference)),
+        type_::Struct(_) => Line(format!(
+            "{}.write_to({})?;",
+            value.method,
+            target.init_struct()
+        )),
+        type_::Interface(_) => match target {
+            Target::Field(_) => line(target.set(&format!("{}.clone()", value.method))),
+            Target::Element(_) => line(target.set(&fmt!(
+                ctx,
+                "{capnp}::capability::FromClientHook::into_client_hook({}.clone())",
+                value.method
+            ))),
+        },
+        type_::List(ls) => {
+            let element_type = ls.get_element_type()?;
+            // Void elements are never looked at.
+            let element = if let type_::Void(()) = element_type.which()? {
+                "_".to_string()
+            } else {
+                format!("e{depth}")
+            };
+            Branch(vec![
+                line("{"),
+                indent(vec![
+                    Line(format!(
+                        "let mut list{depth} = {};",
+                        target.init_list(&format!("{}.len() as u32", value.method))
+                    )),
+                    Line(format!(
+                        "for (i{depth}, {element}) in {}.iter().enumerate() {{",
+                        value.method
+                    )),
+                    indent(write_value(
+                        ctx,
+                        element_type,
+                        &Value::borrowed(&element),
+                        Target::Element(depth),
+                        depth + 1,
+                    )?),
+                    line("}"),
+                ]),
+                line("}"),
+            ])
+        }
+        type_::AnyPointer(_) => {
+            return Err(capnp::Error::failed(
+                "AnyPointer has no native type".to_string(),
+            ))
+        }
+        _ => line(target.set(&value.copy)),
+    })
+}
+
+/// The arguments to pass to `init_*()` for a pointer field of type `ty`, when only the
+/// union discriminant needs to be set.
+fn empty_init_args(ty: type_::Reader) -> capnp::Result<&'static str> {
+    Ok(match ty.which()? {
+        type_::Text(()) | type_::Data(()) | type_::List(_) => "0",
+        _ => "",
+    })
+}
+
+/// The generated code for a single field of the native type.
+struct Member {
+    /// The name of the field (or union variant) in the native type.
+    name: String,
+    /// The native type, or `None` if the field carries no data.
+    ty: Option<String>,
+    /// The body of a `match` arm (for union members) or an expression (otherwise) that reads
+    /// the field from `reader`.
+    read: String,
+    /// Statements writing the field to `builder`.
+    write: FormattedText,
+}
+
+fn generate_member(
+    ctx: &GeneratorContext,
+    owner_id: u64,
+    field: field::Reader,
+    is_union_member: bool,
+) -> capnp::Result<Member> {
+    let name = get_field_name(field)?;
+    let styled_name = camel_to_snake_case(name);
+    let variant_name = capitalize_first_letter(name);
+    let member_name = if is_union_member {
+        variant_name.clone()
+    } else {
+        module_name(name)
+    };
+    let getter = if is_union_member {
+        "v".to_string()
+    } else {
+        format!("reader.get_{styled_name}()")
+    };
+    let value = if is_union_member {
+        Value::borrowed("v")
+    } else {
+        Value::field(&member_name)
+    };
+
+    let no_data = |read: String, write: FormattedText| Member {
+        name: member_name.clone(),
+        ty: None,
+        read,
+        write,
+    };
+
+    match field.which()? {
+        field::Group(group) => {
+            let ty = format!("{}::Native", ctx.get_qualified_module(group.get_type_id()));
+            let read = format!("{ty}::from_reader({getter})?");
+            let builder = if is_union_member {
+                format!("builder.reborrow().init_{styled_name}()")
+            } else {
+                format!("builder.reborrow().get_{styled_name}()")
+            };
+            Ok(Member {
+                name: member_name,
+                ty: Some(ty),
+                read,
+                write: Line(format!("{}.write_to({builder})?;", value.method)),
+            })
+        }
+        field::Slot(slot) => {
+            let ty = slot.get_type()?;
+            let Some(mut native) = native_type(ctx, ty)? else {
+                // No native representation. For union members we still record which
+                // member was set.
+                return Ok(no_data(
+                    String::new(),
+                    Line(format!(
+                        "builder.reborrow().init_{styled_name}({});",
+                        empty_init_args(ty)?
+                    )),
+                ));
+            };
+            if let type_::Void(()) = ty.which()? {
+                return Ok(no_data(
+                    String::new(),
+                    line(Target::Field(&styled_name).set("()")),
+                ));
+            }
+
+            let is_fallible = ty.is_pointer()? || matches!(ty.which()?, type_::Enum(_));
+            let unwrapped = if is_fallible {
+                format!("{getter}?")
+            } else {
+                getter.clone()
+            };
+            let is_boxed = match ty.which()? {
+                type_::Struct(st) => is_recursive(ctx, owner_id, st.get_type_id())?,
+                _ => false,
+            };
+            if is_boxed {
+                native = format!("Box<{native}>");
+            }
+            let boxed = |read: String| {
+                if is_boxed {
+                    format!("Box::new({read})")
+                } else {
+                    read
+                }
+            };
+
+            let target = Target::Field(&styled_name);
+            let is_interface = matches!(ty.which()?, type_::Interface(_));
+            let read = if is_option_field(field)? {
+                let read = boxed(read_value(ctx, ty, "v", 0)?);
+                fmt!(ctx, "{unwrapped}.map(|v| -> {capnp}::Result<{native}> {{ ::core::result::Result::Ok({read}) }}).transpose()?")
+            } else if is_boxed || (is_interface && !is_union_member) {
+                // Unset capabilities can't be read at all, so they are `None` too.
+                let read = boxed(read_value(ctx, ty, &unwrapped, 0)?);
+                format!(
+                    "if reader.has_{styled_name}() {{ ::core::option::Option::Some({read}) }} else {{ ::core::option::Option::None }}"
+                )
+            } else {
+                return Ok(Member {
+                    name: member_name,
+                    ty: Some(native),
+                    read: read_value(ctx, ty, &unwrapped, 0)?,
+                    write: write_value(ctx, ty, &value, target, 0)?,
+                });
+            };
+
+            let write = write_value(ctx, ty, &Value::borrowed("v"), target, 0)?;
+            let write = if is_union_member {
+                Branch(vec![
+                    line("match v {"),
+                    indent(vec![
+                        line("::core::option::Option::Some(v) => {"),
+                        indent(write),
+                        line("}"),
+                        line("::core::option::Option::None => {"),
+                        indent(vec![
+                            Line(format!(
+                                "builder.reborrow().init_{styled_name}({});",
+                                empty_init_args(ty)?
+                            )),
+                            Line(format!("let _ = builder.disown_{styled_name}();")),
+                        ]),
+                        line("}"),
+                    ]),
+                    line("}"),
+                ])
+            } else {
+                Branch(vec![
+                    Line(format!(
+                        "if let ::core::option::Option::Some(v) = {} {{",
+                        value.reference
+                    )),
+                    indent(write),
+                    line("}"),
+                ])
+            };
+            Ok(Member {
+                name: member_name,
+                ty: Some(format!("::core::option::Option<{native}>")),
+                read,
+                write,
+            })
+        }
+    }
+}
+
+/// Generates the native type for the struct node `node_id`, to be placed in the module
+/// that also contains its `Reader` and `Builder`.
+pub fn generate_native_type(ctx: &GeneratorContext, node_id: u64) -> capnp::Result<FormattedText> {
+    let derive = if has_capabilities(ctx, node_id, &mut HashSet::new())? {
+        line("#[derive(Clone)]")
+    } else {
+        line("#[derive(Clone, Debug, PartialEq)]")
+    };
+
+    let mut struct_members = Vec::new();
+    let mut union_members = Vec::new();
+    for field in fields_in_code_order(ctx, node_id)? {
+        let is_union_member = field.get_discriminant_value() != field::NO_DISCRIMINANT;
+        let member = generate_member(ctx, node_id, field, is_union_member)?;
+        if is_union_member {
+            union_members.push(member);
+        } else if member.ty.is_some() {
+            struct_members.push(member);
+        }
+    }
+
+    // A struct that is nothing but a union is represented by the enum itself.
+    let is_enum = struct_members.is_empty() && !union_members.is_empty();
+    let union_name = if is_enum { "Native" } else { "NativeWhich" };
+    let union_path = if is_enum { "Self" } else { "NativeWhich" };
+
+    let mut result = vec![BlankLine];
+    let mut read_union = Vec::new();
+    let mut write_union = Vec::new();
+    if !union_members.is_empty() {
+        let mut variants = Vec::new();
+        for member in &union_members {
+            let name = &member.name;
+            match &member.ty {
+                Some(ty) => {
+                    variants.push(Line(format!("{name}({ty}),")));
+                    read_union.push(Line(format!(
+                        "Which::{name}(v) => {union_path}::{name}({}),",
+                        member.read
+                    )));
+                    write_union.push(Line(format!("{union_path}::{name}(v) => {{")));
+                }
+                None => {
+                    variants.push(Line(format!("{name},")));
+                    read_union.push(Line(format!("Which::{name}(_) => {union_path}::{name},")));
+                    write_union.push(Line(format!("{union_path}::{name} => {{")));
+                }
+            }
+            write_union.push(indent(member.write.clone()));
+            write_union.push(line("}"));
+        }
+        result.push(derive.clone());
+        result.push(Line(format!("pub enum {union_name} {{")));
+        result.push(indent(variants));
+        result.push(line("}"));
+    }
+
+    if !is_enum {
+        result.push(derive);
+        result.push(line("pub struct Native {"));
+        result.push(indent(
+            struct_members
+                .iter()
+                .map(|m| Line(format!("pub {}: {},", m.name, m.ty.as_ref().unwrap())))
+                .collect::<Vec<_>>(),
+        ));
+        if !union_members.is_empty() {
+            result.push(indent(line("pub which: NativeWhich,")));
+        }
+        result.push(line("}"));
+    }
+
+    let match_which = |interior: Vec<FormattedText>| {
+        Branch(vec![
+            line("match reader.which()? {"),
+            indent(interior),
+            line("}"),
+        ])
+    };
+    let read_body = if is_enum {
+        Branch(vec![
+            line("::core::result::Result::Ok("),
+            indent(match_which(read_union)),
+            line(")"),
+        ])
+    } else {
+        let mut interior: Vec<_> = struct_members
+            .iter()
+            .map(|m| Line(format!("{}: {},", m.name, m.read)))
+            .collect();
+        if !union_members.is_empty() {
+            interior.push(line("which: match reader.which()? {"));
+            interior.push(indent(read_union));
+            interior.push(line("},"));
+        }
+        Branch(vec![
+            line("::core::result::Result::Ok(Self {"),
+            indent(interior),
+            line("})"),
+        ])
+    };
+
+    let mut write_body: Vec<_> = struct_members.iter().map(|m| m.write.clone()).collect();
+    if !union_members.is_empty() {
+        write_body.push(Line(format!(
+            "match {} {{",
+            if is_enum { "self" } else { "&self.which" }
+        )));
+        write_body.push(indent(write_union));
+        write_body.push(line("}"));
+    }
+    let (reader_param, builder_param) = if struct_members.is_empty() && union_members.is_empty() {
+        ("_reader", "_builder")
+    } else {
+        ("reader", "mut builder")
+    };
+
+    result.push(BlankLine);
+    result.push(line("impl Native {"));
+    result.push(indent(vec![
+        Line(fmt!(
+            ctx,
+            "pub fn from_reader({reader_param}: Reader<'_>) -> {capnp}::Result<Self> {{"
+        )),
+        indent(read_body),
+        line("}"),
+        BlankLine,
+        Line(fmt!(
+            ctx,
+            "pub fn write_to(&self, {builder_param}: Builder<'_>) -> {capnp}::Result<()> {{"
+        )),
+        indent(write_body),
+        indent(line("::core::result::Result::Ok(())")),
+        line("}"),
+    ]));
+    result.push(line("}"));
+    result.push(BlankLine);
+    result.push(line(
+        "impl <'a> ::core::convert::TryFrom<Reader<'a>> for Native {",
+    ));
+    result.push(indent(vec![
+        Line(fmt!(ctx, "type Error = {capnp}::Error;")),
+        Line(fmt!(
+            ctx,
+            "fn try_from(reader: Reader<'a>) -> {capnp}::Result<Self> {{"
+        )),
+        indent(line("Self::from_reader(reader)")),
+        line("}"),
+    ]));
+    result.push(line("}"));
+
+    Ok(Branch(result))
+}
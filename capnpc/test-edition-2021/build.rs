@@ -1,5 +1,6 @@
 fn main() {
     capnpc::CompilerCommand::new()
+        .builtin_compiler()
         .file("../test/test.capnp")
         .src_prefix("../test/")
         .run()
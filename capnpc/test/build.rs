@@ -1,11 +1,15 @@
 fn main() {
     capnpc::CompilerCommand::new()
+        .builtin_compiler()
         .file("test.capnp")
         .file("in-submodule.capnp")
         .file("in-other-submodule.capnp")
+        .file("json.capnp")
+        .file("test-json.capnp")
         .file("schema/test-in-dir.capnp")
         .file("schema-with-src-prefix/test-in-src-prefix-dir.capnp")
         .src_prefix("schema-with-src-prefix")
+        .native_types()
         .raw_code_generator_request_path(
             std::env::var("OUT_DIR").expect("OUT_DIR env var is not set")
                 + "/raw_code_gen_request.bin",
@@ -14,6 +18,7 @@ fn main() {
         .expect("compiling schema");
 
     capnpc::CompilerCommand::new()
+        .builtin_compiler()
         .file("test-default-parent-module.capnp")
         .file("test-default-parent-module-override.capnp")
         .default_parent_module(vec![
@@ -29,6 +34,7 @@ fn main() {
     // `capnp compile` will create this directory
     output_path.push("inner-output-path");
     capnpc::CompilerCommand::new()
+        .builtin_compiler()
         .file("test-output-path.capnp")
         .output_path(output_path)
         .run()
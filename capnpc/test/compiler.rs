@@ -0,0 +1,129 @@
+//! Checks the builtin schema compiler against nodes produced by the C++ `capnp` tool.
+//!
+//! The nodes embedded in the checked-in `schema_capnp.rs` and `stream_capnp.rs` were
+//! generated from `capnp compile -o-` output, so they serve as golden data that does not
+//! need the C++ tool to be installed. When a `capnp` binary is on the `PATH`, the test
+//! schemas of this crate are also compiled by both and compared node by node.
+
+use capnp::introspect::{Type, TypeVariant};
+use capnp::message::{self, ReaderOptions};
+use capnp::schema::{EnumSchema, InterfaceSchema, StructSchema};
+use capnp::schema_capnp::{code_generator_request, node};
+use capnpc::compiler::Compiler;
+use std::collections::HashMap;
+
+const TEST_SCHEMAS: &[&str] = &[
+    "test.capnp",
+    "in-submodule.capnp",
+    "in-other-submodule.capnp",
+    "json.capnp",
+    "test-json.capnp",
+    "schema/test-in-dir.capnp",
+    "test-default-parent-module.capnp",
+    "test-default-parent-module-override.capnp",
+    "test-output-path.capnp",
+];
+
+/// Copies `node` into a fresh message, so that two nodes with the same content
+/// produce the same words no matter how their original messages were laid out.
+fn node_words(node: node::Reader<'_>) -> Vec<u8> {
+    let mut message = message::Builder::new_default();
+    message.set_root(node).unwrap();
+    message.get_segments_for_output()[0].to_vec()
+}
+
+fn nodes_by_id(request: code_generator_request::Reader<'_>) -> HashMap<u64, node::Reader<'_>> {
+    request
+        .get_nodes()
+        .unwrap()
+        .iter()
+        .map(|node| (node.get_id(), node))
+        .collect()
+}
+
+fn assert_same_node(expected: node::Reader<'_>, builtin: &HashMap<u64, node::Reader<'_>>) {
+    let name = expected.get_display_name().unwrap();
+    let Some(&actual) = builtin.get(&expected.get_id()) else {
+        panic!("builtin compiler did not produce {name}");
+    };
+    assert!(
+        node_words(expected) == node_words(actual),
+        "builtin compiler disagrees with capnp on {name}"
+    );
+}
+
+fn proto(schema: fn() -> Type) -> node::Reader<'static> {
+    match schema().which() {
+        TypeVariant::Struct(raw) => StructSchema::new(raw).get_proto(),
+        TypeVariant::Enum(raw) => EnumSchema::new(raw).get_proto(),
+        TypeVariant::Capability(raw) => InterfaceSchema::new(raw).get_proto(),
+        _ => panic!("not a named type"),
+    }
+}
+
+fn check_golden(file: &str, src_prefix: &str, schemas: &[fn() -> Type]) {
+    let message = Compiler::new()
+        .file(file)
+        .src_prefix(src_prefix)
+        .compile()
+        .unwrap();
+    let request = message.get_root_as_reader().unwrap();
+    let builtin = nodes_by_id(request);
+    for &schema in schemas {
+        assert_same_node(proto(schema), &builtin);
+    }
+}
+
+#[test]
+fn schema_capnp_matches_golden() {
+    check_golden(
+        "../../capnp/schema.capnp",
+        "../../capnp",
+        capnp::schema_capnp::schemas(),
+    );
+}
+
+#[test]
+fn stream_capnp_matches_golden() {
+    check_golden(
+        "../src/compiler/stream.capnp",
+        "../src/compiler",
+        capnp::stream_capnp::schemas(),
+    );
+}
+
+#[test]
+fn test_schemas_match_capnp_tool() {
+    let mut command = std::process::Command::new("capnp");
+    command.args(["compile", "-o-", "--src-prefix=schema-with-src-prefix"]);
+    command.args(TEST_SCHEMAS);
+    command.arg("schema-with-src-prefix/test-in-src-prefix-dir.capnp");
+    let Ok(output) = command.output() else {
+        eprintln!("skipping: capnp tool not found");
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let expected = capnp::serialize::read_message_from_flat_slice(
+        &mut &output.stdout[..],
+        ReaderOptions::new(),
+    )
+    .unwrap();
+    let expected: code_generator_request::Reader<'_> = expected.get_root().unwrap();
+
+    let mut compiler = Compiler::new();
+    for file in TEST_SCHEMAS {
+        compiler.file(file);
+    }
+    compiler
+        .file("schema-with-src-prefix/test-in-src-prefix-dir.capnp")
+        .src_prefix("schema-with-src-prefix");
+    let message = compiler.compile().unwrap();
+    let builtin = nodes_by_id(message.get_root_as_reader().unwrap());
+    for node in expected.get_nodes().unwrap() {
+        assert_same_node(node, &builtin);
+    }
+}
@@ -246,3 +246,126 @@ fn test_stringify() {
     let stringified = format!("{:?}", root.into_reader());
     assert_eq!(stringified, "(voidField = (), boolField = false, int8Field = 3, int16Field = 0, int32Field = 0, int64Field = 0, uInt8Field = 0, uInt16Field = 0, uInt32Field = 0, uInt64Field = 0, float32Field = 0, float64Field = 0, textField = \"hello world\", dataField = 0x\"01020304057fff\", structField = (voidField = (), boolField = false, int8Field = 0, int16Field = 0, int32Field = 0, int64Field = 0, uInt8Field = 0, uInt16Field = 0, uInt32Field = 123456, uInt64Field = 0, float32Field = 0, float64Field = 0, enumField = foo), enumField = bar, boolList = [false, true])");
 }
+
+#[test]
+fn test_interface_schema() -> ::capnp::Result<()> {
+    use crate::test_capnp::{test_big_struct, test_extends, test_interface};
+    use capnp::introspect::{Introspect, TypeVariant};
+    use capnp::schema::InterfaceSchema;
+
+    let TypeVariant::Capability(raw) = test_extends::Client::introspect().which() else {
+        panic!("expected an interface");
+    };
+    let extends = InterfaceSchema::from(raw);
+    let methods = extends.get_methods()?;
+    assert_eq!(methods.len(), 3);
+    assert_eq!("qux", methods.get(0).get_proto().get_name()?);
+
+    let corge = extends.get_method_by_name("corge")?;
+    assert_eq!(corge.get_ordinal(), 1);
+    assert_eq!(
+        corge.get_param_type().get_proto().get_id(),
+        <test_big_struct::Reader<'static> as capnp::traits::HasTypeId>::TYPE_ID
+    );
+
+    let superclasses = extends.get_superclasses()?;
+    assert_eq!(superclasses.len(), 1);
+    let base = superclasses.get(0);
+    assert_eq!(
+        base.get_proto().get_id(),
+        <test_interface::Client as capnp::traits::HasTypeId>::TYPE_ID
+    );
+    assert!(extends.extends(base)?);
+    assert!(!base.extends(extends)?);
+
+    let foo = extends.get_method_by_name("foo")?;
+    assert_eq!(foo.get_ordinal(), 0);
+    assert_eq!(
+        foo.get_containing_interface().get_proto().get_id(),
+        base.get_proto().get_id()
+    );
+    let params = foo.get_param_type();
+    assert_eq!(params.get_fields()?.len(), 2);
+    assert!(matches!(
+        params.get_field_by_name("j")?.get_type().which(),
+        TypeVariant::Bool
+    ));
+    assert!(matches!(
+        foo.get_result_type()
+            .get_field_by_name("x")?
+            .get_type()
+            .which(),
+        TypeVariant::Text
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_generic_interface_schema() -> ::capnp::Result<()> {
+    use crate::test_capnp::{generic_base, generic_extend};
+    use capnp::introspect::{Introspect, TypeVariant};
+    use capnp::schema::InterfaceSchema;
+
+    let TypeVariant::Capability(raw) = generic_extend::Client::introspect().which() else {
+        panic!("expected an interface");
+    };
+    let extend = InterfaceSchema::from(raw);
+    let base = extend.get_superclasses()?.get(0);
+    assert_eq!(
+        base.get_proto().get_id(),
+        <generic_base::Client<capnp::data::Owned> as capnp::traits::HasTypeId>::TYPE_ID
+    );
+    assert!(extend.extends(base)?);
+    assert!(extend.get_methods()?.is_empty());
+    assert!(raw.superclass_type(0) == generic_base::Client::<capnp::data::Owned>::introspect());
+
+    // Brands are compared by the types they resolve to.
+    use crate::test_capnp::test_generics::interface;
+    use capnp::{data, text};
+    let branded = |ty: capnp::introspect::Type| match ty.which() {
+        TypeVariant::Capability(raw) => raw,
+        _ => panic!("expected an interface"),
+    };
+    let text_qux = branded(interface::Owned::<text::Owned, text::Owned, text::Owned>::introspect());
+    let data_qux = branded(interface::Owned::<text::Owned, text::Owned, data::Owned>::introspect());
+    let text_qux2 =
+        branded(interface::Owned::<text::Owned, text::Owned, text::Owned>::introspect());
+    assert!(text_qux == text_qux2);
+    assert!(text_qux != data_qux);
+    Ok(())
+}
+
+#[test]
+fn test_schema_registry() -> ::capnp::Result<()> {
+    use crate::test_capnp::{test_generics, test_interface, TestEnum};
+    use capnp::traits::HasTypeId;
+
+    let registry = capnp::schema::registry();
+    registry.register_all(crate::test_capnp::schemas())?;
+
+    let id = <test_all_types::Reader<'static> as HasTypeId>::TYPE_ID;
+    assert_eq!(registry.get_struct(id)?.get_proto().get_id(), id);
+    assert!(registry.get_enum(id).is_err());
+    let id = <TestEnum as HasTypeId>::TYPE_ID;
+    assert_eq!(registry.get_enum(id)?.get_proto().get_id(), id);
+    let id = <test_interface::Client as HasTypeId>::TYPE_ID;
+    assert_eq!(registry.get_interface(id)?.get_methods()?.len(), 4);
+
+    // Generic types are registered with their parameters bound to `AnyPointer`.
+    let id = <test_generics::Reader<'static, capnp::text::Owned, capnp::text::Owned> as HasTypeId>::TYPE_ID;
+    let generics = registry.get_struct(id)?;
+    assert!(matches!(
+        generics.get_field_by_name("foo")?.get_type().which(),
+        capnp::introspect::TypeVariant::AnyPointer
+    ));
+
+    let mut message = message::Builder::new_default();
+    let mut root: test_all_types::Builder<'_> = message.init_root();
+    root.set_u_int32_field(12345);
+    let any: capnp::any_pointer::Reader<'_> = message.get_root_as_reader()?;
+    let value = any.get_as_dynamic(<test_all_types::Reader<'static> as HasTypeId>::TYPE_ID)?;
+    let value: dynamic_struct::Reader<'_> = value.downcast();
+    assert_eq!(value.get_named("uInt32Field")?.downcast::<u32>(), 12345);
+    assert!(any.get_as_dynamic(0x1234).is_err());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use crate::test_json_capnp::{test_json_annotations, TestJsonAnnotatedEnum};
+use capnp::{json, message};
+
+const ANNOTATED_JSON: &str = concat!(
+    r#"{"names-can_contain!anything Really":"foo","#,
+    r#""flatFoo":123,"flatBar":"abc","renamed-flatBaz":{"hello":true},"flatQux":"cba","#,
+    r#""pfx.foo":"this is a long string in order to force multi-line pretty printing","#,
+    r#""pfx.renamed-bar":321,"pfx.baz":{"hello":true},"pfx.xfp.qux":"fed","#,
+    r#""union-type":"renamed-bar","barMember":789,"multiMember":"ghi","#,
+    r#""dependency":{"renamed-foo":"corge"},"simpleGroup":{"renamed-grault":"garply"},"#,
+    r#""enums":["qux","renamed-bar","foo","renamed-baz"],"#,
+    r#""testBase64":"ZnJlZA==","testHex":"706c756768","#,
+    r#""bUnion":"renamed-bar","bValue":678,"#,
+    r#""unionWithVoid":{"type":"voidValue"}}"#,
+);
+
+fn init_annotated(mut root: test_json_annotations::Builder<'_>) {
+    root.set_some_field("foo");
+    {
+        let mut group = root.reborrow().get_a_group();
+        group.set_flat_foo(123);
+        group.set_flat_bar("abc");
+        group.reborrow().get_flat_baz().set_hello(true);
+        group.reborrow().get_double_flat().set_flat_qux("cba");
+    }
+    {
+        let mut group = root.reborrow().get_prefixed_group();
+        group.set_foo("this is a long string in order to force multi-line pretty printing");
+        group.set_bar(321);
+        group.reborrow().get_baz().set_hello(true);
+        group.reborrow().get_more_prefix().set_qux("fed");
+    }
+    {
+        let mut bar = root.reborrow().get_a_union().init_bar();
+        bar.set_bar_member(789);
+        bar.set_multi_member("ghi");
+    }
+    root.reborrow().init_dependency().set_foo("corge");
+    root.reborrow().get_simple_group().set_grault("garply");
+    {
+        let mut enums = root.reborrow().init_enums(4);
+        enums.set(0, TestJsonAnnotatedEnum::Qux);
+        enums.set(1, TestJsonAnnotatedEnum::Bar);
+        enums.set(2, TestJsonAnnotatedEnum::Foo);
+        enums.set(3, TestJsonAnnotatedEnum::Baz);
+    }
+    root.set_test_base64(b"fred");
+    root.set_test_hex(b"plugh");
+    root.reborrow().get_b_union().set_bar(678);
+    root.reborrow().get_union_with_void().set_void_value(());
+}
+
+#[test]
+fn encode_annotated() {
+    let mut message = message::Builder::new_default();
+    init_annotated(message.init_root());
+    let root = message
+        .get_root_as_reader::<test_json_annotations::Reader<'_>>()
+        .unwrap();
+    assert_eq!(json::to_string(root).unwrap(), ANNOTATED_JSON);
+}
+
+#[test]
+fn decode_annotated() {
+    let mut message = message::Builder::new_default();
+    json::from_str(
+        ANNOTATED_JSON,
+        message.init_root::<test_json_annotations::Builder<'_>>(),
+    )
+    .unwrap();
+    let root = message
+        .get_root_as_reader::<test_json_annotations::Reader<'_>>()
+        .unwrap();
+
+    assert_eq!(root.get_some_field().unwrap(), "foo");
+    assert_eq!(root.get_a_group().get_flat_baz().get_hello(), true);
+    assert_eq!(
+        root.get_a_group().get_double_flat().get_flat_qux().unwrap(),
+        "cba"
+    );
+    assert_eq!(root.get_prefixed_group().get_bar(), 321);
+    assert_eq!(
+        root.get_prefixed_group()
+            .get_more_prefix()
+            .get_qux()
+            .unwrap(),
+        "fed"
+    );
+    match root.get_a_union().which().unwrap() {
+        test_json_annotations::a_union::Bar(bar) => {
+            assert_eq!(bar.get_bar_member(), 789);
+            assert_eq!(bar.get_multi_member().unwrap(), "ghi");
+        }
+        _ => panic!("expected bar"),
+    }
+    assert_eq!(root.get_test_base64().unwrap(), b"fred");
+    assert_eq!(root.get_test_hex().unwrap(), b"plugh");
+    assert!(matches!(
+        root.get_b_union().which().unwrap(),
+        test_json_annotations::b_union::Bar(678)
+    ));
+    assert!(matches!(
+        root.get_union_with_void().which().unwrap(),
+        test_json_annotations::union_with_void::VoidValue(())
+    ));
+
+    let mut expected = message::Builder::new_default();
+    init_annotated(expected.init_root());
+    let expected = expected
+        .get_root_as_reader::<test_json_annotations::Reader<'_>>()
+        .unwrap();
+    assert_eq!(root, expected);
+}
+
+#[test]
+fn external_union_discriminator() {
+    let mut message = message::Builder::new_default();
+    json::from_str(
+        r#"{"externalUnion":{"type":"bar","value":"hi"}}"#,
+        message.init_root::<test_json_annotations::Builder<'_>>(),
+    )
+    .unwrap();
+    let root = message
+        .get_root_as_reader::<test_json_annotations::Reader<'_>>()
+        .unwrap();
+    assert_eq!(
+        json::to_string(root.get_external_union().unwrap()).unwrap(),
+        r#"{"type":"bar","value":"hi"}"#
+    );
+}
@@ -0,0 +1,129 @@
+use crate::test_capnp::{
+    test_all_types, test_field_get_option, test_groups, test_unnamed_union, TestEnum,
+};
+use crate::test_util::{init_test_message, CheckTestMessage};
+use capnp::message;
+
+#[test]
+fn all_types_round_trip() {
+    let mut message = message::Builder::new_default();
+    init_test_message(message.init_root());
+    let reader = message
+        .get_root_as_reader::<test_all_types::Reader<'_>>()
+        .unwrap();
+    let native = test_all_types::Native::from_reader(reader).unwrap();
+
+    assert_eq!(native.text_field, "foo");
+    assert_eq!(native.data_field, b"bar");
+    assert_eq!(native.enum_field, TestEnum::Corge);
+    let struct_field = native.struct_field.as_ref().unwrap();
+    assert_eq!(struct_field.text_field, "baz");
+    assert_eq!(
+        struct_field.struct_field.as_ref().unwrap().text_field,
+        "nested"
+    );
+    assert!(native.interface_field.is_none());
+    assert_eq!(native.void_list.len(), 6);
+    assert_eq!(native.bool_list, [true, false, false, true]);
+    assert_eq!(native.struct_list[2].text_field, "structlist 3");
+
+    let mut message2 = message::Builder::new_default();
+    native.write_to(message2.init_root()).unwrap();
+    let reader2 = message2
+        .get_root_as_reader::<test_all_types::Reader<'_>>()
+        .unwrap();
+    CheckTestMessage::check_test_message(reader2);
+    assert!(!reader2.has_interface_field());
+
+    // `interfaceField` keeps `Native` from implementing `PartialEq`.
+    let native2: test_all_types::Native = reader2.try_into().unwrap();
+    assert_eq!(native2.bool_list, native.bool_list);
+    assert_eq!(native2.struct_list[2].text_field, "structlist 3");
+}
+
+#[test]
+fn unnamed_union() {
+    let native = test_unnamed_union::Native {
+        before: "before".into(),
+        middle: 1234,
+        after: "after".into(),
+        which: test_unnamed_union::NativeWhich::Bar(321),
+    };
+
+    let mut message = message::Builder::new_default();
+    native.write_to(message.init_root()).unwrap();
+    let reader = message
+        .get_root_as_reader::<test_unnamed_union::Reader<'_>>()
+        .unwrap();
+    assert_eq!(reader.get_before().unwrap(), "before");
+    assert_eq!(reader.get_middle(), 1234);
+    assert!(matches!(
+        reader.which().unwrap(),
+        test_unnamed_union::Bar(321)
+    ));
+
+    assert_eq!(
+        test_unnamed_union::Native::from_reader(reader).unwrap(),
+        native
+    );
+}
+
+#[test]
+fn group_union() {
+    let native = test_groups::Native {
+        groups: test_groups::groups::Native::Baz(test_groups::groups::baz::Native {
+            corge: 1,
+            grault: "grault".into(),
+            garply: "garply".into(),
+            quz: 2.5,
+            an_enum: TestEnum::Qux,
+        }),
+    };
+
+    let mut message = message::Builder::new_default();
+    native.write_to(message.init_root()).unwrap();
+    let reader = message
+        .get_root_as_reader::<test_groups::Reader<'_>>()
+        .unwrap();
+    match reader.get_groups().which().unwrap() {
+        test_groups::groups::Baz(baz) => {
+            assert_eq!(baz.get_corge(), 1);
+            assert_eq!(baz.get_grault().unwrap(), "grault");
+            assert_eq!(baz.get_garply().unwrap(), "garply");
+            assert_eq!(baz.get_quz(), 2.5);
+            assert_eq!(baz.get_an_enum().unwrap(), TestEnum::Qux);
+        }
+        _ => panic!("expected baz"),
+    }
+
+    assert_eq!(test_groups::Native::from_reader(reader).unwrap(), native);
+}
+
+#[test]
+fn option_fields() {
+    let mut message = message::Builder::new_default();
+    let mut root = message.init_root::<test_field_get_option::Builder<'_>>();
+    root.set_text("foo");
+    root.reborrow().init_simple_struct();
+
+    let native = test_field_get_option::Native::from_reader(root.into_reader()).unwrap();
+    assert_eq!(native.text.as_deref(), Some("foo"));
+    assert_eq!(native.data, None);
+    assert_eq!(native.list, None);
+    assert_eq!(native.empty_struct, None);
+    assert_eq!(
+        native.simple_struct,
+        Some(test_field_get_option::simple_struct::Native { field: None })
+    );
+
+    let mut message2 = message::Builder::new_default();
+    native.write_to(message2.init_root()).unwrap();
+    let reader = message2
+        .get_root_as_reader::<test_field_get_option::Reader<'_>>()
+        .unwrap();
+    assert_eq!(reader.get_text().unwrap(), Some("foo"));
+    assert!(!reader.has_data());
+    assert!(!reader.has_list());
+    assert!(!reader.has_empty_struct());
+    assert!(reader.has_simple_struct());
+}
@@ -27,6 +27,14 @@ pub mod test_capnp {
     include!(concat!(env!("OUT_DIR"), "/test_capnp.rs"));
 }
 
+pub mod json_capnp {
+    include!(concat!(env!("OUT_DIR"), "/json_capnp.rs"));
+}
+
+pub mod test_json_capnp {
+    include!(concat!(env!("OUT_DIR"), "/test_json_capnp.rs"));
+}
+
 pub mod foo {
     pub mod bar {
         pub mod in_submodule_capnp {
@@ -81,9 +89,18 @@ pub mod test_output_path_capnp {
 #[cfg(test)]
 mod test_util;
 
+#[cfg(test)]
+mod compiler;
+
 #[cfg(test)]
 mod dynamic;
 
+#[cfg(test)]
+mod json;
+
+#[cfg(test)]
+mod native;
+
 #[cfg(test)]
 mod tests {
     use crate::test_util::{init_test_message, CheckTestMessage};
@@ -2129,6 +2146,89 @@ mod tests {
         CheckTestMessage::check_test_message(message_reader.get().unwrap());
     }
 
+    #[test]
+    fn test_orphans() {
+        use crate::test_capnp::test_all_types;
+
+        let mut message = message::Builder::new_default();
+        let mut root = message.init_root::<test_all_types::Builder>();
+        init_test_message(root.reborrow().init_struct_field());
+        root.set_text_field("foobar");
+
+        let orphan = root.disown_struct_field();
+        assert!(!root.has_struct_field());
+        CheckTestMessage::check_test_message(orphan.get_reader().unwrap());
+
+        let mut child = root.reborrow().init_struct_list(1).get(0);
+        child.adopt_struct_field(orphan).unwrap();
+        CheckTestMessage::check_test_message(child.into_reader().get_struct_field().unwrap());
+
+        let mut text = root.disown_text_field();
+        assert!(!root.has_text_field());
+        text.truncate(3).unwrap();
+        root.adopt_text_field(text).unwrap();
+        assert_eq!(root.reborrow_as_reader().get_text_field().unwrap(), "foo");
+
+        let interface = root.disown_interface_field();
+        root.adopt_interface_field(interface).unwrap();
+        assert!(!root.has_interface_field());
+    }
+
+    #[test]
+    fn test_union_adopt_failure() {
+        use crate::test_capnp::test_union;
+
+        let mut message1 = message::Builder::new_default();
+        let mut message2 = message::Builder::new_default();
+        let text = message2
+            .get_orphanage()
+            .new_orphan_copy::<capnp::text::Owned>("foo")
+            .unwrap();
+
+        let mut root = message1.init_root::<test_union::Builder>();
+        root.reborrow().get_union0().set_u0f0s32(42);
+        assert!(root.reborrow().get_union0().adopt_u0f0sp(text).is_err());
+        match root.into_reader().get_union0().which().unwrap() {
+            test_union::union0::U0f0s32(n) => assert_eq!(n, 42),
+            _ => panic!("expected u0f0s32"),
+        }
+    }
+
+    #[test]
+    fn test_reader_equality() {
+        use crate::test_capnp::test_all_types;
+        use std::hash::{BuildHasher, RandomState};
+
+        let mut message1 = message::Builder::new_default();
+        init_test_message(message1.init_root::<test_all_types::Builder<'_>>());
+        let reader1 = message1
+            .get_root_as_reader::<test_all_types::Reader<'_>>()
+            .unwrap();
+
+        let mut message2 = message::Builder::new(
+            message::HeapAllocator::new()
+                .first_segment_words(1)
+                .allocation_strategy(::capnp::message::AllocationStrategy::FixedSize),
+        );
+        message2.set_root(reader1).unwrap();
+        let reader2 = message2
+            .get_root_as_reader::<test_all_types::Reader<'_>>()
+            .unwrap();
+
+        let hasher = RandomState::new();
+        assert_eq!(reader1, reader2);
+        assert_eq!(hasher.hash_one(reader1), hasher.hash_one(reader2));
+
+        message2
+            .get_root::<test_all_types::Builder<'_>>()
+            .unwrap()
+            .set_u_int32_field(0);
+        let reader2 = message2
+            .get_root_as_reader::<test_all_types::Reader<'_>>()
+            .unwrap();
+        assert_ne!(reader1, reader2);
+    }
+
     #[test]
     fn test_slice_segments() {
         use crate::test_capnp::test_all_types;
@@ -172,7 +172,7 @@ impl<R: Rng> Filler<R> {
             }
 
             TypeVariant::AnyPointer => Ok(()),
-            TypeVariant::Capability => Ok(()),
+            TypeVariant::Capability(_) => Ok(()),
         }
     }
 
@@ -211,7 +211,7 @@ impl<R: Rng> Filler<R> {
                 self.fill_list(recursion_depth + 1, builder.get(index)?.downcast())
             }
             TypeVariant::AnyPointer => Ok(()),
-            TypeVariant::Capability => Ok(()),
+            TypeVariant::Capability(_) => Ok(()),
         }
     }
 